@@ -40,6 +40,8 @@ case "$method" in
 
 		cp thirdparty/zlib/libz.so libs
 		cp thirdparty/bzip2/libbz2.so libs
+		cp thirdparty/zstd/lib/libzstd.so libs
+		cp thirdparty/xz/src/liblzma/.libs/liblzma.so libs
 
 		cp thirdparty/libpng/.libs/libpng16.so libs
 		cp thirdparty/libjpeg/.libs/libjpeg.so libs
@@ -65,4 +67,8 @@ cd src/mupdf_wrapper
 ./build-kobo.sh
 cd ../..
 
-cargo build --release --target=arm-unknown-linux-gnueabihf
+# The ZIM archives need zstd and xz, which are only built by the slow method.
+features=""
+[ -e libs/libzstd.so -a -e libs/liblzma.so ] && features="--features zim"
+
+cargo build --release --target=arm-unknown-linux-gnueabihf $features
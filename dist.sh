@@ -11,6 +11,8 @@ mkdir dist/dictionaries
 
 cp libs/libz.so dist/libs/libz.so.1
 cp libs/libbz2.so dist/libs/libbz2.so.1.0
+[ -e libs/libzstd.so ] && cp libs/libzstd.so dist/libs/libzstd.so.1
+[ -e libs/liblzma.so ] && cp libs/liblzma.so dist/libs/liblzma.so.5
 
 cp libs/libpng16.so dist/libs/libpng16.so.16
 cp libs/libjpeg.so dist/libs/libjpeg.so.9
@@ -25,6 +27,7 @@ cp libs/libmupdf.so dist/libs
 
 cp -R hyphenation-patterns dist
 cp -R keyboard-layouts dist
+cp -R i18n dist
 cp -R bin dist
 cp -R scripts dist
 cp -R icons dist
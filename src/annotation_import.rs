@@ -0,0 +1,727 @@
+//! Imports the highlights and notes made with *KOReader* or on a *Kindle*.
+//!
+//! Neither source records positions that Plato can use, so the highlighted text is searched
+//! within the document instead.
+
+use crate::{
+  document::{self, Document, Location, TextLocation},
+  helpers::datetime_format,
+  metadata::{Annotation, Info, ReaderInfo},
+};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+const CLIPPINGS_SEPARATOR: &str = "==========";
+// Shorter highlights can't be located reliably.
+const MIN_QUERY_LEN: usize = 3;
+
+lazy_static! {
+  static ref KINDLE_PAGE: Regex = Regex::new(r"(?i)\bpage\s+(\d+)").unwrap();
+  static ref KINDLE_LOCATION: Regex = Regex::new(r"(?i)\blocation\s+(\d+)(?:-(\d+))?").unwrap();
+  static ref KINDLE_DATE: Regex = Regex::new(r"(?i)added on\s+(.+)$").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignAnnotation {
+  pub text: String,
+  pub note: String,
+  // The page number, starting at one. Only used for documents with a fixed layout.
+  pub page: Option<usize>,
+  pub modified: Option<DateTime<Local>>,
+}
+
+// The annotations of one book of a Kindle clippings file.
+#[derive(Debug, Clone)]
+pub struct Clippings {
+  pub title: String,
+  pub author: String,
+  pub annotations: Vec<ForeignAnnotation>,
+}
+
+pub fn parse_kindle_clippings(text: &str) -> Vec<Clippings> {
+  let mut books: Vec<Clippings> = Vec::new();
+  // The location ranges of the highlights, used to attach the notes.
+  let mut ranges: Vec<Vec<[usize; 2]>> = Vec::new();
+
+  for entry in text.split(CLIPPINGS_SEPARATOR) {
+    let mut lines = entry
+      .lines()
+      .map(|l| l.trim_start_matches('\u{feff}').trim())
+      .skip_while(|l| l.is_empty());
+    let heading = match lines.next() {
+      Some(heading) => heading,
+      None => continue,
+    };
+    let meta = match lines.next() {
+      Some(meta) if meta.starts_with('-') => meta,
+      _ => continue,
+    };
+    let body = lines.collect::<Vec<&str>>().join("\n").trim().to_string();
+
+    if body.is_empty() {
+      continue;
+    }
+
+    let (title, author) = split_heading(heading);
+    let index = books
+      .iter()
+      .position(|b| b.title == title && b.author == author)
+      .unwrap_or_else(|| {
+        books.push(Clippings {
+          title,
+          author,
+          annotations: Vec::new(),
+        });
+        ranges.push(Vec::new());
+        books.len() - 1
+      });
+
+    let location = KINDLE_LOCATION.captures(meta).and_then(|caps| {
+      let start = caps[1].parse::<usize>().ok()?;
+      let end = caps
+        .get(2)
+        .and_then(|m| m.as_str().parse::<usize>().ok())
+        .unwrap_or(start);
+      Some([start, end])
+    });
+
+    if meta.to_lowercase().contains(" note ") {
+      let highlight = location.and_then(|[start, _]| {
+        ranges[index]
+          .iter()
+          .rposition(|r| r[0] <= start && start <= r[1])
+      });
+      if let Some(i) = highlight {
+        books[index].annotations[i].note = body;
+      }
+      continue;
+    }
+
+    let page = KINDLE_PAGE
+      .captures(meta)
+      .and_then(|caps| caps[1].parse::<usize>().ok());
+    let modified = KINDLE_DATE.captures(meta).and_then(|caps| {
+      NaiveDateTime::parse_from_str(caps[1].trim(), "%A, %B %e, %Y %I:%M:%S %p")
+        .ok()
+        .and_then(|dt| Local.from_local_datetime(&dt).single())
+    });
+
+    books[index].annotations.push(ForeignAnnotation {
+      text: body,
+      note: String::new(),
+      page,
+      modified,
+    });
+    ranges[index].push(location.unwrap_or([0, 0]));
+  }
+
+  books
+}
+
+// Splits *Title (Author)* into its parts.
+fn split_heading(heading: &str) -> (String, String) {
+  if heading.ends_with(')') {
+    if let Some(index) = heading.rfind(" (") {
+      return (
+        heading[..index].trim().to_string(),
+        heading[index + 2..heading.len() - 1].trim().to_string(),
+      );
+    }
+  }
+  (heading.to_string(), String::new())
+}
+
+pub fn matches_book(clippings: &Clippings, info: &Info) -> bool {
+  let title = normalize(&info.title);
+  if title.is_empty() {
+    return false;
+  }
+  let candidate = normalize(&clippings.title);
+  // The Kindle titles often include the subtitle.
+  let short_candidate = normalize(clippings.title.split(&[':', '('][..]).next().unwrap_or(""));
+  (candidate == title || short_candidate == title)
+    && (clippings.author.is_empty()
+      || info.author.is_empty()
+      || clippings
+        .author
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 1)
+        .any(|w| info.author.contains(w)))
+}
+
+// KOReader stores its data in `<stem>.sdr/metadata.<extension>.lua`, next to the document.
+pub fn koreader_sidecar_path(path: &Path) -> Option<PathBuf> {
+  let stem = path.file_stem()?.to_str()?;
+  let extension = path.extension()?.to_str()?;
+  let sidecar = path
+    .with_file_name(format!("{}.sdr", stem))
+    .join(format!("metadata.{}.lua", extension.to_lowercase()));
+  if sidecar.exists() {
+    Some(sidecar)
+  } else {
+    None
+  }
+}
+
+pub fn parse_koreader_sidecar(text: &str) -> Vec<ForeignAnnotation> {
+  let root = match LuaParser::new(text).parse() {
+    Some(root) => root,
+    None => return Vec::new(),
+  };
+  let datetime = |value: Option<&LuaValue>| {
+    value
+      .and_then(LuaValue::as_str)
+      .and_then(|s| Local.datetime_from_str(s, datetime_format::FORMAT).ok())
+  };
+  let mut annotations = Vec::new();
+
+  // Recent versions store everything in the *annotations* table.
+  if let Some(table) = root.get("annotations") {
+    for (_, entry) in table.entries() {
+      let text = match entry.get("text").and_then(LuaValue::as_str) {
+        Some(text) if entry.get("pos0").is_some() => text.to_string(),
+        _ => continue,
+      };
+      annotations.push(ForeignAnnotation {
+        text,
+        note: entry
+          .get("note")
+          .and_then(LuaValue::as_str)
+          .unwrap_or_default()
+          .to_string(),
+        page: entry
+          .get("pageno")
+          .and_then(LuaValue::as_f64)
+          .map(|p| p as usize),
+        modified: datetime(entry.get("datetime")),
+      });
+    }
+    return annotations;
+  }
+
+  // Older versions store the highlights by page, and their notes in the bookmarks.
+  if let Some(table) = root.get("highlight") {
+    for (page, items) in table.entries() {
+      for (_, entry) in items.entries() {
+        if let Some(text) = entry.get("text").and_then(LuaValue::as_str) {
+          annotations.push(ForeignAnnotation {
+            text: text.to_string(),
+            note: String::new(),
+            page: page.as_f64().map(|p| p as usize),
+            modified: datetime(entry.get("datetime")),
+          });
+        }
+      }
+    }
+  }
+
+  if let Some(table) = root.get("bookmarks") {
+    for (_, entry) in table.entries() {
+      let (notes, text) = match (
+        entry.get("notes").and_then(LuaValue::as_str),
+        entry.get("text").and_then(LuaValue::as_str),
+      ) {
+        (Some(notes), Some(text)) => (notes, text),
+        _ => continue,
+      };
+      // The text of the bookmarks that weren't edited ends with their date.
+      let automatic = entry
+        .get("datetime")
+        .and_then(LuaValue::as_str)
+        .map_or(false, |dt| text.ends_with(dt));
+      if automatic {
+        continue;
+      }
+      if let Some(annot) = annotations.iter_mut().find(|a| a.text == notes) {
+        annot.note = text.to_string();
+      }
+    }
+  }
+
+  annotations
+}
+
+// Imports the annotations of the KOReader sidecar of the given document. Returns whether
+// annotations were added.
+pub fn import_koreader_annotations(prefix: &Path, info: &mut Info) -> bool {
+  let path = prefix.join(&info.file.path);
+  let sidecar = match koreader_sidecar_path(&path) {
+    Some(sidecar) => sidecar,
+    None => return false,
+  };
+  let annotations = match fs::read_to_string(&sidecar) {
+    Ok(text) => parse_koreader_sidecar(&text),
+    Err(e) => {
+      eprintln!("Can't read {}: {}.", sidecar.display(), e);
+      return false;
+    },
+  };
+  import_annotations(&path, info, &annotations)
+}
+
+// Imports the Kindle clippings that belong to the given document. Returns whether annotations
+// were added.
+pub fn import_kindle_clippings(prefix: &Path, info: &mut Info, books: &[Clippings]) -> bool {
+  let annotations: Vec<ForeignAnnotation> = books
+    .iter()
+    .filter(|b| matches_book(b, info))
+    .flat_map(|b| b.annotations.iter().cloned())
+    .collect();
+  let path = prefix.join(&info.file.path);
+  import_annotations(&path, info, &annotations)
+}
+
+fn import_annotations(path: &Path, info: &mut Info, annotations: &[ForeignAnnotation]) -> bool {
+  if annotations.is_empty() {
+    return false;
+  }
+
+  let mut doc = match document::open(path) {
+    Some(doc) => doc,
+    None => return false,
+  };
+
+  let text = DocumentText::new(doc.as_mut());
+  let pages_count = doc.pages_count();
+  let reader = info.reader.get_or_insert_with(|| ReaderInfo {
+    pages_count,
+    ..Default::default()
+  });
+  let count = merge_annotations(reader, &text, annotations);
+
+  if count > 0 {
+    println!("{}: {} annotation(s).", info.file.path.display(), count);
+  }
+
+  count > 0
+}
+
+// Adds the annotations that can be located in the document and aren't already present.
+// Returns the number of added annotations.
+pub fn merge_annotations(
+  reader: &mut ReaderInfo,
+  text: &DocumentText,
+  annotations: &[ForeignAnnotation],
+) -> usize {
+  let mut count = 0;
+
+  for annot in annotations {
+    let query = normalize(&annot.text);
+    if reader
+      .annotations
+      .iter()
+      .any(|a| normalize(&a.text) == query)
+    {
+      continue;
+    }
+
+    if let Some(selection) = text.locate(&query, annot.page) {
+      reader.annotations.push(Annotation {
+        text: annot.text.clone(),
+        note: annot.note.clone(),
+        selection,
+        modified: annot.modified.unwrap_or_else(Local::now),
+      });
+      count += 1;
+    }
+  }
+
+  count
+}
+
+// The lowercase alphanumeric characters of the text. Ignoring the rest makes the matching
+// insensitive to hyphenation, punctuation and whitespace.
+fn normalize(text: &str) -> String {
+  text
+    .chars()
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+pub struct DocumentText {
+  text: String,
+  // The offset, within *text*, at which each word starts.
+  words: Vec<(usize, TextLocation)>,
+  // Whether the page numbers can be used to disambiguate.
+  fixed_layout: bool,
+}
+
+impl DocumentText {
+  pub fn new(doc: &mut dyn Document) -> DocumentText {
+    let mut text = String::new();
+    let mut words = Vec::new();
+    let mut loc = Location::Exact(0);
+
+    while let Some(location) = doc.resolve_location(loc) {
+      if let Some((page_words, _)) = doc.words(Location::Exact(location)) {
+        for word in page_words {
+          let normalized = normalize(&word.text);
+          if !normalized.is_empty() {
+            words.push((text.len(), word.location));
+            text += &normalized;
+          }
+        }
+      }
+      loc = Location::Next(location);
+    }
+
+    DocumentText {
+      text,
+      words,
+      fixed_layout: !doc.is_reflowable(),
+    }
+  }
+
+  // Returns the locations of the first and last words of the given normalized text. When
+  // several passages match, the one closest to *page* is chosen.
+  fn locate(&self, query: &str, page: Option<usize>) -> Option<[TextLocation; 2]> {
+    if query.chars().count() < MIN_QUERY_LEN {
+      return None;
+    }
+
+    let mut selections = self.text.match_indices(query).filter_map(|(start, m)| {
+      let first = self.word_at(start)?;
+      let last = self.word_at(start + m.len() - 1)?;
+      Some([first, last])
+    });
+
+    match page.filter(|_| self.fixed_layout) {
+      Some(page) => selections.min_by_key(|s| {
+        let index = s[0].location() as i64;
+        (index - page.saturating_sub(1) as i64).abs()
+      }),
+      None => selections.next(),
+    }
+  }
+
+  fn word_at(&self, offset: usize) -> Option<TextLocation> {
+    let index = match self
+      .words
+      .binary_search_by_key(&offset, |(start, _)| *start)
+    {
+      Ok(index) => index,
+      Err(index) => index.checked_sub(1)?,
+    };
+    self.words.get(index).map(|(_, loc)| *loc)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum LuaValue {
+  Nil,
+  Bool(bool),
+  Number(f64),
+  Str(String),
+  Table(Vec<(LuaValue, LuaValue)>),
+}
+
+impl LuaValue {
+  fn get(&self, key: &str) -> Option<&LuaValue> {
+    self.entries().iter().find_map(|(k, v)| {
+      if k.as_str() == Some(key) {
+        Some(v)
+      } else {
+        None
+      }
+    })
+  }
+
+  fn entries(&self) -> &[(LuaValue, LuaValue)] {
+    match self {
+      LuaValue::Table(entries) => entries,
+      _ => &[],
+    }
+  }
+
+  fn as_str(&self) -> Option<&str> {
+    match self {
+      LuaValue::Str(s) => Some(s),
+      _ => None,
+    }
+  }
+
+  fn as_f64(&self) -> Option<f64> {
+    match self {
+      LuaValue::Number(n) => Some(*n),
+      _ => None,
+    }
+  }
+}
+
+// Parses the subset of Lua used by the KOReader sidecars: a returned table of literals.
+struct LuaParser {
+  chars: Vec<char>,
+  pos: usize,
+}
+
+impl LuaParser {
+  fn new(text: &str) -> LuaParser {
+    LuaParser {
+      chars: text.chars().collect(),
+      pos: 0,
+    }
+  }
+
+  fn parse(&mut self) -> Option<LuaValue> {
+    self.skip_whitespace();
+    if self.starts_with("return") {
+      self.pos += "return".len();
+    }
+    self.parse_value()
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).cloned()
+  }
+
+  fn starts_with(&self, word: &str) -> bool {
+    word
+      .chars()
+      .enumerate()
+      .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+  }
+
+  fn skip_whitespace(&mut self) {
+    loop {
+      while self.peek().map_or(false, char::is_whitespace) {
+        self.pos += 1;
+      }
+      if self.starts_with("--") {
+        while self.peek().map_or(false, |c| c != '\n') {
+          self.pos += 1;
+        }
+      } else {
+        break;
+      }
+    }
+  }
+
+  fn parse_value(&mut self) -> Option<LuaValue> {
+    self.skip_whitespace();
+    match self.peek()? {
+      '{' => self.parse_table(),
+      '"' | '\'' => self.parse_string().map(LuaValue::Str),
+      c if c == '-' || c.is_ascii_digit() => self.parse_number(),
+      _ => {
+        let word = self.parse_identifier();
+        match word.as_str() {
+          "true" => Some(LuaValue::Bool(true)),
+          "false" => Some(LuaValue::Bool(false)),
+          "nil" => Some(LuaValue::Nil),
+          _ => None,
+        }
+      },
+    }
+  }
+
+  fn parse_identifier(&mut self) -> String {
+    let start = self.pos;
+    while self
+      .peek()
+      .map_or(false, |c| c.is_alphanumeric() || c == '_')
+    {
+      self.pos += 1;
+    }
+    self.chars[start..self.pos].iter().collect()
+  }
+
+  fn parse_number(&mut self) -> Option<LuaValue> {
+    let start = self.pos;
+    while self
+      .peek()
+      .map_or(false, |c| c.is_ascii_alphanumeric() || "-+.".contains(c))
+    {
+      self.pos += 1;
+    }
+    let text: String = self.chars[start..self.pos].iter().collect();
+    text.parse::<f64>().ok().map(LuaValue::Number)
+  }
+
+  fn parse_string(&mut self) -> Option<String> {
+    let quote = self.peek()?;
+    self.pos += 1;
+    let mut result = String::new();
+    loop {
+      let c = self.peek()?;
+      self.pos += 1;
+      match c {
+        _ if c == quote => return Some(result),
+        '\\' => {
+          let e = self.peek()?;
+          self.pos += 1;
+          match e {
+            'n' | '\n' => result.push('\n'),
+            't' => result.push('\t'),
+            'r' => result.push('\r'),
+            _ if e.is_ascii_digit() => {
+              let mut code = e.to_digit(10)?;
+              for _ in 0..2 {
+                match self.peek().and_then(|d| d.to_digit(10)) {
+                  Some(d) => {
+                    code = 10 * code + d;
+                    self.pos += 1;
+                  },
+                  None => break,
+                }
+              }
+              result.push(std::char::from_u32(code)?);
+            },
+            _ => result.push(e),
+          }
+        },
+        _ => result.push(c),
+      }
+    }
+  }
+
+  fn parse_table(&mut self) -> Option<LuaValue> {
+    // Skip the opening brace.
+    self.pos += 1;
+    let mut entries = Vec::new();
+    let mut index = 1;
+
+    loop {
+      self.skip_whitespace();
+      match self.peek()? {
+        '}' => {
+          self.pos += 1;
+          return Some(LuaValue::Table(entries));
+        },
+        ',' | ';' => {
+          self.pos += 1;
+        },
+        '[' => {
+          self.pos += 1;
+          let key = self.parse_value()?;
+          self.skip_whitespace();
+          self.expect(']')?;
+          self.skip_whitespace();
+          self.expect('=')?;
+          let value = self.parse_value()?;
+          entries.push((key, value));
+        },
+        c if c.is_alphabetic() || c == '_' => {
+          let start = self.pos;
+          let word = self.parse_identifier();
+          self.skip_whitespace();
+          if self.peek() == Some('=') {
+            self.pos += 1;
+            let value = self.parse_value()?;
+            entries.push((LuaValue::Str(word), value));
+          } else {
+            self.pos = start;
+            let value = self.parse_value()?;
+            entries.push((LuaValue::Number(index as f64), value));
+            index += 1;
+          }
+        },
+        _ => {
+          let value = self.parse_value()?;
+          entries.push((LuaValue::Number(index as f64), value));
+          index += 1;
+        },
+      }
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Option<()> {
+    if self.peek() == Some(c) {
+      self.pos += 1;
+      Some(())
+    } else {
+      None
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse_kindle_clippings, parse_koreader_sidecar, DocumentText};
+  use crate::document::TextLocation;
+
+  #[test]
+  fn test_parse_kindle_clippings() {
+    let text = "\u{feff}The Dispossessed: An Ambiguous Utopia (Le Guin, Ursula K.)
+- Your Highlight on page 12 | Location 170-172 | Added on Monday, March 1, 2021 10:15:30 PM
+
+There was a wall.
+==========
+The Dispossessed: An Ambiguous Utopia (Le Guin, Ursula K.)
+- Your Note on page 12 | Location 172 | Added on Monday, March 1, 2021 10:16:02 PM
+
+Walls everywhere.
+==========
+The Dispossessed: An Ambiguous Utopia (Le Guin, Ursula K.)
+- Your Bookmark on page 20 | Location 300 | Added on Monday, March 1, 2021 10:20:00 PM
+
+
+==========
+";
+    let books = parse_kindle_clippings(text);
+    assert_eq!(books.len(), 1);
+    assert_eq!(books[0].title, "The Dispossessed: An Ambiguous Utopia");
+    assert_eq!(books[0].author, "Le Guin, Ursula K.");
+    assert_eq!(books[0].annotations.len(), 1);
+    assert_eq!(books[0].annotations[0].text, "There was a wall.");
+    assert_eq!(books[0].annotations[0].note, "Walls everywhere.");
+    assert_eq!(books[0].annotations[0].page, Some(12));
+    assert!(books[0].annotations[0].modified.is_some());
+  }
+
+  #[test]
+  fn test_parse_koreader_sidecar() {
+    let text = r#"-- we can read Lua syntax here!
+return {
+    ["annotations"] = {
+        [1] = {
+            ["chapter"] = "One",
+            ["datetime"] = "2023-04-02 09:12:45",
+            ["note"] = "A \"quoted\" note",
+            ["pageno"] = 7,
+            ["pos0"] = "/body/DocFragment[3]/body/p[2]/text().0",
+            ["pos1"] = "/body/DocFragment[3]/body/p[2]/text().24",
+            ["text"] = "It was a dark\nand stormy night",
+        },
+        [2] = {
+            ["datetime"] = "2023-04-02 09:20:00",
+            ["page"] = "/body/DocFragment[4]/body/p[1]/text().0",
+            ["pageno"] = 9,
+        },
+    },
+    ["doc_pages"] = 211,
+}"#;
+    let annotations = parse_koreader_sidecar(text);
+    assert_eq!(annotations.len(), 1);
+    assert_eq!(annotations[0].text, "It was a dark\nand stormy night");
+    assert_eq!(annotations[0].note, "A \"quoted\" note");
+    assert_eq!(annotations[0].page, Some(7));
+    assert!(annotations[0].modified.is_some());
+  }
+
+  #[test]
+  fn test_locate() {
+    let words = ["It", "was", "a", "dark", "and", "storm-", "y", "night."];
+    let mut text = DocumentText {
+      text: String::new(),
+      words: Vec::new(),
+      fixed_layout: false,
+    };
+    for (i, word) in words.iter().enumerate() {
+      text
+        .words
+        .push((text.text.len(), TextLocation::Dynamic(10 * i)));
+      text.text += &super::normalize(word);
+    }
+    assert_eq!(
+      text.locate(&super::normalize("dark and stormy"), None),
+      Some([TextLocation::Dynamic(30), TextLocation::Dynamic(60)])
+    );
+    assert_eq!(text.locate(&super::normalize("bright"), None), None);
+  }
+}
@@ -1,34 +1,88 @@
+#[cfg(feature = "cloud-sync")]
+use crate::cloud_sync::spawn_sync;
+#[cfg(feature = "zim")]
+use crate::dictionary::load_zim_dictionary;
+#[cfg(any(feature = "feeds", feature = "cloud-sync"))]
+use crate::network::NetworkTask;
+#[cfg(feature = "dictionary-manager")]
+use crate::view::dictionary::manager::DictionaryManager;
+#[cfg(feature = "feeds")]
+use crate::view::feeds::{spawn_fetch, Feeds};
+#[cfg(feature = "opds")]
+use crate::view::opds::OpdsBrowser;
 use crate::{
   battery::{Battery, KoboBattery},
+  conversion::queue_conversions,
   device::{FrontlightKind, Orientation, CURRENT_DEVICE},
   dictionary::{load_dictionary_from_file, Dictionary},
-  document::sys_info_as_html,
-  font::Fonts,
-  framebuffer::{Display, Framebuffer, KoboFramebuffer, UpdateMode},
-  frontlight::{Frontlight, NaturalFrontlight, PremixedFrontlight, StandardFrontlight},
-  geom::{Edge, Rectangle},
+  document::{sys_info_as_html, HumanSize},
+  feedback::{Feedback, FeedbackKind, KoboBeeper, KoboVibrator},
+  font::{FontIndex, Fonts},
+  framebuffer::{Display, Framebuffer, KoboFramebuffer, UpdateMode, UpdateStats},
+  frontlight::{
+    Frontlight,
+    LightLevels,
+    NaturalFrontlight,
+    PremixedFrontlight,
+    StandardFrontlight,
+  },
+  geom::{Axis, DiagDir, Dir, Edge, Rectangle},
   gesture::{gesture_events, GestureEvent},
   helpers::{load_json, load_toml, save_toml, IsHidden},
+  i18n::load_catalog,
   input::{
     button_scheme_event,
     device_events,
     display_rotate_event,
+    hotplug_events,
     raw_events,
     usb_events,
     ButtonCode,
     ButtonStatus,
     DeviceEvent,
+    InputDeviceKind,
     PowerSource,
     VAL_PRESS,
     VAL_RELEASE,
   },
-  library::Library,
+  insights::{self, insights_as_html, load_insights, purge_insights, Usage, INSIGHTS_PATH},
+  library::{Library, IMPORT_REPORT_PATH},
   lightsensor::{KoboLightSensor, LightSensor},
+  network::NetworkManager,
+  phrasebook::{export_phrasebook, load_phrasebook, phrasebook_as_html, PHRASEBOOK_PATH},
+  recorder::{record_raw_events, replay_raw_events, Session, RECORD_INPUT_VAR, REPLAY_INPUT_VAR},
+  resources::{available_memory, free_space},
+  resume,
   rtc::Rtc,
-  settings::{ButtonScheme, RotationLock, Settings, SETTINGS_PATH},
+  scheduler::{backup, Scheduler},
+  search_history::{
+    input_id,
+    input_name,
+    load_search_history,
+    save_search_history,
+    SearchHistory,
+    SEARCH_HISTORY_PATH,
+  },
+  settings::{
+    guess_frontlight,
+    ButtonScheme,
+    GlobalGesture,
+    ImportSettings,
+    NightFilterMode,
+    PowerProfile,
+    RotationLock,
+    ScheduledAction,
+    ScheduledTask,
+    Settings,
+    SETTINGS_PATH,
+  },
   view::{
     calculator::Calculator,
+    command_palette::toggle_command_palette,
     common::{
+      button_action,
+      button_action_event,
+      fetches_when_online,
       locate,
       locate_by_id,
       overlapping_rectangle,
@@ -40,18 +94,31 @@ use crate::{
     dictionary::Dictionary as DictionaryApp,
     frontlight::FrontlightWindow,
     handle_event,
+    has_inversion_exemptions,
     home::Home,
     intermission::{IntermKind, Intermission},
-    keyboard::Layout,
+    keyboard::{Layout, WordModel},
     menu::{Menu, MenuKind},
-    notification::Notification,
+    notification::{
+      notify_progress,
+      push_notification,
+      Notification,
+      NotificationCenter,
+      NotificationPriority,
+    },
     process_render_queue,
+    progress::show_progress,
     reader::Reader,
+    report::{report_error, show_report, Severity},
     sketch::Sketch,
+    tap_zones_editor::TapZonesEditor,
+    transition::Transition,
+    undo::{revert_last, show_undo_notification, UndoDir, UndoStack},
     AppCmd,
     EntryId,
     EntryKind,
     Event,
+    Hub,
     RenderData,
     RenderQueue,
     View,
@@ -59,7 +126,7 @@ use crate::{
   },
 };
 use anyhow::{format_err, Context as ResultExt, Error};
-use chrono::Local;
+use chrono::{Duration as ChronoDuration, Local, Timelike, Utc};
 use fxhash::FxHashMap;
 use globset::Glob;
 use rand_core::SeedableRng;
@@ -68,9 +135,12 @@ use std::{
   collections::{BTreeMap, VecDeque},
   env,
   fs,
-  path::Path,
+  path::{Path, PathBuf},
   process::Command,
-  sync::mpsc::{self, Receiver, Sender},
+  sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc,
+  },
   thread,
   time::{Duration, Instant},
 };
@@ -83,14 +153,20 @@ const EVENT_BUTTONS: &str = "/dev/input/event0";
 const EVENT_TOUCH_SCREEN: &str = "/dev/input/event1";
 const KOBO_UPDATE_BUNDLE: &str = "/mnt/onboard/.kobo/KoboRoot.tgz";
 const KEYBOARD_LAYOUTS_DIRNAME: &str = "keyboard-layouts";
-const DICTIONARIES_DIRNAME: &str = "dictionaries";
+pub const DICTIONARIES_DIRNAME: &str = "dictionaries";
 const INPUT_HISTORY_SIZE: usize = 32;
+const RECENT_COMMANDS_SIZE: usize = 8;
 
 const CLOCK_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
 const BATTERY_REFRESH_INTERVAL: Duration = Duration::from_secs(299);
 const AUTO_SUSPEND_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
 const SUSPEND_WAIT_DELAY: Duration = Duration::from_secs(15);
 const PREPARE_SUSPEND_WAIT_DELAY: Duration = Duration::from_secs(3);
+// The timers don't advance while the device is asleep: the next run of the scheduled tasks is
+// checked at least this often.
+const MAX_SCHEDULER_DELAY: Duration = Duration::from_secs(3600);
+// How long the device stays awake after an alarm of the scheduled tasks.
+const SCHEDULED_WAKE_UP_DELAY: Duration = Duration::from_secs(120);
 
 pub struct Context {
   pub fb: Box<dyn Framebuffer>,
@@ -99,19 +175,37 @@ pub struct Context {
   pub settings: Settings,
   pub library: Library,
   pub fonts: Fonts,
+  // The user fonts available to the reader.
+  pub font_index: FontIndex,
   pub dictionaries: BTreeMap<String, Dictionary>,
   pub keyboard_layouts: BTreeMap<String, Layout>,
   pub input_history: FxHashMap<ViewId, VecDeque<String>>,
+  // The queries pinned in the history menu of the search inputs.
+  pub saved_searches: FxHashMap<ViewId, Vec<String>>,
+  // The labels of the commands recently run from the command palette.
+  pub recent_commands: VecDeque<String>,
+  // The text copied by the last copy command, shared by the applications.
+  pub clipboard: Option<String>,
   pub frontlight: Box<dyn Frontlight>,
   pub battery: Box<dyn Battery>,
   pub lightsensor: Box<dyn LightSensor>,
-  pub notification_index: u8,
+  pub feedback: Box<dyn Feedback>,
+  pub notifications: NotificationCenter,
+  // The destructive actions that can be undone.
+  pub undo: UndoStack,
   pub kb_rect: Rectangle,
   pub rng: Xoroshiro128Plus,
   pub plugged: bool,
   pub covered: bool,
   pub shared: bool,
   pub online: bool,
+  pub network: NetworkManager,
+  // Whether the night filter is currently applied.
+  pub night_filter: bool,
+  // The power profile in effect, consulted by the subsystems that can trade speed for battery.
+  pub power_profile: PowerProfile,
+  // Whether the layout of the interface is mirrored for a right-to-left language.
+  pub rtl: bool,
 }
 
 impl Context {
@@ -124,10 +218,12 @@ impl Context {
     battery: Box<dyn Battery>,
     frontlight: Box<dyn Frontlight>,
     lightsensor: Box<dyn LightSensor>,
+    feedback: Box<dyn Feedback>,
   ) -> Context {
     let dims = fb.dims();
     let rotation = CURRENT_DEVICE.transformed_rotation(fb.rotation());
     let rng = Xoroshiro128Plus::seed_from_u64(Local::now().timestamp_nanos() as u64);
+    let power_profile = settings.power.profile;
     Context {
       fb,
       rtc,
@@ -135,34 +231,104 @@ impl Context {
       library,
       settings,
       fonts,
+      font_index: FontIndex::default(),
       dictionaries: BTreeMap::new(),
       keyboard_layouts: BTreeMap::new(),
       input_history: FxHashMap::default(),
+      saved_searches: FxHashMap::default(),
+      recent_commands: VecDeque::new(),
+      clipboard: None,
       battery,
       frontlight,
       lightsensor,
-      notification_index: 0,
+      feedback,
+      notifications: NotificationCenter::default(),
+      undo: UndoStack::default(),
       kb_rect: Rectangle::default(),
       rng,
       plugged: false,
       covered: false,
       shared: false,
       online: false,
+      network: NetworkManager::default(),
+      night_filter: false,
+      power_profile,
+      rtl: false,
+    }
+  }
+
+  // Emits a click or a vibration, if the settings ask for it.
+  pub fn emit_feedback(&mut self, kind: FeedbackKind) {
+    let enabled = match kind {
+      FeedbackKind::PageTurn => self.settings.feedback.page_turns,
+      FeedbackKind::KeyPress => self.settings.feedback.key_presses,
+    };
+    if enabled {
+      if let Err(e) = self.feedback.emit(kind) {
+        eprintln!("Can't emit feedback: {:#}.", e);
+      }
+    }
+  }
+
+  // Selects the power profile matching the battery state. Returns whether it changed.
+  pub fn update_power_profile(&mut self) -> bool {
+    let capacity = self.battery.capacity().ok();
+    let profile = self
+      .settings
+      .power
+      .effective_profile(capacity, self.plugged);
+    if profile == self.power_profile {
+      return false;
     }
+    self.power_profile = profile;
+    true
+  }
+
+  // Appends an event to the usage insights, if the user opted in.
+  pub fn record_usage(&self, usage: Usage) {
+    if self.settings.usage_insights {
+      if let Err(e) = insights::record_usage(INSIGHTS_PATH, usage) {
+        eprintln!("Can't record usage: {:#}.", e);
+      }
+    }
+  }
+
+  // Whether new documents can be added to the current library.
+  pub fn has_room(&self) -> bool {
+    free_space(&self.library.home).map_or(true, |free| free >= self.settings.storage.block)
   }
 
   pub fn batch_import(&mut self) {
+    if !self.has_room() {
+      eprintln!("Not enough storage space to import documents.");
+      return;
+    }
     let prefix = self.library.home.clone();
     let import_settings = self.settings.import.clone();
-    self.library.import(&prefix, &import_settings);
+    let mut report = self.library.import(&prefix, &import_settings);
+    // The sources are only brought into the current library.
+    let other_settings = ImportSettings {
+      sources: Vec::new(),
+      ..import_settings.clone()
+    };
     let selected_library = self.settings.selected_library;
     for (index, library_settings) in self.settings.libraries.iter().enumerate() {
       if index == selected_library {
         continue;
       }
       let mut library = Library::new(&library_settings.path, library_settings.mode);
-      library.import(&library_settings.path, &import_settings);
-      library.flush();
+      report.append(library.import(&library_settings.path, &other_settings));
+      if let Err(e) = library.flush() {
+        eprintln!(
+          "Can't save the metadata of {}: {:#}.",
+          library_settings.name, e
+        );
+      }
+    }
+    if import_settings.dry_run {
+      if let Err(e) = report.save(IMPORT_REPORT_PATH) {
+        eprintln!("Can't save the import report: {:#}.", e);
+      }
     }
   }
 
@@ -181,14 +347,52 @@ impl Context {
       if !glob.is_match(path) {
         continue;
       }
-      if let Ok(layout) = load_json::<Layout, _>(path) {
+      if let Ok(mut layout) = load_json::<Layout, _>(path) {
+        // The word frequencies of the layout's language sit next to it.
+        let words_path = path.with_extension("txt");
+        if words_path.exists() {
+          match WordModel::load(&words_path) {
+            Ok(model) => layout.words = Arc::new(model),
+            Err(e) => eprintln!("Can't load {}: {}.", words_path.display(), e),
+          }
+        }
         self.keyboard_layouts.insert(layout.name.clone(), layout);
       }
     }
   }
 
+  pub fn load_font_index(&mut self) {
+    let mut dirs = vec![PathBuf::from(&self.settings.reader.font_path)];
+    dirs.extend_from_slice(&self.settings.reader.font_dirs);
+    self.font_index = FontIndex::scan(&dirs);
+  }
+
+  pub fn load_catalog(&mut self) {
+    match load_catalog(&self.settings.language) {
+      Ok(rtl) => self.rtl = rtl,
+      Err(e) => {
+        self.rtl = false;
+        eprintln!(
+          "Can't load the catalog of {:?}: {:#}.",
+          self.settings.language, e
+        );
+      },
+    }
+  }
+
+  // Swaps the horizontal directions of the interface gestures when the layout is mirrored.
+  pub fn ui_dir(&self, dir: Dir) -> Dir {
+    if self.rtl && dir.axis() == Axis::Horizontal {
+      dir.opposite()
+    } else {
+      dir
+    }
+  }
+
   pub fn load_dictionaries(&mut self) {
     let glob = Glob::new("**/*.index").unwrap().compile_matcher();
+    #[cfg(feature = "zim")]
+    let zim_glob = Glob::new("**/*.zim").unwrap().compile_matcher();
     for entry in WalkDir::new(Path::new(DICTIONARIES_DIRNAME))
       .min_depth(1)
       .into_iter()
@@ -198,6 +402,23 @@ impl Context {
         continue;
       }
       let entry = entry.unwrap();
+      #[cfg(feature = "zim")]
+      if zim_glob.is_match(entry.path()) {
+        match load_zim_dictionary(entry.path()) {
+          Ok(mut dict) => {
+            let name = dict.short_name().ok().unwrap_or_else(|| {
+              entry
+                .path()
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+            });
+            self.dictionaries.insert(name, dict);
+          },
+          Err(e) => eprintln!("Can't load {}: {}.", entry.path().display(), e),
+        }
+        continue;
+      }
       if !glob.is_match(entry.path()) {
         continue;
       }
@@ -238,6 +459,73 @@ impl Context {
     }
   }
 
+  pub fn load_search_history(&mut self) {
+    let history = match load_search_history(SEARCH_HISTORY_PATH) {
+      Ok(history) => history,
+      Err(e) => {
+        eprintln!("Can't load the search history: {:#}.", e);
+        return;
+      },
+    };
+    for (name, queries) in history.recent {
+      if let Some(id) = input_id(&name) {
+        self
+          .input_history
+          .insert(id, queries.into_iter().take(INPUT_HISTORY_SIZE).collect());
+      }
+    }
+    for (name, queries) in history.saved {
+      if let Some(id) = input_id(&name) {
+        self.saved_searches.insert(id, queries);
+      }
+    }
+  }
+
+  pub fn save_search_history(&self) -> Result<(), Error> {
+    let mut history = SearchHistory::default();
+    for (id, queries) in &self.input_history {
+      if let Some(name) = input_name(*id) {
+        history
+          .recent
+          .insert(name.to_string(), queries.iter().cloned().collect());
+      }
+    }
+    for (id, queries) in &self.saved_searches {
+      if let Some(name) = input_name(*id) {
+        history.saved.insert(name.to_string(), queries.clone());
+      }
+    }
+    save_search_history(&history, SEARCH_HISTORY_PATH)
+  }
+
+  pub fn save_search(&mut self, text: &str, id: ViewId) {
+    if text.is_empty() {
+      return;
+    }
+    let saved = self.saved_searches.entry(id).or_default();
+    if !saved.iter().any(|s| s == text) {
+      saved.push(text.to_string());
+    }
+    if let Err(e) = self.save_search_history() {
+      eprintln!("Can't save the search history: {:#}.", e);
+    }
+  }
+
+  pub fn remove_saved_search(&mut self, text: &str, id: ViewId) {
+    if let Some(saved) = self.saved_searches.get_mut(&id) {
+      saved.retain(|s| s != text);
+    }
+    if let Err(e) = self.save_search_history() {
+      eprintln!("Can't save the search history: {:#}.", e);
+    }
+  }
+
+  pub fn record_command(&mut self, label: &str) {
+    self.recent_commands.retain(|l| l != label);
+    self.recent_commands.push_front(label.to_string());
+    self.recent_commands.truncate(RECENT_COMMANDS_SIZE);
+  }
+
   pub fn set_frontlight(&mut self, enable: bool) {
     self.settings.frontlight = enable;
 
@@ -251,6 +539,19 @@ impl Context {
       self.frontlight.set_warmth(0.0);
     }
   }
+
+  // Sets the frontlight levels guessed from the presets and the ambient light.
+  pub fn guess_frontlight(&mut self) {
+    let lightsensor_level = if CURRENT_DEVICE.has_lightsensor() {
+      self.lightsensor.level().ok()
+    } else {
+      None
+    };
+    if let Some(levels) = guess_frontlight(lightsensor_level, &self.settings.frontlight_presets) {
+      self.frontlight.set_intensity(levels.intensity);
+      self.frontlight.set_warmth(levels.warmth);
+    }
+  }
 }
 
 struct Task {
@@ -267,8 +568,10 @@ impl Task {
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum TaskId {
   CheckBattery,
+  AutoSave,
   PrepareSuspend,
   Suspend,
+  Scheduler,
 }
 
 struct HistoryItem {
@@ -291,6 +594,7 @@ fn build_context(fb: Box<dyn Framebuffer>) -> Result<Context, Error> {
   }
 
   let mut settings = settings.unwrap_or_default();
+  settings.unbind_duplicate_gestures();
 
   if settings.libraries.is_empty() {
     return Err(format_err!("No libraries found."));
@@ -313,6 +617,14 @@ fn build_context(fb: Box<dyn Framebuffer>) -> Result<Context, Error> {
     Box::new(0u16) as Box<dyn LightSensor>
   };
 
+  let feedback = if CURRENT_DEVICE.has_beeper() {
+    Box::new(KoboBeeper::new().context("Can't create beeper.")?) as Box<dyn Feedback>
+  } else if CURRENT_DEVICE.has_vibrator() {
+    Box::new(KoboVibrator::new().context("Can't create vibrator.")?) as Box<dyn Feedback>
+  } else {
+    Box::new(()) as Box<dyn Feedback>
+  };
+
   let levels = settings.frontlight_levels;
   let frontlight = match CURRENT_DEVICE.frontlight_kind() {
     FrontlightKind::Standard => Box::new(
@@ -337,6 +649,7 @@ fn build_context(fb: Box<dyn Framebuffer>) -> Result<Context, Error> {
     battery,
     frontlight,
     lightsensor,
+    feedback,
   ))
 }
 
@@ -358,6 +671,108 @@ fn schedule_task(
   });
 }
 
+// Replaces the pending automatic save of the reading state, if any.
+fn schedule_auto_save(context: &Context, hub: &Sender<Event>, tasks: &mut Vec<Task>) {
+  tasks.retain(|task| task.id != TaskId::AutoSave);
+  let interval = context.settings.reader.auto_save.interval;
+  if interval > 0 {
+    schedule_task(
+      TaskId::AutoSave,
+      Event::AutoSave,
+      Duration::from_secs(60 * interval),
+      hub,
+      tasks,
+    );
+  }
+}
+
+// Replaces the pending run of the scheduled tasks, if any.
+fn schedule_scheduled_tasks(scheduler: &Scheduler, hub: &Sender<Event>, tasks: &mut Vec<Task>) {
+  tasks.retain(|task| task.id != TaskId::Scheduler);
+  if let Some(next) = scheduler.next_run() {
+    let delay = (next - Local::now())
+      .to_std()
+      .unwrap_or_default()
+      .min(MAX_SCHEDULER_DELAY);
+    schedule_task(
+      TaskId::Scheduler,
+      Event::RunScheduledTasks,
+      delay + Duration::from_secs(1),
+      hub,
+      tasks,
+    );
+  }
+}
+
+fn run_scheduled_task(
+  task: &ScheduledTask,
+  asleep: bool,
+  view: &mut dyn View,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  match task.action {
+    #[cfg(feature = "feeds")]
+    ScheduledAction::FetchFeeds => {
+      if context.settings.feeds.sources.is_empty() {
+        return;
+      }
+      if context.online {
+        spawn_fetch(hub, context);
+      } else if context
+        .network
+        .defer(NetworkTask::Feeds, context.settings.wifi)
+      {
+        set_wifi(true, context);
+      }
+    },
+    #[cfg(feature = "cloud-sync")]
+    ScheduledAction::Sync => {
+      if context.online {
+        spawn_sync(hub, context);
+      } else if context
+        .network
+        .defer(NetworkTask::Sync, context.settings.wifi)
+      {
+        set_wifi(true, context);
+      }
+    },
+    #[cfg(not(feature = "feeds"))]
+    ScheduledAction::FetchFeeds => {
+      eprintln!("Can't fetch the feeds: the feed reader isn't built in.")
+    },
+    #[cfg(not(feature = "cloud-sync"))]
+    ScheduledAction::Sync => eprintln!("Can't synchronize: the cloud sync isn't built in."),
+    ScheduledAction::Backup => match backup(context) {
+      Ok(path) => println!("Saved a backup in {}.", path.display()),
+      Err(e) => report_error(hub, Severity::Warning, "Can't save the backup", &e),
+    },
+    ScheduledAction::LoadPreset => {
+      if task.preset >= context.settings.frontlight_presets.len() {
+        eprintln!("Can't find the frontlight preset {}.", task.preset);
+      } else if asleep {
+        // The levels are restored when the device wakes up.
+        context.settings.frontlight_levels =
+          context.settings.frontlight_presets[task.preset].frontlight_levels;
+      } else {
+        load_frontlight_preset(task.preset, view, hub, rq, context);
+      }
+    },
+    ScheduledAction::Run => {
+      if let Some(program) = task.program.clone() {
+        thread::spawn(move || match Command::new(&program).status() {
+          Ok(status) if !status.success() => {
+            eprintln!("{} failed: {}.", program.display(), status);
+          },
+          Err(e) => eprintln!("Can't run {}: {:#}.", program.display(), e),
+          _ => (),
+        });
+      }
+    },
+  }
+}
+
 fn resume(
   id: TaskId,
   tasks: &mut Vec<Task>,
@@ -379,6 +794,7 @@ fn resume(
   }
   if id == TaskId::Suspend || id == TaskId::PrepareSuspend {
     tasks.retain(|task| task.id != TaskId::PrepareSuspend);
+    resume::clear_snapshot();
     if let Some(index) = locate::<Intermission>(view) {
       let rect = *view.child(index).rect();
       view.children_mut().remove(index);
@@ -389,12 +805,132 @@ fn resume(
   }
 }
 
+// The resources that are currently known to be running low.
+#[derive(Debug, Default)]
+struct ResourceWarnings {
+  storage: bool,
+  memory: bool,
+}
+
+// Warns once each time a resource starts running low.
+fn check_resources(
+  warnings: &mut ResourceWarnings,
+  view: &mut dyn View,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  if let Some(free) = free_space(&context.library.home) {
+    let low = free < context.settings.storage.warn;
+    if low && !warnings.storage {
+      let notif = Notification::new(
+        ViewId::LowStorageNotif,
+        tr!("The storage is almost full: {} left.", free.human_size()),
+        context,
+      )
+      .with_priority(NotificationPriority::High);
+      push_notification(view.children_mut(), notif, hub, rq, context);
+    }
+    warnings.storage = low;
+  }
+
+  if let Some(available) = available_memory() {
+    let low = available < context.settings.memory.warn;
+    if low && !warnings.memory {
+      let notif = Notification::new(
+        ViewId::LowMemoryNotif,
+        tr!("The available memory is getting low."),
+        context,
+      )
+      .with_priority(NotificationPriority::High);
+      push_notification(view.children_mut(), notif, hub, rq, context);
+    }
+    warnings.memory = low;
+  }
+}
+
+fn is_global_gesture(ge: &GestureEvent, gesture: GlobalGesture) -> bool {
+  match (gesture, ge) {
+    (GlobalGesture::BottomLeftMultiCorner, GestureEvent::MultiCorner { dir, .. }) => {
+      *dir == DiagDir::SouthWest
+    },
+    (GlobalGesture::BottomRightMultiCorner, GestureEvent::MultiCorner { dir, .. }) => {
+      *dir == DiagDir::SouthEast
+    },
+    (GlobalGesture::NorthMultiArrow, GestureEvent::MultiArrow { dir, .. }) => *dir == Dir::North,
+    _ => false,
+  }
+}
+
+// Applies the frontlight preset that follows the one matching the current levels.
+fn cycle_frontlight_preset(
+  view: &mut dyn View,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  if context.settings.frontlight_presets.is_empty() {
+    return;
+  }
+
+  let current = if context.settings.frontlight {
+    context.frontlight.levels()
+  } else {
+    context.settings.frontlight_levels
+  };
+  let presets = &context.settings.frontlight_presets;
+  let index = presets
+    .iter()
+    .position(|p| {
+      (p.frontlight_levels.intensity - current.intensity).abs() < 0.5
+        && (p.frontlight_levels.warmth - current.warmth).abs() < 0.5
+    })
+    .map_or(0, |i| (i + 1) % presets.len());
+  load_frontlight_preset(index, view, hub, rq, context);
+}
+
+fn load_frontlight_preset(
+  index: usize,
+  view: &mut dyn View,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  let preset = &context.settings.frontlight_presets[index];
+  let LightLevels { intensity, warmth } = preset.frontlight_levels;
+  let text = tr!("Frontlight preset {}.", preset.name());
+
+  if context.settings.frontlight {
+    context.frontlight.set_intensity(intensity);
+    context.frontlight.set_warmth(warmth);
+  } else {
+    // The levels are restored when the frontlight is turned on.
+    context.settings.frontlight_levels = preset.frontlight_levels;
+    hub.send(Event::ToggleFrontlight).ok();
+  }
+
+  if let Some(index) = locate_by_id(view, ViewId::PresetNotif) {
+    let rect = overlapping_rectangle(view.child(index));
+    rq.add(RenderData::expose(rect, UpdateMode::Gui));
+    view.children_mut().remove(index);
+  }
+
+  let notif =
+    Notification::new(ViewId::PresetNotif, text, context).with_priority(NotificationPriority::Low);
+  push_notification(view.children_mut(), notif, hub, rq, context);
+}
+
 fn power_off(
   view: &mut dyn View,
   history: &mut Vec<HistoryItem>,
   updating: &mut FxHashMap<u32, Rectangle>,
   context: &mut Context,
 ) {
+  if context.settings.instant_resume {
+    resume::save_snapshot(view, context.fb.as_ref(), &mut context.fonts)
+      .map_err(|e| eprintln!("Can't save the resume snapshot: {}.", e))
+      .ok();
+  }
   let (tx, _rx) = mpsc::channel();
   view.handle_event(
     &Event::Back,
@@ -428,9 +964,28 @@ fn set_wifi(enable: bool, context: &mut Context) {
   } else {
     Command::new("scripts/wifi-disable.sh").status().ok();
     context.online = false;
+    context.network.reset();
   }
 }
 
+// Applies or removes the night filter according to its settings. Returns whether the screen
+// needs to be rendered again.
+fn update_night_filter(context: &mut Context) -> bool {
+  let settings = &context.settings.night_filter;
+  let active = settings.is_active(Local::now().time());
+  if active == context.night_filter {
+    return false;
+  }
+  let table = if active {
+    Some(settings.tone_table())
+  } else {
+    None
+  };
+  context.fb.set_tone_table(table);
+  context.night_filter = active;
+  true
+}
+
 enum ExitStatus {
   Quit,
   Reboot,
@@ -448,14 +1003,40 @@ pub fn run() -> Result<(), Error> {
   }
 
   let mut context = build_context(Box::new(fb)).context("Can't build context.")?;
+  update_night_filter(&mut context);
+  context.update_power_profile();
+  let resume_info = if context.settings.instant_resume {
+    resume::load_snapshot(context.fb.as_mut())
+  } else {
+    None
+  };
   if context.settings.import.startup_trigger {
     context.batch_import();
   }
   context.load_dictionaries();
+  context.load_search_history();
   context.load_keyboard_layouts();
+  context.load_font_index();
+  context.load_catalog();
 
   let paths = vec![EVENT_BUTTONS.to_string(), EVENT_TOUCH_SCREEN.to_string()];
-  let (raw_sender, raw_receiver) = raw_events(paths);
+  let (raw_sender, raw_receiver) = raw_events(paths.clone());
+  let hotplug = hotplug_events(paths, raw_sender.clone());
+  let raw_receiver = match env::var_os(RECORD_INPUT_VAR) {
+    Some(path) => record_raw_events(raw_receiver, &path, context.display)
+      .context("Can't record input events.")?,
+    None => raw_receiver,
+  };
+  if let Some(path) = env::var_os(REPLAY_INPUT_VAR) {
+    let session = Session::load(&path).context("Can't load input session.")?;
+    if session
+      .display
+      .map_or(false, |d| d.rotation != context.display.rotation)
+    {
+      eprintln!("The input session was recorded with a different rotation.");
+    }
+    replay_raw_events(session, raw_sender.clone());
+  }
   let touch_screen = gesture_events(device_events(
     raw_receiver,
     context.display,
@@ -464,6 +1045,9 @@ pub fn run() -> Result<(), Error> {
   let usb_port = usb_events();
 
   let (tx, rx) = mpsc::channel();
+  if context.settings.import.startup_trigger {
+    queue_conversions(&tx, &context);
+  }
   let tx2 = tx.clone();
 
   thread::spawn(move || {
@@ -479,6 +1063,13 @@ pub fn run() -> Result<(), Error> {
     }
   });
 
+  let tx3 = tx.clone();
+  thread::spawn(move || {
+    while let Ok(evt) = hotplug.recv() {
+      tx3.send(Event::Device(evt)).ok();
+    }
+  });
+
   let tx4 = tx.clone();
   thread::spawn(move || loop {
     thread::sleep(CLOCK_REFRESH_INTERVAL);
@@ -520,6 +1111,7 @@ pub fn run() -> Result<(), Error> {
   let mut view: Box<dyn View> = Box::new(Home::new(context.fb.rect(), &mut rq, &mut context)?);
 
   let mut updating = FxHashMap::default();
+  let mut resource_warnings = ResourceWarnings::default();
   let current_dir = env::current_dir()?;
 
   println!(
@@ -541,9 +1133,22 @@ pub fn run() -> Result<(), Error> {
     &tx,
     &mut tasks,
   );
+  schedule_auto_save(&context, &tx, &mut tasks);
+  let mut scheduler = Scheduler::new(&context.settings.scheduled_tasks);
+  schedule_scheduled_tasks(&scheduler, &tx, &mut tasks);
   tx.send(Event::WakeUp).ok();
 
+  // The snapshot stays on the screen until the reader is rendered.
+  if let Some(info) = resume_info {
+    rq = RenderQueue::new();
+    tx.send(Event::Open(Box::new(info))).ok();
+  }
+
+  let mut transition: Option<Transition> = None;
+
   while let Ok(evt) = rx.recv() {
+    let view_id = view.id();
+
     match evt {
       Event::Device(de) => match de {
         DeviceEvent::Button {
@@ -574,7 +1179,7 @@ pub fn run() -> Result<(), Error> {
               &mut context,
             );
           } else {
-            let interm = Intermission::new(context.fb.rect(), IntermKind::Suspend, &context);
+            let interm = Intermission::new(context.fb.rect(), IntermKind::Suspend, &mut context);
             rq.add(RenderData::new(
               interm.id(),
               *interm.rect(),
@@ -597,6 +1202,17 @@ pub fn run() -> Result<(), Error> {
         } => {
           tx.send(Event::ToggleFrontlight).ok();
         },
+        DeviceEvent::Button { code, status, .. }
+          if button_action(view.as_ref(), code, &context).is_some() =>
+        {
+          if status == ButtonStatus::Pressed {
+            if let Some(evt) =
+              button_action(view.as_ref(), code, &context).and_then(button_action_event)
+            {
+              tx.send(evt).ok();
+            }
+          }
+        },
         DeviceEvent::CoverOn => {
           context.covered = true;
 
@@ -609,7 +1225,7 @@ pub fn run() -> Result<(), Error> {
             continue;
           }
 
-          let interm = Intermission::new(context.fb.rect(), IntermKind::Suspend, &context);
+          let interm = Intermission::new(context.fb.rect(), IntermKind::Suspend, &mut context);
           rq.add(RenderData::new(
             interm.id(),
             *interm.rect(),
@@ -685,16 +1301,30 @@ pub fn run() -> Result<(), Error> {
             .unwrap_or_default();
           let notif = Notification::new(
             ViewId::NetUpNotif,
-            format!("Network is up ({}, {}).", ip, essid),
-            &tx,
-            &mut rq,
+            tr!("Network is up ({}, {}).", ip, essid),
             &mut context,
-          );
+          )
+          .with_priority(NotificationPriority::Low);
           context.online = true;
-          view.children_mut().push(Box::new(notif) as Box<dyn View>);
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+          #[cfg(feature = "cloud-sync")]
+          spawn_sync(&tx, &context);
+          for task in context.network.take_deferred() {
+            context.network.release(task);
+            #[cfg(feature = "feeds")]
+            {
+              if task == NetworkTask::Feeds {
+                spawn_fetch(&tx, &context);
+              }
+            }
+          }
           if view.is::<Home>() {
             view.handle_event(&evt, &tx, &mut bus, &mut rq, &mut context);
           } else {
+            // The feeds, the dictionaries index and the catalogs are fetched when the network is up.
+            if fetches_when_online(view.as_ref()) {
+              view.handle_event(&evt, &tx, &mut bus, &mut rq, &mut context);
+            }
             let (tx, _rx) = mpsc::channel();
             history[0].view.handle_event(
               &evt,
@@ -711,6 +1341,7 @@ pub fn run() -> Result<(), Error> {
           }
 
           context.plugged = true;
+          context.update_power_profile();
 
           tasks.retain(|task| task.id != TaskId::CheckBattery);
 
@@ -762,7 +1393,7 @@ pub fn run() -> Result<(), Error> {
                 let dialog = Dialog::new(
                   ViewId::ShareDialog,
                   Some(Event::PrepareShare),
-                  "Share storage via USB?".to_string(),
+                  tr!("Share storage via USB?"),
                   &mut context,
                 );
                 rq.add(RenderData::new(
@@ -801,6 +1432,7 @@ pub fn run() -> Result<(), Error> {
               load_toml::<Settings, _>(path).map_err(|e| eprintln!("Can't load settings: {}", e))
             {
               context.settings = settings;
+              context.settings.unbind_duplicate_gestures();
             }
             if context.settings.wifi {
               Command::new("scripts/wifi-enable.sh").status().ok();
@@ -821,10 +1453,15 @@ pub fn run() -> Result<(), Error> {
             context.library.reload();
             if context.settings.import.unshare_trigger {
               context.batch_import();
+              queue_conversions(&tx, &context);
             }
             view.handle_event(&Event::Reseed, &tx, &mut bus, &mut rq, &mut context);
+            schedule_auto_save(&context, &tx, &mut tasks);
+            scheduler = Scheduler::new(&context.settings.scheduled_tasks);
+            schedule_scheduled_tasks(&scheduler, &tx, &mut tasks);
           } else {
             context.plugged = false;
+            context.update_power_profile();
             schedule_task(
               TaskId::CheckBattery,
               Event::CheckBattery,
@@ -869,7 +1506,12 @@ pub fn run() -> Result<(), Error> {
             continue;
           }
 
-          if let Some(rotation_lock) = context.settings.rotation_lock {
+          let rotation_lock = view
+            .downcast_ref::<Reader>()
+            .map_or(context.settings.rotation_lock, |r| {
+              r.rotation_lock(context.settings.rotation_lock)
+            });
+          if let Some(rotation_lock) = rotation_lock {
             let orientation = CURRENT_DEVICE.orientation(n);
             if rotation_lock == RotationLock::Current
               || (rotation_lock == RotationLock::Portrait && orientation == Orientation::Landscape)
@@ -884,10 +1526,43 @@ pub fn run() -> Result<(), Error> {
         DeviceEvent::UserActivity if context.settings.auto_suspend > 0 => {
           inactive_since = Instant::now();
         },
+        DeviceEvent::Attach(kind) | DeviceEvent::Detach(kind) => {
+          let attached = matches!(de, DeviceEvent::Attach(..));
+          let msg = match (kind, attached) {
+            (InputDeviceKind::Keyboard, true) => tr!("Keyboard connected."),
+            (InputDeviceKind::Keyboard, false) => tr!("Keyboard disconnected."),
+            (InputDeviceKind::Stylus, true) => tr!("Stylus connected."),
+            (InputDeviceKind::Stylus, false) => tr!("Stylus disconnected."),
+            (InputDeviceKind::Other, true) => tr!("Input device connected."),
+            (InputDeviceKind::Other, false) => tr!("Input device disconnected."),
+          };
+          tx.send(Event::Notify(msg)).ok();
+          handle_event(view.as_mut(), &evt, &tx, &mut bus, &mut rq, &mut context);
+        },
         _ => {
           handle_event(view.as_mut(), &evt, &tx, &mut bus, &mut rq, &mut context);
         },
       },
+      Event::RunScheduledTasks => {
+        let asleep = tasks
+          .iter()
+          .any(|task| task.id == TaskId::PrepareSuspend || task.id == TaskId::Suspend);
+        for task in scheduler.due_tasks(Local::now()) {
+          run_scheduled_task(&task, asleep, view.as_mut(), &tx, &mut rq, &mut context);
+        }
+        schedule_scheduled_tasks(&scheduler, &tx, &mut tasks);
+      },
+      Event::AutoSave => {
+        schedule_auto_save(&context, &tx, &mut tasks);
+        if tasks
+          .iter()
+          .any(|task| task.id == TaskId::PrepareSuspend || task.id == TaskId::Suspend)
+          || context.shared
+        {
+          continue;
+        }
+        handle_event(view.as_mut(), &evt, &tx, &mut bus, &mut rq, &mut context);
+      },
       Event::CheckBattery => {
         schedule_task(
           TaskId::CheckBattery,
@@ -910,23 +1585,40 @@ pub fn run() -> Result<(), Error> {
           } else if v < context.settings.battery.warn {
             let notif = Notification::new(
               ViewId::LowBatteryNotif,
-              "The battery capacity is getting low.".to_string(),
-              &tx,
-              &mut rq,
+              tr!("The battery capacity is getting low."),
               &mut context,
-            );
-            view.children_mut().push(Box::new(notif) as Box<dyn View>);
+            )
+            .with_priority(NotificationPriority::High);
+            push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
           }
         }
+        if context.update_power_profile() {
+          let msg = tr!("Switched to the {} power profile.", context.power_profile);
+          tx.send(Event::Notify(msg.to_lowercase())).ok();
+        }
+        check_resources(
+          &mut resource_warnings,
+          view.as_mut(),
+          &tx,
+          &mut rq,
+          &mut context,
+        );
       },
       Event::PrepareSuspend => {
         tasks.retain(|task| task.id != TaskId::PrepareSuspend);
         updating.retain(|tok, _| context.fb.wait(*tok).is_err());
+        if context.settings.instant_resume {
+          resume::save_snapshot(view.as_ref(), context.fb.as_ref(), &mut context.fonts)
+            .map_err(|e| eprintln!("Can't save the resume snapshot: {}.", e))
+            .ok();
+        }
         let path = Path::new(SETTINGS_PATH);
-        save_toml(&context.settings, path)
-          .map_err(|e| eprintln!("Can't save settings: {}", e))
-          .ok();
-        context.library.flush();
+        if let Err(e) = save_toml(&context.settings, path) {
+          report_error(&tx, Severity::Error, "Can't save the settings", &e);
+        }
+        if let Err(e) = context.library.flush() {
+          report_error(&tx, Severity::Critical, "Can't save the metadata", &e);
+        }
 
         if context.settings.frontlight {
           context.settings.frontlight_levels = context.frontlight.levels();
@@ -947,10 +1639,17 @@ pub fn run() -> Result<(), Error> {
         );
       },
       Event::Suspend => {
-        if context.settings.auto_power_off > 0 {
+        let wake_up = scheduler.next_wake_up();
+        let power_off_at = if context.settings.auto_power_off > 0 {
+          Some(Local::now() + ChronoDuration::days(context.settings.auto_power_off as i64))
+        } else {
+          None
+        };
+        let alarm = wake_up.into_iter().chain(power_off_at).min();
+        if let Some(time) = alarm {
           context.rtc.iter().for_each(|rtc| {
             rtc
-              .set_alarm(context.settings.auto_power_off)
+              .set_alarm(time.with_timezone(&Utc))
               .map_err(|e| eprintln!("Can't set alarm: {}.", e))
               .ok();
           });
@@ -963,7 +1662,9 @@ pub fn run() -> Result<(), Error> {
         println!("{}", Local::now().format("Woke up on %B %-d, %Y at %H:%M."));
         Command::new("scripts/resume.sh").status().ok();
         inactive_since = Instant::now();
-        if context.settings.auto_power_off > 0 {
+        // Catch up with the tasks that were due while the device was asleep.
+        tx.send(Event::RunScheduledTasks).ok();
+        if alarm.is_some() {
           if let Some(enabled) = context.rtc.as_ref().and_then(|rtc| {
             rtc
               .is_alarm_enabled()
@@ -977,6 +1678,18 @@ pub fn run() -> Result<(), Error> {
                   .map_err(|e| eprintln!("Can't disable alarm: {}.", e))
                   .ok();
               });
+            } else if wake_up.map_or(false, |time| {
+              time <= Local::now() + ChronoDuration::minutes(1)
+            }) {
+              // Woken up by the scheduled tasks: go back to sleep once they're done.
+              tasks.retain(|task| task.id != TaskId::Suspend);
+              schedule_task(
+                TaskId::Suspend,
+                Event::Suspend,
+                SCHEDULED_WAKE_UP_DELAY,
+                &tx,
+                &mut tasks,
+              );
             } else {
               power_off(view.as_mut(), &mut history, &mut updating, &mut context);
               exit_status = ExitStatus::PowerOff;
@@ -1007,10 +1720,12 @@ pub fn run() -> Result<(), Error> {
           view = item.view;
         }
         let path = Path::new(SETTINGS_PATH);
-        save_toml(&context.settings, path)
-          .map_err(|e| eprintln!("Can't save settings: {}", e))
-          .ok();
-        context.library.flush();
+        if let Err(e) = save_toml(&context.settings, path) {
+          report_error(&tx, Severity::Error, "Can't save the settings", &e);
+        }
+        if let Err(e) = context.library.flush() {
+          report_error(&tx, Severity::Critical, "Can't save the metadata", &e);
+        }
 
         if context.settings.frontlight {
           context.settings.frontlight_levels = context.frontlight.levels();
@@ -1021,7 +1736,7 @@ pub fn run() -> Result<(), Error> {
           Command::new("scripts/wifi-disable.sh").status().ok();
           context.online = false;
         }
-        let interm = Intermission::new(context.fb.rect(), IntermKind::Share, &context);
+        let interm = Intermission::new(context.fb.rect(), IntermKind::Share, &mut context);
         rq.add(RenderData::new(
           interm.id(),
           *interm.rect(),
@@ -1065,11 +1780,20 @@ pub fn run() -> Result<(), Error> {
             }
           }
         },
+        _ if is_global_gesture(&ge, context.settings.preset_gesture) => {
+          cycle_frontlight_preset(view.as_mut(), &tx, &mut rq, &mut context);
+        },
+        _ if is_global_gesture(&ge, context.settings.palette_gesture) => {
+          toggle_command_palette(view.as_mut(), None, &tx, &mut rq, &mut context);
+        },
+        _ if is_global_gesture(&ge, context.settings.undo_gesture) => {
+          tx.send(Event::Undo).ok();
+        },
         _ => {
           handle_event(view.as_mut(), &evt, &tx, &mut bus, &mut rq, &mut context);
         },
       },
-      Event::ToggleFrontlight => {
+      Event::ToggleFrontlight if CURRENT_DEVICE.has_frontlight() => {
         context.set_frontlight(!context.settings.frontlight);
         view.handle_event(
           &Event::ToggleFrontlight,
@@ -1134,6 +1858,17 @@ pub fn run() -> Result<(), Error> {
         });
         view = next_view;
       },
+      Event::OpenHtml(ref html) => {
+        let r = Reader::from_html(context.fb.rect(), html, &tx, &mut context);
+        let mut next_view = Box::new(r) as Box<dyn View>;
+        transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
+        history.push(HistoryItem {
+          view,
+          rotation: context.display.rotation,
+          monochrome: context.fb.monochrome(),
+        });
+        view = next_view;
+      },
       Event::Select(EntryId::About) => {
         let dialog = Dialog::new(
           ViewId::AboutDialog,
@@ -1150,7 +1885,20 @@ pub fn run() -> Result<(), Error> {
       },
       Event::Select(EntryId::SystemInfo) => {
         view.children_mut().retain(|child| !child.is::<Menu>());
-        let html = sys_info_as_html();
+        // Measuring the storage used by each directory can take a while.
+        let home = context.library.home.clone();
+        let hub = tx.clone();
+        thread::spawn(move || {
+          hub.send(Event::OpenHtml(sys_info_as_html(&home))).ok();
+        });
+      },
+      Event::Select(EntryId::BrowsePhrasebook) => {
+        view.children_mut().retain(|child| !child.is::<Menu>());
+        let phrases = load_phrasebook(PHRASEBOOK_PATH).unwrap_or_else(|e| {
+          report_error(&tx, Severity::Warning, "Can't load the phrasebook", &e);
+          Vec::new()
+        });
+        let html = phrasebook_as_html(&phrases);
         let r = Reader::from_html(context.fb.rect(), &html, &tx, &mut context);
         let mut next_view = Box::new(r) as Box<dyn View>;
         transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
@@ -1161,14 +1909,112 @@ pub fn run() -> Result<(), Error> {
         });
         view = next_view;
       },
+      Event::Undo | Event::Select(EntryId::Revert(UndoDir::Undo)) => {
+        revert_last(
+          UndoDir::Undo,
+          view.as_mut(),
+          &tx,
+          &mut bus,
+          &mut rq,
+          &mut context,
+        );
+      },
+      Event::Redo | Event::Select(EntryId::Revert(UndoDir::Redo)) => {
+        revert_last(
+          UndoDir::Redo,
+          view.as_mut(),
+          &tx,
+          &mut bus,
+          &mut rq,
+          &mut context,
+        );
+      },
+      Event::UndoRecorded => {
+        show_undo_notification(view.as_mut(), &tx, &mut rq, &mut context);
+      },
+      Event::Select(EntryId::ExportPhrasebook) => match export_phrasebook(&context.library.home) {
+        Ok(path) => {
+          let notif = Notification::new(
+            ViewId::PhrasebookNotif,
+            tr!("Phrasebook exported to {}.", path.display()),
+            &mut context,
+          );
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+        },
+        Err(e) => report_error(&tx, Severity::Error, "Can't export the phrasebook", &e),
+      },
+      Event::Select(EntryId::SetLanguage(ref language)) => {
+        if *language != context.settings.language {
+          context.settings.language = language.clone();
+          context.load_catalog();
+          let notif = Notification::new(
+            ViewId::MessageNotif,
+            tr!("The views opened from now on will use the new language."),
+            &mut context,
+          );
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+        }
+      },
+      Event::Select(EntryId::ToggleUsageInsights) => {
+        context.settings.usage_insights = !context.settings.usage_insights;
+      },
+      Event::Select(EntryId::TogglePrivateSleepScreen) => {
+        context.settings.private_sleep_screen = !context.settings.private_sleep_screen;
+      },
+      Event::Select(EntryId::ToggleFeedback(kind)) => {
+        let feedback = &mut context.settings.feedback;
+        match kind {
+          FeedbackKind::PageTurn => feedback.page_turns = !feedback.page_turns,
+          FeedbackKind::KeyPress => feedback.key_presses = !feedback.key_presses,
+        }
+      },
+      Event::Select(EntryId::BrowseInsights) => {
+        view.children_mut().retain(|child| !child.is::<Menu>());
+        let records = load_insights(INSIGHTS_PATH).unwrap_or_else(|e| {
+          report_error(&tx, Severity::Warning, "Can't load the usage insights", &e);
+          Vec::new()
+        });
+        let html = insights_as_html(&records, context.settings.usage_insights);
+        let r = Reader::from_html(context.fb.rect(), &html, &tx, &mut context);
+        let mut next_view = Box::new(r) as Box<dyn View>;
+        transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
+        history.push(HistoryItem {
+          view,
+          rotation: context.display.rotation,
+          monochrome: context.fb.monochrome(),
+        });
+        view = next_view;
+      },
+      Event::Select(EntryId::PurgeInsights) => match purge_insights(INSIGHTS_PATH) {
+        Ok(()) => {
+          let notif = Notification::new(
+            ViewId::MessageNotif,
+            tr!("Usage insights purged."),
+            &mut context,
+          );
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+        },
+        Err(e) => report_error(&tx, Severity::Error, "Can't purge the usage insights", &e),
+      },
       Event::Select(EntryId::Launch(app_cmd)) => {
         view.children_mut().retain(|child| !child.is::<Menu>());
+        context.record_usage(Usage::launch(&app_cmd));
         let monochrome = context.fb.monochrome();
         let mut next_view: Box<dyn View> = match app_cmd {
-          AppCmd::Sketch => {
+          AppCmd::Sketch { file } => {
             context.fb.set_monochrome(true);
-            Box::new(Sketch::new(context.fb.rect(), &mut rq, &mut context))
+            Box::new(Sketch::new(
+              context.fb.rect(),
+              file.as_deref(),
+              &mut rq,
+              &mut context,
+            ))
           },
+          AppCmd::TapZones => Box::new(TapZonesEditor::new(
+            context.fb.rect(),
+            &mut rq,
+            &mut context,
+          )),
           AppCmd::Calculator => Box::new(Calculator::new(
             context.fb.rect(),
             &tx,
@@ -1186,6 +2032,22 @@ pub fn run() -> Result<(), Error> {
             &mut rq,
             &mut context,
           )),
+          #[cfg(feature = "feeds")]
+          AppCmd::Feeds => Box::new(Feeds::new(context.fb.rect(), &tx, &mut rq, &mut context)),
+          #[cfg(feature = "dictionary-manager")]
+          AppCmd::Dictionaries => Box::new(DictionaryManager::new(
+            context.fb.rect(),
+            &tx,
+            &mut rq,
+            &mut context,
+          )),
+          #[cfg(feature = "opds")]
+          AppCmd::Opds => Box::new(OpdsBrowser::new(
+            context.fb.rect(),
+            &tx,
+            &mut rq,
+            &mut context,
+          )),
         };
         transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
         history.push(HistoryItem {
@@ -1227,7 +2089,7 @@ pub fn run() -> Result<(), Error> {
             ViewId::PresetMenu,
             MenuKind::Contextual,
             vec![EntryKind::Command(
-              "Remove".to_string(),
+              tr!("Remove"),
               EntryId::RemovePreset(index),
             )],
             &mut context,
@@ -1242,7 +2104,7 @@ pub fn run() -> Result<(), Error> {
             .push(Box::new(preset_menu) as Box<dyn View>);
         }
       },
-      Event::Show(ViewId::Frontlight) => {
+      Event::Show(ViewId::Frontlight) if CURRENT_DEVICE.has_frontlight() => {
         if !context.settings.frontlight {
           context.set_frontlight(true);
           view.handle_event(
@@ -1270,6 +2132,43 @@ pub fn run() -> Result<(), Error> {
           rq.add(RenderData::expose(rect, UpdateMode::Gui));
         }
       },
+      Event::Expire(id) => {
+        if let Some(index) = view.children().iter().position(|c| c.id() == id) {
+          let rect = overlapping_rectangle(view.child(index));
+          rq.add(RenderData::expose(rect, UpdateMode::Gui));
+          view.children_mut().remove(index);
+        }
+      },
+      Event::NotifyProgress(id, msg, progress) => {
+        notify_progress(
+          view.children_mut(),
+          id,
+          msg,
+          progress,
+          &tx,
+          &mut rq,
+          &mut context,
+        );
+      },
+      Event::Error(report) => {
+        show_report(view.children_mut(), &report, &tx, &mut rq, &mut context);
+      },
+      Event::Progress(id, msg, progress) => {
+        show_progress(
+          view.children_mut(),
+          id,
+          msg,
+          progress,
+          &mut rq,
+          &mut context,
+        );
+      },
+      Event::Select(EntryId::RemoveSavedSearch(id, ref text)) => {
+        context.remove_saved_search(text, id);
+      },
+      Event::Select(EntryId::ClearNotifications) => {
+        context.notifications.clear();
+      },
       Event::Close(id) => {
         if let Some(index) = locate_by_id(view.as_ref(), id) {
           let rect = overlapping_rectangle(view.child(index));
@@ -1278,12 +2177,74 @@ pub fn run() -> Result<(), Error> {
         }
       },
       Event::Select(EntryId::ToggleInverted) => {
+        // The inversion is performed by the EPDC: the content of the framebuffer doesn't
+        // need to be rendered again, unless some regions are exempted from the inversion.
         context.fb.toggle_inverted();
-        rq.add(RenderData::new(
-          view.id(),
-          context.fb.rect(),
-          UpdateMode::Gui,
-        ));
+        let rect = context.fb.rect();
+        if has_inversion_exemptions(view.as_ref()) {
+          rq.add(RenderData::new(view.id(), rect, UpdateMode::Gui));
+        } else {
+          match context.fb.update(&rect, UpdateMode::Gui) {
+            Ok(tok) => {
+              updating.insert(tok, rect);
+            },
+            Err(e) => eprintln!("{}", e),
+          }
+        }
+      },
+      Event::Select(EntryId::SetPowerProfile(profile)) => {
+        context.settings.power.profile = profile;
+        context.update_power_profile();
+      },
+      Event::Select(EntryId::ToggleAutoPowerSaver) => {
+        context.settings.power.auto_saver = !context.settings.power.auto_saver;
+        context.update_power_profile();
+      },
+      Event::Select(EntryId::SetNightFilter(mode)) => {
+        context.settings.night_filter.mode = mode;
+        if update_night_filter(&mut context) {
+          rq.add(RenderData::new(
+            view.id(),
+            context.fb.rect(),
+            UpdateMode::Gui,
+          ));
+        }
+      },
+      Event::ClockTick => {
+        let period = context.power_profile.background_period();
+        if context.settings.auto_frontlight
+          && context.settings.frontlight
+          && Local::now().minute() % period == 0
+        {
+          context.guess_frontlight();
+        }
+        let wifi_timeout = context.power_profile.wifi_timeout();
+        if context.settings.wifi
+          && wifi_timeout > 0
+          && !context.network.is_busy()
+          && inactive_since.elapsed() >= Duration::from_secs(60 * wifi_timeout)
+        {
+          set_wifi(false, &mut context);
+        }
+        let idle_timeout = context.settings.power.wifi_idle_timeout;
+        if context.settings.wifi
+          && idle_timeout > 0
+          && context
+            .network
+            .should_disable(Duration::from_secs(60 * idle_timeout))
+        {
+          set_wifi(false, &mut context);
+        }
+        if context.settings.night_filter.mode == NightFilterMode::Scheduled
+          && update_night_filter(&mut context)
+        {
+          rq.add(RenderData::new(
+            view.id(),
+            context.fb.rect(),
+            UpdateMode::Full,
+          ));
+        }
+        handle_event(view.as_mut(), &evt, &tx, &mut bus, &mut rq, &mut context);
       },
       Event::Select(EntryId::ToggleMonochrome) => {
         context.fb.toggle_monochrome();
@@ -1295,15 +2256,26 @@ pub fn run() -> Result<(), Error> {
       },
       Event::Select(EntryId::ToggleIntermissionImage(ref kind, ref path)) => {
         let key = kind.key();
-        if context.settings.intermission_images.get(key) == Some(path) {
-          context.settings.intermission_images.remove(key);
+        let index = context.settings.selected_library;
+        let images = &mut context.settings.libraries[index].intermission_images;
+        if images.get(key) == Some(path) {
+          images.remove(key);
         } else {
-          context
-            .settings
-            .intermission_images
-            .insert(key.to_string(), path.clone());
+          images.insert(key.to_string(), path.clone());
         }
       },
+      Event::Select(EntryId::UseCoverAsSleepScreen(ref path)) => {
+        let index = context.settings.selected_library;
+        context.settings.libraries[index]
+          .intermission_images
+          .insert(IntermKind::Suspend.key().to_string(), path.clone());
+        let notif = Notification::new(
+          ViewId::MessageNotif,
+          tr!("The cover will be shown while sleeping."),
+          &mut context,
+        );
+        push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+      },
       Event::Select(EntryId::Rotate(n)) if n != context.display.rotation && view.might_rotate() => {
         updating.retain(|tok, _| context.fb.wait(*tok).is_err());
         if let Ok(dims) = context.fb.set_rotation(n) {
@@ -1339,19 +2311,44 @@ pub fn run() -> Result<(), Error> {
         }
       },
       Event::SetWifi(enable) => {
+        context.network.set_manual();
         set_wifi(enable, &mut context);
       },
       Event::Select(EntryId::ToggleWifi) => {
+        context.network.set_manual();
         set_wifi(!context.settings.wifi, &mut context);
       },
+      Event::AcquireNetwork(task) => {
+        if context.network.acquire(task, context.settings.wifi) {
+          set_wifi(true, &mut context);
+        }
+      },
+      Event::ReleaseNetwork(task) => {
+        context.network.release(task);
+      },
       Event::Select(EntryId::TakeScreenshot) => {
         let name = Local::now().format("screenshot-%Y%m%d_%H%M%S.png");
         let msg = match context.fb.save(&name.to_string()) {
           Err(e) => format!("{}", e),
-          Ok(_) => format!("Saved {}.", name),
+          Ok(_) => tr!("Saved {}.", name),
         };
-        let notif = Notification::new(ViewId::TakeScreenshotNotif, msg, &tx, &mut rq, &mut context);
-        view.children_mut().push(Box::new(notif) as Box<dyn View>);
+        let notif = Notification::new(ViewId::TakeScreenshotNotif, msg, &mut context);
+        push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+      },
+      Event::Select(EntryId::ToggleBenchmark) => {
+        let report = context.fb.benchmark().map(UpdateStats::report);
+        context.fb.set_benchmark(report.is_none());
+        let msg = if let Some(report) = report {
+          let name = Local::now().format("benchmark-%Y%m%d_%H%M%S.txt");
+          match fs::write(name.to_string(), report) {
+            Err(e) => tr!("Can't save the benchmark: {}.", e),
+            Ok(_) => tr!("Saved {}.", name),
+          }
+        } else {
+          tr!("Measuring the update latencies.")
+        };
+        let notif = Notification::new(ViewId::MessageNotif, msg, &mut context);
+        push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
       },
       Event::AddDocument(..) => {
         if view.is::<Home>() {
@@ -1367,9 +2364,27 @@ pub fn run() -> Result<(), Error> {
           );
         };
       },
+      Event::OpdsDownloaded(..) | Event::Converted(..) => {
+        // The catalogs browser and Home both handle the new books.
+        view.handle_event(&evt, &tx, &mut bus, &mut rq, &mut context);
+        if !view.is::<Home>() {
+          let (tx, _rx) = mpsc::channel();
+          history[0].view.handle_event(
+            &evt,
+            &tx,
+            &mut VecDeque::new(),
+            &mut RenderQueue::new(),
+            &mut context,
+          );
+        }
+      },
       Event::Notify(msg) => {
-        let notif = Notification::new(ViewId::MessageNotif, msg, &tx, &mut rq, &mut context);
-        view.children_mut().push(Box::new(notif) as Box<dyn View>);
+        let notif = Notification::new(ViewId::MessageNotif, msg, &mut context);
+        push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+      },
+      Event::Select(EntryId::Copy(text)) => {
+        context.clipboard = Some(text);
+        tx.send(Event::Notify(tr!("Copied to the clipboard."))).ok();
       },
       Event::Select(EntryId::Reboot) => {
         exit_status = ExitStatus::Reboot;
@@ -1398,7 +2413,7 @@ pub fn run() -> Result<(), Error> {
         }
         let seconds = 60 * context.settings.auto_suspend as u64;
         if inactive_since.elapsed() > Duration::from_secs(seconds) {
-          let interm = Intermission::new(context.fb.rect(), IntermKind::Suspend, &context);
+          let interm = Intermission::new(context.fb.rect(), IntermKind::Suspend, &mut context);
           rq.add(RenderData::new(
             interm.id(),
             *interm.rect(),
@@ -1419,6 +2434,16 @@ pub fn run() -> Result<(), Error> {
       },
     }
 
+    if view.id() != view_id {
+      transition = Transition::new(view.as_ref(), context.settings.transitions);
+    }
+
+    if let Some(t) = transition.as_ref() {
+      if t.play(view.as_ref(), &mut rq, &mut context, &mut updating) {
+        transition = None;
+      }
+    }
+
     process_render_queue(view.as_ref(), &mut rq, &mut context, &mut updating);
 
     while let Some(ce) = bus.pop_front() {
@@ -1434,7 +2459,17 @@ pub fn run() -> Result<(), Error> {
     context.settings.frontlight_levels = context.frontlight.levels();
   }
 
-  context.library.flush();
+  if let Err(e) = context
+    .library
+    .flush()
+    .and_then(|_| context.library.compact())
+  {
+    eprintln!("Can't save the metadata: {:#}.", e);
+  }
+
+  if let Err(e) = context.save_search_history() {
+    eprintln!("Can't save the search history: {:#}.", e);
+  }
 
   let path = Path::new(SETTINGS_PATH);
   save_toml(&context.settings, path).context("Can't save settings.")?;
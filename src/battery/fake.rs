@@ -8,10 +8,11 @@ pub struct FakeBattery {
 
 impl FakeBattery {
   pub fn new() -> FakeBattery {
-    FakeBattery {
-      capacity: 50.0,
-      status: Status::Discharging,
-    }
+    FakeBattery::with_state(50.0, Status::Discharging)
+  }
+
+  pub fn with_state(capacity: f32, status: Status) -> FakeBattery {
+    FakeBattery { capacity, status }
   }
 }
 
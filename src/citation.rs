@@ -0,0 +1,214 @@
+//! Formats citations of the documents and of their annotations.
+
+use crate::{metadata::Info, settings::CitationStyle};
+
+// The last and first names of an author written *First Last*.
+fn split_name(name: &str) -> (&str, &str) {
+  if let Some(index) = name.rfind(' ') {
+    (name[index + 1..].trim(), name[..index].trim())
+  } else {
+    (name.trim(), "")
+  }
+}
+
+fn authors(info: &Info) -> Vec<&str> {
+  info
+    .author
+    .split(&[',', '&'][..])
+    .map(str::trim)
+    .filter(|a| !a.is_empty())
+    .collect()
+}
+
+// The author as *Last, First*.
+fn inverted_author(author: &str) -> String {
+  match split_name(author) {
+    (last, "") => last.to_string(),
+    (last, first) => format!("{}, {}", last, first),
+  }
+}
+
+fn full_title(info: &Info) -> String {
+  if info.subtitle.is_empty() {
+    info.title.clone()
+  } else {
+    format!("{}: {}", info.title, info.subtitle)
+  }
+}
+
+fn escape_bibtex(text: &str) -> String {
+  text
+    .replace('\\', "\\textbackslash{}")
+    .replace('{', "\\{")
+    .replace('}', "\\}")
+    .replace('%', "\\%")
+    .replace('&', "\\&")
+}
+
+// A key made of the last name of the first author, the year and the first word of the title.
+pub fn bibtex_key(info: &Info) -> String {
+  let author = authors(info)
+    .first()
+    .map(|a| split_name(a).0.to_string())
+    .unwrap_or_default();
+  let word = info
+    .alphabetic_title()
+    .split_whitespace()
+    .next()
+    .unwrap_or("")
+    .to_string();
+  let key = format!("{}{}{}", author, info.year, word)
+    .chars()
+    .filter(char::is_ascii_alphanumeric)
+    .collect::<String>()
+    .to_lowercase();
+  if key.is_empty() {
+    "untitled".to_string()
+  } else {
+    key
+  }
+}
+
+fn bibtex_fields(info: &Info) -> Vec<(&'static str, String)> {
+  let mut fields = Vec::new();
+  if !info.author.is_empty() {
+    fields.push(("author", authors(info).join(" and ")));
+  }
+  fields.push(("title", full_title(info)));
+  for (name, value) in &[
+    ("publisher", &info.publisher),
+    ("year", &info.year),
+    ("edition", &info.edition),
+    ("series", &info.series),
+    ("volume", &info.volume),
+    ("isbn", &info.identifier),
+  ] {
+    if !value.is_empty() {
+      fields.push((*name, value.to_string()));
+    }
+  }
+  fields
+}
+
+fn bibtex_entry(kind: &str, key: &str, fields: &[(&str, String)]) -> String {
+  let body = fields
+    .iter()
+    .map(|(name, value)| format!("  {} = {{{}}}", name, escape_bibtex(value)))
+    .collect::<Vec<String>>()
+    .join(",\n");
+  format!("@{}{{{},\n{}\n}}\n", kind, key, body)
+}
+
+// Cites the page labeled *page*, and the quoted text, if any.
+pub fn cite(info: &Info, page: &str, quote: &str, style: CitationStyle) -> String {
+  let title = full_title(info);
+  let authors = authors(info);
+  match style {
+    CitationStyle::Apa => {
+      let mut text = String::new();
+      if !quote.is_empty() {
+        text += &format!("“{}” ", quote);
+      }
+      if let Some(first) = authors.first() {
+        text += &inverted_author(first);
+        if authors.len() > 1 {
+          text += " et al.";
+        }
+        text.push(' ');
+      }
+      if !info.year.is_empty() {
+        text += &format!("({}). ", info.year);
+      }
+      text += &format!("{}.", title);
+      if !info.publisher.is_empty() {
+        text += &format!(" {}.", info.publisher);
+      }
+      text += &format!(" p. {}.", page);
+      text
+    },
+    CitationStyle::Mla => {
+      let mut text = String::new();
+      if !quote.is_empty() {
+        text += &format!("“{}” ", quote);
+      }
+      if let Some(first) = authors.first() {
+        text += &inverted_author(first);
+        if authors.len() > 1 {
+          text += ", et al";
+        }
+        text += ". ";
+      }
+      text += &format!("{}.", title);
+      let details = [&info.publisher, &info.year]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.as_str())
+        .collect::<Vec<&str>>();
+      if !details.is_empty() {
+        text += &format!(" {},", details.join(", "));
+      }
+      text += &format!(" p. {}.", page);
+      text
+    },
+    CitationStyle::Bibtex => {
+      let mut fields = bibtex_fields(info);
+      fields.push(("pages", page.to_string()));
+      if !quote.is_empty() {
+        fields.push(("note", quote.to_string()));
+      }
+      bibtex_entry("book", &bibtex_key(info), &fields)
+    },
+  }
+}
+
+// The document as a `@book` entry, followed by one `@note` entry per annotation.
+// *notes* holds the page label, the highlighted text and the note of each annotation.
+pub fn annotations_as_bibtex(info: &Info, notes: &[(String, String, String)]) -> String {
+  let key = bibtex_key(info);
+  let mut text = bibtex_entry("book", &key, &bibtex_fields(info));
+
+  for (index, (page, quote, note)) in notes.iter().enumerate() {
+    let mut fields = vec![("crossref", key.clone()), ("pages", page.clone())];
+    if !quote.is_empty() {
+      fields.push(("quote", quote.clone()));
+    }
+    if !note.is_empty() {
+      fields.push(("note", note.clone()));
+    }
+    text.push('\n');
+    text += &bibtex_entry("note", &format!("{}-{}", key, index + 1), &fields);
+  }
+
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{bibtex_key, cite};
+  use crate::{metadata::Info, settings::CitationStyle};
+
+  fn info() -> Info {
+    Info {
+      title: "The Name of the Rose".to_string(),
+      author: "Umberto Eco".to_string(),
+      year: "1980".to_string(),
+      publisher: "Bompiani".to_string(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_styles() {
+    let info = info();
+    assert_eq!(bibtex_key(&info), "eco1980name");
+    assert_eq!(
+      cite(&info, "12", "", CitationStyle::Apa),
+      "Eco, Umberto (1980). The Name of the Rose. Bompiani. p. 12."
+    );
+    assert_eq!(
+      cite(&info, "12", "Stat rosa", CitationStyle::Mla),
+      "“Stat rosa” Eco, Umberto. The Name of the Rose. Bompiani, 1980, p. 12."
+    );
+    assert!(cite(&info, "12", "", CitationStyle::Bibtex).starts_with("@book{eco1980name,\n"));
+  }
+}
@@ -0,0 +1,359 @@
+//! Uploads the files created on the device (sketches, exported annotations and the
+//! phrasebook) to a WebDAV folder, so that they don't stay trapped on the device.
+//!
+//! The files that were already uploaded, and the remote names they were given, are recorded
+//! in a state file: only the new and the modified files are sent. A new file never replaces a
+//! remote file that it didn't upload: a free name is chosen instead.
+
+use crate::{
+  app::Context,
+  helpers::{load_json, save_json_atomically},
+  network::NetworkTask,
+  phrasebook::{PHRASEBOOK_EXPORT_NAME, PHRASEBOOK_PATH},
+  settings::CloudSyncSettings,
+  view::{
+    report::{report_error, Severity},
+    Event,
+    Hub,
+  },
+};
+use anyhow::{format_err, Error};
+use chrono::Local;
+use fxhash::FxHashSet;
+use reqwest::{
+  blocking::{Client, RequestBuilder},
+  Method,
+  StatusCode,
+  Url,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::BTreeMap,
+  fs::{self, File, OpenOptions},
+  io::Write,
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicBool, Ordering},
+  thread,
+  time::{Duration, UNIX_EPOCH},
+};
+use walkdir::WalkDir;
+
+const SYNC_STATE_PATH: &str = ".cloud-sync.json";
+const SYNC_LOG_PATH: &str = "CloudSync.log";
+// The log is started anew when it grows beyond this size, in bytes.
+const MAX_LOG_SIZE: u64 = 256 * 1024;
+const SYNC_TIMEOUT: Duration = Duration::from_secs(60);
+// The name suffixes of the exported annotations.
+const ANNOTATIONS_SUFFIXES: [&str; 3] = [".bib", ".annotations.md", ".annotations.json"];
+// The number of alternative names tried when a remote name is taken.
+const MAX_ALTERNATIVES: usize = 99;
+
+static SYNCING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SyncedFile {
+  // The path of the file in the remote folder.
+  remote: String,
+  modified: u64,
+  size: u64,
+}
+
+// Maps the paths of the local files to their last upload.
+type SyncState = BTreeMap<String, SyncedFile>;
+
+// Returns the local files to upload, along with their paths in the remote folder.
+fn local_files(
+  settings: &CloudSyncSettings,
+  library: &Path,
+  sketches: &Path,
+) -> Vec<(PathBuf, String)> {
+  let mut files = Vec::new();
+  let mut walk = |dir: &Path, keep: &dyn Fn(&Path) -> bool| {
+    for entry in WalkDir::new(dir)
+      .into_iter()
+      .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+      .filter_map(|e| e.ok())
+      .filter(|e| e.file_type().is_file() && keep(e.path()))
+    {
+      if let Ok(relat) = entry.path().strip_prefix(library) {
+        let name = relat
+          .components()
+          .map(|c| c.as_os_str().to_string_lossy())
+          .collect::<Vec<_>>()
+          .join("/");
+        files.push((entry.path().to_path_buf(), name));
+      }
+    }
+  };
+
+  if settings.sketches && sketches.exists() {
+    walk(sketches, &|_| true);
+  }
+  if settings.annotations {
+    walk(library, &|path| {
+      !path.starts_with(sketches)
+        && path
+          .file_name()
+          .and_then(|n| n.to_str())
+          .map_or(false, |n| {
+            ANNOTATIONS_SUFFIXES.iter().any(|s| n.ends_with(s))
+          })
+    });
+  }
+  if settings.phrasebook {
+    for path in &[
+      PathBuf::from(PHRASEBOOK_PATH),
+      library.join(PHRASEBOOK_EXPORT_NAME),
+    ] {
+      if path.exists() {
+        let name = path
+          .file_name()
+          .unwrap_or_default()
+          .to_string_lossy()
+          .into_owned();
+        files.push((path.clone(), name));
+      }
+    }
+  }
+
+  files
+}
+
+// Inserts a counter before the extension of a remote name: `a/b.png` becomes `a/b (2).png`.
+fn alternative_name(name: &str, index: usize) -> String {
+  let (dir, file) = match name.rfind('/') {
+    Some(i) => name.split_at(i + 1),
+    None => ("", name),
+  };
+  match file.rfind('.').filter(|&i| i > 0) {
+    Some(i) => format!("{}{} ({}){}", dir, &file[..i], index, &file[i..]),
+    None => format!("{}{} ({})", dir, file, index),
+  }
+}
+
+struct Remote {
+  client: Client,
+  base: Url,
+  username: String,
+  password: String,
+  // The remote directories known to exist.
+  dirs: FxHashSet<String>,
+}
+
+impl Remote {
+  fn new(settings: &CloudSyncSettings) -> Result<Remote, Error> {
+    let mut base = Url::parse(&settings.url)?;
+    if base.cannot_be_a_base() {
+      return Err(format_err!("invalid folder URL: {}", settings.url));
+    }
+    // The last segment is a directory.
+    if let Ok(mut segments) = base.path_segments_mut() {
+      segments.pop_if_empty();
+    }
+    Ok(Remote {
+      client: Client::builder().timeout(SYNC_TIMEOUT).build()?,
+      base,
+      username: settings.username.clone(),
+      password: settings.password.clone(),
+      dirs: FxHashSet::default(),
+    })
+  }
+
+  fn url(&self, name: &str) -> Url {
+    let mut url = self.base.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+      segments.extend(name.split('/'));
+    }
+    url
+  }
+
+  fn request(&self, method: Method, name: &str) -> RequestBuilder {
+    let builder = self.client.request(method, self.url(name));
+    if self.username.is_empty() {
+      builder
+    } else {
+      builder.basic_auth(&self.username, Some(&self.password))
+    }
+  }
+
+  fn exists(&self, name: &str) -> Result<bool, Error> {
+    let status = self.request(Method::HEAD, name).send()?.status();
+    if status == StatusCode::NOT_FOUND {
+      Ok(false)
+    } else if status.is_success() {
+      Ok(true)
+    } else {
+      Err(format_err!("unexpected status {} for {}", status, name))
+    }
+  }
+
+  // Creates the parent directories of the given remote name.
+  fn create_dirs(&mut self, name: &str) -> Result<(), Error> {
+    let mut dir = String::new();
+    for segment in name
+      .split('/')
+      .rev()
+      .skip(1)
+      .collect::<Vec<_>>()
+      .into_iter()
+      .rev()
+    {
+      if !dir.is_empty() {
+        dir.push('/');
+      }
+      dir.push_str(segment);
+      if self.dirs.contains(&dir) {
+        continue;
+      }
+      let method = Method::from_bytes(b"MKCOL")?;
+      let status = self.request(method, &dir).send()?.status();
+      // An existing directory is reported as not allowed.
+      if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED {
+        return Err(format_err!(
+          "can't create the directory {}: {}",
+          dir,
+          status
+        ));
+      }
+      self.dirs.insert(dir.clone());
+    }
+    Ok(())
+  }
+
+  fn upload(&mut self, path: &Path, name: &str) -> Result<(), Error> {
+    self.create_dirs(name)?;
+    let file = File::open(path)?;
+    self
+      .request(Method::PUT, name)
+      .body(file)
+      .send()?
+      .error_for_status()?;
+    Ok(())
+  }
+}
+
+fn log(message: &str) {
+  if fs::metadata(SYNC_LOG_PATH).map_or(false, |m| m.len() > MAX_LOG_SIZE) {
+    fs::remove_file(SYNC_LOG_PATH).ok();
+  }
+  let line = format!("{} {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+  if let Ok(mut file) = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(SYNC_LOG_PATH)
+  {
+    file.write_all(line.as_bytes()).ok();
+  }
+}
+
+// Uploads the new and the modified files in a separate thread, unless a sync is already
+// running.
+pub fn spawn_sync(hub: &Hub, context: &Context) {
+  let settings = context.settings.cloud_sync.clone();
+  if settings.url.is_empty() || SYNCING.swap(true, Ordering::SeqCst) {
+    return;
+  }
+  let library = context.library.home.clone();
+  let sketches = library.join(&context.settings.sketch.save_path);
+  let hub2 = hub.clone();
+  hub.send(Event::AcquireNetwork(NetworkTask::Sync)).ok();
+  thread::spawn(move || {
+    match sync_files(&settings, &library, &sketches) {
+      Ok(0) => (),
+      Ok(count) => {
+        hub2
+          .send(Event::Notify(tr!(
+            "Uploaded {} files to the cloud folder.",
+            count
+          )))
+          .ok();
+      },
+      Err(e) => {
+        log(&format!("Sync failed: {:#}.", e));
+        report_error(
+          &hub2,
+          Severity::Warning,
+          "Can't sync with the cloud folder",
+          &e,
+        );
+      },
+    }
+    SYNCING.store(false, Ordering::SeqCst);
+    hub2.send(Event::ReleaseNetwork(NetworkTask::Sync)).ok();
+  });
+}
+
+// Returns the number of uploaded files.
+fn sync_files(
+  settings: &CloudSyncSettings,
+  library: &Path,
+  sketches: &Path,
+) -> Result<usize, Error> {
+  let mut remote = Remote::new(settings)?;
+  let mut state: SyncState = load_json(SYNC_STATE_PATH).unwrap_or_default();
+  let mut count = 0;
+
+  for (path, name) in local_files(settings, library, sketches) {
+    let metadata = match fs::metadata(&path) {
+      Ok(metadata) => metadata,
+      Err(_) => continue,
+    };
+    let modified = metadata
+      .modified()
+      .ok()
+      .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+      .map_or(0, |d| d.as_secs());
+    let size = metadata.len();
+    let key = path.to_string_lossy().into_owned();
+
+    let remote_name = match state.get(&key) {
+      Some(synced) if synced.modified == modified && synced.size == size => continue,
+      Some(synced) => synced.remote.clone(),
+      None => {
+        let taken: FxHashSet<&str> = state.values().map(|s| s.remote.as_str()).collect();
+        let mut candidate = name.clone();
+        let mut index = 1;
+        while taken.contains(candidate.as_str()) || remote.exists(&candidate)? {
+          index += 1;
+          if index > MAX_ALTERNATIVES {
+            return Err(format_err!("no free remote name for {}", name));
+          }
+          candidate = alternative_name(&name, index);
+        }
+        candidate
+      },
+    };
+
+    if let Err(e) = remote.upload(&path, &remote_name) {
+      log(&format!("Can't upload {}: {:#}.", path.display(), e));
+      continue;
+    }
+
+    log(&format!("Uploaded {} to {}.", path.display(), remote_name));
+    state.insert(
+      key,
+      SyncedFile {
+        remote: remote_name,
+        modified,
+        size,
+      },
+    );
+    count += 1;
+  }
+
+  save_json_atomically(&state, SYNC_STATE_PATH)?;
+  Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::alternative_name;
+
+  #[test]
+  fn test_alternative_name() {
+    assert_eq!(alternative_name("Sketches/a.png", 2), "Sketches/a (2).png");
+    assert_eq!(alternative_name("Phrasebook", 3), "Phrasebook (3)");
+    assert_eq!(alternative_name("a.b/.hidden", 2), "a.b/.hidden (2)");
+  }
+}
@@ -0,0 +1,253 @@
+//! Converts the documents of the library that can't be opened into EPUB with an external
+//! program, such as Calibre's *ebook-convert*, and imports the results.
+//!
+//! The library is walked and the conversions are run one at a time in a separate thread. The
+//! documents whose conversion failed are skipped until they change. The converter is
+//! called with the paths of the document and of the EPUB to create, followed by the
+//! configured arguments: a script can forward the document to a converter on another host.
+
+use crate::{
+  app::Context,
+  document::registry::opener_for,
+  helpers::IsHidden,
+  settings::{ConversionSettings, ImportSettings},
+  view::{
+    report::{report_error, Severity},
+    Event,
+    Hub,
+    ViewId,
+  },
+};
+use anyhow::{format_err, Error};
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use std::{
+  collections::VecDeque,
+  fs,
+  path::{Path, PathBuf},
+  process::{Command, Stdio},
+  sync::Mutex,
+  thread,
+  time::{Duration, Instant, SystemTime},
+};
+use walkdir::WalkDir;
+
+const CONVERTED_EXTENSION: &str = "epub";
+// How often a running converter is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Default)]
+struct ConversionQueue {
+  jobs: VecDeque<PathBuf>,
+  // The document being converted.
+  current: Option<PathBuf>,
+  // The number of jobs of the current batch, finished or not.
+  total: usize,
+  converted: usize,
+  running: bool,
+  // The size and modification time of the documents whose conversion failed.
+  failed: FxHashMap<PathBuf, (u64, SystemTime)>,
+}
+
+lazy_static! {
+  static ref QUEUE: Mutex<ConversionQueue> = Mutex::new(ConversionQueue::default());
+}
+
+// The size and modification time of *path*.
+fn fingerprint(path: &Path) -> Option<(u64, SystemTime)> {
+  let metadata = fs::metadata(path).ok()?;
+  Some((metadata.len(), metadata.modified().ok()?))
+}
+
+// The path of the EPUB created from *path*.
+fn converted_path(path: &Path) -> PathBuf {
+  path.with_extension(CONVERTED_EXTENSION)
+}
+
+// The documents of *home* that can't be opened, weren't converted yet and have one of the
+// extensions handled by the converter.
+pub fn pending_conversions(home: &Path, settings: &ImportSettings) -> Vec<PathBuf> {
+  let conversion = &settings.conversion;
+  if conversion.program.is_none() || conversion.extensions.is_empty() {
+    return Vec::new();
+  }
+  WalkDir::new(home)
+    .min_depth(1)
+    .into_iter()
+    .filter_entry(|e| settings.traverse_hidden || !e.is_hidden())
+    .filter_map(|e| e.ok())
+    .filter(|e| e.file_type().is_file())
+    .map(|e| e.into_path())
+    .filter(|path| {
+      path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| {
+          conversion
+            .extensions
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(e))
+        })
+        && opener_for(path).is_none()
+        && !converted_path(path).exists()
+    })
+    .collect()
+}
+
+// Adds, from a separate thread, the pending conversions of the current library to the queue,
+// and starts converting unless a conversion is already running.
+pub fn queue_conversions(hub: &Hub, context: &Context) {
+  let settings = &context.settings.import;
+  if settings.conversion.program.is_none() || settings.conversion.extensions.is_empty() {
+    return;
+  }
+  let home = context.library.home.clone();
+  let settings = settings.clone();
+  let hub = hub.clone();
+  thread::spawn(move || {
+    let paths = pending_conversions(&home, &settings);
+    {
+      let mut queue = QUEUE.lock().unwrap();
+      for path in paths {
+        if queue.current.as_ref() == Some(&path) || queue.jobs.contains(&path) {
+          continue;
+        }
+        if let Some(failure) = queue.failed.get(&path) {
+          if fingerprint(&path).as_ref() == Some(failure) {
+            continue;
+          }
+          queue.failed.remove(&path);
+        }
+        queue.jobs.push_back(path);
+        queue.total += 1;
+      }
+      if queue.running || queue.jobs.is_empty() {
+        return;
+      }
+      queue.running = true;
+    }
+    run_queue(&settings.conversion, &hub);
+  });
+}
+
+fn run_queue(settings: &ConversionSettings, hub: &Hub) {
+  loop {
+    let (path, index, total) = {
+      let mut queue = QUEUE.lock().unwrap();
+      match queue.jobs.pop_front() {
+        Some(path) => {
+          let index = queue.total - queue.jobs.len();
+          queue.current = Some(path.clone());
+          (path, index, queue.total)
+        },
+        None => {
+          hub
+            .send(Event::Progress(
+              ViewId::ConversionProgress,
+              String::new(),
+              Some(1.0),
+            ))
+            .ok();
+          if queue.converted > 0 {
+            hub
+              .send(Event::Notify(tr!(
+                "Converted {} documents.",
+                queue.converted
+              )))
+              .ok();
+          }
+          let failed = std::mem::take(&mut queue.failed);
+          *queue = ConversionQueue {
+            failed,
+            ..Default::default()
+          };
+          return;
+        },
+      }
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    hub
+      .send(Event::Progress(
+        ViewId::ConversionProgress,
+        tr!("Converting {} ({}/{}).", name, index, total),
+        Some((index - 1) as f32 / total as f32),
+      ))
+      .ok();
+    match convert(settings, &path) {
+      Ok(target) => {
+        QUEUE.lock().unwrap().converted += 1;
+        hub.send(Event::Converted(target)).ok();
+      },
+      Err(e) => {
+        if let Some(failure) = fingerprint(&path) {
+          QUEUE.lock().unwrap().failed.insert(path.clone(), failure);
+        }
+        let e = e.context(name.into_owned());
+        report_error(hub, Severity::Warning, "Can't convert a document", &e);
+      },
+    }
+  }
+}
+
+// Runs the converter on *path* and returns the path of the resulting EPUB.
+fn convert(settings: &ConversionSettings, path: &Path) -> Result<PathBuf, Error> {
+  let program = settings
+    .program
+    .as_ref()
+    .ok_or_else(|| format_err!("no converter"))?;
+  let target = converted_path(path);
+  // The EPUB is hidden from the imports until it's complete.
+  let partial = path.with_file_name(format!(
+    ".{}",
+    target.file_name().unwrap_or_default().to_string_lossy()
+  ));
+  let mut child = Command::new(program)
+    .arg(path)
+    .arg(&partial)
+    .args(&settings.arguments)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()?;
+  let deadline = Instant::now() + Duration::from_secs(settings.timeout);
+  let status = loop {
+    if let Some(status) = child.try_wait()? {
+      break status;
+    }
+    if Instant::now() >= deadline {
+      child.kill().ok();
+      child.wait().ok();
+      fs::remove_file(&partial).ok();
+      return Err(format_err!("the converter timed out"));
+    }
+    thread::sleep(POLL_INTERVAL);
+  };
+  if !status.success() || !partial.exists() {
+    fs::remove_file(&partial).ok();
+    return Err(format_err!("the converter failed ({})", status));
+  }
+  fs::rename(&partial, &target)?;
+  Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::pending_conversions;
+  use crate::settings::ImportSettings;
+  use std::{env, fs, path::PathBuf};
+
+  #[test]
+  fn test_pending_conversions() {
+    let home = env::temp_dir().join("plato-test-conversions");
+    fs::create_dir_all(&home).unwrap();
+    for name in &["a.mobi", "b.azw3", "c.mobi", "c.epub", "d.txt"] {
+      fs::write(home.join(name), b"Call me Ishmael.").unwrap();
+    }
+    let mut settings = ImportSettings::default();
+    settings.conversion.program = Some(PathBuf::from("ebook-convert"));
+    let mut paths = pending_conversions(&home, &settings);
+    paths.sort();
+    fs::remove_dir_all(&home).ok();
+    assert_eq!(paths, vec![home.join("a.mobi"), home.join("b.azw3")]);
+  }
+}
@@ -25,6 +25,88 @@ pub enum Model {
   TouchAB,
 }
 
+// The hardware features of a model: the views check these instead of the model.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Capabilities {
+  pub frontlight: bool,
+  // The warmth of the light can be adjusted.
+  pub natural_light: bool,
+  pub light_sensor: bool,
+  pub gyroscope: bool,
+  pub page_turn_buttons: bool,
+  pub removable_storage: bool,
+  pub stylus: bool,
+  pub color: bool,
+  pub beeper: bool,
+  pub vibrator: bool,
+}
+
+impl Capabilities {
+  pub fn labels(&self) -> Vec<&'static str> {
+    [
+      (self.frontlight, "Frontlight"),
+      (self.natural_light, "Natural light"),
+      (self.light_sensor, "Light sensor"),
+      (self.gyroscope, "Gyroscope"),
+      (self.page_turn_buttons, "Page turn buttons"),
+      (self.removable_storage, "Removable storage"),
+      (self.stylus, "Stylus"),
+      (self.color, "Color"),
+      (self.beeper, "Beeper"),
+      (self.vibrator, "Vibration motor"),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, label)| *label)
+    .collect()
+  }
+}
+
+impl Model {
+  // The registry of the capabilities of each model.
+  pub fn capabilities(self) -> Capabilities {
+    let lit = Capabilities {
+      frontlight: true,
+      natural_light: self.frontlight_kind() != FrontlightKind::Standard,
+      ..Default::default()
+    };
+    match self {
+      Model::LibraH2O | Model::Forma32GB | Model::Forma => Capabilities {
+        gyroscope: true,
+        page_turn_buttons: true,
+        ..lit
+      },
+      Model::ClaraHD | Model::AuraH2OEd2V2 | Model::AuraH2OEd2V1 => lit,
+      Model::AuraONELimEd | Model::AuraONE => Capabilities {
+        light_sensor: true,
+        ..lit
+      },
+      Model::AuraEd2V2 | Model::AuraEd2V1 | Model::GloHD => lit,
+      Model::AuraH2O | Model::Aura | Model::AuraHD | Model::Glo => Capabilities {
+        removable_storage: true,
+        ..lit
+      },
+      Model::Touch2 => Capabilities::default(),
+      Model::Mini | Model::TouchC | Model::TouchAB => Capabilities {
+        removable_storage: true,
+        ..Default::default()
+      },
+    }
+  }
+
+  pub fn frontlight_kind(self) -> FrontlightKind {
+    match self {
+      Model::AuraONE | Model::AuraONELimEd | Model::AuraH2OEd2V1 | Model::AuraH2OEd2V2 => {
+        FrontlightKind::Natural
+      },
+      Model::ClaraHD | Model::Forma | Model::Forma32GB | Model::LibraH2O => {
+        FrontlightKind::Premixed
+      },
+      _ => FrontlightKind::Standard,
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Orientation {
   Portrait,
@@ -65,6 +147,45 @@ pub struct Device {
   pub dpi: u16,
 }
 
+// The default reading typography of a screen size.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TypographyProfile {
+  // Font size in points.
+  pub font_size: f32,
+  // Margin width in millimeters.
+  pub margin_width: i32,
+  // Line height in ems.
+  pub line_height: f32,
+}
+
+// The profiles, by increasing maximum screen diagonal, in inches.
+const TYPOGRAPHY_PROFILES: [(f32, TypographyProfile); 3] = [
+  (
+    6.5,
+    TypographyProfile {
+      font_size: 11.0,
+      margin_width: 8,
+      line_height: 1.2,
+    },
+  ),
+  (
+    7.5,
+    TypographyProfile {
+      font_size: 11.5,
+      margin_width: 9,
+      line_height: 1.2,
+    },
+  ),
+  (
+    f32::INFINITY,
+    TypographyProfile {
+      font_size: 12.0,
+      margin_width: 10,
+      line_height: 1.25,
+    },
+  ),
+];
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FrontlightKind {
   Standard,
@@ -182,57 +303,64 @@ impl Device {
     }
   }
 
+  // The diagonal of the screen, in inches.
+  pub fn diagonal(&self) -> f32 {
+    let (width, height) = self.dims;
+    ((width * width + height * height) as f32).sqrt() / self.dpi as f32
+  }
+
+  pub fn typography_profile(&self) -> TypographyProfile {
+    let diagonal = self.diagonal();
+    TYPOGRAPHY_PROFILES
+      .iter()
+      .find(|(max_diagonal, _)| diagonal < *max_diagonal)
+      .map(|(_, profile)| *profile)
+      .unwrap_or(TYPOGRAPHY_PROFILES[0].1)
+  }
+
+  // The font size step of the reader, in points: about one pixel of the em, rounded to 0.05.
+  pub fn font_size_step(&self) -> f32 {
+    (1440.0 / self.dpi as f32).round() / 20.0
+  }
+
   pub fn frontlight_kind(&self) -> FrontlightKind {
-    match self.model {
-      Model::AuraONE | Model::AuraONELimEd | Model::AuraH2OEd2V1 | Model::AuraH2OEd2V2 => {
-        FrontlightKind::Natural
-      },
-      Model::ClaraHD | Model::Forma | Model::Forma32GB | Model::LibraH2O => {
-        FrontlightKind::Premixed
-      },
-      _ => FrontlightKind::Standard,
-    }
+    self.model.frontlight_kind()
+  }
+
+  pub fn capabilities(&self) -> Capabilities {
+    self.model.capabilities()
+  }
+
+  pub fn has_frontlight(&self) -> bool {
+    self.capabilities().frontlight
   }
 
   pub fn has_natural_light(&self) -> bool {
-    match self.frontlight_kind() {
-      FrontlightKind::Standard => false,
-      _ => true,
-    }
+    self.capabilities().natural_light
   }
 
   pub fn has_lightsensor(&self) -> bool {
-    match self.model {
-      Model::AuraONE | Model::AuraONELimEd => true,
-      _ => false,
-    }
+    self.capabilities().light_sensor
   }
 
   pub fn has_gyroscope(&self) -> bool {
-    match self.model {
-      Model::Forma | Model::Forma32GB | Model::LibraH2O => true,
-      _ => false,
-    }
+    self.capabilities().gyroscope
   }
 
   pub fn has_page_turn_buttons(&self) -> bool {
-    match self.model {
-      Model::Forma | Model::Forma32GB | Model::LibraH2O => true,
-      _ => false,
-    }
+    self.capabilities().page_turn_buttons
+  }
+
+  pub fn has_beeper(&self) -> bool {
+    self.capabilities().beeper
+  }
+
+  pub fn has_vibrator(&self) -> bool {
+    self.capabilities().vibrator
   }
 
   pub fn has_removable_storage(&self) -> bool {
-    match self.model {
-      Model::AuraH2O
-      | Model::Aura
-      | Model::AuraHD
-      | Model::Mini
-      | Model::Glo
-      | Model::TouchAB
-      | Model::TouchC => true,
-      _ => false,
-    }
+    self.capabilities().removable_storage
   }
 
   pub fn should_invert_buttons(&self, rotation: i8) -> bool {
@@ -345,6 +473,29 @@ impl Device {
   }
 }
 
+// The product and model number of every supported model.
+pub const PRODUCTS: [(&str, &str); 19] = [
+  ("trilogy", ""),
+  ("trilogy", "320"),
+  ("kraken", ""),
+  ("pixie", ""),
+  ("dragon", ""),
+  ("phoenix", ""),
+  ("dahlia", ""),
+  ("alyssum", ""),
+  ("pika", ""),
+  ("daylight", ""),
+  ("daylight", "381"),
+  ("star", ""),
+  ("star", "379"),
+  ("snow", ""),
+  ("snow", "378"),
+  ("nova", ""),
+  ("frost", ""),
+  ("frost", "380"),
+  ("storm", ""),
+];
+
 lazy_static! {
   pub static ref CURRENT_DEVICE: Device = {
     let product = env::var("PRODUCT").unwrap_or_default();
@@ -367,6 +518,20 @@ mod tests {
     assert_eq!(CURRENT_DEVICE.model, Model::Forma32GB);
   }
 
+  #[test]
+  fn test_device_typography_profile() {
+    let clara = Device::new("nova", "");
+    let forma = Device::new("frost", "");
+    assert_eq!(clara.typography_profile().font_size, 11.0);
+    assert_eq!(
+      Device::new("storm", "").typography_profile().font_size,
+      11.5
+    );
+    assert_eq!(forma.typography_profile().font_size, 12.0);
+    assert_eq!(clara.font_size_step(), 0.25);
+    assert_eq!(Device::new("pika", "").font_size_step(), 0.45);
+  }
+
   #[test]
   fn test_device_frontlight_kind() {
     let device = Device::new("frost", "380");
@@ -394,6 +559,16 @@ mod tests {
     assert_eq!(device.has_lightsensor(), false);
   }
 
+  #[test]
+  fn test_device_has_frontlight() {
+    let device = Device::new("nova", "");
+    assert_eq!(device.has_frontlight(), true);
+
+    let device = Device::new("pika", "378");
+    assert_eq!(device.has_frontlight(), false);
+    assert!(device.capabilities().labels().is_empty());
+  }
+
   #[test]
   fn test_device_has_gyroscope() {
     let device = Device::new("frost", "380");
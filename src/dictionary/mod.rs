@@ -6,6 +6,10 @@
 mod dictreader;
 mod errors;
 mod indexing;
+#[cfg(feature = "zim")]
+mod zim;
+#[cfg(feature = "zim")]
+mod zim_sys;
 
 use std::path::Path;
 
@@ -112,6 +116,16 @@ pub fn load_dictionary_from_file<P: AsRef<Path>>(
   Ok(load_dictionary(content, index))
 }
 
+/// Load a dictionary from a ZIM archive.
+///
+/// The articles are looked up by title, and the fuzzy look up lists the titles that start with the
+/// query.
+#[cfg(feature = "zim")]
+pub fn load_zim_dictionary<P: AsRef<Path>>(path: P) -> Result<Dictionary, errors::DictError> {
+  let (content, index) = zim::load_zim(path)?;
+  Ok(load_dictionary(Box::new(content), Box::new(index)))
+}
+
 /// Load dictionary from given `DictReader` and `Index`.
 ///
 /// A dictionary is made of an index and a dictionary (data). Both are required for look up. This
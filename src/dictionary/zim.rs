@@ -0,0 +1,713 @@
+//! Read the articles of `*.zim` files, the offline copies of Wikipedia, Wiktionary, etc.
+//!
+//! A ZIM file is made of a header, a list of directory entries (one per article, image,
+//! redirection or metadata), sorted by URL, a list of indices of these entries sorted by title,
+//! and clusters of blobs that hold the actual content. The clusters are usually compressed with
+//! *zstd* or *xz*. The [format](https://wiki.openzim.org/wiki/ZIM_file_format) is read through the
+//! index and dictionary interfaces: an entry's offset is the index of its directory entry.
+
+use std::{
+  cell::RefCell,
+  cmp::Ordering,
+  fs::File,
+  io::{Read, Seek, SeekFrom},
+  path::Path,
+  ptr,
+  rc::Rc,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use flate2::read::ZlibDecoder;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use super::{
+  dictreader::DictReader,
+  errors::DictError,
+  indexing::{Entry, IndexReader},
+  zim_sys::*,
+  Metadata,
+};
+
+const ZIM_MAGIC: u32 = 72_173_914;
+const HEADER_SIZE: usize = 80;
+const MIME_REDIRECT: u16 = 0xffff;
+const MAX_REDIRECTS: usize = 8;
+// The maximum number of titles returned by a fuzzy search.
+const MAX_SEARCH_RESULTS: usize = 24;
+// The entries returned for the metadata headwords, and for the titles of a fuzzy search.
+const METADATA_FLAG: u64 = 1 << 63;
+const LINK_FLAG: u64 = 1 << 62;
+// The size of the largest decompressed cluster.
+const MAX_CLUSTER_SIZE: usize = 1 << 30;
+const TITLE_METADATA: u64 = METADATA_FLAG | 1;
+
+lazy_static! {
+  static ref IGNORED_ELEMENTS: Regex =
+    Regex::new(r"(?is)<script\b.*?</script>|<style\b.*?</style>|<img\b[^>]*>|<link\b[^>]*>")
+      .unwrap();
+  static ref HREF: Regex = Regex::new(r#"href="([^"]*)""#).unwrap();
+}
+
+struct Header {
+  minor_version: u16,
+  entry_count: u32,
+  cluster_count: u32,
+  url_ptr_pos: u64,
+  title_ptr_pos: u64,
+  cluster_ptr_pos: u64,
+  mime_list_pos: u64,
+  checksum_pos: u64,
+  major_version: u16,
+}
+
+enum Target {
+  Blob { cluster: u32, blob: u32 },
+  Redirect(u32),
+  Other,
+}
+
+struct DirEntry {
+  namespace: u8,
+  target: Target,
+  url: String,
+  title: String,
+}
+
+pub struct ZimArchive {
+  file: File,
+  size: u64,
+  header: Header,
+  // The last decompressed cluster, and whether its offsets are eight bytes long.
+  cache: Option<(u32, bool, Vec<u8>)>,
+}
+
+fn invalid(explanation: &str) -> DictError {
+  DictError::InvalidFileFormat(explanation.to_string(), None)
+}
+
+impl ZimArchive {
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<ZimArchive, DictError> {
+    let mut file = File::open(path.as_ref())?;
+    let size = file.metadata()?.len();
+    let mut buf = [0u8; HEADER_SIZE];
+    file.read_exact(&mut buf)?;
+    let mut cursor = &buf[..];
+    if cursor.read_u32::<LittleEndian>()? != ZIM_MAGIC {
+      return Err(DictError::InvalidFileFormat(
+        "Not a ZIM file: ".to_string(),
+        Some(path.as_ref().to_string_lossy().into_owned()),
+      ));
+    }
+    let major_version = cursor.read_u16::<LittleEndian>()?;
+    let minor_version = cursor.read_u16::<LittleEndian>()?;
+    // Skip the UUID.
+    cursor = &cursor[16..];
+    let header = Header {
+      major_version,
+      minor_version,
+      entry_count: cursor.read_u32::<LittleEndian>()?,
+      cluster_count: cursor.read_u32::<LittleEndian>()?,
+      url_ptr_pos: cursor.read_u64::<LittleEndian>()?,
+      title_ptr_pos: cursor.read_u64::<LittleEndian>()?,
+      cluster_ptr_pos: cursor.read_u64::<LittleEndian>()?,
+      mime_list_pos: cursor.read_u64::<LittleEndian>()?,
+      checksum_pos: {
+        // Skip the main and layout pages.
+        cursor = &cursor[8..];
+        cursor.read_u64::<LittleEndian>()?
+      },
+    };
+    Ok(ZimArchive {
+      file,
+      size,
+      header,
+      cache: None,
+    })
+  }
+
+  // The namespace of the articles: the newer files put all the content in `C`.
+  fn content_namespace(&self) -> u8 {
+    if self.header.major_version >= 6 && self.header.minor_version >= 1 {
+      b'C'
+    } else {
+      b'A'
+    }
+  }
+
+  fn read_u32_at(&mut self, pos: u64) -> Result<u32, DictError> {
+    self.file.seek(SeekFrom::Start(pos))?;
+    Ok(self.file.read_u32::<LittleEndian>()?)
+  }
+
+  fn read_u64_at(&mut self, pos: u64) -> Result<u64, DictError> {
+    self.file.seek(SeekFrom::Start(pos))?;
+    Ok(self.file.read_u64::<LittleEndian>()?)
+  }
+
+  fn read_string(&mut self) -> Result<String, DictError> {
+    let mut bytes = Vec::new();
+    loop {
+      let byte = self.file.read_u8()?;
+      if byte == 0 {
+        break;
+      }
+      bytes.push(byte);
+    }
+    Ok(String::from_utf8(bytes)?)
+  }
+
+  fn entry(&mut self, index: u32) -> Result<DirEntry, DictError> {
+    if index >= self.header.entry_count {
+      return Err(invalid("Entry index out of bounds."));
+    }
+    let pos = self.read_u64_at(self.header.url_ptr_pos + 8 * index as u64)?;
+    self.file.seek(SeekFrom::Start(pos))?;
+    let mime_type = self.file.read_u16::<LittleEndian>()?;
+    let _parameter_len = self.file.read_u8()?;
+    let namespace = self.file.read_u8()?;
+    let _revision = self.file.read_u32::<LittleEndian>()?;
+    let target = if mime_type == MIME_REDIRECT {
+      Target::Redirect(self.file.read_u32::<LittleEndian>()?)
+    } else if mime_type < 0xfffd {
+      let cluster = self.file.read_u32::<LittleEndian>()?;
+      let blob = self.file.read_u32::<LittleEndian>()?;
+      Target::Blob { cluster, blob }
+    } else {
+      // Link targets and deleted entries.
+      Target::Other
+    };
+    let url = self.read_string()?;
+    let mut title = self.read_string()?;
+    if title.is_empty() {
+      title = url.clone();
+    }
+    Ok(DirEntry {
+      namespace,
+      target,
+      url,
+      title,
+    })
+  }
+
+  // The index of the directory entry at *rank* in the title order.
+  fn title_entry(&mut self, rank: u32) -> Result<u32, DictError> {
+    self.read_u32_at(self.header.title_ptr_pos + 4 * rank as u64)
+  }
+
+  // The first rank, in the title order, whose key isn't less than the given one.
+  fn lower_bound(&mut self, namespace: u8, title: &str, by_url: bool) -> Result<u32, DictError> {
+    let (mut low, mut high) = (0, self.header.entry_count);
+    while low < high {
+      let mid = low + (high - low) / 2;
+      let index = if by_url { mid } else { self.title_entry(mid)? };
+      let entry = self.entry(index)?;
+      let key = if by_url { &entry.url } else { &entry.title };
+      let ordering = entry
+        .namespace
+        .cmp(&namespace)
+        .then_with(|| key.as_bytes().cmp(title.as_bytes()));
+      if ordering == Ordering::Less {
+        low = mid + 1;
+      } else {
+        high = mid;
+      }
+    }
+    Ok(low)
+  }
+
+  fn find_entry(
+    &mut self,
+    namespace: u8,
+    key: &str,
+    by_url: bool,
+  ) -> Result<Option<u32>, DictError> {
+    let rank = self.lower_bound(namespace, key, by_url)?;
+    if rank >= self.header.entry_count {
+      return Ok(None);
+    }
+    let index = if by_url {
+      rank
+    } else {
+      self.title_entry(rank)?
+    };
+    let entry = self.entry(index)?;
+    let found = entry.namespace == namespace
+      && if by_url {
+        entry.url == key
+      } else {
+        entry.title == key
+      };
+    Ok(if found { Some(index) } else { None })
+  }
+
+  // Follows the redirections.
+  fn resolve(&mut self, mut index: u32) -> Result<(u32, DirEntry), DictError> {
+    for _ in 0..MAX_REDIRECTS {
+      let entry = self.entry(index)?;
+      match entry.target {
+        Target::Redirect(target) => index = target,
+        _ => return Ok((index, entry)),
+      }
+    }
+    Err(invalid("Too many redirections."))
+  }
+
+  fn cluster_data(&mut self, cluster: u32) -> Result<Vec<u8>, DictError> {
+    if cluster >= self.header.cluster_count {
+      return Err(invalid("Cluster index out of bounds."));
+    }
+    let start = self.read_u64_at(self.header.cluster_ptr_pos + 8 * cluster as u64)?;
+    let end = if cluster + 1 < self.header.cluster_count {
+      self.read_u64_at(self.header.cluster_ptr_pos + 8 * (cluster as u64 + 1))?
+    } else {
+      let header = &self.header;
+      [
+        header.url_ptr_pos,
+        header.title_ptr_pos,
+        header.cluster_ptr_pos,
+        header.mime_list_pos,
+        header.checksum_pos,
+      ]
+      .iter()
+      .cloned()
+      .filter(|&pos| pos > start)
+      .fold(self.size, u64::min)
+    };
+    if end <= start {
+      return Err(invalid("Invalid cluster boundaries."));
+    }
+    self.file.seek(SeekFrom::Start(start))?;
+    let mut buf = vec![0; (end - start) as usize];
+    self.file.read_exact(&mut buf)?;
+    Ok(buf)
+  }
+
+  fn blob(&mut self, cluster: u32, blob: u32) -> Result<Vec<u8>, DictError> {
+    if self.cache.as_ref().map(|(c, ..)| *c) != Some(cluster) {
+      let raw = self.cluster_data(cluster)?;
+      let info = raw[0];
+      let data = match info & 0x0f {
+        0 | 1 => raw[1..].to_vec(),
+        2 => {
+          let mut data = Vec::new();
+          ZlibDecoder::new(&raw[1..]).read_to_end(&mut data)?;
+          data
+        },
+        4 => decompress_xz(&raw[1..])?,
+        5 => decompress_zstd(&raw[1..])?,
+        _ => return Err(invalid("Unsupported cluster compression.")),
+      };
+      self.cache = Some((cluster, info & 0x10 != 0, data));
+    }
+    let (_, extended, data) = self.cache.as_ref().unwrap();
+    let offset_size = if *extended { 8 } else { 4 };
+    let read_offset = |i: usize| -> Option<usize> {
+      let mut bytes = data.get(i * offset_size..(i + 1) * offset_size)?;
+      if *extended {
+        bytes.read_u64::<LittleEndian>().ok().map(|v| v as usize)
+      } else {
+        bytes.read_u32::<LittleEndian>().ok().map(|v| v as usize)
+      }
+    };
+    let start = read_offset(blob as usize).ok_or_else(|| invalid("Invalid blob index."))?;
+    let end = read_offset(blob as usize + 1).ok_or_else(|| invalid("Invalid blob index."))?;
+    data
+      .get(start..end)
+      .map(|bytes| bytes.to_vec())
+      .ok_or_else(|| invalid("Invalid blob boundaries."))
+  }
+
+  pub fn metadata(&mut self, name: &str) -> Result<String, DictError> {
+    let index = self
+      .find_entry(b'M', name, true)?
+      .ok_or_else(|| DictError::WordNotFound(name.to_string()))?;
+    let (_, entry) = self.resolve(index)?;
+    match entry.target {
+      Target::Blob { cluster, blob } => Ok(String::from_utf8(self.blob(cluster, blob)?)?),
+      _ => Err(DictError::WordNotFound(name.to_string())),
+    }
+  }
+
+  // The article whose title, or URL, is one of the variants of *query*.
+  fn find_article(&mut self, query: &str) -> Result<Option<(u32, DirEntry)>, DictError> {
+    let namespace = self.content_namespace();
+    for variant in variants(query) {
+      let index = match self.find_entry(namespace, &variant, false)? {
+        Some(index) => Some(index),
+        None => self.find_entry(namespace, &variant.replace(' ', "_"), true)?,
+      };
+      if let Some(index) = index {
+        return self.resolve(index).map(Some);
+      }
+    }
+    Ok(None)
+  }
+
+  // The titles that start with *prefix*.
+  fn search(&mut self, prefix: &str) -> Result<Vec<Entry>, DictError> {
+    let namespace = self.content_namespace();
+    let mut entries: Vec<Entry> = Vec::new();
+    for variant in variants(prefix).into_iter().take(2) {
+      let mut rank = self.lower_bound(namespace, &variant, false)?;
+      while rank < self.header.entry_count && entries.len() < MAX_SEARCH_RESULTS {
+        let index = self.title_entry(rank)?;
+        let entry = self.entry(index)?;
+        if entry.namespace != namespace || !entry.title.starts_with(&variant) {
+          break;
+        }
+        if entries.iter().all(|e| e.headword != entry.title) {
+          entries.push(Entry {
+            headword: entry.title,
+            offset: LINK_FLAG | index as u64,
+            size: 0,
+            original: None,
+          });
+        }
+        rank += 1;
+      }
+    }
+    Ok(entries)
+  }
+}
+
+// The spellings tried for a query: as typed, capitalized, in lower case and in title case.
+fn variants(query: &str) -> Vec<String> {
+  let query = query.trim();
+  let mut variants = vec![query.to_string()];
+  let capitalized = capitalize(query);
+  let lowercase = query.to_lowercase();
+  let title_case = query
+    .split(' ')
+    .map(capitalize)
+    .collect::<Vec<String>>()
+    .join(" ");
+  for variant in vec![capitalized, lowercase, title_case] {
+    if !variants.contains(&variant) {
+      variants.push(variant);
+    }
+  }
+  variants
+}
+
+fn capitalize(word: &str) -> String {
+  let mut chars = word.chars();
+  match chars.next() {
+    Some(c) => c.to_uppercase().chain(chars).collect(),
+    None => String::new(),
+  }
+}
+
+fn percent_decode(text: &str) -> String {
+  let bytes = text.as_bytes();
+  let mut result = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] == b'%' && i + 2 < bytes.len() {
+      if let Some(byte) = text
+        .get(i + 1..i + 3)
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+      {
+        result.push(byte);
+        i += 3;
+        continue;
+      }
+    }
+    result.push(bytes[i]);
+    i += 1;
+  }
+  String::from_utf8_lossy(&result).into_owned()
+}
+
+// Keeps the body of an article, without the scripts and the images, and turns the links to the
+// other articles into lookups.
+fn article_body(html: &str) -> String {
+  let start = html
+    .find("<body")
+    .and_then(|i| html[i..].find('>').map(|j| i + j + 1))
+    .unwrap_or(0);
+  let end = html
+    .rfind("</body>")
+    .filter(|&e| e >= start)
+    .unwrap_or(html.len());
+  let body = IGNORED_ELEMENTS.replace_all(&html[start..end], "");
+  HREF
+    .replace_all(&body, |caps: &Captures| {
+      let href = &caps[1];
+      if href.is_empty() || href.starts_with('#') || href.contains("://") {
+        return caps[0].to_string();
+      }
+      let path = href.split('#').next().unwrap_or_default();
+      let name = path.rsplit('/').next().unwrap_or_default();
+      format!("href=\"?{}\"", percent_decode(name).replace('_', " "))
+    })
+    .into_owned()
+}
+
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn decompress_zstd(input: &[u8]) -> Result<Vec<u8>, DictError> {
+  let mut output: Vec<u8> = Vec::new();
+  unsafe {
+    let stream = ZSTD_createDStream();
+    if stream.is_null() {
+      return Err(DictError::MemoryError);
+    }
+    ZSTD_initDStream(stream);
+    let chunk_size = ZSTD_DStreamOutSize();
+    let mut in_buf = ZSTD_inBuffer {
+      src: input.as_ptr() as *const _,
+      size: input.len(),
+      pos: 0,
+    };
+    let mut result = Ok(());
+    loop {
+      let len = output.len();
+      output.reserve(chunk_size);
+      let mut out_buf = ZSTD_outBuffer {
+        dst: output.as_mut_ptr().add(len) as *mut _,
+        size: chunk_size,
+        pos: 0,
+      };
+      let ret = ZSTD_decompressStream(stream, &mut out_buf, &mut in_buf);
+      if ZSTD_isError(ret) != 0 {
+        result = Err(invalid("Can't decompress a zstd cluster."));
+        break;
+      }
+      output.set_len(len + out_buf.pos);
+      // The frame is complete, or the input is exhausted and the output flushed.
+      if ret == 0 || (in_buf.pos == in_buf.size && out_buf.pos < out_buf.size) {
+        break;
+      }
+    }
+    ZSTD_freeDStream(stream);
+    result.map(|_| output)
+  }
+}
+
+fn decompress_xz(input: &[u8]) -> Result<Vec<u8>, DictError> {
+  let mut size = 4 * input.len().max(1024);
+  loop {
+    let mut output = vec![0u8; size.min(MAX_CLUSTER_SIZE)];
+    let mut memlimit = u64::MAX;
+    let (mut in_pos, mut out_pos) = (0, 0);
+    let ret = unsafe {
+      lzma_stream_buffer_decode(
+        &mut memlimit,
+        0,
+        ptr::null(),
+        input.as_ptr(),
+        &mut in_pos,
+        input.len(),
+        output.as_mut_ptr(),
+        &mut out_pos,
+        output.len(),
+      )
+    };
+    match ret {
+      LZMA_OK => {
+        output.truncate(out_pos);
+        return Ok(output);
+      },
+      // The output buffer is too small, or the input is truncated.
+      LZMA_BUF_ERROR if size < MAX_CLUSTER_SIZE => size *= 2,
+      _ => return Err(invalid("Can't decompress an xz cluster.")),
+    }
+  }
+}
+
+pub struct ZimIndex(Rc<RefCell<ZimArchive>>);
+
+pub struct ZimContent(Rc<RefCell<ZimArchive>>);
+
+pub fn load_zim<P: AsRef<Path>>(path: P) -> Result<(ZimContent, ZimIndex), DictError> {
+  let archive = Rc::new(RefCell::new(ZimArchive::open(path)?));
+  Ok((ZimContent(archive.clone()), ZimIndex(archive)))
+}
+
+impl IndexReader for ZimIndex {
+  fn load_and_find(&mut self, headword: &str, fuzzy: bool, _metadata: &Metadata) -> Vec<Entry> {
+    self.find(headword, fuzzy)
+  }
+
+  fn find(&self, headword: &str, fuzzy: bool) -> Vec<Entry> {
+    let metadata_entry = |offset| {
+      vec![Entry {
+        headword: headword.to_string(),
+        offset,
+        size: 0,
+        original: None,
+      }]
+    };
+    // The queries are neither normalized nor lower cased.
+    match headword {
+      "00-database-allchars" | "00-database-case-sensitive" => {
+        return metadata_entry(METADATA_FLAG)
+      },
+      "00-database-short" => return metadata_entry(TITLE_METADATA),
+      _ => (),
+    }
+    let mut archive = self.0.borrow_mut();
+    let result = if fuzzy {
+      archive.search(headword)
+    } else {
+      archive.find_article(headword).map(|article| {
+        article
+          .map(|(index, entry)| Entry {
+            headword: entry.title,
+            offset: index as u64,
+            size: 0,
+            original: None,
+          })
+          .into_iter()
+          .collect()
+      })
+    };
+    result.unwrap_or_else(|e| {
+      eprintln!("Can't search the ZIM archive: {}.", e);
+      Vec::new()
+    })
+  }
+}
+
+impl DictReader for ZimContent {
+  fn fetch_definition(&mut self, offset: u64, _length: u64) -> Result<String, DictError> {
+    let mut archive = self.0.borrow_mut();
+    if offset == TITLE_METADATA {
+      return archive.metadata("Title");
+    } else if offset & METADATA_FLAG != 0 {
+      return Ok(String::new());
+    } else if offset & LINK_FLAG != 0 {
+      let entry = archive.entry((offset & !LINK_FLAG) as u32)?;
+      let title = escape_html(&entry.title);
+      return Ok(format!(
+        "<h2 class=\"headword\"><a href=\"?{0}\">{0}</a></h2>\n",
+        title
+      ));
+    }
+    let (_, entry) = archive.resolve(offset as u32)?;
+    match entry.target {
+      Target::Blob { cluster, blob } => {
+        let html = String::from_utf8_lossy(&archive.blob(cluster, blob)?).into_owned();
+        Ok(article_body(&html))
+      },
+      _ => Err(DictError::WordNotFound(entry.title)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use byteorder::WriteBytesExt;
+  use std::{env, fs, io::Write};
+
+  // Builds an uncompressed archive with two articles, a redirection and a title.
+  fn build_archive(path: &Path) {
+    let blobs: [&[u8]; 3] = [
+      b"<html><head><title>Apple</title></head><body><p>An <a href=\"Banana\">apple</a>.</p>\
+        <script>run();</script></body></html>",
+      b"<html><body><p>A banana.</p></body></html>",
+      b"Fruits",
+    ];
+    let mut dirents = Vec::new();
+    let mut dirent_positions = Vec::new();
+    let mime_list = b"text/html\0\0";
+    let entries = 4u64;
+    let dirents_start = HEADER_SIZE as u64 + mime_list.len() as u64 + 12 * entries;
+    let article = |buf: &mut Vec<u8>, namespace: u8, url: &str, blob: u32| {
+      buf.write_u16::<LittleEndian>(0).unwrap();
+      buf.write_u8(0).unwrap();
+      buf.write_u8(namespace).unwrap();
+      buf.write_u32::<LittleEndian>(0).unwrap();
+      buf.write_u32::<LittleEndian>(0).unwrap();
+      buf.write_u32::<LittleEndian>(blob).unwrap();
+      buf.extend_from_slice(url.as_bytes());
+      buf.extend_from_slice(b"\0\0");
+    };
+    dirent_positions.push(dirents_start + dirents.len() as u64);
+    article(&mut dirents, b'A', "Apple", 0);
+    dirent_positions.push(dirents_start + dirents.len() as u64);
+    article(&mut dirents, b'A', "Banana", 1);
+    dirent_positions.push(dirents_start + dirents.len() as u64);
+    dirents.write_u16::<LittleEndian>(MIME_REDIRECT).unwrap();
+    dirents.write_u8(0).unwrap();
+    dirents.write_u8(b'A').unwrap();
+    dirents.write_u32::<LittleEndian>(0).unwrap();
+    dirents.write_u32::<LittleEndian>(0).unwrap();
+    dirents.extend_from_slice(b"Pomme\0\0");
+    dirent_positions.push(dirents_start + dirents.len() as u64);
+    article(&mut dirents, b'M', "Title", 2);
+
+    let mut cluster = vec![1u8];
+    let mut offset = 4 * (blobs.len() as u32 + 1);
+    for blob in blobs.iter() {
+      cluster.write_u32::<LittleEndian>(offset).unwrap();
+      offset += blob.len() as u32;
+    }
+    cluster.write_u32::<LittleEndian>(offset).unwrap();
+    for blob in blobs.iter() {
+      cluster.extend_from_slice(blob);
+    }
+
+    let cluster_ptr_pos = dirents_start + dirents.len() as u64;
+    let cluster_pos = cluster_ptr_pos + 8;
+    let checksum_pos = cluster_pos + cluster.len() as u64;
+    let mut data = Vec::new();
+    data.write_u32::<LittleEndian>(ZIM_MAGIC).unwrap();
+    data.write_u16::<LittleEndian>(5).unwrap();
+    data.write_u16::<LittleEndian>(0).unwrap();
+    data.extend_from_slice(&[0; 16]);
+    data.write_u32::<LittleEndian>(entries as u32).unwrap();
+    data.write_u32::<LittleEndian>(1).unwrap();
+    let url_ptr_pos = HEADER_SIZE as u64 + mime_list.len() as u64;
+    data.write_u64::<LittleEndian>(url_ptr_pos).unwrap();
+    data
+      .write_u64::<LittleEndian>(url_ptr_pos + 8 * entries)
+      .unwrap();
+    data.write_u64::<LittleEndian>(cluster_ptr_pos).unwrap();
+    data.write_u64::<LittleEndian>(HEADER_SIZE as u64).unwrap();
+    data.write_u32::<LittleEndian>(0).unwrap();
+    data.write_u32::<LittleEndian>(0xffff_ffff).unwrap();
+    data.write_u64::<LittleEndian>(checksum_pos).unwrap();
+    data.extend_from_slice(mime_list);
+    for pos in &dirent_positions {
+      data.write_u64::<LittleEndian>(*pos).unwrap();
+    }
+    // The titles are the URLs, hence they're in the same order.
+    for index in 0..entries {
+      data.write_u32::<LittleEndian>(index as u32).unwrap();
+    }
+    data.extend_from_slice(&dirents);
+    data.write_u64::<LittleEndian>(cluster_pos).unwrap();
+    data.extend_from_slice(&cluster);
+    data.extend_from_slice(&[0; 16]);
+    fs::File::create(path).unwrap().write_all(&data).unwrap();
+  }
+
+  #[test]
+  fn test_zim_lookup() {
+    let path = env::temp_dir().join("plato-test.zim");
+    build_archive(&path);
+    let (content, index) = load_zim(&path).unwrap();
+    let mut dict = crate::dictionary::load_dictionary(Box::new(content), Box::new(index));
+    fs::remove_file(&path).ok();
+    assert_eq!(dict.short_name().unwrap(), "Fruits");
+    let results = dict.lookup("apple", false).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0][0], "Apple");
+    assert_eq!(results[0][1], "<p>An <a href=\"?Banana\">apple</a>.</p>");
+    let results = dict.lookup("Pomme", false).unwrap();
+    assert_eq!(results[0][0], "Apple");
+    let results = dict.lookup("b", true).unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0][1].contains("href=\"?Banana\""));
+    assert!(dict.lookup("cherry", false).unwrap().is_empty());
+  }
+}
@@ -0,0 +1,51 @@
+#![allow(unused, non_camel_case_types)]
+
+use std::os::raw::{c_int, c_uint, c_void};
+
+pub const LZMA_OK: c_int = 0;
+pub const LZMA_BUF_ERROR: c_int = 10;
+
+pub enum ZSTD_DStream {}
+
+#[repr(C)]
+pub struct ZSTD_inBuffer {
+  pub src: *const c_void,
+  pub size: usize,
+  pub pos: usize,
+}
+
+#[repr(C)]
+pub struct ZSTD_outBuffer {
+  pub dst: *mut c_void,
+  pub size: usize,
+  pub pos: usize,
+}
+
+#[link(name = "zstd")]
+extern "C" {
+  pub fn ZSTD_createDStream() -> *mut ZSTD_DStream;
+  pub fn ZSTD_freeDStream(zds: *mut ZSTD_DStream) -> usize;
+  pub fn ZSTD_initDStream(zds: *mut ZSTD_DStream) -> usize;
+  pub fn ZSTD_decompressStream(
+    zds: *mut ZSTD_DStream,
+    output: *mut ZSTD_outBuffer,
+    input: *mut ZSTD_inBuffer,
+  ) -> usize;
+  pub fn ZSTD_DStreamOutSize() -> usize;
+  pub fn ZSTD_isError(code: usize) -> c_uint;
+}
+
+#[link(name = "lzma")]
+extern "C" {
+  pub fn lzma_stream_buffer_decode(
+    memlimit: *mut u64,
+    flags: u32,
+    allocator: *const c_void,
+    input: *const u8,
+    in_pos: *mut usize,
+    in_size: usize,
+    output: *mut u8,
+    out_pos: *mut usize,
+    out_size: usize,
+  ) -> c_int;
+}
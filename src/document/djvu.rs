@@ -2,9 +2,10 @@ use super::djvulibre_sys::*;
 
 use super::{chapter, chapter_relative, BoundedText, Document, Location, TextLocation, TocEntry};
 use crate::{
+  font::FamilyFiles,
   framebuffer::Pixmap,
   geom::{CycleDir, Rectangle},
-  metadata::TextAlign,
+  metadata::{PageMargins, TextAlign},
 };
 use std::{
   ffi::{CStr, CString},
@@ -255,10 +256,12 @@ impl Document for DjvuDocument {
 
   fn set_text_align(&mut self, _text_align: TextAlign) {}
 
-  fn set_font_family(&mut self, _family_name: &str, _search_path: &str) {}
+  fn set_font_family(&mut self, _files: &FamilyFiles) {}
 
   fn set_margin_width(&mut self, _width: i32) {}
 
+  fn set_margins(&mut self, _margins: &PageMargins) {}
+
   fn set_line_height(&mut self, _line_height: f32) {}
 }
 
@@ -16,13 +16,17 @@ use super::html::{
 };
 use crate::{
   document::{chapter_from_uri, BoundedText, Document, Location, TextLocation, TocEntry},
-  framebuffer::Pixmap,
+  font::FamilyFiles,
+  framebuffer::{DitherMode, Pixmap},
   geom::{CycleDir, Edge, Rectangle},
   helpers::{decode_entities, Normalize},
+  metadata::{PageMargins, TextAlignOverride},
+  settings::TypographySettings,
   unit::pt_to_px,
 };
 use anyhow::{format_err, Error};
 use fxhash::FxHashMap;
+use regex::Regex;
 use std::{
   borrow::Cow,
   collections::BTreeSet,
@@ -37,6 +41,23 @@ const USER_STYLESHEET: &str = "css/epub-user.css";
 
 type UriCache = FxHashMap<String, usize>;
 
+// Returns the first node of the tree rooted at *node* for which *test* holds.
+fn find_with<'a, F: Fn(&Node) -> bool + Copy>(node: &'a Node, test: F) -> Option<&'a Node> {
+  if test(node) {
+    return Some(node);
+  }
+  node
+    .children()?
+    .iter()
+    .find_map(|child| find_with(child, test))
+}
+
+fn has_epub_type(node: &Node, kind: &str) -> bool {
+  node
+    .attr("epub:type")
+    .map_or(false, |t| t.split_whitespace().any(|t| t == kind))
+}
+
 impl ResourceFetcher for ZipArchive<File> {
   fn fetch(&mut self, name: &str) -> Result<Vec<u8>, Error> {
     let mut file = self.by_name(name)?;
@@ -51,7 +72,10 @@ pub struct EpubDocument {
   info: Node,
   parent: PathBuf,
   engine: Engine,
+  // The linear chunks come first, followed by the non-linear ones.
   spine: Vec<Chunk>,
+  // The number of linear chunks.
+  linear_len: usize,
   cache: FxHashMap<usize, Vec<Page>>,
   ignore_document_css: bool,
 }
@@ -95,6 +119,9 @@ impl EpubDocument {
 
     let info = XmlParser::new(&text).parse();
     let mut spine = Vec::new();
+    // The non-linear items (answer keys, appendices, etc.) are only reached through links and
+    // the table of contents: they're moved past the offsets that count as pages.
+    let mut extras = Vec::new();
 
     {
       let manifest = info
@@ -126,11 +153,23 @@ impl EpubDocument {
           });
 
         if let Some((size, path)) = vertebra_opt {
-          spine.push(Chunk { path, size });
+          if child.attr("linear") == Some("no") {
+            extras.push(Chunk { path, size });
+          } else {
+            spine.push(Chunk { path, size });
+          }
         }
       }
     }
 
+    // A spine without linear items is read linearly.
+    let linear_len = if spine.is_empty() {
+      extras.len()
+    } else {
+      spine.len()
+    };
+    spine.append(&mut extras);
+
     if spine.is_empty() {
       return Err(format_err!("The spine is empty."));
     }
@@ -141,6 +180,7 @@ impl EpubDocument {
       parent: parent.to_path_buf(),
       engine: Engine::new(),
       spine,
+      linear_len,
       cache: FxHashMap::default(),
       ignore_document_css: false,
     })
@@ -203,6 +243,129 @@ impl EpubDocument {
     rect![0, 0, width as i32, height as i32]
   }
 
+  // The start of the body matter given by the landmarks of the navigation document (EPUB 3).
+  fn landmarks_start(&mut self) -> Option<String> {
+    let name = self
+      .info
+      .find("manifest")
+      .and_then(Node::children)?
+      .iter()
+      .find(|child| {
+        child
+          .attr("properties")
+          .map_or(false, |p| p.split_whitespace().any(|p| p == "nav"))
+      })
+      .and_then(|entry| entry.attr("href"))
+      .map(|href| {
+        self
+          .parent
+          .join(href)
+          .normalize()
+          .to_string_lossy()
+          .into_owned()
+      })?;
+
+    let mut text = String::new();
+    self
+      .archive
+      .by_name(&name)
+      .ok()?
+      .read_to_string(&mut text)
+      .ok()?;
+
+    let root = XmlParser::new(&text).parse();
+    let landmarks = find_with(&root, |n| {
+      n.tag_name() == Some("nav") && has_epub_type(n, "landmarks")
+    })?;
+    let href = find_with(landmarks, |n| {
+      n.tag_name() == Some("a") && has_epub_type(n, "bodymatter")
+    })
+    .and_then(|a| a.attr("href"))?;
+    let nav_dir = Path::new(&name).parent().unwrap_or_else(|| Path::new(""));
+    nav_dir.join(href).normalize().to_str().map(String::from)
+  }
+
+  // The start of the text given by the guide of the package document (EPUB 2).
+  fn guide_start(&self) -> Option<String> {
+    let href = self
+      .info
+      .find("guide")
+      .and_then(Node::children)?
+      .iter()
+      .find(|child| {
+        child.tag_name() == Some("reference")
+          && matches!(child.attr("type"), Some("text") | Some("bodymatter"))
+      })
+      .and_then(|child| child.attr("href"))?;
+    self
+      .parent
+      .join(href)
+      .normalize()
+      .to_str()
+      .map(String::from)
+  }
+
+  // The first entry of the table of contents that follows the front matter entries.
+  fn toc_start(&mut self) -> Option<String> {
+    let front_matter = Regex::new(
+      r"(?i)^\s*(cover|title( page)?|half[- ]title|copyright|dedication|epigraph|contents|table of contents|also by|praise|front ?matter)\b",
+    )
+    .unwrap();
+    let toc = self.toc()?;
+    if !toc
+      .first()
+      .map_or(false, |entry| front_matter.is_match(&entry.title))
+    {
+      return None;
+    }
+    toc
+      .iter()
+      .find(|entry| !front_matter.is_match(&entry.title))
+      .and_then(|entry| match entry.location {
+        Location::Uri(ref uri) => Some(uri.clone()),
+        _ => None,
+      })
+  }
+
+  // The table of contents described by the NCX file, *index* ends up being the number of
+  // entries.
+  fn nav_toc(&mut self, index: &mut usize) -> Option<Vec<TocEntry>> {
+    let name = self
+      .info
+      .find("spine")
+      .and_then(|spine| spine.attr("toc"))
+      .and_then(|toc_id| {
+        self
+          .info
+          .find("manifest")
+          .and_then(|manifest| manifest.find_by_id(toc_id))
+          .and_then(|entry| entry.attr("href"))
+      })
+      .map(|href| {
+        self
+          .parent
+          .join(href)
+          .normalize()
+          .to_string_lossy()
+          .into_owned()
+      })?;
+
+    let toc_dir = Path::new(&name).parent().unwrap_or_else(|| Path::new(""));
+
+    let mut text = String::new();
+    if let Ok(mut zf) = self.archive.by_name(&name) {
+      zf.read_to_string(&mut text).ok()?;
+    } else {
+      return None;
+    }
+
+    let root = XmlParser::new(&text).parse();
+    root.find("navMap").map(|map| {
+      let mut cache = FxHashMap::default();
+      self.walk_toc(&map, &toc_dir, index, &mut cache)
+    })
+  }
+
   fn walk_toc(
     &mut self,
     node: &Node,
@@ -327,29 +490,6 @@ impl EpubDocument {
     }
   }
 
-  fn images(&mut self, loc: Location) -> Option<(Vec<Rectangle>, usize)> {
-    if self.spine.is_empty() {
-      return None;
-    }
-
-    let offset = self.resolve_location(loc)?;
-    let (index, start_offset) = self.vertebra_coordinates(offset)?;
-    let page_index = self.page_index(offset, index, start_offset)?;
-
-    self.cache.get(&index).map(|display_list| {
-      (
-        display_list[page_index]
-          .iter()
-          .filter_map(|dc| match dc {
-            DrawCommand::Image(ImageCommand { rect, .. }) => Some(*rect),
-            _ => None,
-          })
-          .collect(),
-        offset,
-      )
-    })
-  }
-
   fn build_display_list(&mut self, index: usize, start_offset: usize) -> Vec<Page> {
     let mut text = String::new();
     let mut spine_dir = PathBuf::from("");
@@ -662,45 +802,47 @@ impl Document for EpubDocument {
   }
 
   fn pages_count(&self) -> usize {
-    self.spine.iter().map(|c| c.size).sum()
+    self.offset(self.linear_len)
   }
 
   fn toc(&mut self) -> Option<Vec<TocEntry>> {
-    let name = self
-      .info
-      .find("spine")
-      .and_then(|spine| spine.attr("toc"))
-      .and_then(|toc_id| {
-        self
-          .info
-          .find("manifest")
-          .and_then(|manifest| manifest.find_by_id(toc_id))
-          .and_then(|entry| entry.attr("href"))
-      })
-      .map(|href| {
-        self
-          .parent
-          .join(href)
-          .normalize()
-          .to_string_lossy()
-          .into_owned()
-      })?;
-
-    let toc_dir = Path::new(&name).parent().unwrap_or_else(|| Path::new(""));
-
-    let mut text = String::new();
-    if let Ok(mut zf) = self.archive.by_name(&name) {
-      zf.read_to_string(&mut text).ok()?;
-    } else {
-      return None;
+    let mut index = 0;
+    let mut toc = self.nav_toc(&mut index);
+
+    if self.linear_len < self.spine.len() {
+      let start = index;
+      let children = self.spine[self.linear_len..]
+        .iter()
+        .map(|chunk| {
+          let title = toc
+            .as_deref()
+            .and_then(|toc| chapter_from_uri(&chunk.path, toc))
+            .map(|entry| entry.title.clone())
+            .or_else(|| {
+              Path::new(&chunk.path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .unwrap_or_default();
+          index += 1;
+          TocEntry {
+            title,
+            location: Location::Uri(chunk.path.clone()),
+            index,
+            children: Vec::new(),
+          }
+        })
+        .collect();
+      let extra = TocEntry {
+        title: "Extra Content".to_string(),
+        location: Location::Exact(self.pages_count()),
+        index: start,
+        children,
+      };
+      toc.get_or_insert_with(Vec::new).push(extra);
     }
 
-    let root = XmlParser::new(&text).parse();
-    root.find("navMap").map(|map| {
-      let mut cache = FxHashMap::default();
-      let mut index = 0;
-      self.walk_toc(&map, &toc_dir, &mut index, &mut cache)
-    })
+    toc
   }
 
   fn chapter<'a>(&mut self, offset: usize, toc: &'a [TocEntry]) -> Option<&'a TocEntry> {
@@ -776,7 +918,7 @@ impl Document for EpubDocument {
               .map(DrawCommand::offset)
           })
         } else {
-          if index == 0 {
+          if index == 0 || index >= self.linear_len {
             return None;
           }
           let (index, start_offset) = (index - 1, start_offset - self.spine[index - 1].size);
@@ -802,7 +944,8 @@ impl Document for EpubDocument {
               .map(DrawCommand::offset)
           })
         } else {
-          if index == self.spine.len() - 1 {
+          // Each non-linear chunk is read on its own.
+          if index + 1 >= self.linear_len {
             return None;
           }
           let (index, start_offset) = (index + 1, start_offset + self.spine[index].size);
@@ -904,6 +1047,29 @@ impl Document for EpubDocument {
     })
   }
 
+  fn images(&mut self, loc: Location) -> Option<(Vec<Rectangle>, usize)> {
+    if self.spine.is_empty() {
+      return None;
+    }
+
+    let offset = self.resolve_location(loc)?;
+    let (index, start_offset) = self.vertebra_coordinates(offset)?;
+    let page_index = self.page_index(offset, index, start_offset)?;
+
+    self.cache.get(&index).map(|display_list| {
+      (
+        display_list[page_index]
+          .iter()
+          .filter_map(|dc| match dc {
+            DrawCommand::Image(ImageCommand { rect, .. }) => Some(*rect),
+            _ => None,
+          })
+          .collect(),
+        offset,
+      )
+    })
+  }
+
   fn pixmap(&mut self, loc: Location, _scale: f32) -> Option<(Pixmap, usize)> {
     if self.spine.is_empty() {
       return None;
@@ -930,8 +1096,8 @@ impl Document for EpubDocument {
     self.cache.clear();
   }
 
-  fn set_font_family(&mut self, family_name: &str, search_path: &str) {
-    self.engine.set_font_family(family_name, search_path);
+  fn set_font_family(&mut self, files: &FamilyFiles) {
+    self.engine.set_font_family(files);
     self.cache.clear();
   }
 
@@ -940,11 +1106,35 @@ impl Document for EpubDocument {
     self.cache.clear();
   }
 
+  fn set_margins(&mut self, margins: &PageMargins) {
+    self.engine.set_margins(margins);
+    self.cache.clear();
+  }
+
   fn set_line_height(&mut self, line_height: f32) {
     self.engine.set_line_height(line_height);
     self.cache.clear();
   }
 
+  fn set_dithering(&mut self, dithering: DitherMode) {
+    self.engine.set_dithering(dithering);
+  }
+
+  fn set_typography(&mut self, typography: TypographySettings) {
+    self.engine.set_typography(typography);
+    self.cache.clear();
+  }
+
+  fn set_verse_layout(&mut self, enable: bool) {
+    self.engine.set_verse_layout(enable);
+    self.cache.clear();
+  }
+
+  fn set_text_align_override(&mut self, value: TextAlignOverride) {
+    self.engine.set_text_align_override(value);
+    self.cache.clear();
+  }
+
   fn title(&self) -> Option<String> {
     self.metadata("dc:title")
   }
@@ -972,6 +1162,14 @@ impl Document for EpubDocument {
     true
   }
 
+  fn start_location(&mut self) -> Option<Location> {
+    self
+      .landmarks_start()
+      .or_else(|| self.guide_start())
+      .or_else(|| self.toc_start())
+      .map(Location::Uri)
+  }
+
   fn has_synthetic_page_numbers(&self) -> bool {
     true
   }
@@ -0,0 +1,124 @@
+//! Extracts the text layer of a document, one section per chapter.
+
+use super::{BoundedText, Document, Location};
+use std::sync::Mutex;
+
+// A line ending before this fraction of the text width ends a paragraph.
+const SHORT_LINE_RATIO: f32 = 0.8;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TextFormat {
+  Plain,
+  Markdown,
+}
+
+impl TextFormat {
+  pub fn label(self) -> &'static str {
+    match self {
+      TextFormat::Plain => "Plain Text",
+      TextFormat::Markdown => "Markdown",
+    }
+  }
+
+  pub fn extension(self) -> &'static str {
+    match self {
+      TextFormat::Plain => "txt",
+      TextFormat::Markdown => "md",
+    }
+  }
+
+  fn heading(self, title: &str) -> String {
+    match self {
+      TextFormat::Plain => format!("{}\n{}", title, "=".repeat(title.chars().count())),
+      TextFormat::Markdown => format!("# {}", title),
+    }
+  }
+}
+
+// *progress* is called with the fraction of the document processed so far. The document is
+// only locked while a page is processed, so that it can be rendered in the meantime.
+pub fn export_text<F: FnMut(f32)>(
+  doc: &Mutex<Box<dyn Document>>,
+  format: TextFormat,
+  mut progress: F,
+) -> String {
+  let (toc, pages_count) = {
+    let mut doc = doc.lock().unwrap();
+    (doc.toc().unwrap_or_default(), doc.pages_count().max(1))
+  };
+  let mut text = String::new();
+  let mut paragraph = String::new();
+  let mut chapter_index = None;
+  let mut loc = Location::Exact(0);
+
+  loop {
+    let (location, chapter, words) = {
+      let mut doc = doc.lock().unwrap();
+      let location = match doc.resolve_location(loc) {
+        Some(location) => location,
+        None => break,
+      };
+      let chapter = doc.chapter(location, &toc);
+      let words = doc.words(Location::Exact(location)).map(|(words, _)| words);
+      (location, chapter, words)
+    };
+
+    progress(location as f32 / pages_count as f32);
+    if let Some(chapter) = chapter {
+      if chapter_index != Some(chapter.index) {
+        flush_paragraph(&mut text, &mut paragraph);
+        if !text.is_empty() {
+          text.push('\n');
+        }
+        text += &format.heading(&chapter.title);
+        text.push_str("\n\n");
+        chapter_index = Some(chapter.index);
+      }
+    }
+
+    if let Some(words) = words {
+      push_words(&words, &mut text, &mut paragraph);
+    }
+
+    loc = Location::Next(location);
+  }
+
+  flush_paragraph(&mut text, &mut paragraph);
+  text
+}
+
+// Appends the words of a page to the current paragraph, and the finished paragraphs to *text*.
+fn push_words(words: &[BoundedText], text: &mut String, paragraph: &mut String) {
+  let right = words.iter().map(|w| w.rect.max.x).fold(0.0, f32::max);
+  let left = words.iter().map(|w| w.rect.min.x).fold(right, f32::min);
+  let mut last: Option<&BoundedText> = None;
+
+  for word in words {
+    if let Some(last) = last {
+      if word.rect.min.y >= last.rect.max.y
+        && last.rect.max.x - left < SHORT_LINE_RATIO * (right - left)
+      {
+        flush_paragraph(text, paragraph);
+      }
+    }
+
+    if paragraph.ends_with('\u{00AD}') {
+      paragraph.pop();
+    } else if !paragraph.ends_with('-') && !paragraph.is_empty() {
+      paragraph.push(' ');
+    }
+
+    *paragraph += &word.text;
+    last = Some(word);
+  }
+}
+
+fn flush_paragraph(text: &mut String, paragraph: &mut String) {
+  if paragraph.is_empty() {
+    return;
+  }
+
+  *text += paragraph.trim();
+  text.push_str("\n\n");
+  paragraph.clear();
+}
@@ -9,6 +9,7 @@ use super::{
     DrawState,
     Float,
     FontKind,
+    FontWeight,
     Fonts,
     GlueMaterial,
     ImageCommand,
@@ -18,9 +19,12 @@ use super::{
     LineStats,
     ListStyleType,
     LoopContext,
+    MathElement,
+    MathMaterial,
     ParagraphElement,
     PenaltyMaterial,
     RootData,
+    RuleCommand,
     SiblingStyle,
     StyleData,
     TextAlign,
@@ -33,6 +37,7 @@ use super::{
     HYPHENATION_PATTERNS,
     WORD_SPACE_RATIOS,
   },
+  math::{from_mathml, parse_latex, split_latex, MathItem, MathLayout, MathNode},
   parse::{
     parse_color,
     parse_display,
@@ -52,17 +57,25 @@ use super::{
     parse_text_align,
     parse_text_indent,
     parse_vertical_align,
+    parse_white_space,
     parse_width,
   },
   style::{specified_values, Stylesheet},
 };
 use crate::{
   document::{pdf::PdfOpener, Document, Location},
-  font::{FontFamily, FontOpener},
-  framebuffer::{Framebuffer, Pixmap},
+  font::{FamilyFiles, FontFamily, FontOpener},
+  framebuffer::{dither, DitherMode, Framebuffer, Pixmap},
   geom::{Edge, Rectangle},
   helpers::{decode_entities, Normalize},
-  settings::{DEFAULT_FONT_SIZE, DEFAULT_LINE_HEIGHT, DEFAULT_MARGIN_WIDTH, DEFAULT_TEXT_ALIGN},
+  metadata::{PageMargins, TextAlignOverride},
+  settings::{
+    TypographySettings,
+    DEFAULT_FONT_SIZE,
+    DEFAULT_LINE_HEIGHT,
+    DEFAULT_MARGIN_WIDTH,
+    DEFAULT_TEXT_ALIGN,
+  },
   unit::{mm_to_px, pt_to_px},
 };
 use anyhow::Error;
@@ -75,14 +88,20 @@ use paragraph_breaker::{
   INFINITE_PENALTY,
 };
 use septem::Roman;
-use std::{convert::TryFrom, path::PathBuf};
+use std::{convert::TryFrom, path::PathBuf, slice};
 use xi_unicode::LineBreakIterator;
 
 const DEFAULT_DPI: u16 = 300;
 const DEFAULT_WIDTH: u32 = 1404;
 const DEFAULT_HEIGHT: u32 = 1872;
 const HYPHEN_PENALTY: i32 = 50;
+// Used when the paragraph can't be broken at all.
 const STRETCH_TOLERANCE: f32 = 1.26;
+// Used when the paragraph can't be broken within the normal tolerance, before falling back to
+// the greedy algorithm.
+const LOOSE_STRETCH_TOLERANCE: f32 = 4.0;
+// The characters that can hang into the right margin at the end of a justified line.
+const HANGING_PUNCTUATION: &str = ".,:;-\u{2010}\u{2019}\u{201D}\u{00BB}\"'";
 
 pub type Page = Vec<DrawCommand>;
 
@@ -99,12 +118,19 @@ pub struct Engine {
   pub font_size: f32,
   // Text alignment.
   pub text_align: TextAlign,
+  // Which alignments of the document give way to the above.
+  pub text_align_override: TextAlignOverride,
   // Line height in ems.
   pub line_height: f32,
   // Page dimensions in pixels.
   pub dims: (u32, u32),
   // Device DPI.
   pub dpi: u16,
+  // How the gray levels of the images are reduced.
+  pub dithering: DitherMode,
+  pub typography: TypographySettings,
+  // Keep the line breaks and the indentation of every block.
+  pub verse_layout: bool,
 }
 
 impl Engine {
@@ -117,9 +143,13 @@ impl Engine {
       margin,
       font_size: DEFAULT_FONT_SIZE,
       text_align: DEFAULT_TEXT_ALIGN,
+      text_align_override: TextAlignOverride::Document,
       line_height,
       dims: (DEFAULT_WIDTH, DEFAULT_HEIGHT),
       dpi: DEFAULT_DPI,
+      dithering: DitherMode::None,
+      typography: TypographySettings::default(),
+      verse_layout: false,
     }
   }
 
@@ -149,8 +179,12 @@ impl Engine {
     self.text_align = text_align;
   }
 
-  pub fn set_font_family(&mut self, family_name: &str, search_path: &str) {
-    if let Ok(serif_family) = FontFamily::from_name(family_name, search_path) {
+  pub fn set_text_align_override(&mut self, value: TextAlignOverride) {
+    self.text_align_override = value;
+  }
+
+  pub fn set_font_family(&mut self, files: &FamilyFiles) {
+    if let Ok(serif_family) = FontFamily::from_files(files) {
       self.load_fonts();
       if let Some(fonts) = self.fonts.as_mut() {
         fonts.serif = serif_family;
@@ -164,10 +198,32 @@ impl Engine {
     }
   }
 
+  pub fn set_margins(&mut self, margins: &PageMargins) {
+    let to_px = |width: i32| mm_to_px(width.max(0).min(10) as f32, self.dpi).round() as i32;
+    self.margin = Edge {
+      top: to_px(margins.top),
+      right: to_px(margins.right),
+      bottom: to_px(margins.bottom),
+      left: to_px(margins.left),
+    };
+  }
+
+  pub fn set_dithering(&mut self, dithering: DitherMode) {
+    self.dithering = dithering;
+  }
+
   pub fn set_line_height(&mut self, line_height: f32) {
     self.line_height = line_height;
   }
 
+  pub fn set_typography(&mut self, typography: TypographySettings) {
+    self.typography = typography;
+  }
+
+  pub fn set_verse_layout(&mut self, enable: bool) {
+    self.verse_layout = enable;
+  }
+
   #[inline]
   pub fn rect(&self) -> Rectangle {
     let (width, height) = self.dims;
@@ -235,6 +291,17 @@ impl Engine {
       _ => (),
     }
 
+    if let Some(retain) = props
+      .get("white-space")
+      .and_then(|value| parse_white_space(value))
+    {
+      style.retain_whitespace = retain;
+    }
+
+    if self.verse_layout || is_verse(node) {
+      style.retain_whitespace = true;
+    }
+
     style.language = props
       .get("lang")
       .cloned()
@@ -311,6 +378,20 @@ impl Engine {
       .and_then(|value| parse_text_align(value))
       .unwrap_or(parent_style.text_align);
 
+    style.text_align = match self.text_align_override {
+      TextAlignOverride::Document => style.text_align,
+      TextAlignOverride::BodyText => match style.text_align {
+        TextAlign::Justify | TextAlign::Left => self.text_align,
+        _ => style.text_align,
+      },
+      TextAlignOverride::Everything => self.text_align,
+    };
+
+    // Justifying the lines of a poem or a code sample would only stretch them.
+    if style.retain_whitespace && style.text_align == TextAlign::Justify {
+      style.text_align = TextAlign::Left;
+    }
+
     style.font_features = props
       .get("font-feature-settings")
       .map(|value| parse_font_features(value))
@@ -644,6 +725,12 @@ impl Engine {
           if node.id().is_some() {
             markers.push(node.offset());
           }
+          // A formula is laid out as a whole.
+          let children = if node.tag_name() == Some("math") {
+            slice::from_ref(node)
+          } else {
+            children.as_slice()
+          };
           let mut sibling = None;
           for child in children {
             self.gather_inline_material(
@@ -822,7 +909,10 @@ impl Engine {
         style.font_style = parent_style.font_style;
         style.line_height = parent_style.line_height;
         style.text_indent = parent_style.text_indent;
-        style.retain_whitespace = parent_style.retain_whitespace;
+        style.retain_whitespace = props
+          .get("white-space")
+          .and_then(|value| parse_white_space(value))
+          .unwrap_or(parent_style.retain_whitespace);
         style.language = parent_style.language.clone();
         style.uri = parent_style.uri.clone();
 
@@ -964,6 +1054,11 @@ impl Engine {
             inlines.push(InlineMaterial::LineBreak);
             return;
           },
+          "math" => {
+            let display = node.attr("display") == Some("block") || style.display == Display::Block;
+            push_math(*offset, from_mathml(node), display, &style, inlines);
+            return;
+          },
           _ => {},
         }
 
@@ -997,35 +1092,18 @@ impl Engine {
         }
       },
       Node::Text(TextData { offset, text }) => {
-        let mut index = 0;
-        while let Some(start_delta) = text[index..].find('&') {
-          if start_delta > 0 {
-            inlines.push(InlineMaterial::Text(TextMaterial {
-              offset: *offset + index,
-              text: text[index..index + start_delta].to_string(),
-              style: parent_style.clone(),
-            }));
-          }
-          index += start_delta;
-          if let Some(end_delta) = text[index..].find(';') {
-            inlines.push(InlineMaterial::Text(TextMaterial {
-              offset: *offset + index,
-              text: decode_entities(&text[index..=index + end_delta]).into_owned(),
-              style: parent_style.clone(),
-            }));
-            index += end_delta + 1;
+        if parent_style.retain_whitespace {
+          self.gather_text(*offset, text, parent_style, inlines);
+          return;
+        }
+        for (index, chunk, display) in split_latex(text) {
+          if let Some(display) = display {
+            let formula = parse_latex(&decode_entities(chunk));
+            push_math(*offset + index, formula, display, parent_style, inlines);
           } else {
-            break;
+            self.gather_text(*offset + index, chunk, parent_style, inlines);
           }
         }
-        if index < text.len() {
-          inlines.push(InlineMaterial::Text(TextMaterial {
-            offset: *offset + index,
-            text: text[index..].to_string(),
-            style: parent_style.clone(),
-          }));
-        }
-        return;
       },
       Node::Whitespace(TextData { offset, text }) => {
         inlines.push(InlineMaterial::Text(TextMaterial {
@@ -1037,6 +1115,43 @@ impl Engine {
     }
   }
 
+  fn gather_text(
+    &self,
+    offset: usize,
+    text: &str,
+    parent_style: &StyleData,
+    inlines: &mut Vec<InlineMaterial>,
+  ) {
+    let mut index = 0;
+    while let Some(start_delta) = text[index..].find('&') {
+      if start_delta > 0 {
+        inlines.push(InlineMaterial::Text(TextMaterial {
+          offset: offset + index,
+          text: text[index..index + start_delta].to_string(),
+          style: parent_style.clone(),
+        }));
+      }
+      index += start_delta;
+      if let Some(end_delta) = text[index..].find(';') {
+        inlines.push(InlineMaterial::Text(TextMaterial {
+          offset: offset + index,
+          text: decode_entities(&text[index..=index + end_delta]).into_owned(),
+          style: parent_style.clone(),
+        }));
+        index += end_delta + 1;
+      } else {
+        break;
+      }
+    }
+    if index < text.len() {
+      inlines.push(InlineMaterial::Text(TextMaterial {
+        offset: offset + index,
+        text: text[index..].to_string(),
+        style: parent_style.clone(),
+      }));
+    }
+  }
+
   fn make_paragraph_items(
     &mut self,
     inlines: &[InlineMaterial],
@@ -1124,6 +1239,27 @@ impl Engine {
             }
           }
         },
+        InlineMaterial::Math(MathMaterial {
+          offset,
+          formula,
+          display,
+          style,
+        }) => {
+          last_c = None;
+          let font_size = (style.font_size * 64.0) as u32;
+          let layout = MathLayout::new(self.fonts.as_mut().unwrap(), self.dpi, font_size)
+            .layout(formula, *display);
+          items.push(ParagraphItem::Box {
+            width: layout.width,
+            data: ParagraphElement::Math(MathElement {
+              offset: *offset,
+              layout,
+              display: *display,
+              color: style.color,
+              uri: style.uri.clone(),
+            }),
+          });
+        },
         InlineMaterial::Text(TextMaterial {
           offset,
           text,
@@ -1501,7 +1637,7 @@ impl Engine {
     };
 
     let stretch_tolerance = if style.text_align == TextAlign::Justify {
-      STRETCH_TOLERANCE
+      self.typography.stretch_tolerance
     } else {
       10.0
     };
@@ -1531,6 +1667,9 @@ impl Engine {
 
     let (mut items, floats) =
       self.make_paragraph_items(inlines, style, line_width, resource_fetcher);
+    if self.typography.hanging_punctuation && style.text_align == TextAlign::Justify {
+      items = self.hang_punctuation(items);
+    }
     let page_index = display_list.len();
 
     for mut element in floats.into_iter() {
@@ -1654,6 +1793,22 @@ impl Engine {
       // Insert optional breaks.
       items = self.insert_breaks(dictionary, items, &mut hyph_indices);
       bps = total_fit(&items, &line_lengths, stretch_tolerance, 0);
+
+      let max_lines = self.typography.max_hyphenated_lines;
+      if max_lines > 0 && !bps.is_empty() && !hyph_indices.is_empty() {
+        limit_hyphenated_lines(
+          &mut items,
+          &mut bps,
+          &line_lengths,
+          stretch_tolerance,
+          max_lines,
+        );
+      }
+    }
+
+    if bps.is_empty() && style.text_align == TextAlign::Justify {
+      let tolerance = LOOSE_STRETCH_TOLERANCE.max(stretch_tolerance);
+      bps = total_fit(&items, &line_lengths, tolerance, 0);
     }
 
     if bps.is_empty() {
@@ -1717,6 +1872,24 @@ impl Engine {
     let mut is_first_line = true;
     let mut j = 0;
 
+    let lines_count = bps.len();
+    let keep_lines =
+      self.typography.widow_orphan_control && !draw_state.floats.contains_key(&page_index);
+    let page_top = root_data.rect.min.y + space_top;
+    let page_bottom = root_data.rect.max.y - space_bottom;
+
+    // Don't leave the first line of the paragraph alone at the bottom of the page.
+    if keep_lines
+      && lines_count > 1
+      && position.y > page_top
+      && position.y + style.line_height > page_bottom
+    {
+      rects.push(page_rect.take());
+      display_list.push(page);
+      position.y = page_top;
+      page = Vec::new();
+    }
+
     if let Some(prefix) = draw_state.prefix.as_ref() {
       let font_size = (style.font_size * 64.0) as u32;
       let prefix_plan = {
@@ -1762,6 +1935,19 @@ impl Engine {
         glue_drifts[j]
       };
 
+      // Don't leave the last line of the paragraph alone at the top of the next page.
+      if keep_lines
+        && j >= 2
+        && lines_count - j == 2
+        && position.y > page_top
+        && position.y + style.line_height > page_bottom
+      {
+        rects.push(page_rect.take());
+        display_list.push(page);
+        position.y = page_top;
+        page = Vec::new();
+      }
+
       let (start_x, end_x) = para_shape[j.min(para_shape.len() - 1)];
 
       let Breakpoint {
@@ -1916,6 +2102,80 @@ impl Engine {
                   uri: element.uri.clone(),
                 }));
               },
+              ParagraphElement::Math(element) => {
+                while let Some(offset) = markers.get(markers_index) {
+                  if *offset < element.offset {
+                    page.push(DrawCommand::Marker(root_data.start_offset + *offset));
+                    markers_index += 1;
+                  } else {
+                    break;
+                  }
+                }
+                let layout = &element.layout;
+                // Make room for the parts of a displayed formula that exceed the line.
+                let rise = (layout.ascent - space_top).max(0);
+                let sink = (layout.descent - space_bottom).max(0);
+                if element.display {
+                  if position.y > page_top && position.y + rise + sink > page_bottom {
+                    rects.push(page_rect.take());
+                    display_list.push(page);
+                    position.y = page_top;
+                    page = Vec::new();
+                  }
+                  position.y += rise;
+                  position.x = start_x + (end_x - start_x - layout.width).max(0) / 2;
+                }
+                let origin = pt!(position.x, position.y - style.vertical_align);
+                let offset = element.offset + root_data.start_offset;
+                for item in &layout.items {
+                  let (command, rect) = match item {
+                    MathItem::Glyphs {
+                      position: item_position,
+                      text,
+                      plan,
+                      font_style,
+                      font_size,
+                      ascent,
+                      descent,
+                    } => {
+                      let pt = origin + *item_position;
+                      let rect = rect![pt + pt!(0, -ascent), pt + pt!(plan.width, *descent)];
+                      let command = DrawCommand::Text(TextCommand {
+                        offset,
+                        position: pt,
+                        rect,
+                        text: text.clone(),
+                        plan: plan.clone(),
+                        uri: element.uri.clone(),
+                        font_kind: FontKind::Serif,
+                        font_style: *font_style,
+                        font_weight: FontWeight::Normal,
+                        font_size: *font_size,
+                        color: element.color,
+                      });
+                      (command, rect)
+                    },
+                    MathItem::Rule(rect) => {
+                      let rect = *rect + origin;
+                      let command = DrawCommand::Rule(RuleCommand {
+                        offset,
+                        color: element.color,
+                        rect,
+                      });
+                      (command, rect)
+                    },
+                  };
+                  if let Some(pr) = page_rect.as_mut() {
+                    pr.absorb(&rect);
+                  } else {
+                    page_rect = Some(rect);
+                  }
+                  page.push(command);
+                }
+                if element.display {
+                  position.y += sink;
+                }
+              },
               _ => (),
             }
 
@@ -2012,6 +2272,61 @@ impl Engine {
     display_list.push(page);
   }
 
+  // Lets the punctuation that ends a word hang into the right margin when the line is broken
+  // after it. The glue that follows the word is then replaced by a penalty of negative width.
+  fn hang_punctuation(
+    &mut self,
+    items: Vec<ParagraphItem<ParagraphElement>>,
+  ) -> Vec<ParagraphItem<ParagraphElement>> {
+    let mut hung_items = Vec::with_capacity(items.len());
+    let mut iter = items.into_iter().peekable();
+
+    while let Some(itm) = iter.next() {
+      let hang_width = match (&itm, iter.peek()) {
+        (
+          ParagraphItem::Box {
+            data: ParagraphElement::Text(element),
+            ..
+          },
+          Some(ParagraphItem::Glue { stretch, .. }),
+        ) if *stretch > 0 => element
+          .text
+          .chars()
+          .next_back()
+          .filter(|c| HANGING_PUNCTUATION.contains(*c))
+          .map(|c| {
+            let font = self.fonts.as_mut().unwrap().get_mut(
+              element.font_kind,
+              element.font_style,
+              element.font_weight,
+            );
+            font.set_size(element.font_size, self.dpi);
+            let mut buf = [0; 4];
+            font
+              .plan(
+                c.encode_utf8(&mut buf),
+                None,
+                element.font_features.as_ref().map(Vec::as_slice),
+              )
+              .width
+          }),
+        _ => None,
+      };
+
+      hung_items.push(itm);
+
+      if let Some(width) = hang_width {
+        hung_items.push(ParagraphItem::Penalty {
+          width: -width,
+          penalty: 0,
+          flagged: false,
+        });
+      }
+    }
+
+    hung_items
+  }
+
   #[inline]
   fn box_from_chunk(
     &mut self,
@@ -2103,17 +2418,22 @@ impl Engine {
                 let mut index = 0;
                 let subchunk = &chunk[index_before..index_after];
                 let len_before = hyph_items.len();
-                for segment in dict.hyphenate(subchunk).iter().segments() {
-                  let subelem =
-                    self.box_from_chunk(segment, start_index + index_before + index, &element);
+                if subchunk.chars().count() < self.typography.min_hyphenated_length {
+                  let subelem = self.box_from_chunk(subchunk, start_index + index_before, &element);
                   hyph_items.push(subelem);
-                  index += segment.len();
-                  if index < subchunk.len() {
-                    hyph_items.push(ParagraphItem::Penalty {
-                      width: hyphen_width,
-                      penalty: HYPHEN_PENALTY,
-                      flagged: true,
-                    });
+                } else {
+                  for segment in dict.hyphenate(subchunk).iter().segments() {
+                    let subelem =
+                      self.box_from_chunk(segment, start_index + index_before + index, &element);
+                    hyph_items.push(subelem);
+                    index += segment.len();
+                    if index < subchunk.len() {
+                      hyph_items.push(ParagraphItem::Penalty {
+                        width: hyphen_width,
+                        penalty: HYPHEN_PENALTY,
+                        flagged: true,
+                      });
+                    }
                   }
                 }
                 let len_after = hyph_items.len();
@@ -2345,17 +2665,22 @@ impl Engine {
           font.set_size(*font_size, self.dpi);
           font.render(&mut fb, *color, plan, *position);
         },
+        DrawCommand::Rule(RuleCommand { rect, color, .. }) => {
+          fb.draw_rectangle(rect, *color);
+        },
         DrawCommand::Image(ImageCommand {
           position,
           path,
           scale,
           ..
         }) => {
+          let dithering = self.dithering;
           if let Ok(buf) = resource_fetcher.fetch(path) {
             PdfOpener::new()
               .and_then(|opener| opener.open_memory(path, &buf))
               .and_then(|mut doc| doc.pixmap(Location::Exact(0), *scale))
-              .map(|(pixmap, _)| {
+              .map(|(mut pixmap, _)| {
+                dither(&mut pixmap, dithering);
                 fb.draw_pixmap(&pixmap, *position);
               });
           }
@@ -2368,6 +2693,98 @@ impl Engine {
   }
 }
 
+// Forbids, one at a time, the optional hyphens that end more than `max_lines` consecutive lines,
+// as long as the paragraph can still be broken.
+fn limit_hyphenated_lines(
+  items: &mut [ParagraphItem<ParagraphElement>],
+  bps: &mut Vec<Breakpoint>,
+  line_lengths: &[i32],
+  stretch_tolerance: f32,
+  max_lines: usize,
+) {
+  for _ in 0..bps.len() {
+    let mut run = 0;
+    let mut culprit = None;
+    for bp in bps.iter() {
+      if items[bp.index].flagged() {
+        run += 1;
+      } else {
+        run = 0;
+      }
+      if run > max_lines && items[bp.index].width() > 0 {
+        culprit = Some(bp.index);
+        break;
+      }
+    }
+
+    let index = match culprit {
+      Some(index) => index,
+      None => break,
+    };
+
+    let width = items[index].width();
+    let penalty = items[index].penalty();
+    items[index] = ParagraphItem::Penalty {
+      width,
+      penalty: INFINITE_PENALTY,
+      flagged: true,
+    };
+
+    let next_bps = total_fit(items, line_lengths, stretch_tolerance, 0);
+    if next_bps.is_empty() {
+      items[index] = ParagraphItem::Penalty {
+        width,
+        penalty,
+        flagged: true,
+      };
+      break;
+    }
+    *bps = next_bps;
+  }
+}
+
+fn push_math(
+  offset: usize,
+  formula: MathNode,
+  display: bool,
+  style: &StyleData,
+  inlines: &mut Vec<InlineMaterial>,
+) {
+  if display {
+    inlines.push(InlineMaterial::LineBreak);
+  }
+  inlines.push(InlineMaterial::Math(MathMaterial {
+    offset,
+    formula,
+    display,
+    style: style.clone(),
+  }));
+  if display {
+    inlines.push(InlineMaterial::LineBreak);
+  }
+}
+
+// Poems, songs and code samples, as marked up by the common conventions.
+fn is_verse(node: &Node) -> bool {
+  let is_verse_class = |name: &str| {
+    let name = name.to_lowercase();
+    ["poem", "poetry", "verse", "stanza", "lyrics", "code"]
+      .iter()
+      .any(|kind| name.contains(kind))
+  };
+  node
+    .classes()
+    .map_or(false, |classes| classes.into_iter().any(is_verse_class))
+    || node.attr("epub:type").map_or(false, |value| {
+      value.split_whitespace().any(|kind| {
+        matches!(
+          kind,
+          "z3998:poem" | "z3998:verse" | "z3998:song" | "z3998:lyrics"
+        )
+      })
+    })
+}
+
 fn format_list_prefix(kind: ListStyleType, index: usize) -> Option<String> {
   match kind {
     ListStyleType::None => None,
@@ -1,4 +1,7 @@
-use super::dom::Node;
+use super::{
+  dom::Node,
+  math::{MathBox, MathNode},
+};
 pub use crate::metadata::TextAlign;
 use crate::{
   color::BLACK,
@@ -201,6 +204,7 @@ impl Default for StyleData {
 pub enum InlineMaterial {
   Text(TextMaterial),
   Image(ImageMaterial),
+  Math(MathMaterial),
   Glue(GlueMaterial),
   Penalty(PenaltyMaterial),
   Box(i32),
@@ -211,7 +215,8 @@ impl InlineMaterial {
   pub fn offset(&self) -> Option<usize> {
     match self {
       InlineMaterial::Text(TextMaterial { offset, .. })
-      | InlineMaterial::Image(ImageMaterial { offset, .. }) => Some(*offset),
+      | InlineMaterial::Image(ImageMaterial { offset, .. })
+      | InlineMaterial::Math(MathMaterial { offset, .. }) => Some(*offset),
       _ => None,
     }
   }
@@ -231,6 +236,15 @@ pub struct ImageMaterial {
   pub style: StyleData,
 }
 
+#[derive(Debug, Clone)]
+pub struct MathMaterial {
+  pub offset: usize,
+  pub formula: MathNode,
+  // Whether the formula is displayed on its own line.
+  pub display: bool,
+  pub style: StyleData,
+}
+
 #[derive(Debug, Clone)]
 pub struct GlueMaterial {
   pub width: i32,
@@ -310,6 +324,7 @@ impl Fonts {
 pub enum ParagraphElement {
   Text(TextElement),
   Image(ImageElement),
+  Math(MathElement),
   Nothing,
 }
 
@@ -344,11 +359,21 @@ pub struct ImageElement {
   pub uri: Option<String>,
 }
 
+#[derive(Debug, Clone)]
+pub struct MathElement {
+  pub offset: usize,
+  pub layout: MathBox,
+  pub display: bool,
+  pub color: u8,
+  pub uri: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub enum DrawCommand {
   Text(TextCommand),
   ExtraText(TextCommand),
   Image(ImageCommand),
+  Rule(RuleCommand),
   Marker(usize),
 }
 
@@ -377,12 +402,21 @@ pub struct ImageCommand {
   pub rect: Rectangle,
 }
 
+// The bars of the fractions and the radicals.
+#[derive(Debug, Clone)]
+pub struct RuleCommand {
+  pub offset: usize,
+  pub color: u8,
+  pub rect: Rectangle,
+}
+
 impl DrawCommand {
   pub fn offset(&self) -> usize {
     match *self {
       DrawCommand::Text(TextCommand { offset, .. }) => offset,
       DrawCommand::ExtraText(TextCommand { offset, .. }) => offset,
       DrawCommand::Image(ImageCommand { offset, .. }) => offset,
+      DrawCommand::Rule(RuleCommand { offset, .. }) => offset,
       DrawCommand::Marker(offset) => offset,
     }
   }
@@ -392,6 +426,7 @@ impl DrawCommand {
       DrawCommand::Text(TextCommand { rect, .. }) => Some(rect),
       DrawCommand::ExtraText(TextCommand { rect, .. }) => Some(rect),
       DrawCommand::Image(ImageCommand { rect, .. }) => Some(rect),
+      DrawCommand::Rule(RuleCommand { rect, .. }) => Some(rect),
       _ => None,
     }
   }
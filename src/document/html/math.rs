@@ -0,0 +1,1476 @@
+//! Lays out the formulas written in MathML or in LaTeX with the glyphs of the serif font.
+
+use super::{
+  dom::{ElementData, Node, TextData},
+  layout::{FontKind, FontStyle, FontWeight, Fonts},
+};
+use crate::{
+  font::RenderPlan,
+  geom::{Point, Rectangle},
+  helpers::decode_entities,
+  unit::pt_to_px,
+};
+use std::mem;
+
+const THIN_SPACE: f32 = 1.0 / 6.0;
+const MEDIUM_SPACE: f32 = 2.0 / 9.0;
+const THICK_SPACE: f32 = 5.0 / 18.0;
+// The height of the fraction bars and of the center of the fences, in ems.
+const AXIS_HEIGHT: f32 = 0.25;
+// The largest scale factor of the stretched fences and radicals.
+const MAX_STRETCH: f32 = 4.0;
+
+const OPEN_FENCES: &str = "([{⟨⌊⌈|‖";
+const CLOSE_FENCES: &str = ")]}⟩⌋⌉|‖";
+const LARGE_OPERATORS: &str = "∑∏∐⋃⋂⨁⨂⋁⋀∫∬∭∮";
+const INTEGRALS: &str = "∫∬∭∮";
+const RELATIONS: &str = "=<>≤≥≠≈≡∼≃≅∝≪≫⊂⊃⊆⊇∈∉∋⊥∥∣→←↔⇒⇐⇔⟹⟸⟺↦⟶⟵↑↓:≔";
+const BINARY_OPERATORS: &str = "+−±∓×÷⋅∗⋆∘∙⊕⊗⊖∩∪∧∨∖";
+const LIMIT_FUNCTIONS: [&str; 9] = [
+  "lim", "liminf", "limsup", "max", "min", "sup", "inf", "det", "Pr",
+];
+const FUNCTIONS: [&str; 30] = [
+  "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+  "coth", "log", "ln", "lg", "exp", "dim", "ker", "deg", "gcd", "hom", "arg", "lim", "liminf",
+  "limsup", "max", "min", "sup", "inf",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MathNode {
+  Identifier(String),
+  Number(String),
+  Operator(String),
+  Text(String),
+  Row(Vec<MathNode>),
+  Scripts {
+    base: Box<MathNode>,
+    sub: Option<Box<MathNode>>,
+    sup: Option<Box<MathNode>>,
+  },
+  Limits {
+    base: Box<MathNode>,
+    under: Option<Box<MathNode>>,
+    over: Option<Box<MathNode>>,
+  },
+  Fraction(Box<MathNode>, Box<MathNode>),
+  Root {
+    body: Box<MathNode>,
+    index: Option<Box<MathNode>>,
+  },
+  Fenced {
+    open: String,
+    close: String,
+    body: Box<MathNode>,
+  },
+  Table(Vec<Vec<MathNode>>),
+  // A horizontal space, in ems.
+  Space(f32),
+}
+
+fn empty() -> MathNode {
+  MathNode::Row(Vec::new())
+}
+
+// Groups the given nodes, and recognizes the fences that enclose them.
+fn row(mut nodes: Vec<MathNode>) -> MathNode {
+  if nodes.len() == 1 {
+    return nodes.pop().unwrap();
+  }
+  if nodes.len() > 2 && is_enclosed(&nodes) {
+    let close = nodes.pop();
+    let open = nodes.remove(0);
+    if let (Some(MathNode::Operator(open)), Some(MathNode::Operator(close))) = (Some(open), close) {
+      return MathNode::Fenced {
+        open,
+        close,
+        body: Box::new(row(nodes)),
+      };
+    }
+  }
+  MathNode::Row(nodes)
+}
+
+// Whether the first node is a fence that is closed by the last one.
+fn is_enclosed(nodes: &[MathNode]) -> bool {
+  if !is_symbol_of(&nodes[0], OPEN_FENCES) || !is_symbol_of(&nodes[nodes.len() - 1], CLOSE_FENCES) {
+    return false;
+  }
+  let mut depth = 0;
+  for (i, node) in nodes.iter().enumerate() {
+    if is_symbol_of(node, OPEN_FENCES) && !(depth > 0 && is_symbol_of(node, CLOSE_FENCES)) {
+      depth += 1;
+    } else if is_symbol_of(node, CLOSE_FENCES) {
+      depth -= 1;
+      if depth == 0 && i < nodes.len() - 1 {
+        return false;
+      }
+    }
+  }
+  depth == 0
+}
+
+// Whether the node is an operator made of one of the given symbols.
+fn is_symbol_of(node: &MathNode, symbols: &str) -> bool {
+  match node {
+    MathNode::Operator(s) => s.chars().count() == 1 && symbols.contains(s.as_str()),
+    _ => false,
+  }
+}
+
+fn is_large_operator(node: &MathNode) -> bool {
+  is_symbol_of(node, LARGE_OPERATORS)
+}
+
+// Whether the scripts of the given node go above and below it in display mode.
+fn has_limits(node: &MathNode) -> bool {
+  match node {
+    MathNode::Operator(s) => is_large_operator(node) && !INTEGRALS.contains(s.as_str()),
+    MathNode::Identifier(s) => LIMIT_FUNCTIONS.contains(&s.as_str()),
+    _ => false,
+  }
+}
+
+fn text_content(node: &Node, buf: &mut String) {
+  match node {
+    Node::Element(ElementData { children, .. }) => {
+      for child in children {
+        text_content(child, buf);
+      }
+    },
+    Node::Text(TextData { text, .. }) | Node::Whitespace(TextData { text, .. }) => {
+      buf.push_str(text);
+    },
+  }
+}
+
+pub fn from_mathml(node: &Node) -> MathNode {
+  let (name, children) = match node {
+    Node::Element(ElementData { name, children, .. }) => (name.as_str(), children),
+    _ => return empty(),
+  };
+  let text = || {
+    let mut buf = String::new();
+    text_content(node, &mut buf);
+    decode_entities(buf.trim()).into_owned()
+  };
+  let mut args = children
+    .iter()
+    .filter(|child| matches!(child, Node::Element(..)))
+    .map(from_mathml);
+  let mut next = || Box::new(args.next().unwrap_or_else(empty));
+
+  match name {
+    "mi" => MathNode::Identifier(text()),
+    "mn" => MathNode::Number(text()),
+    "mo" => {
+      let text = text();
+      MathNode::Operator(if text == "-" { "−".to_string() } else { text })
+    },
+    "mtext" | "ms" => MathNode::Text(text()),
+    "mspace" => MathNode::Space(
+      node
+        .attr("width")
+        .and_then(|w| w.trim_end_matches("em").parse().ok())
+        .unwrap_or(0.0),
+    ),
+    "msup" => MathNode::Scripts {
+      base: next(),
+      sub: None,
+      sup: Some(next()),
+    },
+    "msub" => MathNode::Scripts {
+      base: next(),
+      sub: Some(next()),
+      sup: None,
+    },
+    "msubsup" | "mmultiscripts" => MathNode::Scripts {
+      base: next(),
+      sub: Some(next()),
+      sup: Some(next()),
+    },
+    "mover" => MathNode::Limits {
+      base: next(),
+      under: None,
+      over: Some(next()),
+    },
+    "munder" => MathNode::Limits {
+      base: next(),
+      under: Some(next()),
+      over: None,
+    },
+    "munderover" => MathNode::Limits {
+      base: next(),
+      under: Some(next()),
+      over: Some(next()),
+    },
+    "mfrac" => MathNode::Fraction(next(), next()),
+    "msqrt" => MathNode::Root {
+      body: Box::new(row(args.collect())),
+      index: None,
+    },
+    "mroot" => MathNode::Root {
+      body: next(),
+      index: Some(next()),
+    },
+    "mfenced" => {
+      let separator = node.attr("separators").unwrap_or(",").trim().to_string();
+      let mut nodes = Vec::new();
+      for (i, arg) in args.enumerate() {
+        if i > 0 && !separator.is_empty() {
+          nodes.push(MathNode::Operator(separator.clone()));
+        }
+        nodes.push(arg);
+      }
+      MathNode::Fenced {
+        open: node.attr("open").unwrap_or("(").to_string(),
+        close: node.attr("close").unwrap_or(")").to_string(),
+        body: Box::new(row(nodes)),
+      }
+    },
+    "mtable" => MathNode::Table(
+      children
+        .iter()
+        .filter_map(|tr| tr.children())
+        .map(|cells| {
+          cells
+            .iter()
+            .filter(|td| matches!(td, Node::Element(..)))
+            .map(from_mathml)
+            .collect()
+        })
+        .collect(),
+    ),
+    "semantics" => *next(),
+    "annotation" | "annotation-xml" | "mprescripts" | "none" | "mphantom" => empty(),
+    _ => row(args.collect()),
+  }
+}
+
+// Splits the given text into the plain parts and the formulas delimited by `\(…\)` (inline), or
+// `\[…\]` and `$$…$$` (display). Returns the offsets of the parts and whether they're displayed
+// formulas.
+pub fn split_latex(text: &str) -> Vec<(usize, &str, Option<bool>)> {
+  const DELIMITERS: [(&str, &str, bool); 3] = [
+    ("\\(", "\\)", false),
+    ("\\[", "\\]", true),
+    ("$$", "$$", true),
+  ];
+  let mut chunks = Vec::new();
+  let mut index = 0;
+
+  while let Some((start, open, close, display)) = DELIMITERS
+    .iter()
+    .filter_map(|&(open, close, display)| {
+      text[index..]
+        .find(open)
+        .map(|i| (index + i, open, close, display))
+    })
+    .min_by_key(|&(start, ..)| start)
+  {
+    let body_start = start + open.len();
+    let end = match text[body_start..].find(close) {
+      Some(i) => body_start + i,
+      None => break,
+    };
+    if start > index {
+      chunks.push((index, &text[index..start], None));
+    }
+    chunks.push((body_start, &text[body_start..end], Some(display)));
+    index = end + close.len();
+  }
+
+  if index < text.len() {
+    chunks.push((index, &text[index..], None));
+  }
+
+  chunks
+}
+
+pub fn parse_latex(source: &str) -> MathNode {
+  let mut parser = LatexParser {
+    chars: source.chars().collect(),
+    pos: 0,
+  };
+  let mut nodes = Vec::new();
+  loop {
+    nodes.push(parser.table());
+    if parser.pos >= parser.chars.len() {
+      break;
+    }
+    parser.skip_stray();
+  }
+  row(nodes)
+}
+
+struct LatexParser {
+  chars: Vec<char>,
+  pos: usize,
+}
+
+impl LatexParser {
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).cloned()
+  }
+
+  fn advance(&mut self) {
+    self.pos = (self.pos + 1).min(self.chars.len());
+  }
+
+  fn skip_spaces(&mut self) {
+    while self.peek().map_or(false, char::is_whitespace) {
+      self.advance();
+    }
+  }
+
+  // Reads the name of the command that follows a backslash.
+  fn read_command(&mut self) -> String {
+    let start = self.pos;
+    while self.peek().map_or(false, |c| c.is_ascii_alphabetic()) {
+      self.advance();
+    }
+    if self.pos == start && self.peek().is_some() {
+      self.advance();
+    }
+    self.chars[start..self.pos].iter().collect()
+  }
+
+  fn eat_command(&mut self, name: &str) -> bool {
+    self.skip_spaces();
+    let save = self.pos;
+    if self.peek() == Some('\\') {
+      self.advance();
+      if self.read_command() == name {
+        return true;
+      }
+    }
+    self.pos = save;
+    false
+  }
+
+  // Reads the raw content of a group, or a single character.
+  fn raw_group(&mut self) -> String {
+    self.skip_spaces();
+    if self.peek() != Some('{') {
+      let c = self.peek();
+      self.advance();
+      return c.map(String::from).unwrap_or_default();
+    }
+    self.advance();
+    let start = self.pos;
+    let mut depth = 1;
+    while let Some(c) = self.peek() {
+      if c == '{' {
+        depth += 1;
+      } else if c == '}' {
+        depth -= 1;
+        if depth == 0 {
+          break;
+        }
+      }
+      self.advance();
+    }
+    let text = self.chars[start..self.pos].iter().collect();
+    self.advance();
+    text
+  }
+
+  // Skips a token that ends a group that wasn't opened.
+  fn skip_stray(&mut self) {
+    match self.peek() {
+      Some('\\') => {
+        self.advance();
+        match self.read_command().as_str() {
+          "right" => {
+            self.delimiter();
+          },
+          "end" => {
+            self.raw_group();
+          },
+          _ => (),
+        }
+      },
+      Some(_) => self.advance(),
+      None => (),
+    }
+  }
+
+  fn list(&mut self) -> Vec<MathNode> {
+    let mut nodes = Vec::new();
+    loop {
+      self.skip_spaces();
+      let c = match self.peek() {
+        Some(c) => c,
+        None => break,
+      };
+      match c {
+        '}' | '&' => break,
+        '\\' => {
+          let save = self.pos;
+          self.advance();
+          let name = self.read_command();
+          if matches!(name.as_str(), "right" | "end" | "\\" | "cr") {
+            self.pos = save;
+            break;
+          }
+          if let Some(node) = self.command(&name) {
+            nodes.push(node);
+          }
+        },
+        '^' | '_' | '\'' => {
+          self.advance();
+          let arg = if c == '\'' {
+            MathNode::Operator("′".to_string())
+          } else {
+            self.argument()
+          };
+          let base = nodes.pop().unwrap_or_else(empty);
+          nodes.push(attach(base, c != '_', arg));
+        },
+        _ => {
+          if let Some(node) = self.atom(false) {
+            nodes.push(node);
+          }
+        },
+      }
+    }
+    nodes
+  }
+
+  // Reads the rows and cells separated by `\\` and `&`.
+  fn table(&mut self) -> MathNode {
+    let mut rows = Vec::new();
+    let mut cells = Vec::new();
+    loop {
+      let nodes = self.list();
+      cells.push(row(nodes));
+      match self.peek() {
+        Some('&') => self.advance(),
+        Some('\\') => {
+          let save = self.pos;
+          self.advance();
+          let name = self.read_command();
+          if name == "\\" || name == "cr" {
+            rows.push(mem::take(&mut cells));
+            self.skip_spaces();
+            if self.peek() == Some('[') {
+              while self.peek().map_or(false, |c| c != ']') {
+                self.advance();
+              }
+              self.advance();
+            }
+          } else {
+            self.pos = save;
+            break;
+          }
+        },
+        _ => break,
+      }
+    }
+    if !(cells.len() == 1 && cells[0] == empty()) || rows.is_empty() {
+      rows.push(cells);
+    }
+    if rows.len() == 1 && rows[0].len() == 1 {
+      rows
+        .pop()
+        .and_then(|mut cells| cells.pop())
+        .unwrap_or_else(empty)
+    } else {
+      MathNode::Table(rows)
+    }
+  }
+
+  fn argument(&mut self) -> MathNode {
+    self.atom(true).unwrap_or_else(empty)
+  }
+
+  fn atom(&mut self, single: bool) -> Option<MathNode> {
+    self.skip_spaces();
+    let c = self.peek()?;
+    self.advance();
+    match c {
+      '{' => {
+        let nodes = self.list();
+        if self.peek() == Some('}') {
+          self.advance();
+        }
+        Some(row(nodes))
+      },
+      '}' => None,
+      '\\' => {
+        let name = self.read_command();
+        self.command(&name)
+      },
+      '~' => Some(MathNode::Space(0.33)),
+      '-' => Some(MathNode::Operator("−".to_string())),
+      '*' => Some(MathNode::Operator("∗".to_string())),
+      _ if c.is_ascii_digit() => {
+        let mut number = c.to_string();
+        while let Some(d) = self
+          .peek()
+          .filter(|d| !single && (d.is_ascii_digit() || *d == '.'))
+        {
+          number.push(d);
+          self.advance();
+        }
+        Some(MathNode::Number(number))
+      },
+      _ if c.is_alphabetic() => Some(MathNode::Identifier(c.to_string())),
+      _ => Some(MathNode::Operator(c.to_string())),
+    }
+  }
+
+  fn delimiter(&mut self) -> String {
+    self.skip_spaces();
+    let c = match self.peek() {
+      Some(c) => c,
+      None => return String::new(),
+    };
+    self.advance();
+    match c {
+      '.' => String::new(),
+      '\\' => match self.read_command().as_str() {
+        "{" | "lbrace" => "{".to_string(),
+        "}" | "rbrace" => "}".to_string(),
+        "langle" => "⟨".to_string(),
+        "rangle" => "⟩".to_string(),
+        "lfloor" => "⌊".to_string(),
+        "rfloor" => "⌋".to_string(),
+        "lceil" => "⌈".to_string(),
+        "rceil" => "⌉".to_string(),
+        "vert" => "|".to_string(),
+        "|" | "Vert" => "‖".to_string(),
+        _ => String::new(),
+      },
+      _ => c.to_string(),
+    }
+  }
+
+  fn environment(&mut self) -> MathNode {
+    let name = self.raw_group();
+    if name == "array" {
+      self.raw_group();
+    }
+    let table = match self.table() {
+      MathNode::Table(rows) => MathNode::Table(rows),
+      cell => MathNode::Table(vec![vec![cell]]),
+    };
+    if self.eat_command("end") {
+      self.raw_group();
+    }
+    let fences = match name.trim_end_matches('*') {
+      "pmatrix" => ("(", ")"),
+      "bmatrix" => ("[", "]"),
+      "Bmatrix" => ("{", "}"),
+      "vmatrix" => ("|", "|"),
+      "Vmatrix" => ("‖", "‖"),
+      "cases" => ("{", ""),
+      _ => return table,
+    };
+    MathNode::Fenced {
+      open: fences.0.to_string(),
+      close: fences.1.to_string(),
+      body: Box::new(table),
+    }
+  }
+
+  fn command(&mut self, name: &str) -> Option<MathNode> {
+    let accent = |parser: &mut Self, mark: &str, over: bool| {
+      let base = Box::new(parser.argument());
+      let mark = Some(Box::new(MathNode::Operator(mark.to_string())));
+      Some(if over {
+        MathNode::Limits {
+          base,
+          under: None,
+          over: mark,
+        }
+      } else {
+        MathNode::Limits {
+          base,
+          under: mark,
+          over: None,
+        }
+      })
+    };
+    match name {
+      "frac" | "dfrac" | "tfrac" | "cfrac" => {
+        let numerator = Box::new(self.argument());
+        let denominator = Box::new(self.argument());
+        Some(MathNode::Fraction(numerator, denominator))
+      },
+      "binom" | "dbinom" | "tbinom" => {
+        let top = self.argument();
+        let bottom = self.argument();
+        Some(MathNode::Fenced {
+          open: "(".to_string(),
+          close: ")".to_string(),
+          body: Box::new(MathNode::Table(vec![vec![top], vec![bottom]])),
+        })
+      },
+      "sqrt" => {
+        self.skip_spaces();
+        let index = if self.peek() == Some('[') {
+          self.advance();
+          let start = self.pos;
+          while self.peek().map_or(false, |c| c != ']') {
+            self.advance();
+          }
+          let source: String = self.chars[start..self.pos].iter().collect();
+          self.advance();
+          Some(Box::new(parse_latex(&source)))
+        } else {
+          None
+        };
+        Some(MathNode::Root {
+          body: Box::new(self.argument()),
+          index,
+        })
+      },
+      "left" => {
+        let open = self.delimiter();
+        let body = self.list();
+        let close = if self.eat_command("right") {
+          self.delimiter()
+        } else {
+          String::new()
+        };
+        Some(MathNode::Fenced {
+          open,
+          close,
+          body: Box::new(row(body)),
+        })
+      },
+      "begin" => Some(self.environment()),
+      "text" | "textrm" | "textnormal" | "textit" | "textbf" | "textsf" | "texttt" | "mbox" => {
+        Some(MathNode::Text(self.raw_group()))
+      },
+      "mathrm" | "mathup" | "operatorname" => Some(MathNode::Identifier(self.raw_group())),
+      "mathbb" => Some(MathNode::Identifier(
+        self.raw_group().chars().map(double_struck).collect(),
+      )),
+      "mathbf" | "mathit" | "mathcal" | "mathscr" | "mathfrak" | "mathsf" | "mathtt"
+      | "boldsymbol" | "bm" => Some(self.argument()),
+      "hat" | "widehat" => accent(self, "^", true),
+      "bar" | "overline" => accent(self, "‾", true),
+      "underline" => accent(self, "_", false),
+      "vec" | "overrightarrow" => accent(self, "→", true),
+      "tilde" | "widetilde" => accent(self, "~", true),
+      "dot" => accent(self, "˙", true),
+      "ddot" => accent(self, "¨", true),
+      "check" => accent(self, "ˇ", true),
+      "breve" => accent(self, "˘", true),
+      "acute" => accent(self, "´", true),
+      "grave" => accent(self, "`", true),
+      "overbrace" => accent(self, "⏞", true),
+      "underbrace" => accent(self, "⏟", false),
+      "overset" | "stackrel" | "underset" => {
+        let mark = Some(Box::new(self.argument()));
+        let base = Box::new(self.argument());
+        Some(if name == "underset" {
+          MathNode::Limits {
+            base,
+            under: mark,
+            over: None,
+          }
+        } else {
+          MathNode::Limits {
+            base,
+            under: None,
+            over: mark,
+          }
+        })
+      },
+      "," | "thinspace" => Some(MathNode::Space(THIN_SPACE)),
+      ":" | ">" | "medspace" => Some(MathNode::Space(MEDIUM_SPACE)),
+      ";" | "thickspace" => Some(MathNode::Space(THICK_SPACE)),
+      "!" => Some(MathNode::Space(-THIN_SPACE)),
+      " " => Some(MathNode::Space(0.33)),
+      "enspace" => Some(MathNode::Space(0.5)),
+      "quad" => Some(MathNode::Space(1.0)),
+      "qquad" => Some(MathNode::Space(2.0)),
+      "{" | "}" | "%" | "$" | "#" | "&" | "_" => Some(MathNode::Operator(name.to_string())),
+      "label" | "tag" => {
+        self.raw_group();
+        None
+      },
+      "displaystyle" | "textstyle" | "scriptstyle" | "limits" | "nolimits" | "nonumber"
+      | "notag" | "big" | "Big" | "bigg" | "Bigg" | "bigl" | "Bigl" | "biggl" | "Biggl"
+      | "bigr" | "Bigr" | "biggr" | "Biggr" | "bigm" | "Bigm" => None,
+      _ if FUNCTIONS.contains(&name) || LIMIT_FUNCTIONS.contains(&name) => {
+        Some(MathNode::Identifier(name.to_string()))
+      },
+      _ => Some(symbol(name).unwrap_or_else(|| MathNode::Text(format!("\\{}", name)))),
+    }
+  }
+}
+
+fn attach(base: MathNode, is_sup: bool, arg: MathNode) -> MathNode {
+  let arg = Some(Box::new(arg));
+  match base {
+    MathNode::Scripts {
+      base,
+      sub,
+      sup: None,
+    } if is_sup => MathNode::Scripts {
+      base,
+      sub,
+      sup: arg,
+    },
+    MathNode::Scripts {
+      base,
+      sub: None,
+      sup,
+    } if !is_sup => MathNode::Scripts {
+      base,
+      sub: arg,
+      sup,
+    },
+    base => {
+      let base = Box::new(base);
+      if is_sup {
+        MathNode::Scripts {
+          base,
+          sub: None,
+          sup: arg,
+        }
+      } else {
+        MathNode::Scripts {
+          base,
+          sub: arg,
+          sup: None,
+        }
+      }
+    },
+  }
+}
+
+fn double_struck(c: char) -> char {
+  match c {
+    'C' => 'ℂ',
+    'H' => 'ℍ',
+    'N' => 'ℕ',
+    'P' => 'ℙ',
+    'Q' => 'ℚ',
+    'R' => 'ℝ',
+    'Z' => 'ℤ',
+    _ => c,
+  }
+}
+
+fn symbol(name: &str) -> Option<MathNode> {
+  let letter = match name {
+    "alpha" => "α",
+    "beta" => "β",
+    "gamma" => "γ",
+    "delta" => "δ",
+    "epsilon" => "ϵ",
+    "varepsilon" => "ε",
+    "zeta" => "ζ",
+    "eta" => "η",
+    "theta" => "θ",
+    "vartheta" => "ϑ",
+    "iota" => "ι",
+    "kappa" => "κ",
+    "lambda" => "λ",
+    "mu" => "μ",
+    "nu" => "ν",
+    "xi" => "ξ",
+    "pi" => "π",
+    "varpi" => "ϖ",
+    "rho" => "ρ",
+    "varrho" => "ϱ",
+    "sigma" => "σ",
+    "varsigma" => "ς",
+    "tau" => "τ",
+    "upsilon" => "υ",
+    "phi" => "ϕ",
+    "varphi" => "φ",
+    "chi" => "χ",
+    "psi" => "ψ",
+    "omega" => "ω",
+    "Gamma" => "Γ",
+    "Delta" => "Δ",
+    "Theta" => "Θ",
+    "Lambda" => "Λ",
+    "Xi" => "Ξ",
+    "Pi" => "Π",
+    "Sigma" => "Σ",
+    "Upsilon" => "Υ",
+    "Phi" => "Φ",
+    "Psi" => "Ψ",
+    "Omega" => "Ω",
+    "ell" => "ℓ",
+    "hbar" => "ℏ",
+    "imath" => "ı",
+    _ => "",
+  };
+  if !letter.is_empty() {
+    return Some(MathNode::Identifier(letter.to_string()));
+  }
+  let operator = match name {
+    "pm" => "±",
+    "mp" => "∓",
+    "times" => "×",
+    "div" => "÷",
+    "cdot" => "⋅",
+    "ast" => "∗",
+    "star" => "⋆",
+    "circ" => "∘",
+    "bullet" => "∙",
+    "oplus" => "⊕",
+    "ominus" => "⊖",
+    "otimes" => "⊗",
+    "cap" => "∩",
+    "cup" => "∪",
+    "wedge" | "land" => "∧",
+    "vee" | "lor" => "∨",
+    "neg" | "lnot" => "¬",
+    "setminus" | "backslash" => "∖",
+    "leq" | "le" => "≤",
+    "geq" | "ge" => "≥",
+    "neq" | "ne" => "≠",
+    "approx" => "≈",
+    "equiv" => "≡",
+    "sim" => "∼",
+    "simeq" => "≃",
+    "cong" => "≅",
+    "propto" => "∝",
+    "ll" => "≪",
+    "gg" => "≫",
+    "subset" => "⊂",
+    "supset" => "⊃",
+    "subseteq" => "⊆",
+    "supseteq" => "⊇",
+    "in" => "∈",
+    "notin" => "∉",
+    "ni" => "∋",
+    "perp" => "⊥",
+    "parallel" => "∥",
+    "mid" => "∣",
+    "coloneqq" => "≔",
+    "to" | "rightarrow" => "→",
+    "gets" | "leftarrow" => "←",
+    "leftrightarrow" => "↔",
+    "Rightarrow" => "⇒",
+    "Leftarrow" => "⇐",
+    "Leftrightarrow" => "⇔",
+    "implies" | "Longrightarrow" => "⟹",
+    "impliedby" | "Longleftarrow" => "⟸",
+    "iff" | "Longleftrightarrow" => "⟺",
+    "mapsto" => "↦",
+    "longrightarrow" => "⟶",
+    "longleftarrow" => "⟵",
+    "uparrow" => "↑",
+    "downarrow" => "↓",
+    "infty" => "∞",
+    "partial" => "∂",
+    "nabla" => "∇",
+    "forall" => "∀",
+    "exists" => "∃",
+    "emptyset" | "varnothing" => "∅",
+    "Re" => "ℜ",
+    "Im" => "ℑ",
+    "aleph" => "ℵ",
+    "angle" => "∠",
+    "prime" => "′",
+    "ldots" | "dots" => "…",
+    "cdots" => "⋯",
+    "vdots" => "⋮",
+    "ddots" => "⋱",
+    "langle" => "⟨",
+    "rangle" => "⟩",
+    "lfloor" => "⌊",
+    "rfloor" => "⌋",
+    "lceil" => "⌈",
+    "rceil" => "⌉",
+    "lbrace" => "{",
+    "rbrace" => "}",
+    "vert" => "|",
+    "Vert" | "|" => "‖",
+    "degree" => "°",
+    "sum" => "∑",
+    "prod" => "∏",
+    "coprod" => "∐",
+    "int" => "∫",
+    "iint" => "∬",
+    "iiint" => "∭",
+    "oint" => "∮",
+    "bigcup" => "⋃",
+    "bigcap" => "⋂",
+    "bigoplus" => "⨁",
+    "bigotimes" => "⨂",
+    "bigvee" => "⋁",
+    "bigwedge" => "⋀",
+    _ => return None,
+  };
+  Some(MathNode::Operator(operator.to_string()))
+}
+
+#[derive(Debug, Clone)]
+pub enum MathItem {
+  Glyphs {
+    // The origin of the glyphs, on their baseline.
+    position: Point,
+    text: String,
+    plan: RenderPlan,
+    font_style: FontStyle,
+    font_size: u32,
+    ascent: i32,
+    descent: i32,
+  },
+  Rule(Rectangle),
+}
+
+// The items of a formula, relative to the left end of its baseline.
+#[derive(Debug, Clone, Default)]
+pub struct MathBox {
+  pub width: i32,
+  pub ascent: i32,
+  pub descent: i32,
+  pub items: Vec<MathItem>,
+}
+
+impl MathBox {
+  fn append(&mut self, other: MathBox, x: i32, y: i32) {
+    let delta = pt!(x, y);
+    self
+      .items
+      .extend(other.items.into_iter().map(|item| match item {
+        MathItem::Glyphs {
+          position,
+          text,
+          plan,
+          font_style,
+          font_size,
+          ascent,
+          descent,
+        } => MathItem::Glyphs {
+          position: position + delta,
+          text,
+          plan,
+          font_style,
+          font_size,
+          ascent,
+          descent,
+        },
+        MathItem::Rule(rect) => MathItem::Rule(rect + delta),
+      }));
+    self.width = self.width.max(x + other.width);
+    self.ascent = self.ascent.max(other.ascent - y);
+    self.descent = self.descent.max(other.descent + y);
+  }
+
+  fn rule(&mut self, rect: Rectangle) {
+    self.width = self.width.max(rect.max.x);
+    self.ascent = self.ascent.max(-rect.min.y);
+    self.descent = self.descent.max(rect.max.y);
+    self.items.push(MathItem::Rule(rect));
+  }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Class {
+  Ordinary,
+  Large,
+  Binary,
+  Relation,
+  Open,
+  Close,
+  Punctuation,
+}
+
+fn class(node: &MathNode) -> Class {
+  match node {
+    MathNode::Operator(s) => {
+      if s.chars().count() != 1 {
+        Class::Ordinary
+      } else if is_large_operator(node) {
+        Class::Large
+      } else if RELATIONS.contains(s.as_str()) {
+        Class::Relation
+      } else if BINARY_OPERATORS.contains(s.as_str()) {
+        Class::Binary
+      } else if s == "," || s == ";" {
+        Class::Punctuation
+      } else if s != "|" && s != "‖" && OPEN_FENCES.contains(s.as_str()) {
+        Class::Open
+      } else if s != "|" && s != "‖" && CLOSE_FENCES.contains(s.as_str()) {
+        Class::Close
+      } else {
+        Class::Ordinary
+      }
+    },
+    MathNode::Identifier(s) if s.chars().count() > 1 => Class::Large,
+    MathNode::Scripts { base, .. } | MathNode::Limits { base, .. } => class(base),
+    _ => Class::Ordinary,
+  }
+}
+
+// The space between two adjacent nodes of a row, in ems.
+fn space_between(left: Class, right: Class) -> f32 {
+  use Class::*;
+  match (left, right) {
+    (Binary, _) | (_, Binary) => MEDIUM_SPACE,
+    (Relation, Relation) | (Open, Relation) | (Relation, Close) | (Relation, Punctuation) => 0.0,
+    (Relation, _) | (_, Relation) => THICK_SPACE,
+    (Large, Ordinary) | (Ordinary, Large) | (Large, Large) | (Close, Large) => THIN_SPACE,
+    (Punctuation, Close) => 0.0,
+    (Punctuation, _) => THIN_SPACE,
+    _ => 0.0,
+  }
+}
+
+pub struct MathLayout<'a> {
+  fonts: &'a mut Fonts,
+  dpi: u16,
+  // The font size of the formula, in 64th of points.
+  font_size: u32,
+}
+
+impl<'a> MathLayout<'a> {
+  pub fn new(fonts: &'a mut Fonts, dpi: u16, font_size: u32) -> MathLayout<'a> {
+    MathLayout {
+      fonts,
+      dpi,
+      font_size,
+    }
+  }
+
+  pub fn layout(&mut self, node: &MathNode, display: bool) -> MathBox {
+    self.node(node, 0, display)
+  }
+
+  // The scripts are smaller than the base, and the scripts of the scripts even smaller.
+  fn size(&self, level: u8) -> u32 {
+    match level {
+      0 => self.font_size,
+      1 => self.font_size * 7 / 10,
+      _ => self.font_size / 2,
+    }
+  }
+
+  fn em(&self, level: u8) -> i32 {
+    pt_to_px(self.size(level) as f32 / 64.0, self.dpi).round() as i32
+  }
+
+  fn glyphs(&mut self, text: &str, font_style: FontStyle, font_size: u32) -> MathBox {
+    let font = self
+      .fonts
+      .get_mut(FontKind::Serif, font_style, FontWeight::Normal);
+    font.set_size(font_size, self.dpi);
+    let plan = font.plan(text, None, None);
+    let (ascent, descent) = text
+      .chars()
+      .filter(|c| !c.is_whitespace())
+      .map(|c| font.ink_extent(c))
+      .fold((0, 0), |(a, d), (top, below)| (a.max(top), d.max(below)));
+    MathBox {
+      width: plan.width,
+      ascent,
+      descent,
+      items: vec![MathItem::Glyphs {
+        position: pt!(0, 0),
+        text: text.to_string(),
+        plan,
+        font_style,
+        font_size,
+        ascent,
+        descent,
+      }],
+    }
+  }
+
+  // Lays out a fence or a radical, stretched to cover the given extent above and below the
+  // baseline.
+  fn stretched(&mut self, text: &str, level: u8, ascent: i32, descent: i32) -> MathBox {
+    let size = self.size(level);
+    let natural = self.glyphs(text, FontStyle::Normal, size);
+    let height = natural.ascent + natural.descent;
+    let target = ascent + descent;
+    if height <= 0 || target <= height {
+      return natural;
+    }
+    let scale = (target as f32 / height as f32).min(MAX_STRETCH);
+    let mut result = MathBox::default();
+    let glyphs = self.glyphs(text, FontStyle::Normal, (size as f32 * scale) as u32);
+    // Center the ink on the middle of the extent.
+    let middle = (descent - ascent) / 2;
+    let y = middle + (glyphs.ascent - glyphs.descent) / 2;
+    result.append(glyphs, 0, y);
+    result
+  }
+
+  fn node(&mut self, node: &MathNode, level: u8, display: bool) -> MathBox {
+    let em = self.em(level);
+    let axis = (AXIS_HEIGHT * em as f32).round() as i32;
+    let thickness = (em / 18).max(1);
+    let gap = (em / 8).max(thickness);
+
+    match node {
+      MathNode::Identifier(s) => {
+        let mut chars = s.chars();
+        let is_italic = match (chars.next(), chars.next()) {
+          (Some(c), None) => c.is_ascii_alphabetic() || ('α'..='ω').contains(&c),
+          _ => false,
+        };
+        let style = if is_italic {
+          FontStyle::Italic
+        } else {
+          FontStyle::Normal
+        };
+        self.glyphs(s, style, self.size(level))
+      },
+      MathNode::Number(s) | MathNode::Text(s) => {
+        self.glyphs(s, FontStyle::Normal, self.size(level))
+      },
+      MathNode::Operator(s) => {
+        if is_large_operator(node) {
+          let scale = if display { 1.6 } else { 1.2 };
+          let glyphs = self.glyphs(
+            s,
+            FontStyle::Normal,
+            (self.size(level) as f32 * scale) as u32,
+          );
+          let mut result = MathBox::default();
+          let y = (glyphs.ascent - glyphs.descent) / 2 - axis;
+          result.append(glyphs, 0, y);
+          result
+        } else {
+          self.glyphs(s, FontStyle::Normal, self.size(level))
+        }
+      },
+      MathNode::Space(width) => MathBox {
+        width: (width * em as f32).round() as i32,
+        ..Default::default()
+      },
+      MathNode::Row(nodes) => {
+        let mut result = MathBox::default();
+        let mut previous: Option<Class> = None;
+        for (i, child) in nodes.iter().enumerate() {
+          let mut current = class(child);
+          // A binary operator at the start of a row, or after another operator, is unary.
+          if current == Class::Binary {
+            let next = nodes.get(i + 1).map(class);
+            let is_unary = matches!(
+              previous,
+              None
+                | Some(Class::Binary)
+                | Some(Class::Relation)
+                | Some(Class::Open)
+                | Some(Class::Punctuation)
+                | Some(Class::Large)
+            ) || matches!(
+              next,
+              None | Some(Class::Relation) | Some(Class::Close) | Some(Class::Punctuation)
+            );
+            if is_unary {
+              current = Class::Ordinary;
+            }
+          }
+          let space = match previous {
+            Some(previous) if level == 0 => space_between(previous, current),
+            _ => 0.0,
+          };
+          let x = result.width + (space * em as f32).round() as i32;
+          let child_box = self.node(child, level, display);
+          result.append(child_box, x, 0);
+          previous = Some(current);
+        }
+        result
+      },
+      MathNode::Scripts { base, sub, sup } => {
+        if display && has_limits(base) {
+          return self.node(
+            &MathNode::Limits {
+              base: base.clone(),
+              under: sub.clone(),
+              over: sup.clone(),
+            },
+            level,
+            display,
+          );
+        }
+        let base = self.node(base, level, display);
+        let sub = sub.as_ref().map(|n| self.node(n, level + 1, false));
+        let sup = sup.as_ref().map(|n| self.node(n, level + 1, false));
+        let x = base.width;
+        let mut sup_shift = sup.as_ref().map_or(0, |sup| {
+          (base.ascent - em / 4)
+            .max(em * 2 / 5)
+            .max(sup.descent + em / 4)
+        });
+        let mut sub_shift = sub.as_ref().map_or(0, |sub| {
+          (base.descent + em / 10)
+            .max(em / 5)
+            .max(sub.ascent - em * 4 / 5)
+        });
+        if let (Some(sub), Some(sup)) = (sub.as_ref(), sup.as_ref()) {
+          let clearance = (sup_shift - sup.descent) - (sub.ascent - sub_shift);
+          if clearance < gap {
+            sub_shift += gap - clearance;
+          }
+        } else if sup.is_none() {
+          sup_shift = 0;
+        }
+        let mut result = MathBox::default();
+        result.append(base, 0, 0);
+        if let Some(sup) = sup {
+          result.append(sup, x, -sup_shift);
+        }
+        if let Some(sub) = sub {
+          result.append(sub, x, sub_shift);
+        }
+        result
+      },
+      MathNode::Limits { base, under, over } => {
+        if !display && has_limits(base) {
+          return self.node(
+            &MathNode::Scripts {
+              base: base.clone(),
+              sub: under.clone(),
+              sup: over.clone(),
+            },
+            level,
+            display,
+          );
+        }
+        let overline = over.as_deref().map_or(false, |n| is_symbol_of(n, "‾"));
+        let underline = under.as_deref().map_or(false, |n| is_symbol_of(n, "_"));
+        // The accents keep the size of their base.
+        let is_accent =
+          matches!(over.as_deref(), Some(MathNode::Operator(s)) if s.chars().count() == 1);
+        let over_level = if is_accent { level } else { level + 1 };
+        let base = self.node(base, level, display);
+        let over = over
+          .as_ref()
+          .filter(|_| !overline)
+          .map(|n| self.node(n, over_level, false));
+        let under = under
+          .as_ref()
+          .filter(|_| !underline)
+          .map(|n| self.node(n, level + 1, false));
+        let width = base
+          .width
+          .max(over.as_ref().map_or(0, |b| b.width))
+          .max(under.as_ref().map_or(0, |b| b.width));
+        let mut result = MathBox::default();
+        let base_ascent = base.ascent;
+        let base_descent = base.descent;
+        let base_x = (width - base.width) / 2;
+        let base_width = base.width;
+        result.append(base, base_x, 0);
+        if overline {
+          let top = -(base_ascent + gap + thickness);
+          result.rule(rect![base_x, top, base_x + base_width, top + thickness]);
+        }
+        if underline {
+          let top = base_descent + gap;
+          result.rule(rect![base_x, top, base_x + base_width, top + thickness]);
+        }
+        if let Some(over) = over {
+          let x = (width - over.width) / 2;
+          let y = -(base_ascent + gap / 2 + over.descent);
+          result.append(over, x, y);
+        }
+        if let Some(under) = under {
+          let x = (width - under.width) / 2;
+          let y = base_descent + gap / 2 + under.ascent;
+          result.append(under, x, y);
+        }
+        result
+      },
+      MathNode::Fraction(numerator, denominator) => {
+        let child_level = if display { level } else { level + 1 };
+        let numerator = self.node(numerator, child_level, false);
+        let denominator = self.node(denominator, child_level, false);
+        let padding = (em / 10).max(1);
+        let width = numerator.width.max(denominator.width) + 2 * padding;
+        let bar_top = -axis - thickness / 2;
+        let numerator_y = bar_top - gap - numerator.descent;
+        let denominator_y = bar_top + thickness + gap + denominator.ascent;
+        let numerator_x = (width - numerator.width) / 2;
+        let denominator_x = (width - denominator.width) / 2;
+        let mut result = MathBox::default();
+        result.append(numerator, numerator_x, numerator_y);
+        result.append(denominator, denominator_x, denominator_y);
+        result.rule(rect![
+          padding / 2,
+          bar_top,
+          width - padding / 2,
+          bar_top + thickness
+        ]);
+        result
+      },
+      MathNode::Root { body, index } => {
+        let body = self.node(body, level, display);
+        let top = body.ascent + gap + thickness;
+        let radical = self.stretched("√", level, top, body.descent);
+        let index = index.as_ref().map(|n| self.node(n, level + 2, false));
+        let shift = index
+          .as_ref()
+          .map_or(0, |index| (index.width - radical.width / 2).max(0));
+        let radical_width = radical.width;
+        let radical_top = radical.ascent;
+        let mut result = MathBox::default();
+        if let Some(index) = index {
+          let y = -(radical_top / 2) - index.descent;
+          result.append(index, 0, y);
+        }
+        result.append(radical, shift, 0);
+        let body_x = shift + radical_width;
+        let body_width = body.width;
+        result.append(body, body_x, 0);
+        let rule_top = -radical_top.max(top);
+        result.rule(rect![
+          body_x - 1,
+          rule_top,
+          body_x + body_width + gap,
+          rule_top + thickness
+        ]);
+        result
+      },
+      MathNode::Fenced { open, close, body } => {
+        let body = self.node(body, level, display);
+        // The fences are symmetric around the axis.
+        let half = (body.ascent - axis).max(body.descent + axis);
+        let (ascent, descent) = (half + axis, half - axis);
+        let mut result = MathBox::default();
+        if !open.is_empty() {
+          let fence = self.stretched(open, level, ascent, descent);
+          result.append(fence, 0, 0);
+        }
+        let x = result.width;
+        result.append(body, x, 0);
+        if !close.is_empty() {
+          let fence = self.stretched(close, level, ascent, descent);
+          let x = result.width;
+          result.append(fence, x, 0);
+        }
+        result
+      },
+      MathNode::Table(rows) => {
+        let rows: Vec<Vec<MathBox>> = rows
+          .iter()
+          .map(|cells| cells.iter().map(|n| self.node(n, level, false)).collect())
+          .collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<i32> = (0..columns)
+          .map(|j| {
+            rows
+              .iter()
+              .filter_map(|cells| cells.get(j))
+              .map(|b| b.width)
+              .max()
+              .unwrap_or(0)
+          })
+          .collect();
+        let column_gap = em * 4 / 5;
+        let row_gap = em / 4;
+        let heights: Vec<(i32, i32)> = rows
+          .iter()
+          .map(|cells| {
+            cells.iter().fold((em * 3 / 5, em / 5), |(a, d), b| {
+              (a.max(b.ascent), d.max(b.descent))
+            })
+          })
+          .collect();
+        let total_height = heights.iter().map(|(a, d)| a + d).sum::<i32>()
+          + row_gap * (rows.len() as i32 - 1).max(0);
+        let mut y = -axis - total_height / 2;
+        let mut result = MathBox::default();
+        for (cells, (ascent, descent)) in rows.into_iter().zip(heights) {
+          y += ascent;
+          let mut x = 0;
+          for (cell, width) in cells.into_iter().zip(widths.iter()) {
+            let cell_x = x + (width - cell.width) / 2;
+            result.append(cell, cell_x, y);
+            x += width + column_gap;
+          }
+          y += descent + row_gap;
+        }
+        result.width = widths.iter().sum::<i32>() + column_gap * (columns as i32 - 1).max(0);
+        result
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn identifier(s: &str) -> MathNode {
+    MathNode::Identifier(s.to_string())
+  }
+
+  fn operator(s: &str) -> MathNode {
+    MathNode::Operator(s.to_string())
+  }
+
+  #[test]
+  fn test_split_latex() {
+    let chunks = split_latex("Let \\(x^2\\) be. $$y$$");
+    assert_eq!(
+      chunks,
+      vec![
+        (0, "Let ", None),
+        (6, "x^2", Some(false)),
+        (11, " be. ", None),
+        (18, "y", Some(true)),
+      ]
+    );
+    assert_eq!(split_latex("Costs $5."), vec![(0, "Costs $5.", None)]);
+  }
+
+  #[test]
+  fn test_parse_latex() {
+    assert_eq!(
+      parse_latex("x_i^2"),
+      MathNode::Scripts {
+        base: Box::new(identifier("x")),
+        sub: Some(Box::new(identifier("i"))),
+        sup: Some(Box::new(MathNode::Number("2".to_string()))),
+      }
+    );
+    assert_eq!(
+      parse_latex("\\frac{a}{b}"),
+      MathNode::Fraction(Box::new(identifier("a")), Box::new(identifier("b")))
+    );
+    assert_eq!(
+      parse_latex("(a+b)"),
+      MathNode::Fenced {
+        open: "(".to_string(),
+        close: ")".to_string(),
+        body: Box::new(MathNode::Row(vec![
+          identifier("a"),
+          operator("+"),
+          identifier("b")
+        ])),
+      }
+    );
+    assert_eq!(
+      parse_latex("(a)+(b)"),
+      MathNode::Row(vec![
+        operator("("),
+        identifier("a"),
+        operator(")"),
+        operator("+"),
+        operator("("),
+        identifier("b"),
+        operator(")")
+      ])
+    );
+    assert_eq!(
+      parse_latex("\\begin{matrix}1&2\\\\3&4\\end{matrix}"),
+      MathNode::Table(vec![
+        vec![
+          MathNode::Number("1".to_string()),
+          MathNode::Number("2".to_string())
+        ],
+        vec![
+          MathNode::Number("3".to_string()),
+          MathNode::Number("4".to_string())
+        ],
+      ])
+    );
+  }
+}
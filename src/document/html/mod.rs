@@ -2,6 +2,7 @@ pub mod css;
 pub mod dom;
 pub mod engine;
 pub mod layout;
+pub mod math;
 pub mod parse;
 pub mod style;
 pub mod xml;
@@ -24,9 +25,12 @@ use self::{
 };
 use crate::{
   document::{BoundedText, Document, Location, TextLocation, TocEntry},
-  framebuffer::Pixmap,
+  font::FamilyFiles,
+  framebuffer::{DitherMode, Pixmap},
   geom::{CycleDir, Edge, Rectangle},
   helpers::{decode_entities, Normalize},
+  metadata::{PageMargins, TextAlignOverride},
+  settings::TypographySettings,
   unit::pt_to_px,
 };
 use anyhow::Error;
@@ -179,22 +183,6 @@ impl HtmlDocument {
     }
   }
 
-  fn images(&mut self, loc: Location) -> Option<(Vec<Rectangle>, usize)> {
-    let offset = self.resolve_location(loc)?;
-    let page_index = self.page_index(offset)?;
-
-    Some((
-      self.pages[page_index]
-        .iter()
-        .filter_map(|dc| match dc {
-          DrawCommand::Image(ImageCommand { rect, .. }) => Some(*rect),
-          _ => None,
-        })
-        .collect(),
-      offset,
-    ))
-  }
-
   fn build_pages(&mut self) -> Vec<Page> {
     let mut stylesheet = Vec::new();
     let spine_dir = PathBuf::from("");
@@ -425,6 +413,22 @@ impl Document for HtmlDocument {
     ))
   }
 
+  fn images(&mut self, loc: Location) -> Option<(Vec<Rectangle>, usize)> {
+    let offset = self.resolve_location(loc)?;
+    let page_index = self.page_index(offset)?;
+
+    Some((
+      self.pages[page_index]
+        .iter()
+        .filter_map(|dc| match dc {
+          DrawCommand::Image(ImageCommand { rect, .. }) => Some(*rect),
+          _ => None,
+        })
+        .collect(),
+      offset,
+    ))
+  }
+
   fn pixmap(&mut self, loc: Location, _scale: f32) -> Option<(Pixmap, usize)> {
     let offset = self.resolve_location(loc)?;
     let page_index = self.page_index(offset)?;
@@ -444,8 +448,8 @@ impl Document for HtmlDocument {
     self.pages.clear();
   }
 
-  fn set_font_family(&mut self, family_name: &str, search_path: &str) {
-    self.engine.set_font_family(family_name, search_path);
+  fn set_font_family(&mut self, files: &FamilyFiles) {
+    self.engine.set_font_family(files);
     self.pages.clear();
   }
 
@@ -454,11 +458,35 @@ impl Document for HtmlDocument {
     self.pages.clear();
   }
 
+  fn set_margins(&mut self, margins: &PageMargins) {
+    self.engine.set_margins(margins);
+    self.pages.clear();
+  }
+
   fn set_line_height(&mut self, line_height: f32) {
     self.engine.set_line_height(line_height);
     self.pages.clear();
   }
 
+  fn set_dithering(&mut self, dithering: DitherMode) {
+    self.engine.set_dithering(dithering);
+  }
+
+  fn set_typography(&mut self, typography: TypographySettings) {
+    self.engine.set_typography(typography);
+    self.pages.clear();
+  }
+
+  fn set_verse_layout(&mut self, enable: bool) {
+    self.engine.set_verse_layout(enable);
+    self.pages.clear();
+  }
+
+  fn set_text_align_override(&mut self, value: TextAlignOverride) {
+    self.engine.set_text_align_override(value);
+    self.pages.clear();
+  }
+
   fn title(&self) -> Option<String> {
     self
       .content
@@ -334,6 +334,15 @@ pub fn parse_text_align(value: &str) -> Option<TextAlign> {
   }
 }
 
+// Returns whether the line breaks and the spaces are kept.
+pub fn parse_white_space(value: &str) -> Option<bool> {
+  match value {
+    "pre" | "pre-wrap" | "pre-line" | "break-spaces" => Some(true),
+    "normal" | "nowrap" => Some(false),
+    _ => None,
+  }
+}
+
 pub fn parse_line_height(value: &str, em: f32, rem: f32, dpi: u16) -> Option<i32> {
   if value == "normal" {
     Some(pt_to_px(1.2 * em, dpi).round() as i32)
@@ -476,4 +485,11 @@ mod tests {
     assert_eq!(d, Some(18));
     assert_eq!(e, Some(255));
   }
+
+  #[test]
+  fn test_parse_white_space() {
+    assert_eq!(parse_white_space("pre-wrap"), Some(true));
+    assert_eq!(parse_white_space("normal"), Some(false));
+    assert_eq!(parse_white_space("inherit"), None);
+  }
 }
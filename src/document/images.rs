@@ -0,0 +1,269 @@
+//! Presents a directory of images as a document with one page per image.
+
+use super::{
+  file_kind,
+  pdf::{PdfDocument, PdfOpener},
+  BoundedText,
+  Document,
+  Location,
+  TocEntry,
+};
+use crate::{font::FamilyFiles, framebuffer::Pixmap, geom::CycleDir, metadata::{PageMargins, TextAlign}};
+use anyhow::{format_err, Error};
+use fxhash::FxHashMap;
+use std::{
+  cmp::Ordering,
+  fs,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+// The kind given to image folders within the library.
+pub const IMAGE_FOLDER_KIND: &str = "images";
+const IMAGE_KINDS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff"];
+// The number of decoded images kept around.
+const CACHE_SIZE: usize = 3;
+
+pub struct ImageFolderDocument {
+  opener: PdfOpener,
+  dir: PathBuf,
+  pages: Vec<PathBuf>,
+  cache: Vec<(usize, PdfDocument)>,
+  dims: Mutex<FxHashMap<usize, (f32, f32)>>,
+}
+
+unsafe impl Send for ImageFolderDocument {}
+unsafe impl Sync for ImageFolderDocument {}
+
+// Returns the images of *dir*, in natural order, and their total size.
+// Returns `None` if *dir* contains anything else than images.
+fn scan_images(dir: &Path) -> Option<(Vec<PathBuf>, u64)> {
+  let mut images = Vec::new();
+  let mut size = 0;
+
+  for entry in fs::read_dir(dir).ok()? {
+    let entry = entry.ok()?;
+    let path = entry.path();
+    if entry
+      .file_name()
+      .to_str()
+      .map_or(false, |name| name.starts_with('.'))
+    {
+      continue;
+    }
+    let md = entry.metadata().ok()?;
+    let kind = file_kind(&path).unwrap_or_default();
+    if md.is_dir() || !IMAGE_KINDS.contains(&kind.as_str()) {
+      return None;
+    }
+    size += md.len();
+    images.push(path);
+  }
+
+  if images.is_empty() {
+    return None;
+  }
+
+  images.sort_by(|a, b| {
+    natural_cmp(
+      &a.file_name().unwrap_or_default().to_string_lossy(),
+      &b.file_name().unwrap_or_default().to_string_lossy(),
+    )
+  });
+
+  Some((images, size))
+}
+
+// Returns the total size of the images if *path* is an image folder.
+pub fn image_folder_size<P: AsRef<Path>>(path: P) -> Option<u64> {
+  if !path.as_ref().is_dir() {
+    return None;
+  }
+  scan_images(path.as_ref()).map(|(_, size)| size)
+}
+
+// Compares the runs of digits by their numeric values, so that *page2* comes before *page10*.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+  let mut a = a.chars().peekable();
+  let mut b = b.chars().peekable();
+
+  loop {
+    match (a.peek().copied(), b.peek().copied()) {
+      (None, None) => return Ordering::Equal,
+      (None, Some(_)) => return Ordering::Less,
+      (Some(_), None) => return Ordering::Greater,
+      (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+        let mut u = String::new();
+        let mut v = String::new();
+        while let Some(c) = a.peek().copied().filter(char::is_ascii_digit) {
+          u.push(c);
+          a.next();
+        }
+        while let Some(c) = b.peek().copied().filter(char::is_ascii_digit) {
+          v.push(c);
+          b.next();
+        }
+        let u = u.trim_start_matches('0');
+        let v = v.trim_start_matches('0');
+        let ord = u.len().cmp(&v.len()).then_with(|| u.cmp(v));
+        if ord != Ordering::Equal {
+          return ord;
+        }
+      },
+      (Some(x), Some(y)) => {
+        let ord = x.to_lowercase().cmp(y.to_lowercase());
+        if ord != Ordering::Equal {
+          return ord;
+        }
+        a.next();
+        b.next();
+      },
+    }
+  }
+}
+
+impl ImageFolderDocument {
+  pub fn new<P: AsRef<Path>>(path: P) -> Result<ImageFolderDocument, Error> {
+    let dir = path.as_ref().to_path_buf();
+    let (pages, _) = scan_images(&dir).ok_or_else(|| format_err!("not a folder of images"))?;
+    let opener = PdfOpener::new().ok_or_else(|| format_err!("can't create the image opener"))?;
+    Ok(ImageFolderDocument {
+      opener,
+      dir,
+      pages,
+      cache: Vec::with_capacity(CACHE_SIZE),
+      dims: Mutex::new(FxHashMap::default()),
+    })
+  }
+
+  // Moves the image of the given page at the front of the cache, decoding it if needed.
+  fn image(&mut self, index: usize) -> Option<&PdfDocument> {
+    if let Some(pos) = self.cache.iter().position(|(i, _)| *i == index) {
+      let entry = self.cache.remove(pos);
+      self.cache.insert(0, entry);
+    } else {
+      let doc = self.opener.open(self.pages.get(index)?)?;
+      if self.cache.len() >= CACHE_SIZE {
+        self.cache.pop();
+      }
+      self.cache.insert(0, (index, doc));
+    }
+    self.cache.first().map(|(_, doc)| doc)
+  }
+}
+
+impl Document for ImageFolderDocument {
+  fn dims(&self, index: usize) -> Option<(f32, f32)> {
+    if let Some(dims) = self.dims.lock().ok()?.get(&index) {
+      return Some(*dims);
+    }
+    let dims = match self.cache.iter().find(|(i, _)| *i == index) {
+      Some((_, doc)) => doc.dims(0)?,
+      None => self.opener.open(self.pages.get(index)?)?.dims(0)?,
+    };
+    self.dims.lock().ok()?.insert(index, dims);
+    Some(dims)
+  }
+
+  fn pages_count(&self) -> usize {
+    self.pages.len()
+  }
+
+  fn pixmap(&mut self, loc: Location, scale: f32) -> Option<(Pixmap, usize)> {
+    let index = self.resolve_location(loc)?;
+    self
+      .image(index)
+      .and_then(|doc| doc.page(0))
+      .and_then(|page| page.pixmap(scale))
+      .map(|pixmap| (pixmap, index))
+  }
+
+  fn toc(&mut self) -> Option<Vec<TocEntry>> {
+    None
+  }
+
+  fn chapter<'a>(&mut self, _offset: usize, _toc: &'a [TocEntry]) -> Option<&'a TocEntry> {
+    None
+  }
+
+  fn chapter_relative<'a>(
+    &mut self,
+    _offset: usize,
+    _dir: CycleDir,
+    _toc: &'a [TocEntry],
+  ) -> Option<&'a TocEntry> {
+    None
+  }
+
+  fn words(&mut self, loc: Location) -> Option<(Vec<BoundedText>, usize)> {
+    self.resolve_location(loc).map(|index| (Vec::new(), index))
+  }
+
+  fn lines(&mut self, loc: Location) -> Option<(Vec<BoundedText>, usize)> {
+    self.resolve_location(loc).map(|index| (Vec::new(), index))
+  }
+
+  fn links(&mut self, loc: Location) -> Option<(Vec<BoundedText>, usize)> {
+    self.resolve_location(loc).map(|index| (Vec::new(), index))
+  }
+
+  fn title(&self) -> Option<String> {
+    self
+      .dir
+      .file_name()
+      .map(|name| name.to_string_lossy().into_owned())
+  }
+
+  fn author(&self) -> Option<String> {
+    None
+  }
+
+  fn metadata(&self, _key: &str) -> Option<String> {
+    None
+  }
+
+  fn is_reflowable(&self) -> bool {
+    false
+  }
+
+  fn layout(&mut self, _width: u32, _height: u32, _font_size: f32, _dpi: u16) {}
+
+  fn set_font_family(&mut self, _files: &FamilyFiles) {}
+
+  fn set_margin_width(&mut self, _width: i32) {}
+
+  fn set_margins(&mut self, _margins: &PageMargins) {}
+
+  fn set_text_align(&mut self, _text_align: TextAlign) {}
+
+  fn set_line_height(&mut self, _line_height: f32) {}
+}
+
+#[cfg(test)]
+mod tests {
+  use super::natural_cmp;
+  use std::cmp::Ordering;
+
+  #[test]
+  fn test_natural_order() {
+    let mut names = vec![
+      "page10.png",
+      "Page2.png",
+      "page1.png",
+      "page02b.png",
+      "cover.jpg",
+    ];
+    names.sort_by(|a, b| natural_cmp(a, b));
+    assert_eq!(
+      names,
+      vec![
+        "cover.jpg",
+        "page1.png",
+        "Page2.png",
+        "page02b.png",
+        "page10.png"
+      ]
+    );
+    assert_eq!(natural_cmp("001", "1"), Ordering::Equal);
+  }
+}
@@ -1,31 +1,39 @@
 pub mod djvu;
 pub mod epub;
+pub mod export;
 pub mod html;
+pub mod images;
 pub mod pdf;
+pub mod registry;
+pub mod skim;
 
 mod djvulibre_sys;
 mod mupdf_sys;
 
-use self::{djvu::DjvuOpener, epub::EpubDocument, html::HtmlDocument, pdf::PdfOpener};
+pub use self::registry::{file_kind, open};
 use crate::{
   device::CURRENT_DEVICE,
-  framebuffer::Pixmap,
-  geom::{Boundary, CycleDir},
-  metadata::TextAlign,
-  settings::INTERNAL_CARD_ROOT,
+  font::FamilyFiles,
+  framebuffer::{DitherMode, Pixmap},
+  geom::{Boundary, CycleDir, Rectangle},
+  metadata::{PageMargins, TextAlign, TextAlignOverride},
+  resources::usage_by_directory,
+  settings::{TypographySettings, INTERNAL_CARD_ROOT},
 };
 use anyhow::{format_err, Error};
-use fxhash::{FxHashMap, FxHashSet};
-use lazy_static::lazy_static;
+use fxhash::FxHashMap;
 use nix::sys::statvfs;
 #[cfg(target_os = "linux")]
 use nix::sys::sysinfo;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{env, ffi::OsStr, path::Path, process::Command};
+use std::{env, path::Path, process::Command};
 use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 
 pub const BYTES_PER_PAGE: f64 = 2048.0;
+// The prefix of the addresses of local files.
+pub const FILE_SCHEME: &str = "file://";
+const STORAGE_USAGE_ENTRIES: usize = 8;
 
 #[derive(Debug, Clone)]
 pub enum Location {
@@ -98,10 +106,16 @@ pub trait Document: Send + Sync {
   fn lines(&mut self, loc: Location) -> Option<(Vec<BoundedText>, usize)>;
   fn links(&mut self, loc: Location) -> Option<(Vec<BoundedText>, usize)>;
 
+  // The rectangles of the embedded images, for the documents that lay out their own pages.
+  fn images(&mut self, _loc: Location) -> Option<(Vec<Rectangle>, usize)> {
+    None
+  }
+
   fn pixmap(&mut self, loc: Location, scale: f32) -> Option<(Pixmap, usize)>;
   fn layout(&mut self, width: u32, height: u32, font_size: f32, dpi: u16);
-  fn set_font_family(&mut self, family_name: &str, search_path: &str);
+  fn set_font_family(&mut self, files: &FamilyFiles);
   fn set_margin_width(&mut self, width: i32);
+  fn set_margins(&mut self, margins: &PageMargins);
   fn set_text_align(&mut self, text_align: TextAlign);
   fn set_line_height(&mut self, line_height: f32);
 
@@ -111,10 +125,26 @@ pub trait Document: Send + Sync {
 
   fn is_reflowable(&self) -> bool;
 
+  // Where the reading starts, past the cover and the front matter.
+  fn start_location(&mut self) -> Option<Location> {
+    None
+  }
+
   fn has_synthetic_page_numbers(&self) -> bool {
     false
   }
 
+  // Sets how the gray levels of the embedded images are reduced.
+  fn set_dithering(&mut self, _dithering: DitherMode) {}
+
+  fn set_typography(&mut self, _typography: TypographySettings) {}
+
+  // Sets which alignments of the document are replaced by the one given to set_text_align.
+  fn set_text_align_override(&mut self, _value: TextAlignOverride) {}
+
+  // Keeps the line breaks and the indentation of the text, for poetry and code.
+  fn set_verse_layout(&mut self, _enable: bool) {}
+
   fn save(&self, _path: &str) -> Result<(), Error> {
     Err(format_err!("This document can't be saved."))
   }
@@ -151,14 +181,6 @@ pub trait Document: Send + Sync {
   }
 }
 
-pub fn file_kind<P: AsRef<Path>>(path: P) -> Option<String> {
-  path
-    .as_ref()
-    .extension()
-    .and_then(OsStr::to_str)
-    .map(str::to_lowercase)
-}
-
 pub trait HumanSize {
   fn human_size(&self) -> String;
 }
@@ -194,23 +216,6 @@ pub fn asciify(name: &str) -> String {
     .replace('’', "'")
 }
 
-pub fn open<P: AsRef<Path>>(path: P) -> Option<Box<dyn Document>> {
-  file_kind(path.as_ref()).and_then(|k| match k.as_ref() {
-    "epub" => EpubDocument::new(&path)
-      .map_err(|e| eprintln!("{}: {}.", path.as_ref().display(), e))
-      .map(|d| Box::new(d) as Box<dyn Document>)
-      .ok(),
-    "html" | "htm" => HtmlDocument::new(&path)
-      .map_err(|e| eprintln!("{}: {}.", path.as_ref().display(), e))
-      .map(|d| Box::new(d) as Box<dyn Document>)
-      .ok(),
-    "djvu" | "djv" => {
-      DjvuOpener::new().and_then(|o| o.open(path).map(|d| Box::new(d) as Box<dyn Document>))
-    },
-    _ => PdfOpener::new().and_then(|o| o.open(path).map(|d| Box::new(d) as Box<dyn Document>)),
-  })
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum SimpleTocEntry {
@@ -234,45 +239,6 @@ impl From<TocLocation> for Location {
   }
 }
 
-pub fn toc_as_html(toc: &[TocEntry], chap_index: usize) -> String {
-  let mut buf = "<html>\n\t<head>\n\t\t<title>Table of Contents</title>\n\t\t\
-                   <link rel=\"stylesheet\" type=\"text/css\" href=\"css/toc.css\"/>\n\t\
-                   </head>\n\t<body>\n"
-    .to_string();
-  toc_as_html_aux(toc, chap_index, 0, &mut buf);
-  buf.push_str("\t</body>\n</html>");
-  buf
-}
-
-pub fn toc_as_html_aux(toc: &[TocEntry], chap_index: usize, depth: usize, buf: &mut String) {
-  buf.push_str(&"\t".repeat(depth + 2));
-  if depth == 0 {
-    buf.push_str("<ul class=\"top\">\n");
-  } else {
-    buf.push_str("<ul>\n");
-  }
-  for entry in toc {
-    buf.push_str(&"\t".repeat(depth + 3));
-    match entry.location {
-      Location::Exact(n) => buf.push_str(&format!("<li><a href=\"@{}\">", n)),
-      Location::Uri(ref uri) => buf.push_str(&format!("<li><a href=\"@{}\">", uri)),
-      _ => buf.push_str("<li><a href=\"#\">"),
-    }
-    let title = entry.title.replace('<', "&lt;").replace('>', "&gt;");
-    if entry.index == chap_index {
-      buf.push_str(&format!("<strong>{}</strong>", title));
-    } else {
-      buf.push_str(&title);
-    }
-    buf.push_str("</a></li>\n");
-    if !entry.children.is_empty() {
-      toc_as_html_aux(&entry.children, chap_index, depth + 1, buf);
-    }
-  }
-  buf.push_str(&"\t".repeat(depth + 2));
-  buf.push_str("</ul>\n");
-}
-
 #[inline]
 fn chapter(index: usize, toc: &[TocEntry]) -> Option<&TocEntry> {
   let mut chap = None;
@@ -418,7 +384,7 @@ const HWINFO_KEYS: [&str; 19] = [
   "Wifi",
 ];
 
-pub fn sys_info_as_html() -> String {
+pub fn sys_info_as_html(library_home: &Path) -> String {
   let mut buf = "<html>\n\t<head>\n\t\t<title>System Info</title>\n\t\t\
                    <link rel=\"stylesheet\" type=\"text/css\" \
                    href=\"css/sysinfo.css\"/>\n\t</head>\n\t<body>\n"
@@ -441,6 +407,14 @@ pub fn sys_info_as_html() -> String {
     CURRENT_DEVICE.mark()
   ));
   buf.push_str("\t\t\t</tr>\n");
+
+  buf.push_str("\t\t\t<tr>\n");
+  buf.push_str("\t\t\t\t<td class=\"key\">Capabilities</td>\n");
+  buf.push_str(&format!(
+    "\t\t\t\t<td class=\"value\">{}</td>\n",
+    CURRENT_DEVICE.capabilities().labels().join(", ")
+  ));
+  buf.push_str("\t\t\t</tr>\n");
   buf.push_str("\t\t\t<tr class=\"sep\"></tr>\n");
 
   for (name, var) in [
@@ -490,6 +464,16 @@ pub fn sys_info_as_html() -> String {
     buf.push_str("\t\t\t</tr>\n");
   }
 
+  for (name, size) in usage_by_directory(library_home)
+    .into_iter()
+    .take(STORAGE_USAGE_ENTRIES)
+  {
+    buf.push_str("\t\t\t<tr>\n");
+    buf.push_str(&format!("\t\t\t\t<td>Storage used by {}</td>\n", name));
+    buf.push_str(&format!("\t\t\t\t<td>{}</td>\n", size.human_size()));
+    buf.push_str("\t\t\t</tr>\n");
+  }
+
   #[cfg(target_os = "linux")]
   if let Ok(info) = sysinfo::sysinfo() {
     buf.push_str("\t\t\t<tr>\n");
@@ -543,59 +527,3 @@ pub fn sys_info_as_html() -> String {
   buf.push_str("\t\t</table>\n\t</body>\n</html>");
   buf
 }
-
-// cd mupdf/source && awk '/_extensions\[/,/}/' */*.c
-lazy_static! {
-pub static ref RECOGNIZED_KINDS: FxHashSet<&'static str> =
-    [
-    // djvu
-    "djvu",
-    "djv",
-    // cbz
-    "cbt",
-    "cbz",
-    "tar",
-    "zip",
-    // img
-    "bmp",
-    "gif",
-    "hdp",
-    "j2k",
-    "jfif",
-    "jfif-tbnl",
-    "jp2",
-    "jpe",
-    "jpeg",
-    "jpg",
-    "jpx",
-    "jxr",
-    "pam",
-    "pbm",
-    "pgm",
-    "png",
-    "pnm",
-    "ppm",
-    "wdp",
-    // tiff
-    "tif",
-    "tiff",
-    // gprf
-    "gproof",
-    // epub
-    "epub",
-    // html
-    "fb2",
-    "htm",
-    "html",
-    "xhtml",
-    "xml",
-    // pdf
-    "pdf",
-    "ai",
-    // svg
-    "svg",
-    // xps
-    "oxps",
-    "xps",
-    ].iter().cloned().collect();
-}
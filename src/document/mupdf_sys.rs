@@ -15,6 +15,7 @@ pub const FZ_TEXT_PRESERVE_IMAGES: libc::c_int = 4;
 pub const FZ_PAGE_BLOCK_TEXT: libc::c_int = 0;
 pub const FZ_PAGE_BLOCK_IMAGE: libc::c_int = 1;
 
+// The largest size of the resource store, which holds the decoded images and fonts.
 pub const CACHE_SIZE: libc::size_t = 32 * 1024 * 1024;
 
 pub enum FzContext {}
@@ -100,6 +101,7 @@ extern "C" {
     doc: *mut FzDocument,
     page_idx: libc::c_int,
   ) -> *mut FzPage;
+  pub fn fz_keep_page(ctx: *mut FzContext, page: *mut FzPage) -> *mut FzPage;
   pub fn fz_drop_page(ctx: *mut FzContext, page: *mut FzPage);
   pub fn fz_bound_page(ctx: *mut FzContext, page: *mut FzPage) -> FzRect;
   pub fn fz_run_page(
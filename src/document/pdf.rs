@@ -2,14 +2,20 @@ use super::mupdf_sys::*;
 
 use super::{chapter, chapter_relative, BoundedText, Document, Location, TextLocation, TocEntry};
 use crate::{
+  font::FamilyFiles,
   framebuffer::Pixmap,
   geom::{Boundary, CycleDir},
-  metadata::TextAlign,
+  metadata::{PageMargins, TextAlign},
+  resources::total_memory,
   unit::pt_to_px,
 };
 use anyhow::Error;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
 use std::{
+  cell::RefCell,
   char,
+  collections::VecDeque,
   ffi::{CStr, CString},
   fs::File,
   io::Read,
@@ -29,13 +35,26 @@ impl Into<Boundary> for FzRect {
   }
 }
 
+// The number of parsed pages kept by a document.
+const PAGE_CACHE_SIZE: usize = 4;
+
+lazy_static! {
+  // A sixteenth of the physical memory, within the limit of the resource store.
+  static ref STORE_SIZE: libc::size_t = total_memory()
+    .map_or(CACHE_SIZE, |total| CACHE_SIZE.min((total / 16) as libc::size_t));
+}
+
 struct PdfContext(*mut FzContext);
 
 pub struct PdfOpener(Rc<PdfContext>);
 
+// The objects of a page are only parsed when it's first needed: the least recently used
+// pages are dropped, and their dimensions remembered.
 pub struct PdfDocument {
   ctx: Rc<PdfContext>,
   doc: *mut FzDocument,
+  pages: RefCell<VecDeque<(usize, *mut FzPage)>>,
+  dims: RefCell<FxHashMap<usize, (f32, f32)>>,
 }
 
 pub struct PdfPage<'a> {
@@ -49,7 +68,7 @@ impl PdfOpener {
   pub fn new() -> Option<PdfOpener> {
     unsafe {
       let version = CString::new(FZ_VERSION).unwrap();
-      let ctx = fz_new_context_imp(ptr::null(), ptr::null(), CACHE_SIZE, version.as_ptr());
+      let ctx = fz_new_context_imp(ptr::null(), ptr::null(), *STORE_SIZE, version.as_ptr());
 
       if ctx.is_null() {
         None
@@ -67,10 +86,7 @@ impl PdfOpener {
       if doc.is_null() {
         None
       } else {
-        Some(PdfDocument {
-          ctx: self.0.clone(),
-          doc,
-        })
+        Some(PdfDocument::new(self.0.clone(), doc))
       }
     }
   }
@@ -89,10 +105,7 @@ impl PdfOpener {
       if doc.is_null() {
         None
       } else {
-        Some(PdfDocument {
-          ctx: self.0.clone(),
-          doc,
-        })
+        Some(PdfDocument::new(self.0.clone(), doc))
       }
     }
   }
@@ -120,20 +133,42 @@ unsafe impl Send for PdfDocument {}
 unsafe impl Sync for PdfDocument {}
 
 impl PdfDocument {
+  fn new(ctx: Rc<PdfContext>, doc: *mut FzDocument) -> PdfDocument {
+    PdfDocument {
+      ctx,
+      doc,
+      pages: RefCell::new(VecDeque::with_capacity(PAGE_CACHE_SIZE + 1)),
+      dims: RefCell::new(FxHashMap::default()),
+    }
+  }
+
   pub fn page(&self, index: usize) -> Option<PdfPage> {
-    unsafe {
-      let page = mp_load_page(self.ctx.0, self.doc, index as libc::c_int);
+    let mut pages = self.pages.borrow_mut();
+    let page = if let Some(position) = pages.iter().position(|(i, _)| *i == index) {
+      let entry = pages.remove(position).unwrap();
+      pages.push_back(entry);
+      entry.1
+    } else {
+      let page = unsafe { mp_load_page(self.ctx.0, self.doc, index as libc::c_int) };
       if page.is_null() {
-        None
-      } else {
-        Some(PdfPage {
-          ctx: self.ctx.clone(),
-          page,
-          index,
-          _doc: self,
-        })
+        return None;
       }
-    }
+      pages.push_back((index, page));
+      if pages.len() > PAGE_CACHE_SIZE {
+        if let Some((_, page)) = pages.pop_front() {
+          unsafe { fz_drop_page(self.ctx.0, page) };
+        }
+      }
+      page
+    };
+    // The cache and the returned page each hold a reference.
+    unsafe { fz_keep_page(self.ctx.0, page) };
+    Some(PdfPage {
+      ctx: self.ctx.clone(),
+      page,
+      index,
+      _doc: self,
+    })
   }
 
   fn walk_toc(outline: *mut FzOutline, index: &mut usize) -> Vec<TocEntry> {
@@ -170,7 +205,12 @@ impl PdfDocument {
 
 impl Document for PdfDocument {
   fn dims(&self, index: usize) -> Option<(f32, f32)> {
-    self.page(index).map(|page| page.dims())
+    if let Some(dims) = self.dims.borrow().get(&index) {
+      return Some(*dims);
+    }
+    let dims = self.page(index).map(|page| page.dims())?;
+    self.dims.borrow_mut().insert(index, dims);
+    Some(dims)
   }
 
   fn pages_count(&self) -> usize {
@@ -269,7 +309,12 @@ impl Document for PdfDocument {
 
   fn layout(&mut self, width: u32, height: u32, font_size: f32, dpi: u16) {
     let em = pt_to_px(font_size, dpi);
+    // The pages of reflowable documents change with the layout.
+    self.dims.get_mut().clear();
     unsafe {
+      for (_, page) in self.pages.get_mut().drain(..) {
+        fz_drop_page(self.ctx.0, page);
+      }
       fz_layout_document(
         self.ctx.0,
         self.doc,
@@ -282,10 +327,12 @@ impl Document for PdfDocument {
 
   fn set_text_align(&mut self, _text_align: TextAlign) {}
 
-  fn set_font_family(&mut self, _family_name: &str, _search_path: &str) {}
+  fn set_font_family(&mut self, _files: &FamilyFiles) {}
 
   fn set_margin_width(&mut self, _width: i32) {}
 
+  fn set_margins(&mut self, _margins: &PageMargins) {}
+
   fn set_line_height(&mut self, _line_height: f32) {}
 }
 
@@ -485,6 +532,9 @@ impl Drop for PdfContext {
 impl Drop for PdfDocument {
   fn drop(&mut self) {
     unsafe {
+      for (_, page) in self.pages.get_mut().drain(..) {
+        fz_drop_page(self.ctx.0, page);
+      }
       fz_drop_document(self.ctx.0, self.doc);
     }
   }
@@ -0,0 +1,307 @@
+//! The registry of the document backends.
+//!
+//! Each backend implements `DocumentOpener` and claims a set of file extensions, and can
+//! recognize its files by their first bytes. A backend provided by another crate, or compiled
+//! for a specific device, is added with `register`: it takes precedence over the built-in
+//! backends.
+
+use super::{
+  djvu::DjvuOpener,
+  epub::EpubDocument,
+  html::HtmlDocument,
+  images::ImageFolderDocument,
+  pdf::PdfOpener,
+  Document,
+};
+use anyhow::{format_err, Error};
+use lazy_static::lazy_static;
+use std::{
+  ffi::OsStr,
+  fs::File,
+  io::Read,
+  path::Path,
+  sync::{Arc, RwLock},
+};
+
+// The number of bytes given to `DocumentOpener::sniff`.
+pub const MAGIC_LEN: usize = 64;
+
+pub trait DocumentOpener: Send + Sync {
+  fn name(&self) -> &'static str;
+  // The extensions of the files handled by this backend: the first one is the kind given to
+  // the files recognized by their magic bytes.
+  fn extensions(&self) -> &'static [&'static str];
+  // Whether the first bytes of a file belong to this backend.
+  fn sniff(&self, _magic: &[u8]) -> bool {
+    false
+  }
+  fn open(&self, path: &Path) -> Result<Box<dyn Document>, Error>;
+}
+
+struct EpubBackend;
+
+impl DocumentOpener for EpubBackend {
+  fn name(&self) -> &'static str {
+    "EPUB"
+  }
+
+  fn extensions(&self) -> &'static [&'static str] {
+    &["epub"]
+  }
+
+  // The first entry of an EPUB archive is its stored mimetype.
+  fn sniff(&self, magic: &[u8]) -> bool {
+    magic.starts_with(b"PK\x03\x04")
+      && magic.get(30..58) == Some(&b"mimetypeapplication/epub+zip"[..])
+  }
+
+  fn open(&self, path: &Path) -> Result<Box<dyn Document>, Error> {
+    EpubDocument::new(path).map(|d| Box::new(d) as Box<dyn Document>)
+  }
+}
+
+struct HtmlBackend;
+
+impl DocumentOpener for HtmlBackend {
+  fn name(&self) -> &'static str {
+    "HTML"
+  }
+
+  fn extensions(&self) -> &'static [&'static str] {
+    &["html", "htm"]
+  }
+
+  fn sniff(&self, magic: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(magic)
+      .trim_start_matches('\u{FEFF}')
+      .trim_start()
+      .to_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+  }
+
+  fn open(&self, path: &Path) -> Result<Box<dyn Document>, Error> {
+    HtmlDocument::new(path).map(|d| Box::new(d) as Box<dyn Document>)
+  }
+}
+
+struct DjvuBackend;
+
+impl DocumentOpener for DjvuBackend {
+  fn name(&self) -> &'static str {
+    "DjVuLibre"
+  }
+
+  fn extensions(&self) -> &'static [&'static str] {
+    &["djvu", "djv"]
+  }
+
+  fn sniff(&self, magic: &[u8]) -> bool {
+    magic.starts_with(b"AT&TFORM")
+  }
+
+  fn open(&self, path: &Path) -> Result<Box<dyn Document>, Error> {
+    DjvuOpener::new()
+      .and_then(|o| o.open(path))
+      .map(|d| Box::new(d) as Box<dyn Document>)
+      .ok_or_else(|| format_err!("can't open the DjVu document"))
+  }
+}
+
+// MuPDF also opens the files that no other backend claims.
+struct MupdfBackend;
+
+impl DocumentOpener for MupdfBackend {
+  fn name(&self) -> &'static str {
+    "MuPDF"
+  }
+
+  // cd mupdf/source && awk '/_extensions\[/,/}/' */*.c
+  fn extensions(&self) -> &'static [&'static str] {
+    &[
+      "pdf",
+      "ai",
+      "cbt",
+      "cbz",
+      "tar",
+      "zip",
+      "bmp",
+      "gif",
+      "hdp",
+      "j2k",
+      "jfif",
+      "jfif-tbnl",
+      "jp2",
+      "jpe",
+      "jpeg",
+      "jpg",
+      "jpx",
+      "jxr",
+      "pam",
+      "pbm",
+      "pgm",
+      "png",
+      "pnm",
+      "ppm",
+      "wdp",
+      "tif",
+      "tiff",
+      "gproof",
+      "fb2",
+      "xhtml",
+      "xml",
+      "svg",
+      "oxps",
+      "xps",
+    ]
+  }
+
+  fn sniff(&self, magic: &[u8]) -> bool {
+    magic.starts_with(b"%PDF")
+  }
+
+  fn open(&self, path: &Path) -> Result<Box<dyn Document>, Error> {
+    PdfOpener::new()
+      .and_then(|o| o.open(path))
+      .map(|d| Box::new(d) as Box<dyn Document>)
+      .ok_or_else(|| format_err!("can't open the document"))
+  }
+}
+
+lazy_static! {
+  // The backends, from the lowest to the highest precedence.
+  static ref OPENERS: RwLock<Vec<Arc<dyn DocumentOpener>>> = RwLock::new(vec![
+    Arc::new(MupdfBackend) as Arc<dyn DocumentOpener>,
+    Arc::new(DjvuBackend),
+    Arc::new(HtmlBackend),
+    Arc::new(EpubBackend),
+  ]);
+}
+
+pub fn register(opener: Arc<dyn DocumentOpener>) {
+  if let Ok(mut openers) = OPENERS.write() {
+    openers.push(opener);
+  }
+}
+
+fn extension<P: AsRef<Path>>(path: P) -> Option<String> {
+  path
+    .as_ref()
+    .extension()
+    .and_then(OsStr::to_str)
+    .map(str::to_lowercase)
+}
+
+fn read_magic(path: &Path) -> Option<Vec<u8>> {
+  let mut magic = Vec::with_capacity(MAGIC_LEN);
+  File::open(path)
+    .ok()?
+    .take(MAGIC_LEN as u64)
+    .read_to_end(&mut magic)
+    .ok()?;
+  Some(magic)
+}
+
+fn opener_by_extension(extension: &str) -> Option<Arc<dyn DocumentOpener>> {
+  let openers = OPENERS.read().ok()?;
+  openers
+    .iter()
+    .rev()
+    .find(|o| o.extensions().contains(&extension))
+    .cloned()
+}
+
+fn opener_by_magic(magic: &[u8]) -> Option<Arc<dyn DocumentOpener>> {
+  let openers = OPENERS.read().ok()?;
+  openers.iter().rev().find(|o| o.sniff(magic)).cloned()
+}
+
+// The extension decides, unless no backend claims it: the first bytes of the file are then
+// checked.
+pub fn opener_for<P: AsRef<Path>>(path: P) -> Option<Arc<dyn DocumentOpener>> {
+  let path = path.as_ref();
+  extension(path)
+    .and_then(|e| opener_by_extension(&e))
+    .or_else(|| read_magic(path).and_then(|m| opener_by_magic(&m)))
+}
+
+// Returns the kind of a document: its extension, or the main extension of the backend that
+// recognized its first bytes.
+pub fn file_kind<P: AsRef<Path>>(path: P) -> Option<String> {
+  let path = path.as_ref();
+  let extension = extension(path);
+  if extension
+    .as_ref()
+    .and_then(|e| opener_by_extension(e))
+    .is_some()
+  {
+    return extension;
+  }
+  read_magic(path)
+    .and_then(|m| opener_by_magic(&m))
+    .map(|o| o.extensions()[0].to_string())
+    .or(extension)
+}
+
+pub fn open<P: AsRef<Path>>(path: P) -> Option<Box<dyn Document>> {
+  let path = path.as_ref();
+  if path.is_dir() {
+    return ImageFolderDocument::new(path)
+      .map_err(|e| eprintln!("{}: {}.", path.display(), e))
+      .map(|d| Box::new(d) as Box<dyn Document>)
+      .ok();
+  }
+  let opener = opener_for(path).unwrap_or_else(|| Arc::new(MupdfBackend));
+  opener
+    .open(path)
+    .map_err(|e| eprintln!("{}: {} ({}).", path.display(), e, opener.name()))
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{opener_by_extension, opener_by_magic, register, Document, DocumentOpener};
+  use anyhow::{format_err, Error};
+  use std::{path::Path, sync::Arc};
+
+  struct TextBackend;
+
+  impl DocumentOpener for TextBackend {
+    fn name(&self) -> &'static str {
+      "Text"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+      &["txt", "pdf"]
+    }
+
+    fn open(&self, _path: &Path) -> Result<Box<dyn Document>, Error> {
+      Err(format_err!("not implemented"))
+    }
+  }
+
+  #[test]
+  fn test_lookup() {
+    assert_eq!(
+      opener_by_extension("djv").map(|o| o.name()),
+      Some("DjVuLibre")
+    );
+    assert_eq!(opener_by_extension("cbz").map(|o| o.name()), Some("MuPDF"));
+    assert!(opener_by_extension("txt").is_none());
+    let mut epub = b"PK\x03\x04".to_vec();
+    epub.extend_from_slice(&[0; 26]);
+    epub.extend_from_slice(b"mimetypeapplication/epub+zip");
+    assert_eq!(opener_by_magic(&epub).map(|o| o.name()), Some("EPUB"));
+    assert_eq!(
+      opener_by_magic(b"%PDF-1.7").map(|o| o.name()),
+      Some("MuPDF")
+    );
+    assert_eq!(
+      opener_by_magic(b"\xEF\xBB\xBF <!DOCTYPE HTML>").map(|o| o.name()),
+      Some("HTML")
+    );
+    assert!(opener_by_magic(b"PK\x03\x04").is_none());
+    register(Arc::new(TextBackend));
+    assert_eq!(opener_by_extension("txt").map(|o| o.name()), Some("Text"));
+    assert_eq!(opener_by_extension("pdf").map(|o| o.name()), Some("Text"));
+  }
+}
@@ -0,0 +1,121 @@
+//! Extracts the headings of a document along with the first sentence of each section.
+
+use super::{Document, Location, TocEntry};
+
+// The number of pages searched for the beginning of a section.
+const SKIM_PAGES: usize = 2;
+// Longer sentences are truncated.
+const SKIM_MAX_WORDS: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct SkimEntry {
+  pub title: String,
+  pub location: Location,
+  pub depth: usize,
+  pub excerpt: String,
+}
+
+pub fn skim(doc: &mut dyn Document, toc: &[TocEntry]) -> Vec<SkimEntry> {
+  let mut entries = Vec::new();
+  skim_aux(doc, toc, 0, &mut entries);
+  entries
+}
+
+fn skim_aux(doc: &mut dyn Document, toc: &[TocEntry], depth: usize, entries: &mut Vec<SkimEntry>) {
+  for entry in toc {
+    let mut words = Vec::new();
+    let mut loc = doc
+      .resolve_location(entry.location.clone())
+      .map(Location::Exact);
+    for _ in 0..SKIM_PAGES {
+      match loc.and_then(|loc| doc.words(loc)) {
+        Some((page_words, offset)) => {
+          words.extend(page_words.into_iter().map(|word| word.text));
+          loc = Some(Location::Next(offset));
+        },
+        None => break,
+      }
+    }
+    entries.push(SkimEntry {
+      title: entry.title.clone(),
+      location: entry.location.clone(),
+      depth,
+      excerpt: first_sentence(&words, &entry.title),
+    });
+    skim_aux(doc, &entry.children, depth + 1, entries);
+  }
+}
+
+fn normalize(word: &str) -> String {
+  word
+    .chars()
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+pub fn ends_sentence(word: &str) -> bool {
+  word
+    .trim_end_matches(|c: char| "\"')]»”’".contains(c))
+    .ends_with(|c: char| ".!?…".contains(c))
+}
+
+// Returns the first sentence that follows the heading *title* in *words*.
+pub fn first_sentence(words: &[String], title: &str) -> String {
+  let heading: Vec<String> = title
+    .split_whitespace()
+    .map(normalize)
+    .filter(|w| !w.is_empty())
+    .collect();
+  let text: Vec<(usize, String)> = words
+    .iter()
+    .enumerate()
+    .map(|(i, w)| (i, normalize(w)))
+    .filter(|(_, w)| !w.is_empty())
+    .collect();
+
+  let start = if heading.is_empty() {
+    0
+  } else {
+    text
+      .windows(heading.len())
+      .find(|window| window.iter().zip(&heading).all(|((_, a), b)| a == b))
+      .map_or(0, |window| window[window.len() - 1].0 + 1)
+  };
+
+  let mut sentence = Vec::new();
+  for word in words[start..].iter().take(SKIM_MAX_WORDS) {
+    sentence.push(word.as_str());
+    if ends_sentence(word) {
+      return sentence.join(" ");
+    }
+  }
+
+  if sentence.len() == SKIM_MAX_WORDS {
+    format!("{}…", sentence.join(" "))
+  } else {
+    sentence.join(" ")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::first_sentence;
+
+  fn words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(String::from).collect()
+  }
+
+  #[test]
+  fn test_first_sentence() {
+    let page = words("Chapter 2 The Method. We sampled forty wells. Then we waited.");
+    assert_eq!(
+      first_sentence(&page, "Chapter 2: The Method"),
+      "We sampled forty wells."
+    );
+    assert_eq!(first_sentence(&page, "Unrelated"), "Chapter 2 The Method.");
+    let page = words("Results Did it “work?” Mostly.");
+    assert_eq!(first_sentence(&page, "Results"), "Did it “work?”");
+    assert_eq!(first_sentence(&words("Summary"), "Summary"), "");
+  }
+}
@@ -1,44 +1,93 @@
 #[macro_use]
 mod geom;
+#[macro_use]
+mod i18n;
 mod app;
 mod battery;
+mod citation;
+#[cfg(feature = "cloud-sync")]
+mod cloud_sync;
 mod color;
+mod conversion;
 mod device;
 mod dictionary;
 mod document;
+#[cfg(feature = "feeds")]
+mod feed;
+mod feedback;
 mod font;
 mod framebuffer;
 mod frontlight;
 mod gesture;
 mod helpers;
 mod input;
+mod insights;
 mod library;
 mod lightsensor;
+mod matrix;
 mod metadata;
+mod network;
+#[cfg(feature = "opds")]
+mod opds;
+mod opf;
+mod phrasebook;
+mod qrcode;
+mod recorder;
+mod resources;
+mod resume;
 mod rtc;
+mod scenario;
+mod scheduler;
+mod search_history;
 mod settings;
+mod share;
 mod symbolic_path;
+mod tts;
 mod unit;
 mod view;
 
+#[cfg(feature = "cloud-sync")]
+use crate::cloud_sync::spawn_sync;
+#[cfg(feature = "dictionary-manager")]
+use crate::view::dictionary::manager::DictionaryManager;
+#[cfg(feature = "feeds")]
+use crate::view::feeds::Feeds;
+#[cfg(feature = "opds")]
+use crate::view::opds::OpdsBrowser;
 use crate::{
   app::Context,
-  battery::{Battery, FakeBattery},
+  battery::{Battery, FakeBattery, Status},
+  conversion::queue_conversions,
   device::CURRENT_DEVICE,
   document::sys_info_as_html,
+  feedback::Feedback,
   font::Fonts,
-  framebuffer::{Framebuffer, UpdateMode},
+  framebuffer::{Framebuffer, Pixmap, UpdateMode},
   frontlight::{Frontlight, LightLevels},
   geom::Rectangle,
   gesture::gesture_events,
   helpers::{load_toml, save_toml},
-  input::{DeviceEvent, FingerStatus},
+  input::{
+    current_time,
+    device_events,
+    display_rotate_event,
+    finger_raw_events,
+    DeviceEvent,
+    FingerStatus,
+    PowerSource,
+  },
+  insights::{insights_as_html, load_insights, purge_insights, Usage, INSIGHTS_PATH},
   library::Library,
   lightsensor::LightSensor,
+  matrix::{render_screens, run_matrix, MATRIX_ARG, MATRIX_DEVICE_ARG},
+  phrasebook::{export_phrasebook, load_phrasebook, phrasebook_as_html, PHRASEBOOK_PATH},
+  recorder::{replay_raw_events, Recorder, Session, RECORD_INPUT_VAR, REPLAY_INPUT_VAR},
+  scenario::{Scenario, Step, SCENARIO_ARG},
   settings::{Settings, SETTINGS_PATH},
   view::{
     calculator::Calculator,
     common::{
+      fetches_when_online,
       locate,
       locate_by_id,
       overlapping_rectangle,
@@ -52,14 +101,20 @@ use crate::{
     handle_event,
     home::Home,
     menu::{Menu, MenuKind},
-    notification::Notification,
+    notification::{notify_progress, push_notification, Notification, NotificationPriority},
     process_render_queue,
+    progress::show_progress,
     reader::Reader,
+    report::show_report,
     sketch::Sketch,
+    tap_zones_editor::TapZonesEditor,
+    transition::Transition,
+    undo::{revert_last, show_undo_notification, UndoDir},
     AppCmd,
     EntryId,
     EntryKind,
     Event,
+    Hub,
     RenderData,
     RenderQueue,
     View,
@@ -76,7 +131,17 @@ use sdl2::{
   rect::{Point as SdlPoint, Rect as SdlRect},
   render::{BlendMode, WindowCanvas},
 };
-use std::{collections::VecDeque, fs::File, mem, path::Path, sync::mpsc, thread, time::Duration};
+use std::{
+  collections::VecDeque,
+  env,
+  fs::File,
+  mem,
+  path::{Path, PathBuf},
+  process,
+  sync::mpsc,
+  thread,
+  time::Duration,
+};
 
 pub const APP_NAME: &str = "Plato";
 const DEFAULT_ROTATION: i8 = 1;
@@ -84,13 +149,15 @@ const DEFAULT_ROTATION: i8 = 1;
 const CLOCK_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
 
 pub fn build_context(fb: Box<dyn Framebuffer>) -> Result<Context, Error> {
-  let settings = load_toml::<Settings, _>(SETTINGS_PATH)?;
+  let mut settings = load_toml::<Settings, _>(SETTINGS_PATH)?;
+  settings.unbind_duplicate_gestures();
   let library_settings = &settings.libraries[settings.selected_library];
   let library = Library::new(&library_settings.path, library_settings.mode);
 
   let battery = Box::new(FakeBattery::new()) as Box<dyn Battery>;
   let frontlight = Box::new(LightLevels::default()) as Box<dyn Frontlight>;
   let lightsensor = Box::new(0u16) as Box<dyn LightSensor>;
+  let feedback = Box::new(()) as Box<dyn Feedback>;
   let fonts = Fonts::load()?;
 
   Ok(Context::new(
@@ -102,6 +169,7 @@ pub fn build_context(fb: Box<dyn Framebuffer>) -> Result<Context, Error> {
     battery,
     frontlight,
     lightsensor,
+    feedback,
   ))
 }
 
@@ -241,7 +309,79 @@ impl Framebuffer for WindowCanvas {
   }
 }
 
+// Applies a scenario step to the fake devices.
+fn apply_step(step: Step, hub: &Hub, context: &mut Context) {
+  match step {
+    Step::Capacity(capacity) => {
+      let status = context.battery.status().unwrap_or(Status::Discharging);
+      context.battery = Box::new(FakeBattery::with_state(capacity, status));
+      hub.send(Event::BatteryTick).ok();
+      hub.send(Event::CheckBattery).ok();
+    },
+    Step::Status(status) => {
+      let capacity = context.battery.capacity().unwrap_or(50.0);
+      context.battery = Box::new(FakeBattery::with_state(capacity, status));
+      let evt = if status == Status::Discharging {
+        DeviceEvent::Unplug(PowerSource::Wall)
+      } else {
+        DeviceEvent::Plug(PowerSource::Wall)
+      };
+      hub.send(Event::Device(evt)).ok();
+    },
+    Step::Light(level) => {
+      context.lightsensor = Box::new(level);
+      if context.settings.auto_frontlight && context.settings.frontlight {
+        context.guess_frontlight();
+      }
+    },
+    Step::Network(true) => {
+      hub.send(Event::Device(DeviceEvent::NetUp)).ok();
+    },
+    Step::Network(false) => {
+      context.online = false;
+    },
+  }
+}
+
+// Selects the emulated device, given as `PRODUCT[:MODEL_NUMBER]`. This needs to happen before
+// the current device is first accessed.
+fn set_device(spec: &str) {
+  let mut parts = spec.splitn(2, ':');
+  env::set_var("PRODUCT", parts.next().unwrap_or_default());
+  env::set_var("MODEL_NUMBER", parts.next().unwrap_or_default());
+}
+
 fn main() -> Result<(), Error> {
+  let mut scenario_path = None;
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--device" => {
+        let spec = args.next().context("Missing device.")?;
+        set_device(&spec);
+      },
+      SCENARIO_ARG => {
+        scenario_path = Some(PathBuf::from(args.next().context("Missing scenario.")?));
+      },
+      MATRIX_ARG => {
+        let dir = PathBuf::from(args.next().context("Missing output directory.")?);
+        return run_matrix(&dir);
+      },
+      MATRIX_DEVICE_ARG => {
+        let dir = PathBuf::from(args.next().context("Missing output directory.")?);
+        let (width, height) = CURRENT_DEVICE.dims;
+        let mut context = build_context(Box::new(Pixmap::new(width, height)))?;
+        let issues = render_screens(&dir, &mut context)?;
+        println!(
+          "{} ({}x{}, {} DPI): {} issue(s).",
+          CURRENT_DEVICE.model, width, height, CURRENT_DEVICE.dpi, issues
+        );
+        process::exit(if issues > 0 { 1 } else { 0 });
+      },
+      _ => eprintln!("Unknown argument: {}.", arg),
+    }
+  }
+
   let sdl_context = sdl2::init().unwrap();
   let video_subsystem = sdl_context.video().unwrap();
   let (width, height) = CURRENT_DEVICE.dims;
@@ -261,10 +401,16 @@ fn main() -> Result<(), Error> {
   }
 
   context.load_dictionaries();
+  context.load_search_history();
   context.load_keyboard_layouts();
+  context.load_font_index();
+  context.load_catalog();
 
   let (tx, rx) = mpsc::channel();
   let (ty, ry) = mpsc::channel();
+  if context.settings.import.startup_trigger {
+    queue_conversions(&tx, &context);
+  }
   let touch_screen = gesture_events(ry);
 
   let tx2 = tx.clone();
@@ -305,7 +451,42 @@ fn main() -> Result<(), Error> {
     context.fb.rect().height()
   );
 
+  let mut recorder = match env::var_os(RECORD_INPUT_VAR) {
+    Some(path) => {
+      Some(Recorder::new(&path, context.display).context("Can't record input events.")?)
+    },
+    None => None,
+  };
+  let mut recorded_rotation = context.display.rotation;
+  let mut finger_down = false;
+
+  if let Some(path) = env::var_os(REPLAY_INPUT_VAR) {
+    let session = Session::load(&path).context("Can't load input session.")?;
+    if session.product != env::var("PRODUCT").unwrap_or_default() {
+      eprintln!(
+        "The input session was recorded on a different device ({}).",
+        session.product
+      );
+    }
+    let display = session.display.unwrap_or(context.display);
+    let (raw_sender, raw_receiver) = mpsc::channel();
+    replay_raw_events(session, raw_sender);
+    let replayed = device_events(raw_receiver, display, context.settings.button_scheme);
+    let ty2 = ty.clone();
+    thread::spawn(move || {
+      while let Ok(evt) = replayed.recv() {
+        ty2.send(evt).ok();
+      }
+    });
+  }
+
   let mut bus = VecDeque::with_capacity(4);
+  let mut transition: Option<Transition> = None;
+
+  let mut scenario = match scenario_path {
+    Some(path) => Some(Scenario::load(&path).context("Can't load scenario.")?),
+    None => None,
+  };
 
   'outer: loop {
     if let Some(sdl_evt) = sdl_context.event_pump().unwrap().wait_event_timeout(20) {
@@ -345,16 +526,69 @@ fn main() -> Result<(), Error> {
             let rot = (5 + context.display.rotation) % 4;
             ty.send(DeviceEvent::RotateScreen(rot)).ok();
           },
+          Scancode::Minus | Scancode::Equals => {
+            let delta = if scancode == Scancode::Minus {
+              -5.0
+            } else {
+              5.0
+            };
+            let capacity = context.battery.capacity().unwrap_or(50.0) + delta;
+            apply_step(
+              Step::Capacity(capacity.max(0.0).min(100.0)),
+              &tx,
+              &mut context,
+            );
+          },
+          Scancode::C => {
+            let status = if context.plugged {
+              Status::Discharging
+            } else {
+              Status::Charging
+            };
+            apply_step(Step::Status(status), &tx, &mut context);
+          },
+          Scancode::N => {
+            apply_step(Step::Network(!context.online), &tx, &mut context);
+          },
           _ => (),
         },
         _ => {
           if let Some(dev_evt) = device_event(sdl_evt) {
+            if let DeviceEvent::Finger {
+              id,
+              status,
+              position,
+              ..
+            } = dev_evt
+            {
+              // The mouse also moves when no button is pressed.
+              let is_hovering = status == FingerStatus::Motion && !finger_down;
+              finger_down =
+                status != FingerStatus::Up && (finger_down || status == FingerStatus::Down);
+              if let Some(recorder) = recorder.as_mut().filter(|_| !is_hovering) {
+                for evt in finger_raw_events(id, status, position, current_time(), context.display)
+                {
+                  recorder
+                    .record(&evt)
+                    .map_err(|e| eprintln!("Can't record input event: {:#}.", e))
+                    .ok();
+                }
+              }
+            }
             ty.send(dev_evt).ok();
           }
         },
       }
     }
 
+    if let Some(scenario) = scenario.as_mut() {
+      for step in scenario.due() {
+        apply_step(step, &tx, &mut context);
+      }
+    }
+
+    let view_id = view.id();
+
     while let Ok(evt) = rx.recv_timeout(Duration::from_millis(20)) {
       match evt {
         Event::Open(info) => {
@@ -401,10 +635,28 @@ fn main() -> Result<(), Error> {
           history.push(view as Box<dyn View>);
           view = next_view;
         },
+        Event::OpenHtml(ref html) => {
+          let r = Reader::from_html(context.fb.rect(), html, &tx, &mut context);
+          let mut next_view = Box::new(r) as Box<dyn View>;
+          transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
+          history.push(view as Box<dyn View>);
+          view = next_view;
+        },
         Event::Select(EntryId::Launch(app_cmd)) => {
           view.children_mut().retain(|child| !child.is::<Menu>());
+          context.record_usage(Usage::launch(&app_cmd));
           let mut next_view: Box<dyn View> = match app_cmd {
-            AppCmd::Sketch => Box::new(Sketch::new(context.fb.rect(), &mut rq, &mut context)),
+            AppCmd::Sketch { file } => Box::new(Sketch::new(
+              context.fb.rect(),
+              file.as_deref(),
+              &mut rq,
+              &mut context,
+            )),
+            AppCmd::TapZones => Box::new(TapZonesEditor::new(
+              context.fb.rect(),
+              &mut rq,
+              &mut context,
+            )),
             AppCmd::Calculator => Box::new(Calculator::new(
               context.fb.rect(),
               &tx,
@@ -422,6 +674,22 @@ fn main() -> Result<(), Error> {
               &mut rq,
               &mut context,
             )),
+            #[cfg(feature = "feeds")]
+            AppCmd::Feeds => Box::new(Feeds::new(context.fb.rect(), &tx, &mut rq, &mut context)),
+            #[cfg(feature = "dictionary-manager")]
+            AppCmd::Dictionaries => Box::new(DictionaryManager::new(
+              context.fb.rect(),
+              &tx,
+              &mut rq,
+              &mut context,
+            )),
+            #[cfg(feature = "opds")]
+            AppCmd::Opds => Box::new(OpdsBrowser::new(
+              context.fb.rect(),
+              &tx,
+              &mut rq,
+              &mut context,
+            )),
           };
           transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
           history.push(view as Box<dyn View>);
@@ -452,7 +720,7 @@ fn main() -> Result<(), Error> {
               ViewId::PresetMenu,
               MenuKind::Contextual,
               vec![EntryKind::Command(
-                "Remove".to_string(),
+                tr!("Remove"),
                 EntryId::RemovePreset(index),
               )],
               &mut context,
@@ -505,6 +773,43 @@ fn main() -> Result<(), Error> {
             rq.add(RenderData::expose(rect, UpdateMode::Gui));
           }
         },
+        Event::Expire(id) => {
+          if let Some(index) = view.children().iter().position(|c| c.id() == id) {
+            let rect = overlapping_rectangle(view.child(index));
+            rq.add(RenderData::expose(rect, UpdateMode::Gui));
+            view.children_mut().remove(index);
+          }
+        },
+        Event::NotifyProgress(id, msg, progress) => {
+          notify_progress(
+            view.children_mut(),
+            id,
+            msg,
+            progress,
+            &tx,
+            &mut rq,
+            &mut context,
+          );
+        },
+        Event::Error(report) => {
+          show_report(view.children_mut(), &report, &tx, &mut rq, &mut context);
+        },
+        Event::Progress(id, msg, progress) => {
+          show_progress(
+            view.children_mut(),
+            id,
+            msg,
+            progress,
+            &mut rq,
+            &mut context,
+          );
+        },
+        Event::Select(EntryId::RemoveSavedSearch(id, ref text)) => {
+          context.remove_saved_search(text, id);
+        },
+        Event::Select(EntryId::ClearNotifications) => {
+          context.notifications.clear();
+        },
         Event::Close(id) => {
           if let Some(index) = locate_by_id(view.as_ref(), id) {
             let rect = overlapping_rectangle(view.child(index));
@@ -528,13 +833,95 @@ fn main() -> Result<(), Error> {
         },
         Event::Select(EntryId::SystemInfo) => {
           view.children_mut().retain(|child| !child.is::<Menu>());
-          let html = sys_info_as_html();
+          let home = context.library.home.clone();
+          let hub = tx.clone();
+          thread::spawn(move || {
+            hub.send(Event::OpenHtml(sys_info_as_html(&home))).ok();
+          });
+        },
+        Event::Select(EntryId::BrowsePhrasebook) => {
+          view.children_mut().retain(|child| !child.is::<Menu>());
+          let phrases = load_phrasebook(PHRASEBOOK_PATH).unwrap_or_else(|e| {
+            eprintln!("Can't load the phrasebook: {:#}.", e);
+            Vec::new()
+          });
+          let html = phrasebook_as_html(&phrases);
+          let r = Reader::from_html(context.fb.rect(), &html, &tx, &mut context);
+          let mut next_view = Box::new(r) as Box<dyn View>;
+          transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
+          history.push(view as Box<dyn View>);
+          view = next_view;
+        },
+        Event::Undo | Event::Select(EntryId::Revert(UndoDir::Undo)) => {
+          revert_last(
+            UndoDir::Undo,
+            view.as_mut(),
+            &tx,
+            &mut bus,
+            &mut rq,
+            &mut context,
+          );
+        },
+        Event::Redo | Event::Select(EntryId::Revert(UndoDir::Redo)) => {
+          revert_last(
+            UndoDir::Redo,
+            view.as_mut(),
+            &tx,
+            &mut bus,
+            &mut rq,
+            &mut context,
+          );
+        },
+        Event::UndoRecorded => {
+          show_undo_notification(view.as_mut(), &tx, &mut rq, &mut context);
+        },
+        Event::Select(EntryId::ExportPhrasebook) => {
+          let text = match export_phrasebook(&context.library.home) {
+            Ok(path) => tr!("Phrasebook exported to {}.", path.display()),
+            Err(e) => tr!("Can't export the phrasebook: {}.", format!("{:#}", e)),
+          };
+          let notif = Notification::new(ViewId::PhrasebookNotif, text, &mut context);
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+        },
+        Event::Select(EntryId::SetLanguage(ref language)) => {
+          if *language != context.settings.language {
+            context.settings.language = language.clone();
+            context.load_catalog();
+            let notif = Notification::new(
+              ViewId::MessageNotif,
+              tr!("The views opened from now on will use the new language."),
+              &mut context,
+            );
+            push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+          }
+        },
+        Event::Select(EntryId::ToggleUsageInsights) => {
+          context.settings.usage_insights = !context.settings.usage_insights;
+        },
+        Event::Select(EntryId::TogglePrivateSleepScreen) => {
+          context.settings.private_sleep_screen = !context.settings.private_sleep_screen;
+        },
+        Event::Select(EntryId::BrowseInsights) => {
+          view.children_mut().retain(|child| !child.is::<Menu>());
+          let records = load_insights(INSIGHTS_PATH).unwrap_or_else(|e| {
+            eprintln!("Can't load the usage insights: {:#}.", e);
+            Vec::new()
+          });
+          let html = insights_as_html(&records, context.settings.usage_insights);
           let r = Reader::from_html(context.fb.rect(), &html, &tx, &mut context);
           let mut next_view = Box::new(r) as Box<dyn View>;
           transfer_notifications(view.as_mut(), next_view.as_mut(), &mut rq, &mut context);
           history.push(view as Box<dyn View>);
           view = next_view;
         },
+        Event::Select(EntryId::PurgeInsights) => {
+          let text = match purge_insights(INSIGHTS_PATH) {
+            Ok(()) => tr!("Usage insights purged."),
+            Err(e) => tr!("Can't purge the usage insights: {}.", format!("{:#}", e)),
+          };
+          let notif = Notification::new(ViewId::MessageNotif, text, &mut context);
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+        },
         Event::Select(EntryId::Rotate(n))
           if n != context.display.rotation && view.might_rotate() =>
         {
@@ -547,7 +934,7 @@ fn main() -> Result<(), Error> {
               view.resize(fb_rect, &tx, &mut rq, &mut context);
             }
           }
-        }
+        },
         Event::Select(EntryId::SetButtonScheme(button_scheme)) => {
           context.settings.button_scheme = button_scheme;
         },
@@ -570,16 +957,32 @@ fn main() -> Result<(), Error> {
         Event::Select(EntryId::TakeScreenshot) => {
           let name = Local::now().format("screenshot-%Y%m%d_%H%M%S.png");
           let msg = match context.fb.save(&name.to_string()) {
-            Err(e) => format!("Couldn't take screenshot: {}).", e),
-            Ok(_) => format!("Saved {}.", name),
+            Err(e) => tr!("Couldn't take screenshot: {}).", e),
+            Ok(_) => tr!("Saved {}.", name),
           };
-          let notif =
-            Notification::new(ViewId::TakeScreenshotNotif, msg, &tx, &mut rq, &mut context);
-          view.children_mut().push(Box::new(notif) as Box<dyn View>);
+          let notif = Notification::new(ViewId::TakeScreenshotNotif, msg, &mut context);
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
         },
         Event::Notify(msg) => {
-          let notif = Notification::new(ViewId::MessageNotif, msg, &tx, &mut rq, &mut context);
-          view.children_mut().push(Box::new(notif) as Box<dyn View>);
+          let notif = Notification::new(ViewId::MessageNotif, msg, &mut context);
+          push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+        },
+        Event::Select(EntryId::Copy(text)) => {
+          context.clipboard = Some(text);
+          tx.send(Event::Notify(tr!("Copied to the clipboard."))).ok();
+        },
+        Event::OpdsDownloaded(..) | Event::Converted(..) => {
+          view.handle_event(&evt, &tx, &mut bus, &mut rq, &mut context);
+          if !view.is::<Home>() {
+            let (tx, _rx) = mpsc::channel();
+            history[0].handle_event(
+              &evt,
+              &tx,
+              &mut VecDeque::new(),
+              &mut RenderQueue::new(),
+              &mut context,
+            );
+          }
         },
         Event::AddDocument(..) => {
           if view.is::<Home>() {
@@ -606,13 +1009,53 @@ fn main() -> Result<(), Error> {
               });
             } else {
               context.online = false;
+              context.network.reset();
             }
           }
         },
+        Event::AcquireNetwork(task) => {
+          if context.network.acquire(task, context.settings.wifi) {
+            tx.send(Event::SetWifi(true)).ok();
+          }
+        },
+        Event::ReleaseNetwork(task) => {
+          context.network.release(task);
+        },
+        Event::Device(DeviceEvent::Plug(..)) | Event::Device(DeviceEvent::Unplug(..)) => {
+          context.plugged = matches!(evt, Event::Device(DeviceEvent::Plug(..)));
+          if context.update_power_profile() {
+            let msg = tr!("Switched to the {} power profile.", context.power_profile);
+            tx.send(Event::Notify(msg.to_lowercase())).ok();
+          }
+          tx.send(Event::BatteryTick).ok();
+        },
+        Event::CheckBattery => {
+          if let Ok(v) = context.battery.capacity() {
+            if v < context.settings.battery.warn {
+              let notif = Notification::new(
+                ViewId::LowBatteryNotif,
+                tr!("The battery capacity is getting low."),
+                &mut context,
+              )
+              .with_priority(NotificationPriority::High);
+              push_notification(view.children_mut(), notif, &tx, &mut rq, &mut context);
+            }
+          }
+          if context.update_power_profile() {
+            let msg = tr!("Switched to the {} power profile.", context.power_profile);
+            tx.send(Event::Notify(msg.to_lowercase())).ok();
+          }
+        },
         Event::Device(DeviceEvent::NetUp) => {
+          context.online = true;
+          #[cfg(feature = "cloud-sync")]
+          spawn_sync(&tx, &context);
           if view.is::<Home>() {
             view.handle_event(&evt, &tx, &mut bus, &mut rq, &mut context);
           } else {
+            if fetches_when_online(view.as_ref()) {
+              view.handle_event(&evt, &tx, &mut bus, &mut rq, &mut context);
+            }
             let (tx, _rx) = mpsc::channel();
             history[0].handle_event(
               &evt,
@@ -635,8 +1078,28 @@ fn main() -> Result<(), Error> {
       }
     }
 
+    if view.id() != view_id {
+      transition = Transition::new(view.as_ref(), context.settings.transitions);
+    }
+
+    if let Some(t) = transition.as_ref() {
+      if t.play(view.as_ref(), &mut rq, &mut context, &mut updating) {
+        transition = None;
+      }
+    }
+
     process_render_queue(view.as_ref(), &mut rq, &mut context, &mut updating);
 
+    if context.display.rotation != recorded_rotation {
+      recorded_rotation = context.display.rotation;
+      if let Some(recorder) = recorder.as_mut() {
+        recorder
+          .record(&display_rotate_event(recorded_rotation))
+          .map_err(|e| eprintln!("Can't record input event: {:#}.", e))
+          .ok();
+      }
+    }
+
     while let Some(ce) = bus.pop_front() {
       tx.send(ce).ok();
     }
@@ -666,7 +1129,17 @@ fn main() -> Result<(), Error> {
     context.settings.frontlight_levels = context.frontlight.levels();
   }
 
-  context.library.flush();
+  if let Err(e) = context
+    .library
+    .flush()
+    .and_then(|_| context.library.compact())
+  {
+    eprintln!("Can't save the metadata: {:#}.", e);
+  }
+
+  if let Err(e) = context.save_search_history() {
+    eprintln!("Can't save the search history: {:#}.", e);
+  }
 
   let path = Path::new(SETTINGS_PATH);
   save_toml(&context.settings, path).context("Can't save settings.")?;
@@ -0,0 +1,370 @@
+//! Parses RSS and Atom feeds, and converts their entries to HTML documents.
+
+use crate::{
+  document::html::{dom::Node, xml::XmlParser},
+  helpers::decode_entities,
+  metadata::{FileInfo, Info},
+};
+use anyhow::{format_err, Error};
+use chrono::{DateTime, Local};
+use filetime::{set_file_mtime, FileTime};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+// The identifiers of the entries already fetched, for each feed URL.
+pub const HISTORY_FILENAME: &str = ".history.json";
+// The maximum number of characters of a file name.
+const MAX_NAME_LEN: usize = 96;
+// The elements whose content is dropped.
+const REMOVED_ELEMENTS: [&str; 7] = [
+  "script", "style", "iframe", "object", "video", "audio", "noscript",
+];
+
+lazy_static! {
+  static ref CDATA_RE: Regex = Regex::new(r"(?s)<!\[CDATA\[(.*?)\]\]>").unwrap();
+  static ref VOID_RE: Regex = Regex::new(
+    r"(?i)<(br|hr|wbr|col|area|base|embed|input|link|meta|param|source|track)\b([^>]*?)/?>"
+  )
+  .unwrap();
+  static ref IMAGE_RE: Regex = Regex::new(r"(?i)<img\b[^>]*>").unwrap();
+  static ref REMOVED_RES: Vec<Regex> = REMOVED_ELEMENTS
+    .iter()
+    .map(|name| Regex::new(&format!(r"(?is)<{0}\b[^>]*?/>|<{0}\b.*?</{0}\s*>", name)).unwrap())
+    .collect();
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Feed {
+  pub title: String,
+  pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FeedEntry {
+  pub id: String,
+  pub title: String,
+  pub link: String,
+  pub author: String,
+  pub published: Option<DateTime<Local>>,
+  // HTML fragment.
+  pub content: String,
+}
+
+pub fn parse_feed(text: &str) -> Result<Feed, Error> {
+  // The XML parser skips the CDATA sections.
+  let text = CDATA_RE.replace_all(text, |caps: &regex::Captures| escape(&caps[1]));
+  let root = XmlParser::new(&text).parse();
+
+  if let Some(channel) = root.find("channel") {
+    let mut items = Vec::new();
+    // The items are children of the channel in RSS 2.0, and siblings in RSS 1.0.
+    find_all(&root, "item", &mut items);
+    Ok(Feed {
+      title: child(channel, "title")
+        .map(text_content)
+        .unwrap_or_default(),
+      entries: items.into_iter().map(parse_item).collect(),
+    })
+  } else if let Some(feed) = root.find("feed") {
+    let mut entries = Vec::new();
+    find_all(feed, "entry", &mut entries);
+    Ok(Feed {
+      title: child(feed, "title").map(text_content).unwrap_or_default(),
+      entries: entries.into_iter().map(parse_entry).collect(),
+    })
+  } else {
+    Err(format_err!("unknown feed format"))
+  }
+}
+
+fn parse_item(item: &Node) -> FeedEntry {
+  let title = child(item, "title").map(text_content).unwrap_or_default();
+  let link = child(item, "link").map(text_content).unwrap_or_default();
+  let id = child(item, "guid")
+    .map(text_content)
+    .filter(|id| !id.is_empty())
+    .unwrap_or_else(|| {
+      if link.is_empty() {
+        title.clone()
+      } else {
+        link.clone()
+      }
+    });
+  let author = child(item, "creator")
+    .or_else(|| child(item, "author"))
+    .map(text_content)
+    .unwrap_or_default();
+  let published = child(item, "pubDate")
+    .and_then(|n| DateTime::parse_from_rfc2822(&text_content(n)).ok())
+    .or_else(|| {
+      child(item, "date").and_then(|n| DateTime::parse_from_rfc3339(&text_content(n)).ok())
+    })
+    .map(|dt| dt.with_timezone(&Local));
+  let content = child(item, "encoded")
+    .or_else(|| child(item, "description"))
+    .map(text_content)
+    .unwrap_or_default();
+  FeedEntry {
+    id,
+    title,
+    link,
+    author,
+    published,
+    content,
+  }
+}
+
+fn parse_entry(entry: &Node) -> FeedEntry {
+  let title = child(entry, "title").map(text_content).unwrap_or_default();
+  let link = entry
+    .children()
+    .into_iter()
+    .flatten()
+    .filter(|n| n.tag_name() == Some("link"))
+    .find(|n| n.attr("rel").map_or(true, |rel| rel == "alternate"))
+    .and_then(|n| n.attr("href"))
+    .map(|href| decode_entities(href).into_owned())
+    .unwrap_or_default();
+  let id = child(entry, "id")
+    .map(text_content)
+    .filter(|id| !id.is_empty())
+    .unwrap_or_else(|| link.clone());
+  let author = child(entry, "author")
+    .and_then(|n| child(n, "name"))
+    .map(text_content)
+    .unwrap_or_default();
+  let published = child(entry, "published")
+    .or_else(|| child(entry, "updated"))
+    .and_then(|n| DateTime::parse_from_rfc3339(&text_content(n)).ok())
+    .map(|dt| dt.with_timezone(&Local));
+  let content = child(entry, "content")
+    .or_else(|| child(entry, "summary"))
+    .map(|n| match n.attr("type") {
+      Some("xhtml") => {
+        let mut buf = String::new();
+        for c in n.children().into_iter().flatten() {
+          serialize(c, &mut buf);
+        }
+        buf
+      },
+      Some("html") | Some("text/html") => text_content(n),
+      _ => format!("<p>{}</p>", escape(&text_content(n))),
+    })
+    .unwrap_or_default();
+  FeedEntry {
+    id,
+    title,
+    link,
+    author,
+    published,
+    content,
+  }
+}
+
+pub fn child<'a>(node: &'a Node, name: &str) -> Option<&'a Node> {
+  node
+    .children()
+    .and_then(|children| children.iter().find(|c| c.tag_name() == Some(name)))
+}
+
+pub fn find_all<'a>(node: &'a Node, name: &str, result: &mut Vec<&'a Node>) {
+  if node.tag_name() == Some(name) {
+    result.push(node);
+    return;
+  }
+  for c in node.children().into_iter().flatten() {
+    find_all(c, name, result);
+  }
+}
+
+pub fn text_content(node: &Node) -> String {
+  fn gather(node: &Node, buf: &mut String) {
+    match node {
+      Node::Text(data) | Node::Whitespace(data) => buf.push_str(&data.text),
+      Node::Element(data) => {
+        for c in &data.children {
+          gather(c, buf);
+        }
+      },
+    }
+  }
+  let mut buf = String::new();
+  gather(node, &mut buf);
+  decode_entities(buf.trim()).into_owned()
+}
+
+fn serialize(node: &Node, buf: &mut String) {
+  match node {
+    Node::Text(data) | Node::Whitespace(data) => buf.push_str(&data.text),
+    Node::Element(data) => {
+      buf.push('<');
+      buf.push_str(&data.name);
+      for (key, value) in &data.attributes {
+        buf.push_str(&format!(" {}=\"{}\"", key, value));
+      }
+      buf.push('>');
+      for c in &data.children {
+        serialize(c, buf);
+      }
+      buf.push_str(&format!("</{}>", data.name));
+    },
+  }
+}
+
+pub fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+// Removes the remote resources and closes the void elements: the documents are read offline by
+// an XML parser.
+fn sanitize_html(html: &str) -> String {
+  let mut html = IMAGE_RE.replace_all(html, "").into_owned();
+  for re in REMOVED_RES.iter() {
+    html = re.replace_all(&html, "").into_owned();
+  }
+  VOID_RE.replace_all(&html, "<$1$2/>").into_owned()
+}
+
+pub fn entry_to_html(feed_name: &str, entry: &FeedEntry) -> String {
+  let title = escape(&entry.title);
+  let mut byline = escape(feed_name);
+  if !entry.author.is_empty() {
+    byline.push_str(&format!(" · {}", escape(&entry.author)));
+  }
+  if let Some(published) = entry.published {
+    byline.push_str(&format!(" · {}", published.format("%Y-%m-%d %H:%M")));
+  }
+  let mut html = format!(
+    "<html>\n<head>\n<meta charset=\"utf-8\"/>\n<title>{}</title>\n</head>\n<body>\n\
+     <h1>{}</h1>\n<p class=\"byline\">{}</p>\n{}\n",
+    title,
+    title,
+    byline,
+    sanitize_html(&entry.content)
+  );
+  if !entry.link.is_empty() {
+    let link = escape(&entry.link);
+    html.push_str(&format!("<p><a href=\"{}\">{}</a></p>\n", link, link));
+  }
+  html.push_str("</body>\n</html>\n");
+  html
+}
+
+// Replaces the characters that aren't allowed in FAT32 file names.
+fn file_name(feed_name: &str, title: &str) -> String {
+  let name: String = format!("{} - {}", feed_name, title)
+    .chars()
+    .map(|c| {
+      if c.is_control() || "<>:\"/\\|?*".contains(c) {
+        '_'
+      } else {
+        c
+      }
+    })
+    .take(MAX_NAME_LEN)
+    .collect();
+  format!(
+    "{}.html",
+    name.trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+  )
+}
+
+// Saves *entry* within *dir* and returns its metadata. Returns `None` if the entry was already
+// saved.
+pub fn save_entry(dir: &Path, feed_name: &str, entry: &FeedEntry) -> Result<Option<Info>, Error> {
+  let title = if entry.title.is_empty() {
+    entry.id.as_str()
+  } else {
+    entry.title.as_str()
+  };
+  let path: PathBuf = dir.join(file_name(feed_name, title));
+  if path.exists() {
+    return Ok(None);
+  }
+  let html = entry_to_html(feed_name, entry);
+  fs::write(&path, &html)?;
+  let added = entry.published.unwrap_or_else(Local::now);
+  set_file_mtime(&path, FileTime::from_unix_time(added.timestamp(), 0))?;
+  Ok(Some(Info {
+    title: title.to_string(),
+    author: feed_name.to_string(),
+    identifier: entry.link.clone(),
+    file: FileInfo {
+      path,
+      kind: "html".to_string(),
+      size: html.len() as u64,
+    },
+    added,
+    ..Default::default()
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_rss() {
+    let text = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+<channel>
+<title>Planet &amp; Co</title>
+<item>
+<title>First</title>
+<link>https://example.org/1</link>
+<pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate>
+<description>&lt;p&gt;Summary&lt;/p&gt;</description>
+<content:encoded><![CDATA[<p>Full <b>text</b><br></p>]]></content:encoded>
+</item>
+<item>
+<title>Second</title>
+<guid>tag:2</guid>
+<description>Plain</description>
+</item>
+</channel>
+</rss>"#;
+    let feed = parse_feed(text).unwrap();
+    assert_eq!(feed.title, "Planet & Co");
+    assert_eq!(feed.entries.len(), 2);
+    assert_eq!(feed.entries[0].id, "https://example.org/1");
+    assert_eq!(feed.entries[0].content, "<p>Full <b>text</b><br></p>");
+    assert!(feed.entries[0].published.is_some());
+    assert_eq!(feed.entries[1].id, "tag:2");
+    assert_eq!(feed.entries[1].content, "Plain");
+    let html = entry_to_html(&feed.title, &feed.entries[0]);
+    assert!(html.contains("<p>Full <b>text</b><br/></p>"));
+  }
+
+  #[test]
+  fn test_parse_atom() {
+    let text = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<title>Example</title>
+<entry>
+<title>Atom-Powered Robots</title>
+<link rel="self" href="https://example.org/self"/>
+<link href="https://example.org/2003/12/13/atom03"/>
+<id>urn:uuid:1225c695</id>
+<updated>2003-12-13T18:30:02Z</updated>
+<author><name>John</name></author>
+<content type="xhtml"><div><p>Some <i>text</i>.</p></div></content>
+</entry>
+</feed>"#;
+    let feed = parse_feed(text).unwrap();
+    assert_eq!(feed.title, "Example");
+    assert_eq!(feed.entries.len(), 1);
+    let entry = &feed.entries[0];
+    assert_eq!(entry.link, "https://example.org/2003/12/13/atom03");
+    assert_eq!(entry.id, "urn:uuid:1225c695");
+    assert_eq!(entry.author, "John");
+    assert_eq!(entry.content, "<div><p>Some <i>text</i>.</p></div>");
+  }
+}
@@ -0,0 +1,65 @@
+use super::{Feedback, FeedbackKind};
+use crate::input::{current_time, InputEvent};
+use anyhow::Error;
+use std::{
+  fs::{File, OpenOptions},
+  io::Write,
+  mem,
+  slice,
+};
+
+// The input device of the beeper, and the timed output of the vibration motor.
+const BEEPER_DEVICE: &str = "/dev/input/by-path/platform-beeper-event";
+const VIBRATOR_ENABLE: &str = "/sys/class/timed_output/vibrator/enable";
+
+const EV_SND: u16 = 0x12;
+const SND_CLICK: u16 = 0x00;
+
+pub struct KoboBeeper(File);
+
+impl KoboBeeper {
+  pub fn new() -> Result<Self, Error> {
+    let file = OpenOptions::new().write(true).open(BEEPER_DEVICE)?;
+    Ok(KoboBeeper(file))
+  }
+}
+
+impl Feedback for KoboBeeper {
+  fn emit(&mut self, _kind: FeedbackKind) -> Result<(), Error> {
+    let event = InputEvent {
+      time: current_time(),
+      kind: EV_SND,
+      code: SND_CLICK,
+      value: 1,
+    };
+    let bytes = unsafe {
+      slice::from_raw_parts(
+        &event as *const InputEvent as *const u8,
+        mem::size_of::<InputEvent>(),
+      )
+    };
+    self.0.write_all(bytes)?;
+    Ok(())
+  }
+}
+
+pub struct KoboVibrator(File);
+
+impl KoboVibrator {
+  pub fn new() -> Result<Self, Error> {
+    let file = OpenOptions::new().write(true).open(VIBRATOR_ENABLE)?;
+    Ok(KoboVibrator(file))
+  }
+}
+
+impl Feedback for KoboVibrator {
+  // The duration of the vibration is written in milliseconds.
+  fn emit(&mut self, kind: FeedbackKind) -> Result<(), Error> {
+    let duration = match kind {
+      FeedbackKind::PageTurn => 20,
+      FeedbackKind::KeyPress => 10,
+    };
+    write!(self.0, "{}", duration)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,23 @@
+mod kobo;
+
+use anyhow::Error;
+
+pub use self::kobo::{KoboBeeper, KoboVibrator};
+
+// The actions confirmed by a click or a vibration, since the display is slow to show them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FeedbackKind {
+  PageTurn,
+  KeyPress,
+}
+
+pub trait Feedback {
+  fn emit(&mut self, kind: FeedbackKind) -> Result<(), Error>;
+}
+
+// The devices without a beeper or a vibration motor.
+impl Feedback for () {
+  fn emit(&mut self, _kind: FeedbackKind) -> Result<(), Error> {
+    Ok(())
+  }
+}
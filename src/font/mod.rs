@@ -3,16 +3,16 @@ mod harfbuzz_sys;
 
 use self::{freetype_sys::*, harfbuzz_sys::*};
 
-use crate::{framebuffer::Framebuffer, geom::Point, helpers::IsHidden};
+use crate::{framebuffer::Framebuffer, geom::Point, helpers::IsHidden, settings::StyleMapping};
 use anyhow::{format_err, Error};
 use bitflags::bitflags;
 use fxhash::FxHashMap;
 use globset::Glob;
 use std::{
-  collections::BTreeSet,
+  collections::{BTreeMap, BTreeSet},
   ffi::{CStr, CString},
   os::unix::ffi::OsStrExt,
-  path::Path,
+  path::{Path, PathBuf},
   ptr,
   rc::Rc,
   slice,
@@ -28,6 +28,9 @@ pub const KEYBOARD_FONT_SIZES: [u32; 2] = [337, 843];
 
 pub const DISPLAY_FONT_SIZE: u32 = 2516;
 
+// The maximum number of shaped runs kept by a font, for each size.
+const PLAN_CACHE_CAPACITY: usize = 512;
+
 pub const NORMAL_STYLE: Style = Style {
   family: Family::SansSerif,
   variant: Variant::REGULAR,
@@ -459,80 +462,100 @@ pub struct FontFamily {
   pub bold_italic: Font,
 }
 
-pub fn family_names<P: AsRef<Path>>(search_path: P) -> Result<BTreeSet<String>, Error> {
-  if !search_path.as_ref().exists() {
-    return Err(format_err!("The search path doesn't exist."));
-  }
-
-  let opener = FontOpener::new()?;
-  let glob = Glob::new("**/*.[ot]tf")?.compile_matcher();
-
-  let mut families = BTreeSet::new();
-
-  for entry in WalkDir::new(search_path.as_ref())
-    .min_depth(1)
-    .into_iter()
-    .filter_entry(|e| !e.is_hidden())
-  {
-    if entry.is_err() {
-      continue;
-    }
-    let entry = entry.unwrap();
-    let path = entry.path();
-    if !glob.is_match(path) {
-      continue;
-    }
-    if let Ok(font) = opener
-      .open(path)
-      .map_err(|e| eprintln!("Can't open '{}': {}", path.display(), e))
-    {
-      if let Some(family_name) = font.family_name() {
-        families.insert(family_name.to_string());
-      } else {
-        eprintln!("Can't get the family name of '{}'.", path.display());
-      }
-    }
-  }
+// The files of the font families found in a set of directories, by family and style names.
+#[derive(Debug, Clone, Default)]
+pub struct FontIndex {
+  families: BTreeMap<String, FxHashMap<String, PathBuf>>,
+}
 
-  Ok(families)
+// The files of the four variants of a font family.
+#[derive(Debug, Clone)]
+pub struct FamilyFiles {
+  pub regular: PathBuf,
+  pub italic: PathBuf,
+  pub bold: PathBuf,
+  pub bold_italic: PathBuf,
 }
 
-impl FontFamily {
-  pub fn from_name<P: AsRef<Path>>(family_name: &str, search_path: P) -> Result<FontFamily, Error> {
-    let opener = FontOpener::new()?;
-    let glob = Glob::new("**/*.[ot]tf")?.compile_matcher();
-    let mut styles = FxHashMap::default();
+impl FontIndex {
+  pub fn scan<P: AsRef<Path>>(dirs: &[P]) -> FontIndex {
+    let mut index = FontIndex::default();
+    let opener = match FontOpener::new() {
+      Ok(opener) => opener,
+      Err(e) => {
+        eprintln!("Can't create font opener: {}.", e);
+        return index;
+      },
+    };
+    let glob = Glob::new("**/*.[ot]tf").unwrap().compile_matcher();
 
-    for entry in WalkDir::new(search_path.as_ref())
-      .min_depth(1)
-      .into_iter()
-      .filter_entry(|e| !e.is_hidden())
-    {
-      if entry.is_err() {
-        continue;
-      }
-      let entry = entry.unwrap();
-      let path = entry.path();
-      if !glob.is_match(path) {
-        continue;
-      }
-      if let Ok(font) = opener
-        .open(path)
-        .map_err(|e| eprintln!("Can't open '{}': {}", path.display(), e))
+    for dir in dirs.iter().filter(|d| d.as_ref().exists()) {
+      for entry in WalkDir::new(dir.as_ref())
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !e.is_hidden())
       {
-        if font.family_name() == Some(family_name) {
-          styles.insert(
-            font
+        if entry.is_err() {
+          continue;
+        }
+        let entry = entry.unwrap();
+        let path = entry.path();
+        if !glob.is_match(path) {
+          continue;
+        }
+        if let Ok(font) = opener
+          .open(path)
+          .map_err(|e| eprintln!("Can't open '{}': {}", path.display(), e))
+        {
+          if let Some(family_name) = font.family_name() {
+            let style_name = font
               .style_name()
               .map(String::from)
-              .unwrap_or_else(|| "Regular".to_string()),
-            path.to_path_buf(),
-          );
+              .unwrap_or_else(|| "Regular".to_string());
+            // The first directories take precedence.
+            index
+              .families
+              .entry(family_name.to_string())
+              .or_insert_with(FxHashMap::default)
+              .entry(style_name)
+              .or_insert_with(|| path.to_path_buf());
+          } else {
+            eprintln!("Can't get the family name of '{}'.", path.display());
+          }
         }
       }
     }
 
-    let regular_path = if styles.len() == 1 {
+    index
+  }
+
+  pub fn family_names(&self) -> BTreeSet<String> {
+    self.families.keys().cloned().collect()
+  }
+
+  pub fn files(
+    &self,
+    family_name: &str,
+    mapping: Option<&StyleMapping>,
+  ) -> Result<FamilyFiles, Error> {
+    let styles = self
+      .families
+      .get(family_name)
+      .ok_or_else(|| format_err!("Unknown font family: {}.", family_name))?;
+    let mapped = |name: Option<&String>| {
+      name.and_then(|name| {
+        let path = styles.get(name);
+        if path.is_none() {
+          eprintln!("Unknown style for {}: {}.", family_name, name);
+        }
+        path
+      })
+    };
+    let mapping = mapping.cloned().unwrap_or_default();
+
+    let regular_path = if let Some(path) = mapped(mapping.regular.as_ref()) {
+      path
+    } else if styles.len() == 1 {
       styles.values().next().unwrap()
     } else {
       styles
@@ -541,26 +564,39 @@ impl FontFamily {
         .or_else(|| styles.get("Book"))
         .ok_or_else(|| format_err!("Can't find regular style."))?
     };
-    let italic_path = styles
-      .get("Italic")
+    let italic_path = mapped(mapping.italic.as_ref())
+      .or_else(|| styles.get("Italic"))
       .or_else(|| styles.get("Book Italic"))
       .unwrap_or(regular_path);
-    let bold_path = styles
-      .get("Bold")
+    let bold_path = mapped(mapping.bold.as_ref())
+      .or_else(|| styles.get("Bold"))
       .or_else(|| styles.get("Semibold"))
       .or_else(|| styles.get("SemiBold"))
       .or_else(|| styles.get("Medium"))
       .unwrap_or(regular_path);
-    let bold_italic_path = styles
-      .get("Bold Italic")
+    let bold_italic_path = mapped(mapping.bold_italic.as_ref())
+      .or_else(|| styles.get("Bold Italic"))
       .or_else(|| styles.get("SemiBold Italic"))
       .or_else(|| styles.get("Medium Italic"))
       .unwrap_or(italic_path);
+
+    Ok(FamilyFiles {
+      regular: regular_path.clone(),
+      italic: italic_path.clone(),
+      bold: bold_path.clone(),
+      bold_italic: bold_italic_path.clone(),
+    })
+  }
+}
+
+impl FontFamily {
+  pub fn from_files(files: &FamilyFiles) -> Result<FontFamily, Error> {
+    let opener = FontOpener::new()?;
     Ok(FontFamily {
-      regular: opener.open(regular_path)?,
-      italic: opener.open(italic_path)?,
-      bold: opener.open(bold_path)?,
-      bold_italic: opener.open(bold_italic_path)?,
+      regular: opener.open(&files.regular)?,
+      italic: opener.open(&files.italic)?,
+      bold: opener.open(&files.bold)?,
+      bold_italic: opener.open(&files.bold_italic)?,
     })
   }
 }
@@ -600,6 +636,23 @@ impl Fonts {
     };
     fonts.monospace.bold.set_variations(&["wght=600"]);
     fonts.monospace.bold_italic.set_variations(&["wght=600"]);
+    // The same labels are drawn over and over again by the interface.
+    for family in &mut [
+      &mut fonts.sans_serif,
+      &mut fonts.serif,
+      &mut fonts.monospace,
+    ] {
+      for font in &mut [
+        &mut family.regular,
+        &mut family.italic,
+        &mut family.bold,
+        &mut family.bold_italic,
+      ] {
+        font.enable_cache();
+      }
+    }
+    fonts.keyboard.enable_cache();
+    fonts.display.enable_cache();
     Ok(fonts)
   }
 }
@@ -1341,6 +1394,36 @@ pub struct Font {
   // lowercase and uppercase x heights
   pub x_heights: (u32, u32),
   space_codepoint: u32,
+  // The runs shaped without features, for the fonts of the interface.
+  cache: Option<PlanCache>,
+}
+
+// The shaped runs of a font, by size and text.
+#[derive(Default)]
+pub struct PlanCache {
+  plans: FxHashMap<(u32, u16), FxHashMap<String, RenderPlan>>,
+}
+
+impl PlanCache {
+  fn get(&self, text: &str, size: u32, dpi: u16) -> Option<&RenderPlan> {
+    self
+      .plans
+      .get(&(size, dpi))
+      .and_then(|plans| plans.get(text))
+  }
+
+  fn insert(&mut self, text: &str, size: u32, dpi: u16, plan: RenderPlan) {
+    let plans = self.plans.entry((size, dpi)).or_default();
+    // Runs aren't worth tracking individually: the cache is emptied when it's full.
+    if plans.len() >= PLAN_CACHE_CAPACITY {
+      plans.clear();
+    }
+    plans.insert(text.to_string(), plan);
+  }
+
+  fn clear(&mut self) {
+    self.plans.clear();
+  }
 }
 
 impl FontOpener {
@@ -1377,6 +1460,7 @@ impl FontOpener {
         ellipsis,
         x_heights,
         space_codepoint,
+        cache: None,
       })
     }
   }
@@ -1407,6 +1491,7 @@ impl FontOpener {
         ellipsis,
         x_heights,
         space_codepoint,
+        cache: None,
       })
     }
   }
@@ -1433,6 +1518,10 @@ impl Font {
     }
   }
 
+  pub fn enable_cache(&mut self) {
+    self.cache.get_or_insert_with(PlanCache::default);
+  }
+
   pub fn set_size(&mut self, size: u32, dpi: u16) {
     if !self.font.is_null() && self.size == size && self.dpi == dpi {
       return;
@@ -1503,6 +1592,10 @@ impl Font {
 
       FT_Done_MM_Var(self.lib.0, varia);
     }
+
+    if let Some(cache) = self.cache.as_mut() {
+      cache.clear();
+    }
   }
 
   pub fn set_variations_from_name(&mut self, name: &str) -> bool {
@@ -1644,12 +1737,42 @@ impl Font {
     max_width: Option<i32>,
     features: Option<&[String]>,
   ) -> RenderPlan {
+    let text = text.as_ref();
+    let cacheable = features.is_none() && self.cache.is_some();
+
+    let mut render_plan = match self
+      .cache
+      .as_ref()
+      .filter(|_| cacheable)
+      .and_then(|cache| cache.get(text, self.size, self.dpi))
+    {
+      Some(render_plan) => render_plan.clone(),
+      None => {
+        let render_plan = self.shape(text, features);
+        if cacheable {
+          let (size, dpi) = (self.size, self.dpi);
+          if let Some(cache) = self.cache.as_mut() {
+            cache.insert(text, size, dpi, render_plan.clone());
+          }
+        }
+        render_plan
+      },
+    };
+
+    if let Some(mw) = max_width {
+      self.crop_right(&mut render_plan, mw);
+    }
+
+    render_plan
+  }
+
+  fn shape(&mut self, text: &str, features: Option<&[String]>) -> RenderPlan {
     unsafe {
       let buf = hb_buffer_create();
       hb_buffer_add_utf8(
         buf,
-        text.as_ref().as_ptr() as *const libc::c_char,
-        text.as_ref().len() as libc::c_int,
+        text.as_ptr() as *const libc::c_char,
+        text.len() as libc::c_int,
         0,
         -1,
       );
@@ -1718,20 +1841,10 @@ impl Font {
         render_plan.glyphs.push(glyph);
       }
 
-      self.patch(
-        text.as_ref(),
-        &features_vec,
-        &mut render_plan,
-        missing_glyphs,
-        buf,
-      );
+      self.patch(text, &features_vec, &mut render_plan, missing_glyphs, buf);
 
       hb_buffer_destroy(buf);
 
-      if let Some(mw) = max_width {
-        self.crop_right(&mut render_plan, mw);
-      }
-
       render_plan
     }
   }
@@ -1766,7 +1879,7 @@ impl Font {
 
     let mut i = 0;
 
-    while render_plan.glyphs[i].codepoint == self.space_codepoint {
+    while i < render_plan.glyphs.len() && render_plan.glyphs[i].codepoint == self.space_codepoint {
       render_plan.width -= render_plan.glyphs[i].advance.x;
       i += 1;
     }
@@ -1861,6 +1974,141 @@ impl Font {
     }
   }
 
+  #[inline]
+  pub fn crop_left(&self, render_plan: &mut RenderPlan, max_width: i32) {
+    if render_plan.width <= max_width {
+      return;
+    }
+
+    render_plan.width += self.ellipsis.width;
+
+    let mut i = 0;
+    while i < render_plan.glyphs.len() && render_plan.width > max_width {
+      render_plan.width -= render_plan.glyphs[i].advance.x;
+      i += 1;
+    }
+
+    let len = self.ellipsis.glyphs.len();
+    render_plan
+      .glyphs
+      .splice(..i, self.ellipsis.glyphs.iter().cloned());
+    render_plan.scripts = render_plan
+      .scripts
+      .iter()
+      .filter_map(|(&k, &v)| if k < i { None } else { Some((k - i + len, v)) })
+      .collect();
+  }
+
+  // Keeps the start and the end of the text, and puts the ellipsis in between.
+  #[inline]
+  pub fn crop_middle(&self, render_plan: &mut RenderPlan, max_width: i32) {
+    if render_plan.width <= max_width {
+      return;
+    }
+
+    let available = max_width - self.ellipsis.width;
+    let glyphs = &render_plan.glyphs;
+    let mut head_width = 0;
+    let mut head = 0;
+    while head < glyphs.len() && head_width + glyphs[head].advance.x <= available / 2 {
+      head_width += glyphs[head].advance.x;
+      head += 1;
+    }
+    let mut tail_width = 0;
+    let mut tail = glyphs.len();
+    while tail > head && head_width + tail_width + glyphs[tail - 1].advance.x <= available {
+      tail -= 1;
+      tail_width += glyphs[tail].advance.x;
+    }
+
+    let len = self.ellipsis.glyphs.len();
+    render_plan
+      .glyphs
+      .splice(head..tail, self.ellipsis.glyphs.iter().cloned());
+    render_plan.scripts = render_plan
+      .scripts
+      .iter()
+      .filter_map(|(&k, &v)| {
+        if k < head {
+          Some((k, v))
+        } else if k >= tail {
+          Some((k - tail + head + len, v))
+        } else {
+          None
+        }
+      })
+      .collect();
+    render_plan.width = head_width + self.ellipsis.width + tail_width;
+  }
+
+  pub fn ellipsize(&self, render_plan: &mut RenderPlan, max_width: i32, ellipsis: Ellipsis) {
+    match ellipsis {
+      Ellipsis::Start => self.crop_left(render_plan, max_width),
+      Ellipsis::Middle => self.crop_middle(render_plan, max_width),
+      Ellipsis::End => self.crop_right(render_plan, max_width),
+    }
+  }
+
+  // Breaks the plan into at most *max_lines* lines. The maximum width of the line *i* is
+  // `widths[i]`, the last width applies to the following lines. The last line is ellipsized.
+  pub fn wrap(
+    &self,
+    mut render_plan: RenderPlan,
+    widths: &[i32],
+    max_lines: usize,
+    ellipsis: Ellipsis,
+  ) -> Vec<RenderPlan> {
+    let line_width = |index: usize| widths[index.min(widths.len() - 1)];
+    let mut lines = Vec::new();
+
+    while render_plan.width > line_width(lines.len())
+      && lines.len() + 1 < max_lines
+      && render_plan.glyphs.len() > 1
+    {
+      let (mut index, mut usable_width) = self.cut_point(&render_plan, line_width(lines.len()));
+      if index == 0 {
+        index = 1;
+        usable_width = render_plan.glyph_advance(0);
+      }
+      let mut next_plan = render_plan.split_off(index, usable_width);
+      self.trim_left(&mut next_plan);
+      lines.push(render_plan);
+      render_plan = next_plan;
+    }
+
+    self.ellipsize(&mut render_plan, line_width(lines.len()), ellipsis);
+    lines.push(render_plan);
+    lines
+  }
+
+  // Plans a text that can span several lines: the line breaks of the text are kept.
+  pub fn plan_lines<S: AsRef<str>>(
+    &mut self,
+    text: S,
+    max_width: i32,
+    max_lines: usize,
+    ellipsis: Ellipsis,
+  ) -> Vec<RenderPlan> {
+    let mut lines = Vec::new();
+    let mut paragraphs = text.as_ref().lines().peekable();
+
+    while let Some(paragraph) = paragraphs.next() {
+      let plan = self.plan(paragraph, None, None);
+      lines.extend(self.wrap(plan, &[max_width], max_lines - lines.len(), ellipsis));
+      if lines.len() >= max_lines {
+        if paragraphs.peek().is_some() {
+          if let Some(last) = lines.last_mut() {
+            last.append(&mut self.ellipsis.clone());
+            self.crop_right(last, max_width);
+          }
+        }
+        break;
+      }
+    }
+
+    lines
+  }
+
   pub fn cut_point(&self, render_plan: &RenderPlan, max_width: i32) -> (usize, i32) {
     let mut width = render_plan.width;
     let glyphs = &render_plan.glyphs;
@@ -1954,6 +2202,16 @@ impl Font {
     }
   }
 
+  // Returns how far the ink of the given character goes above and below the baseline.
+  pub fn ink_extent(&self, c: char) -> (i32, i32) {
+    unsafe {
+      FT_Load_Char(self.face, c as libc::c_ulong, FT_LOAD_DEFAULT);
+      let metrics = &((*(*self.face).glyph).metrics);
+      let top = (metrics.hori_bearing_y >> 6) as i32;
+      (top, (metrics.height >> 6) as i32 - top)
+    }
+  }
+
   pub fn em(&self) -> u16 {
     unsafe { (*(*self.face).size).metrics.x_ppem as u16 }
   }
@@ -1979,6 +2237,14 @@ struct GlyphPlan {
   advance: Point,
 }
 
+// Where the ellipsis goes when a text is too wide.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Ellipsis {
+  Start,
+  Middle,
+  End,
+}
+
 #[derive(Debug, Clone)]
 pub struct RenderPlan {
   pub width: i32,
@@ -2472,3 +2738,26 @@ impl From<FtError> for FreetypeError {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{PlanCache, RenderPlan, PLAN_CACHE_CAPACITY};
+
+  #[test]
+  fn test_plan_cache() {
+    let mut cache = PlanCache::default();
+    let plan = RenderPlan {
+      width: 42,
+      ..Default::default()
+    };
+    cache.insert("Library", 349, 300, plan);
+    assert_eq!(cache.get("Library", 349, 300).map(|p| p.width), Some(42));
+    assert!(cache.get("Library", 524, 300).is_none());
+    for i in 0..PLAN_CACHE_CAPACITY {
+      cache.insert(&i.to_string(), 349, 300, RenderPlan::default());
+    }
+    assert!(cache.get("Library", 349, 300).is_none());
+    cache.clear();
+    assert!(cache.get("0", 349, 300).is_none());
+  }
+}
@@ -0,0 +1,55 @@
+//! Collects the latencies of the display updates.
+
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+#[derive(Debug, Clone, Default)]
+pub struct UpdateStats {
+  // The latencies of each waveform, in the order they were measured.
+  samples: BTreeMap<&'static str, Vec<Duration>>,
+}
+
+impl UpdateStats {
+  pub fn add(&mut self, waveform: &'static str, latency: Duration) {
+    self.samples.entry(waveform).or_default().push(latency);
+  }
+
+  pub fn report(&self) -> String {
+    let mut text = String::new();
+    writeln!(text, "waveform\tcount\tmedian\tmean\tmax").ok();
+    for (waveform, latencies) in &self.samples {
+      let mut millis: Vec<f64> = latencies.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+      millis.sort_by(|a, b| a.partial_cmp(b).unwrap());
+      let count = millis.len();
+      let median = millis[count / 2];
+      let mean = millis.iter().sum::<f64>() / count as f64;
+      let max = millis[count - 1];
+      writeln!(
+        text,
+        "{}\t{}\t{:.0} ms\t{:.0} ms\t{:.0} ms",
+        waveform, count, median, mean, max
+      )
+      .ok();
+    }
+    text
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::UpdateStats;
+  use std::time::Duration;
+
+  #[test]
+  fn test_report() {
+    let mut stats = UpdateStats::default();
+    for millis in &[120, 80, 100] {
+      stats.add("A2", Duration::from_millis(*millis));
+    }
+    stats.add("GC16", Duration::from_millis(450));
+    let report = stats.report();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1], "A2\t3\t100 ms\t100 ms\t120 ms");
+    assert_eq!(lines[2], "GC16\t1\t450 ms\t450 ms\t450 ms");
+  }
+}
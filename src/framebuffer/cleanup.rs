@@ -0,0 +1,305 @@
+//! Cleans up the rendered pages of scanned documents before they're displayed.
+
+use super::Pixmap;
+use crate::color::WHITE;
+use serde::{Deserialize, Serialize};
+
+// The side of the square blocks in which the color of the paper is estimated.
+const BLOCK_SIZE: usize = 32;
+// The darkest color that can be mistaken for paper.
+const MIN_BACKGROUND: u8 = 96;
+// The pixels darker than this are considered ink.
+const INK_THRESHOLD: u8 = 128;
+// The flattened pixels lighter than this become white.
+const PAPER_THRESHOLD: f32 = 224.0;
+// The largest skew that is corrected, in degrees.
+const MAX_SKEW: f32 = 3.0;
+const SKEW_STEP: f32 = 0.2;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ScanFilter {
+  Despeckle,
+  FlattenBackground,
+  Deskew,
+}
+
+impl ScanFilter {
+  pub fn label(self) -> &'static str {
+    match self {
+      ScanFilter::Despeckle => "Despeckle",
+      ScanFilter::FlattenBackground => "Flatten Background",
+      ScanFilter::Deskew => "Deskew",
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ScanCleanup {
+  pub despeckle: bool,
+  pub flatten_background: bool,
+  pub deskew: bool,
+}
+
+impl ScanCleanup {
+  pub fn is_enabled(&self, filter: ScanFilter) -> bool {
+    match filter {
+      ScanFilter::Despeckle => self.despeckle,
+      ScanFilter::FlattenBackground => self.flatten_background,
+      ScanFilter::Deskew => self.deskew,
+    }
+  }
+
+  pub fn toggle(&mut self, filter: ScanFilter) {
+    match filter {
+      ScanFilter::Despeckle => self.despeckle = !self.despeckle,
+      ScanFilter::FlattenBackground => self.flatten_background = !self.flatten_background,
+      ScanFilter::Deskew => self.deskew = !self.deskew,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    !(self.despeckle || self.flatten_background || self.deskew)
+  }
+}
+
+// The background is flattened first, so that the other filters can tell the ink from the paper.
+pub fn clean_up(pixmap: &mut Pixmap, cleanup: &ScanCleanup) {
+  if cleanup.flatten_background {
+    flatten_background(pixmap);
+  }
+  if cleanup.despeckle {
+    despeckle(pixmap);
+  }
+  if cleanup.deskew {
+    let angle = skew_angle(pixmap);
+    if angle.abs() >= SKEW_STEP / 2.0 {
+      rotate(pixmap, angle);
+    }
+  }
+}
+
+// Divides each pixel by the color of the surrounding paper: yellowed paper and uneven
+// lighting become white, and the contrast of the ink is preserved.
+fn flatten_background(pixmap: &mut Pixmap) {
+  let width = pixmap.width as usize;
+  let height = pixmap.height as usize;
+  if width == 0 || height == 0 {
+    return;
+  }
+  let grid_width = (width + BLOCK_SIZE - 1) / BLOCK_SIZE;
+  let grid_height = (height + BLOCK_SIZE - 1) / BLOCK_SIZE;
+  let mut estimates = vec![WHITE as f32; grid_width * grid_height];
+
+  for j in 0..grid_height {
+    for i in 0..grid_width {
+      let mut histogram = [0usize; 256];
+      let mut count = 0;
+      for y in j * BLOCK_SIZE..((j + 1) * BLOCK_SIZE).min(height) {
+        for x in i * BLOCK_SIZE..((i + 1) * BLOCK_SIZE).min(width) {
+          histogram[pixmap.data[y * width + x] as usize] += 1;
+          count += 1;
+        }
+      }
+      // The paper covers most of a block, even in the middle of the text.
+      let mut rank = count * 9 / 10;
+      let mut color = 0;
+      for (c, n) in histogram.iter().enumerate() {
+        color = c;
+        if *n > rank {
+          break;
+        }
+        rank -= n;
+      }
+      estimates[j * grid_width + i] = (color as u8).max(MIN_BACKGROUND) as f32;
+    }
+  }
+
+  let coordinate = |v: usize, len: usize| {
+    let f = ((v as f32 + 0.5) / BLOCK_SIZE as f32 - 0.5)
+      .max(0.0)
+      .min((len - 1) as f32);
+    let i0 = f as usize;
+    (i0, (i0 + 1).min(len - 1), f - i0 as f32)
+  };
+
+  for y in 0..height {
+    let (j0, j1, ty) = coordinate(y, grid_height);
+    for x in 0..width {
+      let (i0, i1, tx) = coordinate(x, grid_width);
+      let top = estimates[j0 * grid_width + i0] * (1.0 - tx) + estimates[j0 * grid_width + i1] * tx;
+      let bottom =
+        estimates[j1 * grid_width + i0] * (1.0 - tx) + estimates[j1 * grid_width + i1] * tx;
+      let background = top * (1.0 - ty) + bottom * ty;
+      let addr = y * width + x;
+      let color = pixmap.data[addr] as f32 * 255.0 / background;
+      pixmap.data[addr] = if color >= PAPER_THRESHOLD {
+        WHITE
+      } else {
+        color as u8
+      };
+    }
+  }
+}
+
+// Removes the clusters of one or two dark pixels surrounded by paper.
+fn despeckle(pixmap: &mut Pixmap) {
+  let width = pixmap.width as usize;
+  let height = pixmap.height as usize;
+  if width < 5 || height < 5 {
+    return;
+  }
+  let source = pixmap.data.clone();
+  let is_ink = |x: usize, y: usize| source[y * width + x] < INK_THRESHOLD;
+
+  for y in 2..height - 2 {
+    for x in 2..width - 2 {
+      if !is_ink(x, y) {
+        continue;
+      }
+      let mut inner = 0;
+      let mut lightest = 0;
+      for ny in y - 1..=y + 1 {
+        for nx in x - 1..=x + 1 {
+          if is_ink(nx, ny) {
+            inner += 1;
+          }
+          lightest = lightest.max(source[ny * width + nx]);
+        }
+      }
+      if inner > 2 {
+        continue;
+      }
+      let ring = (x - 2..=x + 2).any(|nx| is_ink(nx, y - 2) || is_ink(nx, y + 2))
+        || (y - 1..=y + 1).any(|ny| is_ink(x - 2, ny) || is_ink(x + 2, ny));
+      if !ring {
+        pixmap.data[y * width + x] = lightest;
+      }
+    }
+  }
+}
+
+// Returns the angle, in degrees, that maximizes the contrast of the horizontal projection of
+// the ink: the lines of text are sharpest when the projection follows them.
+fn skew_angle(pixmap: &Pixmap) -> f32 {
+  let width = pixmap.width as usize;
+  let height = pixmap.height as usize;
+  let step = 2;
+  let ink: Vec<(f32, f32)> = (0..height)
+    .step_by(step)
+    .flat_map(|y| (0..width).step_by(step).map(move |x| (x, y)))
+    .filter(|&(x, y)| pixmap.data[y * width + x] < INK_THRESHOLD)
+    .map(|(x, y)| (x as f32, y as f32))
+    .collect();
+  if ink.is_empty() {
+    return 0.0;
+  }
+
+  let max_shift = width as f32 * MAX_SKEW.to_radians().tan();
+  let bins_count = ((height as f32 + 2.0 * max_shift) / step as f32) as usize + 2;
+  let mut bins = vec![0u32; bins_count];
+  let mut best = (0.0, 0u64);
+  let steps = (MAX_SKEW / SKEW_STEP).round() as i32;
+
+  for k in -steps..=steps {
+    let angle = k as f32 * SKEW_STEP;
+    let slope = angle.to_radians().tan();
+    bins.iter_mut().for_each(|b| *b = 0);
+    for &(x, y) in &ink {
+      let index = ((y - x * slope + max_shift) / step as f32) as usize;
+      bins[index.min(bins_count - 1)] += 1;
+    }
+    let score = bins.iter().map(|&b| (b as u64) * (b as u64)).sum::<u64>();
+    // Prefer the smallest correction on ties.
+    if score > best.1 || (score == best.1 && angle.abs() < f32::abs(best.0)) {
+      best = (angle, score);
+    }
+  }
+
+  best.0
+}
+
+// Rotates the pixmap around its center: the lines slanted by *angle* degrees become horizontal.
+fn rotate(pixmap: &mut Pixmap, angle: f32) {
+  let width = pixmap.width as usize;
+  let height = pixmap.height as usize;
+  let (sin, cos) = angle.to_radians().sin_cos();
+  let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+  let source = pixmap.data.clone();
+  let sample = |x: i32, y: i32| {
+    if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+      WHITE as f32
+    } else {
+      source[y as usize * width + x as usize] as f32
+    }
+  };
+
+  for y in 0..height {
+    for x in 0..width {
+      let dx = x as f32 + 0.5 - cx;
+      let dy = y as f32 + 0.5 - cy;
+      let sx = cx + dx * cos - dy * sin - 0.5;
+      let sy = cy + dx * sin + dy * cos - 0.5;
+      let (x0, y0) = (sx.floor() as i32, sy.floor() as i32);
+      let (tx, ty) = (sx - x0 as f32, sy - y0 as f32);
+      let top = sample(x0, y0) * (1.0 - tx) + sample(x0 + 1, y0) * tx;
+      let bottom = sample(x0, y0 + 1) * (1.0 - tx) + sample(x0 + 1, y0 + 1) * tx;
+      pixmap.data[y * width + x] = (top * (1.0 - ty) + bottom * ty).round() as u8;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{despeckle, flatten_background, rotate, skew_angle};
+  use crate::framebuffer::Pixmap;
+
+  fn lines(width: u32, height: u32, angle: f32) -> Pixmap {
+    let mut pixmap = Pixmap::new(width, height);
+    let slope = angle.to_radians().tan();
+    for row in (20..height as i32 - 20).step_by(16) {
+      for x in 10..width as i32 - 10 {
+        for thickness in 0..3 {
+          let y = row + thickness + (x as f32 * slope).round() as i32;
+          pixmap.data[(y as u32 * width + x as u32) as usize] = 0;
+        }
+      }
+    }
+    pixmap
+  }
+
+  #[test]
+  fn test_despeckle() {
+    let mut pixmap = Pixmap::new(16, 16);
+    pixmap.data[3 * 16 + 3] = 0;
+    for y in 8..12 {
+      for x in 8..12 {
+        pixmap.data[y * 16 + x] = 0;
+      }
+    }
+    despeckle(&mut pixmap);
+    assert_eq!(pixmap.data[3 * 16 + 3], 255);
+    assert!(pixmap.data[8 * 16 + 8..8 * 16 + 12].iter().all(|&c| c == 0));
+  }
+
+  #[test]
+  fn test_flatten_background() {
+    let mut pixmap = Pixmap::new(128, 128);
+    for (i, color) in pixmap.data.iter_mut().enumerate() {
+      *color = 160 + ((i % 128) * 60 / 128) as u8;
+    }
+    pixmap.data[64 * 128 + 64] = 40;
+    flatten_background(&mut pixmap);
+    assert!(pixmap.data[..64 * 128].iter().all(|&c| c == 255));
+    assert!(pixmap.data[64 * 128 + 64] < 80);
+  }
+
+  #[test]
+  fn test_deskew() {
+    let mut pixmap = lines(300, 200, 2.0);
+    assert!((skew_angle(&pixmap) - 2.0).abs() < 0.3);
+    rotate(&mut pixmap, 2.0);
+    assert!(skew_angle(&pixmap).abs() < 0.3);
+    assert!(skew_angle(&lines(300, 200, -1.4)) < -1.0);
+  }
+}
@@ -0,0 +1,102 @@
+//! Reduces the gray levels of a pixmap to the palette of the display.
+
+use super::Pixmap;
+use crate::color::{BLACK, WHITE};
+use serde::{Deserialize, Serialize};
+
+// The displays have 16 gray levels.
+const LEVELS: i32 = 16;
+const STEP: i32 = 255 / (LEVELS - 1);
+
+const BAYER_MATRIX: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DitherMode {
+  None,
+  FloydSteinberg,
+  Ordered,
+}
+
+#[inline]
+fn quantize(value: i32) -> u8 {
+  (((value.max(0).min(255) + STEP / 2) / STEP) * STEP) as u8
+}
+
+// Pure black and pure white are left untouched, and don't absorb the errors of their
+// neighbors: the backgrounds and the borders of the images stay clean, and the average
+// brightness of the other areas is preserved.
+pub fn dither(pixmap: &mut Pixmap, mode: DitherMode) {
+  match mode {
+    DitherMode::None => (),
+    DitherMode::FloydSteinberg => floyd_steinberg(pixmap),
+    DitherMode::Ordered => ordered(pixmap),
+  }
+}
+
+fn floyd_steinberg(pixmap: &mut Pixmap) {
+  let width = pixmap.width as usize;
+  let height = pixmap.height as usize;
+  // The errors of the current and next rows, with one cell of padding on each side.
+  let mut current = vec![0i32; width + 2];
+  let mut next = vec![0i32; width + 2];
+
+  for y in 0..height {
+    for x in 0..width {
+      let addr = y * width + x;
+      let source = pixmap.data[addr];
+      if source == BLACK || source == WHITE {
+        continue;
+      }
+      // The errors are stored in sixteenths.
+      let value = source as i32 + current[x + 1] / 16;
+      let color = quantize(value);
+      let error = value - color as i32;
+      pixmap.data[addr] = color;
+      current[x + 2] += 7 * error;
+      next[x] += 3 * error;
+      next[x + 1] += 5 * error;
+      next[x + 2] += error;
+    }
+    std::mem::swap(&mut current, &mut next);
+    next.iter_mut().for_each(|e| *e = 0);
+  }
+}
+
+fn ordered(pixmap: &mut Pixmap) {
+  let width = pixmap.width as usize;
+  for (addr, color) in pixmap.data.iter_mut().enumerate() {
+    if *color == BLACK || *color == WHITE {
+      continue;
+    }
+    let (x, y) = (addr % width, addr / width);
+    // Centered on zero, so that the average of the offsets doesn't shift the brightness.
+    let offset = (2 * BAYER_MATRIX[y % 4][x % 4] + 1 - LEVELS) * STEP / (2 * LEVELS);
+    *color = quantize(*color as i32 + offset);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{dither, DitherMode, STEP};
+  use crate::framebuffer::Pixmap;
+
+  fn average(pixmap: &Pixmap) -> f32 {
+    pixmap.data.iter().map(|&c| c as f32).sum::<f32>() / pixmap.data.len() as f32
+  }
+
+  #[test]
+  fn test_dither() {
+    for mode in [DitherMode::FloydSteinberg, DitherMode::Ordered].iter() {
+      let mut pixmap = Pixmap::new(16, 16);
+      for (i, color) in pixmap.data.iter_mut().enumerate() {
+        *color = if i < 16 { 255 } else { 120 };
+      }
+      let before = average(&pixmap);
+      dither(&mut pixmap, *mode);
+      assert!(pixmap.data.iter().all(|&c| c as i32 % STEP == 0));
+      assert!(pixmap.data[..16].iter().all(|&c| c == 255));
+      assert!((average(&pixmap) - before).abs() < 2.0);
+    }
+  }
+}
@@ -1,9 +1,10 @@
-use super::{mxcfb_sys::*, Framebuffer, UpdateMode};
+use super::{mxcfb_sys::*, Framebuffer, UpdateMode, UpdateStats};
 use crate::{
   device::{Model, CURRENT_DEVICE},
   geom::Rectangle,
 };
 use anyhow::{Context, Error};
+use fxhash::FxHashMap;
 use std::{
   fs::{File, OpenOptions},
   io,
@@ -12,8 +13,14 @@ use std::{
   path::Path,
   ptr,
   slice,
+  time::{Duration, Instant},
 };
 
+// The longest duration of a regular update: the updates sent before are assumed to be complete.
+const MAX_UPDATE_DURATION: Duration = Duration::from_millis(1500);
+// The largest area, in pixels, scanned to find out whether a region is bitonal.
+const MAX_SCANNED_AREA: u32 = 600 * 800;
+
 impl Into<MxcfbRect> for Rectangle {
   fn into(self) -> MxcfbRect {
     MxcfbRect {
@@ -42,6 +49,10 @@ pub struct KoboFramebuffer {
   bytes_per_pixel: u8,
   var_info: VarScreenInfo,
   fix_info: FixScreenInfo,
+  // The region, the waveform and the start of the updates that might still be in progress.
+  pending: FxHashMap<u32, (Rectangle, u32, Instant)>,
+  stats: Option<UpdateStats>,
+  tone_table: Option<Box<[u8; 256]>>,
 }
 
 impl KoboFramebuffer {
@@ -95,6 +106,9 @@ impl KoboFramebuffer {
         bytes_per_pixel: bytes_per_pixel as u8,
         var_info,
         fix_info,
+        pending: FxHashMap::default(),
+        stats: None,
+        tone_table: None,
       })
     }
   }
@@ -102,10 +116,111 @@ impl KoboFramebuffer {
   fn as_bytes(&self) -> &[u8] {
     unsafe { slice::from_raw_parts(self.frame as *const u8, self.frame_size) }
   }
+
+  // Returns whether the region only contains black and white pixels.
+  fn is_bitonal(&self, rect: &Rectangle) -> bool {
+    if rect.area() > MAX_SCANNED_AREA {
+      return false;
+    }
+    for y in rect.min.y..rect.max.y {
+      for x in rect.min.x..rect.max.x {
+        let gray = (self.get_pixel_rgb)(self, x as u32, y as u32)[0];
+        // The 16 bits framebuffers store white as 0xF8.
+        if gray > 0x07 && gray < 0xF8 {
+          return false;
+        }
+      }
+    }
+    true
+  }
+
+  // The update mode, waveform and flags of an update.
+  fn update_modes(&self, rect: &Rectangle, mode: UpdateMode) -> (u32, u32, u32) {
+    let mut flags = self.flags;
+    let mark = CURRENT_DEVICE.mark();
+    let (update_mode, mut waveform_mode) = match mode {
+      // The regions without gray pixels can use the faster two-levels waveform.
+      UpdateMode::Gui if self.is_bitonal(rect) => {
+        flags |= EPDC_FLAG_FORCE_MONOCHROME;
+        (UPDATE_MODE_PARTIAL, NTX_WFM_MODE_DU)
+      },
+      UpdateMode::Gui => (UPDATE_MODE_PARTIAL, WAVEFORM_MODE_AUTO),
+      UpdateMode::Partial => {
+        if mark >= 7 {
+          (UPDATE_MODE_PARTIAL, NTX_WFM_MODE_GLR16)
+        } else if CURRENT_DEVICE.model == Model::Aura {
+          flags |= EPDC_FLAG_USE_AAD;
+          (UPDATE_MODE_FULL, NTX_WFM_MODE_GLD16)
+        } else {
+          (UPDATE_MODE_PARTIAL, WAVEFORM_MODE_AUTO)
+        }
+      },
+      UpdateMode::Full => (UPDATE_MODE_FULL, NTX_WFM_MODE_GC16),
+      UpdateMode::Fast => (UPDATE_MODE_PARTIAL, NTX_WFM_MODE_A2),
+      UpdateMode::FastMono => {
+        flags |= EPDC_FLAG_FORCE_MONOCHROME;
+        (UPDATE_MODE_PARTIAL, NTX_WFM_MODE_A2)
+      },
+    };
+
+    if self.monochrome && mode != UpdateMode::Full {
+      flags |= EPDC_FLAG_FORCE_MONOCHROME;
+      waveform_mode = NTX_WFM_MODE_A2;
+    }
+
+    (update_mode, waveform_mode, flags)
+  }
+
+  // A two-levels waveform colliding with a grayscale update leaves artifacts on the
+  // screen: wait for the overlapping grayscale updates to complete.
+  fn resolve_collisions(&mut self, rect: &Rectangle, waveform_mode: u32) {
+    let now = Instant::now();
+    self
+      .pending
+      .retain(|_, (_, _, start)| now.duration_since(*start) < MAX_UPDATE_DURATION);
+
+    if !is_bitonal_waveform(waveform_mode) {
+      return;
+    }
+
+    let colliding: Vec<u32> = self
+      .pending
+      .iter()
+      .filter(|(_, (urect, wfm, _))| urect.overlaps(rect) && !is_bitonal_waveform(*wfm))
+      .map(|(tok, _)| *tok)
+      .collect();
+
+    for tok in colliding {
+      self.wait(tok).ok();
+      self.pending.remove(&tok);
+    }
+  }
+}
+
+fn is_bitonal_waveform(waveform_mode: u32) -> bool {
+  waveform_mode == NTX_WFM_MODE_A2 || waveform_mode == NTX_WFM_MODE_DU
+}
+
+fn waveform_name(waveform_mode: u32) -> &'static str {
+  match waveform_mode {
+    NTX_WFM_MODE_INIT => "INIT",
+    NTX_WFM_MODE_DU => "DU",
+    NTX_WFM_MODE_GC16 => "GC16",
+    NTX_WFM_MODE_GC4 => "GC4",
+    NTX_WFM_MODE_A2 => "A2",
+    NTX_WFM_MODE_GL16 => "GL16",
+    NTX_WFM_MODE_GLR16 => "GLR16",
+    NTX_WFM_MODE_GLD16 => "GLD16",
+    _ => "AUTO",
+  }
 }
 
 impl Framebuffer for KoboFramebuffer {
   fn set_pixel(&mut self, x: u32, y: u32, color: u8) {
+    let color = self
+      .tone_table
+      .as_ref()
+      .map_or(color, |t| t[color as usize]);
     (self.set_pixel_rgb)(self, x, y, [color, color, color]);
   }
 
@@ -114,6 +229,10 @@ impl Framebuffer for KoboFramebuffer {
       self.set_pixel(x, y, color);
       return;
     }
+    let color = self
+      .tone_table
+      .as_ref()
+      .map_or(color, |t| t[color as usize]);
     let rgb = (self.get_pixel_rgb)(self, x, y);
     let color_alpha = color as f32 * alpha;
     let red = color_alpha + (1.0 - alpha) * rgb[0] as f32;
@@ -146,36 +265,19 @@ impl Framebuffer for KoboFramebuffer {
     }
   }
 
+  fn waveform(&self, rect: &Rectangle, mode: UpdateMode) -> Option<&'static str> {
+    let (_, waveform_mode, _) = self.update_modes(rect, mode);
+    Some(waveform_name(waveform_mode))
+  }
+
   // Tell the driver that the screen needs to be redrawn.
   fn update(&mut self, rect: &Rectangle, mode: UpdateMode) -> Result<u32, Error> {
     let update_marker = self.token;
-    let mut flags = self.flags;
     let mark = CURRENT_DEVICE.mark();
+    let (update_mode, waveform_mode, flags) = self.update_modes(rect, mode);
 
-    let (update_mode, mut waveform_mode) = match mode {
-      UpdateMode::Gui => (UPDATE_MODE_PARTIAL, WAVEFORM_MODE_AUTO),
-      UpdateMode::Partial => {
-        if mark >= 7 {
-          (UPDATE_MODE_PARTIAL, NTX_WFM_MODE_GLR16)
-        } else if CURRENT_DEVICE.model == Model::Aura {
-          flags |= EPDC_FLAG_USE_AAD;
-          (UPDATE_MODE_FULL, NTX_WFM_MODE_GLD16)
-        } else {
-          (UPDATE_MODE_PARTIAL, WAVEFORM_MODE_AUTO)
-        }
-      },
-      UpdateMode::Full => (UPDATE_MODE_FULL, NTX_WFM_MODE_GC16),
-      UpdateMode::Fast => (UPDATE_MODE_PARTIAL, NTX_WFM_MODE_A2),
-      UpdateMode::FastMono => {
-        flags |= EPDC_FLAG_FORCE_MONOCHROME;
-        (UPDATE_MODE_PARTIAL, NTX_WFM_MODE_A2)
-      },
-    };
-
-    if self.monochrome && mode != UpdateMode::Full {
-      flags |= EPDC_FLAG_FORCE_MONOCHROME;
-      waveform_mode = NTX_WFM_MODE_A2;
-    }
+    self.resolve_collisions(rect, waveform_mode);
+    let start = Instant::now();
 
     let result = if mark >= 7 {
       let update_data = MxcfbUpdateDataV2 {
@@ -207,6 +309,16 @@ impl Framebuffer for KoboFramebuffer {
       Err(e) => Err(Error::from(e).context("Can't send framebuffer update.")),
       _ => {
         self.token = self.token.wrapping_add(1);
+        if self.stats.is_some() {
+          self.wait(update_marker).ok();
+          if let Some(stats) = self.stats.as_mut() {
+            stats.add(waveform_name(waveform_mode), start.elapsed());
+          }
+        } else {
+          self
+            .pending
+            .insert(update_marker, (*rect, waveform_mode, start));
+        }
         Ok(update_marker)
       },
     }
@@ -292,6 +404,22 @@ impl Framebuffer for KoboFramebuffer {
     self.monochrome = enable;
   }
 
+  fn set_benchmark(&mut self, enable: bool) {
+    self.stats = if enable {
+      Some(UpdateStats::default())
+    } else {
+      None
+    };
+  }
+
+  fn set_tone_table(&mut self, table: Option<[u8; 256]>) {
+    self.tone_table = table.map(Box::new);
+  }
+
+  fn benchmark(&self) -> Option<&UpdateStats> {
+    self.stats.as_ref()
+  }
+
   fn monochrome(&self) -> bool {
     self.monochrome
   }
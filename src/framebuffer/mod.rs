@@ -1,3 +1,6 @@
+mod benchmark;
+mod cleanup;
+mod dither;
 mod image;
 mod kobo;
 mod mxcfb_sys;
@@ -18,7 +21,13 @@ use crate::{
 };
 use anyhow::Error;
 
-pub use self::{image::Pixmap, kobo::KoboFramebuffer};
+pub use self::{
+  benchmark::UpdateStats,
+  cleanup::{clean_up, ScanCleanup, ScanFilter},
+  dither::{dither, DitherMode},
+  image::Pixmap,
+  kobo::KoboFramebuffer,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Display {
@@ -57,6 +66,21 @@ pub trait Framebuffer {
     self.set_monochrome(!self.monochrome());
   }
 
+  // When enabled, each update is awaited and its latency is recorded.
+  fn set_benchmark(&mut self, _enable: bool) {}
+
+  // Sets the table through which the gray levels are drawn.
+  fn set_tone_table(&mut self, _table: Option<[u8; 256]>) {}
+
+  fn benchmark(&self) -> Option<&UpdateStats> {
+    None
+  }
+
+  // The name of the waveform that an update of the given region would use.
+  fn waveform(&self, _rect: &Rectangle, _mode: UpdateMode) -> Option<&'static str> {
+    None
+  }
+
   fn rotation(&self) -> i8 {
     0
   }
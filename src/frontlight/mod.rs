@@ -7,9 +7,13 @@ pub use self::{
   premixed::PremixedFrontlight,
   standard::StandardFrontlight,
 };
-use crate::geom::lerp;
+use crate::geom::{lerp, CycleDir};
 use serde::{Deserialize, Serialize};
 
+// Below this intensity, the steps are tenths of a percent.
+const FINE_INTENSITY: f32 = 5.0;
+const FINE_STEP: f32 = 0.1;
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct LightLevels {
   pub intensity: f32,
@@ -34,6 +38,27 @@ impl LightLevels {
   }
 }
 
+// The intensity reached from *intensity* after a step of *step* percents in the given direction.
+pub fn step_intensity(intensity: f32, step: f32, dir: CycleDir) -> f32 {
+  let intensity = match dir {
+    CycleDir::Next if intensity < FINE_INTENSITY => intensity + FINE_STEP,
+    CycleDir::Next => intensity + step,
+    CycleDir::Previous if intensity <= FINE_INTENSITY => intensity - FINE_STEP,
+    CycleDir::Previous => (intensity - step).max(FINE_INTENSITY),
+  };
+  ((intensity * 10.0).round() / 10.0).max(0.0).min(100.0)
+}
+
+// The raw value of a light whose maximum is *max_value*: zero turns it off, and any other
+// intensity lights it at least at its lowest level.
+pub fn raw_level(percent: f32, max_value: f32) -> f32 {
+  if percent <= 0.0 {
+    0.0
+  } else {
+    (percent.min(100.0) / 100.0 * max_value).round().max(1.0)
+  }
+}
+
 pub trait Frontlight {
   // value is a percentage.
   fn set_intensity(&mut self, value: f32);
@@ -54,3 +79,28 @@ impl Frontlight for LightLevels {
     *self
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_step_intensity() {
+    assert_eq!(step_intensity(0.0, 5.0, CycleDir::Next), 0.1);
+    assert_eq!(step_intensity(0.1, 5.0, CycleDir::Previous), 0.0);
+    assert_eq!(step_intensity(0.0, 5.0, CycleDir::Previous), 0.0);
+    assert_eq!(step_intensity(4.9, 5.0, CycleDir::Next), 5.0);
+    assert_eq!(step_intensity(5.0, 5.0, CycleDir::Next), 10.0);
+    assert_eq!(step_intensity(5.0, 5.0, CycleDir::Previous), 4.9);
+    assert_eq!(step_intensity(7.0, 5.0, CycleDir::Previous), 5.0);
+    assert_eq!(step_intensity(98.0, 5.0, CycleDir::Next), 100.0);
+  }
+
+  #[test]
+  fn test_raw_level() {
+    assert_eq!(raw_level(0.0, 100.0), 0.0);
+    assert_eq!(raw_level(0.1, 100.0), 1.0);
+    assert_eq!(raw_level(50.0, 255.0), 128.0);
+    assert_eq!(raw_level(120.0, 100.0), 100.0);
+  }
+}
@@ -1,4 +1,4 @@
-use super::{Frontlight, LightLevels};
+use super::{raw_level, Frontlight, LightLevels};
 use crate::device::{Model, CURRENT_DEVICE};
 use anyhow::Error;
 use fxhash::FxHashMap;
@@ -99,7 +99,7 @@ impl NaturalFrontlight {
 
   fn set(&mut self, c: LightColor, percent: f32) {
     let max_value = self.maxima[&c] as f32;
-    let value = (percent.max(0.0).min(100.0) / 100.0 * max_value) as i16;
+    let value = raw_level(percent, max_value) as i16;
     let mut file = &self.values[&c];
     write!(file, "{}", value).unwrap();
     let mut file = &self.powers[&c];
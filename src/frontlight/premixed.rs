@@ -1,4 +1,4 @@
-use super::{Frontlight, LightLevels};
+use super::{raw_level, Frontlight, LightLevels};
 use crate::device::{Model, CURRENT_DEVICE};
 use anyhow::Error;
 use std::{
@@ -9,6 +9,10 @@ use std::{
 
 const FRONTLIGHT_INTERFACE: &str = "/sys/class/backlight";
 const FRONTLIGHT_WHITE: &str = "mxc_msp430.0/brightness";
+// Some drivers keep the LEDs faintly lit at a zero brightness until they're powered down.
+const FRONTLIGHT_POWER: &str = "mxc_msp430.0/bl_power";
+const FRONTLIGHT_POWER_ON: i16 = 0;
+const FRONTLIGHT_POWER_OFF: i16 = 4;
 // Forma
 const FRONTLIGHT_ORANGE_A: &str = "tlc5947_bl/color";
 // Libra H₂O, Clara HD
@@ -19,6 +23,7 @@ pub struct PremixedFrontlight {
   warmth: f32,
   white: File,
   orange: File,
+  power: Option<File>,
 }
 
 impl PremixedFrontlight {
@@ -34,19 +39,36 @@ impl PremixedFrontlight {
       FRONTLIGHT_ORANGE_B
     });
     let orange = OpenOptions::new().write(true).open(orange_path)?;
+    let power = OpenOptions::new()
+      .write(true)
+      .open(base.join(FRONTLIGHT_POWER))
+      .ok();
     Ok(PremixedFrontlight {
       intensity,
       warmth,
       white,
       orange,
+      power,
     })
   }
+
+  fn set_power(&mut self, value: i16) {
+    if let Some(power) = self.power.as_mut() {
+      write!(power, "{}", value).ok();
+    }
+  }
 }
 
 impl Frontlight for PremixedFrontlight {
   fn set_intensity(&mut self, intensity: f32) {
-    let white = intensity.round() as i16;
+    let white = raw_level(intensity, 100.0) as i16;
+    if white > 0 {
+      self.set_power(FRONTLIGHT_POWER_ON);
+    }
     write!(self.white, "{}", white).unwrap();
+    if white == 0 {
+      self.set_power(FRONTLIGHT_POWER_OFF);
+    }
     self.intensity = intensity;
   }
 
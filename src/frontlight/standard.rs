@@ -1,4 +1,4 @@
-use super::{Frontlight, LightLevels};
+use super::{raw_level, Frontlight, LightLevels};
 use anyhow::Error;
 use nix::ioctl_write_int_bad;
 use std::{
@@ -23,8 +23,8 @@ impl StandardFrontlight {
 
 impl Frontlight for StandardFrontlight {
   fn set_intensity(&mut self, value: f32) {
-    let ret =
-      unsafe { write_frontlight_intensity(self.interface.as_raw_fd(), value as libc::c_int) };
+    let level = raw_level(value, 100.0) as libc::c_int;
+    let ret = unsafe { write_frontlight_intensity(self.interface.as_raw_fd(), level) };
     if ret.is_ok() {
       self.value = value;
     }
@@ -14,7 +14,8 @@ pub enum Dir {
   West,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DiagDir {
   NorthWest,
   NorthEast,
@@ -677,6 +678,12 @@ impl Rectangle {
     self.max.x -= edges.right;
     self.max.y -= edges.bottom;
   }
+
+  // Reflects the rectangle across the vertical axis of *within*.
+  pub fn mirrored(&self, within: &Rectangle) -> Rectangle {
+    let sum = within.min.x + within.max.x;
+    rect![sum - self.max.x, self.min.y, sum - self.min.x, self.max.y]
+  }
 }
 
 impl Default for Rectangle {
@@ -1281,6 +1288,15 @@ mod tests {
     assert!(a.overlaps(&e));
   }
 
+  #[test]
+  fn mirrored_rectangles() {
+    let a = rect![0, 0, 100, 10];
+    assert_eq!(rect![0, 0, 10, 10].mirrored(&a), rect![90, 0, 100, 10]);
+    assert_eq!(rect![20, 2, 30, 8].mirrored(&a), rect![70, 2, 80, 8]);
+    let b = rect![50, 0, 100, 10];
+    assert_eq!(rect![50, 0, 60, 10].mirrored(&b), rect![90, 0, 100, 10]);
+  }
+
   #[test]
   fn contained_rectangles() {
     let a = rect![2, 2, 10, 10];
@@ -83,6 +83,34 @@ where
     .map_err(Into::into)
 }
 
+// Writes to a temporary file that then replaces the destination, so that the previous version
+// survives an interrupted write.
+pub fn save_json_atomically<T, P: AsRef<Path>>(data: &T, path: P) -> Result<(), Error>
+where
+  T: Serialize,
+{
+  let path = path.as_ref();
+  let mut tmp_path = path.as_os_str().to_owned();
+  tmp_path.push(".tmp");
+  let tmp_path = PathBuf::from(tmp_path);
+  let file = File::create(&tmp_path)
+    .with_context(|| format!("Cannot create file {}.", tmp_path.display()))?;
+  serde_json::to_writer_pretty(&file, data)
+    .with_context(|| format!("Cannot serialize to JSON file {}.", tmp_path.display()))?;
+  file
+    .sync_all()
+    .with_context(|| format!("Cannot sync file {}.", tmp_path.display()))?;
+  fs::rename(&tmp_path, path)
+    .with_context(|| {
+      format!(
+        "Cannot rename {} to {}.",
+        tmp_path.display(),
+        path.display()
+      )
+    })
+    .map_err(Into::into)
+}
+
 pub fn load_toml<T, P: AsRef<Path>>(path: P) -> Result<T, Error>
 where
   for<'a> T: Deserialize<'a>,
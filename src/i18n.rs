@@ -0,0 +1,160 @@
+//! Translates the messages of the user interface.
+//!
+//! The messages are written in English in the code, and the catalog of the selected language
+//! maps them to their translations. A message missing from the catalog is shown in English.
+//! The arguments of a message are given by `{}`, or by `{N}` when a translation needs to
+//! reorder them.
+
+use crate::helpers::load_toml;
+use anyhow::Error;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, fs, path::Path, sync::RwLock};
+
+pub const I18N_DIRNAME: &str = "i18n";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Catalog {
+  // The name of the language, in this language.
+  pub name: String,
+  // Whether the language is written from right to left.
+  pub right_to_left: bool,
+  pub messages: FxHashMap<String, String>,
+}
+
+lazy_static! {
+  static ref CATALOG: RwLock<Catalog> = RwLock::new(Catalog::default());
+}
+
+// Loads the catalog of the given language, an empty language selects English.
+// Returns whether the language is written from right to left.
+pub fn load_catalog(language: &str) -> Result<bool, Error> {
+  let catalog = if language.is_empty() {
+    Catalog::default()
+  } else {
+    load_toml::<Catalog, _>(
+      Path::new(I18N_DIRNAME)
+        .join(language)
+        .with_extension("toml"),
+    )?
+  };
+  let right_to_left = catalog.right_to_left;
+  if let Ok(mut current) = CATALOG.write() {
+    *current = catalog;
+  }
+  Ok(right_to_left)
+}
+
+// Returns the codes and the names of the languages that have a catalog.
+pub fn available_languages() -> Vec<(String, String)> {
+  let mut languages = Vec::new();
+  if let Ok(entries) = fs::read_dir(I18N_DIRNAME) {
+    for entry in entries.filter_map(|e| e.ok()) {
+      let path = entry.path();
+      if entry.file_name().to_string_lossy().starts_with('.')
+        || path.extension().map_or(true, |ext| ext != "toml")
+      {
+        continue;
+      }
+      if let (Some(code), Ok(catalog)) = (
+        path.file_stem().and_then(|s| s.to_str()),
+        load_toml::<Catalog, _>(&path),
+      ) {
+        languages.push((code.to_string(), catalog.name));
+      }
+    }
+  }
+  languages.sort_by(|a, b| a.1.cmp(&b.1));
+  languages
+}
+
+pub fn tr(message: &str) -> String {
+  CATALOG
+    .read()
+    .ok()
+    .and_then(|catalog| catalog.messages.get(message).cloned())
+    .unwrap_or_else(|| message.to_string())
+}
+
+// Replaces the arguments placeholders of *template*.
+pub fn format_message(template: &str, args: &[&dyn Display]) -> String {
+  let mut result = String::new();
+  let mut next = 0;
+  let mut rest = template;
+  while let Some(start) = rest.find('{') {
+    result.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let end = match rest.find('}') {
+      Some(end) => end,
+      None => break,
+    };
+    let index = if end == 1 {
+      next += 1;
+      Some(next - 1)
+    } else {
+      rest[1..end].parse::<usize>().ok()
+    };
+    match index.and_then(|i| args.get(i)) {
+      Some(arg) => result.push_str(&arg.to_string()),
+      None => result.push_str(&rest[..=end]),
+    }
+    rest = &rest[end + 1..];
+  }
+  result.push_str(rest);
+  result
+}
+
+#[macro_export]
+macro_rules! tr {
+  ($msg:expr) => {
+    $crate::i18n::tr($msg)
+  };
+  ($msg:expr, $($arg:expr),+ $(,)?) => {
+    $crate::i18n::format_message(
+      &$crate::i18n::tr($msg),
+      &[$(&$arg as &dyn std::fmt::Display),+],
+    )
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn placeholders(text: &str) -> usize {
+    text.matches('{').count()
+  }
+
+  #[test]
+  fn test_format_message() {
+    assert_eq!(
+      format_message("{} of {}", &[&3, &"Dune"]),
+      "3 of Dune".to_string()
+    );
+    assert_eq!(
+      format_message("{1}: {0}", &[&"a", &"b"]),
+      "b: a".to_string()
+    );
+    assert_eq!(format_message("{2} {", &[&1]), "{2} {".to_string());
+  }
+
+  #[test]
+  fn test_catalogs() {
+    for entry in fs::read_dir(I18N_DIRNAME).unwrap() {
+      let path = entry.unwrap().path();
+      let catalog = load_toml::<Catalog, _>(&path).unwrap();
+      assert!(!catalog.name.is_empty(), "{}", path.display());
+      for (message, translation) in &catalog.messages {
+        assert_eq!(
+          placeholders(message),
+          placeholders(translation),
+          "{}: {}",
+          path.display(),
+          message
+        );
+      }
+    }
+  }
+}
@@ -1,5 +1,6 @@
 #[macro_use]
 mod geom;
+mod annotation_import;
 mod color;
 mod device;
 mod document;
@@ -11,10 +12,17 @@ mod input;
 mod library;
 mod lightsensor;
 mod metadata;
+mod opf;
+mod resources;
 mod settings;
 mod unit;
 
 use crate::{
+  annotation_import::{
+    import_kindle_clippings,
+    import_koreader_annotations,
+    parse_kindle_clippings,
+  },
   helpers::datetime_format,
   library::Library,
   metadata::{
@@ -24,6 +32,7 @@ use crate::{
     rename_from_info,
     Info,
   },
+  opf::{extract_metadata_from_opf, write_metadata_to_opf},
   settings::{ImportSettings, LibraryMode},
 };
 use anyhow::{format_err, Context, Error};
@@ -39,7 +48,22 @@ fn main() -> Result<(), Error> {
   opts.optflag("h", "help", "Print this help message.");
   opts.optflag("I", "import", "Import new files or update existing files.");
   opts.optflag("C", "clean-up", "Remove entries with dangling paths.");
+  opts.optflag(
+    "V",
+    "check-integrity",
+    "List the missing and modified files, and the orphaned reading states.",
+  );
   opts.optflag("E", "extract-metadata-epub", "Extract metadata from ePUBs.");
+  opts.optflag(
+    "O",
+    "extract-metadata-opf",
+    "Extract metadata from OPF sidecars.",
+  );
+  opts.optflag(
+    "W",
+    "write-metadata-opf",
+    "Write metadata back to OPF sidecars.",
+  );
   opts.optflag(
     "F",
     "extract-metadata-filename",
@@ -55,7 +79,19 @@ fn main() -> Result<(), Error> {
     "rename-from-info",
     "Rename files based on their information.",
   );
+  opts.optflag(
+    "R",
+    "import-koreader-annotations",
+    "Import the annotations of KOReader's sidecars.",
+  );
+  opts.optopt(
+    "K",
+    "import-kindle-clippings",
+    "Import the annotations of a Kindle clippings file.",
+    "CLIPPINGS_PATH",
+  );
   opts.optflag("t", "traverse-hidden", "Traverse hidden directories.");
+  opts.optflag("n", "dry-run", "Only list what would be imported.");
   opts.optopt(
     "k",
     "allowed-kinds",
@@ -80,7 +116,7 @@ fn main() -> Result<(), Error> {
     .context("Failed to parse the command line arguments.")?;
 
   if matches.opt_present("h") {
-    println!("{}", opts.usage("Usage: plato-import -h|-I|-C|-EFSN [-t] [-k ALLOWED_KINDS] [-a ADDED_DATETIME] [-m LIBRARY_MODE] LIBRARY_PATH"));
+    println!("{}", opts.usage("Usage: plato-import -h|-I|-C|-V|-EOFSNWR [-K CLIPPINGS_PATH] [-t] [-n] [-k ALLOWED_KINDS] [-a ADDED_DATETIME] [-m LIBRARY_MODE] LIBRARY_PATH"));
     return Ok(());
   }
 
@@ -92,7 +128,9 @@ fn main() -> Result<(), Error> {
 
   let mut import_settings = ImportSettings {
     extract_epub_metadata: false,
+    extract_opf_metadata: matches.opt_present("O"),
     traverse_hidden: matches.opt_present("t"),
+    dry_run: matches.opt_present("n"),
     ..Default::default()
   };
 
@@ -121,17 +159,30 @@ fn main() -> Result<(), Error> {
   let mut library = Library::new(&library_path, mode);
 
   if matches.opt_present("I") {
-    library.import(&library_path, &import_settings);
+    let report = library.import(&library_path, &import_settings);
+    if import_settings.dry_run {
+      print!("{}", report);
+    }
   } else if matches.opt_present("C") {
     library.clean_up();
+  } else if matches.opt_present("V") {
+    for issue in library.check_integrity() {
+      println!("{}", issue);
+    }
   } else {
     let opt_extract_metadata_epub = matches.opt_present("E");
+    let opt_extract_metadata_opf = matches.opt_present("O");
+    let opt_write_metadata_opf = matches.opt_present("W");
     let opt_extract_metadata_filename = matches.opt_present("F");
     let opt_consolidate = matches.opt_present("S");
     let opt_rename_from_info = matches.opt_present("N");
 
     library.apply(|path, info| {
       if added_after.map_or(true, |added| info.added >= added) {
+        if opt_extract_metadata_opf {
+          extract_metadata_from_opf(path, info);
+        }
+
         if opt_extract_metadata_epub {
           extract_metadata_from_epub(path, info);
         }
@@ -147,11 +198,35 @@ fn main() -> Result<(), Error> {
         if opt_rename_from_info {
           rename_from_info(path, info);
         }
+
+        if opt_write_metadata_opf {
+          if let Err(e) = write_metadata_to_opf(path, info) {
+            eprintln!("{:#}", e);
+          }
+        }
       }
     });
+
+    if matches.opt_present("R") {
+      library.update_reading_states(|path, info| {
+        added_after.map_or(true, |added| info.added >= added)
+          && import_koreader_annotations(path, info)
+      });
+    }
+
+    if let Some(clippings_path) = matches.opt_str("K") {
+      let text = fs::read_to_string(&clippings_path)
+        .with_context(|| format!("Can't read clippings file {}.", clippings_path))?;
+      let books = parse_kindle_clippings(&text);
+      library.update_reading_states(|path, info| {
+        added_after.map_or(true, |added| info.added >= added)
+          && import_kindle_clippings(path, info, &books)
+      });
+    }
   }
 
-  library.flush();
+  library.flush()?;
+  library.compact()?;
 
   Ok(())
 }
@@ -8,16 +8,24 @@ use anyhow::{Context, Error};
 use fxhash::{FxHashMap, FxHashSet};
 use std::{
   ffi::CString,
-  fs::File,
-  io::Read,
+  fs::{self, File},
+  io::{self, Read},
   mem::{self, MaybeUninit},
   os::unix::io::AsRawFd,
+  path::Path,
   ptr,
   slice,
-  sync::mpsc::{self, Receiver, Sender},
+  sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc,
+    Mutex,
+  },
   thread,
 };
 
+const INPUT_DIR: &str = "/dev/input";
+const INPUT_CLASS_DIR: &str = "/sys/class/input";
+
 // Event types
 pub const EV_SYN: u16 = 0x00;
 pub const EV_KEY: u16 = 0x01;
@@ -63,6 +71,8 @@ pub const KEY_HOME: u16 = 102;
 pub const KEY_LIGHT: u16 = 90;
 pub const KEY_BACKWARD: u16 = 193;
 pub const KEY_FORWARD: u16 = 194;
+pub const KEY_A: u16 = 30;
+pub const BTN_TOOL_PEN: u16 = 0x140;
 // The following key codes are fake, and are used to support
 // software toggles within this design
 pub const KEY_ROTATE_DISPLAY: u16 = 0xffff;
@@ -87,6 +97,7 @@ pub const MULTI_TOUCH_CODES_B: TouchCodes = TouchCodes {
 };
 
 #[repr(C)]
+#[derive(Copy, Clone)]
 pub struct InputEvent {
   pub time: libc::timeval,
   pub kind: u16, // type
@@ -173,7 +184,7 @@ fn resolve_button_direction(
   ButtonCode::Backward
 }
 
-pub fn display_rotate_event(n: i8) -> InputEvent {
+pub fn current_time() -> libc::timeval {
   let mut tp = libc::timeval {
     tv_sec: 0,
     tv_usec: 0,
@@ -181,8 +192,12 @@ pub fn display_rotate_event(n: i8) -> InputEvent {
   unsafe {
     libc::gettimeofday(&mut tp, ptr::null_mut());
   }
+  tp
+}
+
+pub fn display_rotate_event(n: i8) -> InputEvent {
   InputEvent {
-    time: tp,
+    time: current_time(),
     kind: EV_KEY,
     code: KEY_ROTATE_DISPLAY,
     value: n as i32,
@@ -190,21 +205,73 @@ pub fn display_rotate_event(n: i8) -> InputEvent {
 }
 
 pub fn button_scheme_event(v: i32) -> InputEvent {
-  let mut tp = libc::timeval {
-    tv_sec: 0,
-    tv_usec: 0,
-  };
-  unsafe {
-    libc::gettimeofday(&mut tp, ptr::null_mut());
-  }
   InputEvent {
-    time: tp,
+    time: current_time(),
     kind: EV_KEY,
     code: KEY_BUTTON_SCHEME,
     value: v,
   }
 }
 
+// Produces the raw events that `parse_device_events` would turn into the given finger event.
+pub fn finger_raw_events(
+  id: i32,
+  status: FingerStatus,
+  position: Point,
+  time: libc::timeval,
+  display: Display,
+) -> Vec<InputEvent> {
+  let Display { dims, rotation } = display;
+  let proto = CURRENT_DEVICE.proto;
+
+  let mut tc = match proto {
+    TouchProto::Single => SINGLE_TOUCH_CODES,
+    TouchProto::MultiA => MULTI_TOUCH_CODES_A,
+    TouchProto::MultiB => MULTI_TOUCH_CODES_B,
+  };
+
+  let (mirror_x, mirror_y) = CURRENT_DEVICE.should_mirror_axes(rotation);
+  if CURRENT_DEVICE.should_swap_axes(rotation) {
+    mem::swap(&mut tc.x, &mut tc.y);
+  }
+
+  let x = if mirror_x {
+    dims.0 as i32 - 1 - position.x
+  } else {
+    position.x
+  };
+  let y = if mirror_y {
+    dims.1 as i32 - 1 - position.y
+  } else {
+    position.y
+  };
+  let pressure = if status == FingerStatus::Up { 0 } else { 1 };
+
+  let event = |kind, code, value| InputEvent {
+    time,
+    kind,
+    code,
+    value,
+  };
+
+  let mut events = Vec::with_capacity(6);
+
+  if proto != TouchProto::Single {
+    events.push(event(EV_ABS, ABS_MT_TRACKING_ID, id));
+  }
+
+  events.push(event(EV_ABS, tc.x, x));
+  events.push(event(EV_ABS, tc.y, y));
+  events.push(event(EV_ABS, tc.pressure, pressure));
+
+  if proto != TouchProto::Single {
+    events.push(event(EV_SYN, SYN_MT_REPORT, 0));
+  }
+
+  events.push(event(EV_SYN, SYN_REPORT, 0));
+  events
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum DeviceEvent {
   Finger {
@@ -225,6 +292,16 @@ pub enum DeviceEvent {
   CoverOff,
   NetUp,
   UserActivity,
+  // An input device was plugged in or connected.
+  Attach(InputDeviceKind),
+  Detach(InputDeviceKind),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputDeviceKind {
+  Keyboard,
+  Stylus,
+  Other,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -338,6 +415,148 @@ fn parse_usb_events(tx: &Sender<DeviceEvent>) {
   }
 }
 
+// Watches the input directory for the devices that come and go, the given paths excepted.
+// The key events of the attached devices are sent through *raw_tx*.
+pub fn hotplug_events(paths: Vec<String>, raw_tx: Sender<InputEvent>) -> Receiver<DeviceEvent> {
+  let (tx, rx) = mpsc::channel();
+  thread::spawn(move || {
+    if let Err(e) = watch_input_devices(&paths, &raw_tx, &tx) {
+      eprintln!("Can't watch the input devices: {:#}.", e);
+    }
+  });
+  rx
+}
+
+fn watch_input_devices(
+  paths: &[String],
+  raw_tx: &Sender<InputEvent>,
+  tx: &Sender<DeviceEvent>,
+) -> Result<(), Error> {
+  let attached = Arc::new(Mutex::new(FxHashSet::default()));
+
+  let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+  if fd < 0 {
+    return Err(io::Error::last_os_error().into());
+  }
+  let dir = CString::new(INPUT_DIR).unwrap();
+  // The permissions of a new device node might be set after its creation.
+  let wd = unsafe { libc::inotify_add_watch(fd, dir.as_ptr(), libc::IN_CREATE | libc::IN_ATTRIB) };
+  if wd < 0 {
+    return Err(io::Error::last_os_error().into());
+  }
+
+  // The devices that were attached before the application started.
+  for entry in fs::read_dir(INPUT_DIR)?.filter_map(|e| e.ok()) {
+    let name = entry.file_name().to_string_lossy().into_owned();
+    attach_input_device(&name, paths, &attached, raw_tx, tx);
+  }
+
+  let mut buf = [0u8; 4096];
+  let header_len = mem::size_of::<libc::inotify_event>();
+
+  loop {
+    let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+    if n <= 0 {
+      break;
+    }
+    let n = n as usize;
+    let mut offset = 0;
+    while offset + header_len <= n {
+      let event =
+        unsafe { ptr::read_unaligned(buf[offset..].as_ptr() as *const libc::inotify_event) };
+      let name_start = offset + header_len;
+      let name_end = (name_start + event.len as usize).min(n);
+      let name = buf[name_start..name_end]
+        .split(|&b| b == 0)
+        .next()
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .unwrap_or_default();
+      attach_input_device(&name, paths, &attached, raw_tx, tx);
+      offset = name_end;
+    }
+  }
+
+  unsafe { libc::close(fd) };
+  Ok(())
+}
+
+// Starts a thread that forwards the key events of the given device until it's removed.
+fn attach_input_device(
+  name: &str,
+  paths: &[String],
+  attached: &Arc<Mutex<FxHashSet<String>>>,
+  raw_tx: &Sender<InputEvent>,
+  tx: &Sender<DeviceEvent>,
+) {
+  if !name.starts_with("event") {
+    return;
+  }
+  let path = Path::new(INPUT_DIR).join(name);
+  if paths.iter().any(|p| Path::new(p) == path) || attached.lock().unwrap().contains(name) {
+    return;
+  }
+  let mut file = match File::open(&path) {
+    Ok(file) => file,
+    Err(_) => return,
+  };
+
+  let kind = input_device_kind(name);
+  attached.lock().unwrap().insert(name.to_string());
+  tx.send(DeviceEvent::Attach(kind)).ok();
+
+  let name = name.to_string();
+  let attached = Arc::clone(attached);
+  let raw_tx = raw_tx.clone();
+  let tx = tx.clone();
+  thread::spawn(move || {
+    loop {
+      let mut input_event = MaybeUninit::<InputEvent>::uninit();
+      let evt = unsafe {
+        let event_slice = slice::from_raw_parts_mut(
+          input_event.as_mut_ptr() as *mut u8,
+          mem::size_of::<InputEvent>(),
+        );
+        if file.read_exact(event_slice).is_err() {
+          break;
+        }
+        input_event.assume_init()
+      };
+      // The other events would be mistaken for the ones of the touch screen.
+      if evt.kind == EV_KEY && raw_tx.send(evt).is_err() {
+        break;
+      }
+    }
+    attached.lock().unwrap().remove(&name);
+    tx.send(DeviceEvent::Detach(kind)).ok();
+  });
+}
+
+fn input_device_kind(name: &str) -> InputDeviceKind {
+  let path = Path::new(INPUT_CLASS_DIR)
+    .join(name)
+    .join("device/capabilities/key");
+  let mask = fs::read_to_string(path).unwrap_or_default();
+  let word_bits = 8 * mem::size_of::<libc::c_ulong>();
+  if has_capability(&mask, BTN_TOOL_PEN, word_bits) {
+    InputDeviceKind::Stylus
+  } else if has_capability(&mask, KEY_A, word_bits) {
+    InputDeviceKind::Keyboard
+  } else {
+    InputDeviceKind::Other
+  }
+}
+
+// The capabilities are given as space separated hexadecimal words, the most significant first.
+fn has_capability(mask: &str, bit: u16, word_bits: usize) -> bool {
+  let bit = bit as usize;
+  mask
+    .split_whitespace()
+    .rev()
+    .nth(bit / word_bits)
+    .and_then(|word| u64::from_str_radix(word, 16).ok())
+    .map_or(false, |word| (word >> (bit % word_bits)) & 1 == 1)
+}
+
 pub fn device_events(
   rx: Receiver<InputEvent>,
   display: Display,
@@ -508,12 +727,21 @@ pub fn parse_device_events(
 #[cfg(test)]
 mod tests {
   use crate::{
+    framebuffer::Display,
     input::{
       button_scheme_event,
+      current_time,
       display_rotate_event,
+      finger_raw_events,
+      has_capability,
+      parse_device_events,
       ButtonCode,
       ButtonStatus,
+      DeviceEvent,
+      FingerStatus,
+      BTN_TOOL_PEN,
       EV_KEY,
+      KEY_A,
       KEY_BACKWARD,
       KEY_BUTTON_SCHEME,
       KEY_FORWARD,
@@ -527,6 +755,18 @@ mod tests {
     },
     settings::ButtonScheme,
   };
+  use std::sync::mpsc;
+
+  #[test]
+  fn test_has_capability() {
+    assert!(has_capability("1 40000000", KEY_A, 32));
+    assert!(has_capability("1 40000000", 32, 32));
+    assert!(!has_capability("1 40000000", 31, 32));
+    assert!(has_capability("1 0 0 0 0 40000000", BTN_TOOL_PEN, 64));
+    assert!(has_capability("1 0 0 0 0 40000000", KEY_A, 64));
+    assert!(!has_capability("40000000", BTN_TOOL_PEN, 64));
+    assert!(!has_capability("", KEY_A, 64));
+  }
 
   #[test]
   fn test_button_status_try_from_raw() {
@@ -664,4 +904,52 @@ mod tests {
     assert_eq!(input.code, KEY_BUTTON_SCHEME);
     assert_eq!(input.value, VAL_PRESS);
   }
+
+  #[test]
+  fn test_finger_raw_events_round_trip() {
+    let statuses = [FingerStatus::Down, FingerStatus::Motion, FingerStatus::Up];
+    let positions = [pt!(12, 34), pt!(56, 78), pt!(56, 78)];
+
+    for rotation in 0..4 {
+      let display = Display {
+        dims: if rotation % 2 == 0 {
+          (800, 600)
+        } else {
+          (600, 800)
+        },
+        rotation,
+      };
+      let (tx, rx) = mpsc::channel();
+      let (ty, ry) = mpsc::channel();
+
+      for (&status, &position) in statuses.iter().zip(positions.iter()) {
+        for evt in finger_raw_events(0, status, position, current_time(), display) {
+          tx.send(evt).unwrap();
+        }
+      }
+
+      drop(tx);
+      parse_device_events(&rx, &ty, display, ButtonScheme::Natural);
+      drop(ty);
+
+      let fingers: Vec<(FingerStatus, _)> = ry
+        .iter()
+        .filter_map(|evt| match evt {
+          DeviceEvent::Finger {
+            status, position, ..
+          } => Some((status, position)),
+          _ => None,
+        })
+        .collect();
+
+      assert_eq!(
+        fingers,
+        vec![
+          (FingerStatus::Down, pt!(12, 34)),
+          (FingerStatus::Motion, pt!(56, 78)),
+          (FingerStatus::Up, pt!(56, 78)),
+        ]
+      );
+    }
+  }
 }
@@ -0,0 +1,219 @@
+//! Records the use of a few features in a local file, and summarizes it.
+//!
+//! Nothing is recorded unless the user opts in, and the file never leaves the device: each line
+//! of the file is a JSON object describing one event.
+
+use crate::{helpers::datetime_format, view::AppCmd};
+use anyhow::Error;
+use chrono::{DateTime, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::BTreeMap,
+  fs::{self, OpenOptions},
+  io::Write,
+  path::Path,
+};
+
+pub const INSIGHTS_PATH: &str = "Insights.jsonl";
+// The width, in characters, of the longest bar of the hours chart.
+const BAR_WIDTH: usize = 24;
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Usage {
+  Launch { app: String },
+  Lookup,
+  Page,
+}
+
+impl Usage {
+  pub fn launch(app_cmd: &AppCmd) -> Usage {
+    let app = match app_cmd {
+      AppCmd::Sketch { .. } => "Sketch",
+      AppCmd::TapZones => "Tap Zones",
+      AppCmd::Calculator => "Calculator",
+      AppCmd::Dictionary { .. } => "Dictionary",
+      #[cfg(feature = "feeds")]
+      AppCmd::Feeds => "Feeds",
+      #[cfg(feature = "dictionary-manager")]
+      AppCmd::Dictionaries => "Dictionaries",
+      #[cfg(feature = "opds")]
+      AppCmd::Opds => "Catalogs",
+    };
+    Usage::Launch {
+      app: app.to_string(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+  #[serde(with = "datetime_format")]
+  pub time: DateTime<Local>,
+  #[serde(flatten)]
+  pub usage: Usage,
+}
+
+// Appends an event to the insights file.
+pub fn record_usage<P: AsRef<Path>>(path: P, usage: Usage) -> Result<(), Error> {
+  let record = Record {
+    time: Local::now(),
+    usage,
+  };
+  let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+  writeln!(file, "{}", serde_json::to_string(&record)?)?;
+  Ok(())
+}
+
+// The lines that can't be parsed are skipped.
+pub fn load_insights<P: AsRef<Path>>(path: P) -> Result<Vec<Record>, Error> {
+  if !path.as_ref().exists() {
+    return Ok(Vec::new());
+  }
+  let text = fs::read_to_string(path)?;
+  Ok(
+    text
+      .lines()
+      .filter_map(|line| serde_json::from_str(line).ok())
+      .collect(),
+  )
+}
+
+pub fn purge_insights<P: AsRef<Path>>(path: P) -> Result<(), Error> {
+  if path.as_ref().exists() {
+    fs::remove_file(path)?;
+  }
+  Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+  pub launches: BTreeMap<String, usize>,
+  pub lookups: usize,
+  pub pages_by_hour: [usize; 24],
+  pub period: Option<(DateTime<Local>, DateTime<Local>)>,
+}
+
+pub fn summarize(records: &[Record]) -> Summary {
+  let mut summary = Summary::default();
+  for record in records {
+    match record.usage {
+      Usage::Launch { ref app } => *summary.launches.entry(app.clone()).or_insert(0) += 1,
+      Usage::Lookup => summary.lookups += 1,
+      Usage::Page => summary.pages_by_hour[record.time.hour() as usize] += 1,
+    }
+    summary.period = match summary.period {
+      Some((start, end)) => Some((start.min(record.time), end.max(record.time))),
+      None => Some((record.time, record.time)),
+    };
+  }
+  summary
+}
+
+pub fn insights_as_html(records: &[Record], enabled: bool) -> String {
+  let summary = summarize(records);
+  let mut buf =
+    "<html>\n\t<head>\n\t\t<title>Usage Insights</title>\n\t</head>\n\t<body>\n".to_string();
+
+  let status = if enabled {
+    "Recording"
+  } else {
+    "Not recording"
+  };
+  buf.push_str(&format!(
+    "\t\t<p>{}. The insights are stored in <code>{}</code>, in Plato's directory, and never leave the device.</p>\n",
+    status, INSIGHTS_PATH
+  ));
+
+  let (start, end) = match summary.period {
+    Some(period) => period,
+    None => {
+      buf.push_str("\t\t<p>Nothing has been recorded yet.</p>\n\t</body>\n</html>");
+      return buf;
+    },
+  };
+
+  buf.push_str(&format!(
+    "\t\t<p>From {} to {}.</p>\n",
+    start.format("%x"),
+    end.format("%x")
+  ));
+
+  buf.push_str("\t\t<h2>Applications</h2>\n");
+  if summary.launches.is_empty() {
+    buf.push_str("\t\t<p>No application was launched.</p>\n");
+  } else {
+    let mut launches: Vec<(&String, &usize)> = summary.launches.iter().collect();
+    launches.sort_by(|a, b| b.1.cmp(a.1));
+    buf.push_str("\t\t<table>\n");
+    for (app, count) in launches {
+      buf.push_str(&format!(
+        "\t\t\t<tr><td>{}</td><td>{}</td></tr>\n",
+        app, count
+      ));
+    }
+    buf.push_str("\t\t</table>\n");
+  }
+
+  buf.push_str(&format!(
+    "\t\t<h2>Dictionary</h2>\n\t\t<p>{} lookup{}.</p>\n",
+    summary.lookups,
+    if summary.lookups == 1 { "" } else { "s" }
+  ));
+
+  let total: usize = summary.pages_by_hour.iter().sum();
+  buf.push_str(&format!(
+    "\t\t<h2>Pages Read</h2>\n\t\t<p>{} pages.</p>\n",
+    total
+  ));
+  if total > 0 {
+    let max = summary.pages_by_hour.iter().max().cloned().unwrap_or(1);
+    buf.push_str("\t\t<table>\n");
+    for (hour, count) in summary.pages_by_hour.iter().enumerate() {
+      let bar = "█".repeat((count * BAR_WIDTH + max - 1) / max);
+      buf.push_str(&format!(
+        "\t\t\t<tr><td>{:02}:00</td><td>{}</td><td>{}</td></tr>\n",
+        hour, bar, count
+      ));
+    }
+    buf.push_str("\t\t</table>\n");
+  }
+
+  buf.push_str("\t</body>\n</html>");
+  buf
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::{NaiveDate, TimeZone};
+
+  #[test]
+  fn test_summarize() {
+    let time = |hour| {
+      let naive = NaiveDate::from_ymd_opt(2021, 6, 3)
+        .and_then(|date| date.and_hms_opt(hour, 15, 0))
+        .unwrap();
+      Local.from_local_datetime(&naive).unwrap()
+    };
+    let lines = [
+      r#"{"time":"2021-06-03 08:15:00","kind":"launch","app":"Calculator"}"#,
+      r#"{"time":"2021-06-03 21:15:00","kind":"page"}"#,
+      "garbage",
+      r#"{"time":"2021-06-03 21:15:00","kind":"page"}"#,
+      r#"{"time":"2021-06-03 22:15:00","kind":"lookup"}"#,
+    ];
+    let records: Vec<Record> = lines
+      .iter()
+      .filter_map(|line| serde_json::from_str(line).ok())
+      .collect();
+    assert_eq!(records.len(), 4);
+    let summary = summarize(&records);
+    assert_eq!(summary.launches.get("Calculator"), Some(&1));
+    assert_eq!(summary.lookups, 1);
+    assert_eq!(summary.pages_by_hour[21], 2);
+    assert_eq!(summary.period, Some((time(8), time(22))));
+    let line = serde_json::to_string(&records[0]).unwrap();
+    assert_eq!(line, lines[0]);
+  }
+}
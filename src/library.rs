@@ -1,35 +1,260 @@
 use crate::{
-  document::file_kind,
-  helpers::{load_json, save_json, Fingerprint, IsHidden},
+  document::{
+    file_kind,
+    images::{image_folder_size, IMAGE_FOLDER_KIND},
+  },
+  helpers::{load_json, save_json_atomically, Fingerprint, IsHidden},
   metadata::{
+    collections,
     extract_metadata_from_epub,
     sort,
+    sort_opened,
     sorter,
+    Collection,
     FileInfo,
     Info,
+    Loan,
     ReaderInfo,
     SimpleStatus,
     SortMethod,
+    Visibility,
   },
-  settings::{ImportSettings, LibraryMode},
+  opf::extract_metadata_from_opf,
+  settings::{ImportSettings, LibraryMode, TransferMode},
 };
-use anyhow::{format_err, Error};
+use anyhow::{format_err, Context, Error};
 use chrono::{Local, TimeZone};
 use filetime::{set_file_handle_times, FileTime};
 use fxhash::{FxBuildHasher, FxHashMap, FxHashSet};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use indexmap::IndexMap;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
+  cmp::Reverse,
   collections::BTreeSet,
-  fs::{self, File},
+  fmt,
+  fs::{self, File, OpenOptions},
+  io::{BufRead, BufReader, Write},
+  os::unix::fs::symlink,
   path::{Path, PathBuf},
   time::{Duration, SystemTime},
 };
 use walkdir::WalkDir;
 
 pub const METADATA_FILENAME: &str = ".metadata.json";
+pub const JOURNAL_FILENAME: &str = ".metadata.journal";
 pub const FAT32_EPOCH_FILENAME: &str = ".fat32-epoch";
 pub const READING_STATES_DIRNAME: &str = ".reading-states";
+pub const IMPORT_REPORT_PATH: &str = "ImportReport.txt";
+
+// The journal is merged into the database once it has more entries than this, or than the
+// database itself.
+const JOURNAL_MAX_ENTRIES: usize = 512;
+
+// A discrepancy between the library and the files on disk.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum IntegrityIssue {
+  // The file of an entry is gone.
+  Missing(u64, PathBuf),
+  // The file of an entry was modified after its import: it has a new fingerprint.
+  Modified(u64, PathBuf, u64),
+  // A reading state doesn't belong to any document.
+  OrphanedState(u64),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Repair {
+  RemoveEntry,
+  UpdateEntry,
+  RemoveState,
+}
+
+impl IntegrityIssue {
+  pub fn repairs(&self) -> &'static [Repair] {
+    match self {
+      IntegrityIssue::Missing(..) => &[Repair::RemoveEntry],
+      IntegrityIssue::Modified(..) => &[Repair::UpdateEntry, Repair::RemoveEntry],
+      IntegrityIssue::OrphanedState(..) => &[Repair::RemoveState],
+    }
+  }
+}
+
+impl fmt::Display for IntegrityIssue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      IntegrityIssue::Missing(fp, path) => {
+        write!(f, "Missing file for {:016X}: {}.", fp, path.display())
+      },
+      IntegrityIssue::Modified(fp, path, new_fp) => write!(
+        f,
+        "Modified file for {:016X}: {} ({:016X}).",
+        fp,
+        path.display(),
+        new_fp
+      ),
+      IntegrityIssue::OrphanedState(fp) => write!(f, "Orphaned reading state: {:016X}.", fp),
+    }
+  }
+}
+
+// Why a document wasn't imported.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SkipReason {
+  Excluded,
+  NotIncluded,
+  TooSmall(u64),
+  Extension,
+  // The destination of a transfer is taken.
+  Exists,
+  Failed(String),
+}
+
+impl fmt::Display for SkipReason {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SkipReason::Excluded => write!(f, "excluded by a rule"),
+      SkipReason::NotIncluded => write!(f, "not included by its directory's rule"),
+      SkipReason::TooSmall(size) => write!(f, "too small ({} bytes)", size),
+      SkipReason::Extension => write!(f, "extension not allowed"),
+      SkipReason::Exists => write!(f, "destination already exists"),
+      SkipReason::Failed(msg) => write!(f, "{}", msg),
+    }
+  }
+}
+
+// What an import did, or would do in a dry run. The paths are absolute.
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+  pub transferred: Vec<(PathBuf, PathBuf)>,
+  pub added: Vec<PathBuf>,
+  pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+impl ImportReport {
+  pub fn append(&mut self, other: ImportReport) {
+    self.transferred.extend(other.transferred);
+    self.added.extend(other.added);
+    self.skipped.extend(other.skipped);
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    fs::write(path.as_ref(), self.to_string())
+      .with_context(|| format!("can't write {}", path.as_ref().display()))
+  }
+}
+
+impl fmt::Display for ImportReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for (source, dest) in &self.transferred {
+      writeln!(f, "Transfer {} → {}.", source.display(), dest.display())?;
+    }
+    for path in &self.added {
+      writeln!(f, "Add {}.", path.display())?;
+    }
+    for (path, reason) in &self.skipped {
+      writeln!(f, "Skip {}: {}.", path.display(), reason)?;
+    }
+    Ok(())
+  }
+}
+
+struct CompiledRule {
+  directory: PathBuf,
+  include: Option<GlobSet>,
+  exclude: GlobSet,
+}
+
+// The import rules of the settings, ready to be matched.
+pub struct ImportRules {
+  rules: Vec<CompiledRule>,
+  min_file_size: u64,
+  allowed_extensions: FxHashSet<String>,
+}
+
+impl ImportRules {
+  // Invalid globs are reported and ignored.
+  pub fn new(home: &Path, settings: &ImportSettings) -> ImportRules {
+    let build = |globs: &[String]| {
+      let mut builder = GlobSetBuilder::new();
+      for glob in globs {
+        match Glob::new(glob) {
+          Ok(glob) => {
+            builder.add(glob);
+          },
+          Err(e) => eprintln!("Invalid import glob {}: {}.", glob, e),
+        }
+      }
+      builder.build().unwrap_or_else(|_| GlobSet::empty())
+    };
+    let mut rules: Vec<CompiledRule> = settings
+      .rules
+      .iter()
+      .map(|rule| CompiledRule {
+        directory: home.join(&rule.directory),
+        include: if rule.include.is_empty() {
+          None
+        } else {
+          Some(build(&rule.include))
+        },
+        exclude: build(&rule.exclude),
+      })
+      .collect();
+    // The most specific rule comes first.
+    rules.sort_by_key(|rule| Reverse(rule.directory.components().count()));
+    ImportRules {
+      rules,
+      min_file_size: settings.min_file_size,
+      allowed_extensions: settings
+        .allowed_extensions
+        .iter()
+        .map(|e| e.to_lowercase())
+        .collect(),
+    }
+  }
+
+  // Checks the document at *path*, which is absolute, of the given size. Image folders have
+  // no extension to check.
+  pub fn check(&self, path: &Path, size: u64, is_folder: bool) -> Option<SkipReason> {
+    if size < self.min_file_size {
+      return Some(SkipReason::TooSmall(size));
+    }
+    if !is_folder && !self.allowed_extensions.is_empty() {
+      let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+      if !self.allowed_extensions.contains(&extension) {
+        return Some(SkipReason::Extension);
+      }
+    }
+    let (rule, relat) = self
+      .rules
+      .iter()
+      .find_map(|rule| path.strip_prefix(&rule.directory).ok().map(|r| (rule, r)))?;
+    if rule.exclude.is_match(relat) {
+      return Some(SkipReason::Excluded);
+    }
+    if rule
+      .include
+      .as_ref()
+      .map_or(false, |set| !set.is_match(relat))
+    {
+      return Some(SkipReason::NotIncluded);
+    }
+    None
+  }
+}
+
+// The changes made to the database since it was last written. They are appended to the
+// journal, which is replayed when the database is loaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum JournalEntry {
+  Insert(u64, Info),
+  Remove(u64),
+}
 
 pub struct Library {
   pub home: PathBuf,
@@ -38,28 +263,38 @@ pub struct Library {
   pub paths: FxHashMap<PathBuf, u64>,
   pub reading_states: FxHashMap<u64, ReaderInfo>,
   pub modified_reading_states: FxHashSet<u64>,
+  // The entries of the database that were inserted, updated or removed since the last flush.
+  pub modified_entries: FxHashSet<u64>,
+  // The whole database needs to be written.
   pub has_db_changed: bool,
+  pub journal_len: usize,
   pub fat32_epoch: SystemTime,
   pub sort_method: SortMethod,
   pub reverse_order: bool,
   pub show_hidden: bool,
+  // List the hidden and archived books instead of the visible ones.
+  pub show_archived: bool,
+  // Only list the books that are lent.
+  pub show_loaned: bool,
+  // Only list the books of this collection.
+  pub collection: Option<String>,
 }
 
 impl Library {
   pub fn new<P: AsRef<Path>>(home: P, mode: LibraryMode) -> Self {
-    let mut db: IndexMap<u64, Info, FxBuildHasher> = if mode == LibraryMode::Database {
-      let path = home.as_ref().join(METADATA_FILENAME);
-      match load_json(&path) {
-        Err(e) => {
-          if path.exists() {
-            eprintln!("{}", e);
-          }
-          IndexMap::with_capacity_and_hasher(0, FxBuildHasher::default())
-        },
-        Ok(v) => v,
-      }
+    let (mut db, journal_len) = if mode == LibraryMode::Database {
+      load_db(home.as_ref()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        (
+          IndexMap::with_capacity_and_hasher(0, FxBuildHasher::default()),
+          0,
+        )
+      })
     } else {
-      IndexMap::with_capacity_and_hasher(0, FxBuildHasher::default())
+      (
+        IndexMap::with_capacity_and_hasher(0, FxBuildHasher::default()),
+        0,
+      )
     };
 
     let mut reading_states = FxHashMap::default();
@@ -119,11 +354,16 @@ impl Library {
       paths,
       reading_states,
       modified_reading_states: FxHashSet::default(),
+      modified_entries: FxHashSet::default(),
       has_db_changed: false,
+      journal_len,
       fat32_epoch,
       sort_method,
       reverse_order: sort_method.reverse_order(),
       show_hidden: false,
+      show_archived: false,
+      show_loaned: false,
+      collection: None,
     }
   }
 
@@ -153,7 +393,16 @@ impl Library {
             if let Some(child) = first {
               dirs.insert(prefix.as_ref().join(child.as_os_str()));
             }
-            if skip_files {
+            if skip_files
+              || !info
+                .visibility
+                .is_listed(query.is_some(), self.show_archived)
+              || (self.show_loaned && info.loan.is_none())
+              || self
+                .collection
+                .as_ref()
+                .map_or(false, |name| !info.collections.contains(name))
+            {
               continue;
             }
             if query.map_or(true, |q| {
@@ -175,23 +424,29 @@ impl Library {
 
         let max_depth = if query.is_some() { usize::MAX } else { 1 };
 
-        for entry in WalkDir::new(prefix.as_ref())
+        let mut iter = WalkDir::new(prefix.as_ref())
           .min_depth(1)
           .max_depth(max_depth)
           .into_iter()
-          .filter_entry(|e| self.show_hidden || !e.is_hidden())
-        {
+          .filter_entry(|e| self.show_hidden || !e.is_hidden());
+
+        while let Some(entry) = iter.next() {
           if entry.is_err() {
             continue;
           }
           let entry = entry.unwrap();
           let path = entry.path();
+          let folder_size = image_folder_size(path);
 
-          if path.is_dir() {
+          if path.is_dir() && folder_size.is_none() {
             if entry.depth() == 1 {
               dirs.insert(path.to_path_buf());
             }
           } else {
+            // The images of an image folder are its pages.
+            if folder_size.is_some() {
+              iter.skip_current_dir();
+            }
             let relat = path
               .strip_prefix(&self.home)
               .unwrap_or_else(|_| path.as_ref());
@@ -201,9 +456,13 @@ impl Library {
               continue;
             }
 
-            let kind = file_kind(&path).unwrap_or_default();
+            let kind = if folder_size.is_some() {
+              IMAGE_FOLDER_KIND.to_string()
+            } else {
+              file_kind(&path).unwrap_or_default()
+            };
             let md = entry.metadata().unwrap();
-            let size = md.len();
+            let size = folder_size.unwrap_or_else(|| md.len());
             let fp = md.fingerprint(self.fat32_epoch).unwrap();
             let file = FileInfo {
               path: relat.to_path_buf(),
@@ -231,29 +490,67 @@ impl Library {
     (files, dirs)
   }
 
-  pub fn import<P: AsRef<Path>>(&mut self, prefix: P, settings: &ImportSettings) {
+  pub fn import<P: AsRef<Path>>(&mut self, prefix: P, settings: &ImportSettings) -> ImportReport {
+    self.import_with_progress(prefix, settings, || ())
+  }
+
+  // Imports the documents of *prefix*, calling *progress* for each visited entry. The
+  // documents of the sources are first brought into the library.
+  pub fn import_with_progress<P, F>(
+    &mut self,
+    prefix: P,
+    settings: &ImportSettings,
+    mut progress: F,
+  ) -> ImportReport
+  where
+    P: AsRef<Path>,
+    F: FnMut(),
+  {
+    let mut report = ImportReport::default();
+
     if self.mode == LibraryMode::Filesystem {
-      return;
+      return report;
+    }
+
+    let rules = ImportRules::new(&self.home, settings);
+
+    for source in &settings.sources {
+      self.transfer_source(source, settings, &rules, &mut report, &mut progress);
     }
 
-    for entry in WalkDir::new(prefix.as_ref())
+    let mut iter = WalkDir::new(prefix.as_ref())
       .min_depth(1)
       .into_iter()
-      .filter_entry(|e| settings.traverse_hidden || !e.is_hidden())
-    {
+      .filter_entry(|e| settings.traverse_hidden || !e.is_hidden());
+
+    while let Some(entry) = iter.next() {
+      progress();
       if entry.is_err() {
         continue;
       }
 
       let entry = entry.unwrap();
       let path = entry.path();
+      let folder_size = image_folder_size(path);
+
+      if folder_size.is_some() {
+        iter.skip_current_dir();
+      }
       let relat = path.strip_prefix(&self.home).unwrap_or_else(|_| path);
-      let md = entry.metadata().unwrap();
+      // The linked documents are fingerprinted through their targets.
+      let md = if entry.path_is_symlink() {
+        match path.metadata() {
+          Ok(md) => md,
+          Err(_) => continue,
+        }
+      } else {
+        entry.metadata().unwrap()
+      };
       let fp = md.fingerprint(self.fat32_epoch).unwrap();
 
       // The fp is know: update the path if it changed.
       if self.db.contains_key(&fp) {
-        if relat != self.db[&fp].file.path {
+        if relat != self.db[&fp].file.path && !settings.dry_run {
           println!(
             "Update path for {:016X}: {} → {}.",
             fp,
@@ -263,10 +560,13 @@ impl Library {
           self.paths.remove(&self.db[&fp].file.path);
           self.paths.insert(relat.to_path_buf(), fp);
           self.db[&fp].file.path = relat.to_path_buf();
-          self.has_db_changed = true;
+          self.modified_entries.insert(fp);
         }
       // The path is known: update the fp.
       } else if let Some(fp2) = self.paths.get(relat) {
+        if settings.dry_run {
+          continue;
+        }
         println!(
           "Update fingerprint for {}: {:016X} → {:016X}.",
           relat.display(),
@@ -275,11 +575,12 @@ impl Library {
         );
         let info = self.db.remove(fp2).unwrap();
         self.db.insert(fp, info);
-        self.db[&fp].file.size = md.len();
+        self.db[&fp].file.size = folder_size.unwrap_or_else(|| md.len());
         let rp1 = self.reading_state_path(*fp2);
         let rp2 = self.reading_state_path(fp);
         fs::rename(rp1, rp2).ok();
-        self.has_db_changed = true;
+        self.modified_entries.insert(*fp2);
+        self.modified_entries.insert(fp);
       } else {
         let fp1 = self
           .fat32_epoch
@@ -305,6 +606,9 @@ impl Library {
         // drift by one second, when the file is created within an operating system
         // and moved within another.
         if let Some(nfp) = nfp {
+          if settings.dry_run {
+            continue;
+          }
           println!(
             "Update fingerprint for {}: {:016X} → {:016X}.",
             self.db[&nfp].file.path.display(),
@@ -316,6 +620,7 @@ impl Library {
           let rp1 = self.reading_state_path(nfp);
           let rp2 = self.reading_state_path(fp);
           fs::rename(rp1, rp2).ok();
+          self.modified_entries.insert(nfp);
           if relat != self.db[&fp].file.path {
             println!(
               "Update path for {:016X}: {} → {}.",
@@ -329,12 +634,24 @@ impl Library {
           }
         // We found a new file: add it to the db.
         } else {
-          let kind = file_kind(&path).unwrap_or_default();
+          let kind = if folder_size.is_some() {
+            IMAGE_FOLDER_KIND.to_string()
+          } else {
+            file_kind(&path).unwrap_or_default()
+          };
           if !settings.allowed_kinds.contains(&kind) {
             continue;
           }
+          let size = folder_size.unwrap_or_else(|| md.len());
+          if let Some(reason) = rules.check(path, size, folder_size.is_some()) {
+            report.skipped.push((path.to_path_buf(), reason));
+            continue;
+          }
+          report.added.push(path.to_path_buf());
+          if settings.dry_run {
+            continue;
+          }
           println!("Add new entry: {:016X}, {}.", fp, relat.display());
-          let size = md.len();
           let file = FileInfo {
             path: relat.to_path_buf(),
             kind,
@@ -344,17 +661,159 @@ impl Library {
             file,
             ..Default::default()
           };
+          if settings.extract_opf_metadata {
+            extract_metadata_from_opf(&self.home, &mut info);
+          }
           if settings.extract_epub_metadata {
             extract_metadata_from_epub(prefix.as_ref(), &mut info);
           }
           self.db.insert(fp, info);
         }
 
-        self.has_db_changed = true;
+        self.modified_entries.insert(fp);
+      }
+    }
+
+    report
+  }
+
+  // Copies, moves or links the documents of *source* to the same relative paths within the
+  // home directory.
+  fn transfer_source<F: FnMut()>(
+    &self,
+    source: &Path,
+    settings: &ImportSettings,
+    rules: &ImportRules,
+    report: &mut ImportReport,
+    progress: &mut F,
+  ) {
+    let mut iter = WalkDir::new(source)
+      .min_depth(1)
+      .into_iter()
+      .filter_entry(|e| settings.traverse_hidden || !e.is_hidden());
+
+    while let Some(entry) = iter.next() {
+      progress();
+      let entry = match entry {
+        Ok(entry) => entry,
+        Err(_) => continue,
+      };
+      let path = entry.path();
+      let folder_size = image_folder_size(path);
+      if folder_size.is_some() {
+        iter.skip_current_dir();
+      } else if entry.file_type().is_dir() {
+        continue;
+      }
+      let kind = if folder_size.is_some() {
+        IMAGE_FOLDER_KIND.to_string()
+      } else {
+        file_kind(&path).unwrap_or_default()
+      };
+      if !settings.allowed_kinds.contains(&kind) {
+        continue;
       }
+      let size = folder_size.unwrap_or_else(|| entry.metadata().map_or(0, |md| md.len()));
+      if let Some(reason) = rules.check(path, size, folder_size.is_some()) {
+        report.skipped.push((path.to_path_buf(), reason));
+        continue;
+      }
+      let dest = self.home.join(path.strip_prefix(source).unwrap_or(path));
+      if dest.exists() {
+        report
+          .skipped
+          .push((path.to_path_buf(), SkipReason::Exists));
+        continue;
+      }
+      if !settings.dry_run {
+        if let Err(e) = transfer(path, &dest, settings.transfer) {
+          report
+            .skipped
+            .push((path.to_path_buf(), SkipReason::Failed(format!("{:#}", e))));
+          continue;
+        }
+        println!("Transfer {} → {}.", path.display(), dest.display());
+      }
+      report.transferred.push((path.to_path_buf(), dest));
     }
   }
 
+  // Returns the information of the document at *path*, which is either relative to the home
+  // directory or absolute. Documents outside of the library get a minimal entry.
+  pub fn document_info<P: AsRef<Path>>(&self, path: P) -> Option<Info> {
+    let full_path = self.home.join(path.as_ref());
+    let md = full_path.metadata().ok().filter(|md| md.is_file())?;
+    let relat = full_path
+      .strip_prefix(&self.home)
+      .unwrap_or_else(|_| full_path.as_ref());
+    if let Some(info) = self.paths.get(relat).and_then(|fp| self.db.get(fp)) {
+      return Some(info.clone());
+    }
+    let fp = md.fingerprint(self.fat32_epoch).ok()?;
+    Some(Info {
+      file: FileInfo {
+        path: relat.to_path_buf(),
+        kind: file_kind(&full_path)?,
+        size: md.len(),
+      },
+      reader: self.reading_states.get(&fp).cloned(),
+      ..Default::default()
+    })
+  }
+
+  // Returns, most recent first, the last *count* books that were opened. The reading states
+  // of the filesystem mode don't know the paths of their books, hence only the database mode
+  // has recent books.
+  pub fn recent(&self, count: usize) -> Vec<Info> {
+    let mut books: Vec<&Info> = self
+      .db
+      .values()
+      .filter(|info| info.reader.is_some())
+      .collect();
+    books.sort_by(|a, b| sort_opened(b, a));
+    books.into_iter().take(count).cloned().collect()
+  }
+
+  // Adds a file that was saved within the library by other means than an import, such as a
+  // download, with the metadata an import would extract. Returns whether it was added.
+  pub fn import_file<P: AsRef<Path>>(&mut self, path: P, settings: &ImportSettings) -> bool {
+    let path = path.as_ref();
+    let relat = match path.strip_prefix(&self.home) {
+      Ok(relat) => relat.to_path_buf(),
+      Err(_) => return false,
+    };
+    if self.mode == LibraryMode::Filesystem || self.paths.contains_key(&relat) {
+      return false;
+    }
+    let (md, kind) = match (path.metadata(), file_kind(path)) {
+      (Ok(md), Some(kind)) => (md, kind),
+      _ => return false,
+    };
+    let fp = match md.fingerprint(self.fat32_epoch) {
+      Ok(fp) => fp,
+      Err(_) => return false,
+    };
+    let mut info = Info {
+      file: FileInfo {
+        path: relat,
+        kind,
+        size: md.len(),
+      },
+      ..Default::default()
+    };
+    if settings.extract_opf_metadata {
+      extract_metadata_from_opf(&self.home, &mut info);
+    }
+    if settings.extract_epub_metadata {
+      extract_metadata_from_epub(&self.home, &mut info);
+    }
+    println!("Add new entry: {:016X}, {}.", fp, info.file.path.display());
+    self.paths.insert(info.file.path.clone(), fp);
+    self.db.insert(fp, info);
+    self.modified_entries.insert(fp);
+    true
+  }
+
   pub fn add_document(&mut self, info: Info) {
     if self.mode == LibraryMode::Filesystem {
       return;
@@ -366,7 +825,7 @@ impl Library {
 
     self.paths.insert(info.file.path.clone(), fp);
     self.db.insert(fp, info);
-    self.has_db_changed = true;
+    self.modified_entries.insert(fp);
   }
 
   pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
@@ -385,7 +844,11 @@ impl Library {
       .ok_or_else(|| format_err!("Can't get fingerprint of {}.", path.as_ref().display()))?;
 
     if full_path.exists() {
-      fs::remove_file(&full_path)?;
+      if image_folder_size(&full_path).is_some() {
+        fs::remove_dir_all(&full_path)?;
+      } else {
+        fs::remove_file(&full_path)?;
+      }
       if let Some(parent) = full_path.parent() {
         if parent != self.home {
           fs::remove_dir(parent).ok();
@@ -401,7 +864,7 @@ impl Library {
     if self.mode == LibraryMode::Database {
       self.paths.remove(path.as_ref());
       if self.db.shift_remove(&fp).is_some() {
-        self.has_db_changed = true;
+        self.modified_entries.insert(fp);
       }
     } else {
       self.reading_states.remove(&fp);
@@ -412,7 +875,12 @@ impl Library {
     Ok(())
   }
 
-  pub fn move_to<P: AsRef<Path>>(&mut self, path: P, other: &mut Library) -> Result<(), Error> {
+  // Returns the path of the document within *other*: the name gets a prefix when it's taken.
+  pub fn move_to<P: AsRef<Path>>(
+    &mut self,
+    path: P,
+    other: &mut Library,
+  ) -> Result<PathBuf, Error> {
     if !self.home.join(path.as_ref()).exists() {
       return Err(format_err!(
         "Can't move non-existing file {}.",
@@ -465,8 +933,8 @@ impl Library {
         other.db.insert(fp, info);
         self.paths.remove(path.as_ref());
         other.paths.insert(dest_path.to_path_buf(), fp);
-        self.has_db_changed = true;
-        other.has_db_changed = true;
+        self.modified_entries.insert(fp);
+        other.modified_entries.insert(fp);
       }
     } else {
       if let Some(reader_info) = self.reading_states.remove(&fp) {
@@ -478,19 +946,20 @@ impl Library {
       other.modified_reading_states.insert(fp);
     }
 
-    Ok(())
+    Ok(dest.strip_prefix(&other.home)?.to_path_buf())
   }
 
   pub fn clean_up(&mut self) {
     if self.mode == LibraryMode::Database {
       let home = &self.home;
-      let len = self.db.len();
+      let modified_entries = &mut self.modified_entries;
       self.db.retain(|fp, info| {
         let path = home.join(&info.file.path);
         if path.exists() {
           true
         } else {
           println!("Remove entry: {:016X}, {}.", fp, info.file.path.display());
+          modified_entries.insert(*fp);
           false
         }
       });
@@ -500,10 +969,6 @@ impl Library {
         .modified_reading_states
         .retain(|fp| db.contains_key(fp));
 
-      if self.db.len() != len {
-        self.has_db_changed = true;
-      }
-
       let path = home.join(READING_STATES_DIRNAME);
       for entry in fs::read_dir(&path).unwrap() {
         if entry.is_err() {
@@ -527,7 +992,7 @@ impl Library {
         .into_iter()
         .filter_map(|entry| entry.ok())
         .filter_map(|entry| {
-          if entry.file_type().is_dir() {
+          if entry.file_type().is_dir() && image_folder_size(entry.path()).is_none() {
             None
           } else {
             Some(
@@ -563,6 +1028,133 @@ impl Library {
     }
   }
 
+  // Lists the entries whose files are missing or were modified, and the orphaned reading
+  // states, without changing anything.
+  pub fn check_integrity(&self) -> Vec<IntegrityIssue> {
+    let mut issues = Vec::new();
+    let mut fps = FxHashSet::default();
+
+    if self.mode == LibraryMode::Database {
+      for (fp, info) in &self.db {
+        let path = self.home.join(&info.file.path);
+        match path.metadata() {
+          Ok(md) => {
+            let new_fp = md.fingerprint(self.fat32_epoch).unwrap_or(*fp);
+            // On a FAT32 file system, the modification time has a two-second precision.
+            let drifted = [
+              self.fat32_epoch.checked_sub(Duration::from_secs(1)),
+              self.fat32_epoch.checked_add(Duration::from_secs(1)),
+            ]
+            .iter()
+            .flatten()
+            .any(|epoch| md.fingerprint(*epoch).ok() == Some(*fp));
+            if new_fp != *fp && !drifted {
+              issues.push(IntegrityIssue::Modified(
+                *fp,
+                info.file.path.clone(),
+                new_fp,
+              ));
+            }
+          },
+          Err(_) => issues.push(IntegrityIssue::Missing(*fp, info.file.path.clone())),
+        }
+      }
+    } else {
+      fps = WalkDir::new(&self.home)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_type().is_dir() || image_folder_size(entry.path()).is_some())
+        .filter_map(|entry| {
+          entry
+            .metadata()
+            .ok()
+            .and_then(|md| md.fingerprint(self.fat32_epoch).ok())
+        })
+        .collect();
+    }
+
+    if let Ok(entries) = fs::read_dir(self.home.join(READING_STATES_DIRNAME)) {
+      for entry in entries.filter_map(|entry| entry.ok()) {
+        if let Some(fp) = entry
+          .path()
+          .file_stem()
+          .and_then(|v| v.to_str())
+          .and_then(|v| u64::from_str_radix(v, 16).ok())
+        {
+          let known = if self.mode == LibraryMode::Database {
+            self.db.contains_key(&fp)
+          } else {
+            fps.contains(&fp)
+          };
+          if !known {
+            issues.push(IntegrityIssue::OrphanedState(fp));
+          }
+        }
+      }
+    }
+
+    issues
+  }
+
+  pub fn repair(&mut self, issue: &IntegrityIssue, repair: Repair) -> Result<(), Error> {
+    match (issue, repair) {
+      (IntegrityIssue::Missing(fp, path), Repair::RemoveEntry)
+      | (IntegrityIssue::Modified(fp, path, _), Repair::RemoveEntry) => {
+        println!("Remove entry: {:016X}, {}.", fp, path.display());
+        self.paths.remove(path);
+        if self.db.shift_remove(fp).is_some() {
+          self.modified_entries.insert(*fp);
+        }
+        self.modified_reading_states.remove(fp);
+        let rsp = self.reading_state_path(*fp);
+        if rsp.exists() {
+          fs::remove_file(rsp)?;
+        }
+      },
+      (IntegrityIssue::Modified(fp, path, new_fp), Repair::UpdateEntry) => {
+        if self.db.contains_key(new_fp) {
+          return Err(format_err!(
+            "the fingerprint {:016X} is already taken",
+            new_fp
+          ));
+        }
+        let mut info = self
+          .db
+          .shift_remove(fp)
+          .ok_or_else(|| format_err!("unknown fingerprint {:016X}", fp))?;
+        println!(
+          "Update fingerprint for {}: {:016X} → {:016X}.",
+          path.display(),
+          fp,
+          new_fp
+        );
+        let full_path = self.home.join(path);
+        info.file.size = image_folder_size(&full_path)
+          .or_else(|| full_path.metadata().ok().map(|md| md.len()))
+          .unwrap_or(info.file.size);
+        self.db.insert(*new_fp, info);
+        self.paths.insert(path.clone(), *new_fp);
+        let rp1 = self.reading_state_path(*fp);
+        let rp2 = self.reading_state_path(*new_fp);
+        fs::rename(rp1, rp2).ok();
+        if self.modified_reading_states.remove(fp) {
+          self.modified_reading_states.insert(*new_fp);
+        }
+        self.modified_entries.insert(*fp);
+        self.modified_entries.insert(*new_fp);
+      },
+      (IntegrityIssue::OrphanedState(fp), Repair::RemoveState) => {
+        println!("Remove reading state for {:016X}.", fp);
+        self.reading_states.remove(fp);
+        self.modified_reading_states.remove(fp);
+        fs::remove_file(self.reading_state_path(*fp))?;
+      },
+      _ => return Err(format_err!("can't apply {:?} to {}", repair, issue)),
+    }
+    Ok(())
+  }
+
   pub fn sort(&mut self, sort_method: SortMethod, reverse_order: bool) {
     self.sort_method = sort_method;
     self.reverse_order = reverse_order;
@@ -595,6 +1187,23 @@ impl Library {
     self.has_db_changed = true;
   }
 
+  // Applies *f* to every document, and saves the reading states of the documents for which
+  // *f* returns `true`.
+  pub fn update_reading_states<F>(&mut self, mut f: F)
+  where
+    F: FnMut(&Path, &mut Info) -> bool,
+  {
+    if self.mode == LibraryMode::Filesystem {
+      return;
+    }
+
+    for (fp, info) in &mut self.db {
+      if f(&self.home, info) {
+        self.modified_reading_states.insert(*fp);
+      }
+    }
+  }
+
   pub fn sync_reader_info<P: AsRef<Path>>(&mut self, path: P, reader: &ReaderInfo) {
     let fp = self.paths.get(path.as_ref()).cloned().unwrap_or_else(|| {
       self
@@ -664,19 +1273,64 @@ impl Library {
     }
   }
 
+  pub fn set_visibility<P: AsRef<Path>>(&mut self, path: P, visibility: Visibility) {
+    if self.mode == LibraryMode::Filesystem {
+      return;
+    }
+
+    if let Some(fp) = self.paths.get(path.as_ref()).cloned() {
+      if let Some(info) = self.db.get_mut(&fp) {
+        info.visibility = visibility;
+        self.modified_entries.insert(fp);
+      }
+    }
+  }
+
+  pub fn set_loan<P: AsRef<Path>>(&mut self, path: P, loan: Option<Loan>) {
+    if self.mode == LibraryMode::Filesystem {
+      return;
+    }
+
+    if let Some(fp) = self.paths.get(path.as_ref()).cloned() {
+      if let Some(info) = self.db.get_mut(&fp) {
+        info.loan = loan;
+        self.modified_entries.insert(fp);
+      }
+    }
+  }
+
+  pub fn set_collection<P: AsRef<Path>>(&mut self, path: P, name: &str, member: bool) {
+    if self.mode == LibraryMode::Filesystem {
+      return;
+    }
+
+    if let Some(fp) = self.paths.get(path.as_ref()).cloned() {
+      if let Some(info) = self.db.get_mut(&fp) {
+        if member {
+          info.collections.insert(name.to_string());
+        } else {
+          info.collections.remove(name);
+        }
+        self.modified_entries.insert(fp);
+      }
+    }
+  }
+
+  pub fn collections(&self) -> Vec<Collection> {
+    collections(self.db.values())
+  }
+
   pub fn reload(&mut self) {
     if self.mode == LibraryMode::Database {
-      let path = self.home.join(METADATA_FILENAME);
-
-      match load_json(&path) {
+      match load_db(&self.home) {
         Err(e) => {
-          if path.exists() {
-            eprintln!("Can't load {}: {}", path.display(), e);
-          }
+          eprintln!("{}", e);
           return;
         },
-        Ok(v) => {
-          self.db = v;
+        Ok((db, journal_len)) => {
+          self.db = db;
+          self.journal_len = journal_len;
+          self.modified_entries.clear();
           self.has_db_changed = false;
         },
       }
@@ -720,7 +1374,11 @@ impl Library {
     }
   }
 
-  pub fn flush(&mut self) {
+  // What can't be saved, e.g. when the storage is full, will be saved during the next flush.
+  pub fn flush(&mut self) -> Result<(), Error> {
+    let mut failed = FxHashSet::default();
+    let mut error = None;
+
     for fp in &self.modified_reading_states {
       let reader_info = if self.mode == LibraryMode::Database {
         self.db.get(fp).and_then(|info| info.reader.as_ref())
@@ -728,20 +1386,71 @@ impl Library {
         self.reading_states.get(fp)
       };
       if let Some(reader_info) = reader_info {
-        save_json(reader_info, self.reading_state_path(*fp))
-          .map_err(|e| eprintln!("{}", e))
-          .ok();
+        if let Err(e) = save_json_atomically(reader_info, self.reading_state_path(*fp)) {
+          failed.insert(*fp);
+          error.get_or_insert(e);
+        }
       }
     }
 
-    self.modified_reading_states.clear();
+    self.modified_reading_states = failed;
 
-    if self.has_db_changed {
-      save_json(&self.db, self.home.join(METADATA_FILENAME))
-        .map_err(|e| eprintln!("{}", e))
-        .ok();
-      self.has_db_changed = false;
+    let result = if self.has_db_changed
+      || self.journal_len + self.modified_entries.len() > JOURNAL_MAX_ENTRIES.max(self.db.len())
+    {
+      self.compact()
+    } else if !self.modified_entries.is_empty() {
+      self
+        .append_to_journal()
+        .context("can't append to the journal")
+    } else {
+      Ok(())
+    };
+
+    match error {
+      Some(e) => Err(e.context("can't save the reading states")),
+      None => result,
+    }
+  }
+
+  // Writes the whole database and empties the journal.
+  pub fn compact(&mut self) -> Result<(), Error> {
+    if self.mode == LibraryMode::Filesystem {
+      return Ok(());
+    }
+
+    if !self.has_db_changed && self.journal_len == 0 && self.modified_entries.is_empty() {
+      return Ok(());
     }
+
+    save_json_atomically(&self.db, self.home.join(METADATA_FILENAME))
+      .context("can't save the database")?;
+    fs::remove_file(self.home.join(JOURNAL_FILENAME)).ok();
+    self.journal_len = 0;
+    self.modified_entries.clear();
+    self.has_db_changed = false;
+    Ok(())
+  }
+
+  fn append_to_journal(&mut self) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    for fp in &self.modified_entries {
+      let entry = match self.db.get(fp) {
+        Some(info) => JournalEntry::Insert(*fp, info.clone()),
+        None => JournalEntry::Remove(*fp),
+      };
+      serde_json::to_writer(&mut buf, &entry)?;
+      buf.push(b'\n');
+    }
+    let mut file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(self.home.join(JOURNAL_FILENAME))?;
+    file.write_all(&buf)?;
+    file.sync_data()?;
+    self.journal_len += self.modified_entries.len();
+    self.modified_entries.clear();
+    Ok(())
   }
 
   fn reading_state_path(&self, fp: u64) -> PathBuf {
@@ -751,3 +1460,120 @@ impl Library {
       .join(format!("{:016X}.json", fp))
   }
 }
+
+// Loads the database and replays its journal. An interrupted write can only affect the last
+// line of the journal, which is then ignored.
+fn load_db(home: &Path) -> Result<(IndexMap<u64, Info, FxBuildHasher>, usize), Error> {
+  let path = home.join(METADATA_FILENAME);
+  let mut db = if path.exists() {
+    load_json(&path)?
+  } else {
+    IndexMap::with_capacity_and_hasher(0, FxBuildHasher::default())
+  };
+
+  let path = home.join(JOURNAL_FILENAME);
+  let mut journal_len = 0;
+  if let Ok(file) = File::open(&path) {
+    for line in BufReader::new(file).lines() {
+      let line = line?;
+      match serde_json::from_str(&line) {
+        Ok(JournalEntry::Insert(fp, info)) => {
+          db.insert(fp, info);
+        },
+        Ok(JournalEntry::Remove(fp)) => {
+          db.shift_remove(&fp);
+        },
+        Err(e) => {
+          eprintln!("Can't parse journal entry {}: {}.", journal_len + 1, e);
+          break;
+        },
+      }
+      journal_len += 1;
+    }
+  }
+
+  Ok((db, journal_len))
+}
+
+// Moving falls back to copying when the source sits on another file system.
+fn transfer(source: &Path, dest: &Path, mode: TransferMode) -> Result<(), Error> {
+  if let Some(parent) = dest.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  match mode {
+    TransferMode::Link => symlink(source, dest)?,
+    TransferMode::Move => {
+      if fs::rename(source, dest).is_err() {
+        copy_document(source, dest)?;
+        if source.is_dir() {
+          fs::remove_dir_all(source)?;
+        } else {
+          fs::remove_file(source)?;
+        }
+      }
+    },
+    TransferMode::Copy => copy_document(source, dest)?,
+  }
+  Ok(())
+}
+
+// The image folders are flat.
+fn copy_document(source: &Path, dest: &Path) -> Result<(), Error> {
+  if source.is_dir() {
+    fs::create_dir(dest)?;
+    for entry in fs::read_dir(source)? {
+      let entry = entry?;
+      fs::copy(entry.path(), dest.join(entry.file_name()))?;
+    }
+  } else {
+    fs::copy(source, dest)?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ImportRules, SkipReason};
+  use crate::settings::{ImportRule, ImportSettings};
+  use std::path::Path;
+
+  #[test]
+  fn test_import_rules() {
+    let settings = ImportSettings {
+      min_file_size: 100,
+      allowed_extensions: ["pdf", "epub"].iter().map(|e| e.to_string()).collect(),
+      rules: vec![
+        ImportRule {
+          directory: "Papers".into(),
+          include: vec!["**/*.pdf".to_string()],
+          exclude: vec!["drafts/**".to_string()],
+        },
+        ImportRule {
+          directory: "Papers/Old".into(),
+          include: Vec::new(),
+          exclude: vec!["*".to_string()],
+        },
+      ],
+      ..Default::default()
+    };
+    let rules = ImportRules::new(Path::new("/books"), &settings);
+    let check = |path: &str, size| rules.check(Path::new(path), size, false);
+    assert_eq!(check("/books/a.epub", 200), None);
+    assert_eq!(check("/books/a.epub", 50), Some(SkipReason::TooSmall(50)));
+    assert_eq!(check("/books/a.txt", 200), Some(SkipReason::Extension));
+    assert_eq!(check("/books/Papers/x/a.pdf", 200), None);
+    assert_eq!(
+      check("/books/Papers/a.epub", 200),
+      Some(SkipReason::NotIncluded)
+    );
+    assert_eq!(
+      check("/books/Papers/drafts/a.pdf", 200),
+      Some(SkipReason::Excluded)
+    );
+    assert_eq!(
+      check("/books/Papers/Old/a.pdf", 200),
+      Some(SkipReason::Excluded)
+    );
+    assert_eq!(rules.check(Path::new("/books/Comic"), 200, true), None);
+  }
+}
@@ -1,23 +1,46 @@
 #[macro_use]
 mod geom;
+#[macro_use]
+mod i18n;
 mod app;
 mod battery;
+mod citation;
+#[cfg(feature = "cloud-sync")]
+mod cloud_sync;
 mod color;
+mod conversion;
 mod device;
 mod dictionary;
 mod document;
+#[cfg(feature = "feeds")]
+mod feed;
+mod feedback;
 mod font;
 mod framebuffer;
 mod frontlight;
 mod gesture;
 mod helpers;
 mod input;
+mod insights;
 mod library;
 mod lightsensor;
 mod metadata;
+mod network;
+#[cfg(feature = "opds")]
+mod opds;
+mod opf;
+mod phrasebook;
+mod qrcode;
+mod recorder;
+mod resources;
+mod resume;
 mod rtc;
+mod scheduler;
+mod search_history;
 mod settings;
+mod share;
 mod symbolic_path;
+mod tts;
 mod unit;
 mod view;
 
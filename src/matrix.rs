@@ -0,0 +1,166 @@
+//! Renders the main screens of the application for every supported device, to catch the layout
+//! issues, such as views overflowing the screen, that only happen at some resolutions.
+
+#[cfg(feature = "feeds")]
+use crate::view::feeds::Feeds;
+use crate::{
+  app::Context,
+  device::{CURRENT_DEVICE, PRODUCTS},
+  document::sys_info_as_html,
+  font::Fonts,
+  framebuffer::{Framebuffer, Pixmap},
+  geom::Rectangle,
+  unit::scale_by_dpi,
+  view::{
+    command_palette::CommandPalette,
+    common::main_menu_entries,
+    dictionary::Dictionary,
+    home::Home,
+    menu::{Menu, MenuKind},
+    reader::Reader,
+    sketch::Sketch,
+    RenderQueue,
+    View,
+    ViewId,
+    SMALL_BAR_HEIGHT,
+  },
+};
+use anyhow::{format_err, Error};
+use std::{env, fs, path::Path, process::Command, sync::mpsc};
+
+pub const MATRIX_ARG: &str = "--matrix";
+// Renders the screens of the current device, used by the child processes.
+pub const MATRIX_DEVICE_ARG: &str = "--matrix-device";
+
+// The device is read once, from the environment, hence each device is rendered by its own
+// process.
+pub fn run_matrix(dir: &Path) -> Result<(), Error> {
+  fs::create_dir_all(dir)?;
+  let exe = env::current_exe()?;
+  let mut failures = Vec::new();
+
+  for (product, model_number) in PRODUCTS.iter() {
+    let status = Command::new(&exe)
+      .arg(MATRIX_DEVICE_ARG)
+      .arg(dir)
+      .env("PRODUCT", product)
+      .env("MODEL_NUMBER", model_number)
+      .status()?;
+    if !status.success() {
+      failures.push(format!("{}:{}", product, model_number));
+    }
+  }
+
+  if failures.is_empty() {
+    println!("All the devices passed.");
+    Ok(())
+  } else {
+    Err(format_err!("Layout issues on: {}.", failures.join(", ")))
+  }
+}
+
+// Renders the screens of the current device into *dir* and returns the number of issues found.
+pub fn render_screens(dir: &Path, context: &mut Context) -> Result<usize, Error> {
+  let (tx, _rx) = mpsc::channel();
+  let mut rq = RenderQueue::new();
+  let rect = context.fb.rect();
+  let dpi = CURRENT_DEVICE.dpi;
+  let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+  let prefix = format!(
+    "{}-{}",
+    env::var("PRODUCT").unwrap_or_default(),
+    env::var("MODEL_NUMBER").unwrap_or_default()
+  );
+
+  let mut screens: Vec<(&str, Box<dyn View>)> = Vec::new();
+
+  let mut home = Home::new(rect, &mut rq, context)?;
+  screens.push((
+    "palette",
+    Box::new(CommandPalette::new(
+      rect,
+      home.commands(context),
+      &mut rq,
+      context,
+    )),
+  ));
+  let entries = main_menu_entries(context);
+  let menu = Menu::new(
+    rect![
+      rect.max.x - small_height,
+      rect.min.y,
+      rect.max.x,
+      rect.min.y + small_height
+    ],
+    ViewId::MainMenu,
+    MenuKind::DropDown,
+    entries,
+    context,
+  );
+  home.children_mut().push(Box::new(menu) as Box<dyn View>);
+  screens.push(("home", Box::new(home)));
+
+  let html = sys_info_as_html(&context.library.home);
+  screens.push((
+    "reader",
+    Box::new(Reader::from_html(rect, &html, &tx, context)),
+  ));
+  screens.push((
+    "dictionary",
+    Box::new(Dictionary::new(rect, "", "", &tx, &mut rq, context)),
+  ));
+  screens.push((
+    "sketch",
+    Box::new(Sketch::new(rect, None, &mut rq, context)),
+  ));
+  #[cfg(feature = "feeds")]
+  screens.push(("feeds", Box::new(Feeds::new(rect, &tx, &mut rq, context))));
+
+  let mut issues = 0;
+  for (name, view) in &screens {
+    let mut pixmap = Pixmap::new(rect.width(), rect.height());
+    render_and_check(
+      view.as_ref(),
+      &rect,
+      name,
+      &mut pixmap,
+      &mut context.fonts,
+      &mut issues,
+    );
+    let path = dir.join(format!("{}-{}.png", prefix, name));
+    pixmap.save(&path.to_string_lossy())?;
+  }
+
+  Ok(issues)
+}
+
+fn render_and_check(
+  view: &dyn View,
+  screen: &Rectangle,
+  name: &str,
+  fb: &mut dyn Framebuffer,
+  fonts: &mut Fonts,
+  issues: &mut usize,
+) {
+  let rect = *view.rect();
+  if rect.min.x > rect.max.x || rect.min.y > rect.max.y || !screen.contains(&rect) {
+    eprintln!(
+      "{}: {} overflows the screen: {:?}.",
+      name,
+      view_name(view),
+      rect
+    );
+    *issues += 1;
+  }
+  view.render(fb, rect, fonts);
+  for child in view.children() {
+    render_and_check(child.as_ref(), screen, name, fb, fonts, issues);
+  }
+}
+
+fn view_name(view: &dyn View) -> String {
+  view
+    .view_id()
+    .map(|id| format!("{:?}", id))
+    .unwrap_or_else(|| format!("view {}", view.id()))
+}
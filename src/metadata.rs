@@ -1,6 +1,16 @@
 use crate::{
-  document::{asciify, epub::EpubDocument, Document, SimpleTocEntry, TextLocation},
+  document::{
+    asciify,
+    epub::EpubDocument,
+    images::IMAGE_FOLDER_KIND,
+    Document,
+    SimpleTocEntry,
+    TextLocation,
+  },
+  framebuffer::ScanCleanup,
+  geom::Dir,
   helpers::datetime_format,
+  settings::RotationLock,
 };
 use chrono::{DateTime, Local};
 use fxhash::FxHashMap;
@@ -49,6 +59,9 @@ pub struct Info {
   pub identifier: String,
   #[serde(skip_serializing_if = "BTreeSet::is_empty")]
   pub categories: BTreeSet<String>,
+  // The names of the collections the book was added to.
+  #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+  pub collections: BTreeSet<String>,
   pub file: FileInfo,
   #[serde(skip)]
   pub reader: Option<ReaderInfo>,
@@ -56,6 +69,89 @@ pub struct Info {
   pub toc: Option<Vec<SimpleTocEntry>>,
   #[serde(with = "datetime_format")]
   pub added: DateTime<Local>,
+  #[serde(skip_serializing_if = "Visibility::is_visible")]
+  pub visibility: Visibility,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub loan: Option<Loan>,
+}
+
+// A named set of books, chosen by the user.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Collection {
+  pub name: String,
+  // The number of books in the collection.
+  pub count: usize,
+}
+
+// The collections of the given books, sorted by name.
+pub fn collections<'a, I>(infos: I) -> Vec<Collection>
+where
+  I: IntoIterator<Item = &'a Info>,
+{
+  let mut counts = BTreeMap::new();
+  for info in infos {
+    for name in &info.collections {
+      *counts.entry(name.as_str()).or_insert(0) += 1;
+    }
+  }
+  counts
+    .into_iter()
+    .map(|(name, count)| Collection {
+      name: name.to_string(),
+      count,
+    })
+    .collect()
+}
+
+// A book lent to someone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Loan {
+  pub borrower: String,
+  #[serde(with = "datetime_format")]
+  pub lent: DateTime<Local>,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub note: String,
+}
+
+impl Default for Loan {
+  fn default() -> Self {
+    Loan {
+      borrower: String::new(),
+      lent: Local::now(),
+      note: String::new(),
+    }
+  }
+}
+
+// Hidden books only appear in the search results, archived books only appear in the archive.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Visibility {
+  Visible,
+  Hidden,
+  Archived,
+}
+
+impl Visibility {
+  pub fn is_visible(&self) -> bool {
+    *self == Visibility::Visible
+  }
+
+  // Returns whether a book with this visibility is listed in the given view.
+  pub fn is_listed(self, searching: bool, archive: bool) -> bool {
+    match self {
+      Visibility::Visible => !archive,
+      Visibility::Hidden => archive || searching,
+      Visibility::Archived => archive,
+    }
+  }
+}
+
+impl Default for Visibility {
+  fn default() -> Self {
+    Visibility::Visible
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,6 +195,82 @@ impl Default for Annotation {
   }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AnnotationsFormat {
+  Markdown,
+  Json,
+}
+
+impl AnnotationsFormat {
+  pub fn label(self) -> &'static str {
+    match self {
+      AnnotationsFormat::Markdown => "Markdown",
+      AnnotationsFormat::Json => "JSON",
+    }
+  }
+
+  // The suffix of the exported file, which keeps it apart from the exported text.
+  pub fn suffix(self) -> &'static str {
+    match self {
+      AnnotationsFormat::Markdown => "annotations.md",
+      AnnotationsFormat::Json => "annotations.json",
+    }
+  }
+}
+
+// An annotation as it's exported, located by the label of its page.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedAnnotation {
+  pub page: String,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub text: String,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub note: String,
+  #[serde(with = "datetime_format")]
+  pub modified: DateTime<Local>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedAnnotations<'a> {
+  title: &'a str,
+  #[serde(skip_serializing_if = "str::is_empty")]
+  author: &'a str,
+  annotations: &'a [ExportedAnnotation],
+}
+
+pub fn annotations_as_markdown(info: &Info, annotations: &[ExportedAnnotation]) -> String {
+  let mut text = format!("# {}\n", info.title);
+  if !info.author.is_empty() {
+    text += &format!("\n*{}*\n", info.author);
+  }
+  for annot in annotations {
+    text += &format!("\n## p. {}\n", annot.page);
+    if !annot.text.is_empty() {
+      text.push('\n');
+      for line in annot.text.lines() {
+        text += &format!("> {}\n", line);
+      }
+    }
+    if !annot.note.is_empty() {
+      text += &format!("\n{}\n", annot.note);
+    }
+  }
+  text
+}
+
+pub fn annotations_as_json(
+  info: &Info,
+  annotations: &[ExportedAnnotation],
+) -> Result<String, serde_json::Error> {
+  serde_json::to_string_pretty(&ExportedAnnotations {
+    title: &info.title,
+    author: &info.author,
+    annotations,
+  })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Margin {
   pub top: f32,
@@ -124,6 +296,53 @@ impl Default for Margin {
   }
 }
 
+// The margins, in millimeters, around the text of a reflowable document.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PageMargins {
+  pub top: i32,
+  pub right: i32,
+  pub bottom: i32,
+  pub left: i32,
+}
+
+impl PageMargins {
+  pub fn uniform(width: i32) -> PageMargins {
+    PageMargins {
+      top: width,
+      right: width,
+      bottom: width,
+      left: width,
+    }
+  }
+
+  pub fn get(&self, dir: Dir) -> i32 {
+    match dir {
+      Dir::North => self.top,
+      Dir::East => self.right,
+      Dir::South => self.bottom,
+      Dir::West => self.left,
+    }
+  }
+
+  pub fn set(&mut self, dir: Dir, width: i32) {
+    match dir {
+      Dir::North => self.top = width,
+      Dir::East => self.right = width,
+      Dir::South => self.bottom = width,
+      Dir::West => self.left = width,
+    }
+  }
+
+  // The width shared by all the edges.
+  pub fn width(&self) -> Option<i32> {
+    if self.top == self.right && self.right == self.bottom && self.bottom == self.left {
+      Some(self.top)
+    } else {
+      None
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum PageScheme {
   Any,
@@ -195,6 +414,27 @@ impl fmt::Display for TextAlign {
   }
 }
 
+// Which alignments of the document give way to the chosen one.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextAlignOverride {
+  // The chosen alignment only applies where the document doesn't specify one.
+  Document,
+  // The justified and left aligned paragraphs are overridden, the centered and right aligned ones are kept.
+  BodyText,
+  Everything,
+}
+
+impl TextAlignOverride {
+  pub fn label(&self) -> &str {
+    match self {
+      TextAlignOverride::Document => "Follow Document",
+      TextAlignOverride::BodyText => "Override Body Text",
+      TextAlignOverride::Everything => "Override Everything",
+    }
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct ReaderInfo {
@@ -205,14 +445,30 @@ pub struct ReaderInfo {
   pub finished: bool,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub zoom_mode: Option<ZoomMode>,
+  // The zoom mode that preceded the current one.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub last_zoom_mode: Option<ZoomMode>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub scroll_mode: Option<ScrollMode>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub top_offset: Option<i32>,
   #[serde(skip_serializing_if = "Option::is_none")]
+  pub column: Option<usize>,
+  // The last detected column layout, used for the pages without text.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub columns: Option<Vec<[f32; 2]>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub rotation: Option<i8>,
+  // Overrides the rotation lock of the settings while reading this book.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub rotation_lock: Option<RotationLock>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub cropping_margins: Option<CroppingMargins>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub margin_width: Option<i32>,
+  // Supersedes the margin width when the edges have different margins.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub margins: Option<PageMargins>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub screen_margin_width: Option<i32>,
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -222,29 +478,157 @@ pub struct ReaderInfo {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub text_align: Option<TextAlign>,
   #[serde(skip_serializing_if = "Option::is_none")]
+  pub text_align_override: Option<TextAlignOverride>,
+  // Whether the line breaks and the indentation of the text are kept.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub verse_layout: Option<bool>,
+  // The hyphenation and justification settings of the book.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub max_hyphenated_lines: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub min_hyphenated_length: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub stretch_tolerance: Option<f32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub line_height: Option<f32>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub contrast_exponent: Option<f32>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub contrast_gray: Option<f32>,
+  // The filters applied to the pages of scanned documents.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub scan_cleanup: Option<ScanCleanup>,
   #[serde(skip_serializing_if = "BTreeMap::is_empty")]
   pub page_names: BTreeMap<usize, String>,
   #[serde(skip_serializing_if = "BTreeSet::is_empty")]
   pub bookmarks: BTreeSet<usize>,
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub annotations: Vec<Annotation>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub reading_speed: Option<ReadingSpeed>,
+}
+
+// The time spent reading, and the words and locations read during that time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReadingSpeed {
+  pub words: u64,
+  pub seconds: f64,
+  pub span: u64,
+}
+
+// The reading time, in seconds, below which the speed isn't reliable.
+const MIN_READING_TIME: f64 = 60.0;
+
+impl ReadingSpeed {
+  pub fn add(&mut self, other: &ReadingSpeed) {
+    self.words += other.words;
+    self.seconds += other.seconds;
+    self.span += other.span;
+  }
+
+  pub fn words_per_minute(&self) -> Option<f64> {
+    if self.seconds < MIN_READING_TIME || self.words == 0 {
+      return None;
+    }
+    Some(60.0 * self.words as f64 / self.seconds)
+  }
+
+  pub fn words_per_location(&self) -> Option<f64> {
+    if self.span == 0 {
+      return None;
+    }
+    Some(self.words as f64 / self.span as f64)
+  }
+
+  // Estimates the time, in seconds, needed to read *span* locations. The speed of *fallback*
+  // is used until enough time has been spent reading.
+  pub fn time_left(&self, span: usize, fallback: Option<&ReadingSpeed>) -> Option<f64> {
+    let words_per_location = self.words_per_location()?;
+    let words_per_minute = self
+      .words_per_minute()
+      .or_else(|| fallback.and_then(ReadingSpeed::words_per_minute))?;
+    Some(60.0 * span as f64 * words_per_location / words_per_minute)
+  }
+}
+
+// The zoom, crop and contrast of a scanned book, shared by the books of its directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DirectoryLayout {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub zoom_mode: Option<ZoomMode>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cropping_margins: Option<CroppingMargins>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub screen_margin_width: Option<i32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub contrast_exponent: Option<f32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub contrast_gray: Option<f32>,
+}
+
+impl DirectoryLayout {
+  pub fn from_reader(r: &ReaderInfo) -> DirectoryLayout {
+    DirectoryLayout {
+      zoom_mode: r.zoom_mode,
+      cropping_margins: r.cropping_margins.clone(),
+      screen_margin_width: r.screen_margin_width,
+      contrast_exponent: r.contrast_exponent,
+      contrast_gray: r.contrast_gray,
+    }
+  }
+
+  pub fn apply(&self, r: &mut ReaderInfo) {
+    r.zoom_mode = self.zoom_mode;
+    r.top_offset = None;
+    r.column = None;
+    r.columns = None;
+    r.cropping_margins = self.cropping_margins.clone();
+    r.screen_margin_width = self.screen_margin_width;
+    r.contrast_exponent = self.contrast_exponent;
+    r.contrast_gray = self.contrast_gray;
+  }
+}
+
+pub fn format_duration(seconds: f64) -> String {
+  let minutes = (seconds / 60.0).round() as u64;
+  if minutes < 1 {
+    "< 1 min".to_string()
+  } else if minutes < 60 {
+    format!("{} min", minutes)
+  } else {
+    format!("{} h {:02} min", minutes / 60, minutes % 60)
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ZoomMode {
   FitToPage,
   FitToWidth,
+  FitToColumn,
+}
+
+// How the pages of reflowable documents follow each other.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScrollMode {
+  Paginated,
+  Continuous,
 }
 
 impl ReaderInfo {
   pub fn progress(&self) -> f32 {
     (self.current_page / self.pages_count) as f32
   }
+
+  // The estimated time needed to finish the book, in seconds.
+  pub fn time_left(&self, fallback: Option<&ReadingSpeed>) -> Option<f64> {
+    let span = self.pages_count.saturating_sub(self.current_page);
+    self
+      .reading_speed
+      .as_ref()
+      .and_then(|speed| speed.time_left(span, fallback))
+  }
 }
 
 impl Default for ReaderInfo {
@@ -255,20 +639,33 @@ impl Default for ReaderInfo {
       pages_count: 1,
       finished: false,
       zoom_mode: None,
+      last_zoom_mode: None,
+      scroll_mode: None,
       top_offset: None,
+      column: None,
+      columns: None,
       rotation: None,
+      rotation_lock: None,
       cropping_margins: None,
       margin_width: None,
+      margins: None,
       screen_margin_width: None,
       font_family: None,
       font_size: None,
       text_align: None,
+      text_align_override: None,
+      verse_layout: None,
+      max_hyphenated_lines: None,
+      min_hyphenated_length: None,
+      stretch_tolerance: None,
       line_height: None,
       contrast_exponent: None,
       contrast_gray: None,
+      scan_cleanup: None,
       page_names: BTreeMap::new(),
       bookmarks: BTreeSet::new(),
       annotations: Vec::new(),
+      reading_speed: None,
     }
   }
 }
@@ -288,10 +685,13 @@ impl Default for Info {
       number: String::default(),
       identifier: String::default(),
       categories: BTreeSet::new(),
+      collections: BTreeSet::new(),
       file: FileInfo::default(),
       added: Local::now(),
       reader: None,
       toc: None,
+      visibility: Visibility::Visible,
+      loan: None,
     }
   }
 }
@@ -391,6 +791,7 @@ impl Info {
         || query.is_match(&self.author)
         || query.is_match(&self.series)
         || self.categories.iter().any(|c| query.is_match(c))
+        || self.collections.iter().any(|c| query.is_match(c))
         || self
           .file
           .path
@@ -761,7 +1162,10 @@ pub fn file_name_from_info(info: &Info) -> String {
   if !info.author.is_empty() {
     base = format!("{} - {}", base, asciify(&info.author));
   }
-  base = format!("{}.{}", base, info.file.kind);
+  // Image folders don't have an extension.
+  if info.file.kind != IMAGE_FOLDER_KIND {
+    base = format!("{}.{}", base, info.file.kind);
+  }
   base
     .replace("..", ".")
     .replace('/', " ")
@@ -769,3 +1173,79 @@ pub fn file_name_from_info(info: &Info) -> String {
     .replace('!', "")
     .replace(':', "")
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_export_annotations() {
+    let info = Info {
+      title: "Walden".to_string(),
+      author: "Henry David Thoreau".to_string(),
+      ..Default::default()
+    };
+    let annotations = vec![
+      ExportedAnnotation {
+        page: "iv".to_string(),
+        text: "I went to the woods\nbecause I wished".to_string(),
+        note: String::new(),
+        modified: Local::now(),
+      },
+      ExportedAnnotation {
+        page: "12".to_string(),
+        text: String::new(),
+        note: "Compare with Emerson.".to_string(),
+        modified: Local::now(),
+      },
+    ];
+    assert_eq!(
+      annotations_as_markdown(&info, &annotations),
+      "# Walden\n\n*Henry David Thoreau*\n\n## p. iv\n\n> I went to the woods\n\
+       > because I wished\n\n## p. 12\n\nCompare with Emerson.\n"
+    );
+    let json: serde_json::Value =
+      serde_json::from_str(&annotations_as_json(&info, &annotations).unwrap()).unwrap();
+    assert_eq!(json["annotations"][0]["page"], "iv");
+    assert_eq!(json["annotations"][1]["note"], "Compare with Emerson.");
+    assert!(json["annotations"][1].get("text").is_none());
+  }
+
+  #[test]
+  fn test_collections() {
+    let mut first = Info::default();
+    first.collections.insert("To Read".to_string());
+    first.collections.insert("Essays".to_string());
+    let mut second = Info::default();
+    second.collections.insert("Essays".to_string());
+    let third = Info::default();
+    assert_eq!(
+      collections(&[first, second, third]),
+      vec![
+        Collection {
+          name: "Essays".to_string(),
+          count: 2,
+        },
+        Collection {
+          name: "To Read".to_string(),
+          count: 1,
+        },
+      ]
+    );
+    let info: Info = serde_json::from_str(
+      r#"{"file":{"path":"a.epub","kind":"epub","size":1},"added":"2020-01-01 00:00:00"}"#,
+    )
+    .unwrap();
+    assert!(info.collections.is_empty());
+  }
+
+  #[test]
+  fn test_page_margins() {
+    let mut margins = PageMargins::uniform(4);
+    assert_eq!(margins.width(), Some(4));
+    margins.set(Dir::North, 9);
+    assert_eq!(margins.get(Dir::North), 9);
+    assert_eq!(margins.get(Dir::South), 4);
+    assert_eq!(margins.width(), None);
+  }
+}
@@ -0,0 +1,113 @@
+//! Turns the Wi-Fi on for the operations that need the network, and off once they're done.
+
+use fxhash::FxHashSet;
+use std::time::{Duration, Instant};
+
+// The operations that need the network.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NetworkTask {
+  Sync,
+  Feeds,
+  // The fetchers of the library directories.
+  Fetcher,
+  Dictionaries,
+  Opds,
+}
+
+#[derive(Debug)]
+pub struct NetworkManager {
+  // The operations that are using the network or waiting for it.
+  tasks: FxHashSet<NetworkTask>,
+  // The operations that will be started when the network is up.
+  deferred: Vec<NetworkTask>,
+  // Whether the Wi-Fi was turned on by the manager, rather than by the user.
+  automatic: bool,
+  idle_since: Instant,
+}
+
+impl Default for NetworkManager {
+  fn default() -> Self {
+    NetworkManager {
+      tasks: FxHashSet::default(),
+      deferred: Vec::new(),
+      automatic: false,
+      idle_since: Instant::now(),
+    }
+  }
+}
+
+impl NetworkManager {
+  // Registers an operation. Returns whether the Wi-Fi needs to be turned on.
+  pub fn acquire(&mut self, task: NetworkTask, wifi: bool) -> bool {
+    self.tasks.insert(task);
+    if wifi {
+      return false;
+    }
+    self.automatic = true;
+    true
+  }
+
+  // Registers an operation that can't start before the network is up.
+  pub fn defer(&mut self, task: NetworkTask, wifi: bool) -> bool {
+    if !self.deferred.contains(&task) {
+      self.deferred.push(task);
+    }
+    self.acquire(task, wifi)
+  }
+
+  pub fn release(&mut self, task: NetworkTask) {
+    if self.tasks.remove(&task) && self.tasks.is_empty() {
+      self.idle_since = Instant::now();
+    }
+  }
+
+  // The operations to start now that the network is up.
+  pub fn take_deferred(&mut self) -> Vec<NetworkTask> {
+    self.deferred.drain(..).collect()
+  }
+
+  // The user took control of the Wi-Fi.
+  pub fn set_manual(&mut self) {
+    self.automatic = false;
+    self.deferred.clear();
+  }
+
+  pub fn is_busy(&self) -> bool {
+    !self.tasks.is_empty()
+  }
+
+  // Whether the Wi-Fi turned on by the manager has been idle for *timeout*.
+  pub fn should_disable(&self, timeout: Duration) -> bool {
+    self.automatic && self.tasks.is_empty() && self.idle_since.elapsed() >= timeout
+  }
+
+  // The Wi-Fi was turned off, and the pending operations are abandoned.
+  pub fn reset(&mut self) {
+    self.automatic = false;
+    self.tasks.clear();
+    self.deferred.clear();
+    self.idle_since = Instant::now();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{NetworkManager, NetworkTask};
+  use std::time::Duration;
+
+  #[test]
+  fn test_idle_timeout() {
+    let mut manager = NetworkManager::default();
+    assert!(manager.defer(NetworkTask::Feeds, false));
+    assert!(!manager.acquire(NetworkTask::Sync, true));
+    assert_eq!(manager.take_deferred(), vec![NetworkTask::Feeds]);
+    assert!(manager.take_deferred().is_empty());
+    manager.release(NetworkTask::Feeds);
+    assert!(!manager.should_disable(Duration::from_secs(0)));
+    manager.release(NetworkTask::Sync);
+    assert!(manager.should_disable(Duration::from_secs(0)));
+    assert!(!manager.should_disable(Duration::from_secs(60)));
+    manager.set_manual();
+    assert!(!manager.should_disable(Duration::from_secs(0)));
+  }
+}
@@ -0,0 +1,222 @@
+//! Parses the OPDS catalogs: Atom feeds whose entries are either books or other catalogs.
+
+use crate::{
+  document::html::{dom::Node, xml::XmlParser},
+  feed::{child, find_all, text_content},
+  helpers::decode_entities,
+};
+use anyhow::{format_err, Error};
+use reqwest::Url;
+
+// The maximum number of characters of a file name.
+const MAX_NAME_LEN: usize = 96;
+const ACQUISITION_REL: &str = "http://opds-spec.org/acquisition";
+// The relations of the acquisition links that don't lead to a free download.
+const RESTRICTED_RELS: [&str; 4] = ["buy", "borrow", "subscribe", "sample"];
+// The supported media types, in order of preference.
+const MEDIA_TYPES: [(&str, &str); 6] = [
+  ("application/epub+zip", "epub"),
+  ("application/pdf", "pdf"),
+  ("application/vnd.comicbook+zip", "cbz"),
+  ("application/x-cbz", "cbz"),
+  ("image/vnd.djvu", "djvu"),
+  ("application/x-fictionbook+xml", "fb2"),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct OpdsFeed {
+  pub url: String,
+  pub title: String,
+  pub entries: Vec<OpdsEntry>,
+  // The neighboring pages of a paginated feed.
+  pub next: Option<String>,
+  pub previous: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpdsEntry {
+  pub title: String,
+  pub author: String,
+  pub link: OpdsLink,
+}
+
+#[derive(Debug, Clone)]
+pub enum OpdsLink {
+  Catalog(String),
+  // The URL of the book and its file kind.
+  Book(String, String),
+}
+
+pub fn parse_opds(text: &str, url: &str) -> Result<OpdsFeed, Error> {
+  let root = XmlParser::new(text).parse();
+  let feed = root
+    .find("feed")
+    .ok_or_else(|| format_err!("not an OPDS catalog"))?;
+  let base = Url::parse(url).ok();
+  let resolve = |href: &str| {
+    let href = decode_entities(href).into_owned();
+    base
+      .as_ref()
+      .and_then(|base| base.join(&href).ok())
+      .map_or(href, |url| url.to_string())
+  };
+
+  let links = links(feed);
+  let find_link = |rels: &[&str]| {
+    links
+      .iter()
+      .find(|n| n.attr("rel").map_or(false, |rel| rels.contains(&rel)))
+      .and_then(|n| n.attr("href"))
+      .map(|href| resolve(href))
+  };
+
+  let mut nodes = Vec::new();
+  find_all(feed, "entry", &mut nodes);
+  let entries = nodes
+    .into_iter()
+    .filter_map(|entry| {
+      let link = entry_link(entry)?;
+      Some(OpdsEntry {
+        title: child(entry, "title").map(text_content).unwrap_or_default(),
+        author: child(entry, "author")
+          .and_then(|n| child(n, "name"))
+          .map(text_content)
+          .unwrap_or_default(),
+        link: match link {
+          OpdsLink::Catalog(href) => OpdsLink::Catalog(resolve(&href)),
+          OpdsLink::Book(href, kind) => OpdsLink::Book(resolve(&href), kind),
+        },
+      })
+    })
+    .collect();
+
+  Ok(OpdsFeed {
+    url: url.to_string(),
+    title: child(feed, "title").map(text_content).unwrap_or_default(),
+    next: find_link(&["next"]),
+    previous: find_link(&["previous", "prev"]),
+    entries,
+  })
+}
+
+fn links(node: &Node) -> Vec<&Node> {
+  node
+    .children()
+    .into_iter()
+    .flatten()
+    .filter(|n| n.tag_name() == Some("link"))
+    .collect()
+}
+
+// The best acquisition link of a book, or the link of a catalog.
+fn entry_link(entry: &Node) -> Option<OpdsLink> {
+  let links = links(entry);
+  let book = links
+    .iter()
+    .filter(|n| {
+      n.attr("rel").map_or(false, |rel| {
+        rel.starts_with(ACQUISITION_REL)
+          && !RESTRICTED_RELS
+            .iter()
+            .any(|r| rel[ACQUISITION_REL.len()..].trim_start_matches('/') == *r)
+      })
+    })
+    .filter_map(|n| {
+      let media_type = n.attr("type")?.split(';').next()?.trim();
+      let rank = MEDIA_TYPES.iter().position(|(t, _)| *t == media_type)?;
+      Some((rank, n.attr("href")?))
+    })
+    .min_by_key(|(rank, _)| *rank)
+    .map(|(rank, href)| OpdsLink::Book(href.to_string(), MEDIA_TYPES[rank].1.to_string()));
+
+  book.or_else(|| {
+    links
+      .iter()
+      .filter(|n| {
+        n.attr("type")
+          .map_or(false, |t| t.starts_with("application/atom+xml"))
+      })
+      .find(|n| {
+        n.attr("rel")
+          .map_or(true, |rel| !rel.starts_with("http://opds-spec.org/"))
+      })
+      .and_then(|n| n.attr("href"))
+      .map(|href| OpdsLink::Catalog(href.to_string()))
+  })
+}
+
+// The name under which a book is saved, without the characters that aren't allowed in FAT32
+// file names.
+pub fn file_name(entry: &OpdsEntry, kind: &str) -> String {
+  let name = if entry.author.is_empty() {
+    entry.title.clone()
+  } else {
+    format!("{} - {}", entry.author, entry.title)
+  };
+  let name: String = name
+    .chars()
+    .map(|c| {
+      if c.is_control() || "<>:\"/\\|?*".contains(c) {
+        '_'
+      } else {
+        c
+      }
+    })
+    .take(MAX_NAME_LEN)
+    .collect();
+  let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+  format!("{}.{}", if name.is_empty() { "_" } else { name }, kind)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_opds() {
+    let text = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:opds="http://opds-spec.org/2010/catalog">
+<title>New Releases</title>
+<link rel="self" href="/opds/new?page=2" type="application/atom+xml;profile=opds-catalog"/>
+<link rel="next" href="/opds/new?page=3&amp;sort=date" type="application/atom+xml"/>
+<link rel="previous" href="/opds/new?page=1" type="application/atom+xml"/>
+<entry>
+<title>Authors</title>
+<link rel="subsection" href="authors" type="application/atom+xml;profile=opds-catalog;kind=navigation"/>
+</entry>
+<entry>
+<title>Moby Dick</title>
+<author><name>Herman Melville</name></author>
+<link rel="http://opds-spec.org/image" href="/covers/1.jpg" type="image/jpeg"/>
+<link rel="http://opds-spec.org/acquisition/buy" href="/buy/1" type="application/epub+zip"/>
+<link rel="http://opds-spec.org/acquisition" href="/get/1.pdf" type="application/pdf"/>
+<link rel="http://opds-spec.org/acquisition/open-access" href="/get/1.epub" type="application/epub+zip; profile=x"/>
+</entry>
+<entry>
+<title>Kindle Only</title>
+<link rel="http://opds-spec.org/acquisition" href="/get/2.azw3" type="application/x-mobi8-ebook"/>
+</entry>
+</feed>"#;
+    let feed = parse_opds(text, "https://example.org/opds/new?page=2").unwrap();
+    assert_eq!(feed.title, "New Releases");
+    assert_eq!(
+      feed.next.as_deref(),
+      Some("https://example.org/opds/new?page=3&sort=date")
+    );
+    assert_eq!(
+      feed.previous.as_deref(),
+      Some("https://example.org/opds/new?page=1")
+    );
+    assert_eq!(feed.entries.len(), 2);
+    assert!(
+      matches!(feed.entries[0].link, OpdsLink::Catalog(ref url) if url == "https://example.org/opds/authors")
+    );
+    let book = &feed.entries[1];
+    assert_eq!(book.author, "Herman Melville");
+    assert!(
+      matches!(book.link, OpdsLink::Book(ref url, ref kind) if url == "https://example.org/get/1.epub" && kind == "epub")
+    );
+    assert_eq!(file_name(book, "epub"), "Herman Melville - Moby Dick.epub");
+    assert!(parse_opds("<html></html>", "").is_err());
+  }
+}
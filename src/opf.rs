@@ -0,0 +1,344 @@
+//! Reads and updates the OPF sidecars that *Calibre* writes next to the documents it sends.
+
+use crate::{
+  document::html::{dom::Node, xml::XmlParser},
+  helpers::decode_entities,
+  metadata::Info,
+};
+use anyhow::{format_err, Error};
+use fxhash::FxHashSet;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+// The name of the sidecar shared by all the documents of a directory.
+const SIDECAR_NAME: &str = "metadata.opf";
+const USER_METADATA_PREFIX: &str = "calibre:user_metadata:";
+// Calibre's placeholder for undefined dates.
+const UNDEFINED_YEAR: &str = "0101";
+
+lazy_static! {
+  static ref CREATOR: Regex = Regex::new(r"(?s)\s*<dc:creator\b([^>]*)>.*?</dc:creator>").unwrap();
+  static ref SERIES: Regex =
+    Regex::new(r#"\s*<meta\s[^>]*name="calibre:series(?:_index)?"[^>]*/>"#).unwrap();
+  static ref METADATA_END: Regex = Regex::new(r"\s*</(?:opf:)?metadata>").unwrap();
+}
+
+// Returns the sidecar of the given document: the OPF file sharing its stem, or `metadata.opf`.
+pub fn sidecar_path(path: &Path) -> Option<PathBuf> {
+  let own = path.with_extension("opf");
+  if own.exists() {
+    return Some(own);
+  }
+  let shared = path.parent()?.join(SIDECAR_NAME);
+  if shared.exists() {
+    Some(shared)
+  } else {
+    None
+  }
+}
+
+pub fn extract_metadata_from_opf(prefix: &Path, info: &mut Info) {
+  let path = prefix.join(&info.file.path);
+
+  if let Some(sidecar) = sidecar_path(&path) {
+    match fs::read_to_string(&sidecar) {
+      Ok(text) => apply_opf(&text, info),
+      Err(e) => eprintln!("Can't read {}: {}.", sidecar.display(), e),
+    }
+  }
+}
+
+// Writes the title, authors, publisher, language, series and categories of *info* back to the
+// sidecar sharing its stem. The other elements of the sidecar are left untouched. The shared
+// `metadata.opf` is never written, since it might describe another document of the directory.
+pub fn write_metadata_to_opf(prefix: &Path, info: &Info) -> Result<(), Error> {
+  let sidecar = prefix.join(&info.file.path).with_extension("opf");
+  if !sidecar.exists() {
+    return Err(format_err!("No sidecar for {}.", info.file.path.display()));
+  }
+  let text = fs::read_to_string(&sidecar)?;
+  let columns = custom_columns(&text);
+
+  let mut text = remove_elements(
+    &text,
+    &["dc:title", "dc:publisher", "dc:language", "dc:subject"],
+  );
+  text = CREATOR
+    .replace_all(&text, |caps: &Captures| {
+      if caps[1].contains("role=") && !caps[1].contains("role=\"aut\"") {
+        caps[0].to_string()
+      } else {
+        String::new()
+      }
+    })
+    .into_owned();
+  text = SERIES.replace_all(&text, "").into_owned();
+
+  let mut elements = Vec::new();
+  if !info.title.is_empty() {
+    elements.push(format!("<dc:title>{}</dc:title>", escape(&info.title)));
+  }
+  for author in info.author.split(", ").filter(|a| !a.is_empty()) {
+    elements.push(format!(
+      "<dc:creator opf:role=\"aut\">{}</dc:creator>",
+      escape(author)
+    ));
+  }
+  if !info.publisher.is_empty() {
+    elements.push(format!(
+      "<dc:publisher>{}</dc:publisher>",
+      escape(&info.publisher)
+    ));
+  }
+  if !info.language.is_empty() {
+    elements.push(format!(
+      "<dc:language>{}</dc:language>",
+      escape(&info.language)
+    ));
+  }
+  // The categories derived from the custom columns are stored in the columns themselves.
+  for categ in info
+    .categories
+    .iter()
+    .filter(|c| !columns.contains(c.split('.').next().unwrap_or_default()))
+  {
+    elements.push(format!("<dc:subject>{}</dc:subject>", escape(categ)));
+  }
+  if !info.series.is_empty() {
+    elements.push(format!(
+      "<meta name=\"calibre:series\" content=\"{}\"/>",
+      escape(&info.series)
+    ));
+    if !info.number.is_empty() {
+      elements.push(format!(
+        "<meta name=\"calibre:series_index\" content=\"{}\"/>",
+        escape(&info.number)
+      ));
+    }
+  }
+
+  let end = METADATA_END
+    .find(&text)
+    .ok_or_else(|| format_err!("No metadata element in {}.", sidecar.display()))?;
+  let mut result = text[..end.start()].to_string();
+  for element in elements {
+    result.push_str("\n    ");
+    result.push_str(&element);
+  }
+  result.push_str(&text[end.start()..]);
+
+  fs::write(&sidecar, result).map_err(Into::into)
+}
+
+fn apply_opf(text: &str, info: &mut Info) {
+  let root = XmlParser::new(text).parse();
+  let children = match root.find("metadata").and_then(Node::children) {
+    Some(children) => children,
+    None => return,
+  };
+  let mut authors = Vec::new();
+  let mut identifier = None;
+
+  for child in children {
+    match child.tag_qualified_name() {
+      Some("dc:title") => {
+        if let Some(title) = element_text(child) {
+          info.title = title;
+        }
+      },
+      Some("dc:creator") => {
+        if child.attr("opf:role").map_or(true, |role| role == "aut") {
+          authors.extend(element_text(child));
+        }
+      },
+      Some("dc:publisher") => {
+        if let Some(publisher) = element_text(child) {
+          info.publisher = publisher;
+        }
+      },
+      Some("dc:language") => {
+        if let Some(language) = element_text(child) {
+          info.language = language;
+        }
+      },
+      Some("dc:date") => {
+        if let Some(date) = element_text(child) {
+          let year: String = date.chars().take(4).collect();
+          if year != UNDEFINED_YEAR {
+            info.year = year;
+          }
+        }
+      },
+      Some("dc:subject") => {
+        info.categories.extend(element_text(child));
+      },
+      Some("dc:identifier") => {
+        let scheme = child.attr("opf:scheme").map(str::to_lowercase);
+        if let Some(value) = element_text(child) {
+          match scheme.as_deref() {
+            Some("isbn") => identifier = Some(value),
+            Some("calibre") | Some("uuid") => (),
+            _ => {
+              identifier.get_or_insert(value);
+            },
+          }
+        }
+      },
+      _ if child.tag_name() == Some("meta") => {
+        let content = child.attr("content").map(decode_entities);
+        match (child.attr("name"), content) {
+          (Some("calibre:series"), Some(series)) => info.series = series.into_owned(),
+          (Some("calibre:series_index"), Some(index)) => {
+            info.number = index.trim_end_matches(".0").to_string();
+          },
+          (Some(name), Some(content)) if name.starts_with(USER_METADATA_PREFIX) => {
+            info.categories.extend(column_categories(&content));
+          },
+          _ => (),
+        }
+      },
+      _ => (),
+    }
+  }
+
+  if !authors.is_empty() {
+    info.author = authors.join(", ");
+  }
+
+  if let Some(identifier) = identifier {
+    info.identifier = identifier;
+  }
+}
+
+fn element_text(node: &Node) -> Option<String> {
+  node
+    .text()
+    .map(|text| decode_entities(text.trim()).into_owned())
+    .filter(|text| !text.is_empty())
+}
+
+// Maps the value of a custom column to categories: the name of the column followed by each value.
+fn column_categories(content: &str) -> Vec<String> {
+  let column: Value = match serde_json::from_str(content) {
+    Ok(column) => column,
+    Err(_) => return Vec::new(),
+  };
+
+  let name = match column.get("name").and_then(Value::as_str) {
+    Some(name) => name.replace('.', " "),
+    None => return Vec::new(),
+  };
+
+  if column.get("datatype").and_then(Value::as_str) == Some("comments") {
+    return Vec::new();
+  }
+
+  match column.get("#value#") {
+    Some(Value::String(value)) if !value.is_empty() => vec![format!("{}.{}", name, value)],
+    Some(Value::Array(values)) => values
+      .iter()
+      .filter_map(Value::as_str)
+      .map(|value| format!("{}.{}", name, value))
+      .collect(),
+    Some(Value::Bool(true)) => vec![name],
+    Some(Value::Number(value)) => vec![format!("{}.{}", name, value)],
+    _ => Vec::new(),
+  }
+}
+
+fn custom_columns(text: &str) -> FxHashSet<String> {
+  let root = XmlParser::new(text).parse();
+  root
+    .find("metadata")
+    .and_then(Node::children)
+    .map(|children| {
+      children
+        .iter()
+        .filter(|child| {
+          child.tag_name() == Some("meta")
+            && child
+              .attr("name")
+              .map_or(false, |name| name.starts_with(USER_METADATA_PREFIX))
+        })
+        .filter_map(|child| child.attr("content"))
+        .filter_map(|content| serde_json::from_str::<Value>(&decode_entities(content)).ok())
+        .filter_map(|column| {
+          column
+            .get("name")
+            .and_then(Value::as_str)
+            .map(|name| name.replace('.', " "))
+        })
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn remove_elements(text: &str, names: &[&str]) -> String {
+  let mut text = text.to_string();
+  for name in names {
+    let re = Regex::new(&format!(
+      r"(?s)\s*<{0}\b[^>]*?(?:/>|>.*?</{0}>)",
+      regex::escape(name)
+    ))
+    .unwrap();
+    text = re.replace_all(&text, "").into_owned();
+  }
+  text
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{apply_opf, column_categories};
+  use crate::metadata::Info;
+
+  #[test]
+  fn test_apply_opf() {
+    let text = r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:identifier opf:scheme="calibre">42</dc:identifier>
+    <dc:identifier opf:scheme="ISBN">9780000000000</dc:identifier>
+    <dc:title>The Left Hand of Darkness</dc:title>
+    <dc:creator opf:role="aut">Ursula K. Le Guin</dc:creator>
+    <dc:creator opf:role="trl">Someone Else</dc:creator>
+    <dc:date>0101-01-01T00:00:00+00:00</dc:date>
+    <dc:subject>Science Fiction</dc:subject>
+    <meta name="calibre:series" content="Hainish Cycle"/>
+    <meta name="calibre:series_index" content="4.0"/>
+  </metadata>
+</package>"#;
+    let mut info = Info::default();
+    apply_opf(text, &mut info);
+    assert_eq!(info.title, "The Left Hand of Darkness");
+    assert_eq!(info.author, "Ursula K. Le Guin");
+    assert_eq!(info.identifier, "9780000000000");
+    assert_eq!(info.series, "Hainish Cycle");
+    assert_eq!(info.number, "4");
+    assert!(info.year.is_empty());
+    assert!(info.categories.contains("Science Fiction"));
+  }
+
+  #[test]
+  fn test_column_categories() {
+    let content = r##"{"name": "Moods", "datatype": "text", "#value#": ["Calm", "Eerie"]}"##;
+    assert_eq!(
+      column_categories(content),
+      vec!["Moods.Calm", "Moods.Eerie"]
+    );
+    let content = r##"{"name": "Notes", "datatype": "comments", "#value#": "<p>Hi</p>"}"##;
+    assert!(column_categories(content).is_empty());
+  }
+}
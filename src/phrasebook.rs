@@ -0,0 +1,177 @@
+//! The phrasebook gathers the translated selections, along with the sentence and the book
+//! they were found in.
+
+use crate::helpers::{datetime_format, load_json, save_json_atomically};
+use anyhow::Error;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+pub const PHRASEBOOK_PATH: &str = "Phrasebook.json";
+pub const PHRASEBOOK_EXPORT_NAME: &str = "Phrasebook.tsv";
+// The maximum number of characters kept from a translation.
+const TARGET_MAX_LENGTH: usize = 160;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Phrase {
+  pub source: String,
+  pub target: String,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub language: String,
+  // The dictionary that gave the translation.
+  pub dictionary: String,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub context: String,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub title: String,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub author: String,
+  #[serde(with = "datetime_format")]
+  pub added: DateTime<Local>,
+}
+
+impl Default for Phrase {
+  fn default() -> Self {
+    Phrase {
+      source: String::new(),
+      target: String::new(),
+      language: String::new(),
+      dictionary: String::new(),
+      context: String::new(),
+      title: String::new(),
+      author: String::new(),
+      added: Local::now(),
+    }
+  }
+}
+
+pub fn load_phrasebook<P: AsRef<Path>>(path: P) -> Result<Vec<Phrase>, Error> {
+  if !path.as_ref().exists() {
+    return Ok(Vec::new());
+  }
+  load_json(path)
+}
+
+// Adds *phrase* to the phrasebook, replacing a previous translation of the same text.
+pub fn add_phrase<P: AsRef<Path>>(path: P, phrase: Phrase) -> Result<(), Error> {
+  let mut phrases = load_phrasebook(path.as_ref())?;
+  phrases.retain(|p| p.source != phrase.source || p.language != phrase.language);
+  phrases.push(phrase);
+  save_json_atomically(&phrases, path)
+}
+
+// Returns the beginning of the text of a dictionary entry.
+pub fn short_translation(body: &str) -> String {
+  let mut text = String::new();
+  let mut in_tag = false;
+  for c in body.chars() {
+    match c {
+      '<' => in_tag = true,
+      '>' if in_tag => {
+        in_tag = false;
+        text.push(' ');
+      },
+      _ if !in_tag => text.push(c),
+      _ => (),
+    }
+  }
+  let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+  if text.chars().count() > TARGET_MAX_LENGTH {
+    let mut short = text.chars().take(TARGET_MAX_LENGTH).collect::<String>();
+    short.push('…');
+    short
+  } else {
+    text
+  }
+}
+
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
+
+pub fn phrasebook_as_html(phrases: &[Phrase]) -> String {
+  let mut buf =
+    "<html>\n\t<head>\n\t\t<title>Phrasebook</title>\n\t</head>\n\t<body>\n".to_string();
+  if phrases.is_empty() {
+    buf.push_str("\t\t<p>The phrasebook is empty.</p>\n");
+  }
+  for phrase in phrases.iter().rev() {
+    buf.push_str(&format!(
+      "\t\t<h2>{}</h2>\n\t\t<p>{}</p>\n",
+      escape_html(&phrase.source),
+      escape_html(&phrase.target)
+    ));
+    if !phrase.context.is_empty() {
+      buf.push_str(&format!(
+        "\t\t<blockquote>{}</blockquote>\n",
+        escape_html(&phrase.context)
+      ));
+    }
+    if !phrase.title.is_empty() {
+      let book = if phrase.author.is_empty() {
+        phrase.title.clone()
+      } else {
+        format!("{}, {}", phrase.title, phrase.author)
+      };
+      buf.push_str(&format!("\t\t<p><i>{}</i></p>\n", escape_html(&book)));
+    }
+  }
+  buf.push_str("\t</body>\n</html>");
+  buf
+}
+
+// One phrase per line, with tab separated fields, as expected by the flashcard applications.
+pub fn phrasebook_as_tsv(phrases: &[Phrase]) -> String {
+  let clean = |text: &str| text.replace(&['\t', '\n'][..], " ");
+  phrases
+    .iter()
+    .map(|p| {
+      format!(
+        "{}\t{}\t{}\t{}\n",
+        clean(&p.source),
+        clean(&p.target),
+        clean(&p.context),
+        clean(&p.title)
+      )
+    })
+    .collect()
+}
+
+// Writes the phrasebook as TSV at the root of the library and returns the path of the file.
+pub fn export_phrasebook(home: &Path) -> Result<PathBuf, Error> {
+  let phrases = load_phrasebook(PHRASEBOOK_PATH)?;
+  let path = home.join(PHRASEBOOK_EXPORT_NAME);
+  fs::write(&path, phrasebook_as_tsv(&phrases))?;
+  Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{phrasebook_as_tsv, short_translation, Phrase};
+
+  #[test]
+  fn test_short_translation() {
+    assert_eq!(
+      short_translation("<b>chat</b> <i>m</i>\n<ol><li>cat</li></ol>"),
+      "chat m cat"
+    );
+  }
+
+  #[test]
+  fn test_phrasebook_as_tsv() {
+    let phrase = Phrase {
+      source: "chat".to_string(),
+      target: "cat".to_string(),
+      context: "Le chat\tdort.".to_string(),
+      ..Default::default()
+    };
+    assert_eq!(phrasebook_as_tsv(&[phrase]), "chat\tcat\tLe chat dort.\t\n");
+  }
+}
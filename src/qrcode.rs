@@ -0,0 +1,404 @@
+//! A QR code encoder, limited to what's needed to share URLs: byte mode, low error
+//! correction level and versions 1 to 10 (up to 271 bytes).
+
+const MIN_VERSION: usize = 1;
+const MAX_VERSION: usize = 10;
+// The error correction codewords per block, and the number of blocks, for the level L.
+const ECC_CODEWORDS_PER_BLOCK: [usize; MAX_VERSION + 1] =
+  [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18];
+const ECC_BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4];
+// The two bits of the level L in the format information.
+const ECC_FORMAT_BITS: u32 = 1;
+
+pub struct QrCode {
+  size: usize,
+  modules: Vec<bool>,
+  is_function: Vec<bool>,
+}
+
+impl QrCode {
+  // Returns the smallest code that holds *data*.
+  pub fn encode(data: &[u8]) -> Option<QrCode> {
+    let version = (MIN_VERSION..=MAX_VERSION).find(|&v| {
+      let count_bits = if v < 10 { 8 } else { 16 };
+      4 + count_bits + 8 * data.len() <= 8 * data_codewords_count(v)
+    })?;
+    let count_bits = if version < 10 { 8 } else { 16 };
+    let capacity = 8 * data_codewords_count(version);
+
+    let mut bits = Vec::with_capacity(capacity);
+    push_bits(&mut bits, 0b0100, 4);
+    push_bits(&mut bits, data.len() as u32, count_bits);
+    for &b in data {
+      push_bits(&mut bits, b as u32, 8);
+    }
+    let terminator = (capacity - bits.len()).min(4);
+    push_bits(&mut bits, 0, terminator);
+    let padding = (8 - bits.len() % 8) % 8;
+    push_bits(&mut bits, 0, padding);
+    let mut codewords = bits
+      .chunks(8)
+      .map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+      .collect::<Vec<u8>>();
+    for pad in [0xEC, 0x11].iter().cycle() {
+      if codewords.len() >= capacity / 8 {
+        break;
+      }
+      codewords.push(*pad);
+    }
+
+    let size = 4 * version + 17;
+    let mut qr = QrCode {
+      size,
+      modules: vec![false; size * size],
+      is_function: vec![false; size * size],
+    };
+    qr.draw_function_patterns(version);
+    qr.draw_codewords(&interleave(version, &codewords));
+
+    let mask = (0..8)
+      .min_by_key(|&mask| {
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+        let penalty = qr.penalty();
+        qr.apply_mask(mask);
+        penalty
+      })
+      .unwrap_or(0);
+    qr.apply_mask(mask);
+    qr.draw_format_bits(mask);
+    Some(qr)
+  }
+
+  pub fn size(&self) -> usize {
+    self.size
+  }
+
+  // Whether the module at the given coordinates is dark.
+  pub fn get(&self, x: usize, y: usize) -> bool {
+    self.modules[y * self.size + x]
+  }
+
+  fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+    self.modules[y * self.size + x] = dark;
+    self.is_function[y * self.size + x] = true;
+  }
+
+  fn draw_function_patterns(&mut self, version: usize) {
+    let size = self.size;
+    for i in 0..size {
+      self.set_function(6, i, i % 2 == 0);
+      self.set_function(i, 6, i % 2 == 0);
+    }
+
+    self.draw_finder_pattern(3, 3);
+    self.draw_finder_pattern(size as i32 - 4, 3);
+    self.draw_finder_pattern(3, size as i32 - 4);
+
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    for (i, &x) in positions.iter().enumerate() {
+      for (j, &y) in positions.iter().enumerate() {
+        // Skip the three finder corners.
+        if (i == 0 && j == 0) || (i == 0 && j == last) || (i == last && j == 0) {
+          continue;
+        }
+        for dy in -2i32..=2 {
+          for dx in -2i32..=2 {
+            let dark = dx.abs().max(dy.abs()) != 1;
+            self.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+          }
+        }
+      }
+    }
+
+    // Reserve the format areas, the actual bits are drawn with the mask.
+    self.draw_format_bits(0);
+
+    if version >= 7 {
+      let mut rem = version as u32;
+      for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+      }
+      let bits = (version as u32) << 12 | rem;
+      for i in 0..18 {
+        let dark = (bits >> i) & 1 == 1;
+        let a = size - 11 + i % 3;
+        let b = i / 3;
+        self.set_function(a, b, dark);
+        self.set_function(b, a, dark);
+      }
+    }
+  }
+
+  // Draws a finder pattern and its separator, centered at the given coordinates.
+  fn draw_finder_pattern(&mut self, x: i32, y: i32) {
+    for dy in -4i32..=4 {
+      for dx in -4i32..=4 {
+        let (xx, yy) = (x + dx, y + dy);
+        if xx < 0 || yy < 0 || xx >= self.size as i32 || yy >= self.size as i32 {
+          continue;
+        }
+        let dist = dx.abs().max(dy.abs());
+        self.set_function(xx as usize, yy as usize, dist != 2 && dist != 4);
+      }
+    }
+  }
+
+  fn draw_format_bits(&mut self, mask: u32) {
+    let bits = format_bits(mask);
+    let bit = |i: u32| (bits >> i) & 1 == 1;
+    let size = self.size;
+    for i in 0..6 {
+      self.set_function(8, i as usize, bit(i));
+    }
+    self.set_function(8, 7, bit(6));
+    self.set_function(8, 8, bit(7));
+    self.set_function(7, 8, bit(8));
+    for i in 9..15 {
+      self.set_function(14 - i as usize, 8, bit(i));
+    }
+    for i in 0..8 {
+      self.set_function(size - 1 - i as usize, 8, bit(i));
+    }
+    for i in 8..15 {
+      self.set_function(8, size - 15 + i as usize, bit(i));
+    }
+    self.set_function(8, size - 8, true);
+  }
+
+  fn draw_codewords(&mut self, data: &[u8]) {
+    let size = self.size;
+    let mut i = 0;
+    let mut right = size as i32 - 1;
+    while right >= 1 {
+      if right == 6 {
+        right = 5;
+      }
+      for vert in 0..size {
+        for j in 0..2 {
+          let x = (right - j) as usize;
+          let upward = (right + 1) & 2 == 0;
+          let y = if upward { size - 1 - vert } else { vert };
+          if !self.is_function[y * size + x] && i < data.len() * 8 {
+            self.modules[y * size + x] = (data[i / 8] >> (7 - i % 8)) & 1 == 1;
+            i += 1;
+          }
+        }
+      }
+      right -= 2;
+    }
+  }
+
+  // Masks are involutions: applying the same mask twice restores the modules.
+  fn apply_mask(&mut self, mask: u32) {
+    let size = self.size;
+    for y in 0..size {
+      for x in 0..size {
+        let invert = match mask {
+          0 => (x + y) % 2 == 0,
+          1 => y % 2 == 0,
+          2 => x % 3 == 0,
+          3 => (x + y) % 3 == 0,
+          4 => (x / 3 + y / 2) % 2 == 0,
+          5 => x * y % 2 + x * y % 3 == 0,
+          6 => (x * y % 2 + x * y % 3) % 2 == 0,
+          _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+        };
+        if invert && !self.is_function[y * size + x] {
+          self.modules[y * size + x] ^= true;
+        }
+      }
+    }
+  }
+
+  // A simplified evaluation of the symbol: long runs, filled blocks and unbalanced colors.
+  fn penalty(&self) -> usize {
+    let size = self.size;
+    let mut penalty = 0;
+    for horizontal in [true, false].iter() {
+      for a in 0..size {
+        let mut run = 1;
+        for b in 1..=size {
+          let module = |b: usize| {
+            if *horizontal {
+              self.get(b, a)
+            } else {
+              self.get(a, b)
+            }
+          };
+          if b < size && module(b) == module(b - 1) {
+            run += 1;
+          } else {
+            if run >= 5 {
+              penalty += run - 2;
+            }
+            run = 1;
+          }
+        }
+      }
+    }
+    for y in 0..size - 1 {
+      for x in 0..size - 1 {
+        let color = self.get(x, y);
+        if color == self.get(x + 1, y)
+          && color == self.get(x, y + 1)
+          && color == self.get(x + 1, y + 1)
+        {
+          penalty += 3;
+        }
+      }
+    }
+    let dark = self.modules.iter().filter(|&&m| m).count();
+    let total = size * size;
+    let deviation = (20 * dark).max(10 * total) - (20 * dark).min(10 * total);
+    penalty + 10 * (deviation / total)
+  }
+}
+
+fn push_bits(bits: &mut Vec<bool>, value: u32, count: usize) {
+  for i in (0..count).rev() {
+    bits.push((value >> i) & 1 == 1);
+  }
+}
+
+fn format_bits(mask: u32) -> u32 {
+  let data = ECC_FORMAT_BITS << 3 | mask;
+  let mut rem = data;
+  for _ in 0..10 {
+    rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+  }
+  (data << 10 | rem) ^ 0x5412
+}
+
+fn alignment_positions(version: usize) -> Vec<usize> {
+  if version == 1 {
+    return Vec::new();
+  }
+  let count = version / 7 + 2;
+  let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+  let mut positions = vec![6];
+  let mut pos = version * 4 + 10;
+  let mut rest = Vec::new();
+  for _ in 0..count - 1 {
+    rest.push(pos);
+    pos -= step;
+  }
+  rest.reverse();
+  positions.extend(rest);
+  positions
+}
+
+fn raw_modules_count(version: usize) -> usize {
+  let mut count = (16 * version + 128) * version + 64;
+  if version >= 2 {
+    let align = version / 7 + 2;
+    count -= (25 * align - 10) * align - 55;
+    if version >= 7 {
+      count -= 36;
+    }
+  }
+  count
+}
+
+fn data_codewords_count(version: usize) -> usize {
+  raw_modules_count(version) / 8 - ECC_CODEWORDS_PER_BLOCK[version] * ECC_BLOCKS[version]
+}
+
+// Splits the data in blocks, appends their error correction codewords and interleaves them.
+fn interleave(version: usize, data: &[u8]) -> Vec<u8> {
+  let blocks_count = ECC_BLOCKS[version];
+  let ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+  let raw_codewords = raw_modules_count(version) / 8;
+  let short_blocks_count = blocks_count - raw_codewords % blocks_count;
+  let short_block_len = raw_codewords / blocks_count;
+  let divisor = rs_divisor(ecc_len);
+
+  let mut blocks = Vec::with_capacity(blocks_count);
+  let mut start = 0;
+  for i in 0..blocks_count {
+    let len = short_block_len - ecc_len + if i < short_blocks_count { 0 } else { 1 };
+    let block = &data[start..start + len];
+    start += len;
+    blocks.push((block.to_vec(), rs_remainder(block, &divisor)));
+  }
+
+  let mut result = Vec::with_capacity(raw_codewords);
+  for i in 0..=short_block_len - ecc_len {
+    for (j, (block, _)) in blocks.iter().enumerate() {
+      if i < short_block_len - ecc_len || j >= short_blocks_count {
+        result.push(block[i]);
+      }
+    }
+  }
+  for i in 0..ecc_len {
+    for (_, ecc) in &blocks {
+      result.push(ecc[i]);
+    }
+  }
+  result
+}
+
+fn gf_multiply(x: u8, y: u8) -> u8 {
+  let mut z: u32 = 0;
+  for i in (0..8).rev() {
+    z = (z << 1) ^ ((z >> 7) * 0x11D);
+    z ^= ((y as u32 >> i) & 1) * x as u32;
+  }
+  z as u8
+}
+
+fn rs_divisor(degree: usize) -> Vec<u8> {
+  let mut result = vec![0; degree];
+  result[degree - 1] = 1;
+  let mut root = 1u8;
+  for _ in 0..degree {
+    for j in 0..degree {
+      result[j] = gf_multiply(result[j], root);
+      if j + 1 < degree {
+        result[j] ^= result[j + 1];
+      }
+    }
+    root = gf_multiply(root, 0x02);
+  }
+  result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+  let mut result = vec![0; divisor.len()];
+  for &b in data {
+    let factor = b ^ result.remove(0);
+    result.push(0);
+    for (r, &d) in result.iter_mut().zip(divisor) {
+      *r ^= gf_multiply(d, factor);
+    }
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_reed_solomon() {
+    let data = [
+      32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+    ];
+    assert_eq!(
+      rs_remainder(&data, &rs_divisor(10)),
+      vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+    );
+  }
+
+  #[test]
+  fn test_format_and_layout() {
+    assert_eq!(format_bits(0), 0b111011111000100);
+    assert_eq!(alignment_positions(7), vec![6, 22, 38]);
+    assert_eq!(data_codewords_count(1), 19);
+    assert_eq!(data_codewords_count(10), 274);
+    let qr = QrCode::encode(b"http://192.168.1.23:8000/").unwrap();
+    assert_eq!(qr.size(), 25);
+    assert!(qr.get(0, 0) && !qr.get(7, 0) && qr.get(8, qr.size() - 8));
+  }
+}
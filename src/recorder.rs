@@ -0,0 +1,186 @@
+//! Records raw input events to a file and replays them later, preserving their timing.
+//!
+//! A session file starts with a few header lines prefixed with `#`, followed by one event per
+//! line: `SECONDS.MICROSECONDS TYPE CODE VALUE`.
+
+use crate::{
+  framebuffer::Display,
+  input::{current_time, seconds, InputEvent},
+};
+use anyhow::{format_err, Context, Error};
+use std::{
+  env,
+  fs::{self, File},
+  io::{LineWriter, Write},
+  path::Path,
+  sync::mpsc::{self, Receiver, Sender},
+  thread,
+  time::Duration,
+};
+
+// Environment variables holding the paths of the session files.
+pub const RECORD_INPUT_VAR: &str = "PLATO_RECORD_INPUT";
+pub const REPLAY_INPUT_VAR: &str = "PLATO_REPLAY_INPUT";
+
+pub struct Recorder {
+  writer: LineWriter<File>,
+}
+
+impl Recorder {
+  pub fn new<P: AsRef<Path>>(path: P, display: Display) -> Result<Recorder, Error> {
+    let file = File::create(path.as_ref())
+      .with_context(|| format!("Can't create session file {}.", path.as_ref().display()))?;
+    let mut writer = LineWriter::new(file);
+    writeln!(
+      writer,
+      "# device {} {}",
+      env::var("PRODUCT").unwrap_or_default(),
+      env::var("MODEL_NUMBER").unwrap_or_default()
+    )?;
+    writeln!(
+      writer,
+      "# display {} {} {}",
+      display.dims.0, display.dims.1, display.rotation
+    )?;
+    Ok(Recorder { writer })
+  }
+
+  pub fn record(&mut self, evt: &InputEvent) -> Result<(), Error> {
+    writeln!(
+      self.writer,
+      "{}.{:06} {} {} {}",
+      evt.time.tv_sec, evt.time.tv_usec, evt.kind, evt.code, evt.value
+    )
+    .map_err(Into::into)
+  }
+}
+
+pub struct Session {
+  pub product: String,
+  pub model_number: String,
+  pub display: Option<Display>,
+  pub events: Vec<InputEvent>,
+}
+
+impl Session {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Session, Error> {
+    let text = fs::read_to_string(path.as_ref())
+      .with_context(|| format!("Can't read session file {}.", path.as_ref().display()))?;
+    let mut session = Session {
+      product: String::new(),
+      model_number: String::new(),
+      display: None,
+      events: Vec::new(),
+    };
+
+    for (index, line) in text.lines().enumerate() {
+      let line = line.trim();
+
+      if line.is_empty() {
+        continue;
+      }
+
+      if line.starts_with('#') {
+        let mut words = line[1..].split_whitespace();
+        match words.next() {
+          Some("device") => {
+            session.product = words.next().unwrap_or_default().to_string();
+            session.model_number = words.next().unwrap_or_default().to_string();
+          },
+          Some("display") => {
+            let values: Vec<i32> = words.filter_map(|w| w.parse().ok()).collect();
+            if values.len() == 3 {
+              session.display = Some(Display {
+                dims: (values[0] as u32, values[1] as u32),
+                rotation: values[2] as i8,
+              });
+            }
+          },
+          _ => (),
+        }
+        continue;
+      }
+
+      let evt = parse_event(line)
+        .ok_or_else(|| format_err!("Invalid event on line {}: {}.", index + 1, line))?;
+      session.events.push(evt);
+    }
+
+    Ok(session)
+  }
+}
+
+fn parse_event(line: &str) -> Option<InputEvent> {
+  let mut words = line.split_whitespace();
+  let mut time = words.next()?.splitn(2, '.');
+  let tv_sec = time.next()?.parse().ok()?;
+  let tv_usec = time.next().unwrap_or("0").parse().ok()?;
+  let kind = words.next()?.parse().ok()?;
+  let code = words.next()?.parse().ok()?;
+  let value = words.next()?.parse().ok()?;
+  Some(InputEvent {
+    time: libc::timeval { tv_sec, tv_usec },
+    kind,
+    code,
+    value,
+  })
+}
+
+// Writes every event received on *rx* to the session file before passing it along.
+pub fn record_raw_events<P: AsRef<Path>>(
+  rx: Receiver<InputEvent>,
+  path: P,
+  display: Display,
+) -> Result<Receiver<InputEvent>, Error> {
+  let mut recorder = Recorder::new(path, display)?;
+  let (ty, ry) = mpsc::channel();
+  thread::spawn(move || {
+    while let Ok(evt) = rx.recv() {
+      recorder
+        .record(&evt)
+        .map_err(|e| eprintln!("Can't record input event: {:#}.", e))
+        .ok();
+      if ty.send(evt).is_err() {
+        break;
+      }
+    }
+  });
+  Ok(ry)
+}
+
+// Sends the events of the session to *tx*, waiting between two events as long as the original
+// user did. The events are stamped with the current time.
+pub fn replay_raw_events(session: Session, tx: Sender<InputEvent>) {
+  thread::spawn(move || {
+    let mut last_time = None;
+    for mut evt in session.events {
+      let time = seconds(evt.time);
+      if let Some(last_time) = last_time {
+        let delay = time - last_time;
+        if delay > 0.0 {
+          thread::sleep(Duration::from_secs_f64(delay));
+        }
+      }
+      last_time = Some(time);
+      evt.time = current_time();
+      if tx.send(evt).is_err() {
+        break;
+      }
+    }
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::parse_event;
+
+  #[test]
+  fn test_parse_event() {
+    let evt = parse_event("1602512345.000042 3 53 764").unwrap();
+    assert_eq!(evt.time.tv_sec, 1_602_512_345);
+    assert_eq!(evt.time.tv_usec, 42);
+    assert_eq!((evt.kind, evt.code, evt.value), (3, 53, 764));
+    assert!(parse_event("1602512345.000042 3 53").is_none());
+    assert!(parse_event("garbage").is_none());
+  }
+}
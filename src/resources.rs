@@ -0,0 +1,62 @@
+//! Keeps an eye on the free space of the storage and on the available memory.
+
+use nix::{
+  sys::statvfs,
+  unistd::{sysconf, SysconfVar},
+};
+use std::{fs, path::Path};
+use walkdir::WalkDir;
+
+pub fn free_space<P: AsRef<Path>>(path: P) -> Option<u64> {
+  statvfs::statvfs(path.as_ref())
+    .map(|info| info.blocks_available() as u64 * info.fragment_size() as u64)
+    .ok()
+}
+
+pub fn available_memory() -> Option<u64> {
+  meminfo_field("MemAvailable:")
+}
+
+pub fn total_memory() -> Option<u64> {
+  meminfo_field("MemTotal:")
+}
+
+fn meminfo_field(name: &str) -> Option<u64> {
+  let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+  meminfo
+    .lines()
+    .find(|line| line.starts_with(name))
+    .and_then(|line| line.split_whitespace().nth(1))
+    .and_then(|kib| kib.parse::<u64>().ok())
+    .map(|kib| kib * 1024)
+}
+
+// The physical memory used by the application.
+pub fn resident_memory() -> Option<u64> {
+  let statm = fs::read_to_string("/proc/self/statm").ok()?;
+  let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+  let page_size = sysconf(SysconfVar::PAGE_SIZE).ok()??;
+  Some(pages * page_size as u64)
+}
+
+// Returns the total size of each entry of the given directory, the largest first.
+pub fn usage_by_directory<P: AsRef<Path>>(path: P) -> Vec<(String, u64)> {
+  let mut usage: Vec<(String, u64)> = match fs::read_dir(path.as_ref()) {
+    Ok(entries) => entries
+      .filter_map(|entry| entry.ok())
+      .map(|entry| {
+        let size = WalkDir::new(entry.path())
+          .into_iter()
+          .filter_map(|e| e.ok())
+          .filter_map(|e| e.metadata().ok())
+          .filter(|md| md.is_file())
+          .map(|md| md.len())
+          .sum();
+        (entry.file_name().to_string_lossy().into_owned(), size)
+      })
+      .collect(),
+    Err(_) => Vec::new(),
+  };
+  usage.sort_by(|a, b| b.1.cmp(&a.1));
+  usage
+}
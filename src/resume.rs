@@ -0,0 +1,96 @@
+//! Saves the screen of the reader when the device goes to sleep or is powered off, so that the
+//! page can be shown as soon as the application starts, while the document is being opened.
+
+use crate::{
+  font::Fonts,
+  framebuffer::{Framebuffer, Pixmap, UpdateMode},
+  geom::Point,
+  helpers::{load_json, save_json},
+  metadata::{Info, ReaderInfo},
+  view::{intermission::Intermission, reader::Reader, View},
+};
+use anyhow::{format_err, Error};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+
+const SNAPSHOT_PATH: &str = ".resume.png";
+const STATE_PATH: &str = ".resume.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ResumeState {
+  info: Info,
+  // The reading state isn't serialized with the document's metadata.
+  reader: Option<ReaderInfo>,
+}
+
+// Saves the screen and the reading position of *view*, if it's a reader. Removes the previous
+// snapshot otherwise.
+pub fn save_snapshot(
+  view: &dyn View,
+  fb: &dyn Framebuffer,
+  fonts: &mut Fonts,
+) -> Result<(), Error> {
+  let info = match view.downcast_ref::<Reader>().and_then(Reader::resume_info) {
+    Some(info) => info,
+    None => {
+      clear_snapshot();
+      return Ok(());
+    },
+  };
+  let (width, height) = fb.dims();
+  let mut pixmap = Pixmap::new(width, height);
+  render_tree(view, &mut pixmap, fonts);
+  pixmap.save(SNAPSHOT_PATH)?;
+  let state = ResumeState {
+    reader: info.reader.clone(),
+    info,
+  };
+  save_json(&state, STATE_PATH)
+}
+
+pub fn clear_snapshot() {
+  fs::remove_file(SNAPSHOT_PATH).ok();
+  fs::remove_file(STATE_PATH).ok();
+}
+
+// Shows the saved screen and returns the document to open. The snapshot is only used once.
+pub fn load_snapshot(fb: &mut dyn Framebuffer) -> Option<Info> {
+  let state: ResumeState = load_json(STATE_PATH).ok()?;
+  let pixmap = load_pixmap(fb.dims());
+  clear_snapshot();
+
+  match pixmap {
+    Ok(pixmap) => {
+      let rect = fb.rect();
+      fb.draw_pixmap(&pixmap, Point::default());
+      fb.update(&rect, UpdateMode::Full).ok();
+    },
+    Err(e) => eprintln!("Can't load the resume snapshot: {}.", e),
+  }
+
+  let mut info = state.info;
+  info.reader = state.reader;
+  Some(info)
+}
+
+fn load_pixmap(dims: (u32, u32)) -> Result<Pixmap, Error> {
+  let decoder = png::Decoder::new(File::open(SNAPSHOT_PATH)?);
+  let (output, mut reader) = decoder.read_info()?;
+  // The snapshot was taken in another orientation.
+  if (output.width, output.height) != dims {
+    return Err(format_err!("unexpected dimensions"));
+  }
+  let mut pixmap = Pixmap::new(output.width, output.height);
+  reader.next_frame(pixmap.data_mut())?;
+  Ok(pixmap)
+}
+
+fn render_tree(view: &dyn View, fb: &mut dyn Framebuffer, fonts: &mut Fonts) {
+  if view.is::<Intermission>() {
+    return;
+  }
+  view.render(fb, *view.rect(), fonts);
+  for child in view.children() {
+    render_tree(child.as_ref(), fb, fonts);
+  }
+}
@@ -1,5 +1,5 @@
 use anyhow::Error;
-use chrono::{Datelike, Duration, Timelike, Utc};
+use chrono::{DateTime, Datelike, Timelike, Utc};
 use nix::{ioctl_none, ioctl_read, ioctl_write_ptr};
 use std::{fs::File, mem, os::unix::io::AsRawFd, path::Path};
 
@@ -52,8 +52,7 @@ impl Rtc {
     }
   }
 
-  pub fn set_alarm(&self, days: u8) -> Result<i32, Error> {
-    let wt = Utc::now() + Duration::days(days as i64);
+  pub fn set_alarm(&self, wt: DateTime<Utc>) -> Result<i32, Error> {
     let rwa = RtcWkalrm {
       enabled: 1,
       pending: 0,
@@ -0,0 +1,123 @@
+//! Scripts the battery, the light sensor and the network of the emulator over time.
+//!
+//! A scenario file holds one step per line: `SECONDS COMMAND [VALUE]`, where *SECONDS* is the
+//! time elapsed since the emulator started. The commands are `battery PERCENT`, `charging`,
+//! `discharging`, `charged`, `light LEVEL`, `net-up` and `net-down`. Lines starting with `#`
+//! are ignored.
+
+use crate::battery::Status;
+use anyhow::{format_err, Context, Error};
+use std::{
+  fs,
+  path::Path,
+  time::{Duration, Instant},
+};
+
+pub const SCENARIO_ARG: &str = "--scenario";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Step {
+  Capacity(f32),
+  Status(Status),
+  Light(u16),
+  Network(bool),
+}
+
+pub struct Scenario {
+  start: Instant,
+  // The steps that are still to come, the next one last.
+  steps: Vec<(Duration, Step)>,
+}
+
+fn parse_step(line: &str) -> Result<(Duration, Step), Error> {
+  let mut parts = line.split_whitespace();
+  let secs = parts
+    .next()
+    .ok_or_else(|| format_err!("missing time"))?
+    .parse::<f64>()
+    .context("invalid time")?;
+  let command = parts.next().ok_or_else(|| format_err!("missing command"))?;
+  let value = parts.next();
+  let step = match command {
+    "battery" => Step::Capacity(
+      value
+        .ok_or_else(|| format_err!("missing capacity"))?
+        .parse::<f32>()
+        .context("invalid capacity")?
+        .max(0.0)
+        .min(100.0),
+    ),
+    "charging" => Step::Status(Status::Charging),
+    "discharging" => Step::Status(Status::Discharging),
+    "charged" => Step::Status(Status::Charged),
+    "light" => Step::Light(
+      value
+        .ok_or_else(|| format_err!("missing level"))?
+        .parse::<u16>()
+        .context("invalid level")?,
+    ),
+    "net-up" => Step::Network(true),
+    "net-down" => Step::Network(false),
+    _ => return Err(format_err!("unknown command: {}", command)),
+  };
+  Ok((Duration::from_secs_f64(secs.max(0.0)), step))
+}
+
+impl Scenario {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Scenario, Error> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+      .with_context(|| format!("can't read scenario {}", path.display()))?;
+    Scenario::parse(&text)
+  }
+
+  pub fn parse(text: &str) -> Result<Scenario, Error> {
+    let mut steps = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      steps.push(parse_step(line).with_context(|| format!("line {}", index + 1))?);
+    }
+    steps.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(Scenario {
+      start: Instant::now(),
+      steps,
+    })
+  }
+
+  // Removes and returns the steps whose time has come.
+  pub fn due(&mut self) -> Vec<Step> {
+    let elapsed = self.start.elapsed();
+    let mut steps = Vec::new();
+    while self.steps.last().map_or(false, |(t, _)| *t <= elapsed) {
+      if let Some((_, step)) = self.steps.pop() {
+        steps.push(step);
+      }
+    }
+    steps
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Scenario, Step};
+  use crate::battery::Status;
+
+  #[test]
+  fn test_parse() {
+    let mut scenario = Scenario::parse(
+      "# Low battery while unplugged.\n\
+       5 battery 15\n\
+       0 discharging\n\
+       0.5 light 120\n\
+       30 net-down\n",
+    )
+    .unwrap();
+    assert_eq!(scenario.due(), vec![Step::Status(Status::Discharging)]);
+    assert_eq!(scenario.steps.len(), 3);
+    assert!(Scenario::parse("3 battery").is_err());
+    assert!(Scenario::parse("3 explode").is_err());
+  }
+}
@@ -0,0 +1,263 @@
+//! Runs the tasks of the settings at the times given by their cron-like schedules.
+
+use crate::{
+  app::Context,
+  helpers::save_toml,
+  library::{METADATA_FILENAME, READING_STATES_DIRNAME},
+  phrasebook::PHRASEBOOK_PATH,
+  settings::{ScheduledTask, SETTINGS_PATH},
+};
+use anyhow::{format_err, Error};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+use std::{fs, path::PathBuf};
+
+pub const BACKUPS_DIRNAME: &str = ".backups";
+// The number of backups kept.
+const BACKUPS_COUNT: usize = 4;
+// A schedule that doesn't match any day within this number of days never matches.
+const MAX_DAYS: usize = 4 * 366;
+
+// The values allowed by one of the fields of a schedule.
+#[derive(Debug, Copy, Clone)]
+struct Field {
+  bits: u64,
+  // Whether the field starts with a star: the day fields are then ignored.
+  any: bool,
+}
+
+impl Field {
+  fn parse(text: &str, min: u32, max: u32) -> Result<Field, Error> {
+    let mut bits = 0;
+    for part in text.split(',') {
+      let mut parts = part.splitn(2, '/');
+      let range = parts.next().unwrap_or_default();
+      let step = match parts.next() {
+        Some(s) => s.parse::<u32>()?,
+        None => 1,
+      };
+      if step == 0 {
+        return Err(format_err!("null step in {}", part));
+      }
+      let (start, end) = if range == "*" {
+        (min, max)
+      } else if let Some(index) = range.find('-') {
+        (range[..index].parse()?, range[index + 1..].parse()?)
+      } else {
+        let value = range.parse()?;
+        (value, if step > 1 { max } else { value })
+      };
+      if start < min || end > max || start > end {
+        return Err(format_err!("invalid range {}", part));
+      }
+      for value in (start..=end).step_by(step as usize) {
+        bits |= 1 << value;
+      }
+    }
+    Ok(Field {
+      bits,
+      any: text.starts_with('*'),
+    })
+  }
+
+  fn contains(&self, value: u32) -> bool {
+    self.bits & (1 << value) != 0
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+  minutes: Field,
+  hours: Field,
+  days: Field,
+  months: Field,
+  weekdays: Field,
+}
+
+impl Schedule {
+  pub fn parse(text: &str) -> Result<Schedule, Error> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() != 5 {
+      return Err(format_err!("expected five fields in {:?}", text));
+    }
+    let mut weekdays = Field::parse(fields[4], 0, 7)?;
+    // Sunday is either 0 or 7.
+    if weekdays.contains(7) {
+      weekdays.bits |= 1;
+    }
+    Ok(Schedule {
+      minutes: Field::parse(fields[0], 0, 59)?,
+      hours: Field::parse(fields[1], 0, 23)?,
+      days: Field::parse(fields[2], 1, 31)?,
+      months: Field::parse(fields[3], 1, 12)?,
+      weekdays,
+    })
+  }
+
+  fn matches_day(&self, date: NaiveDate) -> bool {
+    if !self.months.contains(date.month()) {
+      return false;
+    }
+    let day = self.days.contains(date.day());
+    let weekday = self
+      .weekdays
+      .contains(date.weekday().num_days_from_sunday());
+    // When both day fields are restricted, matching either is enough.
+    match (self.days.any, self.weekdays.any) {
+      (false, false) => day || weekday,
+      (false, true) => day,
+      (true, false) => weekday,
+      (true, true) => true,
+    }
+  }
+
+  // The first matching minute after *time*.
+  pub fn next_after<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let start = (time.clone() + Duration::minutes(1)).naive_local();
+    let mut date = start.date();
+    for i in 0..MAX_DAYS {
+      if self.matches_day(date) {
+        let (first_hour, first_minute) = if i == 0 {
+          (start.hour(), start.minute())
+        } else {
+          (0, 0)
+        };
+        for hour in (first_hour..24).filter(|&h| self.hours.contains(h)) {
+          let first_minute = if hour == first_hour { first_minute } else { 0 };
+          for minute in (first_minute..60).filter(|&m| self.minutes.contains(m)) {
+            let naive = date.and_hms_opt(hour, minute, 0)?;
+            if let Some(time) = time.timezone().from_local_datetime(&naive).earliest() {
+              return Some(time);
+            }
+          }
+        }
+      }
+      date = date.succ_opt()?;
+    }
+    None
+  }
+}
+
+pub struct Scheduler {
+  entries: Vec<(Schedule, ScheduledTask)>,
+  // The tasks scheduled up to this time have been run.
+  checked: DateTime<Local>,
+}
+
+impl Scheduler {
+  pub fn new(tasks: &[ScheduledTask]) -> Scheduler {
+    let entries = tasks
+      .iter()
+      .filter_map(|task| {
+        Schedule::parse(&task.schedule)
+          .map_err(|e| eprintln!("Can't parse schedule {:?}: {:#}.", task.schedule, e))
+          .ok()
+          .map(|schedule| (schedule, task.clone()))
+      })
+      .collect();
+    Scheduler {
+      entries,
+      checked: Local::now(),
+    }
+  }
+
+  pub fn next_run(&self) -> Option<DateTime<Local>> {
+    self
+      .entries
+      .iter()
+      .filter_map(|(schedule, _)| schedule.next_after(&self.checked))
+      .min()
+  }
+
+  // The next run of the tasks that wake the device up.
+  pub fn next_wake_up(&self) -> Option<DateTime<Local>> {
+    self
+      .entries
+      .iter()
+      .filter(|(_, task)| task.wake_up)
+      .filter_map(|(schedule, _)| schedule.next_after(&self.checked))
+      .min()
+  }
+
+  // Returns the tasks that were due since the last call. A task that missed several runs,
+  // while the device was asleep, is only returned once.
+  pub fn due_tasks(&mut self, now: DateTime<Local>) -> Vec<ScheduledTask> {
+    let checked = self.checked;
+    self.checked = now;
+    self
+      .entries
+      .iter()
+      .filter(|(schedule, _)| schedule.next_after(&checked).map_or(false, |t| t <= now))
+      .map(|(_, task)| task.clone())
+      .collect()
+  }
+}
+
+// Copies the settings, the phrasebook and the metadata of the current library in a new
+// directory of the library's backups, and removes the oldest backups.
+pub fn backup(context: &mut Context) -> Result<PathBuf, Error> {
+  save_toml(&context.settings, SETTINGS_PATH)?;
+  context.library.flush()?;
+  let home = &context.library.home;
+  let backups = home.join(BACKUPS_DIRNAME);
+  let dir = backups.join(Local::now().format("%Y%m%d_%H%M%S").to_string());
+  fs::create_dir_all(&dir)?;
+
+  for name in &[SETTINGS_PATH, PHRASEBOOK_PATH] {
+    if fs::metadata(name).is_ok() {
+      fs::copy(name, dir.join(name))?;
+    }
+  }
+  if home.join(METADATA_FILENAME).exists() {
+    fs::copy(home.join(METADATA_FILENAME), dir.join(METADATA_FILENAME))?;
+  }
+  let states = home.join(READING_STATES_DIRNAME);
+  if states.is_dir() {
+    fs::create_dir_all(dir.join(READING_STATES_DIRNAME))?;
+    for entry in fs::read_dir(&states)? {
+      let path = entry?.path();
+      if let Some(name) = path.file_name().filter(|_| path.is_file()) {
+        fs::copy(&path, dir.join(READING_STATES_DIRNAME).join(name))?;
+      }
+    }
+  }
+
+  let mut names: Vec<PathBuf> = fs::read_dir(&backups)?
+    .filter_map(|entry| entry.ok().map(|e| e.path()))
+    .filter(|path| path.is_dir())
+    .collect();
+  names.sort();
+  while names.len() > BACKUPS_COUNT {
+    fs::remove_dir_all(names.remove(0))?;
+  }
+
+  Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Schedule;
+  use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+  fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+    let naive = NaiveDate::from_ymd_opt(year, month, day)
+      .and_then(|date| date.and_hms_opt(hour, minute, second))
+      .unwrap();
+    Utc.from_utc_datetime(&naive)
+  }
+
+  #[test]
+  fn test_next_after() {
+    let time = utc(2021, 3, 5, 6, 40, 12);
+    let schedule = Schedule::parse("30 6 * * *").unwrap();
+    assert_eq!(schedule.next_after(&time), Some(utc(2021, 3, 6, 6, 30, 0)));
+    let schedule = Schedule::parse("*/15 * * * 1-5").unwrap();
+    assert_eq!(schedule.next_after(&time), Some(utc(2021, 3, 5, 6, 45, 0)));
+    // March 5, 2021 is a Friday.
+    let schedule = Schedule::parse("0 9 * * 0,6").unwrap();
+    assert_eq!(schedule.next_after(&time), Some(utc(2021, 3, 6, 9, 0, 0)));
+    let schedule = Schedule::parse("0 0 29 2 *").unwrap();
+    assert_eq!(schedule.next_after(&time), Some(utc(2024, 2, 29, 0, 0, 0)));
+    assert!(Schedule::parse("60 * * * *").is_err());
+    assert!(Schedule::parse("* * *").is_err());
+  }
+}
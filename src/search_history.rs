@@ -0,0 +1,67 @@
+//! The queries of the search inputs, kept across sessions, along with the saved searches.
+
+use crate::{
+  helpers::{load_json, save_json_atomically},
+  view::ViewId,
+};
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+pub const SEARCH_HISTORY_PATH: &str = "SearchHistory.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchHistory {
+  // The recent queries of each input, most recent first.
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub recent: BTreeMap<String, Vec<String>>,
+  // The queries pinned by the user, in the order they were saved.
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub saved: BTreeMap<String, Vec<String>>,
+}
+
+const INPUTS: [(ViewId, &str); 3] = [
+  (ViewId::HomeSearchInput, "home"),
+  (ViewId::ReaderSearchInput, "reader"),
+  (ViewId::DictionarySearchInput, "dictionary"),
+];
+
+// The name under which the queries of a search input are stored.
+pub fn input_name(id: ViewId) -> Option<&'static str> {
+  INPUTS.iter().find(|(v, _)| *v == id).map(|(_, name)| *name)
+}
+
+pub fn input_id(name: &str) -> Option<ViewId> {
+  INPUTS.iter().find(|(_, n)| *n == name).map(|(id, _)| *id)
+}
+
+pub fn load_search_history<P: AsRef<Path>>(path: P) -> Result<SearchHistory, Error> {
+  if !path.as_ref().exists() {
+    return Ok(SearchHistory::default());
+  }
+  load_json(path)
+}
+
+pub fn save_search_history<P: AsRef<Path>>(history: &SearchHistory, path: P) -> Result<(), Error> {
+  save_json_atomically(history, path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{input_id, input_name};
+  use crate::view::ViewId;
+
+  #[test]
+  fn test_input_names() {
+    for id in &[
+      ViewId::HomeSearchInput,
+      ViewId::ReaderSearchInput,
+      ViewId::DictionarySearchInput,
+    ] {
+      assert_eq!(input_name(*id).and_then(input_id), Some(*id));
+    }
+    assert_eq!(input_name(ViewId::CalculatorInput), None);
+    assert_eq!(input_id("calculator"), None);
+  }
+}
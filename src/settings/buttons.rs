@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+// The action performed by a page turn button. *Default* keeps the behavior of the application.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ButtonAction {
+  Default,
+  None,
+  PreviousPage,
+  NextPage,
+  PreviousChapter,
+  NextChapter,
+  Undo,
+  Redo,
+  Back,
+  ToggleFrontlight,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ButtonMapping {
+  pub backward: ButtonAction,
+  pub forward: ButtonAction,
+}
+
+impl Default for ButtonMapping {
+  fn default() -> Self {
+    ButtonMapping {
+      backward: ButtonAction::Default,
+      forward: ButtonAction::Default,
+    }
+  }
+}
+
+// The actions of the page turn buttons, for each application.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ButtonMappings {
+  pub home: ButtonMapping,
+  pub reader: ButtonMapping,
+  pub sketch: ButtonMapping,
+  pub dictionary: ButtonMapping,
+  pub calculator: ButtonMapping,
+  pub feeds: ButtonMapping,
+}
+
+impl Default for ButtonMappings {
+  fn default() -> Self {
+    ButtonMappings {
+      home: ButtonMapping::default(),
+      reader: ButtonMapping::default(),
+      sketch: ButtonMapping {
+        backward: ButtonAction::Undo,
+        forward: ButtonAction::Redo,
+      },
+      dictionary: ButtonMapping::default(),
+      calculator: ButtonMapping::default(),
+      feeds: ButtonMapping::default(),
+    }
+  }
+}
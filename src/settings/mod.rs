@@ -1,10 +1,24 @@
+mod buttons;
+mod night;
+mod power;
 mod preset;
+mod tap_zones;
 
 use crate::{
   color::BLACK,
   device::CURRENT_DEVICE,
+  framebuffer::DitherMode,
   frontlight::LightLevels,
-  metadata::{SortMethod, TextAlign},
+  geom::DiagDir,
+  metadata::{
+    DirectoryLayout,
+    PageMargins,
+    ReadingSpeed,
+    SortMethod,
+    TextAlign,
+    TextAlignOverride,
+    ZoomMode,
+  },
   unit::mm_to_px,
 };
 use fxhash::{FxHashMap, FxHashSet};
@@ -16,7 +30,13 @@ use std::{
   path::PathBuf,
 };
 
-pub use self::preset::{guess_frontlight, LightPreset};
+pub use self::{
+  buttons::{ButtonAction, ButtonMapping, ButtonMappings},
+  night::{NightFilterMode, NightFilterSettings},
+  power::{PowerProfile, PowerSettings},
+  preset::{guess_frontlight, LightPreset},
+  tap_zones::{TapAction, TapZones, TAP_ACTIONS},
+};
 
 pub const SETTINGS_PATH: &str = "Settings.toml";
 pub const DEFAULT_FONT_PATH: &str = "/mnt/onboard/fonts";
@@ -59,21 +79,43 @@ impl fmt::Display for ButtonScheme {
 pub struct Settings {
   pub selected_library: usize,
   pub keyboard_layout: String,
+  // The code of the language of the user interface, English when empty.
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub language: String,
+  // Show a row of word suggestions above the keyboard.
+  pub keyboard_suggestions: bool,
   pub frontlight: bool,
+  // Adjust the frontlight levels to the ambient light, using the presets.
+  pub auto_frontlight: bool,
   pub wifi: bool,
   pub sleep_cover: bool,
+  // Only show a generic image on the sleeping and powered off screens.
+  pub private_sleep_screen: bool,
+  // Show the last page of the reader when the application starts.
+  pub instant_resume: bool,
   pub auto_share: bool,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub rotation_lock: Option<RotationLock>,
   pub button_scheme: ButtonScheme,
+  pub buttons: ButtonMappings,
   pub auto_suspend: u8,
   pub auto_power_off: u8,
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub libraries: Vec<LibrarySettings>,
   #[serde(skip_serializing_if = "FxHashMap::is_empty")]
   pub intermission_images: FxHashMap<String, PathBuf>,
+  pub intermission_quotes: QuoteSource,
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub frontlight_presets: Vec<LightPreset>,
+  pub preset_gesture: GlobalGesture,
+  pub palette_gesture: GlobalGesture,
+  pub undo_gesture: GlobalGesture,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub scheduled_tasks: Vec<ScheduledTask>,
+  // How the screen is refreshed when a view replaces another.
+  pub transitions: TransitionIntensity,
+  // Record the use of a few features in a local file, to browse them in the insights.
+  pub usage_insights: bool,
   pub home: HomeSettings,
   pub reader: ReaderSettings,
   pub import: ImportSettings,
@@ -81,7 +123,18 @@ pub struct Settings {
   pub sketch: SketchSettings,
   pub calculator: CalculatorSettings,
   pub battery: BatterySettings,
+  pub storage: StorageSettings,
+  pub memory: MemorySettings,
+  pub dithering: DitheringSettings,
+  pub night_filter: NightFilterSettings,
+  pub power: PowerSettings,
+  pub feeds: FeedsSettings,
+  pub opds: OpdsSettings,
+  pub cloud_sync: CloudSyncSettings,
+  pub feedback: FeedbackSettings,
   pub frontlight_levels: LightLevels,
+  // The reading speed measured across all the books.
+  pub reading_speed: ReadingSpeed,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -98,10 +151,19 @@ pub struct LibrarySettings {
   pub path: PathBuf,
   pub mode: LibraryMode,
   pub sort_method: SortMethod,
+  // Overrides the default order of the sort method.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub reverse_order: Option<bool>,
   pub first_column: FirstColumn,
   pub second_column: SecondColumn,
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub hooks: Vec<Hook>,
+  // Takes precedence over the global intermission images.
+  #[serde(skip_serializing_if = "FxHashMap::is_empty")]
+  pub intermission_images: FxHashMap<String, PathBuf>,
+  // The layouts given to the scanned books of a directory when they're first opened.
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub directory_layouts: BTreeMap<PathBuf, DirectoryLayout>,
 }
 
 impl Default for LibrarySettings {
@@ -113,9 +175,12 @@ impl Default for LibrarySettings {
         .unwrap_or_else(|| PathBuf::from("/")),
       mode: LibraryMode::Database,
       sort_method: SortMethod::Opened,
+      reverse_order: None,
       first_column: FirstColumn::TitleAndAuthor,
       second_column: SecondColumn::Progress,
       hooks: Vec::new(),
+      intermission_images: FxHashMap::default(),
+      directory_layouts: BTreeMap::new(),
     }
   }
 }
@@ -127,7 +192,58 @@ pub struct ImportSettings {
   pub startup_trigger: bool,
   pub traverse_hidden: bool,
   pub extract_epub_metadata: bool,
+  // Read the metadata from the OPF sidecars written by Calibre.
+  pub extract_opf_metadata: bool,
   pub allowed_kinds: FxHashSet<String>,
+  // The extensions of the files that can be imported, any if empty.
+  #[serde(skip_serializing_if = "FxHashSet::is_empty")]
+  pub allowed_extensions: FxHashSet<String>,
+  // The size, in bytes, below which files are ignored.
+  pub min_file_size: u64,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub rules: Vec<ImportRule>,
+  // Directories outside of the library whose documents are brought into it.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub sources: Vec<PathBuf>,
+  pub transfer: TransferMode,
+  // Only report what would be imported.
+  pub dry_run: bool,
+  pub conversion: ConversionSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ConversionSettings {
+  // Called with the paths of the document and of the EPUB to create.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub program: Option<PathBuf>,
+  // Given to the program after the paths.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub arguments: Vec<String>,
+  // The extensions of the documents to convert.
+  pub extensions: Vec<String>,
+  // The time, in seconds, after which a conversion is abandoned.
+  pub timeout: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ImportRule {
+  // Relative to the library's home, unless absolute.
+  pub directory: PathBuf,
+  // Globs matched against the paths relative to the directory.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub include: Vec<String>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransferMode {
+  Copy,
+  Move,
+  Link,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +253,22 @@ pub struct DictionarySettings {
   pub font_size: f32,
   #[serde(skip_serializing_if = "BTreeMap::is_empty")]
   pub languages: BTreeMap<String, Vec<String>>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub groups: Vec<DictionaryGroup>,
+  // The URL of the JSON list of the dictionaries that can be installed.
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub index_url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DictionaryGroup {
+  pub name: String,
+  // The names of the dictionaries, searched in this order.
+  pub dictionaries: Vec<String>,
+  // The group is selected by default when looking up words from books in these languages.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub languages: Vec<String>,
 }
 
 impl Default for DictionarySettings {
@@ -145,6 +277,8 @@ impl Default for DictionarySettings {
       font_size: 11.0,
       margin_width: 4,
       languages: BTreeMap::new(),
+      groups: Vec::new(),
+      index_url: String::new(),
     }
   }
 }
@@ -157,6 +291,14 @@ pub struct SketchSettings {
   pub pen: Pen,
 }
 
+// The actions confirmed by a click or a vibration, on the devices that can emit them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FeedbackSettings {
+  pub page_turns: bool,
+  pub key_presses: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct CalculatorSettings {
@@ -197,6 +339,15 @@ impl Default for SketchSettings {
   }
 }
 
+impl Default for FeedbackSettings {
+  fn default() -> Self {
+    FeedbackSettings {
+      page_turns: true,
+      key_presses: false,
+    }
+  }
+}
+
 impl Default for CalculatorSettings {
   fn default() -> Self {
     CalculatorSettings {
@@ -214,10 +365,68 @@ pub struct Columns {
   second: SecondColumn,
 }
 
+// Where the quotes shown on the intermission screens come from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuoteSource {
+  None,
+  File,
+  Highlights,
+  All,
+}
+
+// The gestures that cycle through the frontlight presets, show the command palette and undo
+// the last destructive action.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GlobalGesture {
+  None,
+  BottomLeftMultiCorner,
+  BottomRightMultiCorner,
+  NorthMultiArrow,
+}
+
+impl Settings {
+  // Only the first of the actions bound to the same global gesture would ever be triggered:
+  // the gesture is unbound from the following ones.
+  pub fn unbind_duplicate_gestures(&mut self) {
+    let mut bound = Vec::new();
+    for (key, gesture) in [
+      ("preset-gesture", &mut self.preset_gesture),
+      ("palette-gesture", &mut self.palette_gesture),
+      ("undo-gesture", &mut self.undo_gesture),
+    ]
+    .iter_mut()
+    {
+      if **gesture == GlobalGesture::None {
+        continue;
+      }
+      if bound.contains(&**gesture) {
+        eprintln!("The gesture of {} is already bound to another action.", key);
+        **gesture = GlobalGesture::None;
+      } else {
+        bound.push(**gesture);
+      }
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionIntensity {
+  // The whole screen is refreshed at once.
+  None,
+  // The bars and the content are refreshed separately.
+  Regional,
+  // The bars are refreshed first, and the content once they're displayed.
+  Sequenced,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum FirstColumn {
   TitleAndAuthor,
+  TitleAndStatus,
   FileName,
 }
 
@@ -225,7 +434,38 @@ pub enum FirstColumn {
 #[serde(rename_all = "kebab-case")]
 pub enum SecondColumn {
   Progress,
+  Status,
   Year,
+  TimeLeft,
+  LastOpened,
+  FileSize,
+  Format,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CitationStyle {
+  Apa,
+  Mla,
+  Bibtex,
+}
+
+impl CitationStyle {
+  pub fn label(self) -> &'static str {
+    match self {
+      CitationStyle::Apa => "APA",
+      CitationStyle::Mla => "MLA",
+      CitationStyle::Bibtex => "BibTeX",
+    }
+  }
+
+  // The extension of the file the citations are appended to.
+  pub fn extension(self) -> &'static str {
+    match self {
+      CitationStyle::Bibtex => "bib",
+      _ => "txt",
+    }
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -250,11 +490,51 @@ impl Default for Hook {
   }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScheduledAction {
+  FetchFeeds,
+  Sync,
+  Backup,
+  LoadPreset,
+  Run,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ScheduledTask {
+  // The minute, hour, day of the month, month and day of the week, as in a crontab.
+  pub schedule: String,
+  pub action: ScheduledAction,
+  // The index of the frontlight preset of *load-preset*.
+  pub preset: usize,
+  // The program started by *run*.
+  pub program: Option<PathBuf>,
+  // Wake the device up when it's asleep.
+  pub wake_up: bool,
+}
+
+impl Default for ScheduledTask {
+  fn default() -> Self {
+    ScheduledTask {
+      schedule: "0 0 * * *".to_string(),
+      action: ScheduledAction::Backup,
+      preset: 0,
+      program: None,
+      wake_up: false,
+    }
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct HomeSettings {
   pub address_bar: bool,
+  // Show the collections of the library above the books.
+  pub collections_bar: bool,
   pub navigation_bar: bool,
+  // Show the titles of the sortable columns above the books.
+  pub column_headers: bool,
   pub max_levels: usize,
   pub max_trash_size: u64,
 }
@@ -270,13 +550,137 @@ pub struct RefreshRateSettings {
 #[serde(default, rename_all = "kebab-case")]
 pub struct ReaderSettings {
   pub finished: FinishedAction,
+  pub auto_status: AutoStatusSettings,
+  pub auto_save: AutoSaveSettings,
+  pub header: HeaderSettings,
   pub font_path: String,
+  // Additional directories searched for fonts, e.g. on the SD card.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub font_dirs: Vec<PathBuf>,
+  // The style names used for the variants of the given families.
+  #[serde(skip_serializing_if = "FxHashMap::is_empty")]
+  pub font_styles: FxHashMap<String, StyleMapping>,
   pub font_family: String,
   pub font_size: f32,
   pub text_align: TextAlign,
+  pub text_align_override: TextAlignOverride,
   pub margin_width: i32,
+  // Supersedes the margin width when the edges have different margins.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub margins: Option<PageMargins>,
   pub line_height: f32,
+  pub typography: TypographySettings,
+  // Show the buttons that step the frontlight intensity in the top bar.
+  pub frontlight_buttons: bool,
+  // The intensity step, in percents, of the frontlight buttons.
+  pub frontlight_step: f32,
+  pub citation_style: CitationStyle,
+  // Where the annotations are exported, relative to the library. Next to the book when unset.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub annotations_directory: Option<PathBuf>,
   pub refresh_rate: RefreshRateSettings,
+  pub tap_zones: TapZones,
+  // What happens when a book with front matter is opened for the first time.
+  pub skip_front_matter: SkipFrontMatter,
+  // The zoom modes cycled through by the *next-zoom-preset* tap action.
+  pub zoom_presets: Vec<ZoomMode>,
+  // The corner that toggles the bookmark of a page, and where its dog-ear is drawn.
+  pub bookmark_corner: DiagDir,
+  pub tts: TtsSettings,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipFrontMatter {
+  // Offers to go to the start of the text.
+  Ask,
+  Always,
+  Never,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TypographySettings {
+  // Lets the punctuation that ends a justified line hang into the margin.
+  pub hanging_punctuation: bool,
+  // Avoids leaving the first or the last line of a paragraph alone on a page.
+  pub widow_orphan_control: bool,
+  // The maximum number of consecutive lines ending with a hyphen, 0 means no limit.
+  pub max_hyphenated_lines: usize,
+  // The words with fewer letters aren't hyphenated.
+  pub min_hyphenated_length: usize,
+  // How much the spaces of a justified line can stretch before hyphenation is tried.
+  pub stretch_tolerance: f32,
+}
+
+impl Default for TypographySettings {
+  fn default() -> Self {
+    TypographySettings {
+      hanging_punctuation: false,
+      widow_orphan_control: false,
+      max_hyphenated_lines: 0,
+      min_hyphenated_length: 0,
+      stretch_tolerance: 1.26,
+    }
+  }
+}
+
+// When a style isn't given, it's guessed from the style names of the family's files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct StyleMapping {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub regular: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub italic: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub bold: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub bold_italic: Option<String>,
+}
+
+// When the reading state of the current document is saved, besides closing it.
+// The line drawn above the page, the header is hidden when both parts are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HeaderSettings {
+  pub show_title: bool,
+  pub show_chapter: bool,
+  // Relative to the height of the small bars.
+  pub font_scale: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TtsSettings {
+  // The speech synthesizer, which reads the text on its standard input.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub program: Option<PathBuf>,
+  // `{speed}` and `{voice}` are replaced by the values below.
+  pub arguments: Vec<String>,
+  // In words per minute.
+  pub speed: u32,
+  pub voice: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AutoSaveSettings {
+  // In minutes, zero disables the periodic saves.
+  pub interval: u64,
+  // Save after this many page turns, zero disables it.
+  pub page_turns: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AutoStatusSettings {
+  // Keep the *Reading* status of a book opened for the first time,
+  // even if the reader didn't leave the initial location.
+  pub reading_on_open: bool,
+  pub finished_on_end: bool,
+  // Percentage of the book after which it's considered finished.
+  pub finished_threshold: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -286,6 +690,87 @@ pub struct BatterySettings {
   pub power_off: f32,
 }
 
+// The sizes are in bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct StorageSettings {
+  pub warn: u64,
+  // New documents aren't imported or downloaded below this amount of free space.
+  pub block: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MemorySettings {
+  pub warn: u64,
+}
+
+// How the gray levels are reduced for each kind of image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DitheringSettings {
+  // The images shown when the device is suspended, powered off or shared.
+  pub covers: DitherMode,
+  // The images embedded in reflowable documents.
+  pub images: DitherMode,
+  // The pages of the fixed-layout documents.
+  pub pages: DitherMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FeedsSettings {
+  // The directory, relative to the library, where the entries are saved.
+  pub directory: PathBuf,
+  // The entries older than this number of days are removed. Zero disables the removal.
+  pub max_age: u16,
+  // Don't remove the old entries that weren't read.
+  pub keep_unread: bool,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub sources: Vec<FeedSource>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FeedSource {
+  // Overrides the title of the feed.
+  pub name: String,
+  pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct OpdsSettings {
+  // The directory, relative to the library, where the books are downloaded.
+  pub directory: PathBuf,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub catalogs: Vec<OpdsCatalog>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct OpdsCatalog {
+  pub name: String,
+  pub url: String,
+  // The credentials of the catalogs that require an HTTP authentication.
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub username: String,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CloudSyncSettings {
+  // The URL of the WebDAV folder the files are uploaded to, the sync is disabled if it's empty.
+  pub url: String,
+  pub username: String,
+  pub password: String,
+  pub sketches: bool,
+  pub annotations: bool,
+  pub phrasebook: bool,
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum FinishedAction {
@@ -302,11 +787,56 @@ impl Default for RefreshRateSettings {
   }
 }
 
+impl Default for HeaderSettings {
+  fn default() -> Self {
+    HeaderSettings {
+      show_title: false,
+      show_chapter: false,
+      font_scale: 1.0,
+    }
+  }
+}
+
+impl Default for TtsSettings {
+  fn default() -> Self {
+    TtsSettings {
+      program: None,
+      arguments: ["-s", "{speed}", "-v", "{voice}", "--stdin"]
+        .iter()
+        .map(|a| a.to_string())
+        .collect(),
+      speed: 175,
+      voice: "en".to_string(),
+    }
+  }
+}
+
+impl Default for AutoSaveSettings {
+  fn default() -> Self {
+    AutoSaveSettings {
+      interval: 5,
+      page_turns: 20,
+    }
+  }
+}
+
+impl Default for AutoStatusSettings {
+  fn default() -> Self {
+    AutoStatusSettings {
+      reading_on_open: true,
+      finished_on_end: true,
+      finished_threshold: 100.0,
+    }
+  }
+}
+
 impl Default for HomeSettings {
   fn default() -> Self {
     HomeSettings {
       address_bar: false,
+      collections_bar: false,
       navigation_bar: true,
+      column_headers: false,
       max_levels: 3,
       max_trash_size: 32 * (1 << 20),
     }
@@ -315,15 +845,37 @@ impl Default for HomeSettings {
 
 impl Default for ReaderSettings {
   fn default() -> Self {
+    let profile = CURRENT_DEVICE.typography_profile();
     ReaderSettings {
       refresh_rate: RefreshRateSettings::default(),
       finished: FinishedAction::Notify,
+      auto_status: AutoStatusSettings::default(),
+      auto_save: AutoSaveSettings::default(),
+      header: HeaderSettings::default(),
       font_path: DEFAULT_FONT_PATH.to_string(),
+      font_dirs: Vec::new(),
+      font_styles: FxHashMap::default(),
       font_family: DEFAULT_FONT_FAMILY.to_string(),
-      font_size: DEFAULT_FONT_SIZE,
+      font_size: profile.font_size,
       text_align: DEFAULT_TEXT_ALIGN,
-      margin_width: DEFAULT_MARGIN_WIDTH,
-      line_height: DEFAULT_LINE_HEIGHT,
+      text_align_override: TextAlignOverride::Document,
+      margin_width: profile.margin_width,
+      margins: None,
+      line_height: profile.line_height,
+      typography: TypographySettings::default(),
+      frontlight_buttons: false,
+      frontlight_step: 5.0,
+      citation_style: CitationStyle::Apa,
+      annotations_directory: None,
+      tap_zones: TapZones::default(),
+      skip_front_matter: SkipFrontMatter::Ask,
+      zoom_presets: vec![
+        ZoomMode::FitToPage,
+        ZoomMode::FitToWidth,
+        ZoomMode::FitToColumn,
+      ],
+      bookmark_corner: DiagDir::NorthEast,
+      tts: TtsSettings::default(),
     }
   }
 }
@@ -335,10 +887,92 @@ impl Default for ImportSettings {
       startup_trigger: true,
       traverse_hidden: false,
       extract_epub_metadata: true,
-      allowed_kinds: ["pdf", "djvu", "epub", "fb2", "xps", "oxps", "cbz"]
+      extract_opf_metadata: true,
+      allowed_kinds: ["pdf", "djvu", "epub", "fb2", "xps", "oxps", "cbz", "images"]
         .iter()
         .map(|k| k.to_string())
         .collect(),
+      allowed_extensions: FxHashSet::default(),
+      min_file_size: 0,
+      rules: Vec::new(),
+      sources: Vec::new(),
+      transfer: TransferMode::Copy,
+      dry_run: false,
+      conversion: ConversionSettings::default(),
+    }
+  }
+}
+
+impl Default for ConversionSettings {
+  fn default() -> Self {
+    ConversionSettings {
+      program: None,
+      arguments: Vec::new(),
+      extensions: ["azw", "azw3", "docx", "lit", "mobi", "odt", "rtf"]
+        .iter()
+        .map(|e| e.to_string())
+        .collect(),
+      timeout: 600,
+    }
+  }
+}
+
+impl Default for StorageSettings {
+  fn default() -> Self {
+    StorageSettings {
+      warn: 64 * (1 << 20),
+      block: 16 * (1 << 20),
+    }
+  }
+}
+
+impl Default for MemorySettings {
+  fn default() -> Self {
+    MemorySettings {
+      warn: 16 * (1 << 20),
+    }
+  }
+}
+
+impl Default for DitheringSettings {
+  fn default() -> Self {
+    DitheringSettings {
+      covers: DitherMode::FloydSteinberg,
+      images: DitherMode::FloydSteinberg,
+      pages: DitherMode::None,
+    }
+  }
+}
+
+impl Default for OpdsSettings {
+  fn default() -> Self {
+    OpdsSettings {
+      directory: PathBuf::from("Downloads"),
+      catalogs: Vec::new(),
+    }
+  }
+}
+
+impl Default for FeedsSettings {
+  fn default() -> Self {
+    FeedsSettings {
+      directory: PathBuf::from("Feeds"),
+      max_age: 30,
+      keep_unread: true,
+      sources: Vec::new(),
+    }
+  }
+}
+
+impl Default for CloudSyncSettings {
+  fn default() -> Self {
+    CloudSyncSettings {
+      url: String::new(),
+      username: String::new(),
+      password: String::new(),
+      sketches: true,
+      annotations: true,
+      phrasebook: true,
     }
   }
 }
@@ -373,15 +1007,22 @@ impl Default for Settings {
         },
       ],
       keyboard_layout: "English".to_string(),
+      language: String::new(),
+      keyboard_suggestions: true,
       frontlight: true,
+      auto_frontlight: false,
       wifi: false,
       sleep_cover: true,
+      private_sleep_screen: false,
+      instant_resume: true,
       auto_share: false,
       rotation_lock: None,
       button_scheme: ButtonScheme::Natural,
+      buttons: ButtonMappings::default(),
       auto_suspend: 30,
       auto_power_off: 3,
       intermission_images: FxHashMap::default(),
+      intermission_quotes: QuoteSource::None,
       home: HomeSettings::default(),
       reader: ReaderSettings::default(),
       import: ImportSettings::default(),
@@ -389,8 +1030,46 @@ impl Default for Settings {
       sketch: SketchSettings::default(),
       calculator: CalculatorSettings::default(),
       battery: BatterySettings::default(),
+      storage: StorageSettings::default(),
+      memory: MemorySettings::default(),
+      dithering: DitheringSettings::default(),
+      night_filter: NightFilterSettings::default(),
+      power: PowerSettings::default(),
+      feeds: FeedsSettings::default(),
+      opds: OpdsSettings::default(),
+      cloud_sync: CloudSyncSettings::default(),
+      feedback: FeedbackSettings::default(),
       frontlight_levels: LightLevels::default(),
       frontlight_presets: Vec::new(),
+      preset_gesture: GlobalGesture::BottomLeftMultiCorner,
+      palette_gesture: GlobalGesture::BottomRightMultiCorner,
+      undo_gesture: GlobalGesture::None,
+      scheduled_tasks: Vec::new(),
+      transitions: TransitionIntensity::None,
+      usage_insights: false,
+      reading_speed: ReadingSpeed::default(),
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{GlobalGesture, Settings};
+
+  #[test]
+  fn test_unbind_duplicate_gestures() {
+    let mut settings = Settings {
+      preset_gesture: GlobalGesture::NorthMultiArrow,
+      palette_gesture: GlobalGesture::BottomRightMultiCorner,
+      undo_gesture: GlobalGesture::NorthMultiArrow,
+      ..Default::default()
+    };
+    settings.unbind_duplicate_gestures();
+    assert_eq!(settings.preset_gesture, GlobalGesture::NorthMultiArrow);
+    assert_eq!(
+      settings.palette_gesture,
+      GlobalGesture::BottomRightMultiCorner
+    );
+    assert_eq!(settings.undo_gesture, GlobalGesture::None);
+  }
+}
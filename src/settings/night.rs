@@ -0,0 +1,101 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NightFilterMode {
+  Off,
+  On,
+  Scheduled,
+}
+
+impl fmt::Display for NightFilterMode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    Debug::fmt(self, f)
+  }
+}
+
+// Softens the rendering at night, independently of the frontlight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NightFilterSettings {
+  pub mode: NightFilterMode,
+  // The time range, in the *HH:MM* format, during which the scheduled filter is active.
+  pub start: String,
+  pub end: String,
+  // The fraction of the full contrast that is kept: the black point is raised accordingly.
+  pub contrast: f32,
+  // Values greater than one lighten the midtones.
+  pub gamma: f32,
+}
+
+impl Default for NightFilterSettings {
+  fn default() -> Self {
+    NightFilterSettings {
+      mode: NightFilterMode::Off,
+      start: "22:00".to_string(),
+      end: "07:00".to_string(),
+      contrast: 0.7,
+      gamma: 1.4,
+    }
+  }
+}
+
+impl NightFilterSettings {
+  pub fn is_active(&self, now: NaiveTime) -> bool {
+    match self.mode {
+      NightFilterMode::Off => false,
+      NightFilterMode::On => true,
+      NightFilterMode::Scheduled => {
+        let start = NaiveTime::parse_from_str(&self.start, "%H:%M");
+        let end = NaiveTime::parse_from_str(&self.end, "%H:%M");
+        match (start, end) {
+          (Ok(start), Ok(end)) if start <= end => now >= start && now < end,
+          // The range spans midnight.
+          (Ok(start), Ok(end)) => now >= start || now < end,
+          _ => false,
+        }
+      },
+    }
+  }
+
+  // Maps each gray level to its filtered value.
+  pub fn tone_table(&self) -> [u8; 256] {
+    let contrast = self.contrast.max(0.0).min(1.0);
+    let gamma = self.gamma.max(0.1);
+    let black = 255.0 * (1.0 - contrast);
+    let mut table = [0u8; 256];
+    for (i, v) in table.iter_mut().enumerate() {
+      let x = (i as f32 / 255.0).powf(1.0 / gamma);
+      *v = (black + x * (255.0 - black)).round() as u8;
+    }
+    table
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{NightFilterMode, NightFilterSettings};
+  use chrono::NaiveTime;
+
+  #[test]
+  fn test_schedule() {
+    let settings = NightFilterSettings {
+      mode: NightFilterMode::Scheduled,
+      ..Default::default()
+    };
+    assert!(settings.is_active(NaiveTime::from_hms_opt(23, 30, 0).unwrap()));
+    assert!(settings.is_active(NaiveTime::from_hms_opt(6, 59, 0).unwrap()));
+    assert!(!settings.is_active(NaiveTime::from_hms_opt(7, 0, 0).unwrap()));
+    assert!(!settings.is_active(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+  }
+
+  #[test]
+  fn test_tone_table() {
+    let table = NightFilterSettings::default().tone_table();
+    assert!(table[0] > 70 && table[0] < 80);
+    assert_eq!(table[255], 255);
+    assert!(table.windows(2).all(|w| w[0] <= w[1]));
+  }
+}
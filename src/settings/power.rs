@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowerProfile {
+  Performance,
+  Balanced,
+  Saver,
+}
+
+impl fmt::Display for PowerProfile {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    Debug::fmt(self, f)
+  }
+}
+
+impl PowerProfile {
+  // The number of pages rendered ahead of the current one by the reader.
+  pub fn prefetch_pages(self) -> usize {
+    match self {
+      PowerProfile::Performance => 2,
+      PowerProfile::Balanced => 1,
+      PowerProfile::Saver => 0,
+    }
+  }
+
+  // Multiplies the number of page turns between two full refreshes.
+  pub fn refresh_factor(self) -> u8 {
+    match self {
+      PowerProfile::Saver => 2,
+      _ => 1,
+    }
+  }
+
+  // The minutes between two runs of the periodic background tasks.
+  pub fn background_period(self) -> u32 {
+    match self {
+      PowerProfile::Saver => 5,
+      _ => 1,
+    }
+  }
+
+  // The minutes of inactivity after which the Wi-Fi is turned off, zero means never.
+  pub fn wifi_timeout(self) -> u64 {
+    match self {
+      PowerProfile::Saver => 5,
+      _ => 0,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PowerSettings {
+  pub profile: PowerProfile,
+  // Switch to the saver profile when the battery capacity falls below the threshold.
+  pub auto_saver: bool,
+  pub saver_threshold: f32,
+  // The minutes after which the Wi-Fi turned on for an operation is turned off, once the
+  // operation is done. Zero means never.
+  pub wifi_idle_timeout: u64,
+}
+
+impl Default for PowerSettings {
+  fn default() -> Self {
+    PowerSettings {
+      profile: PowerProfile::Balanced,
+      auto_saver: true,
+      saver_threshold: 20.0,
+      wifi_idle_timeout: 2,
+    }
+  }
+}
+
+impl PowerSettings {
+  // The profile in effect for the given battery state.
+  pub fn effective_profile(&self, capacity: Option<f32>, plugged: bool) -> PowerProfile {
+    if self.auto_saver && !plugged && capacity.map_or(false, |c| c < self.saver_threshold) {
+      PowerProfile::Saver
+    } else {
+      self.profile
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{PowerProfile, PowerSettings};
+
+  #[test]
+  fn test_effective_profile() {
+    let settings = PowerSettings::default();
+    assert_eq!(
+      settings.effective_profile(Some(50.0), false),
+      PowerProfile::Balanced
+    );
+    assert_eq!(
+      settings.effective_profile(Some(10.0), false),
+      PowerProfile::Saver
+    );
+    assert_eq!(
+      settings.effective_profile(Some(10.0), true),
+      PowerProfile::Balanced
+    );
+    assert_eq!(
+      settings.effective_profile(None, false),
+      PowerProfile::Balanced
+    );
+  }
+}
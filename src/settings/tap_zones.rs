@@ -0,0 +1,258 @@
+use crate::geom::{Axis, Point, Rectangle};
+use serde::{Deserialize, Serialize};
+
+// Minimum size of a zone, as a fraction of the screen's width or height.
+pub const MIN_ZONE_SIZE: f32 = 0.05;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TapAction {
+  None,
+  PreviousPage,
+  NextPage,
+  PreviousChapter,
+  NextChapter,
+  LastLocation,
+  ToggleBars,
+  ToggleBookmark,
+  TableOfContents,
+  GoToPage,
+  NextZoomPreset,
+  LastZoomMode,
+}
+
+pub const TAP_ACTIONS: [TapAction; 12] = [
+  TapAction::None,
+  TapAction::PreviousPage,
+  TapAction::NextPage,
+  TapAction::PreviousChapter,
+  TapAction::NextChapter,
+  TapAction::LastLocation,
+  TapAction::ToggleBars,
+  TapAction::ToggleBookmark,
+  TapAction::TableOfContents,
+  TapAction::GoToPage,
+  TapAction::NextZoomPreset,
+  TapAction::LastZoomMode,
+];
+
+impl TapAction {
+  pub fn label(self) -> &'static str {
+    match self {
+      TapAction::None => "None",
+      TapAction::PreviousPage => "Previous Page",
+      TapAction::NextPage => "Next Page",
+      TapAction::PreviousChapter => "Previous Chapter",
+      TapAction::NextChapter => "Next Chapter",
+      TapAction::LastLocation => "Last Location",
+      TapAction::ToggleBars => "Toggle Bars",
+      TapAction::ToggleBookmark => "Toggle Bookmark",
+      TapAction::TableOfContents => "Table of Contents",
+      TapAction::GoToPage => "Go to Page",
+      TapAction::NextZoomPreset => "Next Zoom Preset",
+      TapAction::LastZoomMode => "Last Zoom Mode",
+    }
+  }
+}
+
+// The screen of the reader is divided into a grid of zones. The boundaries between
+// the columns and the rows are fractions of the width and height of the screen, and
+// the actions of the zones are listed row by row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TapZones {
+  pub enabled: bool,
+  pub columns: Vec<f32>,
+  pub rows: Vec<f32>,
+  pub actions: Vec<TapAction>,
+}
+
+impl Default for TapZones {
+  fn default() -> Self {
+    TapZones {
+      enabled: false,
+      columns: vec![1.0 / 3.0, 2.0 / 3.0],
+      rows: vec![1.0 / 6.0, 5.0 / 6.0],
+      actions: vec![
+        TapAction::LastLocation,
+        TapAction::ToggleBars,
+        TapAction::ToggleBookmark,
+        TapAction::PreviousPage,
+        TapAction::ToggleBars,
+        TapAction::NextPage,
+        TapAction::TableOfContents,
+        TapAction::ToggleBars,
+        TapAction::GoToPage,
+      ],
+    }
+  }
+}
+
+impl TapZones {
+  pub fn boundaries(&self, axis: Axis) -> &Vec<f32> {
+    match axis {
+      Axis::Horizontal => &self.columns,
+      Axis::Vertical => &self.rows,
+    }
+  }
+
+  fn boundaries_mut(&mut self, axis: Axis) -> &mut Vec<f32> {
+    match axis {
+      Axis::Horizontal => &mut self.columns,
+      Axis::Vertical => &mut self.rows,
+    }
+  }
+
+  pub fn columns_count(&self) -> usize {
+    self.columns.len() + 1
+  }
+
+  pub fn rows_count(&self) -> usize {
+    self.rows.len() + 1
+  }
+
+  // Sorts the boundaries and makes sure that every zone has an action.
+  pub fn normalize(&mut self) {
+    for axis in [Axis::Horizontal, Axis::Vertical].iter() {
+      let boundaries = self.boundaries_mut(*axis);
+      boundaries.retain(|b| *b > 0.0 && *b < 1.0);
+      boundaries.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    }
+    let count = self.columns_count() * self.rows_count();
+    self.actions.resize(count, TapAction::None);
+  }
+
+  pub fn zone_rect(&self, rect: &Rectangle, column: usize, row: usize) -> Rectangle {
+    let xs = positions(&self.columns, rect.min.x, rect.width() as i32);
+    let ys = positions(&self.rows, rect.min.y, rect.height() as i32);
+    rect![xs[column], ys[row], xs[column + 1], ys[row + 1]]
+  }
+
+  pub fn zone_at(&self, rect: &Rectangle, pt: Point) -> (usize, usize) {
+    let fx = (pt.x - rect.min.x) as f32 / rect.width() as f32;
+    let fy = (pt.y - rect.min.y) as f32 / rect.height() as f32;
+    let column = self.columns.iter().take_while(|b| fx >= **b).count();
+    let row = self.rows.iter().take_while(|b| fy >= **b).count();
+    (column, row)
+  }
+
+  pub fn action_at(&self, rect: &Rectangle, pt: Point) -> TapAction {
+    let (column, row) = self.zone_at(rect, pt);
+    self
+      .actions
+      .get(row * self.columns_count() + column)
+      .cloned()
+      .unwrap_or(TapAction::None)
+  }
+
+  // Moves the given boundary to *value*, without crossing its neighbors.
+  pub fn move_boundary(&mut self, axis: Axis, index: usize, value: f32) {
+    let boundaries = self.boundaries_mut(axis);
+    let low = if index > 0 {
+      boundaries[index - 1]
+    } else {
+      0.0
+    };
+    let high = boundaries.get(index + 1).cloned().unwrap_or(1.0);
+    if high - low < 2.0 * MIN_ZONE_SIZE {
+      return;
+    }
+    boundaries[index] = value.max(low + MIN_ZONE_SIZE).min(high - MIN_ZONE_SIZE);
+  }
+
+  // Splits the largest column or row in two. The new zones inherit the actions of the split ones.
+  pub fn add_boundary(&mut self, axis: Axis) {
+    let (index, start, end) = {
+      let boundaries = self.boundaries(axis);
+      let mut edges = vec![0.0];
+      edges.extend_from_slice(boundaries);
+      edges.push(1.0);
+      let (index, w) = edges
+        .windows(2)
+        .enumerate()
+        .max_by(|a, b| (a.1[1] - a.1[0]).partial_cmp(&(b.1[1] - b.1[0])).unwrap())
+        .unwrap();
+      (index, w[0], w[1])
+    };
+
+    let columns_count = self.columns_count();
+    match axis {
+      Axis::Horizontal => {
+        for row in (0..self.rows_count()).rev() {
+          let action = self.actions[row * columns_count + index];
+          self.actions.insert(row * columns_count + index, action);
+        }
+      },
+      Axis::Vertical => {
+        let start_cell = index * columns_count;
+        for column in 0..columns_count {
+          let action = self.actions[start_cell + 2 * column];
+          self.actions.insert(start_cell + column, action);
+        }
+      },
+    }
+
+    self.boundaries_mut(axis).insert(index, (start + end) / 2.0);
+  }
+
+  // Merges the last column or row into the previous one.
+  pub fn remove_boundary(&mut self, axis: Axis) {
+    if self.boundaries(axis).is_empty() {
+      return;
+    }
+
+    let columns_count = self.columns_count();
+    match axis {
+      Axis::Horizontal => {
+        for row in (0..self.rows_count()).rev() {
+          self.actions.remove(row * columns_count + columns_count - 1);
+        }
+      },
+      Axis::Vertical => {
+        let len = self.actions.len();
+        self.actions.truncate(len - columns_count);
+      },
+    }
+
+    self.boundaries_mut(axis).pop();
+  }
+}
+
+fn positions(boundaries: &[f32], start: i32, size: i32) -> Vec<i32> {
+  let mut result = vec![start];
+  result.extend(
+    boundaries
+      .iter()
+      .map(|b| start + (b * size as f32).round() as i32),
+  );
+  result.push(start + size);
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{TapAction, TapZones};
+  use crate::geom::Axis;
+
+  #[test]
+  fn test_zones() {
+    let rect = rect![0, 0, 600, 800];
+    let mut zones = TapZones::default();
+    assert_eq!(
+      zones.action_at(&rect, pt!(10, 400)),
+      TapAction::PreviousPage
+    );
+    assert_eq!(zones.action_at(&rect, pt!(590, 790)), TapAction::GoToPage);
+    zones.add_boundary(Axis::Horizontal);
+    assert_eq!(zones.columns.len(), 3);
+    assert_eq!(zones.actions.len(), 12);
+    assert_eq!(
+      zones.action_at(&rect, pt!(10, 400)),
+      TapAction::PreviousPage
+    );
+    zones.remove_boundary(Axis::Vertical);
+    assert_eq!(zones.actions.len(), 8);
+    zones.move_boundary(Axis::Vertical, 0, 0.0);
+    assert!(zones.rows[0] > 0.0);
+  }
+}
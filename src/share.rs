@@ -0,0 +1,231 @@
+//! Shares a document with the devices of the local network, through a minimal HTTP server
+//! that only serves this document.
+
+use anyhow::{format_err, Context, Error};
+use std::{
+  fs::File,
+  io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+  net::{IpAddr, TcpListener, TcpStream, UdpSocket},
+  path::Path,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  thread,
+  time::Duration,
+};
+
+pub const SHARE_PORT: u16 = 8000;
+const DOWNLOAD_PATH: &str = "/download";
+// How long the server waits between two checks for incoming connections.
+const ACCEPT_DELAY: Duration = Duration::from_millis(250);
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+// The encryption algorithms used to obfuscate the embedded fonts, which aren't DRM.
+const FONT_OBFUSCATION_ALGORITHMS: [&str; 2] = [
+  "http://www.idpf.org/2008/embedding",
+  "http://ns.adobe.com/pdf/enc#RC",
+];
+
+// The server stops when it's dropped.
+pub struct ShareServer {
+  url: String,
+  stop: Arc<AtomicBool>,
+}
+
+impl ShareServer {
+  pub fn start(path: &Path) -> Result<ShareServer, Error> {
+    let address = local_address().ok_or_else(|| format_err!("no network address"))?;
+    let listener = TcpListener::bind(("0.0.0.0", SHARE_PORT))
+      .with_context(|| format!("can't listen on port {}", SHARE_PORT))?;
+    listener.set_nonblocking(true)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop2 = stop.clone();
+    let path = path.to_path_buf();
+
+    thread::spawn(move || {
+      while !stop2.load(Ordering::Relaxed) {
+        match listener.accept() {
+          Ok((stream, _)) => {
+            if let Err(e) = serve(stream, &path) {
+              eprintln!("Can't serve {}: {:#}.", path.display(), e);
+            }
+          },
+          Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_DELAY),
+          Err(e) => {
+            eprintln!("Can't accept connections: {:#}.", e);
+            break;
+          },
+        }
+      }
+    });
+
+    Ok(ShareServer {
+      url: format!("http://{}:{}/", address, SHARE_PORT),
+      stop,
+    })
+  }
+
+  pub fn url(&self) -> &str {
+    &self.url
+  }
+}
+
+impl Drop for ShareServer {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::Relaxed);
+  }
+}
+
+// The address of the interface that routes to the outside: connecting a UDP socket doesn't
+// send any packet.
+fn local_address() -> Option<IpAddr> {
+  let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+  socket.connect("192.0.2.1:80").ok()?;
+  socket.local_addr().ok().map(|a| a.ip())
+}
+
+fn serve(mut stream: TcpStream, path: &Path) -> Result<(), Error> {
+  stream.set_nonblocking(false)?;
+  stream.set_read_timeout(Some(READ_TIMEOUT))?;
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let mut request = String::new();
+  reader.read_line(&mut request)?;
+  // Skip the headers.
+  let mut line = String::new();
+  while reader.read_line(&mut line)? > 2 {
+    line.clear();
+  }
+
+  let mut parts = request.split_whitespace();
+  let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+  let name = path
+    .file_name()
+    .map(|n| n.to_string_lossy().into_owned())
+    .unwrap_or_default();
+
+  match (method, target) {
+    ("GET", "/") => {
+      let body = download_page(&name);
+      write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+      )?;
+    },
+    ("GET", DOWNLOAD_PATH) => {
+      let mut file = File::open(path)?;
+      let size = file.metadata()?.len();
+      write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nContent-Disposition: attachment; filename=\"{}\"; filename*=UTF-8''{}\r\nConnection: close\r\n\r\n",
+        mime_type(path),
+        size,
+        name.replace(|c: char| c == '"' || !c.is_ascii() || c.is_ascii_control(), "_"),
+        percent_encode(&name)
+      )?;
+      io::copy(&mut file, &mut stream)?;
+    },
+    _ => {
+      write!(
+        stream,
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+      )?;
+    },
+  }
+  stream.flush()?;
+  Ok(())
+}
+
+fn download_page(name: &str) -> String {
+  let name = name
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;");
+  format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width\">\n<title>{0}</title>\n</head>\n<body>\n<p><a href=\"{1}\">{0}</a></p>\n</body>\n</html>\n",
+    name, DOWNLOAD_PATH
+  )
+}
+
+fn mime_type(path: &Path) -> &'static str {
+  match path.extension().and_then(|e| e.to_str()) {
+    Some("epub") => "application/epub+zip",
+    Some("pdf") => "application/pdf",
+    Some("djvu") => "image/vnd.djvu",
+    Some("fb2") => "application/x-fictionbook+xml",
+    Some("html") | Some("htm") => "text/html",
+    Some("txt") => "text/plain",
+    Some("cbz") => "application/vnd.comicbook+zip",
+    _ => "application/octet-stream",
+  }
+}
+
+fn percent_encode(text: &str) -> String {
+  text
+    .bytes()
+    .map(|b| {
+      if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+        (b as char).to_string()
+      } else {
+        format!("%{:02X}", b)
+      }
+    })
+    .collect()
+}
+
+// Whether the document can be shared: the EPUBs whose content is encrypted and the PDFs with
+// an encryption dictionary are considered protected.
+pub fn is_drm_free(path: &Path) -> bool {
+  match path.extension().and_then(|e| e.to_str()) {
+    Some("epub") => epub_is_drm_free(path).unwrap_or(false),
+    Some("pdf") => pdf_is_drm_free(path).unwrap_or(false),
+    _ => true,
+  }
+}
+
+fn epub_is_drm_free(path: &Path) -> Result<bool, Error> {
+  let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+  if archive.by_name("META-INF/rights.xml").is_ok() {
+    return Ok(false);
+  }
+  let mut text = String::new();
+  match archive.by_name("META-INF/encryption.xml") {
+    Ok(mut entry) => {
+      entry.read_to_string(&mut text)?;
+    },
+    Err(_) => return Ok(true),
+  }
+  Ok(
+    text
+      .split("Algorithm=\"")
+      .skip(1)
+      .filter_map(|s| s.split('"').next())
+      .filter(|a| a.contains("enc#") || a.contains("embedding"))
+      .all(|a| FONT_OBFUSCATION_ALGORITHMS.contains(&a)),
+  )
+}
+
+fn pdf_is_drm_free(path: &Path) -> Result<bool, Error> {
+  // The encryption dictionary is referenced by the trailer, at the end of the file.
+  let mut file = File::open(path)?;
+  let size = file.metadata()?.len();
+  let tail_size = size.min(1 << 16);
+  file.seek(SeekFrom::End(-(tail_size as i64)))?;
+  let mut buf = Vec::with_capacity(tail_size as usize);
+  file.read_to_end(&mut buf)?;
+  Ok(!buf.windows(8).any(|w| w == b"/Encrypt"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::percent_encode;
+
+  #[test]
+  fn test_percent_encode() {
+    assert_eq!(
+      percent_encode("Les Misérables.epub"),
+      "Les%20Mis%C3%A9rables.epub"
+    );
+  }
+}
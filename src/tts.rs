@@ -0,0 +1,165 @@
+//! Reads the text aloud, one sentence at a time, with an external speech synthesizer such as
+//! *espeak-ng*. The synthesizer reads the sentence on its standard input and plays it on the
+//! audio output of the device, or on a USB audio device.
+
+use crate::{
+  document::{skim::ends_sentence, BoundedText, TextLocation},
+  settings::TtsSettings,
+  view::{Event, Hub},
+};
+use anyhow::{format_err, Error};
+use std::{
+  io::Write,
+  os::unix::process::CommandExt,
+  process::{Child, Command, Stdio},
+  sync::{Arc, Mutex},
+  thread,
+  time::Duration,
+};
+
+// How often a running synthesizer is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// A running speech synthesizer. The child is dropped once it's reaped.
+#[derive(Debug, Clone)]
+pub struct Synthesizer {
+  pub id: u32,
+  child: Arc<Mutex<Option<Child>>>,
+}
+
+impl Synthesizer {
+  // Interrupts the synthesizer and the processes it started.
+  pub fn silence(&self) {
+    // The child isn't reaped yet, hence its process group can't have been reused.
+    if let Some(child) = self.child.lock().unwrap().as_ref() {
+      unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGTERM) };
+    }
+  }
+}
+
+// Returns the words of the sentence that follows the word at *after*, or of the first sentence
+// when *after* is unknown. The words are sorted by location, and the last sentence may be
+// unfinished.
+pub fn next_sentence(words: &[BoundedText], after: Option<TextLocation>) -> Option<&[BoundedText]> {
+  let start = match after {
+    Some(after) => words.iter().position(|w| w.location > after)?,
+    None if words.is_empty() => return None,
+    None => 0,
+  };
+  let end = words[start..]
+    .iter()
+    .position(|w| ends_sentence(&w.text))
+    .map_or(words.len(), |index| start + index + 1);
+  Some(&words[start..end])
+}
+
+fn expand(argument: &str, settings: &TtsSettings) -> String {
+  argument
+    .replace("{speed}", &settings.speed.to_string())
+    .replace("{voice}", &settings.voice)
+}
+
+// Starts speaking *text* with a synthesizer running in its own process group. The hub is sent
+// `Event::Spoken` with the identifier of the synthesizer when its process ends.
+pub fn speak(text: &str, settings: &TtsSettings, hub: &Hub) -> Result<Synthesizer, Error> {
+  let program = settings
+    .program
+    .as_ref()
+    .ok_or_else(|| format_err!("no speech synthesizer"))?;
+  let mut command = Command::new(program);
+  command
+    .args(settings.arguments.iter().map(|arg| expand(arg, settings)))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null());
+  unsafe {
+    command.pre_exec(|| {
+      libc::setpgid(0, 0);
+      Ok(())
+    });
+  }
+  let mut child = command.spawn()?;
+  if let Some(mut stdin) = child.stdin.take() {
+    if let Err(e) = writeln!(stdin, "{}", text) {
+      child.kill().ok();
+      child.wait().ok();
+      return Err(e.into());
+    }
+  }
+  let synthesizer = Synthesizer {
+    id: child.id(),
+    child: Arc::new(Mutex::new(Some(child))),
+  };
+  let child = Arc::clone(&synthesizer.child);
+  let id = synthesizer.id;
+  let hub = hub.clone();
+  thread::spawn(move || {
+    loop {
+      {
+        let mut child = child.lock().unwrap();
+        if !matches!(child.as_mut().map(Child::try_wait), Some(Ok(None))) {
+          child.take();
+          break;
+        }
+      }
+      thread::sleep(POLL_INTERVAL);
+    }
+    hub.send(Event::Spoken(id)).ok();
+  });
+  Ok(synthesizer)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::next_sentence;
+  use crate::{
+    document::{BoundedText, TextLocation},
+    geom::Boundary,
+  };
+
+  fn words(text: &str) -> Vec<BoundedText> {
+    text
+      .split_whitespace()
+      .enumerate()
+      .map(|(index, word)| BoundedText {
+        text: word.to_string(),
+        rect: Boundary::new(vec2!(0.0, 0.0), vec2!(1.0, 1.0)),
+        location: TextLocation::Dynamic(index),
+      })
+      .collect()
+  }
+
+  fn text(words: &[BoundedText]) -> String {
+    words
+      .iter()
+      .map(|w| w.text.as_str())
+      .collect::<Vec<&str>>()
+      .join(" ")
+  }
+
+  #[test]
+  fn test_next_sentence() {
+    let page = words("It rained. “Again?” she asked. The end of the");
+    assert_eq!(next_sentence(&page, None).map(text).unwrap(), "It rained.");
+    let first = next_sentence(&page, None).unwrap();
+    let last = first[first.len() - 1].location;
+    assert_eq!(
+      next_sentence(&page, Some(last)).map(text).unwrap(),
+      "“Again?”"
+    );
+    assert_eq!(
+      next_sentence(&page, Some(TextLocation::Dynamic(2)))
+        .map(text)
+        .unwrap(),
+      "she asked."
+    );
+    assert_eq!(
+      next_sentence(&page, Some(TextLocation::Dynamic(4)))
+        .map(text)
+        .unwrap(),
+      "The end of the"
+    );
+    assert!(next_sentence(&page, Some(TextLocation::Dynamic(8))).is_none());
+    assert!(next_sentence(&[], None).is_none());
+  }
+}
@@ -5,10 +5,10 @@ use crate::{
   device::CURRENT_DEVICE,
   font::Fonts,
   framebuffer::{Framebuffer, UpdateMode},
-  geom::{CycleDir, Dir, Rectangle},
+  geom::{CycleDir, Dir, Point, Rectangle},
   gesture::GestureEvent,
   unit::mm_to_px,
-  view::{Bus, Event, Hub, Id, RenderQueue, View, ID_FEEDER},
+  view::{Bus, EntryId, Event, Hub, Id, RenderQueue, View, ID_FEEDER},
 };
 
 pub struct CodeArea {
@@ -61,16 +61,39 @@ impl CodeArea {
     self.font_size = font_size;
     self.margin_width = margin_width;
   }
+
+  // The line drawn at the given point, following the wrapping of render.
+  fn line_at(&self, pt: Point, fonts: &mut Fonts) -> Option<&Line> {
+    let dpi = CURRENT_DEVICE.dpi;
+    let font = &mut fonts.monospace.regular;
+    font.set_size((64.0 * self.font_size) as u32, dpi);
+    let line_height = font.ascender() - font.descender();
+    let char_width = font.plan(" ", None, None).width.max(1);
+    let padding = mm_to_px(self.margin_width as f32, dpi) as i32;
+    let columns = ((self.rect.width() as i32 - 2 * padding) / char_width).max(1) as usize;
+    let mut y = self.rect.min.y + padding;
+
+    for line in &self.data {
+      let rows = ((line.content.chars().count() + columns - 1) / columns).max(1) as i32;
+      let next_y = y + rows * line_height;
+      if pt.y >= y && pt.y < next_y {
+        return Some(line);
+      }
+      y = next_y;
+    }
+
+    None
+  }
 }
 
 impl View for CodeArea {
   fn handle_event(
     &mut self,
     evt: &Event,
-    _hub: &Hub,
+    hub: &Hub,
     bus: &mut Bus,
     _rq: &mut RenderQueue,
-    _context: &mut Context,
+    context: &mut Context,
   ) -> bool {
     match *evt {
       Event::Gesture(GestureEvent::Swipe {
@@ -83,6 +106,15 @@ impl View for CodeArea {
         }
         true
       },
+      Event::Gesture(GestureEvent::HoldFingerLong(pt, _)) if self.rect.includes(pt) => {
+        if let Some(line) = self.line_at(pt, &mut context.fonts) {
+          let text = line.content.trim().to_string();
+          if !text.is_empty() {
+            hub.send(Event::Select(EntryId::Copy(text))).ok();
+          }
+        }
+        true
+      },
       Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
         let middle_x = (self.rect.min.x + self.rect.max.x) / 2;
         if center.x < middle_x {
@@ -162,7 +162,7 @@ impl Calculator {
         rect.min.y + side - small_thickness
       ],
       Event::Back,
-      "Calculator".to_string(),
+      tr!("Calculator"),
       context,
     );
     children.push(Box::new(top_bar) as Box<dyn View>);
@@ -0,0 +1,452 @@
+use super::{
+  common::{locate_by_id, main_menu_entries},
+  filler::Filler,
+  icon::Icon,
+  keyboard::Keyboard,
+  label::Label,
+  Align,
+  Bus,
+  EntryKind,
+  Event,
+  Hub,
+  Id,
+  KeyboardEvent,
+  RenderData,
+  RenderQueue,
+  TextKind,
+  View,
+  ViewId,
+  BIG_BAR_HEIGHT,
+  ID_FEEDER,
+  SMALL_BAR_HEIGHT,
+  THICKNESS_MEDIUM,
+};
+use crate::{
+  app::Context,
+  color::{BLACK, WHITE},
+  device::CURRENT_DEVICE,
+  font::Fonts,
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{halves, LinearDir, Rectangle},
+  gesture::GestureEvent,
+  unit::scale_by_dpi,
+};
+
+const QUERY_PLACEHOLDER: &str = "Type a command";
+
+#[derive(Debug, Clone)]
+pub struct PaletteCommand {
+  pub label: String,
+  pub event: Event,
+}
+
+impl PaletteCommand {
+  pub fn new(label: &str, event: Event) -> PaletteCommand {
+    PaletteCommand {
+      label: tr!(label),
+      event,
+    }
+  }
+}
+
+// Appends the commands of the given menu entries. The labels of the entries of a submenu are
+// prefixed with the label of the submenu.
+pub fn menu_commands(entries: &[EntryKind], prefix: &str, commands: &mut Vec<PaletteCommand>) {
+  for entry in entries {
+    match entry {
+      EntryKind::Command(label, id)
+      | EntryKind::CheckBox(label, id, _)
+      | EntryKind::RadioButton(label, id, _) => {
+        commands.push(PaletteCommand {
+          label: join_labels(prefix, label),
+          event: Event::Select(id.clone()),
+        });
+      },
+      EntryKind::SubMenu(label, entries) => {
+        menu_commands(entries, &join_labels(prefix, label), commands);
+      },
+      _ => (),
+    }
+  }
+}
+
+fn join_labels(prefix: &str, label: &str) -> String {
+  if prefix.is_empty() {
+    label.to_string()
+  } else {
+    format!("{} › {}", prefix, label)
+  }
+}
+
+// Returns the score of *text* if it contains the characters of *query* in order. Consecutive
+// characters and characters at the beginning of words are worth more.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+  let mut score = 0;
+  let mut chars = query.chars().flat_map(char::to_lowercase).peekable();
+  let mut prev_matched = false;
+  let mut prev_char = ' ';
+
+  for c in text.chars() {
+    let next = match chars.peek() {
+      Some(&next) => next,
+      None => break,
+    };
+    let lc = c.to_lowercase().next().unwrap_or(c);
+    if lc == next {
+      score += 1;
+      if prev_matched {
+        score += 4;
+      }
+      if !prev_char.is_alphanumeric() {
+        score += 2;
+      }
+      chars.next();
+      prev_matched = true;
+    } else {
+      prev_matched = false;
+    }
+    prev_char = c;
+  }
+
+  if chars.peek().is_none() {
+    Some(score)
+  } else {
+    None
+  }
+}
+
+pub struct CommandPalette {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  commands: Vec<PaletteCommand>,
+  query: String,
+  // The indices of the commands matching the query, best first.
+  matches: Vec<usize>,
+  // The index of the first row.
+  rows_start: usize,
+}
+
+impl CommandPalette {
+  pub fn new(
+    rect: Rectangle,
+    commands: Vec<PaletteCommand>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> CommandPalette {
+    let mut palette = CommandPalette {
+      id: ID_FEEDER.next(),
+      rect,
+      children: Vec::new(),
+      commands,
+      query: String::new(),
+      matches: Vec::new(),
+      rows_start: 0,
+    };
+    palette.update_matches(context);
+    palette.layout(context);
+    rq.add(RenderData::new(palette.id, rect, UpdateMode::Gui));
+    palette
+  }
+
+  fn layout(&mut self, context: &mut Context) {
+    let rect = self.rect;
+    let dpi = CURRENT_DEVICE.dpi;
+    let (small_height, big_height) = (
+      scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32,
+      scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32,
+    );
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+    let padding = small_height / 4;
+
+    self.children.clear();
+
+    let query_label = Label::new(
+      rect![
+        rect.min.x,
+        rect.min.y,
+        rect.max.x - small_height,
+        rect.min.y + small_height - small_thickness
+      ],
+      self.query_text(),
+      Align::Left(padding),
+    );
+    self.children.push(Box::new(query_label) as Box<dyn View>);
+
+    let close_icon = Icon::new(
+      "close",
+      rect![
+        rect.max.x - small_height,
+        rect.min.y,
+        rect.max.x,
+        rect.min.y + small_height - small_thickness
+      ],
+      Event::Close(ViewId::CommandPalette),
+    );
+    self.children.push(Box::new(close_icon) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height - small_thickness,
+        rect.max.x,
+        rect.min.y + small_height + big_thickness
+      ],
+      BLACK,
+    );
+    self.children.push(Box::new(separator) as Box<dyn View>);
+
+    let mut kb_rect = rect![
+      rect.min.x,
+      rect.max.y - 3 * big_height + big_thickness,
+      rect.max.x,
+      rect.max.y
+    ];
+    let keyboard = Keyboard::new(&mut kb_rect, false, context);
+    self.children.push(Box::new(keyboard) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        kb_rect.min.y - thickness,
+        rect.max.x,
+        kb_rect.min.y
+      ],
+      BLACK,
+    );
+    self.children.push(Box::new(separator) as Box<dyn View>);
+
+    self.rows_start = self.children.len();
+    self.update_rows(&mut RenderQueue::new());
+  }
+
+  fn rows_rect(&self) -> Rectangle {
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (_, big_thickness) = halves(thickness);
+    let kb_top = self.children[self.rows_start - 1].rect().min.y;
+    rect![
+      self.rect.min.x,
+      self.rect.min.y + small_height + big_thickness,
+      self.rect.max.x,
+      kb_top
+    ]
+  }
+
+  fn query_text(&self) -> String {
+    if self.query.is_empty() {
+      tr!(QUERY_PLACEHOLDER)
+    } else {
+      format!("› {}", self.query)
+    }
+  }
+
+  fn update_matches(&mut self, context: &Context) {
+    if self.query.is_empty() {
+      // The recent commands come first.
+      let mut matches: Vec<usize> = context
+        .recent_commands
+        .iter()
+        .filter_map(|label| self.commands.iter().position(|c| &c.label == label))
+        .collect();
+      let others: Vec<usize> = (0..self.commands.len())
+        .filter(|index| !matches.contains(index))
+        .collect();
+      matches.extend(others);
+      self.matches = matches;
+    } else {
+      let mut scores: Vec<(usize, u32)> = self
+        .commands
+        .iter()
+        .enumerate()
+        .filter_map(|(index, c)| fuzzy_score(&self.query, &c.label).map(|score| (index, score)))
+        .collect();
+      scores.sort_by(|a, b| b.1.cmp(&a.1));
+      self.matches = scores.into_iter().map(|(index, _)| index).collect();
+    }
+  }
+
+  fn update_rows(&mut self, rq: &mut RenderQueue) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let padding = small_height / 4;
+    let rows_rect = self.rows_rect();
+    let max_rows = (rows_rect.height() as i32 / small_height).max(0) as usize;
+
+    self.children.truncate(self.rows_start);
+
+    let mut y = rows_rect.min.y;
+    for &index in self.matches.iter().take(max_rows) {
+      let row = Label::new(
+        rect![rows_rect.min.x, y, rows_rect.max.x, y + small_height],
+        self.commands[index].label.clone(),
+        Align::Left(padding),
+      )
+      .event(Some(Event::RunCommand(index)));
+      self.children.push(Box::new(row) as Box<dyn View>);
+      y += small_height;
+    }
+
+    if y < rows_rect.max.y {
+      let filler = Filler::new(
+        rect![rows_rect.min.x, y, rows_rect.max.x, rows_rect.max.y],
+        WHITE,
+      );
+      self.children.push(Box::new(filler) as Box<dyn View>);
+    }
+
+    rq.add(RenderData::new(self.id, rows_rect, UpdateMode::Gui));
+  }
+
+  fn update_query(&mut self, rq: &mut RenderQueue, context: &Context) {
+    let text = self.query_text();
+    if let Some(label) = self.children[0].downcast_mut::<Label>() {
+      label.update(&text, rq);
+    }
+    self.update_matches(context);
+    self.update_rows(rq);
+  }
+
+  fn run(&mut self, index: usize, hub: &Hub, context: &mut Context) {
+    let command = &self.commands[index];
+    context.record_command(&command.label);
+    hub.send(Event::Close(ViewId::CommandPalette)).ok();
+    hub.send(command.event.clone()).ok();
+  }
+}
+
+// Shows the command palette with the commands of *view* and the ones of the main menu.
+pub fn toggle_command_palette(
+  view: &mut dyn View,
+  enable: Option<bool>,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  if let Some(index) = locate_by_id(view, ViewId::CommandPalette) {
+    if let Some(true) = enable {
+      return;
+    }
+    rq.add(RenderData::expose(
+      *view.child(index).rect(),
+      UpdateMode::Gui,
+    ));
+    view.children_mut().remove(index);
+  } else {
+    if let Some(false) = enable {
+      return;
+    }
+    let mut commands = view.commands(context);
+    menu_commands(&main_menu_entries(context), "", &mut commands);
+    hub.send(Event::Focus(None)).ok();
+    let palette = CommandPalette::new(*view.rect(), commands, rq, context);
+    view.children_mut().push(Box::new(palette) as Box<dyn View>);
+  }
+}
+
+impl View for CommandPalette {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Keyboard(kbd_evt) => {
+        match kbd_evt {
+          KeyboardEvent::Append(c) => self.query.push(c),
+          KeyboardEvent::Delete {
+            target,
+            dir: LinearDir::Backward,
+          } => match target {
+            TextKind::Char => {
+              self.query.pop();
+            },
+            TextKind::Word => {
+              let end = self.query.trim_end().len();
+              let start = self.query[..end]
+                .rfind(char::is_whitespace)
+                .map(|index| index + 1)
+                .unwrap_or(0);
+              self.query.truncate(start);
+            },
+            TextKind::Extremum => self.query.clear(),
+          },
+          KeyboardEvent::Submit => {
+            if let Some(&index) = self.matches.first() {
+              self.run(index, hub, context);
+            }
+            return true;
+          },
+          _ => return true,
+        }
+        self.update_query(rq, context);
+        true
+      },
+      Event::RunCommand(index) => {
+        self.run(index, hub, context);
+        true
+      },
+      Event::Gesture(GestureEvent::Cross(_)) => {
+        hub.send(Event::Close(ViewId::CommandPalette)).ok();
+        true
+      },
+      // The palette is modal.
+      Event::Gesture(..) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, rect: Rectangle, _fonts: &mut Fonts) {
+    if let Some(region) = rect.intersection(&self.rect) {
+      fb.draw_rectangle(&region, WHITE);
+    }
+  }
+
+  fn resize(&mut self, rect: Rectangle, _hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    self.rect = rect;
+    self.layout(context);
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+
+  fn view_id(&self) -> Option<ViewId> {
+    Some(ViewId::CommandPalette)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::fuzzy_score;
+
+  #[test]
+  fn test_fuzzy_score() {
+    assert_eq!(fuzzy_score("xyz", "Take Screenshot"), None);
+    assert!(fuzzy_score("ts", "Take Screenshot").is_some());
+    assert!(fuzzy_score("scr", "Take Screenshot") > fuzzy_score("scr", "Sort › Reverse Order"));
+    assert!(fuzzy_score("INV", "Invert Colors").is_some());
+  }
+}
@@ -1,9 +1,22 @@
+#[cfg(feature = "dictionary-manager")]
+use super::dictionary::manager::DictionaryManager;
+#[cfg(feature = "feeds")]
+use super::feeds::Feeds;
+#[cfg(feature = "opds")]
+use super::opds::OpdsBrowser;
 use super::{
+  calculator::Calculator,
+  dictionary::Dictionary,
+  home::Home,
   menu::{Menu, MenuKind},
   notification::Notification,
+  reader::Reader,
+  sketch::Sketch,
+  undo::UndoDir,
   AppCmd,
   EntryId,
   EntryKind,
+  Event,
   RenderData,
   RenderQueue,
   View,
@@ -12,9 +25,13 @@ use super::{
 use crate::{
   app::Context,
   device::CURRENT_DEVICE,
+  feedback::FeedbackKind,
   framebuffer::UpdateMode,
-  geom::{Point, Rectangle},
-  settings::{ButtonScheme, RotationLock},
+  geom::{CycleDir, Point, Rectangle},
+  i18n::available_languages,
+  input::ButtonCode,
+  search_history::input_name,
+  settings::{ButtonAction, ButtonScheme, NightFilterMode, PowerProfile, RotationLock},
 };
 use chrono::Local;
 use std::{env, sync::mpsc};
@@ -26,6 +43,64 @@ pub fn shift(view: &mut dyn View, delta: Point) {
   }
 }
 
+// Whether the given application fetches its content when the network is up.
+pub fn fetches_when_online(view: &dyn View) -> bool {
+  let apps: &[fn(&dyn View) -> bool] = &[
+    #[cfg(feature = "feeds")]
+    |view| view.is::<Feeds>(),
+    #[cfg(feature = "dictionary-manager")]
+    |view| view.is::<DictionaryManager>(),
+    #[cfg(feature = "opds")]
+    |view| view.is::<OpdsBrowser>(),
+  ];
+  apps.iter().any(|is_app| is_app(view))
+}
+
+// The action bound to a page turn button in the current application,
+// unless it's the default one.
+pub fn button_action(view: &dyn View, code: ButtonCode, context: &Context) -> Option<ButtonAction> {
+  let buttons = &context.settings.buttons;
+  #[cfg(feature = "feeds")]
+  let is_feeds = view.is::<Feeds>();
+  #[cfg(not(feature = "feeds"))]
+  let is_feeds = false;
+  let mapping = if view.is::<Reader>() {
+    &buttons.reader
+  } else if view.is::<Home>() {
+    &buttons.home
+  } else if view.is::<Sketch>() {
+    &buttons.sketch
+  } else if view.is::<Dictionary>() {
+    &buttons.dictionary
+  } else if view.is::<Calculator>() {
+    &buttons.calculator
+  } else if is_feeds {
+    &buttons.feeds
+  } else {
+    return None;
+  };
+  let action = match code {
+    ButtonCode::Backward => mapping.backward,
+    ButtonCode::Forward => mapping.forward,
+    _ => return None,
+  };
+  Some(action).filter(|a| *a != ButtonAction::Default)
+}
+
+pub fn button_action_event(action: ButtonAction) -> Option<Event> {
+  match action {
+    ButtonAction::Default | ButtonAction::None => None,
+    ButtonAction::PreviousPage => Some(Event::Page(CycleDir::Previous)),
+    ButtonAction::NextPage => Some(Event::Page(CycleDir::Next)),
+    ButtonAction::PreviousChapter => Some(Event::Chapter(CycleDir::Previous)),
+    ButtonAction::NextChapter => Some(Event::Chapter(CycleDir::Next)),
+    ButtonAction::Undo => Some(Event::Select(EntryId::Undo)),
+    ButtonAction::Redo => Some(Event::Select(EntryId::Redo)),
+    ButtonAction::Back => Some(Event::Back),
+    ButtonAction::ToggleFrontlight => Some(Event::ToggleFrontlight),
+  }
+}
+
 pub fn locate<T: View>(view: &dyn View) -> Option<usize> {
   for (index, child) in view.children().iter().enumerate() {
     if child.as_ref().is::<T>() {
@@ -78,6 +153,241 @@ pub fn transfer_notifications(
   }
 }
 
+pub fn main_menu_entries(context: &Context) -> Vec<EntryKind> {
+  let rotation = CURRENT_DEVICE.to_canonical(context.display.rotation);
+  let rotate = (0..4)
+    .map(|n| {
+      EntryKind::RadioButton(
+        (n as i16 * 90).to_string(),
+        EntryId::Rotate(CURRENT_DEVICE.from_canonical(n)),
+        n == rotation,
+      )
+    })
+    .collect::<Vec<EntryKind>>();
+
+  let night_filter = [
+    NightFilterMode::Off,
+    NightFilterMode::On,
+    NightFilterMode::Scheduled,
+  ]
+  .iter()
+  .map(|mode| {
+    EntryKind::RadioButton(
+      tr!(&mode.to_string()),
+      EntryId::SetNightFilter(*mode),
+      *mode == context.settings.night_filter.mode,
+    )
+  })
+  .collect::<Vec<EntryKind>>();
+
+  let mut power_profile = [
+    PowerProfile::Performance,
+    PowerProfile::Balanced,
+    PowerProfile::Saver,
+  ]
+  .iter()
+  .map(|profile| {
+    EntryKind::RadioButton(
+      tr!(&profile.to_string()),
+      EntryId::SetPowerProfile(*profile),
+      *profile == context.settings.power.profile,
+    )
+  })
+  .collect::<Vec<EntryKind>>();
+  power_profile.push(EntryKind::Separator);
+  power_profile.push(EntryKind::CheckBox(
+    tr!("Saver on Low Battery"),
+    EntryId::ToggleAutoPowerSaver,
+    context.settings.power.auto_saver,
+  ));
+
+  let mut languages = vec![EntryKind::RadioButton(
+    "English".to_string(),
+    EntryId::SetLanguage(String::new()),
+    context.settings.language.is_empty(),
+  )];
+  languages.extend(available_languages().into_iter().map(|(code, name)| {
+    let selected = context.settings.language == code;
+    EntryKind::RadioButton(name, EntryId::SetLanguage(code), selected)
+  }));
+
+  let apps = vec![
+    EntryKind::Command(
+      tr!("Dictionary"),
+      EntryId::Launch(AppCmd::Dictionary {
+        query: "".to_string(),
+        language: "".to_string(),
+      }),
+    ),
+    EntryKind::Command(tr!("Calculator"), EntryId::Launch(AppCmd::Calculator)),
+    #[cfg(feature = "feeds")]
+    EntryKind::Command(tr!("Feeds"), EntryId::Launch(AppCmd::Feeds)),
+    #[cfg(feature = "opds")]
+    EntryKind::Command(tr!("Catalogs"), EntryId::Launch(AppCmd::Opds)),
+    EntryKind::Command(
+      tr!("Sketch"),
+      EntryId::Launch(AppCmd::Sketch { file: None }),
+    ),
+  ];
+
+  let mut entries = vec![
+    EntryKind::Command(tr!("About"), EntryId::About),
+    EntryKind::Command(tr!("System Info"), EntryId::SystemInfo),
+    EntryKind::SubMenu(
+      tr!("Phrasebook"),
+      vec![
+        EntryKind::Command(tr!("Browse"), EntryId::BrowsePhrasebook),
+        EntryKind::Command(tr!("Export"), EntryId::ExportPhrasebook),
+      ],
+    ),
+    EntryKind::SubMenu(
+      tr!("Usage Insights"),
+      vec![
+        EntryKind::CheckBox(
+          tr!("Record"),
+          EntryId::ToggleUsageInsights,
+          context.settings.usage_insights,
+        ),
+        EntryKind::Command(tr!("Browse"), EntryId::BrowseInsights),
+        EntryKind::Command(tr!("Purge"), EntryId::PurgeInsights),
+      ],
+    ),
+    EntryKind::Command(tr!("Undo"), EntryId::Revert(UndoDir::Undo)),
+    EntryKind::Command(tr!("Redo"), EntryId::Revert(UndoDir::Redo)),
+    EntryKind::Separator,
+    EntryKind::CheckBox(
+      tr!("Invert Colors"),
+      EntryId::ToggleInverted,
+      context.fb.inverted(),
+    ),
+    EntryKind::CheckBox(
+      tr!("Make Bitonal"),
+      EntryId::ToggleMonochrome,
+      context.fb.monochrome(),
+    ),
+    EntryKind::SubMenu(tr!("Night Filter"), night_filter),
+    EntryKind::SubMenu(tr!("Power Profile"), power_profile),
+    EntryKind::CheckBox(
+      tr!("Enable WiFi"),
+      EntryId::ToggleWifi,
+      context.settings.wifi,
+    ),
+    EntryKind::CheckBox(
+      tr!("Private Sleep Screen"),
+      EntryId::TogglePrivateSleepScreen,
+      context.settings.private_sleep_screen,
+    ),
+    EntryKind::Separator,
+    EntryKind::SubMenu(tr!("Rotate"), rotate),
+    EntryKind::Command(tr!("Take Screenshot"), EntryId::TakeScreenshot),
+    EntryKind::CheckBox(
+      tr!("Benchmark Updates"),
+      EntryId::ToggleBenchmark,
+      context.fb.benchmark().is_some(),
+    ),
+    EntryKind::Separator,
+  ];
+
+  if !context.notifications.is_empty() {
+    let mut notifications = context
+      .notifications
+      .entries()
+      .map(EntryKind::Message)
+      .collect::<Vec<EntryKind>>();
+    notifications.push(EntryKind::Separator);
+    notifications.push(EntryKind::Command(
+      tr!("Clear"),
+      EntryId::ClearNotifications,
+    ));
+    entries.push(EntryKind::SubMenu(tr!("Notifications"), notifications));
+    entries.push(EntryKind::Separator);
+  }
+
+  let mut settings = vec![
+    EntryKind::SubMenu(tr!("Language"), languages),
+    EntryKind::Command(tr!("Tap Zones"), EntryId::Launch(AppCmd::TapZones)),
+  ];
+
+  if CURRENT_DEVICE.has_beeper() || CURRENT_DEVICE.has_vibrator() {
+    let feedback = vec![
+      EntryKind::CheckBox(
+        tr!("Page Turns"),
+        EntryId::ToggleFeedback(FeedbackKind::PageTurn),
+        context.settings.feedback.page_turns,
+      ),
+      EntryKind::CheckBox(
+        tr!("Key Presses"),
+        EntryId::ToggleFeedback(FeedbackKind::KeyPress),
+        context.settings.feedback.key_presses,
+      ),
+    ];
+    settings.push(EntryKind::SubMenu(tr!("Feedback"), feedback));
+  }
+  entries.extend(vec![
+    EntryKind::SubMenu(tr!("Applications"), apps),
+    EntryKind::SubMenu(tr!("Settings"), settings),
+    EntryKind::Separator,
+  ]);
+
+  if env::var_os("PLATO_STANDALONE").is_some() {
+    entries.push(EntryKind::Command(
+      tr!("Reboot in Nickel"),
+      EntryId::RebootInNickel,
+    ));
+    entries.push(EntryKind::Command(tr!("Reboot"), EntryId::Reboot));
+  } else {
+    entries.push(EntryKind::Command(tr!("Reboot"), EntryId::Reboot));
+    entries.push(EntryKind::Command(tr!("Quit"), EntryId::Quit));
+  }
+
+  if CURRENT_DEVICE.has_page_turn_buttons() {
+    let button_scheme = context.settings.button_scheme;
+    let button_schemes = vec![
+      EntryKind::RadioButton(
+        tr!(&ButtonScheme::Natural.to_string()),
+        EntryId::SetButtonScheme(ButtonScheme::Natural),
+        button_scheme == ButtonScheme::Natural,
+      ),
+      EntryKind::RadioButton(
+        tr!(&ButtonScheme::Inverted.to_string()),
+        EntryId::SetButtonScheme(ButtonScheme::Inverted),
+        button_scheme == ButtonScheme::Inverted,
+      ),
+    ];
+    entries.insert(5, EntryKind::SubMenu(tr!("Button Scheme"), button_schemes));
+  }
+
+  if CURRENT_DEVICE.has_gyroscope() {
+    let rotation_lock = context.settings.rotation_lock;
+    let gyro = vec![
+      EntryKind::RadioButton(
+        tr!("Auto"),
+        EntryId::SetRotationLock(None),
+        rotation_lock.is_none(),
+      ),
+      EntryKind::Separator,
+      EntryKind::RadioButton(
+        tr!("Portrait"),
+        EntryId::SetRotationLock(Some(RotationLock::Portrait)),
+        rotation_lock == Some(RotationLock::Portrait),
+      ),
+      EntryKind::RadioButton(
+        tr!("Landscape"),
+        EntryId::SetRotationLock(Some(RotationLock::Landscape)),
+        rotation_lock == Some(RotationLock::Landscape),
+      ),
+      EntryKind::RadioButton(
+        tr!("Ignore"),
+        EntryId::SetRotationLock(Some(RotationLock::Current)),
+        rotation_lock == Some(RotationLock::Current),
+      ),
+    ];
+    entries.insert(5, EntryKind::SubMenu(tr!("Gyroscope"), gyro));
+  }
+
+  entries
+}
+
 pub fn toggle_main_menu(
   view: &mut dyn View,
   rect: Rectangle,
@@ -99,118 +409,7 @@ pub fn toggle_main_menu(
       return;
     }
 
-    let rotation = CURRENT_DEVICE.to_canonical(context.display.rotation);
-    let rotate = (0..4)
-      .map(|n| {
-        EntryKind::RadioButton(
-          (n as i16 * 90).to_string(),
-          EntryId::Rotate(CURRENT_DEVICE.from_canonical(n)),
-          n == rotation,
-        )
-      })
-      .collect::<Vec<EntryKind>>();
-
-    let apps = vec![
-      EntryKind::Command(
-        "Dictionary".to_string(),
-        EntryId::Launch(AppCmd::Dictionary {
-          query: "".to_string(),
-          language: "".to_string(),
-        }),
-      ),
-      EntryKind::Command(
-        "Calculator".to_string(),
-        EntryId::Launch(AppCmd::Calculator),
-      ),
-      EntryKind::Command("Sketch".to_string(), EntryId::Launch(AppCmd::Sketch)),
-    ];
-
-    let mut entries = vec![
-      EntryKind::Command("About".to_string(), EntryId::About),
-      EntryKind::Command("System Info".to_string(), EntryId::SystemInfo),
-      EntryKind::Separator,
-      EntryKind::CheckBox(
-        "Invert Colors".to_string(),
-        EntryId::ToggleInverted,
-        context.fb.inverted(),
-      ),
-      EntryKind::CheckBox(
-        "Make Bitonal".to_string(),
-        EntryId::ToggleMonochrome,
-        context.fb.monochrome(),
-      ),
-      EntryKind::CheckBox(
-        "Enable WiFi".to_string(),
-        EntryId::ToggleWifi,
-        context.settings.wifi,
-      ),
-      EntryKind::Separator,
-      EntryKind::SubMenu("Rotate".to_string(), rotate),
-      EntryKind::Command("Take Screenshot".to_string(), EntryId::TakeScreenshot),
-      EntryKind::Separator,
-      EntryKind::SubMenu("Applications".to_string(), apps),
-      EntryKind::Separator,
-    ];
-
-    if env::var_os("PLATO_STANDALONE").is_some() {
-      entries.push(EntryKind::Command(
-        "Reboot in Nickel".to_string(),
-        EntryId::RebootInNickel,
-      ));
-      entries.push(EntryKind::Command("Reboot".to_string(), EntryId::Reboot));
-    } else {
-      entries.push(EntryKind::Command("Reboot".to_string(), EntryId::Reboot));
-      entries.push(EntryKind::Command("Quit".to_string(), EntryId::Quit));
-    }
-
-    if CURRENT_DEVICE.has_page_turn_buttons() {
-      let button_scheme = context.settings.button_scheme;
-      let button_schemes = vec![
-        EntryKind::RadioButton(
-          ButtonScheme::Natural.to_string(),
-          EntryId::SetButtonScheme(ButtonScheme::Natural),
-          button_scheme == ButtonScheme::Natural,
-        ),
-        EntryKind::RadioButton(
-          ButtonScheme::Inverted.to_string(),
-          EntryId::SetButtonScheme(ButtonScheme::Inverted),
-          button_scheme == ButtonScheme::Inverted,
-        ),
-      ];
-      entries.insert(
-        5,
-        EntryKind::SubMenu("Button Scheme".to_string(), button_schemes),
-      );
-    }
-
-    if CURRENT_DEVICE.has_gyroscope() {
-      let rotation_lock = context.settings.rotation_lock;
-      let gyro = vec![
-        EntryKind::RadioButton(
-          "Auto".to_string(),
-          EntryId::SetRotationLock(None),
-          rotation_lock.is_none(),
-        ),
-        EntryKind::Separator,
-        EntryKind::RadioButton(
-          "Portrait".to_string(),
-          EntryId::SetRotationLock(Some(RotationLock::Portrait)),
-          rotation_lock == Some(RotationLock::Portrait),
-        ),
-        EntryKind::RadioButton(
-          "Landscape".to_string(),
-          EntryId::SetRotationLock(Some(RotationLock::Landscape)),
-          rotation_lock == Some(RotationLock::Landscape),
-        ),
-        EntryKind::RadioButton(
-          "Ignore".to_string(),
-          EntryId::SetRotationLock(Some(RotationLock::Current)),
-          rotation_lock == Some(RotationLock::Current),
-        ),
-      ];
-      entries.insert(5, EntryKind::SubMenu("Gyroscope".to_string(), gyro));
-    }
-
+    let entries = main_menu_entries(context);
     let main_menu = Menu::new(rect, ViewId::MainMenu, MenuKind::DropDown, entries, context);
     rq.add(RenderData::new(
       main_menu.id(),
@@ -247,7 +446,7 @@ pub fn toggle_battery_menu(
       (Ok(status), Ok(capacity)) => format!("{:?} {}%", status, capacity),
       (Ok(status), Err(..)) => format!("{:?}", status),
       (Err(..), Ok(capacity)) => format!("{} %", capacity),
-      _ => "Unknown".to_string(),
+      _ => tr!("Unknown"),
     };
     let entries = vec![EntryKind::Message(text)];
     let battery_menu = Menu::new(
@@ -329,12 +528,45 @@ pub fn toggle_input_history_menu(
     if let Some(false) = enable {
       return;
     }
-    let entries = context.input_history.get(&id).map(|h| {
-      h.iter()
-        .map(|s| EntryKind::Command(s.to_string(), EntryId::SetInputText(id, s.to_string())))
-        .collect::<Vec<EntryKind>>()
-    });
-    if let Some(entries) = entries {
+    let saved = context.saved_searches.get(&id).cloned().unwrap_or_default();
+    let mut entries: Vec<EntryKind> = saved
+      .iter()
+      .map(|s| EntryKind::Command(s.to_string(), EntryId::SetInputText(id, s.to_string())))
+      .collect();
+    if context.clipboard.is_some() {
+      if !entries.is_empty() {
+        entries.insert(0, EntryKind::Separator);
+      }
+      entries.insert(0, EntryKind::Command(tr!("Paste"), EntryId::PasteInput(id)));
+    }
+    if let Some(h) = context.input_history.get(&id).filter(|h| !h.is_empty()) {
+      if !entries.is_empty() {
+        entries.push(EntryKind::Separator);
+      }
+      entries.extend(
+        h.iter()
+          .filter(|s| !saved.contains(*s))
+          .map(|s| EntryKind::Command(s.to_string(), EntryId::SetInputText(id, s.to_string()))),
+      );
+    }
+    // The saved searches are only offered by the search inputs.
+    if input_name(id).is_some() {
+      if !entries.is_empty() {
+        entries.push(EntryKind::Separator);
+      }
+      entries.push(EntryKind::Command(
+        tr!("Save Search"),
+        EntryId::SaveSearch(id),
+      ));
+      if !saved.is_empty() {
+        let removals = saved
+          .iter()
+          .map(|s| EntryKind::Command(s.to_string(), EntryId::RemoveSavedSearch(id, s.to_string())))
+          .collect();
+        entries.push(EntryKind::SubMenu(tr!("Remove Saved Search"), removals));
+      }
+    }
+    if !entries.is_empty() {
       let menu_kind = match id {
         ViewId::HomeSearchInput
         | ViewId::ReaderSearchInput
@@ -1,7 +1,5 @@
 use super::{
   button::Button,
-  label::Label,
-  Align,
   Bus,
   Event,
   Hub,
@@ -16,9 +14,9 @@ use super::{
 };
 use crate::{
   app::Context,
-  color::{BLACK, WHITE},
+  color::{BLACK, TEXT_NORMAL, WHITE},
   device::CURRENT_DEVICE,
-  font::{font_from_style, Fonts, NORMAL_STYLE},
+  font::{font_from_style, Ellipsis, Fonts, NORMAL_STYLE},
   framebuffer::Framebuffer,
   geom::{BorderSpec, CornerSpec, Rectangle},
   gesture::GestureEvent,
@@ -28,6 +26,7 @@ use std::thread;
 
 const LABEL_VALIDATE: &str = "OK";
 const LABEL_CANCEL: &str = "Cancel";
+const MAX_MESSAGE_LINES: usize = 4;
 
 pub struct Dialog {
   id: Id,
@@ -35,6 +34,8 @@ pub struct Dialog {
   children: Vec<Box<dyn View>>,
   view_id: ViewId,
   event: Option<Event>,
+  text: String,
+  max_message_width: i32,
   will_close: bool,
 }
 
@@ -54,26 +55,18 @@ impl Dialog {
     let max_button_width = width as i32 / 4;
     let button_height = 4 * x_height;
 
-    let plan = font.plan(&text, Some(max_message_width), None);
+    let line_height = font.line_height();
+    let lines = font.plan_lines(&text, max_message_width, MAX_MESSAGE_LINES, Ellipsis::End);
+    let message_width = lines.iter().map(|plan| plan.width).max().unwrap_or(0);
+    let message_height = button_height + (lines.len().max(1) as i32 - 1) * line_height;
 
-    let dialog_width = plan.width.max(min_message_width) + 3 * padding;
-    let dialog_height = 2 * button_height + 3 * padding;
+    let dialog_width = message_width.max(min_message_width) + 3 * padding;
+    let dialog_height = message_height + button_height + 3 * padding;
 
     let dx = (width as i32 - dialog_width) / 2;
     let dy = (height as i32 - dialog_height) / 2;
     let rect = rect![dx, dy, dx + dialog_width, dy + dialog_height];
 
-    let rect_label = rect![
-      rect.min.x + padding,
-      rect.min.y + padding,
-      rect.max.x - padding,
-      rect.min.y + padding + button_height
-    ];
-
-    let label = Label::new(rect_label, text, Align::Center);
-
-    children.push(Box::new(label) as Box<dyn View>);
-
     let plan_cancel = event
       .as_ref()
       .map(|_| font.plan(LABEL_CANCEL, Some(max_button_width), None));
@@ -107,6 +100,8 @@ impl Dialog {
       children,
       view_id,
       event,
+      text,
+      max_message_width,
       will_close: false,
     }
   }
@@ -149,7 +144,7 @@ impl View for Dialog {
     }
   }
 
-  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
     let dpi = CURRENT_DEVICE.dpi;
 
     let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
@@ -164,6 +159,30 @@ impl View for Dialog {
       },
       &WHITE,
     );
+
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let line_height = font.line_height();
+    let padding = font.em() as i32;
+    let lines = font.plan_lines(
+      &self.text,
+      self.max_message_width,
+      MAX_MESSAGE_LINES,
+      Ellipsis::End,
+    );
+
+    // The message is centered in the space above the buttons.
+    let message_height = self.rect.height() as i32 - 4 * x_height - 3 * padding;
+    let text_height = x_height + (lines.len().max(1) as i32 - 1) * line_height;
+    let mut pt = pt!(
+      0,
+      self.rect.min.y + padding + (message_height - text_height) / 2 + x_height
+    );
+    for plan in &lines {
+      pt.x = self.rect.center().x - plan.width / 2;
+      font.render(fb, TEXT_NORMAL[1], plan, pt);
+      pt.y += line_height;
+    }
   }
 
   fn resize(&mut self, _rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
@@ -192,15 +211,7 @@ impl View for Dialog {
     let dy = (height as i32 - dialog_height) / 2;
     let rect = rect![dx, dy, dx + dialog_width, dy + dialog_height];
 
-    let label_rect = rect![
-      rect.min.x + padding,
-      rect.min.y + padding,
-      rect.max.x - padding,
-      rect.min.y + padding + button_height
-    ];
-    self.children[0].resize(label_rect, hub, rq, context);
-
-    let mut index = 1;
+    let mut index = 0;
     if self.event.is_some() {
       let cancel_rect = rect![
         rect.min.x + padding,
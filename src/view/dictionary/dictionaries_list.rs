@@ -0,0 +1,252 @@
+use super::manager::{DictionaryItem, ItemKind};
+use crate::{
+  app::Context,
+  color::{SEPARATOR_NORMAL, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  document::HumanSize,
+  font::{font_from_style, Fonts, MD_AUTHOR, MD_SIZE, MD_TITLE},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{divide, halves, CycleDir, Dir, Rectangle},
+  gesture::GestureEvent,
+  unit::scale_by_dpi,
+  view::{
+    filler::Filler,
+    Bus,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    BIG_BAR_HEIGHT,
+    ID_FEEDER,
+    THICKNESS_MEDIUM,
+  },
+};
+
+pub struct DictionariesList {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  pub max_lines: usize,
+}
+
+impl DictionariesList {
+  pub fn new(rect: Rectangle) -> DictionariesList {
+    let dpi = CURRENT_DEVICE.dpi;
+    let big_height = scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let max_lines = ((rect.height() as i32 + thickness) / big_height) as usize;
+    DictionariesList {
+      id: ID_FEEDER.next(),
+      rect,
+      children: vec![],
+      max_lines,
+    }
+  }
+
+  // The index of the first item is given so that the rows report their position in the whole list.
+  pub fn update(&mut self, items: &[DictionaryItem], first_index: usize, rq: &mut RenderQueue) {
+    self.children.clear();
+    let dpi = CURRENT_DEVICE.dpi;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+    let max_lines = self.max_lines.max(1);
+    let heights = divide(self.rect.height() as i32, max_lines as i32);
+    let mut y_pos = self.rect.min.y;
+
+    for (index, item) in items.iter().enumerate() {
+      let y_min = y_pos + if index > 0 { big_thickness } else { 0 };
+      let y_max = y_pos + heights[index]
+        - if index < max_lines - 1 {
+          small_thickness
+        } else {
+          0
+        };
+      let row = DictionaryRow::new(
+        rect![self.rect.min.x, y_min, self.rect.max.x, y_max],
+        item.clone(),
+        first_index + index,
+      );
+      self.children.push(Box::new(row) as Box<dyn View>);
+      if index < max_lines - 1 {
+        let separator = Filler::new(
+          rect![self.rect.min.x, y_max, self.rect.max.x, y_max + thickness],
+          SEPARATOR_NORMAL,
+        );
+        self.children.push(Box::new(separator) as Box<dyn View>);
+      }
+      y_pos += heights[index];
+    }
+
+    if items.len() < max_lines {
+      let y_start = y_pos + if items.is_empty() { 0 } else { thickness };
+      let filler = Filler::new(
+        rect![self.rect.min.x, y_start, self.rect.max.x, self.rect.max.y],
+        WHITE,
+      );
+      self.children.push(Box::new(filler) as Box<dyn View>);
+    }
+
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Partial));
+  }
+}
+
+impl View for DictionariesList {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    _hub: &Hub,
+    bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
+        match context.ui_dir(dir) {
+          Dir::West => {
+            bus.push_back(Event::Page(CycleDir::Next));
+            true
+          },
+          Dir::East => {
+            bus.push_back(Event::Page(CycleDir::Previous));
+            true
+          },
+          _ => false,
+        }
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, _fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {}
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
+
+struct DictionaryRow {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  item: DictionaryItem,
+  index: usize,
+}
+
+impl DictionaryRow {
+  fn new(rect: Rectangle, item: DictionaryItem, index: usize) -> DictionaryRow {
+    DictionaryRow {
+      id: ID_FEEDER.next(),
+      rect,
+      children: vec![],
+      item,
+      index,
+    }
+  }
+}
+
+impl View for DictionaryRow {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    _hub: &Hub,
+    bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Tap(center))
+      | Event::Gesture(GestureEvent::HoldFingerShort(center, ..))
+        if self.rect.includes(center) =>
+      {
+        let pt = pt!(center.x, self.rect.center().y);
+        bus.push_back(Event::ToggleBookMenu(Rectangle::from_point(pt), self.index));
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    fb.draw_rectangle(&self.rect, TEXT_NORMAL[0]);
+
+    let (x_height, padding, baseline) = {
+      let font = font_from_style(fonts, &MD_TITLE, dpi);
+      let x_height = font.x_heights.0 as i32;
+      (
+        x_height,
+        font.em() as i32,
+        (self.rect.height() as i32 - 2 * x_height) / 3,
+      )
+    };
+
+    let size_width = 8 * x_height;
+    let width = self.rect.width() as i32 - size_width - 2 * padding;
+
+    {
+      let font = font_from_style(fonts, &MD_TITLE, dpi);
+      let plan = font.plan(&self.item.name, Some(width), None);
+      let pt = pt!(
+        self.rect.min.x + padding,
+        self.rect.min.y + baseline + x_height
+      );
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    }
+
+    {
+      let font = font_from_style(fonts, &MD_AUTHOR, dpi);
+      let plan = font.plan(&self.item.details, Some(width), None);
+      let pt = pt!(self.rect.min.x + padding, self.rect.max.y - baseline);
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    }
+
+    // The size of the available dictionaries is only known when the index gives it.
+    let size = match self.item.kind {
+      ItemKind::Available(_) if self.item.size == 0 => String::new(),
+      _ => self.item.size.human_size(),
+    };
+    let font = font_from_style(fonts, &MD_SIZE, dpi);
+    let plan = font.plan(&size, Some(size_width), None);
+    let dy = (self.rect.height() as i32 - font.x_heights.0 as i32) / 2;
+    let pt = pt!(self.rect.max.x - padding - plan.width, self.rect.max.y - dy);
+    font.render(fb, TEXT_NORMAL[1], &plan, pt);
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
@@ -0,0 +1,799 @@
+use super::dictionaries_list::DictionariesList;
+use crate::{
+  app::{Context, DICTIONARIES_DIRNAME},
+  color::BLACK,
+  device::CURRENT_DEVICE,
+  font::Fonts,
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{halves, CycleDir, Rectangle},
+  gesture::GestureEvent,
+  helpers::IsHidden,
+  input::DeviceEvent,
+  network::NetworkTask,
+  unit::scale_by_dpi,
+  view::{
+    common::{locate_by_id, toggle_battery_menu, toggle_clock_menu, toggle_main_menu},
+    filler::Filler,
+    home::bottom_bar::BottomBar,
+    menu::{Menu, MenuKind},
+    report::{report_error, Severity},
+    top_bar::TopBar,
+    Bus,
+    EntryId,
+    EntryKind,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_MEDIUM,
+  },
+};
+use anyhow::{format_err, Error};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::{self, File},
+  io::{self, Read, Write},
+  path::{Component, Path, PathBuf},
+  thread,
+  time::Duration,
+};
+use walkdir::WalkDir;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+// Dictionaries can be large.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(3600);
+const DOWNLOAD_FILENAME: &str = ".download";
+
+// An entry of the dictionaries index.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexEntry {
+  pub name: String,
+  pub description: String,
+  // The archives are unpacked, the other files are saved as is.
+  pub urls: Vec<String>,
+  pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum ItemKind {
+  // The path of the index or of the ZIM file.
+  Installed(PathBuf),
+  // The position in the dictionaries index.
+  Available(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct DictionaryItem {
+  pub name: String,
+  pub details: String,
+  pub size: u64,
+  pub kind: ItemKind,
+}
+
+pub struct DictionaryManager {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  items: Vec<DictionaryItem>,
+  index: Vec<IndexEntry>,
+  current_page: usize,
+  pages_count: usize,
+  busy: bool,
+  // Fetch the index when the network is up.
+  pending_fetch: bool,
+}
+
+impl DictionaryManager {
+  pub fn new(
+    rect: Rectangle,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> DictionaryManager {
+    let id = ID_FEEDER.next();
+    let mut children = Vec::new();
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+
+    let top_bar = TopBar::new(
+      rect![
+        rect.min.x,
+        rect.min.y,
+        rect.max.x,
+        rect.min.y + small_height - small_thickness
+      ],
+      Event::Back,
+      tr!("Dictionaries"),
+      context,
+    );
+    children.push(Box::new(top_bar) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height - small_thickness,
+        rect.max.x,
+        rect.min.y + small_height + big_thickness
+      ],
+      BLACK,
+    );
+    children.push(Box::new(separator) as Box<dyn View>);
+
+    let list = DictionariesList::new(rect![
+      rect.min.x,
+      rect.min.y + small_height + big_thickness,
+      rect.max.x,
+      rect.max.y - small_height - small_thickness
+    ]);
+    children.push(Box::new(list) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height - small_thickness,
+        rect.max.x,
+        rect.max.y - small_height + big_thickness
+      ],
+      BLACK,
+    );
+    children.push(Box::new(separator) as Box<dyn View>);
+
+    let bottom_bar = BottomBar::new(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height + big_thickness,
+        rect.max.x,
+        rect.max.y
+      ],
+      0,
+      0,
+      &tr!("Installed"),
+      0,
+      false,
+    );
+    children.push(Box::new(bottom_bar) as Box<dyn View>);
+
+    rq.add(RenderData::new(id, rect, UpdateMode::Gui));
+
+    let mut manager = DictionaryManager {
+      id,
+      rect,
+      children,
+      items: Vec::new(),
+      index: Vec::new(),
+      current_page: 0,
+      pages_count: 0,
+      busy: false,
+      pending_fetch: false,
+    };
+
+    manager.reload(&mut RenderQueue::new());
+    manager.fetch(hub, context);
+
+    manager
+  }
+
+  // The installed dictionaries come first, followed by the ones that can be installed.
+  fn reload(&mut self, rq: &mut RenderQueue) {
+    let root = Path::new(DICTIONARIES_DIRNAME);
+    let mut items = installed_dictionaries(root);
+    for (index, entry) in self.index.iter().enumerate() {
+      if root.join(install_dirname(&entry.name)).exists() {
+        continue;
+      }
+      let details = if entry.description.is_empty() {
+        tr!("Available")
+      } else {
+        entry.description.clone()
+      };
+      items.push(DictionaryItem {
+        name: entry.name.clone(),
+        details,
+        size: entry.size,
+        kind: ItemKind::Available(index),
+      });
+    }
+    self.items = items;
+    self.update_list(rq);
+    self.update_bottom_bar(rq);
+  }
+
+  fn update_list(&mut self, rq: &mut RenderQueue) {
+    let list = self.children[2].downcast_mut::<DictionariesList>().unwrap();
+    let max_lines = list.max_lines.max(1);
+    self.pages_count = (self.items.len() as f32 / max_lines as f32).ceil() as usize;
+    self.current_page = self.current_page.min(self.pages_count.saturating_sub(1));
+    let index_lower = self.current_page * max_lines;
+    let index_upper = (index_lower + max_lines).min(self.items.len());
+    list.update(&self.items[index_lower..index_upper], index_lower, rq);
+  }
+
+  fn update_bottom_bar(&mut self, rq: &mut RenderQueue) {
+    let installed = self
+      .items
+      .iter()
+      .filter(|item| matches!(item.kind, ItemKind::Installed(..)))
+      .count();
+    let bottom_bar = self.children[4].downcast_mut::<BottomBar>().unwrap();
+    bottom_bar.update_library_label(&tr!("Installed"), installed, false, rq);
+    bottom_bar.update_page_label(self.current_page, self.pages_count, rq);
+    bottom_bar.update_icons(self.current_page, self.pages_count, rq);
+  }
+
+  fn go_to_neighbor(&mut self, dir: CycleDir, rq: &mut RenderQueue) {
+    match dir {
+      CycleDir::Next if self.current_page < self.pages_count.saturating_sub(1) => {
+        self.current_page += 1;
+      },
+      CycleDir::Previous if self.current_page > 0 => {
+        self.current_page -= 1;
+      },
+      _ => return,
+    }
+    self.update_list(rq);
+    self.update_bottom_bar(rq);
+  }
+
+  fn fetch(&mut self, hub: &Hub, context: &mut Context) {
+    let url = context.settings.dictionary.index_url.clone();
+    if self.busy || url.is_empty() {
+      return;
+    }
+
+    if !context.online {
+      self.pending_fetch = true;
+      hub
+        .send(Event::AcquireNetwork(NetworkTask::Dictionaries))
+        .ok();
+      return;
+    }
+
+    self.busy = true;
+    self.pending_fetch = false;
+    let hub2 = hub.clone();
+    hub
+      .send(Event::AcquireNetwork(NetworkTask::Dictionaries))
+      .ok();
+    thread::spawn(move || {
+      let index = fetch_index(&url)
+        .map_err(|e| {
+          report_error(
+            &hub2,
+            Severity::Error,
+            "Can't fetch the dictionaries index",
+            &e,
+          )
+        })
+        .unwrap_or_default();
+      hub2.send(Event::DictionaryIndex(index)).ok();
+      hub2
+        .send(Event::ReleaseNetwork(NetworkTask::Dictionaries))
+        .ok();
+    });
+  }
+
+  fn install(&mut self, index: usize, hub: &Hub, context: &mut Context) {
+    let entry = match self.index.get(index) {
+      Some(entry) => entry.clone(),
+      None => return,
+    };
+    if self.busy {
+      hub
+        .send(Event::Notify(tr!("Another operation is in progress.")))
+        .ok();
+      return;
+    }
+    if !context.online {
+      hub.send(Event::Notify(tr!("The network is down."))).ok();
+      return;
+    }
+
+    self.busy = true;
+    let hub2 = hub.clone();
+    hub
+      .send(Event::AcquireNetwork(NetworkTask::Dictionaries))
+      .ok();
+    thread::spawn(move || {
+      let dir = Path::new(DICTIONARIES_DIRNAME).join(install_dirname(&entry.name));
+      match install_dictionary(&entry, &dir, &hub2) {
+        Ok(()) => {
+          hub2
+            .send(Event::Notify(tr!("Installed {}.", entry.name)))
+            .ok();
+        },
+        Err(e) => {
+          fs::remove_dir_all(&dir).ok();
+          report_error(
+            &hub2,
+            Severity::Error,
+            &tr!("Can't install {}", entry.name),
+            &e,
+          );
+        },
+      }
+      hub2
+        .send(Event::Progress(
+          ViewId::FetchProgress,
+          String::new(),
+          Some(1.0),
+        ))
+        .ok();
+      hub2.send(Event::Select(EntryId::ReloadDictionaries)).ok();
+      hub2
+        .send(Event::ReleaseNetwork(NetworkTask::Dictionaries))
+        .ok();
+    });
+  }
+
+  fn toggle_title_menu(
+    &mut self,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::TitleMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let entries = vec![
+        EntryKind::Command(tr!("Refresh"), EntryId::Refresh),
+        EntryKind::Command(tr!("Reload Dictionaries"), EntryId::ReloadDictionaries),
+      ];
+      let title_menu = Menu::new(
+        rect,
+        ViewId::TitleMenu,
+        MenuKind::DropDown,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        title_menu.id(),
+        *title_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(title_menu) as Box<dyn View>);
+    }
+  }
+
+  fn toggle_dictionary_menu(
+    &mut self,
+    index: usize,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::BookMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let entry = match self.items.get(index).map(|item| &item.kind) {
+        Some(ItemKind::Installed(path)) => {
+          EntryKind::Command(tr!("Remove"), EntryId::Remove(path.clone()))
+        },
+        Some(ItemKind::Available(index)) => {
+          EntryKind::Command(tr!("Install"), EntryId::InstallDictionary(*index))
+        },
+        None => return,
+      };
+
+      let dictionary_menu = Menu::new(
+        rect,
+        ViewId::BookMenu,
+        MenuKind::Contextual,
+        vec![entry],
+        context,
+      );
+      rq.add(RenderData::new(
+        dictionary_menu.id(),
+        *dictionary_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self
+        .children
+        .push(Box::new(dictionary_menu) as Box<dyn View>);
+    }
+  }
+}
+
+// Derives the name of the directory where a dictionary is installed from its name.
+fn install_dirname(name: &str) -> String {
+  let name = name
+    .trim()
+    .trim_start_matches('.')
+    .replace(|c: char| c == '/' || c == '\\', "_");
+  if name.is_empty() {
+    "_".to_string()
+  } else {
+    name
+  }
+}
+
+fn fetch_index(url: &str) -> Result<Vec<IndexEntry>, Error> {
+  let client = Client::builder().timeout(FETCH_TIMEOUT).build()?;
+  let index = client.get(url).send()?.error_for_status()?.json()?;
+  Ok(index)
+}
+
+fn install_dictionary(entry: &IndexEntry, dir: &Path, hub: &Hub) -> Result<(), Error> {
+  if entry.urls.is_empty() {
+    return Err(format_err!("no URLs are given"));
+  }
+  fs::create_dir_all(dir)?;
+  let client = Client::builder().timeout(DOWNLOAD_TIMEOUT).build()?;
+  let download_path = dir.join(DOWNLOAD_FILENAME);
+
+  for url in &entry.urls {
+    let filename = url
+      .split(|c| c == '?' || c == '#')
+      .next()
+      .and_then(|s| s.rsplit('/').next())
+      .map(install_dirname)
+      .ok_or_else(|| format_err!("invalid URL: {}", url))?;
+    download(
+      client.get(url),
+      &download_path,
+      &tr!("Downloading {}", filename),
+      hub,
+    )?;
+    if filename.to_lowercase().ends_with(".zip") {
+      unpack(&download_path, dir)?;
+      fs::remove_file(&download_path)?;
+    } else {
+      fs::rename(&download_path, dir.join(&filename))?;
+    }
+  }
+
+  Ok(())
+}
+
+// Saves the response to *request* at *path*, reporting the progress under *label*.
+pub fn download(request: RequestBuilder, path: &Path, label: &str, hub: &Hub) -> Result<(), Error> {
+  let mut response = request.send()?.error_for_status()?;
+  let total = response.content_length();
+  let mut file = File::create(path)?;
+  let mut buf = vec![0; 1 << 16];
+  let mut done = 0u64;
+  let mut last_step = 0;
+
+  loop {
+    let n = response.read(&mut buf)?;
+    if n == 0 {
+      break;
+    }
+    file.write_all(&buf[..n])?;
+    done += n as u64;
+    // Report the progress at every percent.
+    let progress = total.map(|t| done as f32 / t.max(1) as f32);
+    let step = progress.map_or(done >> 20, |p| (100.0 * p) as u64);
+    if step > last_step {
+      last_step = step;
+      hub
+        .send(Event::Progress(
+          ViewId::FetchProgress,
+          label.to_string(),
+          progress,
+        ))
+        .ok();
+    }
+  }
+
+  Ok(())
+}
+
+// Extracts the files of a ZIP archive, ignoring the entries that would land outside of `dir`.
+fn unpack(path: &Path, dir: &Path) -> Result<(), Error> {
+  let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+  for i in 0..archive.len() {
+    let mut entry = archive.by_index(i)?;
+    let name = PathBuf::from(entry.name());
+    if name
+      .components()
+      .any(|c| !matches!(c, Component::Normal(_)))
+    {
+      continue;
+    }
+    let target = dir.join(&name);
+    if entry.is_dir() {
+      fs::create_dir_all(&target)?;
+      continue;
+    }
+    if let Some(parent) = target.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(&target)?;
+    io::copy(&mut entry, &mut file)?;
+  }
+  Ok(())
+}
+
+// The dictd content files that go with the given index.
+fn content_paths(index_path: &Path) -> [PathBuf; 2] {
+  [
+    index_path.with_extension("dict.dz"),
+    index_path.with_extension("dict"),
+  ]
+}
+
+fn installed_dictionaries(root: &Path) -> Vec<DictionaryItem> {
+  let mut items = Vec::new();
+  for entry in WalkDir::new(root)
+    .min_depth(1)
+    .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    .into_iter()
+    .filter_entry(|e| !e.is_hidden())
+    .filter_map(|e| e.ok())
+  {
+    let path = entry.path();
+    let size_of = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let (kind, size) = match path.extension().and_then(|e| e.to_str()) {
+      Some("zim") => ("ZIM", size_of(path)),
+      Some("index") => (
+        "dictd",
+        size_of(path) + content_paths(path).iter().map(|p| size_of(p)).sum::<u64>(),
+      ),
+      _ => continue,
+    };
+    let name = path
+      .file_stem()
+      .map(|s| s.to_string_lossy().into_owned())
+      .unwrap_or_default();
+    let location = path
+      .strip_prefix(root)
+      .ok()
+      .and_then(|p| p.parent())
+      .filter(|p| p != &Path::new(""))
+      .map(|p| format!(" — {}", p.display()))
+      .unwrap_or_default();
+    items.push(DictionaryItem {
+      name,
+      details: format!("{}{}", kind, location),
+      size,
+      kind: ItemKind::Installed(path.to_path_buf()),
+    });
+  }
+  items
+}
+
+// Removes the files of a dictionary, and its directory when it becomes empty.
+fn remove_dictionary(root: &Path, path: &Path) -> Result<(), Error> {
+  fs::remove_file(path)?;
+  if path.extension().and_then(|e| e.to_str()) == Some("index") {
+    for content_path in &content_paths(path) {
+      if content_path.exists() {
+        fs::remove_file(content_path)?;
+      }
+    }
+  }
+  if let Some(parent) = path.parent().filter(|p| *p != root) {
+    if fs::read_dir(parent)?.next().is_none() {
+      fs::remove_dir(parent)?;
+    }
+  }
+  Ok(())
+}
+
+impl View for DictionaryManager {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Page(dir) => {
+        self.go_to_neighbor(dir, rq);
+        true
+      },
+      Event::ToggleBookMenu(rect, index) => {
+        self.toggle_dictionary_menu(index, rect, None, rq, context);
+        true
+      },
+      Event::Select(EntryId::InstallDictionary(index)) => {
+        self.install(index, hub, context);
+        true
+      },
+      Event::Select(EntryId::Remove(ref path)) => {
+        if let Err(e) = remove_dictionary(Path::new(DICTIONARIES_DIRNAME), path) {
+          report_error(hub, Severity::Error, "Can't remove the dictionary", &e);
+        }
+        hub.send(Event::Select(EntryId::ReloadDictionaries)).ok();
+        true
+      },
+      Event::Select(EntryId::ReloadDictionaries) => {
+        self.busy = false;
+        context.dictionaries.clear();
+        context.load_dictionaries();
+        self.reload(rq);
+        true
+      },
+      Event::Select(EntryId::Refresh) => {
+        if context.settings.dictionary.index_url.is_empty() {
+          hub
+            .send(Event::Notify(tr!("No dictionaries index is configured.")))
+            .ok();
+        } else {
+          self.fetch(hub, context);
+        }
+        true
+      },
+      Event::DictionaryIndex(ref index) => {
+        self.busy = false;
+        self.index = index.clone();
+        self.reload(rq);
+        true
+      },
+      Event::Device(DeviceEvent::NetUp) => {
+        if self.pending_fetch {
+          self.fetch(hub, context);
+        }
+        true
+      },
+      Event::ToggleNear(ViewId::TitleMenu, rect) => {
+        self.toggle_title_menu(rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::MainMenu, rect) => {
+        toggle_main_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::BatteryMenu, rect) => {
+        toggle_battery_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::ClockMenu, rect) => {
+        toggle_clock_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::Reseed => {
+        self.reload(rq);
+        true
+      },
+      Event::Gesture(GestureEvent::Cross(_)) => {
+        hub.send(Event::Back).ok();
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, _fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {}
+
+  fn resize(&mut self, rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+
+    self.children[0].resize(
+      rect![
+        rect.min.x,
+        rect.min.y,
+        rect.max.x,
+        rect.min.y + small_height - small_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[1].resize(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height - small_thickness,
+        rect.max.x,
+        rect.min.y + small_height + big_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[2] = Box::new(DictionariesList::new(rect![
+      rect.min.x,
+      rect.min.y + small_height + big_thickness,
+      rect.max.x,
+      rect.max.y - small_height - small_thickness
+    ])) as Box<dyn View>;
+
+    self.children[3].resize(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height - small_thickness,
+        rect.max.x,
+        rect.max.y - small_height + big_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[4].resize(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height + big_thickness,
+        rect.max.x,
+        rect.max.y
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    // Floating windows.
+    for i in 5..self.children.len() {
+      self.children[i].resize(rect, hub, rq, context);
+    }
+
+    self.rect = rect;
+    self.update_list(&mut RenderQueue::new());
+    self.update_bottom_bar(&mut RenderQueue::new());
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Full));
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::install_dirname;
+
+  #[test]
+  fn test_install_dirname() {
+    assert_eq!(install_dirname("WordNet 3.0"), "WordNet 3.0");
+    assert_eq!(install_dirname("../etc"), "_etc");
+    assert_eq!(install_dirname(" a/b\\c "), "a_b_c");
+    assert_eq!(install_dirname("..."), "_");
+  }
+}
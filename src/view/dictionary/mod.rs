@@ -1,4 +1,8 @@
 mod bottom_bar;
+#[cfg(feature = "dictionary-manager")]
+mod dictionaries_list;
+#[cfg(feature = "dictionary-manager")]
+pub mod manager;
 
 use self::bottom_bar::BottomBar;
 use crate::{
@@ -10,6 +14,7 @@ use crate::{
   framebuffer::{Framebuffer, Pixmap, UpdateMode},
   geom::{halves, CycleDir, Dir, Point, Rectangle},
   gesture::GestureEvent,
+  insights::Usage,
   unit::scale_by_dpi,
   view::{
     common::{locate, locate_by_id, toggle_battery_menu, toggle_clock_menu, toggle_main_menu},
@@ -36,6 +41,8 @@ use crate::{
     THICKNESS_MEDIUM,
   },
 };
+#[cfg(feature = "dictionary-manager")]
+use crate::view::AppCmd;
 use regex::Regex;
 
 const VIEWER_STYLESHEET: &str = "css/dictionary.css";
@@ -51,30 +58,82 @@ pub struct Dictionary {
   query: String,
   language: String,
   target: Option<String>,
+  // The dictionary group searched when there's no target.
+  group: Option<String>,
   focus: Option<ViewId>,
 }
 
+// Returns the names of the dictionaries to search, in order.
+fn search_order(
+  language: &String,
+  target: Option<&String>,
+  group: Option<&String>,
+  context: &Context,
+) -> Vec<String> {
+  if let Some(name) = target {
+    return vec![name.clone()];
+  }
+
+  if let Some(group) = group.and_then(|name| {
+    context
+      .settings
+      .dictionary
+      .groups
+      .iter()
+      .find(|g| &g.name == name)
+  }) {
+    return group.dictionaries.clone();
+  }
+
+  let langs = &context.settings.dictionary.languages;
+  context
+    .dictionaries
+    .keys()
+    .filter(|&name| {
+      language.is_empty() || !langs.contains_key(name) || langs[name].contains(language)
+    })
+    .cloned()
+    .collect()
+}
+
+// Returns the first entry found for *query*, and the name of its dictionary.
+pub fn translate(
+  query: &str,
+  language: &String,
+  context: &mut Context,
+) -> Option<(String, String)> {
+  for name in search_order(language, None, None, context) {
+    let dict = match context.dictionaries.get_mut(&name) {
+      Some(dict) => dict,
+      None => continue,
+    };
+    if let Some([_, body]) = dict
+      .lookup(query, false)
+      .map_err(|e| eprintln!("{}", e))
+      .ok()
+      .and_then(|results| results.into_iter().next())
+    {
+      return Some((name, body));
+    }
+  }
+  None
+}
+
 fn query_to_content(
   query: &str,
   language: &String,
   fuzzy: bool,
   target: Option<&String>,
+  group: Option<&String>,
   context: &mut Context,
 ) -> String {
   let mut content = String::new();
 
-  for (name, dict) in context.dictionaries.iter_mut() {
-    if target.is_some() && target != Some(name) {
-      continue;
-    }
-
-    if target.is_none()
-      && !language.is_empty()
-      && context.settings.dictionary.languages.contains_key(name)
-      && !context.settings.dictionary.languages[name].contains(language)
-    {
-      continue;
-    }
+  for name in search_order(language, target, group, context) {
+    let dict = match context.dictionaries.get_mut(&name) {
+      Some(dict) => dict,
+      None => continue,
+    };
 
     if let Some(results) = dict
       .lookup(query, fuzzy)
@@ -142,7 +201,7 @@ impl Dictionary {
         rect.min.y + small_height - small_thickness
       ],
       Event::Back,
-      "Dictionary".to_string(),
+      tr!("Dictionary"),
       context,
     );
     children.push(Box::new(top_bar) as Box<dyn View>);
@@ -183,13 +242,22 @@ impl Dictionary {
     );
     children.push(Box::new(separator) as Box<dyn View>);
 
+    let group = context
+      .settings
+      .dictionary
+      .groups
+      .iter()
+      .find(|g| !language.is_empty() && g.languages.iter().any(|l| l == language))
+      .map(|g| g.name.clone());
     let langs = &context.settings.dictionary.languages;
     let matches = context
       .dictionaries
       .keys()
       .filter(|&k| langs.contains_key(k) && langs[k].contains(&language.to_string()))
       .collect::<Vec<&String>>();
-    let target = if matches.len() == 1 {
+    let target = if group.is_some() {
+      None
+    } else if matches.len() == 1 {
       Some(matches[0].clone())
     } else {
       if context.dictionaries.len() == 1 {
@@ -238,7 +306,11 @@ impl Dictionary {
         rect.max.x,
         rect.max.y
       ],
-      target.as_ref().map(String::as_str).unwrap_or("All"),
+      &target
+        .as_ref()
+        .or(group.as_ref())
+        .cloned()
+        .unwrap_or_else(|| tr!("All")),
       false,
       false,
     );
@@ -264,6 +336,7 @@ impl Dictionary {
       query: query.to_string(),
       language: language.to_string(),
       target,
+      group,
       focus: None,
     }
   }
@@ -289,10 +362,21 @@ impl Dictionary {
       if let Some(false) = enable {
         return;
       }
-      let entries = vec![EntryKind::Command(
-        "Reload Dictionaries".to_string(),
-        EntryId::ReloadDictionaries,
-      )];
+      let mut entries = vec![
+        EntryKind::Command(tr!("Reload Dictionaries"), EntryId::ReloadDictionaries),
+        #[cfg(feature = "dictionary-manager")]
+        EntryKind::Command(
+          tr!("Manage Dictionaries"),
+          EntryId::Launch(AppCmd::Dictionaries),
+        ),
+      ];
+      if !self.query.is_empty() {
+        entries.push(EntryKind::Separator);
+        entries.push(EntryKind::Command(
+          tr!("Copy Headword"),
+          EntryId::Copy(self.query.clone()),
+        ));
+      }
       let title_menu = Menu::new(
         rect,
         ViewId::TitleMenu,
@@ -331,7 +415,7 @@ impl Dictionary {
         return;
       }
       let entries = vec![EntryKind::CheckBox(
-        "Fuzzy".to_string(),
+        tr!("Fuzzy"),
         EntryId::ToggleFuzzy,
         self.fuzzy,
       )];
@@ -373,23 +457,35 @@ impl Dictionary {
         return;
       }
       let mut entries = context
-        .dictionaries
-        .keys()
-        .map(|k| {
+        .settings
+        .dictionary
+        .groups
+        .iter()
+        .map(|g| {
           EntryKind::RadioButton(
-            k.to_string(),
-            EntryId::SetSearchTarget(Some(k.to_string())),
-            self.target == Some(k.to_string()),
+            g.name.clone(),
+            EntryId::SetDictionaryGroup(g.name.clone()),
+            self.target.is_none() && self.group.as_ref() == Some(&g.name),
           )
         })
         .collect::<Vec<EntryKind>>();
       if !entries.is_empty() {
         entries.push(EntryKind::Separator);
       }
+      entries.extend(context.dictionaries.keys().map(|k| {
+        EntryKind::RadioButton(
+          k.to_string(),
+          EntryId::SetSearchTarget(Some(k.to_string())),
+          self.target == Some(k.to_string()),
+        )
+      }));
+      if !context.dictionaries.is_empty() {
+        entries.push(EntryKind::Separator);
+      }
       entries.push(EntryKind::RadioButton(
-        "All".to_string(),
+        tr!("All"),
         EntryId::SetSearchTarget(None),
-        self.target.is_none(),
+        self.target.is_none() && self.group.is_none(),
       ));
       let search_target_menu = Menu::new(
         rect,
@@ -510,7 +606,7 @@ impl Dictionary {
       }
 
       let mut edit_languages = NamedInput::new(
-        "Languages".to_string(),
+        tr!("Languages"),
         ViewId::EditLanguages,
         ViewId::EditLanguagesInput,
         16,
@@ -547,9 +643,22 @@ impl Dictionary {
       hub.send(Event::BatteryTick).ok();
     }
 
+    // The dictionaries might have been removed in the meantime.
+    self.check_target(&mut RenderQueue::new(), context);
     rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
   }
 
+  fn check_target(&mut self, rq: &mut RenderQueue, context: &Context) {
+    if let Some(name) = self.target.as_ref() {
+      if !context.dictionaries.contains_key(name) {
+        self.target = None;
+        if let Some(bottom_bar) = self.child_mut(6).downcast_mut::<BottomBar>() {
+          bottom_bar.update_name(&tr!("All"), rq);
+        }
+      }
+    }
+  }
+
   fn go_to_neighbor(&mut self, dir: CycleDir, rq: &mut RenderQueue) {
     let location = match dir {
       CycleDir::Previous => Location::Previous(self.location),
@@ -583,11 +692,15 @@ impl Dictionary {
         search_bar.set_text(query, rq, context);
       }
     }
+    if !self.query.is_empty() {
+      context.record_usage(Usage::Lookup);
+    }
     let content = query_to_content(
       &self.query,
       &self.language,
       self.fuzzy,
       self.target.as_ref(),
+      self.group.as_ref(),
       context,
     );
     self.doc.update(&content);
@@ -704,9 +817,23 @@ impl View for Dictionary {
         true
       },
       Event::Select(EntryId::SetSearchTarget(ref target)) => {
-        if *target != self.target {
+        if *target != self.target || self.group.is_some() {
           self.target = target.clone();
-          let name = self.target.as_ref().map(String::as_str).unwrap_or("All");
+          self.group = None;
+          let name = self.target.clone().unwrap_or_else(|| tr!("All"));
+          if let Some(bottom_bar) = self.children[6].downcast_mut::<BottomBar>() {
+            bottom_bar.update_name(&name, rq);
+          }
+          if !self.query.is_empty() {
+            self.define(None, rq, context);
+          }
+        }
+        true
+      },
+      Event::Select(EntryId::SetDictionaryGroup(ref name)) => {
+        if self.target.is_some() || self.group.as_ref() != Some(name) {
+          self.target = None;
+          self.group = Some(name.clone());
           if let Some(bottom_bar) = self.children[6].downcast_mut::<BottomBar>() {
             bottom_bar.update_name(name, rq);
           }
@@ -726,14 +853,7 @@ impl View for Dictionary {
       Event::Select(EntryId::ReloadDictionaries) => {
         context.dictionaries.clear();
         context.load_dictionaries();
-        if let Some(name) = self.target.as_ref() {
-          if !context.dictionaries.contains_key(name) {
-            self.target = None;
-            if let Some(bottom_bar) = self.child_mut(6).downcast_mut::<BottomBar>() {
-              bottom_bar.update_name("All", rq);
-            }
-          }
-        }
+        self.check_target(rq, context);
         true
       },
       Event::EditLanguages => {
@@ -0,0 +1,669 @@
+use crate::{
+  app::Context,
+  color::BLACK,
+  device::CURRENT_DEVICE,
+  feed::{parse_feed, save_entry, HISTORY_FILENAME},
+  font::Fonts,
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{halves, CycleDir, Rectangle},
+  gesture::GestureEvent,
+  helpers::{load_json, save_json},
+  input::DeviceEvent,
+  metadata::{sort, Info, ReadingSpeed, SimpleStatus, SortMethod},
+  network::NetworkTask,
+  settings::{FeedSource, FirstColumn, SecondColumn},
+  unit::scale_by_dpi,
+  view::{
+    common::{locate_by_id, toggle_battery_menu, toggle_clock_menu, toggle_main_menu},
+    filler::Filler,
+    home::{bottom_bar::BottomBar, shelf::Shelf},
+    menu::{Menu, MenuKind},
+    report::{report_error, Severity},
+    top_bar::TopBar,
+    Bus,
+    EntryId,
+    EntryKind,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_MEDIUM,
+  },
+};
+use anyhow::Error;
+use chrono::{Duration as ChronoDuration, Local};
+use reqwest::blocking::Client;
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs,
+  path::Path,
+  thread,
+  time::Duration,
+};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Feeds {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  entries: Vec<Info>,
+  current_page: usize,
+  pages_count: usize,
+  fetching: bool,
+  // Fetch when the network is up.
+  pending_fetch: bool,
+}
+
+impl Feeds {
+  pub fn new(rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) -> Feeds {
+    let id = ID_FEEDER.next();
+    let mut children = Vec::new();
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+
+    let top_bar = TopBar::new(
+      rect![
+        rect.min.x,
+        rect.min.y,
+        rect.max.x,
+        rect.min.y + small_height - small_thickness
+      ],
+      Event::Back,
+      tr!("Feeds"),
+      context,
+    );
+    children.push(Box::new(top_bar) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height - small_thickness,
+        rect.max.x,
+        rect.min.y + small_height + big_thickness
+      ],
+      BLACK,
+    );
+    children.push(Box::new(separator) as Box<dyn View>);
+
+    let shelf = Shelf::new(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height + big_thickness,
+        rect.max.x,
+        rect.max.y - small_height - small_thickness
+      ],
+      FirstColumn::TitleAndAuthor,
+      SecondColumn::Status,
+      context.rtl,
+    );
+    children.push(Box::new(shelf) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height - small_thickness,
+        rect.max.x,
+        rect.max.y - small_height + big_thickness
+      ],
+      BLACK,
+    );
+    children.push(Box::new(separator) as Box<dyn View>);
+
+    let bottom_bar = BottomBar::new(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height + big_thickness,
+        rect.max.x,
+        rect.max.y
+      ],
+      0,
+      0,
+      &tr!("Entries"),
+      0,
+      false,
+    );
+    children.push(Box::new(bottom_bar) as Box<dyn View>);
+
+    rq.add(RenderData::new(id, rect, UpdateMode::Gui));
+
+    let mut feeds = Feeds {
+      id,
+      rect,
+      children,
+      entries: Vec::new(),
+      current_page: 0,
+      pages_count: 0,
+      fetching: false,
+      pending_fetch: false,
+    };
+
+    feeds.reload(&mut RenderQueue::new(), context);
+    feeds.fetch(hub, context);
+
+    feeds
+  }
+
+  fn reload(&mut self, rq: &mut RenderQueue, context: &mut Context) {
+    let dir = context.library.home.join(&context.settings.feeds.directory);
+    let (mut entries, _) = context.library.list(&dir, None, false);
+    sort(&mut entries, SortMethod::Added, true);
+    self.entries = entries;
+    self.update_shelf(rq);
+    self.update_bottom_bar(rq);
+  }
+
+  fn update_shelf(&mut self, rq: &mut RenderQueue) {
+    let shelf = self.children[2].downcast_mut::<Shelf>().unwrap();
+    let max_lines = shelf.max_lines.max(1);
+    self.pages_count = (self.entries.len() as f32 / max_lines as f32).ceil() as usize;
+    self.current_page = self.current_page.min(self.pages_count.saturating_sub(1));
+    let index_lower = self.current_page * max_lines;
+    let index_upper = (index_lower + max_lines).min(self.entries.len());
+    shelf.update(
+      &self.entries[index_lower..index_upper],
+      &ReadingSpeed::default(),
+      rq,
+    );
+  }
+
+  fn update_bottom_bar(&mut self, rq: &mut RenderQueue) {
+    let unread = self
+      .entries
+      .iter()
+      .filter(|info| info.simple_status() != SimpleStatus::Finished)
+      .count();
+    let bottom_bar = self.children[4].downcast_mut::<BottomBar>().unwrap();
+    bottom_bar.update_library_label(&tr!("Unread"), unread, false, rq);
+    bottom_bar.update_page_label(self.current_page, self.pages_count, rq);
+    bottom_bar.update_icons(self.current_page, self.pages_count, rq);
+  }
+
+  fn go_to_neighbor(&mut self, dir: CycleDir, rq: &mut RenderQueue) {
+    match dir {
+      CycleDir::Next if self.current_page < self.pages_count.saturating_sub(1) => {
+        self.current_page += 1;
+      },
+      CycleDir::Previous if self.current_page > 0 => {
+        self.current_page -= 1;
+      },
+      _ => return,
+    }
+    self.update_shelf(rq);
+    self.update_bottom_bar(rq);
+  }
+
+  fn fetch(&mut self, hub: &Hub, context: &mut Context) {
+    if self.fetching {
+      return;
+    }
+
+    let sources = context.settings.feeds.sources.clone();
+    if sources.is_empty() {
+      hub
+        .send(Event::Notify(tr!("No feeds are configured.")))
+        .ok();
+      return;
+    }
+
+    if !context.online {
+      self.pending_fetch = true;
+      hub.send(Event::AcquireNetwork(NetworkTask::Feeds)).ok();
+      return;
+    }
+
+    self.fetching = true;
+    self.pending_fetch = false;
+    spawn_fetch(hub, context);
+  }
+
+  // Removes the entries older than the maximum age.
+  fn clean_up(&mut self, context: &mut Context) -> usize {
+    let settings = &context.settings.feeds;
+    if settings.max_age == 0 {
+      return 0;
+    }
+    let oldest = Local::now() - ChronoDuration::days(settings.max_age as i64);
+    let mut count = 0;
+    for info in &self.entries {
+      if info.added >= oldest
+        || (settings.keep_unread && info.simple_status() != SimpleStatus::Finished)
+      {
+        continue;
+      }
+      if let Err(e) = context.library.remove(&info.file.path) {
+        eprintln!("{}", e);
+      } else {
+        count += 1;
+      }
+    }
+    count
+  }
+
+  fn toggle_title_menu(
+    &mut self,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::TitleMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let entries = vec![
+        EntryKind::Command(tr!("Refresh"), EntryId::Refresh),
+        EntryKind::Command(tr!("Mark All As Read"), EntryId::MarkAllAsRead),
+        EntryKind::Command(tr!("Clean Up"), EntryId::CleanUpFeeds),
+      ];
+      let title_menu = Menu::new(
+        rect,
+        ViewId::TitleMenu,
+        MenuKind::DropDown,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        title_menu.id(),
+        *title_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(title_menu) as Box<dyn View>);
+    }
+  }
+
+  fn toggle_book_menu(
+    &mut self,
+    index: usize,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::BookMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let max_lines = self.children[2].downcast_ref::<Shelf>().unwrap().max_lines;
+      let info = match self.entries.get(self.current_page * max_lines + index) {
+        Some(info) => info,
+        None => return,
+      };
+      let path = &info.file.path;
+
+      let status_entry = if info.simple_status() == SimpleStatus::Finished {
+        EntryKind::Command(
+          tr!("Mark As Unread"),
+          EntryId::SetStatus(path.clone(), SimpleStatus::New),
+        )
+      } else {
+        EntryKind::Command(
+          tr!("Mark As Read"),
+          EntryId::SetStatus(path.clone(), SimpleStatus::Finished),
+        )
+      };
+      let entries = vec![
+        status_entry,
+        EntryKind::Separator,
+        EntryKind::Command(tr!("Remove"), EntryId::Remove(path.clone())),
+      ];
+
+      let book_menu = Menu::new(
+        rect,
+        ViewId::BookMenu,
+        MenuKind::Contextual,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        book_menu.id(),
+        *book_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(book_menu) as Box<dyn View>);
+    }
+  }
+}
+
+// Fetches the feeds in a separate thread, and sends `Event::FeedsFetched` when it's done.
+pub fn spawn_fetch(hub: &Hub, context: &Context) {
+  let sources = context.settings.feeds.sources.clone();
+  let dir = context.library.home.join(&context.settings.feeds.directory);
+  let max_age = context.settings.feeds.max_age;
+  let hub2 = hub.clone();
+  hub.send(Event::AcquireNetwork(NetworkTask::Feeds)).ok();
+  thread::spawn(move || {
+    let count = fetch_feeds(&sources, &dir, max_age, &hub2)
+      .map_err(|e| report_error(&hub2, Severity::Error, "Can't fetch the feeds", &e))
+      .unwrap_or(0);
+    hub2
+      .send(Event::Progress(
+        ViewId::FetchProgress,
+        String::new(),
+        Some(1.0),
+      ))
+      .ok();
+    hub2.send(Event::FeedsFetched(count)).ok();
+    hub2.send(Event::ReleaseNetwork(NetworkTask::Feeds)).ok();
+  });
+}
+
+// Saves the new entries of each feed and returns their number.
+fn fetch_feeds(
+  sources: &[FeedSource],
+  dir: &Path,
+  max_age: u16,
+  hub: &Hub,
+) -> Result<usize, Error> {
+  fs::create_dir_all(dir)?;
+  let history_path = dir.join(HISTORY_FILENAME);
+  let mut history: BTreeMap<String, BTreeSet<String>> =
+    load_json(&history_path).unwrap_or_default();
+  let oldest = if max_age > 0 {
+    Some(Local::now() - ChronoDuration::days(max_age as i64))
+  } else {
+    None
+  };
+  let client = Client::builder().timeout(FETCH_TIMEOUT).build()?;
+  let mut count = 0;
+
+  for (index, source) in sources.iter().enumerate() {
+    hub
+      .send(Event::Progress(
+        ViewId::FetchProgress,
+        tr!("Fetching feeds"),
+        Some(index as f32 / sources.len() as f32),
+      ))
+      .ok();
+    let feed = client
+      .get(&source.url)
+      .send()
+      .and_then(|response| response.error_for_status())
+      .and_then(|response| response.text())
+      .map_err(Error::from)
+      .and_then(|text| parse_feed(&text));
+    let feed = match feed {
+      Ok(feed) => feed,
+      Err(e) => {
+        report_error(
+          hub,
+          Severity::Warning,
+          &tr!("Can't fetch {}", source.url),
+          &e,
+        );
+        continue;
+      },
+    };
+
+    let name = if !source.name.is_empty() {
+      source.name.as_str()
+    } else if !feed.title.is_empty() {
+      feed.title.as_str()
+    } else {
+      source.url.as_str()
+    };
+    let seen = history.entry(source.url.clone()).or_default();
+    let mut ids = BTreeSet::new();
+
+    for entry in &feed.entries {
+      let is_old = match (entry.published, oldest) {
+        (Some(published), Some(oldest)) => published < oldest,
+        _ => false,
+      };
+      if seen.contains(&entry.id) || is_old {
+        ids.insert(entry.id.clone());
+        continue;
+      }
+      match save_entry(dir, name, entry) {
+        Ok(info) => {
+          if let Some(info) = info {
+            hub.send(Event::AddDocument(Box::new(info))).ok();
+            count += 1;
+          }
+          ids.insert(entry.id.clone());
+        },
+        Err(e) => report_error(
+          hub,
+          Severity::Warning,
+          &tr!("Can't save {}", entry.title),
+          &e,
+        ),
+      }
+    }
+
+    // The entries that left the feed won't come back.
+    *seen = ids;
+  }
+
+  save_json(&history, &history_path)?;
+  Ok(count)
+}
+
+impl View for Feeds {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Page(dir) => {
+        self.go_to_neighbor(dir, rq);
+        true
+      },
+      Event::ToggleBookMenu(rect, index) => {
+        self.toggle_book_menu(index, rect, None, rq, context);
+        true
+      },
+      Event::Select(EntryId::SetStatus(ref path, status)) => {
+        context.library.set_status(path, status);
+        self.reload(rq, context);
+        true
+      },
+      Event::Select(EntryId::Remove(ref path)) => {
+        if let Err(e) = context.library.remove(path) {
+          eprintln!("{}", e);
+        }
+        self.reload(rq, context);
+        true
+      },
+      Event::Select(EntryId::MarkAllAsRead) => {
+        for info in &self.entries {
+          if info.simple_status() != SimpleStatus::Finished {
+            context
+              .library
+              .set_status(&info.file.path, SimpleStatus::Finished);
+          }
+        }
+        self.reload(rq, context);
+        true
+      },
+      Event::Select(EntryId::CleanUpFeeds) => {
+        let count = self.clean_up(context);
+        self.reload(rq, context);
+        hub
+          .send(Event::Notify(tr!("Removed {} entries.", count)))
+          .ok();
+        true
+      },
+      Event::Select(EntryId::Refresh) => {
+        self.fetch(hub, context);
+        true
+      },
+      Event::FeedsFetched(count) => {
+        self.fetching = false;
+        self.reload(&mut RenderQueue::new(), context);
+        self.clean_up(context);
+        self.reload(rq, context);
+        let msg = match count {
+          0 => tr!("No new entries."),
+          1 => "1 new entry.".to_string(),
+          _ => format!("{} new entries.", count),
+        };
+        hub.send(Event::Notify(msg)).ok();
+        true
+      },
+      Event::Device(DeviceEvent::NetUp) => {
+        if self.pending_fetch {
+          self.fetch(hub, context);
+        }
+        true
+      },
+      Event::ToggleNear(ViewId::TitleMenu, rect) => {
+        self.toggle_title_menu(rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::MainMenu, rect) => {
+        toggle_main_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::BatteryMenu, rect) => {
+        toggle_battery_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::ClockMenu, rect) => {
+        toggle_clock_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::Reseed => {
+        self.reload(rq, context);
+        true
+      },
+      Event::Gesture(GestureEvent::Cross(_)) => {
+        hub.send(Event::Back).ok();
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, _fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {}
+
+  fn resize(&mut self, rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+
+    self.children[0].resize(
+      rect![
+        rect.min.x,
+        rect.min.y,
+        rect.max.x,
+        rect.min.y + small_height - small_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[1].resize(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height - small_thickness,
+        rect.max.x,
+        rect.min.y + small_height + big_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    let shelf_rect = rect![
+      rect.min.x,
+      rect.min.y + small_height + big_thickness,
+      rect.max.x,
+      rect.max.y - small_height - small_thickness
+    ];
+    self.children[2] = Box::new(Shelf::new(
+      shelf_rect,
+      FirstColumn::TitleAndAuthor,
+      SecondColumn::Status,
+      context.rtl,
+    )) as Box<dyn View>;
+
+    self.children[3].resize(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height - small_thickness,
+        rect.max.x,
+        rect.max.y - small_height + big_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[4].resize(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height + big_thickness,
+        rect.max.x,
+        rect.max.y
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    // Floating windows.
+    for i in 5..self.children.len() {
+      self.children[i].resize(rect, hub, rq, context);
+    }
+
+    self.rect = rect;
+    self.update_shelf(&mut RenderQueue::new());
+    self.update_bottom_bar(&mut RenderQueue::new());
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Full));
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
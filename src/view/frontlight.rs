@@ -5,6 +5,7 @@ use super::{
   label::Label,
   presets_list::PresetsList,
   slider::Slider,
+  undo::{record_undo, UndoAction},
   Align,
   Bus,
   EntryId,
@@ -107,7 +108,7 @@ impl FrontlightWindow {
         rect.max.x - small_height,
         rect.min.y + small_height
       ],
-      "Frontlight".to_string(),
+      tr!("Frontlight"),
       Align::Center,
     );
 
@@ -287,6 +288,29 @@ impl FrontlightWindow {
     }
   }
 
+  // Shows, hides or updates the presets after their number went from *old_len* to the current one.
+  fn refresh_presets(&mut self, old_len: usize, rq: &mut RenderQueue, context: &mut Context) {
+    let len = context.settings.frontlight_presets.len();
+    if len == 0 || old_len == 0 {
+      if len != old_len {
+        self.toggle_presets(len > 0, rq, context);
+      }
+      return;
+    }
+    if (len < 2) != (old_len < 2) {
+      let index = self.len() - 2;
+      if let Some(button_guess) = self.child_mut(index).downcast_mut::<Button>() {
+        button_guess.disabled = len < 2;
+        rq.add(RenderData::new(
+          button_guess.id(),
+          *button_guess.rect(),
+          UpdateMode::Gui,
+        ));
+      }
+    }
+    self.update_presets(rq, context);
+  }
+
   fn update_presets(&mut self, rq: &mut RenderQueue, context: &mut Context) {
     let len = self.len();
     if let Some(presets_list) = self.child_mut(len - 1).downcast_mut::<PresetsList>() {
@@ -329,51 +353,30 @@ impl View for FrontlightWindow {
           frontlight_levels: context.frontlight.levels(),
           ..Default::default()
         };
+        let old_len = context.settings.frontlight_presets.len();
         context.settings.frontlight_presets.push(light_preset);
         context
           .settings
           .frontlight_presets
           .sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-        if context.settings.frontlight_presets.len() == 1 {
-          self.toggle_presets(true, rq, context);
-        } else {
-          if context.settings.frontlight_presets.len() == 2 {
-            let index = self.len() - 2;
-            if let Some(button_guess) = self.child_mut(index).downcast_mut::<Button>() {
-              button_guess.disabled = false;
-              rq.add(RenderData::new(
-                button_guess.id(),
-                *button_guess.rect(),
-                UpdateMode::Gui,
-              ));
-            }
-          }
-          self.update_presets(rq, context);
-        }
+        self.refresh_presets(old_len, rq, context);
         true
       },
       Event::Select(EntryId::RemovePreset(index)) => {
-        if index < context.settings.frontlight_presets.len() {
-          context.settings.frontlight_presets.remove(index);
-          if context.settings.frontlight_presets.is_empty() {
-            self.toggle_presets(false, rq, context);
-          } else {
-            if context.settings.frontlight_presets.len() == 1 {
-              let index = self.len() - 2;
-              if let Some(button_guess) = self.child_mut(index).downcast_mut::<Button>() {
-                button_guess.disabled = true;
-                rq.add(RenderData::new(
-                  button_guess.id(),
-                  *button_guess.rect(),
-                  UpdateMode::Gui,
-                ));
-              }
-            }
-            self.update_presets(rq, context);
-          }
+        let old_len = context.settings.frontlight_presets.len();
+        if index < old_len {
+          let preset = context.settings.frontlight_presets.remove(index);
+          self.refresh_presets(old_len, rq, context);
+          record_undo(UndoAction::RemovePreset { index, preset }, hub, context);
         }
         true
       },
+      Event::Revert(ref action, dir) if matches!(**action, UndoAction::RemovePreset { .. }) => {
+        let old_len = context.settings.frontlight_presets.len();
+        action.apply_to_presets(&mut context.settings.frontlight_presets, dir);
+        self.refresh_presets(old_len, rq, context);
+        true
+      },
       Event::LoadPreset(index) => {
         let frontlight_levels = context.settings.frontlight_presets[index].frontlight_levels;
         self.set_frontlight_levels(frontlight_levels, rq, context);
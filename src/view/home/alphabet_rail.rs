@@ -0,0 +1,233 @@
+use crate::{
+  app::Context,
+  color::{BLACK, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, DISPLAY_STYLE, MD_KIND},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{BorderSpec, CornerSpec, Rectangle},
+  gesture::GestureEvent,
+  input::{DeviceEvent, FingerStatus},
+  unit::scale_by_dpi,
+  view::{
+    Bus,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    BORDER_RADIUS_LARGE,
+    ID_FEEDER,
+    THICKNESS_LARGE,
+  },
+};
+use unicode_normalization::UnicodeNormalization;
+
+// The letters of the rail, from top to bottom: the titles that don't start with a letter are
+// gathered under `#`.
+pub const LETTERS: &str = "#ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// Returns the letter of the rail under which the given text is listed.
+pub fn initial(text: &str) -> char {
+  text
+    .nfd()
+    .next()
+    .map(|c| c.to_ascii_uppercase())
+    .filter(char::is_ascii_uppercase)
+    .unwrap_or('#')
+}
+
+pub struct AlphabetRail {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  active: bool,
+}
+
+impl AlphabetRail {
+  pub fn new(rect: Rectangle) -> AlphabetRail {
+    AlphabetRail {
+      id: ID_FEEDER.next(),
+      rect,
+      children: Vec::new(),
+      active: false,
+    }
+  }
+
+  fn letter_at(&self, y: i32) -> char {
+    let count = LETTERS.len() as i32;
+    let index = (y - self.rect.min.y) * count / (self.rect.height() as i32).max(1);
+    LETTERS
+      .chars()
+      .nth(index.max(0).min(count - 1) as usize)
+      .unwrap_or('#')
+  }
+}
+
+impl View for AlphabetRail {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    _hub: &Hub,
+    bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Device(DeviceEvent::Finger {
+        status, position, ..
+      }) => match status {
+        FingerStatus::Down if self.rect.includes(position) => {
+          self.active = true;
+          bus.push_back(Event::AlphabetRail(self.letter_at(position.y), status));
+          true
+        },
+        FingerStatus::Motion | FingerStatus::Up if self.active => {
+          self.active = status == FingerStatus::Motion;
+          bus.push_back(Event::AlphabetRail(self.letter_at(position.y), status));
+          true
+        },
+        _ => false,
+      },
+      Event::Gesture(GestureEvent::Tap(center))
+      | Event::Gesture(GestureEvent::HoldFingerShort(center, ..))
+        if self.rect.includes(center) =>
+      {
+        true
+      },
+      Event::Gesture(GestureEvent::Swipe { start, .. }) if self.rect.includes(start) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    fb.draw_rectangle(&self.rect, TEXT_NORMAL[0]);
+    let font = font_from_style(fonts, &MD_KIND, dpi);
+    let x_height = font.x_heights.1 as i32;
+    let count = LETTERS.len() as i32;
+    let height = self.rect.height() as i32;
+    for (i, letter) in LETTERS.chars().enumerate() {
+      let plan = font.plan(&letter.to_string(), None, None);
+      let y_min = self.rect.min.y + i as i32 * height / count;
+      let y_max = self.rect.min.y + (i as i32 + 1) * height / count;
+      let dx = (self.rect.width() as i32 - plan.width) / 2;
+      let dy = (y_max - y_min - x_height) / 2;
+      let pt = pt!(self.rect.min.x + dx, y_max - dy);
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    }
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
+
+// The large letter shown while dragging along the rail.
+pub struct LetterOverlay {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  letter: char,
+}
+
+impl LetterOverlay {
+  pub fn new(rect: Rectangle, letter: char) -> LetterOverlay {
+    LetterOverlay {
+      id: ID_FEEDER.next(),
+      rect,
+      children: Vec::new(),
+      letter,
+    }
+  }
+
+  pub fn update(&mut self, letter: char, rq: &mut RenderQueue) {
+    if self.letter != letter {
+      self.letter = letter;
+      rq.add(RenderData::new(self.id, self.rect, UpdateMode::Fast));
+    }
+  }
+}
+
+impl View for LetterOverlay {
+  fn handle_event(
+    &mut self,
+    _evt: &Event,
+    _hub: &Hub,
+    _bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    false
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let border_radius = scale_by_dpi(BORDER_RADIUS_LARGE, dpi) as i32;
+    let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+    fb.draw_rounded_rectangle_with_border(
+      &self.rect,
+      &CornerSpec::Uniform(border_radius),
+      &BorderSpec {
+        thickness: border_thickness,
+        color: BLACK,
+      },
+      &WHITE,
+    );
+    let font = font_from_style(fonts, &DISPLAY_STYLE, dpi);
+    let plan = font.plan(&self.letter.to_string(), None, None);
+    let dx = (self.rect.width() as i32 - plan.width) / 2;
+    let dy = (self.rect.height() as i32 - font.x_heights.1 as i32) / 2;
+    let pt = pt!(self.rect.min.x + dx, self.rect.max.y - dy);
+    font.render(fb, BLACK, &plan, pt);
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::initial;
+
+  #[test]
+  fn test_initial() {
+    assert_eq!(initial("dune"), 'D');
+    assert_eq!(initial("Éloge de l'ombre"), 'E');
+    assert_eq!(initial("1984"), '#');
+    assert_eq!(initial(""), '#');
+  }
+}
@@ -3,15 +3,16 @@ use crate::{
   color::{BLACK, READING_PROGRESS, TEXT_INVERTED_HARD, TEXT_NORMAL, WHITE},
   device::CURRENT_DEVICE,
   document::HumanSize,
-  font::{font_from_style, Fonts, MD_AUTHOR, MD_KIND, MD_SIZE, MD_TITLE, MD_YEAR},
+  font::{font_from_style, Ellipsis, Fonts, MD_AUTHOR, MD_KIND, MD_SIZE, MD_TITLE, MD_YEAR},
   framebuffer::{Framebuffer, UpdateMode},
-  geom::{halves, BorderSpec, CornerSpec, Rectangle},
+  geom::{halves, BorderSpec, CornerSpec, Point, Rectangle},
   gesture::GestureEvent,
-  metadata::{Info, Status},
+  metadata::{format_duration, Info, ReadingSpeed, Status},
   settings::{FirstColumn, SecondColumn},
   unit::scale_by_dpi,
   view::{Bus, Event, Hub, Id, RenderData, RenderQueue, View, ID_FEEDER, THICKNESS_SMALL},
 };
+use chrono::{Datelike, Local};
 
 const PROGRESS_HEIGHT: f32 = 13.0;
 
@@ -23,7 +24,10 @@ pub struct Book {
   index: usize,
   first_column: FirstColumn,
   second_column: SecondColumn,
+  // The speed measured across all the books.
+  reading_speed: ReadingSpeed,
   active: bool,
+  rtl: bool,
 }
 
 impl Book {
@@ -33,6 +37,8 @@ impl Book {
     index: usize,
     first_column: FirstColumn,
     second_column: SecondColumn,
+    reading_speed: &ReadingSpeed,
+    rtl: bool,
   ) -> Book {
     Book {
       id: ID_FEEDER.next(),
@@ -42,9 +48,117 @@ impl Book {
       index,
       first_column,
       second_column,
+      reading_speed: reading_speed.clone(),
       active: false,
+      rtl,
     }
   }
+
+  // Returns the text of the second column, when it's neither a progress bar nor a status chip.
+  fn second_column_text(&self) -> String {
+    match self.second_column {
+      SecondColumn::Year => self.info.year.clone(),
+      SecondColumn::TimeLeft => match self.info.status() {
+        Status::Reading(..) => self
+          .info
+          .reader
+          .as_ref()
+          .and_then(|r| r.time_left(Some(&self.reading_speed)))
+          .map(format_duration)
+          .unwrap_or_default(),
+        _ => String::new(),
+      },
+      SecondColumn::LastOpened => self
+        .info
+        .reader
+        .as_ref()
+        .map(|r| {
+          // The year is only shown for the books that weren't opened this year.
+          if r.opened.year() == Local::now().year() {
+            r.opened.format("%m-%d").to_string()
+          } else {
+            r.opened.format("%Y").to_string()
+          }
+        })
+        .unwrap_or_default(),
+      SecondColumn::FileSize => self.info.file.size.human_size(),
+      SecondColumn::Format => self.info.file.kind.to_uppercase(),
+      SecondColumn::Progress | SecondColumn::Status => String::new(),
+    }
+  }
+
+  // Returns the abscissa of a span of the given width that starts at *x* in left-to-right
+  // layouts: the columns are mirrored in right-to-left layouts.
+  fn x(&self, x: i32, width: i32) -> i32 {
+    if self.rtl {
+      self.rect.min.x + self.rect.max.x - x - width
+    } else {
+      x
+    }
+  }
+}
+
+fn status_label(status: Status) -> String {
+  match status {
+    Status::New => tr!("New"),
+    Status::Reading(progress) => format!("{:.0}%", 100.0 * progress.min(1.0)),
+    Status::Finished => tr!("Finished"),
+  }
+}
+
+fn chip_width(fonts: &mut Fonts, label: &str) -> i32 {
+  let font = font_from_style(fonts, &MD_SIZE, CURRENT_DEVICE.dpi);
+  let plan = font.plan(label, None, None);
+  plan.width + font.em() as i32
+}
+
+fn render_status_chip(
+  fb: &mut dyn Framebuffer,
+  fonts: &mut Fonts,
+  status: Status,
+  pt: Point,
+  scheme: [u8; 3],
+) -> i32 {
+  let inverted = matches!(status, Status::Finished);
+  render_chip(fb, fonts, &status_label(status), inverted, pt, scheme)
+}
+
+// Draws the label inside a chip whose left end is at *pt.x* and whose text sits on the
+// baseline *pt.y*. Returns the width of the chip.
+fn render_chip(
+  fb: &mut dyn Framebuffer,
+  fonts: &mut Fonts,
+  label: &str,
+  inverted: bool,
+  pt: Point,
+  scheme: [u8; 3],
+) -> i32 {
+  let dpi = CURRENT_DEVICE.dpi;
+  let font = font_from_style(fonts, &MD_SIZE, dpi);
+  let plan = font.plan(label, None, None);
+  let x_height = font.x_heights.0 as i32;
+  let padding = font.em() as i32 / 2;
+  let width = plan.width + 2 * padding;
+  let rect = rect![pt.x, pt.y - 2 * x_height, pt.x + width, pt.y + x_height];
+  let thickness = scale_by_dpi(THICKNESS_SMALL, dpi) as u16;
+  let (background, foreground) = if inverted {
+    (scheme[1], scheme[0])
+  } else {
+    (scheme[0], scheme[1])
+  };
+
+  fb.draw_rounded_rectangle_with_border(
+    &rect,
+    &CornerSpec::Uniform(rect.height() as i32 / 2),
+    &BorderSpec {
+      thickness,
+      color: scheme[1],
+    },
+    &background,
+  );
+  font.render(fb, foreground, &plan, pt!(pt.x + padding, pt.y));
+
+  width
 }
 
 impl View for Book {
@@ -94,6 +208,8 @@ impl View for Book {
 
     let (title, author) = if self.first_column == FirstColumn::TitleAndAuthor {
       (self.info.title(), self.info.author.as_str())
+    } else if self.first_column == FirstColumn::TitleAndStatus {
+      (self.info.title(), "")
     } else {
       let filename = self
         .info
@@ -105,7 +221,6 @@ impl View for Book {
       (filename, "")
     };
 
-    let year = &self.info.year;
     let file_info = &self.info.file;
 
     let (x_height, padding, baseline) = {
@@ -124,11 +239,22 @@ impl View for Book {
     let first_width = self.rect.width() as i32 - second_width - third_width;
     let width = first_width - padding - small_half_padding;
 
-    // Author
-    let author_width = {
+    // Author or Status
+    let author_width = if self.first_column == FirstColumn::TitleAndStatus {
+      let status = self.info.status();
+      let x = self.x(
+        self.rect.min.x + padding,
+        chip_width(fonts, &status_label(status)),
+      );
+      let pt = pt!(x, self.rect.max.y - baseline);
+      render_status_chip(fb, fonts, status, pt, scheme)
+    } else {
       let font = font_from_style(fonts, &MD_AUTHOR, dpi);
       let plan = font.plan(author, Some(width), None);
-      let pt = pt!(self.rect.min.x + padding, self.rect.max.y - baseline);
+      let pt = pt!(
+        self.x(self.rect.min.x + padding, plan.width),
+        self.rect.max.y - baseline
+      );
       font.render(fb, scheme[1], &plan, pt);
       plan.width
     };
@@ -141,23 +267,25 @@ impl View for Book {
 
       if plan.width > width {
         let available = width - author_width;
-        if available > 3 * padding {
-          let (index, usable_width) = font.cut_point(&plan, width);
-          let leftover = plan.width - usable_width;
-          if leftover > 2 * padding {
-            let mut plan2 = plan.split_off(index, usable_width);
-            let max_width = available - if author_width > 0 { padding } else { 0 };
-            font.trim_left(&mut plan2);
-            font.crop_right(&mut plan2, max_width);
-            let pt = pt!(
+        let max_width = available - if author_width > 0 { padding } else { 0 };
+        let mut lines = if available > 3 * padding {
+          font.wrap(plan.clone(), &[width, max_width], 2, Ellipsis::End)
+        } else {
+          Vec::new()
+        };
+        // A short leftover isn't worth a second line.
+        if lines.len() == 2 && lines[1].width > 2 * padding {
+          let plan2 = lines.pop().unwrap();
+          plan = lines.pop().unwrap();
+          let pt = pt!(
+            self.x(
               self.rect.min.x + first_width - small_half_padding - plan2.width,
-              self.rect.max.y - baseline
-            );
-            font.render(fb, scheme[1], &plan2, pt);
-            title_lines += 1;
-          } else {
-            font.crop_right(&mut plan, width);
-          }
+              plan2.width
+            ),
+            self.rect.max.y - baseline
+          );
+          font.render(fb, scheme[1], &plan2, pt);
+          title_lines += 1;
         } else {
           font.crop_right(&mut plan, width);
         }
@@ -169,19 +297,41 @@ impl View for Book {
         baseline + x_height
       };
 
-      let pt = self.rect.min + pt!(padding, dy);
+      let pt = pt!(
+        self.x(self.rect.min.x + padding, plan.width),
+        self.rect.min.y + dy
+      );
       font.render(fb, scheme[1], &plan, pt);
     }
 
-    // Year or Progress
+    // Progress, Status or one of the text columns.
     match self.second_column {
-      SecondColumn::Year => {
+      SecondColumn::Status => {
+        let status = self.info.status();
+        let width = chip_width(fonts, &status_label(status));
+        let dx = (second_width - padding - width) / 2;
+        let dy = (self.rect.height() as i32 - x_height) / 2;
+        let pt = pt!(
+          self.x(self.rect.min.x + first_width + big_half_padding + dx, width),
+          self.rect.max.y - dy
+        );
+        render_status_chip(fb, fonts, status, pt, scheme);
+      },
+      SecondColumn::Year
+      | SecondColumn::TimeLeft
+      | SecondColumn::LastOpened
+      | SecondColumn::FileSize
+      | SecondColumn::Format => {
+        let text = self.second_column_text();
         let font = font_from_style(fonts, &MD_YEAR, dpi);
-        let plan = font.plan(year, None, None);
+        let plan = font.plan(&text, Some(second_width - padding), None);
         let dx = (second_width - padding - plan.width) / 2;
         let dy = (self.rect.height() as i32 - font.x_heights.1 as i32) / 2;
         let pt = pt!(
-          self.rect.min.x + first_width + big_half_padding + dx,
+          self.x(
+            self.rect.min.x + first_width + big_half_padding + dx,
+            plan.width
+          ),
           self.rect.max.y - dy
         );
         font.render(fb, scheme[1], &plan, pt);
@@ -191,7 +341,7 @@ impl View for Book {
         let thickness = scale_by_dpi(THICKNESS_SMALL, dpi) as u16;
         let (small_radius, big_radius) = halves(progress_height);
         let center = pt!(
-          self.rect.min.x + first_width + second_width / 2,
+          self.x(self.rect.min.x + first_width + second_width / 2, 0),
           self.rect.min.y + self.rect.height() as i32 / 2
         );
         match self.info.status() {
@@ -217,8 +367,13 @@ impl View for Book {
           Status::Reading(progress) => {
             let progress_width = 2 * (second_width - padding) / 3;
             let (small_progress_width, big_progress_width) = halves(progress_width);
-            let x_offset =
-              center.x - progress_width / 2 + (progress_width as f32 * progress.min(1.0)) as i32;
+            let filled_width = (progress_width as f32 * progress.min(1.0)) as i32;
+            let x_offset = if self.rtl {
+              center.x + progress_width / 2 - filled_width
+            } else {
+              center.x - progress_width / 2 + filled_width
+            };
+            let rtl = self.rtl;
             fb.draw_rounded_rectangle_with_border(
               &rect![
                 center - pt!(small_progress_width, small_radius),
@@ -230,7 +385,7 @@ impl View for Book {
                 color: BLACK,
               },
               &|x, _| {
-                if x < x_offset {
+                if (x < x_offset) != rtl {
                   READING_PROGRESS
                 } else {
                   WHITE
@@ -250,19 +405,27 @@ impl View for Book {
       let letter_spacing = scale_by_dpi(3.0, dpi) as i32;
       plan.space_out(letter_spacing);
       let pt = pt!(
-        self.rect.max.x - padding - plan.width,
+        self.x(self.rect.max.x - padding - plan.width, plan.width),
         self.rect.min.y + baseline + x_height
       );
       font.render(fb, scheme[1], &plan, pt);
     }
 
-    // File size
-    {
+    // Loan or file size
+    if self.info.loan.is_some() {
+      let label = tr!("Lent");
+      let width = chip_width(fonts, &label);
+      let pt = pt!(
+        self.x(self.rect.max.x - padding - width, width),
+        self.rect.max.y - baseline
+      );
+      render_chip(fb, fonts, &label, true, pt, scheme);
+    } else {
       let size = file_info.size.human_size();
       let font = font_from_style(fonts, &MD_SIZE, dpi);
       let plan = font.plan(&size, None, None);
       let pt = pt!(
-        self.rect.max.x - padding - plan.width,
+        self.x(self.rect.max.x - padding - plan.width, plan.width),
         self.rect.max.y - baseline
       );
       font.render(fb, scheme[1], &plan, pt);
@@ -0,0 +1,285 @@
+use crate::{
+  app::Context,
+  color::{BLACK, TEXT_BUMP_SMALL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{BorderSpec, CornerSpec, CycleDir, Dir, Point, Rectangle},
+  gesture::GestureEvent,
+  metadata::Collection,
+  unit::scale_by_dpi,
+  view::{
+    filler::Filler,
+    Bus,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    BORDER_RADIUS_SMALL,
+    ID_FEEDER,
+    THICKNESS_SMALL,
+  },
+};
+
+// Lists the collections of the library on a single line, one page at a time. Tapping a
+// collection only shows its books, tapping it again shows all the books.
+#[derive(Debug)]
+pub struct CollectionsBar {
+  id: Id,
+  pub rect: Rectangle,
+  collections: Vec<Collection>,
+  selected: Option<String>,
+  pages: Vec<Vec<Box<dyn View>>>,
+  current_page: usize,
+}
+
+impl CollectionsBar {
+  pub fn new(
+    rect: Rectangle,
+    collections: Vec<Collection>,
+    selected: Option<&str>,
+    fonts: &mut Fonts,
+  ) -> CollectionsBar {
+    let mut collections_bar = CollectionsBar {
+      id: ID_FEEDER.next(),
+      rect,
+      collections,
+      selected: selected.map(String::from),
+      pages: vec![vec![]],
+      current_page: 0,
+    };
+    collections_bar.layout(fonts);
+    collections_bar
+  }
+
+  pub fn update(
+    &mut self,
+    collections: Vec<Collection>,
+    selected: Option<&str>,
+    rq: &mut RenderQueue,
+    fonts: &mut Fonts,
+  ) {
+    self.collections = collections;
+    self.selected = selected.map(String::from);
+    self.layout(fonts);
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
+
+  pub fn shift(&mut self, delta: Point) {
+    for children in &mut self.pages {
+      for child in children {
+        *child.rect_mut() += delta;
+      }
+    }
+    self.rect += delta;
+  }
+
+  fn set_current_page(&mut self, dir: CycleDir) -> bool {
+    match dir {
+      CycleDir::Next if self.current_page < self.pages.len() - 1 => {
+        self.current_page += 1;
+      },
+      CycleDir::Previous if self.current_page > 0 => {
+        self.current_page -= 1;
+      },
+      _ => return false,
+    }
+    true
+  }
+
+  fn layout(&mut self, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let padding = font.em() as i32;
+    let max_width = self.rect.width() as i32 - 2 * padding;
+    let background = TEXT_BUMP_SMALL[0];
+
+    self.pages.clear();
+    self.current_page = 0;
+    let mut children: Vec<Box<dyn View>> = Vec::new();
+    let mut x_offset = self.rect.min.x;
+
+    for collection in &self.collections {
+      let plan = font.plan(&collection.name, Some(max_width), None);
+      let width = plan.width + 2 * padding;
+      if x_offset + width > self.rect.max.x && !children.is_empty() {
+        let filler = Filler::new(
+          rect![x_offset, self.rect.min.y, self.rect.max.x, self.rect.max.y],
+          background,
+        );
+        children.push(Box::new(filler) as Box<dyn View>);
+        self.pages.push(children);
+        children = Vec::new();
+        x_offset = self.rect.min.x;
+      }
+      let selected = self.selected.as_ref() == Some(&collection.name);
+      if selected {
+        self.current_page = self.pages.len();
+      }
+      let label = CollectionLabel::new(
+        rect![x_offset, self.rect.min.y, x_offset + width, self.rect.max.y],
+        collection.name.clone(),
+        selected,
+        max_width,
+      );
+      children.push(Box::new(label) as Box<dyn View>);
+      x_offset += width;
+    }
+
+    let filler = Filler::new(
+      rect![x_offset, self.rect.min.y, self.rect.max.x, self.rect.max.y],
+      background,
+    );
+    children.push(Box::new(filler) as Box<dyn View>);
+    self.pages.push(children);
+  }
+}
+
+impl View for CollectionsBar {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    _hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
+        let dir = match context.ui_dir(dir) {
+          Dir::West => CycleDir::Next,
+          Dir::East => CycleDir::Previous,
+          _ => return false,
+        };
+        if self.set_current_page(dir) {
+          rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        }
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, _fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {}
+
+  fn resize(&mut self, rect: Rectangle, _hub: &Hub, _rq: &mut RenderQueue, context: &mut Context) {
+    self.rect = rect;
+    self.layout(&mut context.fonts);
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.pages[self.current_page]
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.pages[self.current_page]
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
+
+#[derive(Debug)]
+struct CollectionLabel {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  name: String,
+  selected: bool,
+  max_width: i32,
+}
+
+impl CollectionLabel {
+  fn new(rect: Rectangle, name: String, selected: bool, max_width: i32) -> CollectionLabel {
+    CollectionLabel {
+      id: ID_FEEDER.next(),
+      rect,
+      children: vec![],
+      name,
+      selected,
+      max_width,
+    }
+  }
+}
+
+impl View for CollectionLabel {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    _hub: &Hub,
+    bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+        bus.push_back(Event::ToggleSelectCollection(self.name.clone()));
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    fb.draw_rectangle(&self.rect, TEXT_BUMP_SMALL[0]);
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let plan = font.plan(&self.name, Some(self.max_width), None);
+    let dx = (self.rect.width() as i32 - plan.width) / 2;
+    let dy = (self.rect.height() as i32 - x_height) / 2;
+
+    if self.selected {
+      let padding = font.em() as i32 / 2 - scale_by_dpi(3.0, dpi) as i32;
+      let bg_width = plan.width + 2 * padding;
+      let bg_height = 3 * x_height;
+      let pt = self.rect.min + pt!(dx - padding, dy - x_height);
+      let bg_rect = rect![pt, pt + pt!(bg_width, bg_height)];
+      let border_radius = scale_by_dpi(BORDER_RADIUS_SMALL, dpi) as i32;
+      let border_thickness = scale_by_dpi(THICKNESS_SMALL, dpi) as u16;
+      fb.draw_rounded_rectangle_with_border(
+        &bg_rect,
+        &CornerSpec::Uniform(border_radius),
+        &BorderSpec {
+          thickness: border_thickness,
+          color: BLACK,
+        },
+        &WHITE,
+      );
+    }
+
+    let pt = pt!(self.rect.min.x + dx, self.rect.max.y - dy);
+    font.render(fb, TEXT_BUMP_SMALL[1], &plan, pt);
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
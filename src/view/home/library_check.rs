@@ -0,0 +1,304 @@
+use crate::{
+  app::Context,
+  color::{BLACK, SEPARATOR_NORMAL, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{Dir, Rectangle},
+  gesture::GestureEvent,
+  library::{IntegrityIssue, Repair},
+  unit::scale_by_dpi,
+  view::{
+    button::Button,
+    icon::Icon,
+    label::Label,
+    report::{report_error, Severity},
+    Align,
+    Bus,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_MEDIUM,
+    THICKNESS_SMALL,
+  },
+};
+
+fn issue_text(issue: &IntegrityIssue) -> String {
+  match issue {
+    IntegrityIssue::Missing(_, path) => tr!("Missing: {}", path.display()),
+    IntegrityIssue::Modified(_, path, _) => tr!("Modified: {}", path.display()),
+    IntegrityIssue::OrphanedState(fp) => tr!("Orphaned reading state: {}", format!("{:016X}", fp)),
+  }
+}
+
+fn repair_label(repair: Repair) -> String {
+  match repair {
+    Repair::RemoveEntry => tr!("Remove"),
+    Repair::UpdateEntry => tr!("Update"),
+    Repair::RemoveState => tr!("Delete"),
+  }
+}
+
+// Lists the discrepancies between the library and the files on disk, with a button for each
+// possible repair.
+pub struct LibraryCheck {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  issues: Vec<IntegrityIssue>,
+  current_page: usize,
+  rows: usize,
+}
+
+impl LibraryCheck {
+  pub fn new(rect: Rectangle, context: &Context) -> LibraryCheck {
+    let mut library_check = LibraryCheck {
+      id: ID_FEEDER.next(),
+      rect,
+      children: Vec::new(),
+      issues: context.library.check_integrity(),
+      current_page: 0,
+      rows: 1,
+    };
+    library_check.layout();
+    library_check
+  }
+
+  fn bar_height(&self) -> i32 {
+    scale_by_dpi(SMALL_BAR_HEIGHT, CURRENT_DEVICE.dpi) as i32
+  }
+
+  fn pages_count(&self) -> usize {
+    ((self.issues.len() + self.rows - 1) / self.rows).max(1)
+  }
+
+  fn layout(&mut self) {
+    let bar_height = self.bar_height();
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, CURRENT_DEVICE.dpi) as i32;
+    self.rows =
+      ((self.rect.height() as i32 - 2 * (bar_height + thickness)) / bar_height).max(1) as usize;
+    self.current_page = self.current_page.min(self.pages_count() - 1);
+
+    let title_rect = rect![
+      self.rect.min.x + bar_height,
+      self.rect.min.y,
+      self.rect.max.x - bar_height,
+      self.rect.min.y + bar_height
+    ];
+    let close_rect = rect![
+      self.rect.max.x - bar_height,
+      self.rect.min.y,
+      self.rect.max.x,
+      self.rect.min.y + bar_height
+    ];
+    let title = if self.pages_count() > 1 {
+      tr!(
+        "Library Check ({}/{})",
+        self.current_page + 1,
+        self.pages_count()
+      )
+    } else {
+      tr!("Library Check")
+    };
+    self.children = vec![
+      Box::new(Label::new(title_rect, title, Align::Center)) as Box<dyn View>,
+      Box::new(Icon::new(
+        "close",
+        close_rect,
+        Event::Close(ViewId::LibraryCheck),
+      )) as Box<dyn View>,
+    ];
+
+    if self.issues.is_empty() {
+      return;
+    }
+
+    let button_width = 3 * bar_height;
+    let padding = bar_height / 8;
+    let start = self.current_page * self.rows;
+    let end = (start + self.rows).min(self.issues.len());
+    for index in start..end {
+      let issue = &self.issues[index];
+      let y = self.rect.min.y + bar_height + thickness + (index - start) as i32 * bar_height;
+      let repairs = issue.repairs();
+      let buttons_x = self.rect.max.x - repairs.len() as i32 * button_width;
+      self.children.push(Box::new(Label::new(
+        rect![self.rect.min.x, y, buttons_x, y + bar_height],
+        issue_text(issue),
+        Align::Left(bar_height / 4),
+      )) as Box<dyn View>);
+      for (i, repair) in repairs.iter().enumerate() {
+        let x = buttons_x + i as i32 * button_width;
+        self.children.push(Box::new(Button::new(
+          rect![
+            x + padding,
+            y + padding,
+            x + button_width - padding,
+            y + bar_height - padding
+          ],
+          Event::Repair(index, *repair),
+          repair_label(*repair),
+        )) as Box<dyn View>);
+      }
+    }
+
+    let y = self.rect.max.y - bar_height;
+    let x = self.rect.center().x;
+    self.children.push(Box::new(Button::new(
+      rect![
+        x - button_width / 2,
+        y + padding,
+        x + button_width / 2,
+        self.rect.max.y - padding
+      ],
+      Event::RepairAll,
+      tr!("Repair All"),
+    )) as Box<dyn View>);
+  }
+
+  fn set_current_page(&mut self, dir: Dir, rq: &mut RenderQueue) {
+    let current_page = match dir {
+      Dir::West if self.current_page + 1 < self.pages_count() => self.current_page + 1,
+      Dir::East if self.current_page > 0 => self.current_page - 1,
+      _ => return,
+    };
+    self.current_page = current_page;
+    self.layout();
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
+
+  // Applies the repairs in reverse order, so that the remaining indices stay valid.
+  fn repair(&mut self, mut repairs: Vec<(usize, Repair)>, hub: &Hub, context: &mut Context) {
+    repairs.sort_by(|a, b| b.0.cmp(&a.0));
+    for (index, repair) in repairs {
+      match context.library.repair(&self.issues[index], repair) {
+        Ok(()) => {
+          self.issues.remove(index);
+        },
+        Err(e) => report_error(hub, Severity::Warning, "Can't repair the library", &e),
+      }
+    }
+  }
+}
+
+impl View for LibraryCheck {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Repair(index, repair) if index < self.issues.len() => {
+        self.repair(vec![(index, repair)], hub, context);
+        self.layout();
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
+      Event::RepairAll => {
+        let repairs = self
+          .issues
+          .iter()
+          .enumerate()
+          .map(|(index, issue)| (index, issue.repairs()[0]))
+          .collect();
+        self.repair(repairs, hub, context);
+        self.layout();
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
+      Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
+        self.set_current_page(context.ui_dir(dir), rq);
+        true
+      },
+      Event::Gesture(..) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let bar_height = self.bar_height();
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    fb.draw_rectangle(&self.rect, WHITE);
+    fb.draw_rectangle(
+      &rect![
+        self.rect.min.x,
+        self.rect.min.y + bar_height,
+        self.rect.max.x,
+        self.rect.min.y + bar_height + thickness
+      ],
+      BLACK,
+    );
+
+    if self.issues.is_empty() {
+      let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+      let x_height = font.x_heights.0 as i32;
+      let plan = font.plan(&tr!("No issues found."), None, None);
+      let pt = pt!(
+        self.rect.center().x - plan.width / 2,
+        self.rect.center().y + x_height / 2
+      );
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+      return;
+    }
+
+    fb.draw_rectangle(
+      &rect![
+        self.rect.min.x,
+        self.rect.max.y - bar_height - thickness,
+        self.rect.max.x,
+        self.rect.max.y - bar_height
+      ],
+      BLACK,
+    );
+
+    let separator = scale_by_dpi(THICKNESS_SMALL, dpi) as i32;
+    let start = self.current_page * self.rows;
+    let count = (self.issues.len() - start).min(self.rows) as i32;
+    for i in 1..count {
+      let y = self.rect.min.y + bar_height + thickness + i * bar_height;
+      fb.draw_rectangle(
+        &rect![self.rect.min.x, y, self.rect.max.x, y + separator],
+        SEPARATOR_NORMAL,
+      );
+    }
+  }
+
+  fn resize(&mut self, rect: Rectangle, _hub: &Hub, _rq: &mut RenderQueue, _context: &mut Context) {
+    self.rect = rect;
+    self.layout();
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+
+  fn view_id(&self) -> Option<ViewId> {
+    Some(ViewId::LibraryCheck)
+  }
+}
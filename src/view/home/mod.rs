@@ -1,33 +1,45 @@
 mod address_bar;
+mod alphabet_rail;
 mod book;
-mod bottom_bar;
+pub mod bottom_bar;
+mod collections_bar;
 mod directories_bar;
 mod directory;
+mod library_check;
 mod library_label;
 mod navigation_bar;
-mod shelf;
+mod notebooks;
+pub mod shelf;
 
 use self::{
   address_bar::AddressBar,
+  alphabet_rail::initial,
   bottom_bar::BottomBar,
+  collections_bar::CollectionsBar,
+  library_check::LibraryCheck,
   navigation_bar::NavigationBar,
+  notebooks::Notebooks,
   shelf::Shelf,
 };
 use super::top_bar::TopBar;
 use crate::{
   app::Context,
   color::BLACK,
+  conversion::queue_conversions,
   device::CURRENT_DEVICE,
+  document::FILE_SCHEME,
   font::Fonts,
   framebuffer::{Framebuffer, UpdateMode},
   geom::{halves, CycleDir, Dir, Rectangle},
   gesture::GestureEvent,
-  input::{ButtonCode, ButtonStatus, DeviceEvent},
-  library::Library,
-  metadata::{make_query, sort, Info, Metadata, SimpleStatus, SortMethod},
+  input::{ButtonCode, ButtonStatus, DeviceEvent, FingerStatus},
+  library::{Library, IMPORT_REPORT_PATH},
+  metadata::{make_query, sort, Info, Metadata, SimpleStatus, SortMethod, Visibility},
+  network::NetworkTask,
   settings::{FirstColumn, Hook, LibraryMode, SecondColumn},
   unit::scale_by_dpi,
   view::{
+    command_palette::{menu_commands, PaletteCommand},
     common::{
       locate,
       locate_by_id,
@@ -42,8 +54,11 @@ use crate::{
     menu::{Menu, MenuKind},
     menu_entry::MenuEntry,
     named_input::NamedInput,
-    notification::Notification,
+    notification::{push_notification, Notification},
+    progress::ProgressDialog,
+    report::{report_error, Severity},
     search_bar::SearchBar,
+    undo::{record_undo, UndoAction, UndoDir},
     Bus,
     EntryId,
     EntryKind,
@@ -76,6 +91,11 @@ use std::{
 
 pub const TRASH_DIRNAME: &str = ".trash";
 
+// The alphabet rail is only useful when the books are sorted alphabetically.
+fn has_alphabet_rail(sort_method: SortMethod) -> bool {
+  matches!(sort_method, SortMethod::Title | SortMethod::Author)
+}
+
 #[derive(Debug)]
 pub struct Home {
   id: Id,
@@ -91,6 +111,8 @@ pub struct Home {
   visible_books: Metadata,
   current_directory: PathBuf,
   background_fetchers: FxHashMap<PathBuf, Fetcher>,
+  // The book being lent, or whose loan note is being edited.
+  target_book: Option<PathBuf>,
 }
 
 #[derive(Debug)]
@@ -119,7 +141,9 @@ impl Home {
 
     let current_directory = context.library.home.clone();
     let sort_method = library_settings.sort_method;
-    let reverse_order = sort_method.reverse_order();
+    let reverse_order = library_settings
+      .reverse_order
+      .unwrap_or_else(|| sort_method.reverse_order());
 
     context.library.sort(sort_method, reverse_order);
 
@@ -177,6 +201,30 @@ impl Home {
       shelf_index += 2;
     }
 
+    if context.settings.home.collections_bar {
+      let collections_bar = CollectionsBar::new(
+        rect![
+          rect.min.x,
+          y_start,
+          rect.max.x,
+          y_start + small_height - thickness
+        ],
+        context.library.collections(),
+        context.library.collection.as_deref(),
+        &mut context.fonts,
+      );
+      children.push(Box::new(collections_bar) as Box<dyn View>);
+      y_start += small_height - thickness;
+
+      let separator = Filler::new(
+        rect![rect.min.x, y_start, rect.max.x, y_start + thickness],
+        BLACK,
+      );
+      children.push(Box::new(separator) as Box<dyn View>);
+      y_start += thickness;
+      shelf_index += 2;
+    }
+
     if context.settings.home.navigation_bar {
       let mut nav_bar = NavigationBar::new(
         rect![
@@ -215,8 +263,14 @@ impl Home {
       ],
       library_settings.first_column,
       library_settings.second_column,
+      context.rtl,
     );
 
+    shelf.rail = has_alphabet_rail(sort_method);
+    if context.settings.home.column_headers {
+      shelf.set_headers(Some((sort_method, reverse_order)));
+    }
+
     let max_lines = shelf.max_lines;
     let pages_count = (visible_books.len() as f32 / max_lines as f32).ceil() as usize;
     let index_lower = current_page * max_lines;
@@ -224,6 +278,7 @@ impl Home {
 
     shelf.update(
       &visible_books[index_lower..index_upper],
+      &context.settings.reading_speed,
       &mut RenderQueue::new(),
     );
 
@@ -271,6 +326,7 @@ impl Home {
       visible_books,
       current_directory,
       background_fetchers: FxHashMap::default(),
+      target_book: None,
     })
   }
 
@@ -293,7 +349,9 @@ impl Home {
     let selected_library = context.settings.selected_library;
     for hook in &context.settings.libraries[selected_library].hooks {
       if context.library.home.join(&hook.path) == path {
-        context.library.flush();
+        if let Err(e) = context.library.flush() {
+          report_error(hub, Severity::Critical, "Can't save the metadata", &e);
+        }
         self.insert_fetcher(hook, hub, context);
       }
     }
@@ -315,6 +373,10 @@ impl Home {
       index += 2;
     }
 
+    if context.settings.home.collections_bar {
+      index += 2;
+    }
+
     if context.settings.home.navigation_bar {
       let nav_bar = self.children[index]
         .as_mut()
@@ -334,7 +396,7 @@ impl Home {
       ));
     }
 
-    self.update_shelf(true, rq);
+    self.update_shelf(true, rq, context);
     self.update_bottom_bar(rq, context);
   }
 
@@ -366,10 +428,44 @@ impl Home {
       return;
     }
     self.current_page = index;
-    self.update_shelf(false, rq);
+    self.update_shelf(false, rq, context);
     self.update_bottom_bar(rq, context);
   }
 
+  // Goes to the page of the first book listed under the given letter of the alphabet rail, or
+  // of the book that would follow it.
+  fn go_to_letter(&mut self, letter: char, rq: &mut RenderQueue, context: &Context) {
+    let sort_method = self.sort_method;
+    let key = |info: &Info| {
+      if sort_method == SortMethod::Author {
+        initial(info.alphabetic_author())
+      } else {
+        initial(info.alphabetic_title())
+      }
+    };
+    let reverse_order = self.reverse_order;
+    let index = self
+      .visible_books
+      .iter()
+      .position(|info| key(info) == letter)
+      .or_else(|| {
+        self.visible_books.iter().position(|info| {
+          if reverse_order {
+            key(info) < letter
+          } else {
+            key(info) > letter
+          }
+        })
+      })
+      .unwrap_or_else(|| self.visible_books.len().saturating_sub(1));
+    let max_lines = self
+      .child(self.shelf_index)
+      .downcast_ref::<Shelf>()
+      .unwrap()
+      .max_lines;
+    self.go_to_page(index / max_lines.max(1), rq, context);
+  }
+
   fn go_to_neighbor(&mut self, dir: CycleDir, rq: &mut RenderQueue, context: &Context) {
     match dir {
       CycleDir::Next if self.current_page < self.pages_count.saturating_sub(1) => {
@@ -381,7 +477,7 @@ impl Home {
       _ => return,
     }
 
-    self.update_shelf(false, rq);
+    self.update_shelf(false, rq, context);
     self.update_bottom_bar(rq, context);
   }
 
@@ -415,7 +511,7 @@ impl Home {
     }
 
     if update {
-      self.update_shelf(false, rq);
+      self.update_shelf(false, rq, context);
       self.update_bottom_bar(rq, context);
     }
   }
@@ -427,7 +523,7 @@ impl Home {
       .downcast_mut::<Shelf>()
       .unwrap()
       .set_first_column(context.settings.libraries[selected_library].first_column);
-    self.update_shelf(false, rq);
+    self.update_shelf(false, rq, context);
   }
 
   fn update_second_column(&mut self, rq: &mut RenderQueue, context: &mut Context) {
@@ -437,18 +533,19 @@ impl Home {
       .downcast_mut::<Shelf>()
       .unwrap()
       .set_second_column(context.settings.libraries[selected_library].second_column);
-    self.update_shelf(false, rq);
+    self.update_shelf(false, rq, context);
   }
 
-  fn update_shelf(&mut self, was_resized: bool, rq: &mut RenderQueue) {
-    let dpi = CURRENT_DEVICE.dpi;
-    let big_height = scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32;
-    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+  fn update_shelf(&mut self, was_resized: bool, rq: &mut RenderQueue, context: &Context) {
     let shelf = self.children[self.shelf_index]
       .as_mut()
       .downcast_mut::<Shelf>()
       .unwrap();
-    let max_lines = ((shelf.rect.height() as i32 + thickness) / big_height) as usize;
+    if shelf.headers.is_some() {
+      shelf.headers = Some((self.sort_method, self.reverse_order));
+    }
+    let max_lines = shelf.lines_count();
+    shelf.rail = has_alphabet_rail(self.sort_method);
 
     if was_resized {
       let page_position = if self.visible_books.is_empty() {
@@ -471,7 +568,11 @@ impl Home {
     let index_lower = self.current_page * max_lines;
     let index_upper = (index_lower + max_lines).min(self.visible_books.len());
 
-    shelf.update(&self.visible_books[index_lower..index_upper], rq);
+    shelf.update(
+      &self.visible_books[index_lower..index_upper],
+      &context.settings.reading_speed,
+      rq,
+    );
   }
 
   fn update_top_bar(&mut self, search_visible: bool, rq: &mut RenderQueue) {
@@ -492,10 +593,21 @@ impl Home {
         .as_mut()
         .downcast_mut::<BottomBar>()
         .unwrap();
-      let filter = self.query.is_some() || self.current_directory != context.library.home;
+      let filter = self.query.is_some()
+        || self.current_directory != context.library.home
+        || context.library.collection.is_some();
       let selected_library = context.settings.selected_library;
       let library_settings = &context.settings.libraries[selected_library];
-      bottom_bar.update_library_label(&library_settings.name, self.visible_books.len(), filter, rq);
+      let name = if context.library.show_archived {
+        tr!("{} Archive", library_settings.name)
+      } else if context.library.show_loaned {
+        tr!("{} Loans", library_settings.name)
+      } else if let Some(name) = context.library.collection.as_ref() {
+        name.clone()
+      } else {
+        library_settings.name.clone()
+      };
+      bottom_bar.update_library_label(&name, self.visible_books.len(), filter, rq);
       bottom_bar.update_page_label(self.current_page, self.pages_count, rq);
       bottom_bar.update_icons(self.current_page, self.pages_count, rq);
     }
@@ -662,6 +774,17 @@ impl Home {
       self.shelf_index -= 2;
       context.settings.home.address_bar = false;
 
+      // Move the collections bar up.
+      if context.settings.home.collections_bar {
+        self.children[2]
+          .downcast_mut::<CollectionsBar>()
+          .unwrap()
+          .shift(pt!(0, -small_height));
+        if context.settings.home.navigation_bar {
+          *self.children[3].rect_mut() += pt!(0, -small_height);
+        }
+      }
+
       // Move the navigation bar up.
       if context.settings.home.navigation_bar {
         let nav_bar = self.children[self.shelf_index - 2]
@@ -702,6 +825,17 @@ impl Home {
       self.shelf_index += 2;
       context.settings.home.address_bar = true;
 
+      // Move the collections bar down.
+      if context.settings.home.collections_bar {
+        self.children[4]
+          .downcast_mut::<CollectionsBar>()
+          .unwrap()
+          .shift(pt!(0, small_height));
+        if context.settings.home.navigation_bar {
+          *self.children[5].rect_mut() += pt!(0, small_height);
+        }
+      }
+
       // Move the separator above the shelf down.
       *self.children[self.shelf_index - 1].rect_mut() += pt!(0, small_height);
 
@@ -735,7 +869,7 @@ impl Home {
         ));
       }
 
-      self.update_shelf(true, rq);
+      self.update_shelf(true, rq, context);
       self.update_bottom_bar(rq, context);
     }
   }
@@ -776,11 +910,7 @@ impl Home {
         return;
       }
 
-      let sep_index = if context.settings.home.address_bar {
-        3
-      } else {
-        1
-      };
+      let sep_index = self.shelf_index - 1;
       let sp_rect = *self.child(sep_index).rect() + pt!(0, small_height);
 
       let separator = Filler::new(sp_rect, BLACK);
@@ -819,11 +949,179 @@ impl Home {
         ));
       }
 
-      self.update_shelf(true, rq);
+      self.update_shelf(true, rq, context);
+      self.update_bottom_bar(rq, context);
+    }
+  }
+
+  fn toggle_collections_bar(
+    &mut self,
+    enable: Option<bool>,
+    update: bool,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let (small_height, big_height) = (
+      scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32,
+      scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32,
+    );
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+
+    if let Some(index) = locate::<CollectionsBar>(self) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      // Remove the collections bar and its separator.
+      self.children.drain(index..=index + 1);
+      self.shelf_index -= 2;
+      context.settings.home.collections_bar = false;
+
+      // Move the navigation bar and its separator up.
+      if context.settings.home.navigation_bar {
+        let nav_bar = self.children[self.shelf_index - 2]
+          .downcast_mut::<NavigationBar>()
+          .unwrap();
+        nav_bar.shift(pt!(0, -small_height));
+        *self.children[self.shelf_index - 1].rect_mut() += pt!(0, -small_height);
+      }
+
+      // Move the shelf's top edge up.
+      self.children[self.shelf_index].rect_mut().min.y -= small_height;
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let index = if context.settings.home.address_bar {
+        4
+      } else {
+        2
+      };
+      let sp_rect = *self.child(index - 1).rect() + pt!(0, small_height);
+
+      let separator = Filler::new(sp_rect, BLACK);
+      self
+        .children
+        .insert(index, Box::new(separator) as Box<dyn View>);
+
+      let collections_bar = CollectionsBar::new(
+        rect![
+          self.rect.min.x,
+          sp_rect.min.y - small_height + thickness,
+          self.rect.max.x,
+          sp_rect.min.y
+        ],
+        context.library.collections(),
+        context.library.collection.as_deref(),
+        &mut context.fonts,
+      );
+      self
+        .children
+        .insert(index, Box::new(collections_bar) as Box<dyn View>);
+
+      self.shelf_index += 2;
+      context.settings.home.collections_bar = true;
+
+      // Move the shelf's top edge down.
+      self.children[self.shelf_index].rect_mut().min.y += small_height;
+
+      if context.settings.home.navigation_bar {
+        let rect = *self.children[self.shelf_index].rect();
+        let y_shift = rect.height() as i32 - (big_height - thickness);
+        let nav_bar = self.children[self.shelf_index - 2]
+          .downcast_mut::<NavigationBar>()
+          .unwrap();
+        // Move the navigation bar and its separator down.
+        nav_bar.shift(pt!(0, small_height));
+        *self.children[self.shelf_index - 1].rect_mut() += pt!(0, small_height);
+
+        // Shrink the nav bar.
+        if y_shift < 0 {
+          let nav_bar = self.children[self.shelf_index - 2]
+            .downcast_mut::<NavigationBar>()
+            .unwrap();
+          let y_shift = nav_bar.shrink(y_shift, &mut context.fonts);
+          self.children[self.shelf_index].rect_mut().min.y += y_shift;
+          *self.children[self.shelf_index - 1].rect_mut() += pt!(0, y_shift);
+        }
+      }
+    }
+
+    if update {
+      for i in 2..self.shelf_index {
+        rq.add(RenderData::new(
+          self.child(i).id(),
+          *self.child(i).rect(),
+          UpdateMode::Gui,
+        ));
+      }
+
+      self.update_shelf(true, rq, context);
       self.update_bottom_bar(rq, context);
     }
   }
 
+  fn update_collections_bar(&mut self, rq: &mut RenderQueue, context: &mut Context) {
+    if let Some(index) = locate::<CollectionsBar>(self) {
+      let collections_bar = self.children[index]
+        .downcast_mut::<CollectionsBar>()
+        .unwrap();
+      collections_bar.update(
+        context.library.collections(),
+        context.library.collection.as_deref(),
+        rq,
+        &mut context.fonts,
+      );
+    }
+  }
+
+  fn toggle_collection_input(
+    &mut self,
+    enable: Option<bool>,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::NewCollection) {
+      if let Some(true) = enable {
+        return;
+      }
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+      if self.focus == Some(ViewId::NewCollectionInput) {
+        self.toggle_keyboard(
+          false,
+          true,
+          Some(ViewId::NewCollectionInput),
+          hub,
+          rq,
+          context,
+        );
+      }
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let input = NamedInput::new(
+        tr!("Collection"),
+        ViewId::NewCollection,
+        ViewId::NewCollectionInput,
+        16,
+        context,
+      );
+      rq.add(RenderData::new(input.id(), *input.rect(), UpdateMode::Gui));
+      hub
+        .send(Event::Focus(Some(ViewId::NewCollectionInput)))
+        .ok();
+      self.children.push(Box::new(input) as Box<dyn View>);
+    }
+  }
+
   fn toggle_search_bar(
     &mut self,
     enable: Option<bool>,
@@ -943,7 +1241,7 @@ impl Home {
         let mut rect = *self.child(self.shelf_index).rect();
         rect.max.y = self.child(self.shelf_index + 1).rect().min.y;
         // Render the part of the shelf that isn't covered.
-        self.update_shelf(true, &mut RenderQueue::new());
+        self.update_shelf(true, &mut RenderQueue::new(), context);
         rq.add(RenderData::new(
           self.child(self.shelf_index).id(),
           rect,
@@ -956,7 +1254,7 @@ impl Home {
       } else {
         for i in self.shelf_index - 1..=self.shelf_index + 1 {
           if i == self.shelf_index {
-            self.update_shelf(true, rq);
+            self.update_shelf(true, rq, context);
             continue;
           }
           rq.add(RenderData::new(
@@ -998,7 +1296,7 @@ impl Home {
         return;
       }
       let go_to_page = NamedInput::new(
-        "Go to page".to_string(),
+        tr!("Go to page"),
         ViewId::GoToPage,
         ViewId::GoToPageInput,
         4,
@@ -1014,6 +1312,183 @@ impl Home {
     }
   }
 
+  fn toggle_loan_input(
+    &mut self,
+    note: bool,
+    enable: Option<bool>,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let (view_id, input_id) = if note {
+      (ViewId::EditLoanNote, ViewId::EditLoanNoteInput)
+    } else {
+      (ViewId::Lend, ViewId::LendInput)
+    };
+
+    if let Some(index) = locate_by_id(self, view_id) {
+      if let Some(true) = enable {
+        return;
+      }
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+      if self.focus == Some(input_id) {
+        self.toggle_keyboard(false, true, Some(input_id), hub, rq, context);
+      }
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let loan = self.target_book.as_ref().and_then(|path| {
+        self
+          .visible_books
+          .iter()
+          .find(|info| &info.file.path == path)
+          .and_then(|info| info.loan.as_ref())
+      });
+      let (label, text) = if note {
+        ("Note", loan.map(|l| l.note.clone()))
+      } else {
+        ("Lend to", loan.map(|l| l.borrower.clone()))
+      };
+      let mut input = NamedInput::new(tr!(label), view_id, input_id, 16, context);
+      if let Some(text) = text.as_ref() {
+        input.set_text(text, &mut RenderQueue::new(), context);
+      }
+      rq.add(RenderData::new(input.id(), *input.rect(), UpdateMode::Gui));
+      hub.send(Event::Focus(Some(input_id))).ok();
+      self.children.push(Box::new(input) as Box<dyn View>);
+    }
+  }
+
+  fn sort_menu_entries(&self) -> Vec<EntryKind> {
+    vec![
+      EntryKind::RadioButton(
+        tr!("Date Opened"),
+        EntryId::Sort(SortMethod::Opened),
+        self.sort_method == SortMethod::Opened,
+      ),
+      EntryKind::RadioButton(
+        tr!("Date Added"),
+        EntryId::Sort(SortMethod::Added),
+        self.sort_method == SortMethod::Added,
+      ),
+      EntryKind::RadioButton(
+        tr!("Progress"),
+        EntryId::Sort(SortMethod::Progress),
+        self.sort_method == SortMethod::Progress,
+      ),
+      EntryKind::RadioButton(
+        tr!("Author"),
+        EntryId::Sort(SortMethod::Author),
+        self.sort_method == SortMethod::Author,
+      ),
+      EntryKind::RadioButton(
+        tr!("Title"),
+        EntryId::Sort(SortMethod::Title),
+        self.sort_method == SortMethod::Title,
+      ),
+      EntryKind::RadioButton(
+        tr!("Year"),
+        EntryId::Sort(SortMethod::Year),
+        self.sort_method == SortMethod::Year,
+      ),
+      EntryKind::RadioButton(
+        tr!("File Size"),
+        EntryId::Sort(SortMethod::Size),
+        self.sort_method == SortMethod::Size,
+      ),
+      EntryKind::RadioButton(
+        tr!("File Type"),
+        EntryId::Sort(SortMethod::Kind),
+        self.sort_method == SortMethod::Kind,
+      ),
+      EntryKind::RadioButton(
+        tr!("File Name"),
+        EntryId::Sort(SortMethod::FileName),
+        self.sort_method == SortMethod::FileName,
+      ),
+      EntryKind::RadioButton(
+        tr!("File Path"),
+        EntryId::Sort(SortMethod::FilePath),
+        self.sort_method == SortMethod::FilePath,
+      ),
+      EntryKind::Separator,
+      EntryKind::CheckBox(
+        tr!("Column Headers"),
+        EntryId::ToggleColumnHeaders,
+        self
+          .child(self.shelf_index)
+          .downcast_ref::<Shelf>()
+          .map_or(false, |shelf| shelf.headers.is_some()),
+      ),
+      // Kept last: its check mark follows the sort method.
+      EntryKind::CheckBox(
+        tr!("Reverse Order"),
+        EntryId::ReverseOrder,
+        self.reverse_order,
+      ),
+    ]
+  }
+
+  fn toggle_notebooks(&mut self, enable: Option<bool>, rq: &mut RenderQueue, context: &Context) {
+    if let Some(index) = locate_by_id(self, ViewId::Notebooks) {
+      if let Some(true) = enable {
+        return;
+      }
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let notebooks = Notebooks::new(self.rect, context);
+      rq.add(RenderData::new(
+        notebooks.id(),
+        *notebooks.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(notebooks) as Box<dyn View>);
+    }
+  }
+
+  fn toggle_library_check(
+    &mut self,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::LibraryCheck) {
+      if let Some(true) = enable {
+        return;
+      }
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+      // The repairs might have changed the visible books.
+      self.refresh_visibles(true, false, rq, context);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let library_check = LibraryCheck::new(self.rect, context);
+      rq.add(RenderData::new(
+        library_check.id(),
+        *library_check.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(library_check) as Box<dyn View>);
+    }
+  }
+
   fn toggle_sort_menu(
     &mut self,
     rect: Rectangle,
@@ -1034,64 +1509,7 @@ impl Home {
       if let Some(false) = enable {
         return;
       }
-      let entries = vec![
-        EntryKind::RadioButton(
-          "Date Opened".to_string(),
-          EntryId::Sort(SortMethod::Opened),
-          self.sort_method == SortMethod::Opened,
-        ),
-        EntryKind::RadioButton(
-          "Date Added".to_string(),
-          EntryId::Sort(SortMethod::Added),
-          self.sort_method == SortMethod::Added,
-        ),
-        EntryKind::RadioButton(
-          "Progress".to_string(),
-          EntryId::Sort(SortMethod::Progress),
-          self.sort_method == SortMethod::Progress,
-        ),
-        EntryKind::RadioButton(
-          "Author".to_string(),
-          EntryId::Sort(SortMethod::Author),
-          self.sort_method == SortMethod::Author,
-        ),
-        EntryKind::RadioButton(
-          "Title".to_string(),
-          EntryId::Sort(SortMethod::Title),
-          self.sort_method == SortMethod::Title,
-        ),
-        EntryKind::RadioButton(
-          "Year".to_string(),
-          EntryId::Sort(SortMethod::Year),
-          self.sort_method == SortMethod::Year,
-        ),
-        EntryKind::RadioButton(
-          "File Size".to_string(),
-          EntryId::Sort(SortMethod::Size),
-          self.sort_method == SortMethod::Size,
-        ),
-        EntryKind::RadioButton(
-          "File Type".to_string(),
-          EntryId::Sort(SortMethod::Kind),
-          self.sort_method == SortMethod::Kind,
-        ),
-        EntryKind::RadioButton(
-          "File Name".to_string(),
-          EntryId::Sort(SortMethod::FileName),
-          self.sort_method == SortMethod::FileName,
-        ),
-        EntryKind::RadioButton(
-          "File Path".to_string(),
-          EntryId::Sort(SortMethod::FilePath),
-          self.sort_method == SortMethod::FilePath,
-        ),
-        EntryKind::Separator,
-        EntryKind::CheckBox(
-          "Reverse Order".to_string(),
-          EntryId::ReverseOrder,
-          self.reverse_order,
-        ),
-      ];
+      let entries = self.sort_menu_entries();
       let sort_menu = Menu::new(rect, ViewId::SortMenu, MenuKind::DropDown, entries, context);
       rq.add(RenderData::new(
         sort_menu.id(),
@@ -1142,7 +1560,7 @@ impl Home {
 
       if let Some(parent) = path.parent() {
         entries.push(EntryKind::Command(
-          "Select Parent".to_string(),
+          tr!("Select Parent"),
           EntryId::SelectDirectory(context.library.home.join(parent)),
         ));
         entries.push(EntryKind::Separator);
@@ -1156,24 +1574,29 @@ impl Home {
 
       let submenu = submenu
         .iter()
-        .map(|s| EntryKind::Command(s.to_string(), EntryId::SetStatus(path.clone(), *s)))
+        .map(|s| EntryKind::Command(tr!(&s.to_string()), EntryId::SetStatus(path.clone(), *s)))
         .collect();
-      entries.push(EntryKind::SubMenu("Mark As".to_string(), submenu));
+      entries.push(EntryKind::SubMenu(tr!("Mark As"), submenu));
 
       {
-        let images = &context.settings.intermission_images;
+        let images =
+          &context.settings.libraries[context.settings.selected_library].intermission_images;
         let submenu = [IntermKind::Suspend, IntermKind::PowerOff, IntermKind::Share]
           .iter()
           .map(|k| {
             EntryKind::CheckBox(
-              k.label().to_string(),
+              tr!(k.label()),
               EntryId::ToggleIntermissionImage(*k, path.clone()),
               images.get(k.key()) == Some(path),
             )
           })
           .collect::<Vec<EntryKind>>();
 
-        entries.push(EntryKind::SubMenu("Set As".to_string(), submenu))
+        entries.push(EntryKind::SubMenu(tr!("Set As"), submenu));
+        entries.push(EntryKind::Command(
+          tr!("Use Cover as Sleep Screen"),
+          EntryId::UseCoverAsSleepScreen(path.clone()),
+        ));
       }
 
       entries.push(EntryKind::Separator);
@@ -1189,11 +1612,73 @@ impl Home {
         })
         .collect::<Vec<EntryKind>>();
       if !libraries.is_empty() {
-        entries.push(EntryKind::SubMenu("Move To".to_string(), libraries));
+        entries.push(EntryKind::SubMenu(tr!("Move To"), libraries));
+      }
+
+      if context.library.mode == LibraryMode::Database {
+        let visibilities: &[(&str, Visibility)] = match info.visibility {
+          Visibility::Visible => &[
+            ("Hide", Visibility::Hidden),
+            ("Archive", Visibility::Archived),
+          ],
+          Visibility::Hidden => &[
+            ("Restore", Visibility::Visible),
+            ("Archive", Visibility::Archived),
+          ],
+          Visibility::Archived => &[
+            ("Restore", Visibility::Visible),
+            ("Hide", Visibility::Hidden),
+          ],
+        };
+        for (label, visibility) in visibilities {
+          entries.push(EntryKind::Command(
+            tr!(label),
+            EntryId::SetVisibility(path.clone(), *visibility),
+          ));
+        }
+
+        if let Some(loan) = info.loan.as_ref() {
+          let submenu = vec![
+            EntryKind::Message(tr!(
+              "Lent to {} on {}",
+              loan.borrower,
+              loan.lent.format("%x")
+            )),
+            EntryKind::Separator,
+            EntryKind::Command(tr!("Borrower"), EntryId::Lend(path.clone())),
+            EntryKind::Command(tr!("Note"), EntryId::EditLoanNote(path.clone())),
+            EntryKind::Command(tr!("Returned"), EntryId::ReturnLoan(path.clone())),
+          ];
+          entries.push(EntryKind::SubMenu(tr!("Loan"), submenu));
+        } else {
+          entries.push(EntryKind::Command(tr!("Lend"), EntryId::Lend(path.clone())));
+        }
+
+        let mut submenu: Vec<EntryKind> = context
+          .library
+          .collections()
+          .into_iter()
+          .map(|c| {
+            let member = info.collections.contains(&c.name);
+            EntryKind::CheckBox(
+              c.name.clone(),
+              EntryId::ToggleCollection(path.clone(), c.name),
+              member,
+            )
+          })
+          .collect();
+        if !submenu.is_empty() {
+          submenu.push(EntryKind::Separator);
+        }
+        submenu.push(EntryKind::Command(
+          tr!("New Collection"),
+          EntryId::NewCollection(path.clone()),
+        ));
+        entries.push(EntryKind::SubMenu(tr!("Collections"), submenu));
       }
 
       entries.push(EntryKind::Command(
-        "Remove".to_string(),
+        tr!("Remove"),
         EntryId::Remove(path.clone()),
       ));
 
@@ -1235,109 +1720,7 @@ impl Home {
         return;
       }
 
-      let selected_library = context.settings.selected_library;
-      let library_settings = &context.settings.libraries[selected_library];
-
-      let libraries: Vec<EntryKind> = context
-        .settings
-        .libraries
-        .iter()
-        .enumerate()
-        .map(|(index, lib)| {
-          EntryKind::RadioButton(
-            lib.name.clone(),
-            EntryId::LoadLibrary(index),
-            index == selected_library,
-          )
-        })
-        .collect();
-
-      let database = if library_settings.mode == LibraryMode::Database {
-        vec![
-          EntryKind::Command("Import".to_string(), EntryId::Import),
-          EntryKind::Command("Clean Up".to_string(), EntryId::CleanUp),
-          EntryKind::Command("Flush".to_string(), EntryId::Flush),
-        ]
-      } else {
-        Vec::new()
-      };
-
-      let filesystem = if library_settings.mode == LibraryMode::Filesystem {
-        vec![
-          EntryKind::CheckBox(
-            "Show Hidden".to_string(),
-            EntryId::ToggleShowHidden,
-            context.library.show_hidden,
-          ),
-          EntryKind::Separator,
-          EntryKind::Command("Clean Up".to_string(), EntryId::CleanUp),
-          EntryKind::Command("Flush".to_string(), EntryId::Flush),
-        ]
-      } else {
-        Vec::new()
-      };
-
-      let mut entries = vec![EntryKind::SubMenu("Library".to_string(), libraries)];
-
-      if !database.is_empty() {
-        entries.push(EntryKind::SubMenu("Database".to_string(), database));
-      }
-
-      if !filesystem.is_empty() {
-        entries.push(EntryKind::SubMenu("Filesystem".to_string(), filesystem));
-      }
-
-      let hooks: Vec<EntryKind> = context.settings.libraries[selected_library]
-        .hooks
-        .iter()
-        .map(|v| {
-          EntryKind::Command(
-            v.path.to_string_lossy().into_owned(),
-            EntryId::ToggleSelectDirectory(context.library.home.join(&v.path)),
-          )
-        })
-        .collect();
-
-      if !hooks.is_empty() {
-        entries.push(EntryKind::SubMenu("Toggle Select".to_string(), hooks));
-      }
-
-      entries.push(EntryKind::Separator);
-
-      let first_column = library_settings.first_column;
-      entries.push(EntryKind::SubMenu(
-        "First Column".to_string(),
-        vec![
-          EntryKind::RadioButton(
-            "Title and Author".to_string(),
-            EntryId::FirstColumn(FirstColumn::TitleAndAuthor),
-            first_column == FirstColumn::TitleAndAuthor,
-          ),
-          EntryKind::RadioButton(
-            "File Name".to_string(),
-            EntryId::FirstColumn(FirstColumn::FileName),
-            first_column == FirstColumn::FileName,
-          ),
-        ],
-      ));
-
-      let second_column = library_settings.second_column;
-      entries.push(EntryKind::SubMenu(
-        "Second Column".to_string(),
-        vec![
-          EntryKind::RadioButton(
-            "Progress".to_string(),
-            EntryId::SecondColumn(SecondColumn::Progress),
-            second_column == SecondColumn::Progress,
-          ),
-          EntryKind::RadioButton(
-            "Year".to_string(),
-            EntryId::SecondColumn(SecondColumn::Year),
-            second_column == SecondColumn::Year,
-          ),
-        ],
-      ));
-
+      let entries = library_menu_entries(context);
       let library_menu = Menu::new(
         rect,
         ViewId::LibraryMenu,
@@ -1383,30 +1766,20 @@ impl Home {
   fn remove(
     &mut self,
     path: &Path,
+    hub: &Hub,
     rq: &mut RenderQueue,
     context: &mut Context,
   ) -> Result<(), Error> {
-    let trash_path = context.library.home.join(TRASH_DIRNAME);
-    if !trash_path.is_dir() {
-      fs::create_dir_all(&trash_path)?;
-    }
-    let mut trash = Library::new(trash_path, LibraryMode::Database);
-    context.library.move_to(path, &mut trash)?;
-    let (mut files, _) = trash.list(&trash.home, None, false);
-    let mut size = files.iter().map(|info| info.file.size).sum::<u64>();
-    if size > context.settings.home.max_trash_size {
-      sort(&mut files, SortMethod::Added, true);
-      while size > context.settings.home.max_trash_size {
-        let info = files.pop().unwrap();
-        if let Err(e) = trash.remove(&info.file.path) {
-          eprintln!("{}", e);
-          break;
-        }
-        size -= info.file.size;
-      }
-    }
-    trash.flush();
+    let trash_path = trash_document(path, context)?;
     self.refresh_visibles(true, false, rq, context);
+    record_undo(
+      UndoAction::RemoveBook {
+        path: path.to_path_buf(),
+        trash_path,
+      },
+      hub,
+      context,
+    );
     Ok(())
   }
 
@@ -1420,7 +1793,7 @@ impl Home {
     let library_settings = &context.settings.libraries[index];
     let mut library = Library::new(&library_settings.path, library_settings.mode);
     context.library.move_to(path, &mut library)?;
-    library.flush();
+    library.flush()?;
     self.refresh_visibles(true, false, rq, context);
     Ok(())
   }
@@ -1428,9 +1801,29 @@ impl Home {
   fn set_reverse_order(&mut self, value: bool, rq: &mut RenderQueue, context: &mut Context) {
     self.reverse_order = value;
     self.current_page = 0;
+    self.save_sort(context);
     self.sort(true, rq, context);
   }
 
+  // Remembers the sort method and order of the current library.
+  fn save_sort(&self, context: &mut Context) {
+    let library_settings = &mut context.settings.libraries[context.settings.selected_library];
+    library_settings.sort_method = self.sort_method;
+    library_settings.reverse_order =
+      Some(self.reverse_order).filter(|&r| r != self.sort_method.reverse_order());
+  }
+
+  fn toggle_column_headers(&mut self, rq: &mut RenderQueue, context: &mut Context) {
+    let enable = !context.settings.home.column_headers;
+    context.settings.home.column_headers = enable;
+    let headers = Some((self.sort_method, self.reverse_order)).filter(|_| enable);
+    if let Some(shelf) = self.children[self.shelf_index].downcast_mut::<Shelf>() {
+      shelf.set_headers(headers);
+    }
+    self.update_shelf(true, rq, context);
+    self.update_bottom_bar(rq, context);
+  }
+
   fn set_sort_method(
     &mut self,
     sort_method: SortMethod,
@@ -1452,6 +1845,7 @@ impl Home {
     }
 
     self.current_page = 0;
+    self.save_sort(context);
     self.sort(true, rq, context);
   }
 
@@ -1464,7 +1858,7 @@ impl Home {
     );
 
     if update {
-      self.update_shelf(false, rq);
+      self.update_shelf(false, rq, context);
       let search_visible = rlocate::<SearchBar>(self).is_some();
       self.update_top_bar(search_visible, rq);
       self.update_bottom_bar(rq, context);
@@ -1483,7 +1877,7 @@ impl Home {
       update_top_bar = true;
     }
 
-    context.library.flush();
+    self.flush(hub, context);
 
     let library_settings = &context.settings.libraries[index];
     let library = Library::new(&library_settings.path, library_settings.mode);
@@ -1491,9 +1885,13 @@ impl Home {
     context.library = library;
     context.settings.selected_library = index;
 
-    if self.sort_method != library_settings.sort_method {
+    let reverse_order = library_settings
+      .reverse_order
+      .unwrap_or_else(|| library_settings.sort_method.reverse_order());
+
+    if self.sort_method != library_settings.sort_method || self.reverse_order != reverse_order {
       self.sort_method = library_settings.sort_method;
-      self.reverse_order = library_settings.sort_method.reverse_order();
+      self.reverse_order = reverse_order;
       update_top_bar = true;
     }
 
@@ -1515,14 +1913,52 @@ impl Home {
     let home = context.library.home.clone();
     self.current_directory = PathBuf::default();
     self.select_directory(&home, hub, rq, context);
+    self.update_collections_bar(rq, context);
   }
 
-  fn import(&mut self, rq: &mut RenderQueue, context: &mut Context) {
+  fn import(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    if !context.has_room() {
+      let notif = Notification::new(
+        ViewId::LowStorageNotif,
+        tr!("Not enough storage space to import documents."),
+        context,
+      );
+      push_notification(&mut self.children, notif, hub, rq, context);
+      return;
+    }
     let home = context.library.home.clone();
     let settings = context.settings.import.clone();
-    context.library.import(&home, &settings);
+    let mut dialog = ProgressDialog::new(
+      ViewId::ImportProgress,
+      tr!("Importing documents"),
+      None,
+      context,
+    );
+    let Context {
+      library, fb, fonts, ..
+    } = context;
+    dialog.show(fb.as_mut(), fonts);
+    let report = library.import_with_progress(&home, &settings, || {
+      dialog.advance(None, fb.as_mut(), fonts)
+    });
+    rq.add(RenderData::expose(*dialog.rect(), UpdateMode::Gui));
+    if settings.dry_run {
+      if let Err(e) = report.save(IMPORT_REPORT_PATH) {
+        report_error(hub, Severity::Warning, "Can't save the import report", &e);
+      } else {
+        let msg = tr!(
+          "Dry run: {} documents to import, {} skipped.",
+          report.added.len() + report.transferred.len(),
+          report.skipped.len()
+        );
+        let notif = Notification::new(ViewId::MessageNotif, msg, context);
+        push_notification(&mut self.children, notif, hub, rq, context);
+      }
+      return;
+    }
     context.library.sort(self.sort_method, self.reverse_order);
     self.refresh_visibles(true, false, rq, context);
+    queue_conversions(hub, context);
   }
 
   fn clean_up(&mut self, rq: &mut RenderQueue, context: &mut Context) {
@@ -1530,8 +1966,10 @@ impl Home {
     self.refresh_visibles(true, false, rq, context);
   }
 
-  fn flush(&mut self, context: &mut Context) {
-    context.library.flush();
+  fn flush(&mut self, hub: &Hub, context: &mut Context) {
+    if let Err(e) = context.library.flush() {
+      report_error(hub, Severity::Critical, "Can't save the metadata", &e);
+    }
   }
 
   fn terminate_fetchers(&mut self, path: &Path, hub: &Hub) {
@@ -1583,7 +2021,15 @@ impl Home {
         .send(Event::Select(EntryId::SecondColumn(second_column)))
         .ok();
     }
-    let process = hook.program.as_ref().and_then(|p| {
+    let has_room = context.has_room();
+    if hook.program.is_some() && !has_room {
+      hub
+        .send(Event::Notify(tr!(
+          "Not enough storage space to download documents."
+        )))
+        .ok();
+    }
+    let process = hook.program.as_ref().filter(|_| has_room).and_then(|p| {
       let dir = context.library.home.join(&hook.path);
       self
         .spawn_child(&dir, p, context.settings.wifi, context.online, hub)
@@ -1634,6 +2080,32 @@ impl Home {
                   hub2.send(Event::Notify(msg.to_string())).ok();
                 }
               },
+              Some("progress") => {
+                if let (Some(msg), Some(progress)) = (
+                  event.get("message").and_then(JsonValue::as_str),
+                  event.get("progress").and_then(JsonValue::as_f64),
+                ) {
+                  hub2
+                    .send(Event::NotifyProgress(
+                      ViewId::HookProgressNotif,
+                      msg.to_string(),
+                      progress as f32,
+                    ))
+                    .ok();
+                }
+              },
+              Some("progressDialog") => {
+                if let Some(msg) = event.get("message").and_then(JsonValue::as_str) {
+                  let progress = event.get("progress").and_then(JsonValue::as_f64);
+                  hub2
+                    .send(Event::Progress(
+                      ViewId::HookProgress,
+                      msg.to_string(),
+                      progress.map(|p| p as f32),
+                    ))
+                    .ok();
+                }
+              },
               Some("addDocument") => {
                 if let Some(info) = event
                   .get("info")
@@ -1645,7 +2117,12 @@ impl Home {
               },
               Some("setWifi") => {
                 if let Some(enable) = event.get("enable").and_then(JsonValue::as_bool) {
-                  hub2.send(Event::SetWifi(enable)).ok();
+                  let evt = if enable {
+                    Event::AcquireNetwork(NetworkTask::Fetcher)
+                  } else {
+                    Event::ReleaseNetwork(NetworkTask::Fetcher)
+                  };
+                  hub2.send(evt).ok();
                 }
               },
               Some("cleanUp") => {
@@ -1682,6 +2159,211 @@ impl Home {
   }
 }
 
+// Moves a document to the trash, and returns its path within the trash.
+pub fn trash_document(path: &Path, context: &mut Context) -> Result<PathBuf, Error> {
+  let trash_path = context.library.home.join(TRASH_DIRNAME);
+  if !trash_path.is_dir() {
+    fs::create_dir_all(&trash_path)?;
+  }
+  let mut trash = Library::new(trash_path, LibraryMode::Database);
+  // The size of the trash is computed from the listed books.
+  context.library.set_visibility(path, Visibility::Visible);
+  let dest = context.library.move_to(path, &mut trash)?;
+  let (mut files, _) = trash.list(&trash.home, None, false);
+  let mut size = files.iter().map(|info| info.file.size).sum::<u64>();
+  if size > context.settings.home.max_trash_size {
+    sort(&mut files, SortMethod::Added, true);
+    while size > context.settings.home.max_trash_size {
+      let info = files.pop().unwrap();
+      if let Err(e) = trash.remove(&info.file.path) {
+        eprintln!("{}", e);
+        break;
+      }
+      size -= info.file.size;
+    }
+  }
+  trash.flush()?;
+  Ok(dest)
+}
+
+// Moves a document back from the trash, or moves it there again.
+pub fn revert_trash(
+  path: &Path,
+  trash_path: &Path,
+  dir: UndoDir,
+  context: &mut Context,
+) -> Result<(), Error> {
+  if dir == UndoDir::Redo {
+    return trash_document(path, context).map(|_| ());
+  }
+  let mut trash = Library::new(
+    context.library.home.join(TRASH_DIRNAME),
+    LibraryMode::Database,
+  );
+  trash.move_to(trash_path, &mut context.library)?;
+  trash.flush()
+}
+
+fn library_menu_entries(context: &Context) -> Vec<EntryKind> {
+  let selected_library = context.settings.selected_library;
+  let library_settings = &context.settings.libraries[selected_library];
+
+  let libraries: Vec<EntryKind> = context
+    .settings
+    .libraries
+    .iter()
+    .enumerate()
+    .map(|(index, lib)| {
+      EntryKind::RadioButton(
+        lib.name.clone(),
+        EntryId::LoadLibrary(index),
+        index == selected_library,
+      )
+    })
+    .collect();
+
+  let database = if library_settings.mode == LibraryMode::Database {
+    vec![
+      EntryKind::Command(tr!("Import"), EntryId::Import),
+      EntryKind::CheckBox(
+        tr!("Show Archived"),
+        EntryId::ToggleShowArchived,
+        context.library.show_archived,
+      ),
+      EntryKind::CheckBox(
+        tr!("Show Loaned"),
+        EntryId::ToggleShowLoaned,
+        context.library.show_loaned,
+      ),
+      EntryKind::CheckBox(
+        tr!("Collections Bar"),
+        EntryId::ToggleCollectionsBar,
+        context.settings.home.collections_bar,
+      ),
+      EntryKind::Separator,
+      EntryKind::Command(tr!("Check Integrity"), EntryId::CheckLibrary),
+      EntryKind::Command(tr!("Clean Up"), EntryId::CleanUp),
+      EntryKind::Command(tr!("Flush"), EntryId::Flush),
+    ]
+  } else {
+    Vec::new()
+  };
+
+  let filesystem = if library_settings.mode == LibraryMode::Filesystem {
+    vec![
+      EntryKind::CheckBox(
+        tr!("Show Hidden"),
+        EntryId::ToggleShowHidden,
+        context.library.show_hidden,
+      ),
+      EntryKind::Separator,
+      EntryKind::Command(tr!("Check Integrity"), EntryId::CheckLibrary),
+      EntryKind::Command(tr!("Clean Up"), EntryId::CleanUp),
+      EntryKind::Command(tr!("Flush"), EntryId::Flush),
+    ]
+  } else {
+    Vec::new()
+  };
+
+  let mut entries = vec![EntryKind::SubMenu(tr!("Library"), libraries)];
+
+  if !database.is_empty() {
+    entries.push(EntryKind::SubMenu(tr!("Database"), database));
+  }
+
+  if !filesystem.is_empty() {
+    entries.push(EntryKind::SubMenu(tr!("Filesystem"), filesystem));
+  }
+
+  let hooks: Vec<EntryKind> = context.settings.libraries[selected_library]
+    .hooks
+    .iter()
+    .map(|v| {
+      EntryKind::Command(
+        v.path.to_string_lossy().into_owned(),
+        EntryId::ToggleSelectDirectory(context.library.home.join(&v.path)),
+      )
+    })
+    .collect();
+
+  if !hooks.is_empty() {
+    entries.push(EntryKind::SubMenu(tr!("Toggle Select"), hooks));
+  }
+
+  entries.push(EntryKind::Command(
+    tr!("Notebooks"),
+    EntryId::ToggleNotebooks,
+  ));
+
+  entries.push(EntryKind::Separator);
+
+  let first_column = library_settings.first_column;
+  entries.push(EntryKind::SubMenu(
+    tr!("First Column"),
+    vec![
+      EntryKind::RadioButton(
+        tr!("Title and Author"),
+        EntryId::FirstColumn(FirstColumn::TitleAndAuthor),
+        first_column == FirstColumn::TitleAndAuthor,
+      ),
+      EntryKind::RadioButton(
+        tr!("Title and Status"),
+        EntryId::FirstColumn(FirstColumn::TitleAndStatus),
+        first_column == FirstColumn::TitleAndStatus,
+      ),
+      EntryKind::RadioButton(
+        tr!("File Name"),
+        EntryId::FirstColumn(FirstColumn::FileName),
+        first_column == FirstColumn::FileName,
+      ),
+    ],
+  ));
+
+  let second_column = library_settings.second_column;
+  entries.push(EntryKind::SubMenu(
+    tr!("Second Column"),
+    vec![
+      EntryKind::RadioButton(
+        tr!("Progress"),
+        EntryId::SecondColumn(SecondColumn::Progress),
+        second_column == SecondColumn::Progress,
+      ),
+      EntryKind::RadioButton(
+        tr!("Status"),
+        EntryId::SecondColumn(SecondColumn::Status),
+        second_column == SecondColumn::Status,
+      ),
+      EntryKind::RadioButton(
+        tr!("Year"),
+        EntryId::SecondColumn(SecondColumn::Year),
+        second_column == SecondColumn::Year,
+      ),
+      EntryKind::RadioButton(
+        tr!("Time Left"),
+        EntryId::SecondColumn(SecondColumn::TimeLeft),
+        second_column == SecondColumn::TimeLeft,
+      ),
+      EntryKind::RadioButton(
+        tr!("Last Opened"),
+        EntryId::SecondColumn(SecondColumn::LastOpened),
+        second_column == SecondColumn::LastOpened,
+      ),
+      EntryKind::RadioButton(
+        tr!("File Size"),
+        EntryId::SecondColumn(SecondColumn::FileSize),
+        second_column == SecondColumn::FileSize,
+      ),
+      EntryKind::RadioButton(
+        tr!("Format"),
+        EntryId::SecondColumn(SecondColumn::Format),
+        second_column == SecondColumn::Format,
+      ),
+    ],
+  ));
+
+  entries
+}
+
 impl View for Home {
   fn handle_event(
     &mut self,
@@ -1705,7 +2387,7 @@ impl View for Home {
             } else if !context.settings.home.address_bar {
               self.toggle_address_bar(Some(true), true, hub, rq, context);
             }
-          }
+          },
           Dir::North
             if self.children[self.shelf_index].rect().includes(start)
               && self.children[0].rect().includes(end) =>
@@ -1727,7 +2409,7 @@ impl View for Home {
         true
       },
       Event::Gesture(GestureEvent::Arrow { dir, .. }) => {
-        match dir {
+        match context.ui_dir(dir) {
           Dir::West => self.go_to_page(0, rq, context),
           Dir::East => {
             let pages_count = self.pages_count;
@@ -1810,6 +2492,32 @@ impl View for Home {
         self.toggle_go_to_page(Some(false), hub, rq, context);
         true
       },
+      Event::Close(ViewId::Lend) => {
+        self.toggle_loan_input(false, Some(false), hub, rq, context);
+        self.target_book = None;
+        true
+      },
+      Event::Close(ViewId::EditLoanNote) => {
+        self.toggle_loan_input(true, Some(false), hub, rq, context);
+        self.target_book = None;
+        true
+      },
+      Event::Close(ViewId::Notebooks) => {
+        self.toggle_notebooks(Some(false), rq, context);
+        true
+      },
+      Event::Close(ViewId::LibraryCheck) => {
+        self.toggle_library_check(Some(false), rq, context);
+        true
+      },
+      Event::Select(EntryId::CheckLibrary) => {
+        self.toggle_library_check(None, rq, context);
+        true
+      },
+      Event::Select(EntryId::ToggleNotebooks) => {
+        self.toggle_notebooks(None, rq, context);
+        true
+      },
       Event::Select(EntryId::Sort(sort_method)) => {
         self.set_sort_method(sort_method, rq, context);
         true
@@ -1819,12 +2527,16 @@ impl View for Home {
         self.set_reverse_order(next_value, rq, context);
         true
       },
+      Event::Select(EntryId::ToggleColumnHeaders) => {
+        self.toggle_column_headers(rq, context);
+        true
+      },
       Event::Select(EntryId::LoadLibrary(index)) => {
         self.load_library(index, hub, rq, context);
         true
       },
       Event::Select(EntryId::Import) => {
-        self.import(rq, context);
+        self.import(hub, rq, context);
         true
       },
       Event::Select(EntryId::CleanUp) => {
@@ -1832,7 +2544,7 @@ impl View for Home {
         true
       },
       Event::Select(EntryId::Flush) => {
-        self.flush(context);
+        self.flush(hub, context);
         true
       },
       Event::AddDocument(ref info) => {
@@ -1840,10 +2552,69 @@ impl View for Home {
         self.add_document(*info2, rq, context);
         true
       },
+      Event::OpdsDownloaded(Some(ref path)) | Event::Converted(ref path) => {
+        if context.library.import_file(path, &context.settings.import) {
+          self.sort(false, rq, context);
+          self.refresh_visibles(true, false, rq, context);
+        }
+        true
+      },
       Event::Select(EntryId::SetStatus(ref path, status)) => {
         self.set_status(path, status, rq, context);
         true
       },
+      Event::Select(EntryId::SetVisibility(ref path, visibility)) => {
+        context.library.set_visibility(path, visibility);
+        self.refresh_visibles(true, false, rq, context);
+        true
+      },
+      Event::Select(EntryId::Lend(ref path)) => {
+        self.target_book = Some(path.clone());
+        self.toggle_loan_input(false, Some(true), hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::EditLoanNote(ref path)) => {
+        self.target_book = Some(path.clone());
+        self.toggle_loan_input(true, Some(true), hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ReturnLoan(ref path)) => {
+        context.library.set_loan(path, None);
+        self.refresh_visibles(true, false, rq, context);
+        true
+      },
+      Event::Submit(ViewId::LendInput, ref text) => {
+        if let Some(path) = self.target_book.take() {
+          let borrower = text.trim();
+          if !borrower.is_empty() {
+            let mut loan = self
+              .visible_books
+              .iter()
+              .find(|info| info.file.path == path)
+              .and_then(|info| info.loan.clone())
+              .unwrap_or_default();
+            loan.borrower = borrower.to_string();
+            context.library.set_loan(&path, Some(loan));
+            self.refresh_visibles(true, false, rq, context);
+          }
+        }
+        true
+      },
+      Event::Submit(ViewId::EditLoanNoteInput, ref text) => {
+        if let Some(path) = self.target_book.take() {
+          let loan = self
+            .visible_books
+            .iter()
+            .find(|info| info.file.path == path)
+            .and_then(|info| info.loan.clone());
+          if let Some(mut loan) = loan {
+            loan.note = text.trim().to_string();
+            context.library.set_loan(&path, Some(loan));
+            self.refresh_visibles(true, false, rq, context);
+          }
+        }
+        true
+      },
       Event::Select(EntryId::FirstColumn(first_column)) => {
         let selected_library = context.settings.selected_library;
         context.settings.libraries[selected_library].first_column = first_column;
@@ -1858,7 +2629,17 @@ impl View for Home {
       },
       Event::Submit(ViewId::AddressBarInput, ref addr) => {
         self.toggle_keyboard(false, true, None, hub, rq, context);
-        self.select_directory(&Path::new(addr), hub, rq, context);
+        let path = context
+          .library
+          .home
+          .join(addr.trim_start_matches(FILE_SCHEME));
+        if path.is_file() {
+          if let Some(info) = context.library.document_info(&path) {
+            hub.send(Event::Open(Box::new(info))).ok();
+          }
+        } else {
+          self.select_directory(&path, hub, rq, context);
+        }
         true
       },
       Event::Submit(ViewId::HomeSearchInput, ref text) => {
@@ -1877,12 +2658,10 @@ impl View for Home {
         } else {
           let notif = Notification::new(
             ViewId::InvalidSearchQueryNotif,
-            "Invalid search query.".to_string(),
-            hub,
-            rq,
+            tr!("Invalid search query."),
             context,
           );
-          self.children.push(Box::new(notif) as Box<dyn View>);
+          push_notification(&mut self.children, notif, hub, rq, context);
         }
         true
       },
@@ -1901,7 +2680,7 @@ impl View for Home {
       },
       Event::NavigationBarResized(_) => {
         self.adjust_shelf_top_edge();
-        self.update_shelf(true, rq);
+        self.update_shelf(true, rq, context);
         self.update_bottom_bar(rq, context);
         for i in self.shelf_index - 2..=self.shelf_index - 1 {
           rq.add(RenderData::new(
@@ -1913,24 +2692,101 @@ impl View for Home {
         true
       },
       Event::Select(EntryId::Remove(ref path)) => {
-        self
-          .remove(path, rq, context)
-          .map_err(|e| eprintln!("{}", e))
-          .ok();
+        if let Err(e) = self.remove(path, hub, rq, context) {
+          report_error(hub, Severity::Error, "Can't remove the document", &e);
+        }
         true
       },
       Event::Select(EntryId::MoveTo(ref path, index)) => {
-        self
-          .move_to(path, index, rq, context)
-          .map_err(|e| eprintln!("{}", e))
-          .ok();
+        if let Err(e) = self.move_to(path, index, rq, context) {
+          report_error(hub, Severity::Error, "Can't move the document", &e);
+        }
         true
       },
+      Event::Revert(ref action, dir) => match **action {
+        UndoAction::RemoveBook {
+          ref path,
+          ref trash_path,
+        } => {
+          if let Err(e) = revert_trash(path, trash_path, dir, context) {
+            report_error(hub, Severity::Error, "Can't restore the document", &e);
+          }
+          self.refresh_visibles(true, false, rq, context);
+          true
+        },
+        _ => false,
+      },
       Event::Select(EntryId::ToggleShowHidden) => {
         context.library.show_hidden = !context.library.show_hidden;
         self.refresh_visibles(true, false, rq, context);
         true
       },
+      Event::Select(EntryId::ToggleShowArchived) => {
+        context.library.show_archived = !context.library.show_archived;
+        self.refresh_visibles(true, true, rq, context);
+        true
+      },
+      Event::Select(EntryId::ToggleShowLoaned) => {
+        context.library.show_loaned = !context.library.show_loaned;
+        self.refresh_visibles(true, true, rq, context);
+        true
+      },
+      Event::Select(EntryId::ToggleCollectionsBar) => {
+        self.toggle_collections_bar(None, true, rq, context);
+        true
+      },
+      Event::ToggleSelectCollection(ref name) => {
+        if context.library.collection.as_ref() == Some(name) {
+          context.library.collection = None;
+        } else {
+          context.library.collection = Some(name.clone());
+        }
+        self.refresh_visibles(true, true, rq, context);
+        self.update_collections_bar(rq, context);
+        true
+      },
+      Event::Select(EntryId::ToggleCollection(ref path, ref name)) => {
+        let member = self
+          .visible_books
+          .iter()
+          .find(|info| &info.file.path == path)
+          .map_or(false, |info| info.collections.contains(name));
+        context.library.set_collection(path, name, !member);
+        // A collection disappears with its last book.
+        if context.library.collection.as_ref() == Some(name)
+          && context
+            .library
+            .collections()
+            .iter()
+            .all(|c| &c.name != name)
+        {
+          context.library.collection = None;
+        }
+        self.refresh_visibles(true, false, rq, context);
+        self.update_collections_bar(rq, context);
+        true
+      },
+      Event::Select(EntryId::NewCollection(ref path)) => {
+        self.target_book = Some(path.clone());
+        self.toggle_collection_input(Some(true), hub, rq, context);
+        true
+      },
+      Event::Submit(ViewId::NewCollectionInput, ref text) => {
+        if let Some(path) = self.target_book.take() {
+          let name = text.trim();
+          if !name.is_empty() {
+            context.library.set_collection(&path, name, true);
+            self.refresh_visibles(true, false, rq, context);
+            self.update_collections_bar(rq, context);
+          }
+        }
+        true
+      },
+      Event::Close(ViewId::NewCollection) => {
+        self.toggle_collection_input(Some(false), hub, rq, context);
+        self.target_book = None;
+        true
+      },
       Event::SelectDirectory(ref path) | Event::Select(EntryId::SelectDirectory(ref path)) => {
         self.select_directory(path, hub, rq, context);
         true
@@ -1956,6 +2812,10 @@ impl View for Home {
         self.go_to_neighbor(dir, rq, context);
         true
       },
+      Event::AlphabetRail(letter, FingerStatus::Up) => {
+        self.go_to_letter(letter, rq, context);
+        true
+      },
       Event::Device(DeviceEvent::Button {
         code: ButtonCode::Backward,
         status: ButtonStatus::Pressed,
@@ -2057,6 +2917,32 @@ impl View for Home {
       index += 1;
     }
 
+    // Collections bar.
+    if context.settings.home.collections_bar {
+      self.children[index].resize(
+        rect![
+          rect.min.x,
+          shelf_min_y,
+          rect.max.x,
+          shelf_min_y + small_height - thickness
+        ],
+        hub,
+        rq,
+        context,
+      );
+      shelf_min_y += small_height - thickness;
+      index += 1;
+
+      self.children[index].resize(
+        rect![rect.min.x, shelf_min_y, rect.max.x, shelf_min_y + thickness],
+        hub,
+        rq,
+        context,
+      );
+      shelf_min_y += thickness;
+      index += 1;
+    }
+
     // Navigation bar.
     if context.settings.home.navigation_bar {
       let count = if self.children[self.shelf_index + 2].is::<SearchBar>() {
@@ -2165,7 +3051,7 @@ impl View for Home {
     let shelf_rect = rect![rect.min.x, shelf_min_y, rect.max.x, shelf_max_y];
     self.children[self.shelf_index].resize(shelf_rect, hub, rq, context);
 
-    self.update_shelf(true, &mut RenderQueue::new());
+    self.update_shelf(true, &mut RenderQueue::new(), context);
     self.update_bottom_bar(&mut RenderQueue::new(), context);
 
     // Floating windows.
@@ -2196,4 +3082,14 @@ impl View for Home {
   fn id(&self) -> Id {
     self.id
   }
+
+  fn commands(&self, context: &Context) -> Vec<PaletteCommand> {
+    let mut commands = vec![
+      PaletteCommand::new("Search", Event::Toggle(ViewId::SearchBar)),
+      PaletteCommand::new("Go To Page", Event::Toggle(ViewId::GoToPage)),
+    ];
+    menu_commands(&self.sort_menu_entries(), "Sort", &mut commands);
+    menu_commands(&library_menu_entries(context), "", &mut commands);
+    commands
+  }
 }
@@ -0,0 +1,339 @@
+use crate::{
+  app::Context,
+  color::{BLACK, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, Pixmap, UpdateMode},
+  geom::{BorderSpec, Dir, Rectangle},
+  gesture::GestureEvent,
+  unit::scale_by_dpi,
+  view::{
+    icon::Icon,
+    label::Label,
+    sketch::sketch_files,
+    Align,
+    AppCmd,
+    Bus,
+    EntryId,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_MEDIUM,
+  },
+};
+use anyhow::{format_err, Error};
+use std::{
+  fs::File,
+  path::{Path, PathBuf},
+};
+
+// Decodes the sketch at *path* and scales it down to fit in the given dimensions.
+fn load_preview(path: &Path, max_width: u32, max_height: u32) -> Result<Pixmap, Error> {
+  let decoder = png::Decoder::new(File::open(path)?);
+  let (info, mut reader) = decoder.read_info()?;
+  if info.color_type != png::ColorType::Grayscale || info.bit_depth != png::BitDepth::Eight {
+    return Err(format_err!("unsupported pixel format"));
+  }
+  let mut data = vec![0; info.buffer_size()];
+  reader.next_frame(&mut data)?;
+  let scale = (max_width as f32 / info.width as f32).min(max_height as f32 / info.height as f32);
+  let width = ((info.width as f32 * scale) as u32).max(1);
+  let height = ((info.height as f32 * scale) as u32).max(1);
+  let mut preview = Pixmap::new(width, height);
+  // Each pixel of the preview gets the darkest value of its source area, so that thin strokes
+  // remain visible.
+  for y in 0..height {
+    let y0 = (y as f32 / scale) as u32;
+    let y1 = (((y + 1) as f32 / scale) as u32)
+      .max(y0 + 1)
+      .min(info.height);
+    for x in 0..width {
+      let x0 = (x as f32 / scale) as u32;
+      let x1 = (((x + 1) as f32 / scale) as u32)
+        .max(x0 + 1)
+        .min(info.width);
+      let mut value = WHITE;
+      for sy in y0..y1 {
+        let row = (sy * info.width) as usize;
+        for sx in x0..x1 {
+          value = value.min(data[row + sx as usize]);
+        }
+      }
+      preview.data[(y * width + x) as usize] = value;
+    }
+  }
+  Ok(preview)
+}
+
+pub struct Notebooks {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  dir: PathBuf,
+  files: Vec<PathBuf>,
+  previews: Vec<(Rectangle, Option<Pixmap>)>,
+  current_page: usize,
+  columns: usize,
+  rows: usize,
+}
+
+impl Notebooks {
+  pub fn new(rect: Rectangle, context: &Context) -> Notebooks {
+    let dir = context
+      .library
+      .home
+      .join(&context.settings.sketch.save_path);
+    let files = sketch_files(&dir);
+    let mut notebooks = Notebooks {
+      id: ID_FEEDER.next(),
+      rect,
+      children: Vec::new(),
+      dir,
+      files,
+      previews: Vec::new(),
+      current_page: 0,
+      columns: 3,
+      rows: 3,
+    };
+    notebooks.layout();
+    notebooks
+  }
+
+  fn bar_height(&self) -> i32 {
+    scale_by_dpi(SMALL_BAR_HEIGHT, CURRENT_DEVICE.dpi) as i32
+  }
+
+  fn pages_count(&self) -> usize {
+    let per_page = self.columns * self.rows;
+    ((self.files.len() + per_page - 1) / per_page).max(1)
+  }
+
+  fn layout(&mut self) {
+    let (columns, rows) = if self.rect.width() > self.rect.height() {
+      (4, 2)
+    } else {
+      (3, 3)
+    };
+    self.columns = columns;
+    self.rows = rows;
+    self.current_page = self.current_page.min(self.pages_count() - 1);
+
+    let bar_height = self.bar_height();
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, CURRENT_DEVICE.dpi) as i32;
+    let title_rect = rect![
+      self.rect.min.x + bar_height,
+      self.rect.min.y,
+      self.rect.max.x - bar_height,
+      self.rect.min.y + bar_height
+    ];
+    let close_rect = rect![
+      self.rect.max.x - bar_height,
+      self.rect.min.y,
+      self.rect.max.x,
+      self.rect.min.y + bar_height
+    ];
+    let title = if self.pages_count() > 1 {
+      tr!(
+        "Notebooks ({}/{})",
+        self.current_page + 1,
+        self.pages_count()
+      )
+    } else {
+      tr!("Notebooks")
+    };
+    self.children = vec![
+      Box::new(Label::new(title_rect, title, Align::Center)) as Box<dyn View>,
+      Box::new(Icon::new(
+        "close",
+        close_rect,
+        Event::Close(ViewId::Notebooks),
+      )) as Box<dyn View>,
+    ];
+
+    let grid_rect = rect![
+      self.rect.min.x,
+      self.rect.min.y + bar_height + thickness,
+      self.rect.max.x,
+      self.rect.max.y
+    ];
+    let cell_width = grid_rect.width() as i32 / columns as i32;
+    let cell_height = grid_rect.height() as i32 / rows as i32;
+    let padding = bar_height / 4;
+    let per_page = columns * rows;
+    let start = self.current_page * per_page;
+    let end = (start + per_page).min(self.files.len());
+    self.previews = (start..end)
+      .map(|index| {
+        let i = (index - start) as i32;
+        let cell = rect![
+          grid_rect.min.x + (i % columns as i32) * cell_width,
+          grid_rect.min.y + (i / columns as i32) * cell_height,
+          grid_rect.min.x + (i % columns as i32 + 1) * cell_width,
+          grid_rect.min.y + (i / columns as i32 + 1) * cell_height
+        ];
+        let max_width = (cell_width - 2 * padding).max(1) as u32;
+        let max_height = (cell_height - 2 * padding - bar_height / 2).max(1) as u32;
+        let preview = load_preview(&self.dir.join(&self.files[index]), max_width, max_height)
+          .map_err(|e| eprintln!("Can't load preview: {:#}.", e))
+          .ok();
+        (cell, preview)
+      })
+      .collect();
+  }
+
+  fn set_current_page(&mut self, dir: Dir, rq: &mut RenderQueue) {
+    let current_page = match dir {
+      Dir::West if self.current_page + 1 < self.pages_count() => self.current_page + 1,
+      Dir::East if self.current_page > 0 => self.current_page - 1,
+      _ => return,
+    };
+    self.current_page = current_page;
+    self.layout();
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
+}
+
+impl View for Notebooks {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+        let per_page = self.columns * self.rows;
+        if let Some(index) = self
+          .previews
+          .iter()
+          .position(|(cell, _)| cell.includes(center))
+        {
+          let file = self
+            .dir
+            .join(&self.files[self.current_page * per_page + index]);
+          hub.send(Event::Close(ViewId::Notebooks)).ok();
+          hub
+            .send(Event::Select(EntryId::Launch(AppCmd::Sketch {
+              file: Some(file),
+            })))
+            .ok();
+        }
+        true
+      },
+      Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
+        self.set_current_page(context.ui_dir(dir), rq);
+        true
+      },
+      Event::Gesture(..) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let bar_height = self.bar_height();
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    fb.draw_rectangle(&self.rect, WHITE);
+    fb.draw_rectangle(
+      &rect![
+        self.rect.min.x,
+        self.rect.min.y + bar_height,
+        self.rect.max.x,
+        self.rect.min.y + bar_height + thickness
+      ],
+      BLACK,
+    );
+
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+
+    if self.files.is_empty() {
+      let plan = font.plan("No notebooks.", None, None);
+      let pt = pt!(
+        self.rect.center().x - plan.width / 2,
+        self.rect.center().y + x_height / 2
+      );
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+      return;
+    }
+
+    let per_page = self.columns * self.rows;
+    for (index, (cell, preview)) in self.previews.iter().enumerate() {
+      let label_height = bar_height / 2;
+      let preview_area = rect![
+        cell.min.x,
+        cell.min.y,
+        cell.max.x,
+        cell.max.y - label_height
+      ];
+      if let Some(pixmap) = preview {
+        let pt = pt!(
+          preview_area.center().x - pixmap.width as i32 / 2,
+          preview_area.center().y - pixmap.height as i32 / 2
+        );
+        fb.draw_pixmap(pixmap, pt);
+        let frame = rect![
+          pt.x - 1,
+          pt.y - 1,
+          pt.x + pixmap.width as i32 + 1,
+          pt.y + pixmap.height as i32 + 1
+        ];
+        fb.draw_rectangle_outline(
+          &frame,
+          &BorderSpec {
+            thickness: 1,
+            color: BLACK,
+          },
+        );
+      }
+      let name = self.files[self.current_page * per_page + index]
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+      let max_width = cell.width() as i32 - x_height;
+      let plan = font.plan(&name, Some(max_width), None);
+      let pt = pt!(
+        cell.center().x - plan.width / 2,
+        cell.max.y - (label_height - x_height) / 2
+      );
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    }
+  }
+
+  fn resize(&mut self, rect: Rectangle, _hub: &Hub, _rq: &mut RenderQueue, _context: &mut Context) {
+    self.rect = rect;
+    self.layout();
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+
+  fn view_id(&self) -> Option<ViewId> {
+    Some(ViewId::Notebooks)
+  }
+}
@@ -1,4 +1,7 @@
-use super::book::Book;
+use super::{
+  alphabet_rail::{AlphabetRail, LetterOverlay},
+  book::Book,
+};
 use crate::{
   app::Context,
   color::{SEPARATOR_NORMAL, WHITE},
@@ -7,12 +10,16 @@ use crate::{
   framebuffer::{Framebuffer, UpdateMode},
   geom::{divide, halves, CycleDir, Dir, Rectangle},
   gesture::GestureEvent,
-  metadata::Info,
+  input::FingerStatus,
+  metadata::{Info, ReadingSpeed, SortMethod},
   settings::{FirstColumn, SecondColumn},
   unit::scale_by_dpi,
   view::{
     filler::Filler,
+    label::Label,
+    Align,
     Bus,
+    EntryId,
     Event,
     Hub,
     Id,
@@ -21,10 +28,20 @@ use crate::{
     View,
     BIG_BAR_HEIGHT,
     ID_FEEDER,
+    SMALL_BAR_HEIGHT,
     THICKNESS_MEDIUM,
   },
 };
 
+const RAIL_WIDTH: f32 = 54.0;
+// The sort methods offered by the column headers.
+const HEADER_METHODS: [SortMethod; 4] = [
+  SortMethod::Title,
+  SortMethod::Author,
+  SortMethod::Progress,
+  SortMethod::Added,
+];
+
 pub struct Shelf {
   id: Id,
   pub rect: Rectangle,
@@ -32,10 +49,20 @@ pub struct Shelf {
   pub max_lines: usize,
   first_column: FirstColumn,
   second_column: SecondColumn,
+  rtl: bool,
+  // Whether the alphabet rail is shown along the edge of the books.
+  pub rail: bool,
+  // The current sort method and order, when the column headers are shown.
+  pub headers: Option<(SortMethod, bool)>,
 }
 
 impl Shelf {
-  pub fn new(rect: Rectangle, first_column: FirstColumn, second_column: SecondColumn) -> Shelf {
+  pub fn new(
+    rect: Rectangle,
+    first_column: FirstColumn,
+    second_column: SecondColumn,
+    rtl: bool,
+  ) -> Shelf {
     let dpi = CURRENT_DEVICE.dpi;
     let big_height = scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32;
     let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
@@ -47,7 +74,97 @@ impl Shelf {
       max_lines,
       first_column,
       second_column,
+      rtl,
+      rail: false,
+      headers: None,
+    }
+  }
+
+  // The rectangle below the column headers.
+  fn list_rect(&self) -> Rectangle {
+    let mut rect = self.rect;
+    if self.headers.is_some() {
+      let dpi = CURRENT_DEVICE.dpi;
+      let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+      let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+      rect.min.y = (rect.min.y + 2 * small_height / 3 + thickness).min(rect.max.y);
+    }
+    rect
+  }
+
+  // The number of books that fit below the column headers.
+  pub fn lines_count(&self) -> usize {
+    let dpi = CURRENT_DEVICE.dpi;
+    let big_height = scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    ((self.list_rect().height() as i32 + thickness) / big_height).max(1) as usize
+  }
+
+  pub fn set_headers(&mut self, headers: Option<(SortMethod, bool)>) {
+    self.headers = headers;
+    self.max_lines = self.lines_count();
+  }
+
+  // The rectangles of the books and of the rail.
+  fn split_rail(&self) -> (Rectangle, Option<Rectangle>) {
+    let list_rect = self.list_rect();
+    if !self.rail {
+      return (list_rect, None);
     }
+    let rail_width = scale_by_dpi(RAIL_WIDTH, CURRENT_DEVICE.dpi) as i32;
+    let mut books_rect = list_rect;
+    let rail_rect = if self.rtl {
+      books_rect.min.x += rail_width;
+      rect![
+        list_rect.min.x,
+        list_rect.min.y,
+        books_rect.min.x,
+        list_rect.max.y
+      ]
+    } else {
+      books_rect.max.x -= rail_width;
+      rect![
+        books_rect.max.x,
+        list_rect.min.y,
+        list_rect.max.x,
+        list_rect.max.y
+      ]
+    };
+    (books_rect, Some(rail_rect))
+  }
+
+  // The titles of the sortable columns: tapping the current one reverses the order.
+  fn push_headers(&mut self, sort_method: SortMethod, reverse_order: bool) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let list_rect = self.list_rect();
+    let y_max = list_rect.min.y - thickness;
+    let widths = divide(self.rect.width() as i32, HEADER_METHODS.len() as i32);
+    let mut x_pos = self.rect.min.x;
+    for (i, method) in HEADER_METHODS.iter().enumerate() {
+      let (text, event) = if *method == sort_method {
+        let arrow = if reverse_order { '↓' } else { '↑' };
+        (
+          format!("{} {}", tr!(method.label()), arrow),
+          Event::Select(EntryId::ReverseOrder),
+        )
+      } else {
+        (tr!(method.label()), Event::Select(EntryId::Sort(*method)))
+      };
+      let label = Label::new(
+        rect![x_pos, self.rect.min.y, x_pos + widths[i], y_max],
+        text,
+        Align::Center,
+      )
+      .event(Some(event));
+      self.children.push(Box::new(label) as Box<dyn View>);
+      x_pos += widths[i];
+    }
+    let separator = Filler::new(
+      rect![self.rect.min.x, y_max, self.rect.max.x, list_rect.min.y],
+      SEPARATOR_NORMAL,
+    );
+    self.children.push(Box::new(separator) as Box<dyn View>);
   }
 
   pub fn set_first_column(&mut self, first_column: FirstColumn) {
@@ -58,15 +175,18 @@ impl Shelf {
     self.second_column = second_column;
   }
 
-  pub fn update(&mut self, metadata: &[Info], rq: &mut RenderQueue) {
+  pub fn update(&mut self, metadata: &[Info], reading_speed: &ReadingSpeed, rq: &mut RenderQueue) {
     self.children.clear();
     let dpi = CURRENT_DEVICE.dpi;
-    let big_height = scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32;
     let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
     let (small_thickness, big_thickness) = halves(thickness);
-    let max_lines = ((self.rect.height() as i32 + thickness) / big_height) as usize;
-    let book_heights = divide(self.rect.height() as i32, max_lines as i32);
-    let mut y_pos = self.rect.min.y;
+    if let Some((sort_method, reverse_order)) = self.headers {
+      self.push_headers(sort_method, reverse_order);
+    }
+    let max_lines = self.lines_count();
+    let (books_rect, rail_rect) = self.split_rail();
+    let book_heights = divide(books_rect.height() as i32, max_lines as i32);
+    let mut y_pos = books_rect.min.y;
 
     for (index, info) in metadata.iter().enumerate() {
       let y_min = y_pos + if index > 0 { big_thickness } else { 0 };
@@ -77,16 +197,18 @@ impl Shelf {
           0
         };
       let book = Book::new(
-        rect![self.rect.min.x, y_min, self.rect.max.x, y_max],
+        rect![books_rect.min.x, y_min, books_rect.max.x, y_max],
         info.clone(),
         index,
         self.first_column,
         self.second_column,
+        reading_speed,
+        self.rtl,
       );
       self.children.push(Box::new(book) as Box<dyn View>);
       if index < max_lines - 1 {
         let separator = Filler::new(
-          rect![self.rect.min.x, y_max, self.rect.max.x, y_max + thickness],
+          rect![books_rect.min.x, y_max, books_rect.max.x, y_max + thickness],
           SEPARATOR_NORMAL,
         );
         self.children.push(Box::new(separator) as Box<dyn View>);
@@ -97,12 +219,22 @@ impl Shelf {
     if metadata.len() < max_lines {
       let y_start = y_pos + if metadata.is_empty() { 0 } else { thickness };
       let filler = Filler::new(
-        rect![self.rect.min.x, y_start, self.rect.max.x, self.rect.max.y],
+        rect![
+          books_rect.min.x,
+          y_start,
+          books_rect.max.x,
+          books_rect.max.y
+        ],
         WHITE,
       );
       self.children.push(Box::new(filler) as Box<dyn View>);
     }
 
+    if let Some(rail_rect) = rail_rect {
+      let rail = AlphabetRail::new(rail_rect);
+      self.children.push(Box::new(rail) as Box<dyn View>);
+    }
+
     self.max_lines = max_lines;
     rq.add(RenderData::new(self.id, self.rect, UpdateMode::Partial));
   }
@@ -114,12 +246,44 @@ impl View for Shelf {
     evt: &Event,
     _hub: &Hub,
     bus: &mut Bus,
-    _rq: &mut RenderQueue,
-    _context: &mut Context,
+    rq: &mut RenderQueue,
+    context: &mut Context,
   ) -> bool {
     match *evt {
+      Event::AlphabetRail(letter, FingerStatus::Down)
+      | Event::AlphabetRail(letter, FingerStatus::Motion) => {
+        if let Some(overlay) = self
+          .children
+          .last_mut()
+          .and_then(|child| child.downcast_mut::<LetterOverlay>())
+        {
+          overlay.update(letter, rq);
+        } else {
+          let (books_rect, _) = self.split_rail();
+          let side = 2 * scale_by_dpi(BIG_BAR_HEIGHT, CURRENT_DEVICE.dpi) as i32;
+          let center = books_rect.center();
+          let rect = rect![center - side / 2, center + side / 2];
+          let overlay = LetterOverlay::new(rect, letter);
+          rq.add(RenderData::new(overlay.id(), rect, UpdateMode::Fast));
+          self.children.push(Box::new(overlay) as Box<dyn View>);
+        }
+        true
+      },
+      // The page is then changed by the parent.
+      Event::AlphabetRail(_, FingerStatus::Up) => {
+        if let Some(rect) = self
+          .children
+          .last()
+          .filter(|child| child.is::<LetterOverlay>())
+          .map(|child| *child.rect())
+        {
+          self.children.pop();
+          rq.add(RenderData::expose(rect, UpdateMode::Gui));
+        }
+        false
+      },
       Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
-        match dir {
+        match context.ui_dir(dir) {
           Dir::West => {
             bus.push_back(Event::Page(CycleDir::Next));
             true
@@ -28,6 +28,8 @@ lazy_static! {
       "back",
       "frontlight",
       "frontlight-disabled",
+      "frontlight-auto",
+      "frontlight-auto-disabled",
       "menu",
       "angle-left",
       "angle-right",
@@ -70,6 +72,7 @@ lazy_static! {
       "enclosed_menu",
       "contrast",
       "gray",
+      "speaker",
     ]
     .iter()
     .cloned()
@@ -92,6 +95,7 @@ pub struct Icon {
   align: Align,
   corners: Option<CornerSpec>,
   event: Event,
+  view_id: Option<ViewId>,
   pub active: bool,
 }
 
@@ -106,6 +110,7 @@ impl Icon {
       align: Align::Center,
       corners: None,
       event,
+      view_id: None,
       active: false,
     }
   }
@@ -124,6 +129,11 @@ impl Icon {
     self.corners = corners;
     self
   }
+
+  pub fn with_view_id(mut self, view_id: ViewId) -> Icon {
+    self.view_id = Some(view_id);
+    self
+  }
 }
 
 impl View for Icon {
@@ -167,6 +177,9 @@ impl View for Icon {
           Event::History(dir, false) => {
             bus.push_back(Event::History(dir, true));
           },
+          Event::Back => {
+            bus.push_back(Event::ToggleNear(ViewId::RecentMenu, self.rect));
+          },
           _ => (),
         }
         true
@@ -230,4 +243,8 @@ impl View for Icon {
   fn id(&self) -> Id {
     self.id
   }
+
+  fn view_id(&self) -> Option<ViewId> {
+    self.view_id
+  }
 }
@@ -69,6 +69,17 @@ impl View for Image {
     rect.intersection(&self.rect).unwrap_or(self.rect)
   }
 
+  fn inversion_exemptions(&self) -> Vec<Rectangle> {
+    let x0 = self.rect.min.x + (self.rect.width() - self.pixmap.width) as i32 / 2;
+    let y0 = self.rect.min.y + (self.rect.height() - self.pixmap.height) as i32 / 2;
+    vec![rect![
+      x0,
+      y0,
+      x0 + self.pixmap.width as i32,
+      y0 + self.pixmap.height as i32
+    ]]
+  }
+
   fn rect(&self) -> &Rectangle {
     &self.rect
   }
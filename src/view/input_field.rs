@@ -304,6 +304,22 @@ impl View for InputField {
           false
         }
       },
+      Event::Select(EntryId::PasteInput(view_id)) if self.view_id == view_id => {
+        if let Some(text) = context.clipboard.as_ref() {
+          let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+          self.text.insert_str(self.cursor, &text);
+          self.cursor += text.len();
+          rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+          if !self.focused {
+            bus.push_back(Event::Submit(self.view_id, self.text.clone()));
+          }
+        }
+        true
+      },
+      Event::Select(EntryId::SaveSearch(view_id)) if self.view_id == view_id => {
+        context.save_search(&self.text, view_id);
+        true
+      },
       _ => false,
     }
   }
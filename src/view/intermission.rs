@@ -1,20 +1,44 @@
-use super::{Bus, Event, Hub, Id, RenderQueue, View, ID_FEEDER};
+use super::{
+  Bus,
+  Event,
+  Hub,
+  Id,
+  RenderQueue,
+  View,
+  BORDER_RADIUS_MEDIUM,
+  ID_FEEDER,
+  THICKNESS_LARGE,
+};
 use crate::{
   app::Context,
   color::{TEXT_INVERTED_HARD, TEXT_NORMAL},
   device::CURRENT_DEVICE,
   document::pdf::PdfOpener,
-  font::{font_from_style, Fonts, DISPLAY_STYLE},
-  framebuffer::Framebuffer,
-  geom::Rectangle,
+  font::{font_from_style, Ellipsis, Fonts, DISPLAY_STYLE, MD_AUTHOR, MD_TITLE, NORMAL_STYLE},
+  framebuffer::{dither, DitherMode, Framebuffer},
+  geom::{BorderSpec, CornerSpec, Rectangle},
+  library::Library,
+  metadata::ReaderInfo,
+  settings::{LibraryMode, QuoteSource},
+  unit::scale_by_dpi,
 };
-use std::path::PathBuf;
+use rand_core::RngCore;
+use std::{fs, path::PathBuf};
+
+const QUOTES_PATH: &str = "quotes.txt";
+// The key of the image used for the kinds that don't have their own.
+pub const DEFAULT_IMAGE_KEY: &str = "default";
+// The key of the generic image shown by the private sleep screens.
+const PRIVATE_IMAGE_KEY: &str = "private";
+const MAX_QUOTE_LINES: usize = 12;
 
 pub struct Intermission {
   id: Id,
   rect: Rectangle,
   children: Vec<Box<dyn View>>,
   message: Message,
+  quote: Option<Quote>,
+  dithering: DitherMode,
   halt: bool,
 }
 
@@ -23,6 +47,11 @@ pub enum Message {
   Image(PathBuf),
 }
 
+pub struct Quote {
+  pub text: String,
+  pub source: String,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum IntermKind {
   Suspend,
@@ -56,21 +85,209 @@ impl IntermKind {
   }
 }
 
+// The quotes are separated by blank lines. The last line of a quote is its source if it starts
+// with an em dash.
+fn load_quotes() -> Vec<Quote> {
+  let text = match fs::read_to_string(QUOTES_PATH) {
+    Ok(text) => text,
+    Err(_) => return Vec::new(),
+  };
+
+  text
+    .split("\n\n")
+    .filter_map(|block| {
+      let mut lines: Vec<&str> = block
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+      let source = match lines.last() {
+        Some(line) if line.starts_with('—') => {
+          let source = line.trim_start_matches('—').trim().to_string();
+          lines.pop();
+          source
+        },
+        _ => String::new(),
+      };
+      if lines.is_empty() {
+        None
+      } else {
+        Some(Quote {
+          text: lines.join(" "),
+          source,
+        })
+      }
+    })
+    .collect()
+}
+
+fn highlights(library: &Library) -> Vec<Quote> {
+  let readings: Vec<(String, &ReaderInfo)> = if library.mode == LibraryMode::Database {
+    library
+      .db
+      .values()
+      .filter_map(|info| info.reader.as_ref().map(|r| (info.title(), r)))
+      .collect()
+  } else {
+    // The reading states aren't tied to titles in this mode.
+    library
+      .reading_states
+      .values()
+      .map(|r| (String::new(), r))
+      .collect()
+  };
+
+  readings
+    .into_iter()
+    .flat_map(|(title, r)| {
+      r.annotations
+        .iter()
+        .filter(|annot| !annot.text.is_empty())
+        .map(move |annot| Quote {
+          text: annot.text.clone(),
+          source: title.clone(),
+        })
+    })
+    .collect()
+}
+
+fn random_quote(context: &mut Context) -> Option<Quote> {
+  let mut quotes = match context.settings.intermission_quotes {
+    QuoteSource::None => return None,
+    QuoteSource::File => load_quotes(),
+    QuoteSource::Highlights => highlights(&context.library),
+    QuoteSource::All => {
+      let mut quotes = load_quotes();
+      quotes.append(&mut highlights(&context.library));
+      quotes
+    },
+  };
+
+  if quotes.is_empty() {
+    return None;
+  }
+
+  let index = context.rng.next_u64() as usize % quotes.len();
+  Some(quotes.swap_remove(index))
+}
+
+// Looks up the image of each key in turn within the images of the current library, and then
+// within the global ones.
+fn intermission_image(keys: &[&str], context: &Context) -> Option<PathBuf> {
+  let library = context
+    .settings
+    .libraries
+    .get(context.settings.selected_library);
+  library
+    .map(|lib| &lib.intermission_images)
+    .into_iter()
+    .chain(Some(&context.settings.intermission_images))
+    .find_map(|images| keys.iter().find_map(|key| images.get(*key)))
+    .map(|path| context.library.home.join(path))
+}
+
 impl Intermission {
-  pub fn new(rect: Rectangle, kind: IntermKind, context: &Context) -> Intermission {
-    let message = if let Some(path) = context.settings.intermission_images.get(kind.key()) {
-      Message::Image(context.library.home.join(path))
+  pub fn new(rect: Rectangle, kind: IntermKind, context: &mut Context) -> Intermission {
+    // The images might be covers, and the quotes might come from the highlights.
+    let private = context.settings.private_sleep_screen && kind != IntermKind::Share;
+    let keys = if private {
+      vec![PRIVATE_IMAGE_KEY]
     } else {
-      Message::Text(kind.text().to_string())
+      vec![kind.key(), DEFAULT_IMAGE_KEY]
     };
+    let message = if let Some(path) = intermission_image(&keys, context) {
+      Message::Image(path)
+    } else {
+      Message::Text(tr!(kind.text()))
+    };
+    let quote = if private { None } else { random_quote(context) };
     Intermission {
       id: ID_FEEDER.next(),
       rect,
       children: Vec::new(),
       message,
+      quote,
+      dithering: context.settings.dithering.covers,
       halt: kind == IntermKind::PowerOff,
     }
   }
+
+  // Renders the quote in the middle of the screen, or at the bottom, inside a frame, when
+  // *framed* is set.
+  fn render_quote(
+    &self,
+    quote: &Quote,
+    framed: bool,
+    fb: &mut dyn Framebuffer,
+    fonts: &mut Fonts,
+    scheme: [u8; 3],
+  ) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let width = self.rect.width() as i32;
+
+    let (mut lines, line_height, padding, max_width) = {
+      let font = font_from_style(fonts, &MD_TITLE, dpi);
+      let padding = font.em() as i32;
+      let max_width = width - 6 * padding;
+      let lines = font.plan_lines(&quote.text, max_width, MAX_QUOTE_LINES, Ellipsis::End);
+      (lines, font.line_height(), padding, max_width)
+    };
+
+    let (source, source_height) = if quote.source.is_empty() {
+      (None, 0)
+    } else {
+      let font = font_from_style(fonts, &MD_AUTHOR, dpi);
+      let mut plan = font.plan(&format!("— {}", quote.source), None, None);
+      font.crop_right(&mut plan, max_width);
+      (Some(plan), font.line_height() + padding)
+    };
+
+    let text_height = lines.len() as i32 * line_height + source_height;
+    let dy = if framed {
+      let frame_height = text_height + 2 * padding;
+      let frame = rect![
+        self.rect.min.x + padding,
+        self.rect.max.y - 2 * padding - frame_height,
+        self.rect.max.x - padding,
+        self.rect.max.y - 2 * padding
+      ];
+      let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+      let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+      fb.draw_rounded_rectangle_with_border(
+        &frame,
+        &CornerSpec::Uniform(border_radius),
+        &BorderSpec {
+          thickness: border_thickness,
+          color: scheme[1],
+        },
+        &scheme[0],
+      );
+      frame.min.y + padding
+    } else {
+      self.rect.min.y + (self.rect.height() as i32 - text_height) / 2
+    };
+
+    {
+      let font = font_from_style(fonts, &MD_TITLE, dpi);
+      for (i, plan) in lines.drain(..).enumerate() {
+        let dx = (width - plan.width) / 2;
+        let pt = pt!(
+          self.rect.min.x + dx,
+          dy + (i as i32 + 1) * line_height - font.descender().abs()
+        );
+        font.render(fb, scheme[1], &plan, pt);
+      }
+    }
+
+    if let Some(plan) = source {
+      let font = font_from_style(fonts, &MD_AUTHOR, dpi);
+      let pt = pt!(
+        self.rect.max.x - 3 * padding - plan.width,
+        dy + text_height - font.descender().abs()
+      );
+      font.render(fb, scheme[1], &plan, pt);
+    }
+  }
 }
 
 impl View for Intermission {
@@ -95,6 +312,23 @@ impl View for Intermission {
     fb.draw_rectangle(&self.rect, scheme[0]);
 
     match self.message {
+      Message::Text(ref text) if self.quote.is_some() => {
+        if let Some(quote) = self.quote.as_ref() {
+          self.render_quote(quote, false, fb, fonts, scheme);
+        }
+
+        let dpi = CURRENT_DEVICE.dpi;
+        let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+        let plan = font.plan(text, None, None);
+        let dx = (self.rect.width() as i32 - plan.width) / 2;
+        let dy = 2 * font.em() as i32;
+        font.render(
+          fb,
+          scheme[1],
+          &plan,
+          pt!(self.rect.min.x + dx, self.rect.max.y - dy),
+        );
+      },
       Message::Text(ref text) => {
         let dpi = CURRENT_DEVICE.dpi;
 
@@ -137,7 +371,8 @@ impl View for Intermission {
             let w_ratio = self.rect.width() as f32 / width;
             let h_ratio = self.rect.height() as f32 / height;
             let scale = w_ratio.min(h_ratio);
-            if let Some(pixmap) = page.pixmap(scale) {
+            if let Some(mut pixmap) = page.pixmap(scale) {
+              dither(&mut pixmap, self.dithering);
               let dx = (self.rect.width() as i32 - pixmap.width as i32) / 2;
               let dy = (self.rect.height() as i32 - pixmap.height as i32) / 2;
               let pt = self.rect.min + pt!(dx, dy);
@@ -145,10 +380,21 @@ impl View for Intermission {
             }
           }
         }
+
+        if let Some(quote) = self.quote.as_ref() {
+          self.render_quote(quote, true, fb, fonts, scheme);
+        }
       },
     }
   }
 
+  fn inversion_exemptions(&self) -> Vec<Rectangle> {
+    match self.message {
+      Message::Image(..) => vec![self.rect],
+      Message::Text(..) => Vec::new(),
+    }
+  }
+
   fn might_rotate(&self) -> bool {
     false
   }
@@ -17,6 +17,7 @@ use crate::{
   app::Context,
   color::{KEYBOARD_BG, TEXT_INVERTED_HARD, TEXT_NORMAL},
   device::CURRENT_DEVICE,
+  feedback::FeedbackKind,
   font::{font_from_style, Fonts, KBD_CHAR, KBD_LABEL},
   framebuffer::{Framebuffer, UpdateMode},
   geom::{CornerSpec, LinearDir, Rectangle},
@@ -227,13 +228,14 @@ impl View for Key {
     hub: &Hub,
     bus: &mut Bus,
     rq: &mut RenderQueue,
-    _context: &mut Context,
+    context: &mut Context,
   ) -> bool {
     match *evt {
       Event::Device(DeviceEvent::Finger {
         status, position, ..
       }) => match status {
         FingerStatus::Down if self.rect.includes(position) => {
+          context.emit_feedback(FeedbackKind::KeyPress);
           self.active = true;
           rq.add(RenderData::no_wait(self.id, self.rect, UpdateMode::Fast));
           true
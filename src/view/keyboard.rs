@@ -1,5 +1,7 @@
 use super::{
   key::{Key, KeyKind},
+  label::Label,
+  Align,
   Bus,
   EntryId,
   Event,
@@ -19,17 +21,20 @@ use crate::{
   device::CURRENT_DEVICE,
   font::Fonts,
   framebuffer::{Framebuffer, UpdateMode},
-  geom::Rectangle,
+  geom::{LinearDir, Rectangle},
   gesture::GestureEvent,
   input::DeviceEvent,
   unit::scale_by_dpi,
 };
+use anyhow::Error;
 use fxhash::FxHashMap;
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use std::{fs, path::Path, sync::Arc};
 
 pub type Keys = Vec<Vec<KeyKind>>;
 const PADDING_RATIO: f32 = 0.06;
+const SUGGESTIONS_COUNT: usize = 3;
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -38,6 +43,106 @@ pub struct Layout {
   pub outputs: [Vec<Vec<char>>; 4],
   pub keys: Vec<Vec<KeyKind>>,
   pub widths: Vec<Vec<f32>>,
+  #[serde(skip)]
+  pub words: Arc<WordModel>,
+}
+
+// The word frequencies of a language, used to suggest completions.
+#[derive(Debug, Default)]
+pub struct WordModel {
+  // Sorted by word.
+  words: Vec<(String, u64)>,
+}
+
+impl WordModel {
+  // Each line holds a word, optionally followed by its count. Without counts, the words
+  // are expected to be listed from the most to the least frequent.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<WordModel, Error> {
+    let text = fs::read_to_string(path)?;
+    let lines = text
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .collect::<Vec<&str>>();
+    let lines_count = lines.len() as u64;
+    let mut words = lines
+      .iter()
+      .enumerate()
+      .filter_map(|(index, line)| {
+        let mut parts = line.split_whitespace();
+        let word = parts.next()?.to_lowercase();
+        let count = parts
+          .next()
+          .and_then(|c| c.parse().ok())
+          .unwrap_or(lines_count - index as u64);
+        Some((word, count))
+      })
+      .collect::<Vec<(String, u64)>>();
+    words.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    words.dedup_by(|a, b| a.0 == b.0);
+    Ok(WordModel { words })
+  }
+
+  // Returns the most likely completions of *prefix*: the words of *history* come first,
+  // by number of occurrences, followed by the most frequent words of the language.
+  pub fn suggest<'a, I>(&self, prefix: &str, history: I, count: usize) -> Vec<String>
+  where
+    I: Iterator<Item = &'a str>,
+  {
+    let lower = prefix.to_lowercase();
+    if lower.is_empty() {
+      return Vec::new();
+    }
+
+    let mut candidates: FxHashMap<String, (usize, u64)> = FxHashMap::default();
+
+    for word in history {
+      let word = word
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+      if word.len() > lower.len() && word.starts_with(&lower) {
+        candidates.entry(word).or_insert((0, 0)).0 += 1;
+      }
+    }
+
+    let start = self
+      .words
+      .partition_point(|(w, _)| w.as_str() < lower.as_str());
+    for (word, freq) in self.words[start..]
+      .iter()
+      .take_while(|(w, _)| w.starts_with(&lower))
+    {
+      if word.len() > lower.len() {
+        candidates.entry(word.clone()).or_insert((0, 0)).1 = *freq;
+      }
+    }
+
+    let mut candidates = candidates
+      .into_iter()
+      .collect::<Vec<(String, (usize, u64))>>();
+    candidates.sort_by(|a, b| (b.1).cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let capitalize = prefix.chars().next().map_or(false, char::is_uppercase);
+
+    candidates
+      .into_iter()
+      .take(count)
+      .map(|(word, _)| {
+        if capitalize {
+          let mut chars = word.chars();
+          chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+        } else {
+          word
+        }
+      })
+      .collect()
+  }
+}
+
+fn is_word_char(ch: char) -> bool {
+  ch.is_alphanumeric() || ch == '\'' || ch == '-'
 }
 
 #[derive(Default, Debug)]
@@ -54,6 +159,10 @@ pub struct Keyboard {
   layout: Layout,
   state: State,
   combine_buffer: String,
+  // The word being typed, and its suggested completions.
+  word: String,
+  suggestions: Vec<String>,
+  keys_count: usize,
 }
 
 impl Keyboard {
@@ -88,7 +197,8 @@ impl Keyboard {
       .expect("Missing row widths");
 
     let kh_1 = (rect.width() as f32) / max_width;
-    let rows_count = layout.keys.len();
+    let row_offset = context.settings.keyboard_suggestions as usize;
+    let rows_count = layout.keys.len() + row_offset;
     let kh_2 =
       (rect.height() as f32) / (rows_count as f32 + PADDING_RATIO * (rows_count + 1) as f32);
     let key_height = kh_1.min(kh_2);
@@ -103,7 +213,7 @@ impl Keyboard {
     let start_y = rect.min.y as f32 + padding + (rect.height() as f32 - rows_height) / 2.0;
 
     for (i, row) in layout.keys.iter().enumerate() {
-      let y = start_y + i as f32 * (padding + key_height);
+      let y = start_y + (i + row_offset) as f32 * (padding + key_height);
       let row_width = (layout.widths[i].len() + 1) as f32 * padding
         + layout.widths[i].iter().sum::<f32>() * key_height;
       let start_x = rect.min.x as f32 + padding + (rect.width() as f32 - row_width) / 2.0;
@@ -135,6 +245,15 @@ impl Keyboard {
       }
     }
 
+    let keys_count = children.len();
+
+    if row_offset > 0 {
+      for rect in suggestion_rects(rect, start_y, key_height, padding) {
+        let label = Label::new(rect, String::new(), Align::Center);
+        children.push(Box::new(label) as Box<dyn View>);
+      }
+    }
+
     Keyboard {
       id,
       rect: *rect,
@@ -142,7 +261,77 @@ impl Keyboard {
       layout,
       state,
       combine_buffer: String::new(),
+      word: String::new(),
+      suggestions: Vec::new(),
+      keys_count,
+    }
+  }
+
+  fn has_suggestions(&self) -> bool {
+    self.children.len() > self.keys_count
+  }
+
+  // Updates the current word after a key press.
+  fn track(&mut self, kind: KeyKind, rq: &mut RenderQueue, context: &Context) {
+    if !self.has_suggestions() {
+      return;
+    }
+    match kind {
+      KeyKind::Output(ch) if is_word_char(ch) => self.word.push(ch),
+      KeyKind::Delete(LinearDir::Backward) => {
+        self.word.pop();
+      },
+      KeyKind::Delete(LinearDir::Forward) => (),
+      _ => self.word.clear(),
+    }
+    self.update_suggestions(rq, context);
+  }
+
+  fn update_suggestions(&mut self, rq: &mut RenderQueue, context: &Context) {
+    let history = context
+      .input_history
+      .values()
+      .flat_map(|entries| entries.iter())
+      .flat_map(|entry| entry.split_whitespace());
+    self.suggestions = self
+      .layout
+      .words
+      .suggest(&self.word, history, SUGGESTIONS_COUNT);
+    for i in 0..SUGGESTIONS_COUNT {
+      let text = self.suggestions.get(i).map(String::as_str).unwrap_or("");
+      let event = self.suggestions.get(i).map(|_| Event::AcceptSuggestion(i));
+      if let Some(label) = self.children[self.keys_count + i].downcast_mut::<Label>() {
+        label.update(text, rq);
+        label.set_event(event);
+      }
+    }
+  }
+
+  // Replaces the current word with the suggestion.
+  fn accept_suggestion(
+    &mut self,
+    index: usize,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &Context,
+  ) {
+    let suggestion = match self.suggestions.get(index) {
+      Some(suggestion) => suggestion.clone(),
+      None => return,
+    };
+    for _ in self.word.chars() {
+      hub
+        .send(Event::Keyboard(KeyboardEvent::Delete {
+          target: TextKind::Char,
+          dir: LinearDir::Backward,
+        }))
+        .ok();
+    }
+    for ch in suggestion.chars().chain(Some(' ')) {
+      hub.send(Event::Keyboard(KeyboardEvent::Append(ch))).ok();
     }
+    self.word.clear();
+    self.update_suggestions(rq, context);
   }
 
   fn update(&mut self, rq: &mut RenderQueue) {
@@ -240,11 +429,13 @@ impl View for Keyboard {
               if self.combine_buffer.len() > 1 {
                 if let Some(&ch) = DEFAULT_COMBINATIONS.get(&self.combine_buffer[..]) {
                   hub.send(Event::Keyboard(KeyboardEvent::Append(ch))).ok();
+                  self.track(KeyKind::Output(ch), rq, context);
                 }
                 self.release_combine(rq);
               }
             } else {
               hub.send(Event::Keyboard(KeyboardEvent::Append(ch))).ok();
+              self.track(k, rq, context);
             }
             if ch != ' ' {
               self.release_modifiers(rq);
@@ -263,6 +454,7 @@ impl View for Keyboard {
             }
           },
           KeyKind::Delete(dir) => {
+            self.track(k, rq, context);
             hub
               .send(Event::Keyboard(KeyboardEvent::Delete {
                 target: TextKind::Char,
@@ -271,6 +463,7 @@ impl View for Keyboard {
               .ok();
           },
           KeyKind::Move(dir) => {
+            self.track(k, rq, context);
             hub
               .send(Event::Keyboard(KeyboardEvent::Move {
                 target: TextKind::Char,
@@ -280,12 +473,17 @@ impl View for Keyboard {
           },
           KeyKind::Combine => self.state.combine = !self.state.combine,
           KeyKind::Return => {
+            self.track(k, rq, context);
             self.release_combine(rq);
             hub.send(Event::Keyboard(KeyboardEvent::Submit)).ok();
           },
         };
         true
       },
+      Event::AcceptSuggestion(index) => {
+        self.accept_suggestion(index, hub, rq, context);
+        true
+      },
       Event::Select(EntryId::SetKeyboardLayout(ref name)) => {
         if *name != context.settings.keyboard_layout {
           context.settings.keyboard_layout = name.to_string();
@@ -313,7 +511,8 @@ impl View for Keyboard {
       Event::Key(..)
       | Event::Gesture(..)
       | Event::Device(DeviceEvent::Finger { .. })
-      | Event::Select(..) => false,
+      | Event::Select(..)
+      | Event::AcceptSuggestion(..) => false,
       _ => true,
     }
   }
@@ -351,7 +550,8 @@ impl View for Keyboard {
       .expect("Missing row widths");
 
     let kh_1 = (rect.width() as f32) / max_width;
-    let rows_count = self.layout.keys.len();
+    let row_offset = self.has_suggestions() as usize;
+    let rows_count = self.layout.keys.len() + row_offset;
     let kh_2 =
       (rect.height() as f32) / (rows_count as f32 + PADDING_RATIO * (rows_count + 1) as f32);
     let key_height = kh_1.min(kh_2);
@@ -366,7 +566,7 @@ impl View for Keyboard {
     let mut index = 0;
 
     for (i, row) in self.layout.keys.iter().enumerate() {
-      let y = start_y + i as f32 * (padding + key_height);
+      let y = start_y + (i + row_offset) as f32 * (padding + key_height);
       let row_width = (self.layout.widths[i].len() + 1) as f32 * padding
         + self.layout.widths[i].iter().sum::<f32>() * key_height;
       let start_x = rect.min.x as f32 + padding + (rect.width() as f32 - row_width) / 2.0;
@@ -387,6 +587,13 @@ impl View for Keyboard {
       }
     }
 
+    if row_offset > 0 {
+      for label_rect in suggestion_rects(&rect, start_y, key_height, padding) {
+        self.children[index].resize(label_rect, hub, rq, context);
+        index += 1;
+      }
+    }
+
     self.rect = rect;
     context.kb_rect = rect;
   }
@@ -416,6 +623,27 @@ impl View for Keyboard {
   }
 }
 
+// The rectangles of the suggestions, in the first row of the keyboard.
+fn suggestion_rects(
+  rect: &Rectangle,
+  start_y: f32,
+  key_height: f32,
+  padding: f32,
+) -> Vec<Rectangle> {
+  let width = (rect.width() as f32 - padding) / SUGGESTIONS_COUNT as f32;
+  (0..SUGGESTIONS_COUNT)
+    .map(|i| {
+      let x = rect.min.x as f32 + padding + i as f32 * width;
+      rect![
+        x.round() as i32,
+        start_y.round() as i32,
+        (x + width - padding).round() as i32,
+        (start_y + key_height).round() as i32
+      ]
+    })
+    .collect()
+}
+
 lazy_static! {
     // Most of the combination sequences come from X.org.
     // The chosen characters come from the layout described by
@@ -37,6 +37,10 @@ impl Label {
     self
   }
 
+  pub fn set_event(&mut self, event: Option<Event>) {
+    self.event = event;
+  }
+
   pub fn hold_event(mut self, event: Option<Event>) -> Label {
     self.hold_event = event;
     self
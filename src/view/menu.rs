@@ -128,7 +128,7 @@ impl Menu {
       }
       let index = kind_counts[0] + kind_counts[1] - 1;
       let more = entries.drain(index..).collect::<Vec<EntryKind>>();
-      entries.push(EntryKind::SubMenu("More".to_string(), more));
+      entries.push(EntryKind::SubMenu(tr!("More"), more));
     }
 
     let mut y_pos = y_start + dir * (border_space - border_thickness);
@@ -147,7 +147,13 @@ impl Menu {
     let (mut x_min, mut x_max) = if kind == MenuKind::SubMenu {
       let west_space = target.min.x;
       let east_space = width as i32 - target.max.x;
-      if west_space > east_space {
+      // Right-to-left layouts open the sub-menus westward whenever they fit.
+      let westward = if context.rtl {
+        west_space >= entry_width || west_space > east_space
+      } else {
+        west_space > east_space
+      };
+      if westward {
         (target.min.x - entry_width, target.min.x)
       } else {
         (target.max.x, target.max.x + entry_width)
@@ -231,7 +237,7 @@ impl Menu {
           None
         };
 
-        let menu_entry = MenuEntry::new(rect, entries[i].clone(), anchor, corner_spec);
+        let menu_entry = MenuEntry::new(rect, entries[i].clone(), anchor, corner_spec, context.rtl);
 
         children.push(Box::new(menu_entry) as Box<dyn View>);
 
@@ -30,6 +30,7 @@ pub struct MenuEntry {
   corner_spec: Option<CornerSpec>,
   anchor: Rectangle,
   active: bool,
+  rtl: bool,
 }
 
 impl MenuEntry {
@@ -38,6 +39,7 @@ impl MenuEntry {
     kind: EntryKind,
     anchor: Rectangle,
     corner_spec: Option<CornerSpec>,
+    rtl: bool,
   ) -> MenuEntry {
     MenuEntry {
       id: ID_FEEDER.next(),
@@ -47,6 +49,7 @@ impl MenuEntry {
       corner_spec,
       anchor,
       active: false,
+      rtl,
     }
   }
 
@@ -118,7 +121,7 @@ impl View for MenuEntry {
           _ => (),
         };
         true
-      }
+      },
       Event::PropagateSelect(ref other_id) => match self.kind {
         EntryKind::RadioButton(_, ref id, ref mut value) if *value => {
           if mem::discriminant(id) == mem::discriminant(other_id) && id != other_id {
@@ -156,14 +159,26 @@ impl View for MenuEntry {
     let max_width = self.rect.width() as i32 - padding;
     let plan = font.plan(self.kind.text(), Some(max_width), None);
     let dy = (self.rect.height() as i32 - x_height) / 2;
-    let pt = pt!(self.rect.min.x + padding / 2, self.rect.max.y - dy);
+    let x = if self.rtl {
+      self.rect.max.x - padding / 2 - plan.width
+    } else {
+      self.rect.min.x + padding / 2
+    };
+    let pt = pt!(x, self.rect.max.y - dy);
 
     font.render(fb, scheme[1], &plan, pt);
 
+    // The marks and the sub-menu arrows switch sides in right-to-left layouts.
+    let (start, end) = if self.rtl {
+      (self.rect.width() as i32 - padding / 2, 0)
+    } else {
+      (0, self.rect.width() as i32 - padding / 2)
+    };
     let (icon_name, x_offset) = match self.kind {
-      EntryKind::CheckBox(_, _, value) if value => ("check_mark", 0),
-      EntryKind::RadioButton(_, _, value) if value => ("bullet", 0),
-      EntryKind::SubMenu(..) => ("angle-right-small", self.rect.width() as i32 - padding / 2),
+      EntryKind::CheckBox(_, _, value) if value => ("check_mark", start),
+      EntryKind::RadioButton(_, _, value) if value => ("bullet", start),
+      EntryKind::SubMenu(..) if self.rtl => ("angle-left-small", end),
+      EntryKind::SubMenu(..) => ("angle-right-small", end),
       _ => ("", 0),
     };
 
@@ -13,9 +13,12 @@ pub mod battery;
 pub mod button;
 pub mod calculator;
 pub mod clock;
+pub mod command_palette;
 pub mod common;
 pub mod dialog;
 pub mod dictionary;
+#[cfg(feature = "feeds")]
+pub mod feeds;
 pub mod filler;
 pub mod frontlight;
 pub mod home;
@@ -31,27 +34,71 @@ pub mod menu;
 pub mod menu_entry;
 pub mod named_input;
 pub mod notification;
+#[cfg(feature = "opds")]
+pub mod opds;
 pub mod page_label;
 pub mod preset;
 pub mod presets_list;
+pub mod progress;
 pub mod reader;
+pub mod report;
 pub mod rounded_button;
 pub mod search_bar;
+pub mod share_dialog;
 pub mod sketch;
 pub mod slider;
+pub mod tap_zones_editor;
 pub mod top_bar;
-
-use self::{calculator::LineOrigin, intermission::IntermKind, key::KeyKind};
+pub mod transition;
+pub mod undo;
+
+#[cfg(feature = "dictionary-manager")]
+use self::dictionary::manager::IndexEntry;
+use self::{
+  calculator::LineOrigin,
+  command_palette::PaletteCommand,
+  intermission::IntermKind,
+  key::KeyKind,
+  reader::SearchScope,
+  report::ErrorReport,
+  undo::{UndoAction, UndoDir},
+};
+#[cfg(feature = "opds")]
+use crate::opds::OpdsFeed;
 use crate::{
   app::Context,
-  document::{Location, TextLocation, TocEntry},
+  document::{export::TextFormat, Location, TextLocation, TocEntry},
+  feedback::FeedbackKind,
   font::Fonts,
-  framebuffer::{Framebuffer, UpdateMode},
-  geom::{Boundary, CycleDir, LinearDir, Rectangle},
+  framebuffer::{Framebuffer, Pixmap, ScanFilter, UpdateMode},
+  geom::{Axis, Boundary, CycleDir, Dir, LinearDir, Rectangle},
   gesture::GestureEvent,
   input::{DeviceEvent, FingerStatus},
-  metadata::{Info, Margin, PageScheme, SimpleStatus, SortMethod, TextAlign, ZoomMode},
-  settings::{ButtonScheme, FirstColumn, RotationLock, SecondColumn},
+  library::Repair,
+  metadata::{
+    AnnotationsFormat,
+    Info,
+    Margin,
+    PageScheme,
+    ScrollMode,
+    SimpleStatus,
+    SortMethod,
+    TextAlign,
+    TextAlignOverride,
+    Visibility,
+    ZoomMode,
+  },
+  network::NetworkTask,
+  settings::{
+    ButtonScheme,
+    CitationStyle,
+    FirstColumn,
+    NightFilterMode,
+    PowerProfile,
+    RotationLock,
+    SecondColumn,
+    TapAction,
+  },
 };
 use downcast_rs::{impl_downcast, Downcast};
 use fxhash::FxHashMap;
@@ -135,9 +182,19 @@ pub trait View: Downcast {
     false
   }
 
+  // The regions, such as pictures, that keep their polarity when the display is inverted.
+  fn inversion_exemptions(&self) -> Vec<Rectangle> {
+    Vec::new()
+  }
+
   fn view_id(&self) -> Option<ViewId> {
     None
   }
+
+  // The commands offered by the command palette, besides the ones of the main menu.
+  fn commands(&self, _context: &Context) -> Vec<PaletteCommand> {
+    Vec::new()
+  }
 }
 
 impl_downcast!(View);
@@ -191,23 +248,68 @@ pub fn handle_event(
   }
 }
 
-// We render from bottom to top. For a view to render it has to either appear in `ids` or intersect
-// one of the rectangles in `bgs`. When we're about to render a view, if `wait` is true, we'll wait
-// for all the updates in `updating` that intersect with the view.
+// The area covered by a view and its descendants, and whether one of them has dirty regions.
+#[derive(Debug, Copy, Clone)]
+pub struct Subtree {
+  // Children, e.g. menus, can be drawn outside of the rectangle of their parent.
+  extent: Rectangle,
+  dirty: bool,
+}
+
+impl Subtree {
+  fn is_clean(&self, regions: &[Rectangle]) -> bool {
+    !self.dirty && !regions.iter().any(|r| r.overlaps(&self.extent))
+  }
+}
+
+fn collect_subtrees(
+  view: &dyn View,
+  views: &FxHashMap<Id, Vec<Rectangle>>,
+  subtrees: &mut FxHashMap<Id, Subtree>,
+) -> Subtree {
+  let mut subtree = Subtree {
+    extent: *view.rect(),
+    dirty: views.contains_key(&view.id()),
+  };
+
+  for i in 0..view.len() {
+    let child = collect_subtrees(view.child(i), views, subtrees);
+    subtree.extent.absorb(&child.extent);
+    subtree.dirty |= child.dirty;
+  }
+
+  subtrees.insert(view.id(), subtree);
+  subtree
+}
+
+// We render from bottom to top. For a view to render it has to either have dirty regions in `views`
+// or intersect one of the rectangles in `rects` (the regions rendered so far) or `bgs`. The subtrees
+// that contain no dirty views and whose extents don't intersect these rectangles are skipped. When we're about to
+// render a view, if `wait` is true, we'll wait for all the updates in `updating` that intersect with
+// the view.
 pub fn render(
   view: &dyn View,
   wait: bool,
-  ids: &FxHashMap<Id, Vec<Rectangle>>,
+  views: &FxHashMap<Id, Vec<Rectangle>>,
+  subtrees: &FxHashMap<Id, Subtree>,
   rects: &mut Vec<Rectangle>,
   bgs: &mut Vec<Rectangle>,
   fb: &mut dyn Framebuffer,
   fonts: &mut Fonts,
   updating: &mut FxHashMap<u32, Rectangle>,
 ) {
+  if let Some(subtree) = subtrees.get(&view.id()) {
+    if subtree.is_clean(rects) && subtree.is_clean(bgs) {
+      return;
+    }
+  }
+
   let mut render_rects = Vec::new();
 
   if view.len() == 0 || view.is_background() {
-    for rect in ids
+    let mut regions = Vec::new();
+
+    for rect in views
       .get(&view.id())
       .cloned()
       .into_iter()
@@ -215,6 +317,10 @@ pub fn render(
       .chain(rects.iter().filter_map(|r| r.intersection(view.rect())))
       .chain(bgs.iter().filter_map(|r| r.intersection(view.rect())))
     {
+      add_region(&mut regions, rect);
+    }
+
+    for rect in regions {
       let render_rect = view.render_rect(&rect);
 
       if wait {
@@ -224,6 +330,16 @@ pub fn render(
       }
 
       view.render(fb, rect, fonts);
+
+      // The EPDC will invert these regions a second time.
+      if fb.inverted() {
+        for exemption in view.inversion_exemptions() {
+          if let Some(region) = exemption.intersection(&render_rect) {
+            fb.invert_region(&region);
+          }
+        }
+      }
+
       render_rects.push(render_rect);
 
       // Most views can't render a subrectangle of themselves.
@@ -232,7 +348,7 @@ pub fn render(
       }
     }
   } else {
-    bgs.extend(ids.get(&view.id()).cloned().into_iter().flatten());
+    bgs.extend(views.get(&view.id()).cloned().into_iter().flatten());
   }
 
   // Merge the contiguous zones to avoid having to schedule lots of small frambuffer updates.
@@ -266,10 +382,28 @@ pub fn render(
   }
 
   for i in 0..view.len() {
-    render(view.child(i), wait, ids, rects, bgs, fb, fonts, updating);
+    render(
+      view.child(i),
+      wait,
+      views,
+      subtrees,
+      rects,
+      bgs,
+      fb,
+      fonts,
+      updating,
+    );
   }
 }
 
+pub fn has_inversion_exemptions(view: &dyn View) -> bool {
+  !view.inversion_exemptions().is_empty()
+    || view
+      .children()
+      .iter()
+      .any(|child| has_inversion_exemptions(child.as_ref()))
+}
+
 #[inline]
 pub fn process_render_queue(
   view: &dyn View,
@@ -277,23 +411,18 @@ pub fn process_render_queue(
   context: &mut Context,
   updating: &mut FxHashMap<u32, Rectangle>,
 ) {
-  for ((mode, wait), pairs) in rq.drain() {
-    let mut ids = FxHashMap::default();
+  for ((mode, wait), damage) in rq.drain() {
+    let mut subtrees = FxHashMap::default();
     let mut rects = Vec::new();
-    let mut bgs = Vec::new();
+    let mut bgs = damage.exposed;
 
-    for (id, rect) in pairs.into_iter().rev() {
-      if let Some(id) = id {
-        ids.entry(id).or_insert_with(|| Vec::new()).push(rect);
-      } else {
-        bgs.push(rect);
-      }
-    }
+    collect_subtrees(view, &damage.views, &mut subtrees);
 
     render(
       view,
       wait,
-      &ids,
+      &damage.views,
+      &subtrees,
       &mut rects,
       &mut bgs,
       context.fb.as_mut(),
@@ -320,31 +449,44 @@ pub enum Event {
   Gesture(GestureEvent),
   Keyboard(KeyboardEvent),
   Key(KeyKind),
+  AcceptSuggestion(usize),
   AddDocument(Box<Info>),
   Open(Box<Info>),
   OpenToc(Vec<TocEntry>, usize),
+  // Shows the given HTML in an ephemeral reader.
+  OpenHtml(String),
   LoadPixmap(usize),
   Update(UpdateMode),
   Invalid(Box<Info>),
   Notify(String),
+  NotifyProgress(ViewId, String, f32),
+  Progress(ViewId, String, Option<f32>),
+  Error(Box<ErrorReport>),
   Page(CycleDir),
   ResultsPage(CycleDir),
   GoTo(usize),
   GoToLocation(Location),
+  // Shows a preview of the given page while scrubbing, or hides it.
+  PagePreview(Option<usize>),
+  // The thumbnail of the given page was rendered.
+  Thumbnail(usize, Box<Pixmap>),
   ResultsGoTo(usize),
   CropMargins(Box<Margin>),
   Chapter(CycleDir),
   Sort(SortMethod),
   SelectDirectory(PathBuf),
   ToggleSelectDirectory(PathBuf),
+  ToggleSelectCollection(String),
   NavigationBarResized(i32),
   Focus(Option<ViewId>),
   Select(EntryId),
   PropagateSelect(EntryId),
+  RunCommand(usize),
   EditLanguages,
   Define(String),
   Submit(ViewId, String),
   Slider(SliderId, f32, FingerStatus),
+  AlphabetRail(char, FingerStatus),
   ToggleNear(ViewId, Rectangle),
   ToggleInputHistoryMenu(ViewId, Rectangle),
   ToggleBookMenu(Rectangle, usize),
@@ -355,21 +497,53 @@ pub enum Event {
   Toggle(ViewId),
   Show(ViewId),
   Close(ViewId),
+  Expire(Id),
   CloseSub(ViewId),
   Search(String),
   SearchResult(usize, Vec<Boundary>),
   EndOfSearch,
+  #[cfg(feature = "feeds")]
+  FeedsFetched(usize),
+  #[cfg(feature = "dictionary-manager")]
+  DictionaryIndex(Vec<IndexEntry>),
+  // A page of an OPDS catalog, or nothing if it couldn't be fetched.
+  #[cfg(feature = "opds")]
+  OpdsFeed(Option<Box<OpdsFeed>>),
+  // The path of the book downloaded from an OPDS catalog, or nothing if the download failed.
+  OpdsDownloaded(Option<PathBuf>),
+  // The EPUB created by the converter from a document that can't be opened.
+  Converted(PathBuf),
+  // The speech synthesizer with the given process identifier is done.
+  Spoken(u32),
+  // Applies a repair to the integrity issue at the given index.
+  Repair(usize, Repair),
+  RepairAll,
   Finished,
   ClockTick,
   BatteryTick,
   ToggleFrontlight,
+  StepFrontlight(CycleDir),
+  ToggleAutoFrontlight,
   Load(PathBuf),
   LoadPreset(usize),
   Scroll(i32),
   Save,
   Guess,
   CheckBattery,
+  // Saves the reading state of the current document.
+  AutoSave,
+  // The file of the open document changed on disk.
+  DocumentChanged,
+  RunScheduledTasks,
+  Undo,
+  Redo,
+  // Sent after an action was pushed on the undo stack.
+  UndoRecorded,
+  Revert(Box<UndoAction>, UndoDir),
   SetWifi(bool),
+  // An operation needs the network, or is done with it.
+  AcquireNetwork(NetworkTask),
+  ReleaseNetwork(NetworkTask),
   MightSuspend,
   PrepareSuspend,
   Suspend,
@@ -385,9 +559,21 @@ pub enum Event {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum AppCmd {
-  Sketch,
+  Sketch {
+    file: Option<PathBuf>,
+  },
+  TapZones,
   Calculator,
-  Dictionary { query: String, language: String },
+  Dictionary {
+    query: String,
+    language: String,
+  },
+  #[cfg(feature = "feeds")]
+  Feeds,
+  #[cfg(feature = "dictionary-manager")]
+  Dictionaries,
+  #[cfg(feature = "opds")]
+  Opds,
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -397,14 +583,22 @@ pub enum ViewId {
   SortMenu,
   MainMenu,
   TitleMenu,
+  HiddenMenu,
+  RecentMenu,
+  CommandPalette,
   SelectionMenu,
   AnnotationMenu,
+  ReviewBar,
   BatteryMenu,
   ClockMenu,
   SearchTargetMenu,
   InputHistoryMenu,
   KeyboardLayoutMenu,
   Frontlight,
+  FrontlightIcon,
+  FrontlightMinus,
+  FrontlightPlus,
+  AutoFrontlight,
   Dictionary,
   FontSizeMenu,
   TextAlignMenu,
@@ -421,6 +615,8 @@ pub enum ViewId {
   MarginCropperMenu,
   SearchMenu,
   SketchMenu,
+  TapZonesMenu,
+  TapActionMenu,
   GoToPage,
   GoToPageInput,
   GoToResultsPage,
@@ -431,12 +627,21 @@ pub enum ViewId {
   EditNoteInput,
   EditLanguages,
   EditLanguagesInput,
+  Lend,
+  LendInput,
+  EditLoanNote,
+  EditLoanNoteInput,
+  NewCollection,
+  NewCollectionInput,
   HomeSearchInput,
   ReaderSearchInput,
   DictionarySearchInput,
   CalculatorInput,
   SearchBar,
   AddressBar,
+  Notebooks,
+  LibraryCheck,
+  Thumbnails,
   AddressBarInput,
   Keyboard,
   AboutDialog,
@@ -446,14 +651,34 @@ pub enum ViewId {
   TableOfContents,
   MessageNotif,
   BoundaryNotif,
+  FinishedNotif,
+  FrontMatterNotif,
   TakeScreenshotNotif,
   SaveDocumentNotif,
   SaveSketchNotif,
+  SaveTapZonesNotif,
   LoadSketchNotif,
   NoSearchResultsNotif,
   InvalidSearchQueryNotif,
   LowBatteryNotif,
+  LowStorageNotif,
+  LowMemoryNotif,
+  PresetNotif,
   NetUpNotif,
+  HookProgressNotif,
+  ImportProgress,
+  #[cfg(any(feature = "feeds", feature = "opds", feature = "dictionary-manager"))]
+  FetchProgress,
+  ExportProgress,
+  ConversionProgress,
+  HookProgress,
+  ErrorNotif,
+  TranslationNotif,
+  PhrasebookNotif,
+  ErrorDialog,
+  ShareBookDialog,
+  ShareNotif,
+  UndoNotif,
   SubMenu(u8),
 }
 
@@ -469,11 +694,11 @@ pub enum SliderId {
 impl SliderId {
   pub fn label(self) -> String {
     match self {
-      SliderId::LightIntensity => "Intensity".to_string(),
-      SliderId::LightWarmth => "Warmth".to_string(),
-      SliderId::FontSize => "Font Size".to_string(),
-      SliderId::ContrastExponent => "Contrast Exponent".to_string(),
-      SliderId::ContrastGray => "Contrast Gray".to_string(),
+      SliderId::LightIntensity => tr!("Intensity"),
+      SliderId::LightWarmth => tr!("Warmth"),
+      SliderId::FontSize => tr!("Font Size"),
+      SliderId::ContrastExponent => tr!("Contrast Exponent"),
+      SliderId::ContrastGray => tr!("Contrast Gray"),
     }
   }
 }
@@ -526,66 +751,148 @@ pub enum EntryKind {
 pub enum EntryId {
   About,
   SystemInfo,
+  BrowsePhrasebook,
+  ExportPhrasebook,
+  BrowseInsights,
+  PurgeInsights,
+  ToggleUsageInsights,
+  Revert(UndoDir),
   LoadLibrary(usize),
   Load(PathBuf),
   Flush,
   Save,
+  ExportNotebook,
+  ToggleNotebooks,
+  Undo,
+  Redo,
+  ExportText(TextFormat),
+  ExportAnnotationsBibtex,
+  ExportAnnotations(AnnotationsFormat),
+  ShareBook,
+  SetCitationStyle(CitationStyle),
+  CiteSelection,
+  CopySelection,
+  // Puts the given text in the clipboard.
+  Copy(String),
   Import,
   CleanUp,
+  CheckLibrary,
   Sort(SortMethod),
   ReverseOrder,
+  ToggleColumnHeaders,
   Remove(PathBuf),
   MoveTo(PathBuf, usize),
   AddDirectory(PathBuf),
   SelectDirectory(PathBuf),
   ToggleSelectDirectory(PathBuf),
   SetStatus(PathBuf, SimpleStatus),
+  SetVisibility(PathBuf, Visibility),
+  Lend(PathBuf),
+  EditLoanNote(PathBuf),
+  ReturnLoan(PathBuf),
+  ToggleCollection(PathBuf, String),
+  NewCollection(PathBuf),
+  ToggleCollectionsBar,
   ToggleIntermissionImage(IntermKind, PathBuf),
+  UseCoverAsSleepScreen(PathBuf),
   RemovePreset(usize),
   FirstColumn(FirstColumn),
   SecondColumn(SecondColumn),
   ApplyCroppings(usize, PageScheme),
   RemoveCroppings,
+  SetScrollMode(ScrollMode),
   SetZoomMode(ZoomMode),
   SetPageName,
+  ToggleThumbnails,
   RemovePageName,
   HighlightSelection,
   AnnotateSelection,
   DefineSelection,
+  TranslateSelection,
   SearchForSelection,
   AdjustSelection,
   RemoveAnnotation([TextLocation; 2]),
   EditAnnotationNote([TextLocation; 2]),
+  ReviewAnnotations(Option<[TextLocation; 2]>),
+  Skim,
+  ReadAloud,
+  ReviewAnnotation(CycleDir),
+  StopReview,
   RemoveAnnotationNote([TextLocation; 2]),
   GoTo(usize),
   GoToSelectedPageName,
   SearchDirection(LinearDir),
+  SearchScope(SearchScope),
   SetButtonScheme(ButtonScheme),
   SetFontFamily(String),
+  ReloadFonts,
+  ReloadDocument,
+  ApplyLayoutToDirectory,
   SetFontSize(i32),
   SetTextAlign(TextAlign),
+  SetTextAlignOverride(TextAlignOverride),
+  ToggleHangingPunctuation,
+  ToggleWidowOrphanControl,
+  SetMaxHyphenatedLines(usize),
+  SetMinHyphenatedLength(usize),
+  // In hundredths.
+  SetStretchTolerance(i32),
+  ToggleVerseLayout,
   SetMarginWidth(i32),
+  SetEdgeMargin(Dir, i32),
   SetLineHeight(i32),
   SetContrastExponent(i32),
   SetContrastGray(i32),
+  ToggleScanFilter(ScanFilter),
   SetRotationLock(Option<RotationLock>),
+  SetBookRotationLock(Option<RotationLock>),
+  UnlockRotation,
+  ToggleDiagnostics,
+  OpenRecent(PathBuf),
   SetSearchTarget(Option<String>),
+  SetDictionaryGroup(String),
   SetInputText(ViewId, String),
+  SaveSearch(ViewId),
+  PasteInput(ViewId),
+  RemoveSavedSearch(ViewId, String),
   SetKeyboardLayout(String),
+  SetLanguage(String),
   ToggleShowHidden,
+  ToggleShowArchived,
+  ToggleShowLoaned,
   ToggleFuzzy,
   ToggleInverted,
   ToggleMonochrome,
+  SetNightFilter(NightFilterMode),
+  SetPowerProfile(PowerProfile),
+  ToggleAutoPowerSaver,
+  ClearNotifications,
   ToggleWifi,
+  TogglePrivateSleepScreen,
+  ToggleFeedback(FeedbackKind),
   Rotate(i8),
   Launch(AppCmd),
   SetPenSize(i32),
   SetPenColor(u8),
   TogglePenDynamism,
+  ToggleTapZones,
+  ResetTapZones,
+  AddTapZone(Axis),
+  RemoveTapZone(Axis),
+  SetTapAction(usize, TapAction),
   ReloadDictionaries,
+  #[cfg(feature = "dictionary-manager")]
+  InstallDictionary(usize),
+  #[cfg(feature = "opds")]
+  DownloadBook(usize),
+  #[cfg(feature = "feeds")]
+  MarkAllAsRead,
+  #[cfg(feature = "feeds")]
+  CleanUpFeeds,
   New,
   Refresh,
   TakeScreenshot,
+  ToggleBenchmark,
   Reboot,
   RebootInNickel,
   Quit,
@@ -661,7 +968,25 @@ impl RenderData {
   }
 }
 
-type RQ = FxHashMap<(UpdateMode, bool), Vec<(Option<Id>, Rectangle)>>;
+// The regions that need to be rendered with a given update mode.
+#[derive(Debug, Default)]
+pub struct Damage {
+  // The dirty regions of each view.
+  pub views: FxHashMap<Id, Vec<Rectangle>>,
+  // The exposed regions: every view that intersects them needs to be rendered.
+  pub exposed: Vec<Rectangle>,
+}
+
+// Adds *rect* to *regions*, unless it's already covered, and removes the regions it covers.
+fn add_region(regions: &mut Vec<Rectangle>, rect: Rectangle) {
+  if regions.iter().any(|r| r.contains(&rect)) {
+    return;
+  }
+  regions.retain(|r| !rect.contains(r));
+  regions.push(rect);
+}
+
+type RQ = FxHashMap<(UpdateMode, bool), Damage>;
 pub struct RenderQueue(RQ);
 
 impl RenderQueue {
@@ -670,10 +995,12 @@ impl RenderQueue {
   }
 
   pub fn add(&mut self, data: RenderData) {
-    self
-      .entry((data.mode, data.wait))
-      .or_insert_with(|| Vec::new())
-      .push((data.id, data.rect));
+    let damage = self.entry((data.mode, data.wait)).or_default();
+    let regions = match data.id {
+      Some(id) => damage.views.entry(id).or_default(),
+      None => &mut damage.exposed,
+    };
+    add_region(regions, data.rect);
   }
 }
 
@@ -704,3 +1031,26 @@ impl IdFeeder {
     self.0.fetch_add(1, Ordering::Relaxed)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{collect_subtrees, filler::Filler, View};
+  use crate::color::WHITE;
+  use fxhash::FxHashMap;
+
+  #[test]
+  fn test_subtree_outside_parent() {
+    let mut parent = Filler::new(rect![0, 0, 100, 50], WHITE);
+    let child = Filler::new(rect![20, 50, 80, 200], WHITE);
+    let parent_id = parent.id();
+    parent.children_mut().push(Box::new(child) as Box<dyn View>);
+
+    let mut subtrees = FxHashMap::default();
+    collect_subtrees(&parent, &FxHashMap::default(), &mut subtrees);
+    let subtree = subtrees[&parent_id];
+
+    assert_eq!(subtree.extent, rect![0, 0, 100, 200]);
+    assert!(!subtree.is_clean(&[rect![30, 100, 60, 150]]));
+    assert!(subtree.is_clean(&[rect![120, 100, 160, 150]]));
+  }
+}
@@ -16,44 +16,180 @@ use crate::{
   app::Context,
   color::{BLACK, TEXT_NORMAL, WHITE},
   device::CURRENT_DEVICE,
-  font::{font_from_style, Fonts, NORMAL_STYLE},
+  font::{font_from_style, Ellipsis, Fonts, NORMAL_STYLE},
   framebuffer::{Framebuffer, UpdateMode},
   geom::{BorderSpec, CornerSpec, Rectangle},
   gesture::GestureEvent,
   input::DeviceEvent,
   unit::scale_by_dpi,
 };
-use std::{thread, time::Duration};
+use std::{
+  collections::VecDeque,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+  thread,
+  time::Duration,
+};
+
+// The number of notifications displayed at once, the others go to the notification center.
+const MAX_NOTIFICATIONS: usize = 6;
+const CENTER_CAPACITY: usize = 32;
+const MAX_LINES: usize = 2;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum NotificationPriority {
+  Low,
+  Normal,
+  High,
+}
+
+impl NotificationPriority {
+  fn close_delay(self) -> Duration {
+    match self {
+      NotificationPriority::Low => Duration::from_secs(3),
+      NotificationPriority::Normal => Duration::from_secs(4),
+      NotificationPriority::High => Duration::from_secs(8),
+    }
+  }
+}
+
+// The messages that couldn't be displayed, most recent first.
+#[derive(Debug, Default)]
+pub struct NotificationCenter {
+  entries: VecDeque<(String, usize)>,
+}
+
+impl NotificationCenter {
+  pub fn push(&mut self, text: String, count: usize) {
+    if let Some(index) = self.entries.iter().position(|(t, _)| *t == text) {
+      if let Some((_, n)) = self.entries.remove(index) {
+        self.entries.push_front((text, n + count));
+      }
+      return;
+    }
+    if self.entries.len() >= CENTER_CAPACITY {
+      self.entries.pop_back();
+    }
+    self.entries.push_front((text, count));
+  }
 
-const NOTIFICATION_CLOSE_DELAY: Duration = Duration::from_secs(4);
+  pub fn entries(&self) -> impl Iterator<Item = String> + '_ {
+    self
+      .entries
+      .iter()
+      .map(|(text, count)| with_count(text, *count))
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn clear(&mut self) {
+    self.entries.clear();
+  }
+}
+
+fn with_count(text: &str, count: usize) -> String {
+  if count > 1 {
+    format!("{} ×{}", text, count)
+  } else {
+    text.to_string()
+  }
+}
 
 pub struct Notification {
   id: Id,
   rect: Rectangle,
   children: Vec<Box<dyn View>>,
   text: String,
+  count: usize,
+  priority: NotificationPriority,
+  sticky: bool,
+  progress: Option<f32>,
   max_width: i32,
   index: u8,
   view_id: ViewId,
+  action: Option<Event>,
+  // Incremented each time the closing timer is restarted.
+  epoch: Arc<AtomicUsize>,
 }
 
 impl Notification {
-  pub fn new(
-    view_id: ViewId,
+  pub fn new(view_id: ViewId, text: String, context: &mut Context) -> Notification {
+    let mut notif = Notification {
+      id: ID_FEEDER.next(),
+      rect: Rectangle::default(),
+      children: vec![],
+      text,
+      count: 1,
+      priority: NotificationPriority::Normal,
+      sticky: false,
+      progress: None,
+      max_width: 0,
+      index: 0,
+      view_id,
+      action: None,
+      epoch: Arc::new(AtomicUsize::new(0)),
+    };
+    notif.layout(context);
+    notif
+  }
+
+  // The given event is sent when the notification is tapped.
+  pub fn with_action(mut self, action: Event) -> Notification {
+    self.action = Some(action);
+    self
+  }
+
+  pub fn with_priority(mut self, priority: NotificationPriority) -> Notification {
+    self.priority = priority;
+    self
+  }
+
+  // Sticky notifications stay on screen until they're closed by their view id.
+  pub fn sticky(mut self) -> Notification {
+    self.sticky = true;
+    self
+  }
+
+  pub fn update(
+    &mut self,
     text: String,
-    hub: &Hub,
+    progress: Option<f32>,
     rq: &mut RenderQueue,
     context: &mut Context,
-  ) -> Notification {
-    let id = ID_FEEDER.next();
-    let hub2 = hub.clone();
-    let index = context.notification_index;
+  ) {
+    let rect = self.rect;
+    self.text = text;
+    self.progress = progress.map(|p| p.max(0.0).min(1.0));
+    self.layout(context);
+    if rect != self.rect {
+      rq.add(RenderData::expose(rect, UpdateMode::Gui));
+    }
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
 
+  // Restarts the closing timer.
+  fn arm(&self, hub: &Hub) {
+    let epoch = self.epoch.fetch_add(1, Ordering::Relaxed) + 1;
+    if self.sticky {
+      return;
+    }
+    let id = self.id;
+    let delay = self.priority.close_delay();
+    let current = Arc::clone(&self.epoch);
+    let hub2 = hub.clone();
     thread::spawn(move || {
-      thread::sleep(NOTIFICATION_CLOSE_DELAY);
-      hub2.send(Event::Close(view_id)).ok();
+      thread::sleep(delay);
+      if current.load(Ordering::Relaxed) == epoch {
+        hub2.send(Event::Expire(id)).ok();
+      }
     });
+  }
 
+  fn layout(&mut self, context: &mut Context) {
     let dpi = CURRENT_DEVICE.dpi;
     let (width, _) = context.display.dims;
     let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
@@ -62,48 +198,144 @@ impl Notification {
     let x_height = font.x_heights.0 as i32;
     let padding = font.em() as i32;
 
+    let line_height = font.line_height();
+
     let max_message_width = width as i32 - 5 * padding;
-    let plan = font.plan(&text, Some(max_message_width), None);
+    let lines = font.plan_lines(
+      &with_count(&self.text, self.count),
+      max_message_width,
+      MAX_LINES,
+      Ellipsis::End,
+    );
+    let lines_count = lines.len().max(1) as i32;
+    let message_width = lines.iter().map(|plan| plan.width).max().unwrap_or(0);
 
-    let dialog_width = plan.width + 3 * padding;
-    let dialog_height = 7 * x_height;
+    let dialog_width = message_width + 3 * padding;
+    let dialog_height = 7 * x_height + (lines_count - 1) * line_height;
+    // The slots are tall enough for the longest messages.
+    let slot_height = 7 * x_height + (MAX_LINES as i32 - 1) * line_height;
 
-    let side = (index / 3) % 2;
+    let side = (self.index / 3) % 2;
     let dx = if side == 0 {
       width as i32 - dialog_width - padding
     } else {
       padding
     };
-    let dy = small_height + padding + (index % 3) as i32 * (dialog_height + padding);
+    let dy = small_height + padding + (self.index % 3) as i32 * (slot_height + padding);
 
-    let rect = rect![dx, dy, dx + dialog_width, dy + dialog_height];
+    self.rect = rect![dx, dy, dx + dialog_width, dy + dialog_height];
+    self.max_width = max_message_width;
+  }
+}
 
-    rq.add(RenderData::new(id, rect, UpdateMode::Gui));
-    context.notification_index = index.wrapping_add(1);
+// Shows the given notification within *children*.
+// Identical messages are merged into a single notification with a counter.
+// When the screen is full, the notification replaces a visible one of lower priority,
+// or goes to the notification center.
+pub fn push_notification(
+  children: &mut Vec<Box<dyn View>>,
+  mut notif: Notification,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  if let Some(other) = children
+    .iter_mut()
+    .filter_map(|c| c.downcast_mut::<Notification>())
+    .find(|n| n.text == notif.text && n.view_id == notif.view_id)
+  {
+    rq.add(RenderData::expose(other.rect, UpdateMode::Gui));
+    other.count += 1;
+    other.priority = other.priority.max(notif.priority);
+    other.layout(context);
+    rq.add(RenderData::new(other.id, other.rect, UpdateMode::Gui));
+    other.arm(hub);
+    return;
+  }
 
-    Notification {
-      id,
-      rect,
-      children: vec![],
-      text,
-      max_width: max_message_width,
-      index,
-      view_id,
+  let visible = children
+    .iter()
+    .enumerate()
+    .filter_map(|(i, c)| c.downcast_ref::<Notification>().map(|n| (i, n)))
+    .map(|(i, n)| (i, n.index, n.priority, n.sticky))
+    .collect::<Vec<_>>();
+
+  let mut slots = visible.iter().map(|v| v.1).collect::<Vec<u8>>();
+
+  if visible.len() >= MAX_NOTIFICATIONS {
+    let victim = visible
+      .iter()
+      .filter(|v| !v.3 && v.2 < notif.priority)
+      .min_by_key(|v| v.2);
+    if let Some(&(index, slot, ..)) = victim {
+      let child = children.remove(index);
+      if let Some(other) = child.downcast_ref::<Notification>() {
+        context.notifications.push(other.text.clone(), other.count);
+      }
+      rq.add(RenderData::expose(*child.rect(), UpdateMode::Gui));
+      slots.retain(|s| *s != slot);
+    } else {
+      context.notifications.push(notif.text, notif.count);
+      return;
     }
   }
+
+  notif.index = (0..).find(|i| !slots.contains(i)).unwrap_or(0);
+  notif.layout(context);
+  rq.add(RenderData::new(notif.id, notif.rect, UpdateMode::Gui));
+  notif.arm(hub);
+  children.push(Box::new(notif) as Box<dyn View>);
+}
+
+// Shows or updates the sticky notification *view_id*, which is closed once the progress reaches one.
+pub fn notify_progress(
+  children: &mut Vec<Box<dyn View>>,
+  view_id: ViewId,
+  text: String,
+  progress: f32,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  let index = children.iter().position(|c| {
+    c.downcast_ref::<Notification>()
+      .map_or(false, |n| n.view_id == view_id)
+  });
+
+  if progress >= 1.0 {
+    if let Some(index) = index {
+      let child = children.remove(index);
+      rq.add(RenderData::expose(*child.rect(), UpdateMode::Gui));
+    }
+    return;
+  }
+
+  if let Some(notif) = index.and_then(|index| children[index].downcast_mut::<Notification>()) {
+    notif.update(text, Some(progress), rq, context);
+  } else {
+    let mut notif = Notification::new(view_id, text, context).sticky();
+    notif.progress = Some(progress.max(0.0));
+    push_notification(children, notif, hub, rq, context);
+  }
 }
 
 impl View for Notification {
   fn handle_event(
     &mut self,
     evt: &Event,
-    _hub: &Hub,
+    hub: &Hub,
     _bus: &mut Bus,
     _rq: &mut RenderQueue,
     _context: &mut Context,
   ) -> bool {
     match *evt {
-      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => true,
+      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+        if let Some(action) = self.action.take() {
+          hub.send(action).ok();
+          hub.send(Event::Expire(self.id)).ok();
+        }
+        true
+      },
       Event::Gesture(GestureEvent::Swipe { start, .. }) if self.rect.includes(start) => true,
       Event::Device(DeviceEvent::Finger { position, .. }) if self.rect.includes(position) => true,
       _ => false,
@@ -127,36 +359,36 @@ impl View for Notification {
     );
 
     let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
-    let plan = font.plan(&self.text, Some(self.max_width), None);
+    let lines = font.plan_lines(
+      &with_count(&self.text, self.count),
+      self.max_width,
+      MAX_LINES,
+      Ellipsis::End,
+    );
     let x_height = font.x_heights.0 as i32;
+    let line_height = font.line_height();
 
-    let dx = (self.rect.width() as i32 - plan.width) as i32 / 2;
-    let dy = (self.rect.height() as i32 - x_height) / 2;
-    let pt = pt!(self.rect.min.x + dx, self.rect.max.y - dy);
+    let text_height = x_height + (lines.len().max(1) as i32 - 1) * line_height;
+    let dy = (self.rect.height() as i32 - text_height) / 2;
+    let mut pt = pt!(0, self.rect.min.y + dy + x_height);
 
-    font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    for plan in &lines {
+      pt.x = self.rect.min.x + (self.rect.width() as i32 - plan.width) / 2;
+      font.render(fb, TEXT_NORMAL[1], plan, pt);
+      pt.y += line_height;
+    }
+
+    if let (Some(progress), Some(plan)) = (self.progress, lines.last()) {
+      let thickness = border_thickness as i32;
+      let y_max = self.rect.max.y - dy / 2;
+      let x_min = self.rect.min.x + (self.rect.width() as i32 - plan.width) / 2;
+      let x_max = x_min + (progress * plan.width as f32) as i32;
+      fb.draw_rectangle(&rect![x_min, y_max - thickness, x_max, y_max], BLACK);
+    }
   }
 
   fn resize(&mut self, _rect: Rectangle, _hub: &Hub, _rq: &mut RenderQueue, context: &mut Context) {
-    let dpi = CURRENT_DEVICE.dpi;
-    let (width, height) = context.display.dims;
-    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
-    let side = (self.index / 3) % 2;
-    let padding = if side == 0 {
-      height as i32 - self.rect.max.x
-    } else {
-      self.rect.min.x
-    };
-    let dialog_width = self.rect.width() as i32;
-    let dialog_height = self.rect.height() as i32;
-    let dx = if side == 0 {
-      width as i32 - dialog_width - padding
-    } else {
-      padding
-    };
-    let dy = small_height + padding + (self.index % 3) as i32 * (dialog_height + padding);
-    let rect = rect![dx, dy, dx + dialog_width, dy + dialog_height];
-    self.rect = rect;
+    self.layout(context);
   }
 
   fn rect(&self) -> &Rectangle {
@@ -183,3 +415,20 @@ impl View for Notification {
     Some(self.view_id)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::NotificationCenter;
+
+  #[test]
+  fn test_center_coalescing() {
+    let mut center = NotificationCenter::default();
+    center.push("Imported.".to_string(), 1);
+    center.push("Low battery.".to_string(), 1);
+    center.push("Imported.".to_string(), 2);
+    assert_eq!(
+      center.entries().collect::<Vec<String>>(),
+      vec!["Imported. ×3".to_string(), "Low battery.".to_string()]
+    );
+  }
+}
@@ -0,0 +1,716 @@
+mod entries_list;
+
+use self::entries_list::EntriesList;
+use crate::{
+  app::Context,
+  color::BLACK,
+  device::CURRENT_DEVICE,
+  font::Fonts,
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{halves, CycleDir, Rectangle},
+  gesture::GestureEvent,
+  input::DeviceEvent,
+  network::NetworkTask,
+  opds::{file_name, parse_opds, OpdsEntry, OpdsFeed, OpdsLink},
+  settings::OpdsCatalog,
+  unit::scale_by_dpi,
+  view::{
+    common::{locate_by_id, toggle_battery_menu, toggle_clock_menu, toggle_main_menu},
+    dictionary::manager::download,
+    filler::Filler,
+    home::bottom_bar::BottomBar,
+    menu::{Menu, MenuKind},
+    report::{report_error, Severity},
+    top_bar::TopBar,
+    Bus,
+    EntryId,
+    EntryKind,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_MEDIUM,
+  },
+};
+use anyhow::Error;
+use reqwest::blocking::{Client, RequestBuilder};
+use std::{fs, path::Path, thread, time::Duration};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+const DOWNLOAD_FILENAME: &str = ".download";
+
+// How a fetched feed relates to the current one.
+#[derive(Debug, Copy, Clone)]
+enum Move {
+  Down,
+  Page(CycleDir),
+  Reload,
+}
+
+pub struct OpdsBrowser {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  feed: OpdsFeed,
+  // The feeds that lead to the current one, with their current pages. The first one lists
+  // the configured catalogs.
+  history: Vec<(OpdsFeed, usize)>,
+  // The catalog being browsed, within the settings.
+  catalog: Option<usize>,
+  current_page: usize,
+  pages_count: usize,
+  busy: bool,
+  moving: Move,
+  // Fetch this URL when the network is up.
+  pending_fetch: Option<(String, Move)>,
+}
+
+impl OpdsBrowser {
+  pub fn new(
+    rect: Rectangle,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> OpdsBrowser {
+    let id = ID_FEEDER.next();
+    let mut children = Vec::new();
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+    let feed = catalogs_feed(&context.settings.opds.catalogs);
+
+    let top_bar = TopBar::new(
+      rect![
+        rect.min.x,
+        rect.min.y,
+        rect.max.x,
+        rect.min.y + small_height - small_thickness
+      ],
+      Event::Back,
+      feed.title.clone(),
+      context,
+    );
+    children.push(Box::new(top_bar) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height - small_thickness,
+        rect.max.x,
+        rect.min.y + small_height + big_thickness
+      ],
+      BLACK,
+    );
+    children.push(Box::new(separator) as Box<dyn View>);
+
+    let list = EntriesList::new(rect![
+      rect.min.x,
+      rect.min.y + small_height + big_thickness,
+      rect.max.x,
+      rect.max.y - small_height - small_thickness
+    ]);
+    children.push(Box::new(list) as Box<dyn View>);
+
+    let separator = Filler::new(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height - small_thickness,
+        rect.max.x,
+        rect.max.y - small_height + big_thickness
+      ],
+      BLACK,
+    );
+    children.push(Box::new(separator) as Box<dyn View>);
+
+    let bottom_bar = BottomBar::new(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height + big_thickness,
+        rect.max.x,
+        rect.max.y
+      ],
+      0,
+      0,
+      &feed.title,
+      0,
+      false,
+    );
+    children.push(Box::new(bottom_bar) as Box<dyn View>);
+
+    rq.add(RenderData::new(id, rect, UpdateMode::Gui));
+
+    if feed.entries.is_empty() {
+      hub
+        .send(Event::Notify(tr!("No OPDS catalogs are configured.")))
+        .ok();
+    }
+
+    let mut browser = OpdsBrowser {
+      id,
+      rect,
+      children,
+      feed,
+      history: Vec::new(),
+      catalog: None,
+      current_page: 0,
+      pages_count: 0,
+      busy: false,
+      moving: Move::Reload,
+      pending_fetch: None,
+    };
+
+    browser.update_list(&mut RenderQueue::new());
+    browser.update_bottom_bar(&mut RenderQueue::new());
+
+    browser
+  }
+
+  fn update_list(&mut self, rq: &mut RenderQueue) {
+    let list = self.children[2].downcast_mut::<EntriesList>().unwrap();
+    let max_lines = list.max_lines.max(1);
+    let entries = &self.feed.entries;
+    self.pages_count = (entries.len() as f32 / max_lines as f32).ceil() as usize;
+    self.current_page = self.current_page.min(self.pages_count.saturating_sub(1));
+    let index_lower = self.current_page * max_lines;
+    let index_upper = (index_lower + max_lines).min(entries.len());
+    list.update(&entries[index_lower..index_upper], index_lower, rq);
+  }
+
+  // The neighboring pages of a paginated feed count as extra pages for the arrows.
+  fn update_bottom_bar(&mut self, rq: &mut RenderQueue) {
+    let before = self.feed.previous.is_some() as usize;
+    let after = self.feed.next.is_some() as usize;
+    let bottom_bar = self.children[4].downcast_mut::<BottomBar>().unwrap();
+    bottom_bar.update_library_label(&self.feed.title, self.feed.entries.len(), false, rq);
+    bottom_bar.update_page_label(self.current_page, self.pages_count, rq);
+    bottom_bar.update_icons(
+      self.current_page + before,
+      self.pages_count + before + after,
+      rq,
+    );
+  }
+
+  fn update_top_bar(&mut self, rq: &mut RenderQueue) {
+    if let Some(top_bar) = self.children[0].downcast_mut::<TopBar>() {
+      top_bar.update_title_label(&self.feed.title, rq);
+    }
+  }
+
+  fn go_to_neighbor(
+    &mut self,
+    dir: CycleDir,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    match dir {
+      CycleDir::Next if self.current_page < self.pages_count.saturating_sub(1) => {
+        self.current_page += 1;
+      },
+      CycleDir::Previous if self.current_page > 0 => {
+        self.current_page -= 1;
+      },
+      CycleDir::Next => {
+        if let Some(url) = self.feed.next.clone() {
+          self.fetch(url, Move::Page(dir), hub, context);
+        }
+        return;
+      },
+      CycleDir::Previous => {
+        if let Some(url) = self.feed.previous.clone() {
+          self.fetch(url, Move::Page(dir), hub, context);
+        }
+        return;
+      },
+    }
+    self.update_list(rq);
+    self.update_bottom_bar(rq);
+  }
+
+  fn credentials(&self, context: &Context) -> Option<(String, String)> {
+    self
+      .catalog
+      .and_then(|index| context.settings.opds.catalogs.get(index))
+      .filter(|catalog| !catalog.username.is_empty())
+      .map(|catalog| (catalog.username.clone(), catalog.password.clone()))
+  }
+
+  fn fetch(&mut self, url: String, moving: Move, hub: &Hub, context: &mut Context) {
+    if self.busy {
+      hub
+        .send(Event::Notify(tr!("Another operation is in progress.")))
+        .ok();
+      return;
+    }
+
+    if !context.online {
+      self.pending_fetch = Some((url, moving));
+      hub.send(Event::AcquireNetwork(NetworkTask::Opds)).ok();
+      return;
+    }
+
+    self.busy = true;
+    self.moving = moving;
+    self.pending_fetch = None;
+    let credentials = self.credentials(context);
+    let hub2 = hub.clone();
+    hub.send(Event::AcquireNetwork(NetworkTask::Opds)).ok();
+    thread::spawn(move || {
+      let feed = fetch_feed(&url, credentials)
+        .map_err(|e| report_error(&hub2, Severity::Error, &tr!("Can't fetch {}", url), &e))
+        .ok();
+      hub2.send(Event::OpdsFeed(feed.map(Box::new))).ok();
+      hub2.send(Event::ReleaseNetwork(NetworkTask::Opds)).ok();
+    });
+  }
+
+  fn open(
+    &mut self,
+    index: usize,
+    rect: Rectangle,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    match self.feed.entries.get(index).map(|entry| entry.link.clone()) {
+      Some(OpdsLink::Catalog(url)) => {
+        // The entries of the first feed are the configured catalogs.
+        if self.history.is_empty() {
+          self.catalog = Some(index);
+        }
+        self.fetch(url, Move::Down, hub, context);
+      },
+      Some(OpdsLink::Book(..)) => {
+        self.toggle_book_menu(index, rect, None, rq, context);
+      },
+      None => (),
+    }
+  }
+
+  fn set_feed(&mut self, feed: OpdsFeed, rq: &mut RenderQueue) {
+    let previous = std::mem::replace(&mut self.feed, feed);
+    match self.moving {
+      Move::Down => {
+        self.history.push((previous, self.current_page));
+        self.current_page = 0;
+      },
+      Move::Page(CycleDir::Next) => self.current_page = 0,
+      Move::Page(CycleDir::Previous) => self.current_page = usize::MAX,
+      Move::Reload => (),
+    }
+    self.update_top_bar(rq);
+    self.update_list(rq);
+    self.update_bottom_bar(rq);
+  }
+
+  fn go_back(&mut self, hub: &Hub, rq: &mut RenderQueue) {
+    if let Some((feed, page)) = self.history.pop() {
+      self.feed = feed;
+      self.current_page = page;
+      if self.history.is_empty() {
+        self.catalog = None;
+      }
+      self.update_top_bar(rq);
+      self.update_list(rq);
+      self.update_bottom_bar(rq);
+    } else {
+      hub.send(Event::Back).ok();
+    }
+  }
+
+  fn download(&mut self, index: usize, hub: &Hub, context: &mut Context) {
+    let entry = match self.feed.entries.get(index) {
+      Some(entry) => entry.clone(),
+      None => return,
+    };
+    let (url, kind) = match entry.link {
+      OpdsLink::Book(ref url, ref kind) => (url.clone(), kind.clone()),
+      OpdsLink::Catalog(..) => return,
+    };
+    if self.busy {
+      hub
+        .send(Event::Notify(tr!("Another operation is in progress.")))
+        .ok();
+      return;
+    }
+    if !context.online {
+      hub.send(Event::Notify(tr!("The network is down."))).ok();
+      return;
+    }
+
+    let dir = context.library.home.join(&context.settings.opds.directory);
+    let path = dir.join(file_name(&entry, &kind));
+    if path.exists() {
+      hub
+        .send(Event::Notify(tr!(
+          "{} is already in the library.",
+          entry.title
+        )))
+        .ok();
+      return;
+    }
+
+    self.busy = true;
+    let credentials = self.credentials(context);
+    let hub2 = hub.clone();
+    hub.send(Event::AcquireNetwork(NetworkTask::Opds)).ok();
+    thread::spawn(move || {
+      let label = tr!("Downloading {}", entry.title);
+      let path = match download_book(&url, &dir, &path, credentials, &label, &hub2) {
+        Ok(()) => {
+          hub2
+            .send(Event::Notify(tr!("Downloaded {}.", entry.title)))
+            .ok();
+          Some(path)
+        },
+        Err(e) => {
+          fs::remove_file(dir.join(DOWNLOAD_FILENAME)).ok();
+          report_error(
+            &hub2,
+            Severity::Error,
+            &tr!("Can't download {}", entry.title),
+            &e,
+          );
+          None
+        },
+      };
+      hub2
+        .send(Event::Progress(
+          ViewId::FetchProgress,
+          String::new(),
+          Some(1.0),
+        ))
+        .ok();
+      hub2.send(Event::OpdsDownloaded(path)).ok();
+      hub2.send(Event::ReleaseNetwork(NetworkTask::Opds)).ok();
+    });
+  }
+
+  fn toggle_title_menu(
+    &mut self,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::TitleMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let entries = vec![EntryKind::Command(tr!("Refresh"), EntryId::Refresh)];
+      let title_menu = Menu::new(
+        rect,
+        ViewId::TitleMenu,
+        MenuKind::DropDown,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        title_menu.id(),
+        *title_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(title_menu) as Box<dyn View>);
+    }
+  }
+
+  fn toggle_book_menu(
+    &mut self,
+    index: usize,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::BookMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let kind = match self.feed.entries.get(index).map(|entry| &entry.link) {
+        Some(OpdsLink::Book(_, kind)) => kind.to_uppercase(),
+        _ => return,
+      };
+
+      let book_menu = Menu::new(
+        rect,
+        ViewId::BookMenu,
+        MenuKind::Contextual,
+        vec![EntryKind::Command(
+          tr!("Download {}", kind),
+          EntryId::DownloadBook(index),
+        )],
+        context,
+      );
+      rq.add(RenderData::new(
+        book_menu.id(),
+        *book_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(book_menu) as Box<dyn View>);
+    }
+  }
+}
+
+// The first feed lists the configured catalogs.
+fn catalogs_feed(catalogs: &[OpdsCatalog]) -> OpdsFeed {
+  OpdsFeed {
+    title: tr!("Catalogs"),
+    entries: catalogs
+      .iter()
+      .map(|catalog| OpdsEntry {
+        title: if catalog.name.is_empty() {
+          catalog.url.clone()
+        } else {
+          catalog.name.clone()
+        },
+        author: String::new(),
+        link: OpdsLink::Catalog(catalog.url.clone()),
+      })
+      .collect(),
+    ..Default::default()
+  }
+}
+
+fn authenticate(request: RequestBuilder, credentials: Option<(String, String)>) -> RequestBuilder {
+  match credentials {
+    Some((username, password)) => request.basic_auth(username, Some(password)),
+    None => request,
+  }
+}
+
+fn fetch_feed(url: &str, credentials: Option<(String, String)>) -> Result<OpdsFeed, Error> {
+  let client = Client::builder().timeout(FETCH_TIMEOUT).build()?;
+  let text = authenticate(client.get(url), credentials)
+    .send()?
+    .error_for_status()?
+    .text()?;
+  parse_opds(&text, url)
+}
+
+// The book is first downloaded to a hidden file, so that an interrupted download doesn't
+// leave a broken document in the library.
+fn download_book(
+  url: &str,
+  dir: &Path,
+  path: &Path,
+  credentials: Option<(String, String)>,
+  label: &str,
+  hub: &Hub,
+) -> Result<(), Error> {
+  fs::create_dir_all(dir)?;
+  let client = Client::builder().timeout(DOWNLOAD_TIMEOUT).build()?;
+  let download_path = dir.join(DOWNLOAD_FILENAME);
+  download(
+    authenticate(client.get(url), credentials),
+    &download_path,
+    label,
+    hub,
+  )?;
+  fs::rename(&download_path, path)?;
+  Ok(())
+}
+
+impl View for OpdsBrowser {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Page(dir) => {
+        self.go_to_neighbor(dir, hub, rq, context);
+        true
+      },
+      Event::ToggleBookMenu(rect, index) => {
+        self.open(index, rect, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::DownloadBook(index)) => {
+        self.download(index, hub, context);
+        true
+      },
+      Event::Select(EntryId::Refresh) => {
+        if !self.feed.url.is_empty() {
+          self.fetch(self.feed.url.clone(), Move::Reload, hub, context);
+        }
+        true
+      },
+      Event::OpdsFeed(ref feed) => {
+        self.busy = false;
+        if let Some(feed) = feed {
+          self.set_feed(*feed.clone(), rq);
+        } else if let Move::Down = self.moving {
+          if self.history.is_empty() {
+            self.catalog = None;
+          }
+        }
+        true
+      },
+      Event::OpdsDownloaded(..) => {
+        self.busy = false;
+        true
+      },
+      Event::Device(DeviceEvent::NetUp) => {
+        if let Some((url, moving)) = self.pending_fetch.take() {
+          self.fetch(url, moving, hub, context);
+        }
+        true
+      },
+      Event::Back => {
+        self.go_back(hub, rq);
+        true
+      },
+      Event::ToggleNear(ViewId::TitleMenu, rect) => {
+        self.toggle_title_menu(rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::MainMenu, rect) => {
+        toggle_main_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::BatteryMenu, rect) => {
+        toggle_battery_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::ClockMenu, rect) => {
+        toggle_clock_menu(self, rect, None, rq, context);
+        true
+      },
+      Event::Reseed => {
+        self.update_list(rq);
+        self.update_bottom_bar(rq);
+        true
+      },
+      Event::Gesture(GestureEvent::Cross(_)) => {
+        hub.send(Event::Back).ok();
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, _fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {}
+
+  fn resize(&mut self, rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+
+    self.children[0].resize(
+      rect![
+        rect.min.x,
+        rect.min.y,
+        rect.max.x,
+        rect.min.y + small_height - small_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[1].resize(
+      rect![
+        rect.min.x,
+        rect.min.y + small_height - small_thickness,
+        rect.max.x,
+        rect.min.y + small_height + big_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[2] = Box::new(EntriesList::new(rect![
+      rect.min.x,
+      rect.min.y + small_height + big_thickness,
+      rect.max.x,
+      rect.max.y - small_height - small_thickness
+    ])) as Box<dyn View>;
+
+    self.children[3].resize(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height - small_thickness,
+        rect.max.x,
+        rect.max.y - small_height + big_thickness
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    self.children[4].resize(
+      rect![
+        rect.min.x,
+        rect.max.y - small_height + big_thickness,
+        rect.max.x,
+        rect.max.y
+      ],
+      hub,
+      rq,
+      context,
+    );
+
+    // Floating windows.
+    for i in 5..self.children.len() {
+      self.children[i].resize(rect, hub, rq, context);
+    }
+
+    self.rect = rect;
+    self.update_list(&mut RenderQueue::new());
+    self.update_bottom_bar(&mut RenderQueue::new());
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Full));
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
@@ -6,10 +6,19 @@ use crate::{
   document::BYTES_PER_PAGE,
   font::{font_from_style, Fonts, NORMAL_STYLE},
   framebuffer::{Framebuffer, UpdateMode},
-  geom::Rectangle,
+  geom::{CycleDir, Point, Rectangle},
   gesture::GestureEvent,
+  input::{DeviceEvent, FingerStatus},
 };
 
+// The page that would be reached by lifting the finger that scrubs the label.
+struct Scrub {
+  finger_id: i32,
+  start: Point,
+  target: usize,
+  moved: bool,
+}
+
 pub struct PageLabel {
   id: Id,
   rect: Rectangle,
@@ -17,6 +26,9 @@ pub struct PageLabel {
   current_page: usize,
   pages_count: usize,
   synthetic: bool,
+  // Whether the thirds of the label navigate through the history and a hold scrubs the document.
+  navigation: bool,
+  scrub: Option<Scrub>,
 }
 
 impl PageLabel {
@@ -33,9 +45,16 @@ impl PageLabel {
       current_page,
       pages_count,
       synthetic,
+      navigation: false,
+      scrub: None,
     }
   }
 
+  pub fn navigation(mut self, navigation: bool) -> PageLabel {
+    self.navigation = navigation;
+    self
+  }
+
   pub fn update(&mut self, current_page: usize, pages_count: usize, rq: &mut RenderQueue) {
     let mut render = false;
     if self.current_page != current_page {
@@ -51,35 +70,48 @@ impl PageLabel {
     }
   }
 
+  fn page_at(&self, x: i32) -> usize {
+    let ratio = (x - self.rect.min.x) as f32 / self.rect.width() as f32;
+    let page = (ratio.max(0.0).min(1.0) * self.pages_count as f32) as usize;
+    let page = page.min(self.pages_count.saturating_sub(1));
+    if self.synthetic {
+      // Snap to the beginning of a synthetic page.
+      page - page % BYTES_PER_PAGE as usize
+    } else {
+      page
+    }
+  }
+
   pub fn text(&self, size: u8) -> String {
     if self.pages_count == 0 {
-      return "No pages".to_string();
+      return tr!("No pages");
+    }
+    let page = self
+      .scrub
+      .as_ref()
+      .map_or(self.current_page, |scrub| scrub.target);
+    if page >= self.pages_count {
+      return tr!("Extra Content");
     }
     let (current_page, pages_count, precision) = if self.synthetic {
       (
-        self.current_page as f64 / BYTES_PER_PAGE,
+        page as f64 / BYTES_PER_PAGE,
         self.pages_count as f64 / BYTES_PER_PAGE,
         1,
       )
     } else {
-      (self.current_page as f64 + 1.0, self.pages_count as f64, 0)
+      (page as f64 + 1.0, self.pages_count as f64, 0)
     };
-    let percent = 100.0 * self.current_page as f32 / self.pages_count as f32;
+    let percent = 100.0 * page as f32 / self.pages_count as f32;
+    let current_page = format!("{:.1$}", current_page, precision);
+    let pages_count = format!("{:.1$}", pages_count, precision);
+    let percent = format!("{:.1}", percent);
     match size {
-      0 => format!(
-        "Page {1:.0$} of {2:.0$} ({3:.1}%)",
-        precision, current_page, pages_count, percent
-      ),
-      1 => format!(
-        "P. {1:.0$} of {2:.0$} ({3:.1}%)",
-        precision, current_page, pages_count, percent
-      ),
-      2 => format!(
-        "{1:.0$}/{2:.0$} ({3:.1}%)",
-        precision, current_page, pages_count, percent
-      ),
-      3 => format!("{1:.0$} ({2:.1}%)", precision, current_page, percent),
-      _ => format!("{:.1}%", percent),
+      0 => tr!("Page {} of {} ({}%)", current_page, pages_count, percent),
+      1 => tr!("P. {} of {} ({}%)", current_page, pages_count, percent),
+      2 => format!("{}/{} ({}%)", current_page, pages_count, percent),
+      3 => format!("{} ({}%)", current_page, percent),
+      _ => format!("{}%", percent),
     }
   }
 }
@@ -90,16 +122,71 @@ impl View for PageLabel {
     evt: &Event,
     _hub: &Hub,
     bus: &mut Bus,
-    _rq: &mut RenderQueue,
+    rq: &mut RenderQueue,
     _context: &mut Context,
   ) -> bool {
     match *evt {
       Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
-        bus.push_back(Event::Toggle(ViewId::GoToPage));
+        let third = self.rect.width() as i32 / 3;
+        if !self.navigation {
+          bus.push_back(Event::Toggle(ViewId::GoToPage));
+        } else if center.x < self.rect.min.x + third {
+          bus.push_back(Event::History(CycleDir::Previous, false));
+        } else if center.x >= self.rect.max.x - third {
+          bus.push_back(Event::History(CycleDir::Next, false));
+        } else {
+          bus.push_back(Event::Toggle(ViewId::GoToPage));
+        }
+        true
+      },
+      Event::Gesture(GestureEvent::HoldFingerShort(center, id)) if self.rect.includes(center) => {
+        if self.navigation && self.pages_count > 0 {
+          self.scrub = Some(Scrub {
+            finger_id: id,
+            start: center,
+            target: self.current_page,
+            moved: false,
+          });
+        } else {
+          bus.push_back(Event::ToggleNear(ViewId::PageMenu, self.rect));
+        }
         true
       },
-      Event::Gesture(GestureEvent::HoldFingerShort(center, ..)) if self.rect.includes(center) => {
-        bus.push_back(Event::ToggleNear(ViewId::PageMenu, self.rect));
+      Event::Device(DeviceEvent::Finger {
+        status: FingerStatus::Motion,
+        id,
+        position,
+        ..
+      }) if self.scrub.as_ref().map_or(false, |s| s.finger_id == id) => {
+        let target = self.page_at(position.x);
+        if let Some(scrub) = self.scrub.as_mut() {
+          scrub.moved |= (position.x - scrub.start.x).abs() > self.rect.height() as i32 / 2;
+          if scrub.moved && scrub.target != target {
+            scrub.target = target;
+            rq.add(RenderData::new(self.id, self.rect, UpdateMode::Fast));
+            bus.push_back(Event::PagePreview(Some(target)));
+          }
+        }
+        true
+      },
+      Event::Device(DeviceEvent::Finger {
+        status: FingerStatus::Up,
+        id,
+        ..
+      }) if self.scrub.as_ref().map_or(false, |s| s.finger_id == id) => {
+        if let Some(scrub) = self.scrub.take() {
+          if scrub.moved {
+            bus.push_back(Event::PagePreview(None));
+            if scrub.target != self.current_page {
+              bus.push_back(Event::GoTo(scrub.target));
+            } else {
+              rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+            }
+          } else {
+            // A hold without motion brings up the page menu.
+            bus.push_back(Event::ToggleNear(ViewId::PageMenu, self.rect));
+          }
+        }
         true
       },
       _ => false,
@@ -122,8 +209,14 @@ impl View for PageLabel {
     let dx = padding + (max_width - plan.width) / 2;
     let dy = (self.rect.height() as i32 - font.x_heights.0 as i32) / 2;
     let pt = pt!(self.rect.min.x + dx, self.rect.max.y - dy);
-    fb.draw_rectangle(&self.rect, WHITE);
-    font.render(fb, BLACK, &plan, pt);
+    // The label is inverted while it shows the target of a scrub.
+    let (bg, fg) = if self.scrub.as_ref().map_or(false, |s| s.moved) {
+      (BLACK, WHITE)
+    } else {
+      (WHITE, BLACK)
+    };
+    fb.draw_rectangle(&self.rect, bg);
+    font.render(fb, fg, &plan, pt);
   }
 
   fn rect(&self) -> &Rectangle {
@@ -118,11 +118,11 @@ impl View for PresetsList {
     _hub: &Hub,
     _bus: &mut Bus,
     rq: &mut RenderQueue,
-    _context: &mut Context,
+    context: &mut Context,
   ) -> bool {
     match *evt {
       Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
-        match dir {
+        match context.ui_dir(dir) {
           Dir::West => {
             self.set_current_page(CycleDir::Next);
             rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
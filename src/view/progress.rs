@@ -0,0 +1,286 @@
+//! A dialog showing the progress of a long operation.
+//!
+//! The progress bar is made of a few segments, and the dialog is only rendered again when the
+//! number of filled segments changes, to limit the number of screen refreshes.
+//! When the amount of work isn't known in advance, a single segment moves along the bar.
+
+use super::{
+  Bus,
+  Event,
+  Hub,
+  Id,
+  RenderData,
+  RenderQueue,
+  View,
+  ViewId,
+  BORDER_RADIUS_MEDIUM,
+  ID_FEEDER,
+  THICKNESS_LARGE,
+  THICKNESS_MEDIUM,
+};
+use crate::{
+  app::Context,
+  color::{BLACK, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{BorderSpec, CornerSpec, Rectangle},
+  gesture::GestureEvent,
+  unit::scale_by_dpi,
+};
+use std::time::{Duration, Instant};
+
+pub const PROGRESS_STEPS: usize = 10;
+// The minimum delay between two moves of the indeterminate segment.
+const INDETERMINATE_DELAY: Duration = Duration::from_millis(750);
+
+pub struct ProgressDialog {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  view_id: ViewId,
+  text: String,
+  determinate: bool,
+  step: usize,
+  last_step: Instant,
+}
+
+// Returns the step of the given progress, or the next position of the indeterminate segment.
+fn next_step(progress: Option<f32>, step: usize, elapsed: Duration) -> usize {
+  match progress {
+    Some(p) => ((p.max(0.0).min(1.0) * PROGRESS_STEPS as f32) as usize).min(PROGRESS_STEPS),
+    None if elapsed >= INDETERMINATE_DELAY => (step + 1) % PROGRESS_STEPS,
+    None => step,
+  }
+}
+
+impl ProgressDialog {
+  pub fn new(
+    view_id: ViewId,
+    text: String,
+    progress: Option<f32>,
+    context: &mut Context,
+  ) -> ProgressDialog {
+    let mut dialog = ProgressDialog {
+      id: ID_FEEDER.next(),
+      rect: Rectangle::default(),
+      children: Vec::new(),
+      view_id,
+      text,
+      determinate: progress.is_some(),
+      step: next_step(progress, 0, Duration::default()),
+      last_step: Instant::now(),
+    };
+    dialog.layout(context);
+    dialog
+  }
+
+  fn layout(&mut self, context: &mut Context) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let (width, height) = context.display.dims;
+    let font = font_from_style(&mut context.fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let padding = font.em() as i32;
+    let max_message_width = width as i32 - 3 * padding;
+    let plan = font.plan(&self.text, Some(max_message_width), None);
+    let dialog_width = plan.width.max(width as i32 / 2) + 3 * padding;
+    let dialog_height = 8 * x_height + 2 * padding;
+    let dx = (width as i32 - dialog_width) / 2;
+    let dy = (height as i32 - dialog_height) / 2;
+    self.rect = rect![dx, dy, dx + dialog_width, dy + dialog_height];
+  }
+
+  // Updates the state of the dialog and returns whether it needs to be rendered again.
+  pub fn update(&mut self, text: String, progress: Option<f32>, context: &mut Context) -> bool {
+    let step = next_step(progress, self.step, self.last_step.elapsed());
+    let changed = step != self.step || text != self.text || progress.is_some() != self.determinate;
+    if changed {
+      if text != self.text {
+        self.text = text;
+        self.layout(context);
+      }
+      self.step = step;
+      self.determinate = progress.is_some();
+      self.last_step = Instant::now();
+    }
+    changed
+  }
+
+  // Updates the progress and draws the dialog directly on the screen, when needed.
+  // This is meant for the operations that block the event loop.
+  pub fn advance(&mut self, progress: Option<f32>, fb: &mut dyn Framebuffer, fonts: &mut Fonts) {
+    let step = next_step(progress, self.step, self.last_step.elapsed());
+    if step != self.step || progress.is_some() != self.determinate {
+      self.step = step;
+      self.determinate = progress.is_some();
+      self.last_step = Instant::now();
+      self.show(fb, fonts);
+    }
+  }
+
+  pub fn show(&self, fb: &mut dyn Framebuffer, fonts: &mut Fonts) {
+    self.render(fb, self.rect, fonts);
+    if let Err(e) = fb.update(&self.rect, UpdateMode::Gui) {
+      eprintln!("Can't update the progress dialog: {:#}.", e);
+    }
+  }
+}
+
+// Shows or updates the progress dialog *view_id* within *children*.
+// The dialog is closed once the progress reaches one.
+pub fn show_progress(
+  children: &mut Vec<Box<dyn View>>,
+  view_id: ViewId,
+  text: String,
+  progress: Option<f32>,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  let index = children.iter().position(|c| {
+    c.downcast_ref::<ProgressDialog>()
+      .map_or(false, |d| d.view_id == view_id)
+  });
+
+  if progress.map_or(false, |p| p >= 1.0) {
+    if let Some(index) = index {
+      let child = children.remove(index);
+      rq.add(RenderData::expose(*child.rect(), UpdateMode::Gui));
+    }
+    return;
+  }
+
+  if let Some(dialog) = index.and_then(|index| children[index].downcast_mut::<ProgressDialog>()) {
+    let rect = dialog.rect;
+    if dialog.update(text, progress, context) {
+      if rect != dialog.rect {
+        rq.add(RenderData::expose(rect, UpdateMode::Gui));
+      }
+      rq.add(RenderData::new(dialog.id, dialog.rect, UpdateMode::Gui));
+    }
+  } else {
+    let dialog = ProgressDialog::new(view_id, text, progress, context);
+    rq.add(RenderData::new(dialog.id, dialog.rect, UpdateMode::Gui));
+    children.push(Box::new(dialog) as Box<dyn View>);
+  }
+}
+
+impl View for ProgressDialog {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    _hub: &Hub,
+    _bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+    let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as u16;
+
+    fb.draw_rounded_rectangle_with_border(
+      &self.rect,
+      &CornerSpec::Uniform(border_radius),
+      &BorderSpec {
+        thickness: border_thickness,
+        color: BLACK,
+      },
+      &WHITE,
+    );
+
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let padding = font.em() as i32;
+    let plan = font.plan(
+      &self.text,
+      Some(self.rect.width() as i32 - 3 * padding),
+      None,
+    );
+    let pt = pt!(
+      self.rect.center().x - plan.width / 2,
+      self.rect.min.y + padding + 3 * x_height
+    );
+    font.render(fb, TEXT_NORMAL[1], &plan, pt);
+
+    let bar_rect = rect![
+      self.rect.min.x + padding,
+      self.rect.max.y - padding - 2 * x_height,
+      self.rect.max.x - padding,
+      self.rect.max.y - padding
+    ];
+    let gap = x_height / 2;
+    let count = PROGRESS_STEPS as i32;
+    let segment_width = (bar_rect.width() as i32 - (count - 1) * gap) / count;
+    for i in 0..PROGRESS_STEPS {
+      let x_min = bar_rect.min.x + i as i32 * (segment_width + gap);
+      let segment = rect![x_min, bar_rect.min.y, x_min + segment_width, bar_rect.max.y];
+      let filled = if self.determinate {
+        i < self.step
+      } else {
+        i == self.step
+      };
+      if filled {
+        fb.draw_rectangle(&segment, BLACK);
+      } else {
+        fb.draw_rectangle_outline(
+          &segment,
+          &BorderSpec {
+            thickness,
+            color: BLACK,
+          },
+        );
+      }
+    }
+  }
+
+  fn resize(&mut self, _rect: Rectangle, _hub: &Hub, _rq: &mut RenderQueue, context: &mut Context) {
+    self.layout(context);
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+
+  fn view_id(&self) -> Option<ViewId> {
+    Some(self.view_id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{next_step, INDETERMINATE_DELAY, PROGRESS_STEPS};
+  use std::time::Duration;
+
+  #[test]
+  fn test_next_step() {
+    assert_eq!(next_step(Some(0.0), 0, Duration::default()), 0);
+    assert_eq!(next_step(Some(0.37), 0, Duration::default()), 3);
+    assert_eq!(next_step(Some(1.5), 0, Duration::default()), PROGRESS_STEPS);
+    assert_eq!(next_step(None, 4, Duration::default()), 4);
+    assert_eq!(next_step(None, 4, INDETERMINATE_DELAY), 5);
+    assert_eq!(next_step(None, PROGRESS_STEPS - 1, INDETERMINATE_DELAY), 0);
+  }
+}
@@ -1,7 +1,7 @@
 use crate::{
   app::Context,
   color::WHITE,
-  document::{Document, Neighbors, TocEntry},
+  document::Neighbors,
   font::Fonts,
   framebuffer::{Framebuffer, UpdateMode},
   geom::{halves, CycleDir, Rectangle},
@@ -37,8 +37,7 @@ pub struct BottomBar {
 impl BottomBar {
   pub fn new(
     rect: Rectangle,
-    doc: &mut dyn Document,
-    toc: Option<Vec<TocEntry>>,
+    chapter: &str,
     current_page: usize,
     pages_count: usize,
     neighbors: &Neighbors,
@@ -67,13 +66,7 @@ impl BottomBar {
       pt!(rect.min.x + side + small_half_width, rect.max.y)
     ];
 
-    let chapter = toc
-      .or_else(|| doc.toc())
-      .as_ref()
-      .and_then(|toc| doc.chapter(current_page, toc))
-      .map(|c| c.title.clone())
-      .unwrap_or_default();
-    let chapter_label = Label::new(chapter_rect, chapter, Align::Center)
+    let chapter_label = Label::new(chapter_rect, chapter.to_string(), Align::Center)
       .event(Some(Event::Show(ViewId::TableOfContents)));
     children.push(Box::new(chapter_label) as Box<dyn View>);
 
@@ -85,7 +78,8 @@ impl BottomBar {
       current_page,
       pages_count,
       synthetic,
-    );
+    )
+    .navigation(true);
     children.push(Box::new(page_label) as Box<dyn View>);
 
     let next_rect = rect![rect.max - side, rect.max];
@@ -0,0 +1,125 @@
+//! Detects the text columns of a page from the horizontal projection of its words.
+
+use crate::document::BoundedText;
+
+const BINS_COUNT: usize = 200;
+// Bins whose coverage is below this fraction of the maximum are considered empty.
+const EMPTY_THRESHOLD: f32 = 0.08;
+// The minimum width of a gutter, relative to the page width.
+const MIN_GUTTER_WIDTH: f32 = 0.025;
+// The minimum width of a column, relative to the page width.
+const MIN_COLUMN_WIDTH: f32 = 0.15;
+// The padding added on both sides of a column, relative to the page width.
+const PADDING: f32 = 0.01;
+
+// Returns the horizontal extents of the columns, relative to the page width, from left to right.
+pub fn detect_columns(words: &[BoundedText], page_width: f32) -> Option<Vec<[f32; 2]>> {
+  if words.is_empty() || page_width <= 0.0 {
+    return None;
+  }
+
+  let mut bins = [0.0f32; BINS_COUNT];
+  for word in words {
+    let x0 = (word.rect.min.x / page_width).max(0.0).min(1.0);
+    let x1 = (word.rect.max.x / page_width).max(0.0).min(1.0);
+    let i0 = (x0 * BINS_COUNT as f32) as usize;
+    let i1 = ((x1 * BINS_COUNT as f32).ceil() as usize).min(BINS_COUNT);
+    // Weigh each word by its height, so that large headings don't hide the gutters.
+    let weight = word.rect.height().max(0.0);
+    for bin in &mut bins[i0.min(BINS_COUNT - 1)..i1.max(i0 + 1).min(BINS_COUNT)] {
+      *bin += weight;
+    }
+  }
+
+  let max_coverage = bins.iter().cloned().fold(0.0, f32::max);
+  if max_coverage <= 0.0 {
+    return None;
+  }
+
+  let filled: Vec<bool> = bins
+    .iter()
+    .map(|&c| c > EMPTY_THRESHOLD * max_coverage)
+    .collect();
+  let min_gutter = (MIN_GUTTER_WIDTH * BINS_COUNT as f32).ceil() as usize;
+  let mut columns = Vec::new();
+  let mut start: Option<usize> = None;
+  let mut gap = 0;
+
+  for (i, &f) in filled.iter().enumerate() {
+    if f {
+      if start.is_none() {
+        start = Some(i);
+      }
+      gap = 0;
+    } else if let Some(s) = start {
+      gap += 1;
+      if gap >= min_gutter {
+        columns.push([s, i + 1 - gap]);
+        start = None;
+        gap = 0;
+      }
+    }
+  }
+  if let Some(s) = start {
+    columns.push([s, BINS_COUNT - gap]);
+  }
+
+  // Merge the narrow fragments (page numbers, marginal notes, etc.) into their neighbors.
+  let min_width = (MIN_COLUMN_WIDTH * BINS_COUNT as f32) as usize;
+  let mut merged: Vec<[usize; 2]> = Vec::new();
+  for column in columns {
+    match merged.last_mut() {
+      Some(last) if last[1] - last[0] < min_width || column[1] - column[0] < min_width => {
+        last[1] = column[1];
+      },
+      _ => merged.push(column),
+    }
+  }
+
+  Some(
+    merged
+      .into_iter()
+      .map(|[a, b]| {
+        [
+          (a as f32 / BINS_COUNT as f32 - PADDING).max(0.0),
+          (b as f32 / BINS_COUNT as f32 + PADDING).min(1.0),
+        ]
+      })
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::detect_columns;
+  use crate::{
+    document::{BoundedText, TextLocation},
+    geom::{Boundary, Vec2},
+  };
+
+  fn word(x0: f32, y0: f32, x1: f32, y1: f32) -> BoundedText {
+    BoundedText {
+      text: "word".to_string(),
+      rect: Boundary::new(Vec2::new(x0, y0), Vec2::new(x1, y1)),
+      location: TextLocation::Dynamic(0),
+    }
+  }
+
+  #[test]
+  fn test_two_columns() {
+    let mut words = vec![word(50.0, 20.0, 550.0, 40.0)];
+    for i in 0..40 {
+      let y = 60.0 + 12.0 * i as f32;
+      words.push(word(50.0, y, 150.0, y + 10.0));
+      words.push(word(160.0, y, 280.0, y + 10.0));
+      words.push(word(320.0, y, 450.0, y + 10.0));
+      words.push(word(460.0, y, 550.0, y + 10.0));
+    }
+    let columns = detect_columns(&words, 600.0).unwrap();
+    assert_eq!(columns.len(), 2);
+    assert!(columns[0][1] < 0.5 && columns[1][0] > 0.5);
+    let columns = detect_columns(&words[..1], 600.0).unwrap();
+    assert_eq!(columns.len(), 1);
+    assert!(detect_columns(&[], 600.0).is_none());
+  }
+}
@@ -0,0 +1,164 @@
+//! Measures how the pages are rendered, to find out why a document is slow to display.
+
+use crate::{
+  color::{BLACK, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  document::HumanSize,
+  font::{font_from_style, Fonts, MD_SIZE},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{BorderSpec, CornerSpec, Rectangle},
+  resources::{available_memory, resident_memory},
+  unit::scale_by_dpi,
+  view::{BORDER_RADIUS_SMALL, THICKNESS_MEDIUM},
+};
+use std::{collections::BTreeMap, time::Duration};
+
+// The number of render times kept.
+const MAX_SAMPLES: usize = 64;
+
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+  // The time it took to render each page, the last time it was rendered.
+  render_times: BTreeMap<usize, Duration>,
+  cache_hits: usize,
+  cache_misses: usize,
+  update_mode: Option<UpdateMode>,
+}
+
+impl Diagnostics {
+  pub fn record_render(&mut self, location: usize, duration: Duration) {
+    self.cache_misses += 1;
+    if self.render_times.len() >= MAX_SAMPLES && !self.render_times.contains_key(&location) {
+      // Forget the page that is the farthest from the rendered one.
+      let first = self.render_times.keys().next().cloned().unwrap_or(location);
+      let last = self
+        .render_times
+        .keys()
+        .next_back()
+        .cloned()
+        .unwrap_or(location);
+      let farthest = if location.saturating_sub(first) > last.saturating_sub(location) {
+        first
+      } else {
+        last
+      };
+      self.render_times.remove(&farthest);
+    }
+    self.render_times.insert(location, duration);
+    eprintln!(
+      "Rendered location {} in {} ms.",
+      location,
+      duration.as_millis()
+    );
+  }
+
+  pub fn record_hit(&mut self) {
+    self.cache_hits += 1;
+  }
+
+  pub fn record_update(&mut self, update_mode: UpdateMode) {
+    self.update_mode = Some(update_mode);
+  }
+
+  // The lines of the overlay for the given displayed locations.
+  pub fn lines(&self, locations: &[usize], cached_pages: usize, cached_bytes: u64) -> Vec<String> {
+    let mut lines = Vec::new();
+    for location in locations {
+      let time = self
+        .render_times
+        .get(location)
+        .map(|d| format!("{} ms", d.as_millis()))
+        .unwrap_or_else(|| "?".to_string());
+      lines.push(format!("Location {}: {}", location, time));
+    }
+    if !self.render_times.is_empty() {
+      let total: Duration = self.render_times.values().sum();
+      let max = self
+        .render_times
+        .values()
+        .max()
+        .cloned()
+        .unwrap_or_default();
+      lines.push(format!(
+        "Mean: {} ms, max: {} ms",
+        (total / self.render_times.len() as u32).as_millis(),
+        max.as_millis()
+      ));
+    }
+    let lookups = self.cache_hits + self.cache_misses;
+    let ratio = if lookups > 0 {
+      100 * self.cache_hits / lookups
+    } else {
+      0
+    };
+    lines.push(format!(
+      "Cache: {} hits, {} misses ({}%)",
+      self.cache_hits, self.cache_misses, ratio
+    ));
+    lines.push(format!(
+      "Cached: {} pages, {}",
+      cached_pages,
+      cached_bytes.human_size()
+    ));
+    let memory = |value: Option<u64>| value.map_or_else(|| "?".to_string(), |v| v.human_size());
+    lines.push(format!(
+      "Memory: {} used, {} available",
+      memory(resident_memory()),
+      memory(available_memory())
+    ));
+    lines
+  }
+
+  // Draws the overlay in the bottom left corner of *rect*.
+  pub fn render(
+    &self,
+    fb: &mut dyn Framebuffer,
+    rect: &Rectangle,
+    mut lines: Vec<String>,
+    fonts: &mut Fonts,
+  ) {
+    if let Some(update_mode) = self.update_mode {
+      let waveform = fb
+        .waveform(rect, update_mode)
+        .map(|name| format!(" ({})", name))
+        .unwrap_or_default();
+      lines.push(format!("Update: {:?}{}", update_mode, waveform));
+    }
+    let dpi = CURRENT_DEVICE.dpi;
+    let font = font_from_style(fonts, &MD_SIZE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let padding = font.em() as i32 / 2;
+    let line_height = 3 * x_height;
+    let max_width = rect.width() as i32 - 4 * padding;
+    let plans: Vec<_> = lines
+      .iter()
+      .map(|line| font.plan(line, Some(max_width), None))
+      .collect();
+    let width = plans.iter().map(|p| p.width).max().unwrap_or(0) + 2 * padding;
+    let height = plans.len() as i32 * line_height + 2 * padding;
+    let overlay_rect = rect![
+      rect.min.x + padding,
+      rect.max.y - padding - height,
+      rect.min.x + padding + width,
+      rect.max.y - padding
+    ];
+    let border_radius = scale_by_dpi(BORDER_RADIUS_SMALL, dpi) as i32;
+    let border_thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as u16;
+    fb.draw_rounded_rectangle_with_border(
+      &overlay_rect,
+      &CornerSpec::Uniform(border_radius),
+      &BorderSpec {
+        thickness: border_thickness,
+        color: BLACK,
+      },
+      &WHITE,
+    );
+    for (i, plan) in plans.iter().enumerate() {
+      let pt = pt!(
+        overlay_rect.min.x + padding,
+        overlay_rect.min.y + padding + i as i32 * line_height + (line_height + x_height) / 2
+      );
+      font.render(fb, TEXT_NORMAL[1], plan, pt);
+    }
+  }
+}
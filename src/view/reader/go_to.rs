@@ -0,0 +1,151 @@
+use crate::document::TocEntry;
+use levenshtein::levenshtein;
+
+// The destinations accepted by the *Go to page* input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoTo {
+  // A page number, or a number of pages relative to the current page.
+  Page(f64, Option<Sign>),
+  // A percentage of the document, or a percentage relative to the current position.
+  Percent(f64, Option<Sign>),
+  PageName(String),
+  Chapter(String),
+  Random,
+  First,
+  Last,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Sign {
+  Plus,
+  Minus,
+}
+
+impl Sign {
+  pub fn apply(self, value: f64) -> f64 {
+    match self {
+      Sign::Plus => value,
+      Sign::Minus => -value,
+    }
+  }
+}
+
+pub fn parse_go_to(text: &str) -> Option<GoTo> {
+  let text = text.trim();
+
+  match text {
+    "" => return None,
+    "_" => return Some(GoTo::Random),
+    "(" => return Some(GoTo::First),
+    ")" => return Some(GoTo::Last),
+    _ => (),
+  }
+
+  if text.starts_with('"') || text.starts_with('\'') {
+    return Some(GoTo::PageName(text[1..].to_string()));
+  }
+
+  let (sign, rest) = if text.starts_with('+') {
+    (Some(Sign::Plus), &text[1..])
+  } else if text.starts_with('-') {
+    (Some(Sign::Minus), &text[1..])
+  } else {
+    (None, text)
+  };
+
+  if rest.ends_with('%') {
+    if let Ok(value) = rest[..rest.len() - 1].trim().parse::<f64>() {
+      return Some(GoTo::Percent(value, sign));
+    }
+  }
+
+  if let Ok(value) = rest.parse::<f64>() {
+    return Some(GoTo::Page(value, sign));
+  }
+
+  Some(GoTo::Chapter(text.to_string()))
+}
+
+// Returns the entry whose title is the closest to *query*: the titles that contain the query
+// are preferred, then the ones that are within a small edit distance of it.
+pub fn find_chapter<'a>(toc: &'a [TocEntry], query: &str) -> Option<&'a TocEntry> {
+  let query = query.to_lowercase();
+  let mut best: Option<(usize, &TocEntry)> = None;
+  find_chapter_aux(toc, &query, &mut best);
+  best.map(|(_, entry)| entry)
+}
+
+fn find_chapter_aux<'a>(
+  toc: &'a [TocEntry],
+  query: &str,
+  best: &mut Option<(usize, &'a TocEntry)>,
+) {
+  for entry in toc {
+    let title = entry.title.to_lowercase();
+    let score = if title == query {
+      0
+    } else if title.contains(query) {
+      1 + title.len() - query.len()
+    } else {
+      let distance = levenshtein(&title, query);
+      // Allow roughly one mistake every four characters.
+      if 4 * distance > query.chars().count() {
+        usize::MAX
+      } else {
+        title.len() + distance
+      }
+    };
+
+    if score < usize::MAX && best.map_or(true, |(s, _)| score < s) {
+      *best = Some((score, entry));
+    }
+
+    find_chapter_aux(&entry.children, query, best);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{find_chapter, parse_go_to, GoTo, Sign};
+  use crate::document::{Location, TocEntry};
+
+  fn entry(title: &str, index: usize) -> TocEntry {
+    TocEntry {
+      title: title.to_string(),
+      location: Location::Exact(index),
+      index,
+      children: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn test_parse_go_to() {
+    assert_eq!(parse_go_to("60%"), Some(GoTo::Percent(60.0, None)));
+    assert_eq!(
+      parse_go_to("-5%"),
+      Some(GoTo::Percent(5.0, Some(Sign::Minus)))
+    );
+    assert_eq!(parse_go_to("+12"), Some(GoTo::Page(12.0, Some(Sign::Plus))));
+    assert_eq!(parse_go_to("42"), Some(GoTo::Page(42.0, None)));
+    assert_eq!(parse_go_to("'xii"), Some(GoTo::PageName("xii".to_string())));
+    assert_eq!(
+      parse_go_to("Epilogue"),
+      Some(GoTo::Chapter("Epilogue".to_string()))
+    );
+    assert_eq!(parse_go_to(" "), None);
+  }
+
+  #[test]
+  fn test_find_chapter() {
+    let toc = vec![
+      entry("Prologue", 0),
+      entry("Chapter One: The Storm", 1),
+      entry("Epilogue", 2),
+    ];
+    assert_eq!(find_chapter(&toc, "epilogue").map(|e| e.index), Some(2));
+    assert_eq!(find_chapter(&toc, "storm").map(|e| e.index), Some(1));
+    assert_eq!(find_chapter(&toc, "prolog").map(|e| e.index), Some(0));
+    assert_eq!(find_chapter(&toc, "Epilgue").map(|e| e.index), Some(2));
+    assert!(find_chapter(&toc, "index").is_none());
+  }
+}
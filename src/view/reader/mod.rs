@@ -1,25 +1,42 @@
 mod bottom_bar;
+mod columns;
+mod diagnostics;
+mod go_to;
 mod margin_cropper;
+mod page_preview;
+mod reading_timer;
 mod results_bar;
 mod results_label;
+mod review_bar;
+mod thumbnails;
+mod toc_tree;
 mod tool_bar;
 
 use self::{
   bottom_bar::BottomBar,
+  columns::detect_columns,
+  diagnostics::Diagnostics,
+  go_to::{find_chapter, parse_go_to, GoTo},
   margin_cropper::{MarginCropper, BUTTON_DIAMETER},
+  page_preview::{scale_down, PagePreview},
+  reading_timer::ReadingTimer,
   results_bar::ResultsBar,
+  review_bar::ReviewBar,
+  thumbnails::Thumbnails,
+  toc_tree::{page_of_link, skim_as_html, TocTree},
   tool_bar::ToolBar,
 };
 use super::top_bar::TopBar;
 use crate::{
   app::Context,
-  color::{BLACK, WHITE},
+  citation::{annotations_as_bibtex, cite},
+  color::{BLACK, TEXT_NORMAL, WHITE},
   device::CURRENT_DEVICE,
   document::{
-    chapter_from_index,
+    export::{export_text, TextFormat},
     html::HtmlDocument,
     open,
-    toc_as_html,
+    skim::skim,
     BoundedText,
     Document,
     Location,
@@ -29,50 +46,63 @@ use crate::{
     TocEntry,
     TocLocation,
     BYTES_PER_PAGE,
+    FILE_SCHEME,
   },
-  font::{family_names, Fonts},
-  framebuffer::{Framebuffer, Pixmap, UpdateMode},
-  frontlight::LightLevels,
-  geom::{
-    halves,
-    Axis,
-    BorderSpec,
-    Boundary,
-    CornerSpec,
-    CycleDir,
-    DiagDir,
-    Dir,
-    LinearDir,
-    Point,
-    Rectangle,
-  },
+  feedback::FeedbackKind,
+  font::{font_from_style, FamilyFiles, FontIndex, Fonts, Style, NORMAL_STYLE},
+  framebuffer::{clean_up, dither, DitherMode, Framebuffer, Pixmap, ScanFilter, UpdateMode},
+  geom::{halves, Axis, Boundary, CycleDir, DiagDir, Dir, LinearDir, Point, Rectangle},
   gesture::GestureEvent,
-  helpers::AsciiExtension,
+  helpers::{AsciiExtension, Fingerprint},
   input::{ButtonCode, ButtonStatus, DeviceEvent, FingerStatus},
+  insights::Usage,
+  library::{IntegrityIssue, Repair},
   metadata::{
+    annotations_as_json,
+    annotations_as_markdown,
+    format_duration,
     make_query,
     Annotation,
+    AnnotationsFormat,
     CroppingMargins,
+    DirectoryLayout,
+    ExportedAnnotation,
     FileInfo,
     Info,
     Margin,
+    PageMargins,
     PageScheme,
     ReaderInfo,
+    ReadingSpeed,
+    ScrollMode,
+    SimpleStatus,
+    Status,
     TextAlign,
+    TextAlignOverride,
     ZoomMode,
     DEFAULT_CONTRAST_EXPONENT,
     DEFAULT_CONTRAST_GRAY,
   },
+  phrasebook::{add_phrase, short_translation, Phrase, PHRASEBOOK_PATH},
   settings::{
-    guess_frontlight,
+    CitationStyle,
     FinishedAction,
+    HeaderSettings,
+    ReaderSettings,
+    RotationLock,
+    SkipFrontMatter,
+    TapAction,
+    TypographySettings,
     DEFAULT_FONT_FAMILY,
     DEFAULT_LINE_HEIGHT,
     DEFAULT_MARGIN_WIDTH,
     DEFAULT_TEXT_ALIGN,
   },
+  share::{is_drm_free, ShareServer},
+  tts::{next_sentence, speak, Synthesizer},
   unit::{mm_to_px, scale_by_dpi},
   view::{
+    command_palette::{menu_commands, PaletteCommand},
     common::{
       locate,
       locate_by_id,
@@ -81,12 +111,17 @@ use crate::{
       toggle_clock_menu,
       toggle_main_menu,
     },
+    dictionary::translate,
     filler::Filler,
     keyboard::Keyboard,
     menu::{Menu, MenuKind},
     named_input::NamedInput,
-    notification::Notification,
+    notification::{push_notification, Notification, NotificationPriority},
+    progress::PROGRESS_STEPS,
+    report::{report_error, Severity},
     search_bar::SearchBar,
+    share_dialog::ShareBookDialog,
+    undo::{record_undo, UndoAction},
     AppCmd,
     Bus,
     EntryId,
@@ -105,6 +140,7 @@ use crate::{
     THICKNESS_MEDIUM,
   },
 };
+use anyhow::{format_err, Error};
 use chrono::Local;
 use fxhash::{FxHashMap, FxHashSet};
 use rand_core::RngCore;
@@ -112,13 +148,16 @@ use regex::Regex;
 use septem::{prelude::*, Digit, Roman};
 use std::{
   collections::{BTreeMap, VecDeque},
-  path::PathBuf,
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
   sync::{
     atomic::{AtomicBool, Ordering as AtomicOrdering},
     Arc,
     Mutex,
   },
   thread,
+  time::{Duration, Instant, SystemTime},
 };
 
 const HISTORY_SIZE: usize = 32;
@@ -127,6 +166,26 @@ const ANNOTATION_DRIFT: u8 = 0x44;
 const HIGHLIGHT_DRIFT: u8 = 0x22;
 const TOC_SCHEME: &str = "toc:";
 const MEM_SCHEME: &str = "mem:";
+// How far the context of a translation extends around the selection, in words.
+const SENTENCE_MAX_WORDS: usize = 48;
+// The stem of the file, at the root of the library, that receives the citations.
+const CITATIONS_STEM: &str = "citations";
+// Swipes faster than this speed, in screen heights per second, scroll further than the finger.
+const KINETIC_SPEED_THRESHOLD: f64 = 1.5;
+const KINETIC_MAX_FACTOR: f64 = 4.0;
+// How long the rotation lock is lifted by the unlock gesture.
+const ROTATION_UNLOCK_DURATION: Duration = Duration::from_secs(30);
+// The number of steps offered by the font size menu on each side of the current size.
+const FONT_SIZE_STEPS: i32 = 10;
+// The number of books listed by the recent books menu.
+const RECENT_BOOKS_COUNT: usize = 8;
+// The side of the dog-ear drawn on the bookmarked pages, in millimeters. The area that toggles
+// the bookmark is twice as large.
+const DOG_EAR_SIZE: f32 = 4.0;
+// The number of words of the current page searched for after a reload.
+const ANCHOR_WORDS: usize = 8;
+// How many pages, in each direction, are searched for the anchor after a reload.
+const ANCHOR_MAX_PAGES: usize = 64;
 
 pub struct Reader {
   id: Id,
@@ -134,16 +193,26 @@ pub struct Reader {
   children: Vec<Box<dyn View>>,
   doc: Arc<Mutex<Box<dyn Document>>>,
   cache: BTreeMap<usize, Resource>,
+  // How the gray levels of the pages of fixed-layout documents are reduced.
+  page_dithering: DitherMode,
   text: FxHashMap<usize, Vec<BoundedText>>,
   annotations: FxHashMap<usize, Vec<Annotation>>,
   chunks: Vec<RenderChunk>,
   focus: Option<ViewId>,
   search: Option<Search>,
   search_direction: LinearDir,
+  search_scope: SearchScope,
   held_buttons: FxHashSet<ButtonCode>,
+  // The time at which the rotation lock was temporarily lifted.
+  rotation_unlocked: Option<Instant>,
   selection: Option<Selection>,
   target_annotation: Option<[TextLocation; 2]>,
+  // The annotation shown by the review bar.
+  review: Option<[TextLocation; 2]>,
+  speech: Option<Speech>,
   history: VecDeque<usize>,
+  // The locations left by going back in the history.
+  forward_history: VecDeque<usize>,
   state: State,
   info: Info,
   current_page: usize,
@@ -152,15 +221,42 @@ pub struct Reader {
   contrast: Contrast,
   synthetic: bool,
   page_turns: usize,
+  // The number of page turns at the last automatic save.
+  saved_page_turns: usize,
+  // The last reading state written to the library.
+  saved_state: Option<String>,
+  // The title and chapter shown above the page, and the height of the strip they occupy.
+  header: String,
+  header_height: i32,
   reflowable: bool,
   ephemeral: bool,
   finished: bool,
+  // The initial location of a book opened for the first time, until the page changes.
+  initial_location: Option<usize>,
+  // The reading state of a book opened for the first time, as it was when it was opened.
+  initial_state: Option<String>,
+  reading_timer: ReadingTimer,
+  // Set once the book was finished by reaching its end, and cleared below the threshold.
+  auto_finished: bool,
+  // The times at which the fingers on the screen touched it.
+  touch_starts: FxHashMap<i32, f64>,
+  // How long the last lifted finger stayed on the screen.
+  touch_duration: Option<f64>,
+  // The state of the table of contents, when the reader shows it.
+  toc_tree: Option<TocTree>,
+  // The measures shown by the diagnostics overlay, when it's enabled.
+  diagnostics: Option<Diagnostics>,
+  // The modification time and size of the document's file, as last seen.
+  file_stamp: Option<(SystemTime, u64)>,
+  bookmark_corner: DiagDir,
 }
 
 #[derive(Debug)]
 struct ViewPort {
   zoom_mode: ZoomMode,
+  scroll_mode: ScrollMode,
   top_offset: i32,
+  column: usize,
   margin_width: i32,
 }
 
@@ -168,12 +264,26 @@ impl Default for ViewPort {
   fn default() -> Self {
     ViewPort {
       zoom_mode: ZoomMode::FitToPage,
+      scroll_mode: ScrollMode::Paginated,
       top_offset: 0,
+      column: 0,
       margin_width: 0,
     }
   }
 }
 
+impl ViewPort {
+  // How the pages are laid out: the pages of the continuous scroll mode are stacked
+  // like in the fit-to-width zoom mode.
+  fn layout(&self) -> ZoomMode {
+    if self.scroll_mode == ScrollMode::Continuous {
+      ZoomMode::FitToWidth
+    } else {
+      self.zoom_mode
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 enum State {
   Idle,
@@ -193,6 +303,8 @@ struct Resource {
   pixmap: Pixmap,
   frame: Rectangle,
   scale: f32,
+  // The rectangles of the embedded images, within the pixmap.
+  images: Vec<Rectangle>,
 }
 
 #[derive(Debug, Clone)]
@@ -203,6 +315,15 @@ struct RenderChunk {
   scale: f32,
 }
 
+// The pages that are searched.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SearchScope {
+  Book,
+  Chapter,
+  // From the current page to the end of the book.
+  Onward,
+}
+
 #[derive(Debug)]
 struct Search {
   query: String,
@@ -210,6 +331,7 @@ struct Search {
   running: Arc<AtomicBool>,
   current_page: usize,
   results_count: usize,
+  scope: SearchScope,
 }
 
 impl Default for Search {
@@ -220,10 +342,20 @@ impl Default for Search {
       running: Arc::new(AtomicBool::new(true)),
       current_page: 0,
       results_count: 0,
+      scope: SearchScope::Book,
     }
   }
 }
 
+// The sentence being read aloud.
+#[derive(Debug, Clone)]
+struct Speech {
+  sentence: [TextLocation; 2],
+  synthesizer: Synthesizer,
+  // The location that follows the chapter being read.
+  end: usize,
+}
+
 #[derive(Debug)]
 struct Contrast {
   exponent: f32,
@@ -239,6 +371,17 @@ impl Default for Contrast {
   }
 }
 
+fn kinetic_delta(delta_y: i32, duration: f64, height: u32) -> i32 {
+  if duration <= 0.0 {
+    return delta_y;
+  }
+  let speed = delta_y.abs() as f64 / height as f64 / duration;
+  let factor = (speed / KINETIC_SPEED_THRESHOLD)
+    .max(1.0)
+    .min(KINETIC_MAX_FACTOR);
+  (delta_y as f64 * factor) as i32
+}
+
 fn scaling_factor(
   rect: &Rectangle,
   cropping_margin: &Margin,
@@ -257,8 +400,85 @@ fn scaling_factor(
       let height_ratio = surface_height / frame_height;
       width_ratio.min(height_ratio)
     },
-    ZoomMode::FitToWidth => width_ratio,
+    ZoomMode::FitToWidth | ZoomMode::FitToColumn => width_ratio,
+  }
+}
+
+// The default family is looked up among the fonts bundled with the application.
+fn family_files(font_family: &str, context: &Context) -> Result<FamilyFiles, Error> {
+  if font_family == DEFAULT_FONT_FAMILY {
+    FontIndex::scan(&["fonts"]).files(font_family, None)
+  } else {
+    context.font_index.files(
+      font_family,
+      context.settings.reader.font_styles.get(font_family),
+    )
+  }
+}
+
+// Returns an index greater than the indices of all the entries of *toc*.
+// The height of the strip above the page that holds the header, zero when it's hidden.
+// The typography settings, with the hyphenation and justification settings of the book.
+// The margins of a reflowable document: the book's uniform width supersedes the margins of
+// the settings.
+pub fn page_margins(reader_info: Option<&ReaderInfo>, settings: &ReaderSettings) -> PageMargins {
+  reader_info
+    .and_then(|r| {
+      r.margins
+        .or_else(|| r.margin_width.map(PageMargins::uniform))
+    })
+    .or(settings.margins)
+    .unwrap_or_else(|| PageMargins::uniform(settings.margin_width))
+}
+
+fn book_typography(info: &Info, typography: TypographySettings) -> TypographySettings {
+  let mut typography = typography;
+  if let Some(r) = info.reader.as_ref() {
+    if let Some(n) = r.max_hyphenated_lines {
+      typography.max_hyphenated_lines = n;
+    }
+    if let Some(n) = r.min_hyphenated_length {
+      typography.min_hyphenated_length = n;
+    }
+    if let Some(v) = r.stretch_tolerance {
+      typography.stretch_tolerance = v;
+    }
+  }
+  typography
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+  let md = path.metadata().ok()?;
+  md.modified().ok().map(|time| (time, md.len()))
+}
+
+fn annotations_formats() -> Vec<EntryKind> {
+  [AnnotationsFormat::Markdown, AnnotationsFormat::Json]
+    .iter()
+    .map(|f| EntryKind::Command(tr!(f.label()), EntryId::ExportAnnotations(*f)))
+    .collect()
+}
+
+fn header_height(settings: &HeaderSettings) -> i32 {
+  if !settings.show_title && !settings.show_chapter {
+    return 0;
   }
+  (scale_by_dpi(SMALL_BAR_HEIGHT, CURRENT_DEVICE.dpi) * settings.font_scale.max(0.5)) as i32
+}
+
+fn header_style(header_height: i32) -> Style {
+  let mut style = NORMAL_STYLE;
+  let bar_height = scale_by_dpi(SMALL_BAR_HEIGHT, CURRENT_DEVICE.dpi) as i32;
+  style.size = style.size * header_height as u32 / bar_height.max(1) as u32;
+  style
+}
+
+fn next_toc_index(toc: &[TocEntry]) -> usize {
+  toc
+    .iter()
+    .map(|entry| (entry.index + 1).max(next_toc_index(&entry.children)))
+    .max()
+    .unwrap_or(0)
 }
 
 fn build_pixmap(rect: &Rectangle, doc: &mut dyn Document, location: usize) -> (Pixmap, usize) {
@@ -307,11 +527,12 @@ fn find_cut(
 impl Reader {
   pub fn new(rect: Rectangle, mut info: Info, hub: &Hub, context: &mut Context) -> Option<Reader> {
     let id = ID_FEEDER.next();
-    let settings = &context.settings;
     let path = context.library.home.join(&info.file.path);
 
     open(&path).and_then(|mut doc| {
+      let settings = &context.settings;
       let (width, height) = context.display.dims;
+      let header_height = header_height(&settings.reader.header);
       let font_size = info
         .reader
         .as_ref()
@@ -319,16 +540,17 @@ impl Reader {
         .unwrap_or(settings.reader.font_size);
       let first_location = doc.resolve_location(Location::Exact(0))?;
 
-      doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
+      doc.layout(
+        width,
+        height - header_height as u32,
+        font_size,
+        CURRENT_DEVICE.dpi,
+      );
 
-      let margin_width = info
-        .reader
-        .as_ref()
-        .and_then(|r| r.margin_width)
-        .unwrap_or(settings.reader.margin_width);
+      let margins = page_margins(info.reader.as_ref(), &settings.reader);
 
-      if margin_width != DEFAULT_MARGIN_WIDTH {
-        doc.set_margin_width(margin_width);
+      if margins != PageMargins::uniform(DEFAULT_MARGIN_WIDTH) {
+        doc.set_margins(&margins);
       }
 
       let font_family = info
@@ -338,7 +560,10 @@ impl Reader {
         .unwrap_or(&settings.reader.font_family);
 
       if font_family != DEFAULT_FONT_FAMILY {
-        doc.set_font_family(font_family, &settings.reader.font_path);
+        match family_files(font_family, context) {
+          Ok(files) => doc.set_font_family(&files),
+          Err(e) => eprintln!("Can't load font family {}: {}.", font_family, e),
+        }
       }
 
       let line_height = info
@@ -361,9 +586,34 @@ impl Reader {
         doc.set_text_align(text_align);
       }
 
+      let text_align_override = info
+        .reader
+        .as_ref()
+        .and_then(|r| r.text_align_override)
+        .unwrap_or(settings.reader.text_align_override);
+
+      if text_align_override != TextAlignOverride::Document {
+        doc.set_text_align_override(text_align_override);
+      }
+
+      if info
+        .reader
+        .as_ref()
+        .and_then(|r| r.verse_layout)
+        .unwrap_or(false)
+      {
+        doc.set_verse_layout(true);
+      }
+
+      doc.set_dithering(settings.dithering.images);
+      doc.set_typography(book_typography(&info, settings.reader.typography));
+
       let mut view_port = ViewPort::default();
       let mut contrast = Contrast::default();
       let pages_count = doc.pages_count();
+      let opened_as_new = info.reader.is_none();
+      let skip_front_matter = settings.reader.skip_front_matter;
+      let mut text_start = None;
       let current_page;
 
       // TODO: use get_or_insert_with?
@@ -384,10 +634,18 @@ impl Reader {
           view_port.zoom_mode = zoom_mode;
         }
 
+        if let Some(scroll_mode) = r.scroll_mode.filter(|_| doc.is_reflowable()) {
+          view_port.scroll_mode = scroll_mode;
+        }
+
         if let Some(top_offset) = r.top_offset {
           view_port.top_offset = top_offset;
         }
 
+        if let Some(column) = r.column {
+          view_port.column = column;
+        }
+
         if !doc.is_reflowable() {
           view_port.margin_width = mm_to_px(
             r.screen_margin_width.unwrap_or(0) as f32,
@@ -403,13 +661,50 @@ impl Reader {
           contrast.gray = gray;
         }
       } else {
-        current_page = first_location;
+        if skip_front_matter != SkipFrontMatter::Never {
+          text_start = doc
+            .start_location()
+            .and_then(|loc| doc.resolve_location(loc))
+            .filter(|&loc| loc > first_location);
+        }
 
-        info.reader = Some(ReaderInfo {
+        current_page = match text_start {
+          Some(loc) if skip_front_matter == SkipFrontMatter::Always => loc,
+          _ => first_location,
+        };
+
+        let mut r = ReaderInfo {
           current_page,
           pages_count,
           ..Default::default()
-        });
+        };
+
+        // The scanned books of a directory can share their layout.
+        if !doc.is_reflowable() {
+          let dir = info.file.path.parent().unwrap_or_else(|| Path::new(""));
+          if let Some(layout) = settings
+            .libraries
+            .get(settings.selected_library)
+            .and_then(|library| library.directory_layouts.get(dir))
+          {
+            layout.apply(&mut r);
+            if let Some(zoom_mode) = r.zoom_mode {
+              view_port.zoom_mode = zoom_mode;
+            }
+            view_port.margin_width = mm_to_px(
+              r.screen_margin_width.unwrap_or(0) as f32,
+              CURRENT_DEVICE.dpi,
+            ) as i32;
+            if let Some(exponent) = r.contrast_exponent {
+              contrast.exponent = exponent;
+            }
+            if let Some(gray) = r.contrast_gray {
+              contrast.gray = gray;
+            }
+          }
+        }
+
+        info.reader = Some(r);
       }
 
       let synthetic = doc.has_synthetic_page_numbers();
@@ -419,22 +714,32 @@ impl Reader {
 
       hub.send(Event::Update(UpdateMode::Partial)).ok();
 
-      Some(Reader {
+      let mut reader = Reader {
         id,
         rect,
         children: Vec::new(),
         doc: Arc::new(Mutex::new(doc)),
         cache: BTreeMap::new(),
+        page_dithering: if reflowable {
+          DitherMode::None
+        } else {
+          settings.dithering.pages
+        },
         text: FxHashMap::default(),
         annotations: FxHashMap::default(),
         chunks: Vec::new(),
         focus: None,
         search: None,
         search_direction: LinearDir::Forward,
+        search_scope: SearchScope::Book,
         held_buttons: FxHashSet::default(),
+        rotation_unlocked: None,
         selection: None,
         target_annotation: None,
+        review: None,
+        speech: None,
         history: VecDeque::new(),
+        forward_history: VecDeque::new(),
         state: State::Idle,
         info,
         current_page,
@@ -442,11 +747,52 @@ impl Reader {
         view_port,
         synthetic,
         page_turns: 0,
+        saved_page_turns: 0,
+        saved_state: None,
+        header: String::new(),
+        header_height,
         contrast,
         ephemeral: false,
         reflowable,
         finished: false,
-      })
+        initial_location: if opened_as_new {
+          Some(current_page)
+        } else {
+          None
+        },
+        initial_state: None,
+        auto_finished: false,
+        touch_starts: FxHashMap::default(),
+        touch_duration: None,
+        reading_timer: ReadingTimer::new(current_page, settings.reading_speed.clone()),
+        toc_tree: None,
+        diagnostics: None,
+        file_stamp: file_stamp(&path),
+        bookmark_corner: settings.reader.bookmark_corner,
+      };
+
+      if let Some(loc) = text_start.filter(|_| skip_front_matter == SkipFrontMatter::Ask) {
+        let notif = Notification::new(
+          ViewId::FrontMatterNotif,
+          tr!("Tap to skip the front matter."),
+          context,
+        )
+        .with_action(Event::GoTo(loc));
+        push_notification(
+          &mut reader.children,
+          notif,
+          hub,
+          &mut RenderQueue::new(),
+          context,
+        );
+      }
+
+      if opened_as_new {
+        reader.update_reader_info(context);
+        reader.initial_state = reader.reading_state();
+      }
+
+      Some(reader)
     })
   }
 
@@ -458,10 +804,11 @@ impl Reader {
     context: &mut Context,
   ) -> Reader {
     let id = ID_FEEDER.next();
-    let html = toc_as_html(toc, chap_index);
+    let toc_tree = TocTree::new(toc, chap_index);
+    let html = toc_tree.as_html();
 
     let info = Info {
-      title: "Table of Contents".to_string(),
+      title: tr!("Table of Contents"),
       file: FileInfo {
         path: PathBuf::from(TOC_SCHEME),
         kind: "html".to_string(),
@@ -476,23 +823,10 @@ impl Reader {
     doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
     let pages_count = doc.pages_count();
 
-    let mut current_page = 0;
-
-    if let Some(chap) = chapter_from_index(chap_index, toc) {
-      let link_uri = match chap.location {
-        Location::Uri(ref uri) => format!("@{}", uri),
-        Location::Exact(offset) => format!("@{}", offset),
-        _ => "#".to_string(),
-      };
-      let mut loc = Location::Exact(0);
-      while let Some((links, offset)) = doc.links(loc) {
-        if links.iter().any(|link| link.text == link_uri) {
-          current_page = offset;
-          break;
-        }
-        loc = Location::Next(offset);
-      }
-    }
+    let current_page = toc_tree
+      .current_link()
+      .and_then(|uri| page_of_link(&mut doc, &uri))
+      .unwrap_or(0);
 
     hub.send(Event::Update(UpdateMode::Partial)).ok();
 
@@ -502,16 +836,22 @@ impl Reader {
       children: vec![],
       doc: Arc::new(Mutex::new(Box::new(doc))),
       cache: BTreeMap::new(),
+      page_dithering: DitherMode::None,
       text: FxHashMap::default(),
       annotations: FxHashMap::default(),
       chunks: Vec::new(),
       focus: None,
       search: None,
       search_direction: LinearDir::Forward,
+      search_scope: SearchScope::Book,
       held_buttons: FxHashSet::default(),
+      rotation_unlocked: None,
       selection: None,
       target_annotation: None,
+      review: None,
+      speech: None,
       history: VecDeque::new(),
+      forward_history: VecDeque::new(),
       state: State::Idle,
       info,
       current_page,
@@ -519,10 +859,24 @@ impl Reader {
       view_port: ViewPort::default(),
       synthetic: false,
       page_turns: 0,
+      saved_page_turns: 0,
+      saved_state: None,
+      header: String::new(),
+      header_height: 0,
       contrast: Contrast::default(),
       ephemeral: true,
       reflowable: true,
       finished: false,
+      initial_location: None,
+      initial_state: None,
+      auto_finished: false,
+      touch_starts: FxHashMap::default(),
+      touch_duration: None,
+      reading_timer: ReadingTimer::new(current_page, ReadingSpeed::default()),
+      toc_tree: Some(toc_tree),
+      diagnostics: None,
+      file_stamp: None,
+      bookmark_corner: context.settings.reader.bookmark_corner,
     }
   }
 
@@ -539,6 +893,8 @@ impl Reader {
     };
 
     let mut doc = HtmlDocument::new_from_memory(html);
+    doc.set_dithering(context.settings.dithering.images);
+    doc.set_typography(context.settings.reader.typography);
     let (width, height) = context.display.dims;
     let font_size = context.settings.reader.font_size;
     doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
@@ -553,16 +909,22 @@ impl Reader {
       children: vec![],
       doc: Arc::new(Mutex::new(Box::new(doc))),
       cache: BTreeMap::new(),
+      page_dithering: DitherMode::None,
       text: FxHashMap::default(),
       annotations: FxHashMap::default(),
       chunks: Vec::new(),
       focus: None,
       search: None,
       search_direction: LinearDir::Forward,
+      search_scope: SearchScope::Book,
       held_buttons: FxHashSet::default(),
+      rotation_unlocked: None,
       selection: None,
       target_annotation: None,
+      review: None,
+      speech: None,
       history: VecDeque::new(),
+      forward_history: VecDeque::new(),
       state: State::Idle,
       info,
       current_page: 0,
@@ -570,18 +932,40 @@ impl Reader {
       view_port: ViewPort::default(),
       synthetic: false,
       page_turns: 0,
+      saved_page_turns: 0,
+      saved_state: None,
+      header: String::new(),
+      header_height: 0,
       contrast: Contrast::default(),
       ephemeral: true,
       reflowable: true,
       finished: false,
+      initial_location: None,
+      initial_state: None,
+      auto_finished: false,
+      touch_starts: FxHashMap::default(),
+      touch_duration: None,
+      reading_timer: ReadingTimer::new(0, ReadingSpeed::default()),
+      toc_tree: None,
+      diagnostics: None,
+      file_stamp: None,
+      bookmark_corner: context.settings.reader.bookmark_corner,
     }
   }
 
   fn load_pixmap(&mut self, location: usize) {
     if self.cache.contains_key(&location) {
+      if let Some(diagnostics) = self.diagnostics.as_mut() {
+        diagnostics.record_hit();
+      }
       return;
     }
 
+    let columns = if self.view_port.zoom_mode == ZoomMode::FitToColumn {
+      Some(self.column_layout(location))
+    } else {
+      None
+    };
     let mut doc = self.doc.lock().unwrap();
     let cropping_margin = self
       .info
@@ -592,26 +976,75 @@ impl Reader {
       .unwrap_or_default();
     let dims = doc.dims(location).unwrap();
     let screen_margin_width = self.view_port.margin_width;
+    // In column mode, the widest column fits the width of the screen.
+    let scaling_margin = if let Some(columns) = columns.as_ref() {
+      let width = columns.iter().map(|c| c[1] - c[0]).fold(0.0, f32::max);
+      Margin {
+        left: 0.0,
+        right: 1.0 - width,
+        ..cropping_margin.clone()
+      }
+    } else {
+      cropping_margin.clone()
+    };
     let scale = scaling_factor(
       &self.rect,
-      &cropping_margin,
+      &scaling_margin,
       screen_margin_width,
       dims,
       self.view_port.zoom_mode,
     );
-    if let Some((pixmap, _)) = doc.pixmap(Location::Exact(location), scale) {
-      let frame = rect![
+    let start = Instant::now();
+    if let Some((mut pixmap, _)) = doc.pixmap(Location::Exact(location), scale) {
+      if let Some(diagnostics) = self.diagnostics.as_mut() {
+        diagnostics.record_render(location, start.elapsed());
+      }
+      if let Some(cleanup) = self.info.reader.as_ref().and_then(|r| r.scan_cleanup) {
+        clean_up(&mut pixmap, &cleanup);
+      }
+      dither(&mut pixmap, self.page_dithering);
+      let mut frame = rect![
         (cropping_margin.left * pixmap.width as f32).ceil() as i32,
         (cropping_margin.top * pixmap.height as f32).ceil() as i32,
         ((1.0 - cropping_margin.right) * pixmap.width as f32).floor() as i32,
         ((1.0 - cropping_margin.bottom) * pixmap.height as f32).floor() as i32
       ];
+      // Remove the vertical margins of the pages of reflowable documents in continuous mode,
+      // so that consecutive pages are stacked seamlessly.
+      if self.reflowable && self.view_port.scroll_mode == ScrollMode::Continuous {
+        if let Some((lines, _)) = doc.lines(Location::Exact(location)) {
+          if let (Some(first), Some(last)) = (lines.first(), lines.last()) {
+            let gap = lines
+              .get(1)
+              .map(|second| ((second.rect.min.y - first.rect.max.y) * scale).max(0.0) as i32)
+              .unwrap_or(0);
+            frame.min.y = frame.min.y.max((first.rect.min.y * scale).floor() as i32);
+            frame.max.y = frame
+              .max
+              .y
+              .min((last.rect.max.y * scale).ceil() as i32 + gap);
+          }
+        }
+      }
+      let images = doc
+        .images(Location::Exact(location))
+        .map(|(rects, _)| {
+          rects
+            .into_iter()
+            .map(|r| {
+              let b: Boundary = r.into();
+              (b * scale).to_rect()
+            })
+            .collect()
+        })
+        .unwrap_or_default();
       self.cache.insert(
         location,
         Resource {
           pixmap,
           frame,
           scale,
+          images,
         },
       );
     }
@@ -628,6 +1061,201 @@ impl Reader {
     self.text.insert(location, words);
   }
 
+  // The horizontal extents of the columns of the given page, relative to its width.
+  // The last detected layout is used for the pages without text.
+  fn column_layout(&mut self, location: usize) -> Vec<[f32; 2]> {
+    self.load_text(location);
+    let page_width = {
+      let doc = self.doc.lock().unwrap();
+      doc.dims(location).map_or(0.0, |dims| dims.0)
+    };
+    if let Some(columns) = detect_columns(&self.text[&location], page_width) {
+      if let Some(r) = self.info.reader.as_mut() {
+        r.columns = Some(columns.clone());
+      }
+      columns
+    } else {
+      self
+        .info
+        .reader
+        .as_ref()
+        .and_then(|r| r.columns.clone())
+        .unwrap_or_else(|| vec![[0.0, 1.0]])
+    }
+  }
+
+  // The frame of the given column within the pixmap of the given page.
+  fn column_frame(&mut self, location: usize, column: usize) -> Rectangle {
+    let columns = self.column_layout(location);
+    self.load_pixmap(location);
+    let Resource { pixmap, frame, .. } = &self.cache[&location];
+    let [x0, x1] = columns[column.min(columns.len() - 1)];
+    let width = pixmap.width as f32;
+    rect![
+      ((x0 * width).floor() as i32).max(frame.min.x),
+      frame.min.y,
+      ((x1 * width).ceil() as i32).min(frame.max.x),
+      frame.max.y
+    ]
+  }
+
+  // Moves *y_pos* to the nearest line boundary within the given column frame.
+  fn column_cut(&mut self, location: usize, frame: &Rectangle, y_pos: i32, dir: LinearDir) -> i32 {
+    let scale = self.cache[&location].scale;
+    let mut doc = self.doc.lock().unwrap();
+    doc
+      .lines(Location::Exact(location))
+      .and_then(|(lines, _)| find_cut(frame, y_pos, scale, dir, &lines))
+      .map_or(y_pos, |y| y.max(frame.min.y).min(frame.max.y))
+  }
+
+  // Serves the document on the local network, until the dialog showing its address is closed.
+  fn share(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    let path = context.library.home.join(&self.info.file.path);
+    if context.online && is_drm_free(&path) {
+      match ShareServer::start(&path) {
+        Ok(server) => {
+          let dialog = ShareBookDialog::new(server, self.info.title(), context);
+          rq.add(RenderData::new(
+            dialog.id(),
+            *dialog.rect(),
+            UpdateMode::Gui,
+          ));
+          self.children.push(Box::new(dialog) as Box<dyn View>);
+        },
+        Err(e) => report_error(hub, Severity::Error, "Can't share the book", &e),
+      }
+      return;
+    }
+    let text = if context.online {
+      "This book is protected and can't be shared."
+    } else {
+      "Connect to a Wi-Fi network to share the book."
+    };
+    let notif = Notification::new(ViewId::ShareNotif, tr!(text), context);
+    push_notification(&mut self.children, notif, hub, rq, context);
+  }
+
+  // Looks up the given selection in the dictionaries and adds the result to the phrasebook.
+  fn translate(
+    &mut self,
+    sel: [TextLocation; 2],
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let source = match self.text_excerpt(sel) {
+      Some(text) => text
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_string(),
+      None => return,
+    };
+    let language = self.info.language.clone();
+    let text = if let Some((dictionary, body)) = translate(&source, &language, context) {
+      let phrase = Phrase {
+        target: short_translation(&body),
+        context: self.enclosing_sentence(sel).unwrap_or_default(),
+        title: self.info.title(),
+        author: self.info.author.clone(),
+        source,
+        language,
+        dictionary,
+        ..Default::default()
+      };
+      let text = format!("{} — {}", phrase.source, phrase.target);
+      if let Err(e) = add_phrase(PHRASEBOOK_PATH, phrase) {
+        report_error(hub, Severity::Error, "Can't save the phrasebook", &e);
+      }
+      text
+    } else {
+      tr!("No translation found for {}.", source)
+    };
+    let notif = Notification::new(ViewId::TranslationNotif, text, context);
+    push_notification(&mut self.children, notif, hub, rq, context);
+  }
+
+  // Returns the local document targeted by a link of an HTML document, and the fragment of the link.
+  fn linked_document(&self, uri: &str, context: &Context) -> Option<(Info, Option<String>)> {
+    if !["html", "htm"].contains(&self.info.file.kind.as_str()) {
+      return None;
+    }
+    let uri = uri.trim_start_matches(FILE_SCHEME);
+    if uri.contains("://") {
+      return None;
+    }
+    let mut parts = uri.splitn(2, '#');
+    let file = parts.next().filter(|file| !file.is_empty())?;
+    let fragment = parts.next().map(String::from);
+    let path = context.library.home.join(&self.info.file.path);
+    if path.file_name().map_or(false, |name| name == file) {
+      return None;
+    }
+    let info = context.library.document_info(path.parent()?.join(file))?;
+    Some((info, fragment))
+  }
+
+  // Shows the thumbnail of the given page, taken from the pre-rendered pages when possible.
+  fn update_page_preview(&mut self, location: Option<usize>, rq: &mut RenderQueue) {
+    let index = rlocate::<PagePreview>(self);
+    let location = match location {
+      Some(location) => location,
+      None => {
+        if let Some(index) = index {
+          let rect = *self.child(index).rect();
+          self.children.remove(index);
+          rq.add(RenderData::expose(rect, UpdateMode::Gui));
+        }
+        return;
+      },
+    };
+
+    let location = {
+      let mut doc = self.doc.lock().unwrap();
+      doc.resolve_location(Location::Exact(location))
+    };
+    let location = match location {
+      Some(location) => location,
+      None => return,
+    };
+
+    if index.map_or(false, |index| {
+      self
+        .child(index)
+        .downcast_ref::<PagePreview>()
+        .unwrap()
+        .location()
+        == location
+    }) {
+      return;
+    }
+
+    let max_width = self.rect.width() / 2;
+    let max_height = self.rect.height() / 2;
+    let pixmap = if let Some(resource) = self.cache.get(&location) {
+      scale_down(&resource.pixmap, max_width, max_height)
+    } else {
+      let mut doc = self.doc.lock().unwrap();
+      let scale = doc.dims(location).map_or(1.0, |(width, height)| {
+        (max_width as f32 / width).min(max_height as f32 / height)
+      });
+      match doc.pixmap(Location::Exact(location), scale) {
+        Some((pixmap, _)) => scale_down(&pixmap, max_width, max_height),
+        None => return,
+      }
+    };
+
+    let preview = PagePreview::new(self.rect, location, pixmap);
+    let mut rect = *preview.rect();
+    if let Some(index) = index {
+      rect.absorb(self.child(index).rect());
+      self.children[index] = Box::new(preview) as Box<dyn View>;
+      rq.add(RenderData::expose(rect, UpdateMode::Fast));
+    } else {
+      rq.add(RenderData::new(preview.id(), rect, UpdateMode::Fast));
+      self.children.push(Box::new(preview) as Box<dyn View>);
+    }
+  }
+
   fn go_to_page(
     &mut self,
     location: usize,
@@ -647,6 +1275,7 @@ impl Reader {
         if self.history.len() > HISTORY_SIZE {
           self.history.pop_front();
         }
+        self.forward_history.clear();
       }
 
       if let Some(ref mut s) = self.search {
@@ -654,6 +1283,7 @@ impl Reader {
       }
 
       self.view_port.top_offset = 0;
+      self.view_port.column = 0;
       self.current_page = location;
       self.update(None, hub, rq, context);
       self.update_bottom_bar(rq);
@@ -769,15 +1399,84 @@ impl Reader {
 
   fn go_to_last_page(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
     if let Some(location) = self.history.pop_back() {
+      self.forward_history.push_back(self.current_page);
+      self.go_to_page(location, false, hub, rq, context);
+    }
+  }
+
+  fn go_to_next_page_in_history(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+    if let Some(location) = self.forward_history.pop_back() {
+      self.history.push_back(self.current_page);
       self.go_to_page(location, false, hub, rq, context);
     }
   }
 
   fn page_scroll(&mut self, delta_y: i32, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-    if delta_y == 0 || self.view_port.zoom_mode == ZoomMode::FitToPage || self.cache.is_empty() {
+    if delta_y == 0 || self.view_port.layout() != ZoomMode::FitToWidth || self.cache.is_empty() {
+      return;
+    }
+
+    let current_page = self.current_page;
+    let top_offset = self.view_port.top_offset;
+    let max_step = self.page_rect().height() as i32;
+    let mut remaining = delta_y;
+    let mut steps = 0;
+
+    while remaining != 0 {
+      let step = remaining.signum() * remaining.abs().min(max_step);
+
+      // Kinetic scrolls might go beyond the neighbors that are already loaded.
+      if steps > 0 {
+        let neighbor = {
+          let mut doc = self.doc.lock().unwrap();
+          let loc = if step < 0 {
+            Location::Next(self.current_page)
+          } else {
+            Location::Previous(self.current_page)
+          };
+          doc.resolve_location(loc)
+        };
+        if let Some(location) = neighbor {
+          self.load_pixmap(location);
+        }
+      }
+
+      if !self.shift_view_port(step) {
+        break;
+      }
+
+      remaining -= step;
+      steps += 1;
+    }
+
+    let location = self.current_page;
+    let location_changed = location != current_page;
+    if !location_changed && self.view_port.top_offset == top_offset {
       return;
     }
 
+    // Reflowable documents are scrolled with a fast waveform.
+    let update_mode = if self.reflowable && !self.full_refresh_due(context) {
+      Some(UpdateMode::Fast)
+    } else {
+      None
+    };
+    self.update(update_mode, hub, rq, context);
+
+    if location_changed {
+      if let Some(ref mut s) = self.search {
+        s.current_page = s.highlights.range(..=location).count().saturating_sub(1);
+      }
+      self.update_bottom_bar(rq);
+      if self.search.is_some() {
+        self.update_results_bar(rq);
+      }
+    }
+  }
+
+  // Moves the view port by *delta_y* pixels, within the current page and its neighbors.
+  // Returns whether the view port has moved.
+  fn shift_view_port(&mut self, delta_y: i32) -> bool {
     let mut next_top_offset = self.view_port.top_offset - delta_y;
     let mut location = self.current_page;
     let max_top_offset = self.cache[&location].frame.height().saturating_sub(1) as i32;
@@ -786,7 +1485,7 @@ impl Reader {
       let mut doc = self.doc.lock().unwrap();
       if let Some(previous_location) = doc.resolve_location(Location::Previous(location)) {
         if !self.cache.contains_key(&previous_location) {
-          return;
+          return false;
         }
         location = previous_location;
         let frame = self.cache[&location].frame;
@@ -798,7 +1497,7 @@ impl Reader {
       let mut doc = self.doc.lock().unwrap();
       if let Some(next_location) = doc.resolve_location(Location::Next(location)) {
         if !self.cache.contains_key(&next_location) {
-          return;
+          return false;
         }
         location = next_location;
         let frame = self.cache[&location].frame;
@@ -826,24 +1525,13 @@ impl Reader {
       }
     }
 
-    let location_changed = location != self.current_page;
-    if !location_changed && next_top_offset == self.view_port.top_offset {
-      return;
+    if location == self.current_page && next_top_offset == self.view_port.top_offset {
+      return false;
     }
 
     self.view_port.top_offset = next_top_offset;
     self.current_page = location;
-    self.update(None, hub, rq, context);
-
-    if location_changed {
-      if let Some(ref mut s) = self.search {
-        s.current_page = s.highlights.range(..=location).count().saturating_sub(1);
-      }
-      self.update_bottom_bar(rq);
-      if self.search.is_some() {
-        self.update_results_bar(rq);
-      }
-    }
+    true
   }
 
   fn go_to_neighbor(
@@ -857,17 +1545,21 @@ impl Reader {
       return;
     }
 
+    context.emit_feedback(FeedbackKind::PageTurn);
+
     let current_page = self.current_page;
     let top_offset = self.view_port.top_offset;
+    let column = self.view_port.column;
 
     let loc = {
       let neighloc = if dir == CycleDir::Previous {
-        match self.view_port.zoom_mode {
+        match self.view_port.layout() {
           ZoomMode::FitToPage => Location::Previous(current_page),
           ZoomMode::FitToWidth => {
             let first_chunk = self.chunks.first().cloned().unwrap();
             let mut location = first_chunk.location;
-            let available_height = self.rect.height() as i32 - 2 * self.view_port.margin_width;
+            let available_height =
+              self.page_rect().height() as i32 - 2 * self.view_port.margin_width;
             let mut height = 0;
 
             loop {
@@ -910,9 +1602,45 @@ impl Reader {
             self.view_port.top_offset = next_top_offset;
             Location::Exact(location)
           },
+          ZoomMode::FitToColumn => {
+            let available_height =
+              self.page_rect().height() as i32 - 2 * self.view_port.margin_width;
+            let first_chunk = self.chunks.first().cloned().unwrap();
+            let mut location = first_chunk.location;
+            self.load_pixmap(location);
+            let previous = if first_chunk.frame.min.y > self.cache[&location].frame.min.y {
+              // Pan up within the current column.
+              Some((column, first_chunk.frame.min.y))
+            } else if column > 0 {
+              Some((column - 1, self.cache[&location].frame.max.y))
+            } else {
+              let previous_location = {
+                let mut doc = self.doc.lock().unwrap();
+                doc.resolve_location(Location::Previous(location))
+              };
+              previous_location.map(|previous_location| {
+                location = previous_location;
+                self.load_pixmap(location);
+                let columns_count = self.column_layout(location).len();
+                (columns_count - 1, self.cache[&location].frame.max.y)
+              })
+            };
+            if let Some((column, bottom)) = previous {
+              let frame = self.column_frame(location, column);
+              let mut y_pos = (bottom - available_height).max(frame.min.y);
+              if y_pos > frame.min.y {
+                y_pos = self.column_cut(location, &frame, y_pos, LinearDir::Forward);
+              }
+              self.view_port.column = column;
+              self.view_port.top_offset = y_pos - frame.min.y;
+              Location::Exact(location)
+            } else {
+              Location::Previous(location)
+            }
+          },
         }
       } else {
-        match self.view_port.zoom_mode {
+        match self.view_port.layout() {
           ZoomMode::FitToPage => Location::Next(current_page),
           ZoomMode::FitToWidth => {
             let &RenderChunk {
@@ -930,13 +1658,37 @@ impl Reader {
               Location::Exact(location)
             }
           },
+          ZoomMode::FitToColumn => {
+            let &RenderChunk {
+              location, frame, ..
+            } = self.chunks.last().unwrap();
+            self.load_pixmap(location);
+            let columns_count = self.column_layout(location).len();
+            let pixmap_frame = self.cache[&location].frame;
+            if frame.max.y < pixmap_frame.max.y {
+              self.view_port.top_offset = frame.max.y - pixmap_frame.min.y;
+              Location::Exact(location)
+            } else if column + 1 < columns_count {
+              self.view_port.column = column + 1;
+              self.view_port.top_offset = 0;
+              Location::Exact(location)
+            } else {
+              self.view_port.column = 0;
+              self.view_port.top_offset = 0;
+              Location::Next(location)
+            }
+          },
         }
       };
       let mut doc = self.doc.lock().unwrap();
       doc.resolve_location(neighloc)
     };
     match loc {
-      Some(location) if location != current_page || self.view_port.top_offset != top_offset => {
+      Some(location)
+        if location != current_page
+          || self.view_port.top_offset != top_offset
+          || self.view_port.column != column =>
+      {
         if let Some(ref mut s) = self.search {
           s.current_page = s.highlights.range(..=location).count().saturating_sub(1);
         }
@@ -950,6 +1702,12 @@ impl Reader {
         }
       },
       _ => match dir {
+        // The end of an extra content isn't the end of the book.
+        CycleDir::Next if current_page >= self.pages_count => {
+          let notif = Notification::new(ViewId::BoundaryNotif, tr!("No next page."), context)
+            .with_priority(NotificationPriority::Low);
+          push_notification(&mut self.children, notif, hub, rq, context);
+        },
         CycleDir::Next => {
           self.finished = true;
           let action = if self.ephemeral {
@@ -959,14 +1717,9 @@ impl Reader {
           };
           match action {
             FinishedAction::Notify => {
-              let notif = Notification::new(
-                ViewId::BoundaryNotif,
-                "No next page.".to_string(),
-                hub,
-                rq,
-                context,
-              );
-              self.children.push(Box::new(notif) as Box<dyn View>);
+              let notif = Notification::new(ViewId::BoundaryNotif, tr!("No next page."), context)
+                .with_priority(NotificationPriority::Low);
+              push_notification(&mut self.children, notif, hub, rq, context);
             },
             FinishedAction::Close => {
               self.quit(context);
@@ -975,14 +1728,9 @@ impl Reader {
           }
         },
         CycleDir::Previous => {
-          let notif = Notification::new(
-            ViewId::BoundaryNotif,
-            "No previous page.".to_string(),
-            hub,
-            rq,
-            context,
-          );
-          self.children.push(Box::new(notif) as Box<dyn View>);
+          let notif = Notification::new(ViewId::BoundaryNotif, tr!("No previous page."), context)
+            .with_priority(NotificationPriority::Low);
+          push_notification(&mut self.children, notif, hub, rq, context);
         },
       },
     }
@@ -1004,6 +1752,7 @@ impl Reader {
     }
     if let Some(location) = loc {
       self.view_port.top_offset = 0;
+      self.view_port.column = 0;
       self.current_page = location;
       self.update_results_bar(rq);
       self.update_bottom_bar(rq);
@@ -1035,6 +1784,7 @@ impl Reader {
         s.current_page = s.highlights.range(..=location).count().saturating_sub(1);
       }
       self.view_port.top_offset = 0;
+      self.view_port.column = 0;
       self.current_page = location;
       self.update_results_bar(rq);
       self.update_bottom_bar(rq);
@@ -1046,13 +1796,7 @@ impl Reader {
     if let Some(index) = locate::<BottomBar>(self) {
       let current_page = self.current_page;
       let mut doc = self.doc.lock().unwrap();
-      let chapter = self
-        .toc()
-        .or_else(|| doc.toc())
-        .as_ref()
-        .and_then(|toc| doc.chapter(current_page, toc))
-        .map(|c| c.title.clone())
-        .unwrap_or_default();
+      let chapter = self.chapter_label(doc.as_mut());
       let bottom_bar = self.children[index]
         .as_mut()
         .downcast_mut::<BottomBar>()
@@ -1107,26 +1851,18 @@ impl Reader {
         tool_bar.update_contrast_exponent_slider(self.contrast.exponent, rq);
         tool_bar.update_contrast_gray_slider(self.contrast.gray, rq);
       }
-      let reflowable = self.reflowable;
-      let margin_width = self
-        .info
-        .reader
-        .as_ref()
-        .and_then(|r| {
-          if reflowable {
-            r.margin_width
-          } else {
-            r.screen_margin_width
-          }
-        })
-        .unwrap_or_else(|| {
-          if reflowable {
-            settings.reader.margin_width
-          } else {
-            0
-          }
-        });
-      tool_bar.update_margin_width(margin_width, rq);
+      if self.reflowable {
+        let margins = page_margins(self.info.reader.as_ref(), &settings.reader);
+        tool_bar.update_margins(&margins, rq);
+      } else {
+        let margin_width = self
+          .info
+          .reader
+          .as_ref()
+          .and_then(|r| r.screen_margin_width)
+          .unwrap_or(0);
+        tool_bar.update_margin_width(margin_width, rq);
+      }
     }
   }
 
@@ -1177,6 +1913,113 @@ impl Reader {
     }
   }
 
+  // Accounts for the time spent on the previous location when moving to the next one.
+  fn record_reading_speed(&mut self) {
+    let previous = self.reading_timer.location();
+    if previous == self.current_page {
+      return;
+    }
+    let next = self
+      .doc
+      .lock()
+      .unwrap()
+      .resolve_location(Location::Next(previous));
+    let words = self.text.get(&previous).map_or(0, Vec::len);
+    if let Some(sample) = self.reading_timer.advance(self.current_page, next, words) {
+      if let Some(ref mut r) = self.info.reader {
+        r.reading_speed
+          .get_or_insert_with(ReadingSpeed::default)
+          .add(&sample);
+      }
+    }
+  }
+
+  fn chapter_title(&self, doc: &mut dyn Document, toc: Option<&[TocEntry]>) -> String {
+    toc
+      .and_then(|toc| doc.chapter(self.current_page, toc))
+      .map(|c| c.title.clone())
+      .unwrap_or_default()
+  }
+
+  // The area of the screen where the pages are drawn.
+  fn page_rect(&self) -> Rectangle {
+    rect![
+      self.rect.min.x,
+      self.rect.min.y + self.header_height,
+      self.rect.max.x,
+      self.rect.max.y
+    ]
+  }
+
+  fn update_header(&mut self, context: &Context) {
+    if self.header_height == 0 {
+      return;
+    }
+    let settings = &context.settings.reader.header;
+    let mut parts = Vec::new();
+    if settings.show_title {
+      parts.push(self.info.title());
+    }
+    if settings.show_chapter {
+      let mut doc = self.doc.lock().unwrap();
+      let toc = self.toc().or_else(|| doc.toc());
+      parts.push(self.chapter_title(doc.as_mut(), toc.as_deref()));
+    }
+    parts.retain(|p| !p.is_empty());
+    parts.dedup();
+    self.header = parts.join(" — ");
+  }
+
+  // The title of the current chapter, followed by the estimated time left in the chapter and
+  // in the book.
+  fn chapter_label(&self, doc: &mut dyn Document) -> String {
+    let toc = self.toc().or_else(|| doc.toc());
+    let title = self.chapter_title(doc, toc.as_deref());
+    let speed = match self
+      .info
+      .reader
+      .as_ref()
+      .and_then(|r| r.reading_speed.as_ref())
+    {
+      Some(speed) => speed,
+      None => return title,
+    };
+    let fallback = Some(&self.reading_timer.global);
+    let book_left = speed.time_left(self.pages_count.saturating_sub(self.current_page), fallback);
+    let chapter_end = toc
+      .as_ref()
+      .and_then(|toc| doc.chapter_relative(self.current_page, CycleDir::Next, toc))
+      .and_then(|chap| doc.resolve_location(chap.location.clone()))
+      .unwrap_or(self.pages_count);
+    let chapter_left = speed.time_left(chapter_end.saturating_sub(self.current_page), fallback);
+
+    match (chapter_left, book_left) {
+      (Some(chapter_left), Some(book_left)) if title.is_empty() => format!(
+        "{} · {}",
+        format_duration(chapter_left),
+        format_duration(book_left)
+      ),
+      (Some(chapter_left), Some(book_left)) => format!(
+        "{} · {} · {}",
+        title,
+        format_duration(chapter_left),
+        format_duration(book_left)
+      ),
+      _ => title,
+    }
+  }
+
+  // Whether the next page turn should trigger a full refresh.
+  fn full_refresh_due(&self, context: &Context) -> bool {
+    let refresh_rate = if context.fb.inverted() {
+      context.settings.reader.refresh_rate.inverted
+    } else {
+      context.settings.reader.refresh_rate.regular
+    };
+    let refresh_rate = refresh_rate as usize * context.power_profile.refresh_factor() as usize;
+    refresh_rate != 0 && (self.page_turns + 1) % refresh_rate == 0
+  }
+
   fn update(
     &mut self,
     update_mode: Option<UpdateMode>,
@@ -1184,40 +2027,56 @@ impl Reader {
     rq: &mut RenderQueue,
     context: &Context,
   ) {
-    self.page_turns += 1;
     let update_mode = update_mode.unwrap_or_else(|| {
-      let refresh_rate = if context.fb.inverted() {
-        context.settings.reader.refresh_rate.inverted
+      if self.full_refresh_due(context) {
+        UpdateMode::Full
       } else {
-        context.settings.reader.refresh_rate.regular
-      };
-      if refresh_rate == 0 || self.page_turns % (refresh_rate as usize) != 0 {
         UpdateMode::Partial
-      } else {
-        UpdateMode::Full
       }
     });
+    if self.check_file(context) {
+      hub.send(Event::DocumentChanged).ok();
+    }
+    self.page_turns += 1;
+    if self.initial_location != Some(self.current_page) {
+      self.initial_location = None;
+    }
+    self.record_reading_speed();
+    if !self.ephemeral {
+      context.record_usage(Usage::Page);
+    }
+    if let Some(diagnostics) = self.diagnostics.as_mut() {
+      diagnostics.record_update(update_mode);
+    }
 
+    let page_turns = context.settings.reader.auto_save.page_turns;
+    if page_turns > 0 && self.page_turns - self.saved_page_turns >= page_turns {
+      self.saved_page_turns = self.page_turns;
+      hub.send(Event::AutoSave).ok();
+    }
+
+    self.update_header(context);
     self.chunks.clear();
     let mut location = self.current_page;
     let smw = self.view_port.margin_width;
+    let page_rect = self.page_rect();
 
-    match self.view_port.zoom_mode {
+    match self.view_port.layout() {
       ZoomMode::FitToPage => {
         self.load_pixmap(location);
         self.load_text(location);
         let Resource { frame, scale, .. } = self.cache[&location];
-        let dx = smw + ((self.rect.width() - frame.width()) as i32 - 2 * smw) / 2;
-        let dy = smw + ((self.rect.height() - frame.height()) as i32 - 2 * smw) / 2;
+        let dx = smw + ((page_rect.width() - frame.width()) as i32 - 2 * smw) / 2;
+        let dy = smw + ((page_rect.height() - frame.height()) as i32 - 2 * smw) / 2;
         self.chunks.push(RenderChunk {
           frame,
           location,
-          position: pt!(dx, dy),
+          position: pt!(dx, page_rect.min.y + dy),
           scale,
         });
       },
       ZoomMode::FitToWidth => {
-        let available_height = self.rect.height() as i32 - 2 * smw;
+        let available_height = page_rect.height() as i32 - 2 * smw;
         let mut height = 0;
         while height < available_height {
           self.load_pixmap(location);
@@ -1226,9 +2085,9 @@ impl Reader {
             mut frame, scale, ..
           } = self.cache[&location];
           if location == self.current_page {
-            frame.min.y += self.view_port.top_offset;
+            frame.min.y = (frame.min.y + self.view_port.top_offset).min(frame.max.y - 1);
           }
-          let position = pt!(smw, smw + height);
+          let position = pt!(smw, page_rect.min.y + smw + height);
           self.chunks.push(RenderChunk {
             frame,
             location,
@@ -1269,13 +2128,45 @@ impl Reader {
           }
         }
       },
+      ZoomMode::FitToColumn => {
+        let available_height = page_rect.height() as i32 - 2 * smw;
+        let columns_count = self.column_layout(location).len();
+        let column = self.view_port.column.min(columns_count - 1);
+        let column_frame = self.column_frame(location, column);
+        let scale = self.cache[&location].scale;
+        let mut frame = column_frame;
+        frame.min.y = (frame.min.y + self.view_port.top_offset).min(frame.max.y - 1);
+        frame.max.y = frame.max.y.min(frame.min.y + available_height);
+        if frame.max.y < column_frame.max.y {
+          let y_pos = self.column_cut(location, &column_frame, frame.max.y, LinearDir::Backward);
+          if y_pos > frame.min.y {
+            frame.max.y = y_pos;
+          }
+        }
+        let dx = smw + (page_rect.width() as i32 - frame.width() as i32 - 2 * smw) / 2;
+        // Center the columns that fit entirely on the screen.
+        let dy = if frame.min.y == column_frame.min.y && frame.max.y == column_frame.max.y {
+          smw + (available_height - frame.height() as i32) / 2
+        } else {
+          smw
+        };
+        self.view_port.column = column;
+        self.chunks.push(RenderChunk {
+          frame,
+          location,
+          position: pt!(dx, page_rect.min.y + dy),
+          scale,
+        });
+      },
     }
 
     rq.add(RenderData::new(self.id, self.rect, update_mode));
     let first_location = self.chunks.first().map(|c| c.location).unwrap();
     let last_location = self.chunks.last().map(|c| c.location).unwrap();
 
-    while self.cache.len() > 3 {
+    let prefetch = context.power_profile.prefetch_pages();
+
+    while self.cache.len() > (2 * prefetch + 1).max(3) {
       let left_count = self.cache.range(..first_location).count();
       let right_count = self.cache.range(last_location + 1..).count();
       let extremum = if left_count >= right_count {
@@ -1287,13 +2178,24 @@ impl Reader {
     }
 
     self.update_annotations();
+    self.update_status(last_location, hub, context);
+
+    if prefetch == 0 {
+      return;
+    }
 
     let doc2 = self.doc.clone();
     let hub2 = hub.clone();
     thread::spawn(move || {
       let mut doc = doc2.lock().unwrap();
-      if let Some(next_location) = doc.resolve_location(Location::Next(last_location)) {
-        hub2.send(Event::LoadPixmap(next_location)).ok();
+      let mut location = last_location;
+      for _ in 0..prefetch {
+        if let Some(next_location) = doc.resolve_location(Location::Next(location)) {
+          hub2.send(Event::LoadPixmap(next_location)).ok();
+          location = next_location;
+        } else {
+          break;
+        }
       }
     });
     let doc3 = self.doc.clone();
@@ -1306,13 +2208,74 @@ impl Reader {
     });
   }
 
+  fn update_status(&mut self, last_location: usize, hub: &Hub, context: &Context) {
+    let settings = &context.settings.reader.auto_status;
+
+    if self.ephemeral || self.finished || !settings.finished_on_end {
+      return;
+    }
+
+    let at_end = {
+      let mut doc = self.doc.lock().unwrap();
+      doc
+        .resolve_location(Location::Next(last_location))
+        .is_none()
+    };
+    let progress = 100.0 * last_location as f32 / self.pages_count as f32;
+    let past_threshold = at_end || progress >= settings.finished_threshold;
+
+    // After an undo, the book can only be finished again once it went back below the threshold.
+    if self.auto_finished {
+      self.auto_finished = past_threshold;
+      return;
+    }
+
+    if past_threshold {
+      self.finished = true;
+      self.auto_finished = true;
+      hub.send(Event::Finished).ok();
+    }
+  }
+
+  // The first location of the current chapter and the location that follows it.
+  fn chapter_bounds(&self) -> (usize, usize) {
+    let mut doc = self.doc.lock().unwrap();
+    let pages_count = doc.pages_count();
+    let toc = match self.toc().or_else(|| doc.toc()) {
+      Some(toc) => toc,
+      None => return (0, pages_count),
+    };
+    let start = doc
+      .chapter(self.current_page, &toc)
+      .and_then(|chap| doc.resolve_location(chap.location.clone()))
+      .unwrap_or(0);
+    // The next chapter can begin on the current page.
+    let end = doc
+      .chapter_relative(self.current_page, CycleDir::Next, &toc)
+      .and_then(|chap| doc.resolve_location(chap.location.clone()))
+      .unwrap_or(pages_count)
+      .max(self.current_page + 1);
+    (start.min(self.current_page), end)
+  }
+
+  // The first location of the searched pages and the location that follows them.
+  fn search_bounds(&self) -> (usize, usize) {
+    match self.search_scope {
+      SearchScope::Book => (0, self.doc.lock().unwrap().pages_count()),
+      SearchScope::Onward => (self.current_page, self.doc.lock().unwrap().pages_count()),
+      SearchScope::Chapter => self.chapter_bounds(),
+    }
+  }
+
   fn search(&mut self, text: &str, query: Regex, hub: &Hub, rq: &mut RenderQueue) {
     let s = Search {
       query: text.to_string(),
+      scope: self.search_scope,
       ..Default::default()
     };
 
     let hub2 = hub.clone();
+    let (first, end) = self.search_bounds();
     let doc2 = Arc::clone(&self.doc);
     let running = Arc::clone(&s.running);
     let current_page = self.current_page;
@@ -1321,6 +2284,7 @@ impl Reader {
     thread::spawn(move || {
       let mut loc = Location::Exact(current_page);
       let mut started = false;
+      let mut wraps = 0;
 
       loop {
         if !running.load(AtomicOrdering::Relaxed) {
@@ -1331,7 +2295,10 @@ impl Reader {
         let mut text = String::new();
         let mut rects = BTreeMap::new();
 
-        if let Some(location) = doc.resolve_location(loc) {
+        if let Some(location) = doc
+          .resolve_location(loc)
+          .filter(|location| (first..end).contains(location))
+        {
           if location == current_page && started {
             break;
           }
@@ -1363,9 +2330,13 @@ impl Reader {
             LinearDir::Backward => Location::Previous(location),
           };
         } else {
+          wraps += 1;
+          if wraps > 2 {
+            break;
+          }
           loc = match search_direction {
-            LinearDir::Forward => Location::Exact(0),
-            LinearDir::Backward => Location::Exact(doc.pages_count() - 1),
+            LinearDir::Forward => Location::Exact(first),
+            LinearDir::Backward => Location::Exact(end - 1),
           };
         }
 
@@ -1383,6 +2354,269 @@ impl Reader {
     self.search = Some(s);
   }
 
+  fn perform_tap_action(
+    &mut self,
+    action: TapAction,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    match action {
+      TapAction::PreviousPage | TapAction::NextPage => {
+        let dir = if action == TapAction::PreviousPage {
+          CycleDir::Previous
+        } else {
+          CycleDir::Next
+        };
+        if self.search.is_none() {
+          self.go_to_neighbor(dir, hub, rq, context);
+        } else {
+          self.go_to_results_neighbor(dir, hub, rq, context);
+        }
+      },
+      TapAction::PreviousChapter => self.go_to_chapter(CycleDir::Previous, hub, rq, context),
+      TapAction::NextChapter => self.go_to_chapter(CycleDir::Next, hub, rq, context),
+      TapAction::LastLocation => self.go_to_last_page(hub, rq, context),
+      TapAction::ToggleBars => self.toggle_bars(None, hub, rq, context),
+      TapAction::ToggleBookmark => self.toggle_bookmark(rq),
+      TapAction::TableOfContents => {
+        if self.ephemeral && self.info.file.path == PathBuf::from(TOC_SCHEME) {
+          self.quit(context);
+          hub.send(Event::Back).ok();
+        } else {
+          hub.send(Event::Show(ViewId::TableOfContents)).ok();
+        }
+      },
+      TapAction::GoToPage => {
+        hub.send(Event::Toggle(ViewId::GoToPage)).ok();
+      },
+      TapAction::NextZoomPreset => self.next_zoom_preset(hub, rq, context),
+      TapAction::LastZoomMode => {
+        let last_zoom_mode = self.info.reader.as_ref().and_then(|r| r.last_zoom_mode);
+        if let Some(zoom_mode) = last_zoom_mode {
+          self.set_zoom_mode(zoom_mode, hub, rq, context);
+        }
+      },
+      TapAction::None => (),
+    }
+  }
+
+  fn book_rotation_lock(&self) -> Option<RotationLock> {
+    self.info.reader.as_ref().and_then(|r| r.rotation_lock)
+  }
+
+  // The rotation lock that applies while reading this book.
+  pub fn rotation_lock(&self, default: Option<RotationLock>) -> Option<RotationLock> {
+    if self
+      .rotation_unlocked
+      .map_or(false, |t| t.elapsed() < ROTATION_UNLOCK_DURATION)
+    {
+      return None;
+    }
+    self.book_rotation_lock().or(default)
+  }
+
+  // Lets the accelerometer rotate the screen for a little while.
+  fn unlock_rotation(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    if self.rotation_lock(context.settings.rotation_lock).is_none() {
+      return;
+    }
+    self.rotation_unlocked = Some(Instant::now());
+    let msg = tr!(
+      "Orientation unlocked for {} seconds.",
+      ROTATION_UNLOCK_DURATION.as_secs()
+    );
+    let notif = Notification::new(ViewId::MessageNotif, msg, context)
+      .with_priority(NotificationPriority::Low);
+    push_notification(&mut self.children, notif, hub, rq, context);
+  }
+
+  // Writes the text of the document next to it, in a background thread.
+  fn export_text(&mut self, format: TextFormat, hub: &Hub, context: &Context) {
+    let path = context
+      .library
+      .home
+      .join(&self.info.file.path)
+      .with_extension(format.extension());
+    let hub2 = hub.clone();
+    let doc2 = Arc::clone(&self.doc);
+
+    thread::spawn(move || {
+      let text = {
+        let mut last_step = None;
+        export_text(&doc2, format, |p| {
+          let step = (p * PROGRESS_STEPS as f32) as usize;
+          if last_step != Some(step) {
+            last_step = Some(step);
+            hub2
+              .send(Event::Progress(
+                ViewId::ExportProgress,
+                tr!("Exporting text"),
+                Some(p),
+              ))
+              .ok();
+          }
+        })
+      };
+      hub2
+        .send(Event::Progress(
+          ViewId::ExportProgress,
+          String::new(),
+          Some(1.0),
+        ))
+        .ok();
+      let msg = match fs::write(&path, text) {
+        Ok(()) => tr!(
+          "Exported the text to {}.",
+          path.file_name().unwrap_or_default().to_string_lossy()
+        ),
+        Err(e) => tr!("Can't export the text: {}.", e),
+      };
+      hub2.send(Event::Notify(msg)).ok();
+    });
+  }
+
+  // The label of the given page, derived from the page names when possible.
+  fn page_label(&self, location: usize) -> String {
+    let named = self.info.reader.as_ref().and_then(|r| {
+      r.page_names
+        .range(..=location)
+        .next_back()
+        .and_then(|(index, name)| {
+          if *index == location {
+            Some(name.clone())
+          } else {
+            u32::from_str_radix(name, 10)
+              .ok()
+              .map(|n| (n as usize + location - index).to_string())
+          }
+        })
+    });
+    named.unwrap_or_else(|| {
+      if self.synthetic {
+        format!("{:.1}", location as f64 / BYTES_PER_PAGE)
+      } else {
+        (location + 1).to_string()
+      }
+    })
+  }
+
+  // Appends a citation of the selected text to the citations file of the library.
+  fn cite_selection(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+    let sel = match self.selection.take() {
+      Some(sel) => sel,
+      None => return,
+    };
+    if let Some(rect) = self.text_rect([sel.start, sel.end]) {
+      rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+    }
+    let quote = self.text_excerpt([sel.start, sel.end]).unwrap_or_default();
+    let page = self.page_label(sel.start.location());
+    let style = context.settings.reader.citation_style;
+    let citation = cite(&self.info, &page, quote.trim(), style);
+    let path = context
+      .library
+      .home
+      .join(CITATIONS_STEM)
+      .with_extension(style.extension());
+    let msg = match OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&path)
+      .and_then(|mut file| write!(file, "{}\n\n", citation.trim_end()))
+    {
+      Ok(()) => tr!(
+        "Added the citation to {}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+      ),
+      Err(e) => tr!("Can't add the citation: {}.", e),
+    };
+    hub.send(Event::Notify(msg)).ok();
+  }
+
+  // Writes the annotations as BibTeX entries next to the document.
+  fn export_annotations_bibtex(&self, hub: &Hub, context: &Context) {
+    let notes = self
+      .info
+      .reader
+      .as_ref()
+      .map(|r| {
+        r.annotations
+          .iter()
+          .map(|annot| {
+            (
+              self.page_label(annot.selection[0].location()),
+              annot.text.clone(),
+              annot.note.clone(),
+            )
+          })
+          .collect::<Vec<(String, String, String)>>()
+      })
+      .unwrap_or_default();
+    let path = context
+      .library
+      .home
+      .join(&self.info.file.path)
+      .with_extension(CitationStyle::Bibtex.extension());
+    let msg = match fs::write(&path, annotations_as_bibtex(&self.info, &notes)) {
+      Ok(()) => tr!(
+        "Exported {} annotations to {}.",
+        notes.len(),
+        path.file_name().unwrap_or_default().to_string_lossy()
+      ),
+      Err(e) => tr!("Can't export the annotations: {}.", e),
+    };
+    hub.send(Event::Notify(msg)).ok();
+  }
+
+  // Writes the highlights and the notes, in reading order, to a standalone file.
+  fn export_annotations(&self, format: AnnotationsFormat, hub: &Hub, context: &Context) {
+    let mut annotations = self
+      .info
+      .reader
+      .as_ref()
+      .map(|r| r.annotations.iter().collect::<Vec<&Annotation>>())
+      .unwrap_or_default();
+    annotations.sort_by(|a, b| a.selection[0].cmp(&b.selection[0]));
+    let annotations = annotations
+      .into_iter()
+      .map(|annot| ExportedAnnotation {
+        page: self.page_label(annot.selection[0].location()),
+        text: annot.text.clone(),
+        note: annot.note.clone(),
+        modified: annot.modified,
+      })
+      .collect::<Vec<ExportedAnnotation>>();
+
+    let home = &context.library.home;
+    let book_path = home.join(&self.info.file.path);
+    let directory = match context.settings.reader.annotations_directory {
+      Some(ref dir) => home.join(dir),
+      None => book_path.parent().unwrap_or(home).to_path_buf(),
+    };
+    let stem = book_path.file_stem().unwrap_or_default().to_string_lossy();
+    let path = directory.join(format!("{}.{}", stem, format.suffix()));
+
+    let text = match format {
+      AnnotationsFormat::Markdown => Ok(annotations_as_markdown(&self.info, &annotations)),
+      AnnotationsFormat::Json => {
+        annotations_as_json(&self.info, &annotations).map_err(io::Error::from)
+      },
+    };
+    let msg = match text.and_then(|text| {
+      fs::create_dir_all(&directory)?;
+      fs::write(&path, text)
+    }) {
+      Ok(()) => tr!(
+        "Exported {} annotations to {}.",
+        annotations.len(),
+        path.file_name().unwrap_or_default().to_string_lossy()
+      ),
+      Err(e) => tr!("Can't export the annotations: {}.", e),
+    };
+    hub.send(Event::Notify(msg)).ok();
+  }
+
   fn toggle_keyboard(
     &mut self,
     enable: bool,
@@ -1597,6 +2831,7 @@ impl Reader {
           s.highlights.len(),
           s.results_count,
           !s.running.load(AtomicOrdering::Relaxed),
+          s.scope,
         );
         self
           .children
@@ -1751,17 +2986,17 @@ impl Reader {
       let mut doc = self.doc.lock().unwrap();
       let mut index = 0;
 
-      let top_bar = TopBar::new(
-        rect![
-          self.rect.min.x,
-          self.rect.min.y,
-          self.rect.max.x,
-          self.rect.min.y + small_height - small_thickness
-        ],
-        Event::Back,
-        self.info.title(),
-        context,
-      );
+      let top_bar_rect = rect![
+        self.rect.min.x,
+        self.rect.min.y,
+        self.rect.max.x,
+        self.rect.min.y + small_height - small_thickness
+      ];
+      let top_bar = if context.settings.reader.frontlight_buttons {
+        TopBar::with_frontlight_buttons(top_bar_rect, Event::Back, self.info.title(), context)
+      } else {
+        TopBar::new(top_bar_rect, Event::Back, self.info.title(), context)
+      };
 
       self
         .children
@@ -1811,6 +3046,7 @@ impl Reader {
             s.highlights.len(),
             s.results_count,
             !s.running.load(AtomicOrdering::Relaxed),
+            s.scope,
           );
           self
             .children
@@ -1907,8 +3143,7 @@ impl Reader {
           self.rect.max.x,
           self.rect.max.y
         ],
-        doc.as_mut(),
-        self.toc(),
+        &self.chapter_label(doc.as_mut()),
         self.current_page,
         self.pages_count,
         &neighbors,
@@ -2015,7 +3250,7 @@ impl Reader {
       }
 
       let mut edit_note = NamedInput::new(
-        "Note".to_string(),
+        tr!("Note"),
         ViewId::EditNote,
         ViewId::EditNoteInput,
         32,
@@ -2067,7 +3302,7 @@ impl Reader {
       }
 
       let name_page = NamedInput::new(
-        "Name page".to_string(),
+        tr!("Name page"),
         ViewId::NamePage,
         ViewId::NamePageInput,
         4,
@@ -2092,10 +3327,11 @@ impl Reader {
     rq: &mut RenderQueue,
     context: &mut Context,
   ) {
-    let (text, input_id) = if id == ViewId::GoToPage {
-      ("Go to page", ViewId::GoToPageInput)
+    // The page input also accepts percentages and chapter names.
+    let (text, input_id, input_size) = if id == ViewId::GoToPage {
+      ("Go to page", ViewId::GoToPageInput, 8)
     } else {
-      ("Go to results page", ViewId::GoToResultsPageInput)
+      ("Go to results page", ViewId::GoToResultsPageInput, 4)
     };
 
     if let Some(index) = locate_by_id(self, id) {
@@ -2121,7 +3357,7 @@ impl Reader {
         return;
       }
 
-      let go_to_page = NamedInput::new(text.to_string(), id, input_id, 4, context);
+      let go_to_page = NamedInput::new(tr!(text), id, input_id, input_size, context);
       rq.add(RenderData::new(
         go_to_page.id(),
         *go_to_page.rect(),
@@ -2161,30 +3397,40 @@ impl Reader {
 
       if annot.note.is_empty() {
         entries.push(EntryKind::Command(
-          "Remove Highlight".to_string(),
+          tr!("Remove Highlight"),
           EntryId::RemoveAnnotation(sel),
         ));
         entries.push(EntryKind::Separator);
         entries.push(EntryKind::Command(
-          "Add Note".to_string(),
+          tr!("Add Note"),
           EntryId::EditAnnotationNote(sel),
         ));
       } else {
         entries.push(EntryKind::Command(
-          "Remove Annotation".to_string(),
+          tr!("Remove Annotation"),
           EntryId::RemoveAnnotation(sel),
         ));
         entries.push(EntryKind::Separator);
         entries.push(EntryKind::Command(
-          "Edit Note".to_string(),
+          tr!("Edit Note"),
           EntryId::EditAnnotationNote(sel),
         ));
         entries.push(EntryKind::Command(
-          "Remove Note".to_string(),
+          tr!("Remove Note"),
           EntryId::RemoveAnnotationNote(sel),
         ));
       }
 
+      entries.push(EntryKind::Separator);
+      entries.push(EntryKind::Command(
+        tr!("Review Annotations"),
+        EntryId::ReviewAnnotations(Some(sel)),
+      ));
+      entries.push(EntryKind::SubMenu(
+        tr!("Export Annotations"),
+        annotations_formats(),
+      ));
+
       let selection_menu = Menu::new(
         rect,
         ViewId::AnnotationMenu,
@@ -2225,19 +3471,24 @@ impl Reader {
         return;
       }
       let mut entries = vec![
-        EntryKind::Command("Highlight".to_string(), EntryId::HighlightSelection),
-        EntryKind::Command("Add Note".to_string(), EntryId::AnnotateSelection),
+        EntryKind::Command(tr!("Highlight"), EntryId::HighlightSelection),
+        EntryKind::Command(tr!("Add Note"), EntryId::AnnotateSelection),
       ];
 
       entries.push(EntryKind::Separator);
+      entries.push(EntryKind::Command(tr!("Define"), EntryId::DefineSelection));
       entries.push(EntryKind::Command(
-        "Define".to_string(),
-        EntryId::DefineSelection,
+        tr!("Translate"),
+        EntryId::TranslateSelection,
       ));
       entries.push(EntryKind::Command(
-        "Search".to_string(),
+        tr!("Search"),
         EntryId::SearchForSelection,
       ));
+      entries.push(EntryKind::Command(tr!("Copy"), EntryId::CopySelection));
+      if !self.ephemeral {
+        entries.push(EntryKind::Command(tr!("Cite"), EntryId::CiteSelection));
+      }
 
       if self
         .info
@@ -2246,14 +3497,14 @@ impl Reader {
         .map_or(false, |r| !r.page_names.is_empty())
       {
         entries.push(EntryKind::Command(
-          "Go To".to_string(),
+          tr!("Go To"),
           EntryId::GoToSelectedPageName,
         ));
       }
 
       entries.push(EntryKind::Separator);
       entries.push(EntryKind::Command(
-        "Adjust Selection".to_string(),
+        tr!("Adjust Selection"),
         EntryId::AdjustSelection,
       ));
 
@@ -2275,6 +3526,159 @@ impl Reader {
     }
   }
 
+  fn title_menu_entries(&self, context: &Context) -> Vec<EntryKind> {
+    let mut entries = if self.reflowable {
+      let scroll_mode = self.view_port.scroll_mode;
+      let mut entries = vec![EntryKind::SubMenu(
+        tr!("Scroll Mode"),
+        vec![
+          EntryKind::RadioButton(
+            tr!("Paginated"),
+            EntryId::SetScrollMode(ScrollMode::Paginated),
+            scroll_mode == ScrollMode::Paginated,
+          ),
+          EntryKind::RadioButton(
+            tr!("Continuous"),
+            EntryId::SetScrollMode(ScrollMode::Continuous),
+            scroll_mode == ScrollMode::Continuous,
+          ),
+        ],
+      )];
+      let verse_layout = self
+        .info
+        .reader
+        .as_ref()
+        .and_then(|r| r.verse_layout)
+        .unwrap_or(false);
+      entries.push(EntryKind::CheckBox(
+        tr!("Preserve Line Breaks"),
+        EntryId::ToggleVerseLayout,
+        verse_layout,
+      ));
+      if self.ephemeral {
+        entries.push(EntryKind::Command(tr!("Save"), EntryId::Save));
+      }
+      entries
+    } else {
+      let zoom_mode = self.view_port.zoom_mode;
+      let mut entries = vec![EntryKind::SubMenu(
+        tr!("Zoom Mode"),
+        vec![
+          EntryKind::RadioButton(
+            tr!("Fit to Page"),
+            EntryId::SetZoomMode(ZoomMode::FitToPage),
+            zoom_mode == ZoomMode::FitToPage,
+          ),
+          EntryKind::RadioButton(
+            tr!("Fit to Width"),
+            EntryId::SetZoomMode(ZoomMode::FitToWidth),
+            zoom_mode == ZoomMode::FitToWidth,
+          ),
+          EntryKind::RadioButton(
+            tr!("Fit to Column"),
+            EntryId::SetZoomMode(ZoomMode::FitToColumn),
+            zoom_mode == ZoomMode::FitToColumn,
+          ),
+        ],
+      )];
+      let cleanup = self
+        .info
+        .reader
+        .as_ref()
+        .and_then(|r| r.scan_cleanup)
+        .unwrap_or_default();
+      let filters = [
+        ScanFilter::FlattenBackground,
+        ScanFilter::Despeckle,
+        ScanFilter::Deskew,
+      ]
+      .iter()
+      .map(|f| {
+        EntryKind::CheckBox(
+          tr!(f.label()),
+          EntryId::ToggleScanFilter(*f),
+          cleanup.is_enabled(*f),
+        )
+      })
+      .collect();
+      entries.push(EntryKind::SubMenu(tr!("Clean Up Scan"), filters));
+      if !self.ephemeral {
+        entries.push(EntryKind::Command(
+          tr!("Apply Layout to Directory"),
+          EntryId::ApplyLayoutToDirectory,
+        ));
+      }
+      entries
+    };
+
+    if !self.ephemeral {
+      let formats = [TextFormat::Plain, TextFormat::Markdown]
+        .iter()
+        .map(|f| EntryKind::Command(tr!(f.label()), EntryId::ExportText(*f)))
+        .collect();
+      entries.push(EntryKind::SubMenu(tr!("Export Text"), formats));
+      entries.push(EntryKind::Command(
+        tr!("Export Annotations as BibTeX"),
+        EntryId::ExportAnnotationsBibtex,
+      ));
+      entries.push(EntryKind::SubMenu(
+        tr!("Export Annotations"),
+        annotations_formats(),
+      ));
+      entries.push(EntryKind::Command(
+        tr!("Review Annotations"),
+        EntryId::ReviewAnnotations(None),
+      ));
+      entries.push(EntryKind::Command(tr!("Skim"), EntryId::Skim));
+      entries.push(EntryKind::CheckBox(
+        tr!("Read Aloud"),
+        EntryId::ReadAloud,
+        self.speech.is_some(),
+      ));
+      entries.push(EntryKind::Command(tr!("Share"), EntryId::ShareBook));
+      let style = context.settings.reader.citation_style;
+      let styles = [
+        CitationStyle::Apa,
+        CitationStyle::Mla,
+        CitationStyle::Bibtex,
+      ]
+      .iter()
+      .map(|s| EntryKind::RadioButton(tr!(s.label()), EntryId::SetCitationStyle(*s), *s == style))
+      .collect();
+      entries.push(EntryKind::SubMenu(tr!("Citation Style"), styles));
+
+      let rotation_lock = self.book_rotation_lock();
+      let mut locks = vec![EntryKind::RadioButton(
+        tr!("Settings"),
+        EntryId::SetBookRotationLock(None),
+        rotation_lock.is_none(),
+      )];
+      for (label, lock) in [
+        ("Portrait", RotationLock::Portrait),
+        ("Landscape", RotationLock::Landscape),
+        ("Current", RotationLock::Current),
+      ]
+      .iter()
+      {
+        locks.push(EntryKind::RadioButton(
+          tr!(label),
+          EntryId::SetBookRotationLock(Some(*lock)),
+          rotation_lock == Some(*lock),
+        ));
+      }
+      if self.rotation_lock(context.settings.rotation_lock).is_some() {
+        locks.push(EntryKind::Separator);
+        locks.push(EntryKind::Command(
+          tr!("Unlock Briefly"),
+          EntryId::UnlockRotation,
+        ));
+      }
+      entries.push(EntryKind::SubMenu(tr!("Rotation Lock"), locks));
+    }
+
+    entries
+  }
+
   pub fn toggle_title_menu(
     &mut self,
     rect: Rectangle,
@@ -2297,30 +3701,7 @@ impl Reader {
         return;
       }
 
-      let entries = if self.reflowable {
-        if self.ephemeral {
-          vec![EntryKind::Command("Save".to_string(), EntryId::Save)]
-        } else {
-          Vec::new()
-        }
-      } else {
-        let zoom_mode = self.view_port.zoom_mode;
-        vec![EntryKind::SubMenu(
-          "Zoom Mode".to_string(),
-          vec![
-            EntryKind::RadioButton(
-              "Fit to Page".to_string(),
-              EntryId::SetZoomMode(ZoomMode::FitToPage),
-              zoom_mode == ZoomMode::FitToPage,
-            ),
-            EntryKind::RadioButton(
-              "Fit to Width".to_string(),
-              EntryId::SetZoomMode(ZoomMode::FitToWidth),
-              zoom_mode == ZoomMode::FitToWidth,
-            ),
-          ],
-        )]
-      };
+      let entries = self.title_menu_entries(context);
 
       if !entries.is_empty() {
         let title_menu = Menu::new(
@@ -2340,6 +3721,112 @@ impl Reader {
     }
   }
 
+  // The menu shown by holding the title of the top bar.
+  fn toggle_hidden_menu(
+    &mut self,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::HiddenMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let entries = vec![EntryKind::CheckBox(
+        tr!("Rendering Diagnostics"),
+        EntryId::ToggleDiagnostics,
+        self.diagnostics.is_some(),
+      )];
+      let hidden_menu = Menu::new(
+        rect,
+        ViewId::HiddenMenu,
+        MenuKind::DropDown,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        hidden_menu.id(),
+        *hidden_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(hidden_menu) as Box<dyn View>);
+    }
+  }
+
+  // The menu shown by holding the back icon, to switch to another recently opened book.
+  fn toggle_recent_menu(
+    &mut self,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::RecentMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let mut entries: Vec<EntryKind> = context
+        .library
+        .recent(RECENT_BOOKS_COUNT + 1)
+        .into_iter()
+        .filter(|info| info.file.path != self.info.file.path)
+        .take(RECENT_BOOKS_COUNT)
+        .map(|info| {
+          let status = match info.status() {
+            Status::New => tr!("New"),
+            Status::Reading(progress) => format!("{:.0}%", 100.0 * progress.min(1.0)),
+            Status::Finished => tr!("Finished"),
+          };
+          EntryKind::Command(
+            format!("{} · {}", info.title(), status),
+            EntryId::OpenRecent(info.file.path),
+          )
+        })
+        .collect();
+
+      if entries.is_empty() {
+        entries.push(EntryKind::Message(tr!("No recent books.")));
+      }
+
+      let recent_menu = Menu::new(
+        rect,
+        ViewId::RecentMenu,
+        MenuKind::DropDown,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        recent_menu.id(),
+        *recent_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(recent_menu) as Box<dyn View>);
+    }
+  }
+
   fn toggle_font_family_menu(
     &mut self,
     rect: Rectangle,
@@ -2362,9 +3849,7 @@ impl Reader {
         return;
       }
 
-      let mut families = family_names(&context.settings.reader.font_path)
-        .map_err(|e| eprintln!("Can't get family names: {}", e))
-        .unwrap_or_default();
+      let mut families = context.font_index.family_names();
       let current_family = self
         .info
         .reader
@@ -2372,7 +3857,7 @@ impl Reader {
         .and_then(|r| r.font_family.clone())
         .unwrap_or_else(|| context.settings.reader.font_family.clone());
       families.insert(DEFAULT_FONT_FAMILY.to_string());
-      let entries = families
+      let mut entries: Vec<EntryKind> = families
         .iter()
         .map(|f| {
           EntryKind::RadioButton(
@@ -2382,6 +3867,11 @@ impl Reader {
           )
         })
         .collect();
+      entries.push(EntryKind::Separator);
+      entries.push(EntryKind::Command(
+        tr!("Reload Fonts"),
+        EntryId::ReloadFonts,
+      ));
       let font_family_menu = Menu::new(
         rect,
         ViewId::FontFamilyMenu,
@@ -2430,14 +3920,15 @@ impl Reader {
         .unwrap_or(context.settings.reader.font_size);
       let min_font_size = context.settings.reader.font_size / 2.0;
       let max_font_size = 3.0 * context.settings.reader.font_size / 2.0;
-      let entries = (0..=20)
+      let step = CURRENT_DEVICE.font_size_step();
+      let entries = (-FONT_SIZE_STEPS..=FONT_SIZE_STEPS)
         .filter_map(|v| {
-          let fs = font_size - 1.0 + v as f32 / 10.0;
+          let fs = font_size + v as f32 * step;
           if fs >= min_font_size && fs <= max_font_size {
             Some(EntryKind::RadioButton(
-              format!("{:.1}", fs),
+              format!("{:.2}", fs),
               EntryId::SetFontSize(v),
-              (fs - font_size).abs() < 0.05,
+              v == 0,
             ))
           } else {
             None
@@ -2496,10 +3987,99 @@ impl Reader {
         TextAlign::Right,
         TextAlign::Center,
       ];
-      let entries = choices
+      let mut entries: Vec<EntryKind> = choices
+        .iter()
+        .map(|v| {
+          EntryKind::RadioButton(
+            tr!(&v.to_string()),
+            EntryId::SetTextAlign(*v),
+            text_align == *v,
+          )
+        })
+        .collect();
+      let text_align_override = self
+        .info
+        .reader
+        .as_ref()
+        .and_then(|r| r.text_align_override)
+        .unwrap_or(context.settings.reader.text_align_override);
+      entries.push(EntryKind::Separator);
+      entries.extend(
+        [
+          TextAlignOverride::Document,
+          TextAlignOverride::BodyText,
+          TextAlignOverride::Everything,
+        ]
+        .iter()
+        .map(|v| {
+          EntryKind::RadioButton(
+            tr!(v.label()),
+            EntryId::SetTextAlignOverride(*v),
+            text_align_override == *v,
+          )
+        }),
+      );
+      let typography = context.settings.reader.typography;
+      entries.push(EntryKind::Separator);
+      entries.push(EntryKind::CheckBox(
+        tr!("Hanging Punctuation"),
+        EntryId::ToggleHangingPunctuation,
+        typography.hanging_punctuation,
+      ));
+      entries.push(EntryKind::CheckBox(
+        tr!("Widow and Orphan Control"),
+        EntryId::ToggleWidowOrphanControl,
+        typography.widow_orphan_control,
+      ));
+      let typography = book_typography(&self.info, typography);
+      entries.push(EntryKind::Separator);
+      let max_lines = [0, 1, 2, 3]
+        .iter()
+        .map(|&n| {
+          let label = if n == 0 {
+            tr!("No Limit")
+          } else {
+            n.to_string()
+          };
+          EntryKind::RadioButton(
+            label,
+            EntryId::SetMaxHyphenatedLines(n),
+            typography.max_hyphenated_lines == n,
+          )
+        })
+        .collect();
+      entries.push(EntryKind::SubMenu(tr!("Consecutive Hyphens"), max_lines));
+      let min_lengths = [0, 5, 7, 9]
         .iter()
-        .map(|v| EntryKind::RadioButton(v.to_string(), EntryId::SetTextAlign(*v), text_align == *v))
+        .map(|&n| {
+          let label = if n == 0 {
+            tr!("Any")
+          } else {
+            tr!("{} Letters", n)
+          };
+          EntryKind::RadioButton(
+            label,
+            EntryId::SetMinHyphenatedLength(n),
+            typography.min_hyphenated_length == n,
+          )
+        })
         .collect();
+      entries.push(EntryKind::SubMenu(tr!("Hyphenated Words"), min_lengths));
+      let tolerances = [
+        (tr!("Tight"), 100),
+        (tr!("Normal"), 126),
+        (tr!("Loose"), 200),
+      ]
+      .iter()
+      .map(|(label, v)| {
+        EntryKind::RadioButton(
+          label.clone(),
+          EntryId::SetStretchTolerance(*v),
+          (typography.stretch_tolerance * 100.0).round() as i32 == *v,
+        )
+      })
+      .collect();
+      entries.push(EntryKind::SubMenu(tr!("Word Spacing"), tolerances));
       let text_align_menu = Menu::new(
         rect,
         ViewId::TextAlignMenu,
@@ -2696,34 +4276,50 @@ impl Reader {
         return;
       }
 
-      let reflowable = self.reflowable;
-      let margin_width = self
-        .info
-        .reader
-        .as_ref()
-        .and_then(|r| {
-          if reflowable {
-            r.margin_width
-          } else {
-            r.screen_margin_width
-          }
-        })
-        .unwrap_or_else(|| {
-          if reflowable {
-            context.settings.reader.margin_width
-          } else {
-            0
-          }
-        });
-      let entries = (0..=10)
+      let margins = if self.reflowable {
+        page_margins(self.info.reader.as_ref(), &context.settings.reader)
+      } else {
+        PageMargins::uniform(
+          self
+            .info
+            .reader
+            .as_ref()
+            .and_then(|r| r.screen_margin_width)
+            .unwrap_or(0),
+        )
+      };
+      let mut entries: Vec<EntryKind> = (0..=10)
         .map(|mw| {
           EntryKind::RadioButton(
             format!("{}", mw),
             EntryId::SetMarginWidth(mw),
-            mw == margin_width,
+            margins.width() == Some(mw),
           )
         })
         .collect();
+      if self.reflowable {
+        entries.push(EntryKind::Separator);
+        for (name, dir) in [
+          (tr!("Top"), Dir::North),
+          (tr!("Right"), Dir::East),
+          (tr!("Bottom"), Dir::South),
+          (tr!("Left"), Dir::West),
+        ]
+        .iter()
+        .cloned()
+        {
+          let edge_entries = (0..=10)
+            .map(|mw| {
+              EntryKind::RadioButton(
+                format!("{}", mw),
+                EntryId::SetEdgeMargin(dir, mw),
+                margins.get(dir) == mw,
+              )
+            })
+            .collect();
+          entries.push(EntryKind::SubMenu(name, edge_entries));
+        }
+      }
       let margin_width_menu = Menu::new(
         rect,
         ViewId::MarginWidthMenu,
@@ -2742,6 +4338,46 @@ impl Reader {
     }
   }
 
+  fn toggle_thumbnails(&mut self, enable: Option<bool>, hub: &Hub, rq: &mut RenderQueue) {
+    if let Some(index) = locate_by_id(self, ViewId::Thumbnails) {
+      if let Some(true) = enable {
+        return;
+      }
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+      let mut thumbnails = Thumbnails::new(
+        self.rect,
+        Arc::clone(&self.doc),
+        self.pages_count,
+        self.current_page,
+      );
+      // The pre-rendered pages don't need to be rendered again.
+      let (max_width, max_height) = thumbnails.thumbnail_size();
+      for location in thumbnails.visible_locations() {
+        if let Some(resource) = self.cache.get(&location) {
+          thumbnails.insert(
+            location,
+            scale_down(&resource.pixmap, max_width, max_height),
+          );
+        }
+      }
+      thumbnails.load(hub);
+      rq.add(RenderData::new(
+        thumbnails.id(),
+        *thumbnails.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(thumbnails) as Box<dyn View>);
+    }
+  }
+
   fn toggle_page_menu(
     &mut self,
     rect: Rectangle,
@@ -2770,13 +4406,19 @@ impl Reader {
         .as_ref()
         .map_or(false, |r| r.page_names.contains_key(&self.current_page));
 
-      let mut entries = vec![EntryKind::Command("Name".to_string(), EntryId::SetPageName)];
+      let mut entries = vec![EntryKind::Command(tr!("Name"), EntryId::SetPageName)];
       if has_name {
         entries.push(EntryKind::Command(
-          "Remove Name".to_string(),
+          tr!("Remove Name"),
           EntryId::RemovePageName,
         ));
       }
+      if !self.reflowable {
+        entries.push(EntryKind::Command(
+          tr!("Thumbnails"),
+          EntryId::ToggleThumbnails,
+        ));
+      }
       let names = self
         .info
         .reader
@@ -2790,7 +4432,7 @@ impl Reader {
         .unwrap_or_default();
       if !names.is_empty() {
         entries.push(EntryKind::Separator);
-        entries.push(EntryKind::SubMenu("Go To".to_string(), names));
+        entries.push(EntryKind::SubMenu(tr!("Go To"), names));
       }
 
       let page_menu = Menu::new(rect, ViewId::PageMenu, MenuKind::DropDown, entries, context);
@@ -2834,12 +4476,12 @@ impl Reader {
 
       let mut entries = vec![
         EntryKind::RadioButton(
-          "Any".to_string(),
+          tr!("Any"),
           EntryId::ApplyCroppings(current_page, PageScheme::Any),
           is_split.is_some() && !is_split.unwrap(),
         ),
         EntryKind::RadioButton(
-          "Even/Odd".to_string(),
+          tr!("Even/Odd"),
           EntryId::ApplyCroppings(current_page, PageScheme::EvenOdd),
           is_split.is_some() && is_split.unwrap(),
         ),
@@ -2854,7 +4496,7 @@ impl Reader {
       if is_applied {
         entries.extend_from_slice(&[
           EntryKind::Separator,
-          EntryKind::Command("Remove".to_string(), EntryId::RemoveCroppings),
+          EntryKind::Command(tr!("Remove"), EntryId::RemoveCroppings),
         ]);
       }
 
@@ -2900,15 +4542,31 @@ impl Reader {
 
       let entries = vec![
         EntryKind::RadioButton(
-          "Forward".to_string(),
+          tr!("Forward"),
           EntryId::SearchDirection(LinearDir::Forward),
           self.search_direction == LinearDir::Forward,
         ),
         EntryKind::RadioButton(
-          "Backward".to_string(),
+          tr!("Backward"),
           EntryId::SearchDirection(LinearDir::Backward),
           self.search_direction == LinearDir::Backward,
         ),
+        EntryKind::Separator,
+        EntryKind::RadioButton(
+          tr!("Whole Book"),
+          EntryId::SearchScope(SearchScope::Book),
+          self.search_scope == SearchScope::Book,
+        ),
+        EntryKind::RadioButton(
+          tr!("Current Chapter"),
+          EntryId::SearchScope(SearchScope::Chapter),
+          self.search_scope == SearchScope::Chapter,
+        ),
+        EntryKind::RadioButton(
+          tr!("From Here Onward"),
+          EntryId::SearchScope(SearchScope::Onward),
+          self.search_scope == SearchScope::Onward,
+        ),
       ];
 
       let search_menu = Menu::new(
@@ -2946,11 +4604,16 @@ impl Reader {
     {
       let mut doc = self.doc.lock().unwrap();
 
-      doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
+      doc.layout(
+        width,
+        height - self.header_height as u32,
+        font_size,
+        CURRENT_DEVICE.dpi,
+      );
 
       if self.synthetic {
-        let current_page = self.current_page.min(doc.pages_count() - 1);
-        if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
+        // The extra content of a document lies beyond its pages count.
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
           self.current_page = location;
         }
       } else {
@@ -2967,6 +4630,73 @@ impl Reader {
     self.update_bottom_bar(rq);
   }
 
+  // Handles the links of the controls of the table of contents.
+  fn toggle_toc_entry(
+    &mut self,
+    link: &str,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    let toc_tree = match self.toc_tree.as_mut() {
+      Some(toc_tree) => toc_tree,
+      None => return false,
+    };
+    let mut chars = link.chars();
+    let sign = chars.next();
+    let rest = chars.as_str();
+    match (sign, rest.parse::<usize>()) {
+      (Some('+'), Ok(index)) | (Some('-'), Ok(index)) => {
+        toc_tree.toggle(index);
+        let focus = if sign == Some('+') { '-' } else { '+' };
+        self.rebuild_toc(Some(format!("{}{}", focus, index)), hub, rq, context);
+      },
+      (Some('='), Ok(depth)) => {
+        toc_tree.expand_to_depth(depth);
+        self.rebuild_toc(None, hub, rq, context);
+      },
+      (Some('='), Err(_)) if rest == "clear" => {
+        toc_tree.set_filter("");
+        let focus = toc_tree.current_link();
+        self.rebuild_toc(focus, hub, rq, context);
+      },
+      _ => return false,
+    }
+    true
+  }
+
+  // Lays out the table of contents again and goes to the page holding the *focus* link.
+  fn rebuild_toc(
+    &mut self,
+    focus: Option<String>,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let html = match self.toc_tree.as_ref() {
+      Some(toc_tree) => toc_tree.as_html(),
+      None => return,
+    };
+    let mut doc = HtmlDocument::new_from_memory(&html);
+    let (width, height) = context.display.dims;
+    let font_size = self
+      .info
+      .reader
+      .as_ref()
+      .and_then(|r| r.font_size)
+      .unwrap_or(context.settings.reader.font_size);
+    doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
+    self.current_page = focus
+      .and_then(|uri| page_of_link(&mut doc, &uri))
+      .unwrap_or(0);
+    self.pages_count = doc.pages_count();
+    self.doc = Arc::new(Mutex::new(Box::new(doc)));
+    self.cache.clear();
+    self.text.clear();
+    self.update(None, hub, rq, context);
+    self.update_bottom_bar(rq);
+  }
+
   fn set_text_align(
     &mut self,
     text_align: TextAlign,
@@ -2987,8 +4717,7 @@ impl Reader {
       doc.set_text_align(text_align);
 
       if self.synthetic {
-        let current_page = self.current_page.min(doc.pages_count() - 1);
-        if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
           self.current_page = location;
         }
       } else {
@@ -3004,9 +4733,9 @@ impl Reader {
     self.update_bottom_bar(rq);
   }
 
-  fn set_font_family(
+  fn set_text_align_override(
     &mut self,
-    font_family: &str,
+    value: TextAlignOverride,
     hub: &Hub,
     rq: &mut RenderQueue,
     context: &mut Context,
@@ -3016,22 +4745,15 @@ impl Reader {
     }
 
     if let Some(ref mut r) = self.info.reader {
-      r.font_family = Some(font_family.to_string());
+      r.text_align_override = Some(value);
     }
 
     {
       let mut doc = self.doc.lock().unwrap();
-      let font_path = if font_family == DEFAULT_FONT_FAMILY {
-        "fonts"
-      } else {
-        &context.settings.reader.font_path
-      };
-
-      doc.set_font_family(font_family, font_path);
+      doc.set_text_align_override(value);
 
       if self.synthetic {
-        let current_page = self.current_page.min(doc.pages_count() - 1);
-        if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
           self.current_page = location;
         }
       } else {
@@ -3047,9 +4769,9 @@ impl Reader {
     self.update_bottom_bar(rq);
   }
 
-  fn set_line_height(
+  fn set_typography(
     &mut self,
-    line_height: f32,
+    typography: TypographySettings,
     hub: &Hub,
     rq: &mut RenderQueue,
     context: &mut Context,
@@ -3058,17 +4780,14 @@ impl Reader {
       return;
     }
 
-    if let Some(ref mut r) = self.info.reader {
-      r.line_height = Some(line_height);
-    }
+    context.settings.reader.typography = typography;
 
     {
       let mut doc = self.doc.lock().unwrap();
-      doc.set_line_height(line_height);
+      doc.set_typography(book_typography(&self.info, typography));
 
       if self.synthetic {
-        let current_page = self.current_page.min(doc.pages_count() - 1);
-        if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
           self.current_page = location;
         }
       } else {
@@ -3080,84 +4799,260 @@ impl Reader {
     self.cache.clear();
     self.text.clear();
     self.update(None, hub, rq, context);
-    self.update_tool_bar(rq, context);
     self.update_bottom_bar(rq);
   }
 
-  fn set_margin_width(
-    &mut self,
-    width: i32,
-    hub: &Hub,
-    rq: &mut RenderQueue,
-    context: &mut Context,
-  ) {
+  fn toggle_verse_layout(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
     if Arc::strong_count(&self.doc) > 1 {
       return;
     }
 
+    let enable = !self
+      .info
+      .reader
+      .as_ref()
+      .and_then(|r| r.verse_layout)
+      .unwrap_or(false);
+
     if let Some(ref mut r) = self.info.reader {
-      if self.reflowable {
-        r.margin_width = Some(width);
-      } else {
-        if width == 0 {
-          r.screen_margin_width = None;
-        } else {
-          r.screen_margin_width = Some(width);
-        }
-      }
+      r.verse_layout = Some(enable).filter(|v| *v);
     }
 
-    if self.reflowable {
+    {
       let mut doc = self.doc.lock().unwrap();
-      doc.set_margin_width(width);
+      doc.set_verse_layout(enable);
 
       if self.synthetic {
-        let current_page = self.current_page.min(doc.pages_count() - 1);
-        if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
           self.current_page = location;
         }
       } else {
         self.pages_count = doc.pages_count();
         self.current_page = self.current_page.min(self.pages_count - 1);
       }
-    } else {
-      let next_margin_width = mm_to_px(width as f32, CURRENT_DEVICE.dpi) as i32;
-      let ratio = (self.rect.width() as i32 - 2 * next_margin_width) as f32
-        / (self.rect.width() as i32 - 2 * self.view_port.margin_width) as f32;
-      self.view_port.top_offset = (self.view_port.top_offset as f32 * ratio) as i32;
-      self.view_port.margin_width = next_margin_width;
     }
 
-    self.text.clear();
     self.cache.clear();
+    self.text.clear();
     self.update(None, hub, rq, context);
-    self.update_tool_bar(rq, context);
     self.update_bottom_bar(rq);
   }
 
-  fn toggle_bookmark(&mut self, rq: &mut RenderQueue) {
-    if let Some(ref mut r) = self.info.reader {
-      if !r.bookmarks.insert(self.current_page) {
-        r.bookmarks.remove(&self.current_page);
-      }
-    }
-    let dpi = CURRENT_DEVICE.dpi;
-    let thickness = scale_by_dpi(3.0, dpi) as u16;
-    let radius = mm_to_px(0.4, dpi) as i32 + thickness as i32;
-    let center = pt!(self.rect.max.x - 5 * radius, self.rect.min.y + 5 * radius);
-    let rect = Rectangle::from_disk(center, radius);
-    rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
-  }
-
-  fn set_contrast_exponent(
+  fn set_font_family(
     &mut self,
-    exponent: f32,
+    font_family: &str,
     hub: &Hub,
     rq: &mut RenderQueue,
     context: &mut Context,
   ) {
-    if let Some(ref mut r) = self.info.reader {
-      r.contrast_exponent = Some(exponent);
+    if Arc::strong_count(&self.doc) > 1 {
+      return;
+    }
+
+    if let Some(ref mut r) = self.info.reader {
+      r.font_family = Some(font_family.to_string());
+    }
+
+    {
+      let mut doc = self.doc.lock().unwrap();
+      match family_files(font_family, context) {
+        Ok(files) => doc.set_font_family(&files),
+        Err(e) => eprintln!("Can't load font family {}: {}.", font_family, e),
+      }
+
+      if self.synthetic {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
+          self.current_page = location;
+        }
+      } else {
+        self.pages_count = doc.pages_count();
+        self.current_page = self.current_page.min(self.pages_count - 1);
+      }
+    }
+
+    self.cache.clear();
+    self.text.clear();
+    self.update(None, hub, rq, context);
+    self.update_tool_bar(rq, context);
+    self.update_bottom_bar(rq);
+  }
+
+  fn set_line_height(
+    &mut self,
+    line_height: f32,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if Arc::strong_count(&self.doc) > 1 {
+      return;
+    }
+
+    if let Some(ref mut r) = self.info.reader {
+      r.line_height = Some(line_height);
+    }
+
+    {
+      let mut doc = self.doc.lock().unwrap();
+      doc.set_line_height(line_height);
+
+      if self.synthetic {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
+          self.current_page = location;
+        }
+      } else {
+        self.pages_count = doc.pages_count();
+        self.current_page = self.current_page.min(self.pages_count - 1);
+      }
+    }
+
+    self.cache.clear();
+    self.text.clear();
+    self.update(None, hub, rq, context);
+    self.update_tool_bar(rq, context);
+    self.update_bottom_bar(rq);
+  }
+
+  fn set_margin_width(
+    &mut self,
+    width: i32,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if Arc::strong_count(&self.doc) > 1 {
+      return;
+    }
+
+    if let Some(ref mut r) = self.info.reader {
+      if self.reflowable {
+        r.margin_width = Some(width);
+        r.margins = None;
+      } else {
+        if width == 0 {
+          r.screen_margin_width = None;
+        } else {
+          r.screen_margin_width = Some(width);
+        }
+      }
+    }
+
+    if self.reflowable {
+      let mut doc = self.doc.lock().unwrap();
+      doc.set_margin_width(width);
+
+      if self.synthetic {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
+          self.current_page = location;
+        }
+      } else {
+        self.pages_count = doc.pages_count();
+        self.current_page = self.current_page.min(self.pages_count - 1);
+      }
+    } else {
+      let next_margin_width = mm_to_px(width as f32, CURRENT_DEVICE.dpi) as i32;
+      let ratio = (self.rect.width() as i32 - 2 * next_margin_width) as f32
+        / (self.rect.width() as i32 - 2 * self.view_port.margin_width) as f32;
+      self.view_port.top_offset = (self.view_port.top_offset as f32 * ratio) as i32;
+      self.view_port.margin_width = next_margin_width;
+    }
+
+    self.text.clear();
+    self.cache.clear();
+    self.update(None, hub, rq, context);
+    self.update_tool_bar(rq, context);
+    self.update_bottom_bar(rq);
+  }
+
+  fn set_edge_margin(
+    &mut self,
+    dir: Dir,
+    width: i32,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if Arc::strong_count(&self.doc) > 1 || !self.reflowable {
+      return;
+    }
+
+    let mut margins = page_margins(self.info.reader.as_ref(), &context.settings.reader);
+    margins.set(dir, width);
+
+    if let Some(ref mut r) = self.info.reader {
+      r.margins = Some(margins);
+    }
+
+    {
+      let mut doc = self.doc.lock().unwrap();
+      doc.set_margins(&margins);
+
+      if self.synthetic {
+        if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
+          self.current_page = location;
+        }
+      } else {
+        self.pages_count = doc.pages_count();
+        self.current_page = self.current_page.min(self.pages_count - 1);
+      }
+    }
+
+    self.text.clear();
+    self.cache.clear();
+    self.update(None, hub, rq, context);
+    self.update_tool_bar(rq, context);
+    self.update_bottom_bar(rq);
+  }
+
+  fn toggle_bookmark(&mut self, rq: &mut RenderQueue) {
+    if let Some(ref mut r) = self.info.reader {
+      if !r.bookmarks.insert(self.current_page) {
+        r.bookmarks.remove(&self.current_page);
+      }
+    }
+    rq.add(RenderData::new(
+      self.id,
+      self.dog_ear_rect(1),
+      UpdateMode::Gui,
+    ));
+  }
+
+  // The square of the bookmark corner whose side is *factor* times the size of the dog-ear.
+  fn dog_ear_rect(&self, factor: i32) -> Rectangle {
+    let side = factor * mm_to_px(DOG_EAR_SIZE, CURRENT_DEVICE.dpi) as i32;
+    let r = &self.rect;
+    match self.bookmark_corner {
+      DiagDir::NorthWest => rect![r.min.x, r.min.y, r.min.x + side, r.min.y + side],
+      DiagDir::NorthEast => rect![r.max.x - side, r.min.y, r.max.x, r.min.y + side],
+      DiagDir::SouthEast => rect![r.max.x - side, r.max.y - side, r.max.x, r.max.y],
+      DiagDir::SouthWest => rect![r.min.x, r.max.y - side, r.min.x + side, r.max.y],
+    }
+  }
+
+  // The folded corner: the corner of the page is cut and the flap lies over the page.
+  fn draw_dog_ear(&self, fb: &mut dyn Framebuffer) {
+    let rect = self.dog_ear_rect(1);
+    let (corner, inner) = match self.bookmark_corner {
+      DiagDir::NorthWest => (rect.min, rect.max),
+      DiagDir::NorthEast => (pt!(rect.max.x, rect.min.y), pt!(rect.min.x, rect.max.y)),
+      DiagDir::SouthEast => (rect.max, rect.min),
+      DiagDir::SouthWest => (pt!(rect.min.x, rect.max.y), pt!(rect.max.x, rect.min.y)),
+    };
+    let horizontal = pt!(inner.x, corner.y);
+    let vertical = pt!(corner.x, inner.y);
+    fb.draw_triangle(&[corner, horizontal, vertical], WHITE);
+    fb.draw_triangle(&[inner, horizontal, vertical], BLACK);
+  }
+
+  fn set_contrast_exponent(
+    &mut self,
+    exponent: f32,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(ref mut r) = self.info.reader {
+      r.contrast_exponent = Some(exponent);
     }
     self.contrast.exponent = exponent;
     self.update(None, hub, rq, context);
@@ -3179,6 +5074,22 @@ impl Reader {
     self.update_tool_bar(rq, context);
   }
 
+  fn toggle_scan_filter(
+    &mut self,
+    filter: ScanFilter,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &Context,
+  ) {
+    if let Some(ref mut r) = self.info.reader {
+      let mut cleanup = r.scan_cleanup.unwrap_or_default();
+      cleanup.toggle(filter);
+      r.scan_cleanup = Some(cleanup).filter(|c| !c.is_empty());
+    }
+    self.cache.clear();
+    self.update(None, hub, rq, context);
+  }
+
   fn set_zoom_mode(
     &mut self,
     zoom_mode: ZoomMode,
@@ -3189,12 +5100,52 @@ impl Reader {
     if self.view_port.zoom_mode == zoom_mode {
       return;
     }
+    if let Some(ref mut r) = self.info.reader {
+      r.last_zoom_mode = Some(self.view_port.zoom_mode);
+    }
     self.view_port.zoom_mode = zoom_mode;
     self.view_port.top_offset = 0;
+    self.view_port.column = 0;
     self.cache.clear();
     self.update(None, hub, rq, context);
   }
 
+  fn set_scroll_mode(
+    &mut self,
+    scroll_mode: ScrollMode,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &Context,
+  ) {
+    if self.view_port.scroll_mode == scroll_mode {
+      return;
+    }
+    self.view_port.scroll_mode = scroll_mode;
+    self.view_port.top_offset = 0;
+    self.cache.clear();
+    self.update(None, hub, rq, context);
+  }
+
+  // Goes to the zoom preset that follows the current zoom mode.
+  fn next_zoom_preset(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+    let presets: Vec<ZoomMode> = context
+      .settings
+      .reader
+      .zoom_presets
+      .iter()
+      .cloned()
+      .filter(|z| !self.reflowable || *z != ZoomMode::FitToColumn)
+      .collect();
+    if presets.is_empty() {
+      return;
+    }
+    let index = presets
+      .iter()
+      .position(|z| *z == self.view_port.zoom_mode)
+      .map_or(0, |i| (i + 1) % presets.len());
+    self.set_zoom_mode(presets[index], hub, rq, context);
+  }
+
   fn crop_margins(
     &mut self,
     index: usize,
@@ -3235,6 +5186,26 @@ impl Reader {
     self.update(None, hub, rq, context);
   }
 
+  // Shows the headings of the document, each followed by the first sentence of its section.
+  fn skim(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    self.toggle_bars(Some(false), hub, rq, context);
+    let entries = {
+      let mut doc = self.doc.lock().unwrap();
+      let toc = self.toc().or_else(|| doc.toc()).unwrap_or_default();
+      skim(doc.as_mut(), &toc)
+    };
+    if entries.is_empty() {
+      hub
+        .send(Event::Notify(tr!("This document has no headings.")))
+        .ok();
+      return;
+    }
+    let title = tr!("Skim: {}", self.info.title);
+    hub
+      .send(Event::OpenHtml(skim_as_html(&title, &entries)))
+      .ok();
+  }
+
   fn toc(&self) -> Option<Vec<TocEntry>> {
     let mut index = 0;
     self
@@ -3276,7 +5247,80 @@ impl Reader {
     toc
   }
 
+  fn resolve_go_to(&self, go_to: GoTo, context: &mut Context) -> Option<usize> {
+    let last_page = self.pages_count.saturating_sub(1);
+    match go_to {
+      GoTo::Random => Some((context.rng.next_u64() % self.pages_count as u64) as usize),
+      GoTo::First => Some(0),
+      GoTo::Last => Some(last_page),
+      GoTo::PageName(ref name) => self.find_page_by_name(name),
+      GoTo::Page(number, sign) => {
+        // The page numbers of reflowable documents are multiples of BYTES_PER_PAGE.
+        let page_size = if self.synthetic { BYTES_PER_PAGE } else { 1.0 };
+        let location = match sign {
+          Some(sign) => self.current_page as f64 + sign.apply(number) * page_size,
+          None if self.synthetic => number * page_size,
+          None => number - 1.0,
+        };
+        Some((location.max(0.0).round() as usize).min(last_page))
+      },
+      GoTo::Percent(value, sign) => {
+        let count = self.pages_count as f64;
+        let location = match sign {
+          Some(sign) => self.current_page as f64 + sign.apply(value) * count / 100.0,
+          None => value * count / 100.0,
+        };
+        Some((location.max(0.0).round() as usize).min(last_page))
+      },
+      GoTo::Chapter(ref query) => {
+        if let Some(index) = self.named_page(query) {
+          return Some(index);
+        }
+        let mut doc = self.doc.lock().unwrap();
+        let toc = self.toc().or_else(|| doc.toc())?;
+        let chapter = find_chapter(&toc, query)?;
+        doc.resolve_location(chapter.location.clone())
+      },
+    }
+  }
+
+  // The named pages, listed in the table of contents. The indices of the entries start at *index*.
+  fn named_pages_entry(&self, index: usize) -> Option<TocEntry> {
+    let page_names = &self.info.reader.as_ref()?.page_names;
+    let first_page = *page_names.keys().next()?;
+    let children = page_names
+      .iter()
+      .enumerate()
+      .map(|(i, (page, name))| TocEntry {
+        title: name.clone(),
+        location: Location::Exact(*page),
+        index: index + 1 + i,
+        children: Vec::new(),
+      })
+      .collect();
+    Some(TocEntry {
+      title: tr!("Named pages"),
+      location: Location::Exact(first_page),
+      index,
+      children,
+    })
+  }
+
+  // Returns the page named exactly *name*, ignoring the case.
+  fn named_page(&self, name: &str) -> Option<usize> {
+    let name = name.trim().to_lowercase();
+    self.info.reader.as_ref().and_then(|r| {
+      r.page_names
+        .iter()
+        .find(|(_, s)| s.to_lowercase() == name)
+        .map(|(i, _)| *i)
+    })
+  }
+
   fn find_page_by_name(&self, name: &str) -> Option<usize> {
+    if let Some(index) = self.named_page(name) {
+      return Some(index);
+    }
     self.info.reader.as_ref().and_then(|r| {
       if let Ok(a) = u32::from_str_radix(name, 10) {
         r.page_names
@@ -3338,6 +5382,29 @@ impl Reader {
     Some(text)
   }
 
+  // Returns the sentence that holds the given selection, if it's on the loaded pages.
+  fn enclosing_sentence(&self, sel: [TextLocation; 2]) -> Option<String> {
+    let mut words = self.text.values().flatten().collect::<Vec<&BoundedText>>();
+    words.sort_by(|a, b| a.location.cmp(&b.location));
+    let ends_sentence = |word: &BoundedText| {
+      word
+        .text
+        .trim_end_matches(|c: char| c == '"' || c == '\'' || c == '”' || c == '’' || c == ')')
+        .ends_with(&['.', '!', '?', '…'][..])
+    };
+    let first = words.iter().position(|w| w.location >= sel[0])?;
+    let last = words.iter().rposition(|w| w.location <= sel[1])?;
+    let mut start = first;
+    while start > 0 && first - start < SENTENCE_MAX_WORDS && !ends_sentence(words[start - 1]) {
+      start -= 1;
+    }
+    let mut end = last;
+    while end + 1 < words.len() && end - last < SENTENCE_MAX_WORDS && !ends_sentence(words[end]) {
+      end += 1;
+    }
+    self.text_excerpt([words[start].location, words[end].location])
+  }
+
   fn selected_text(&self) -> Option<String> {
     self
       .selection
@@ -3399,6 +5466,252 @@ impl Reader {
       .and_then(|sel| self.text_rect([sel.start, sel.end]))
   }
 
+  // The annotations of the book, in reading order.
+  fn sorted_annotations(&self) -> Vec<Annotation> {
+    let mut annotations = self
+      .info
+      .reader
+      .as_ref()
+      .map(|r| r.annotations.clone())
+      .unwrap_or_default();
+    annotations.sort_by(|a, b| a.selection[0].cmp(&b.selection[0]));
+    annotations
+  }
+
+  // Starts reviewing the annotations, from the given one or from the first one that follows
+  // the current page.
+  fn start_review(
+    &mut self,
+    sel: Option<[TextLocation; 2]>,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let annotations = self.sorted_annotations();
+    if annotations.is_empty() {
+      let notif = Notification::new(
+        ViewId::MessageNotif,
+        tr!("This book has no annotations."),
+        context,
+      );
+      push_notification(&mut self.children, notif, hub, rq, context);
+      return;
+    }
+    let index = sel
+      .and_then(|sel| annotations.iter().position(|a| a.selection == sel))
+      .or_else(|| {
+        self
+          .text_location_range()
+          .and_then(|[min, _]| annotations.iter().position(|a| a.selection[1] >= min))
+      })
+      .unwrap_or(0);
+    self.show_review(&annotations, index, hub, rq, context);
+  }
+
+  fn show_review(
+    &mut self,
+    annotations: &[Annotation],
+    index: usize,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let annot = annotations[index].clone();
+    let previous = self.review.replace(annot.selection);
+    let marker = WHITE - annotation_drift(&annot);
+    let location = annot.selection[0].location();
+    if let Some(index_bar) = locate_by_id(self, ViewId::ReviewBar) {
+      if let Some(review_bar) = self.children[index_bar].downcast_mut::<ReviewBar>() {
+        review_bar.update(annot, index, annotations.len(), marker, rq);
+      }
+    } else {
+      let review_bar = ReviewBar::new(self.rect, annot, index, annotations.len(), marker);
+      rq.add(RenderData::new(
+        review_bar.id(),
+        *review_bar.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(review_bar) as Box<dyn View>);
+    }
+    for sel in previous.iter().chain(self.review.iter()) {
+      if let Some(rect) = self.text_rect(*sel) {
+        rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+      }
+    }
+    if self.text_location_range().map_or(true, |[min, max]| {
+      location < min.location() || location > max.location()
+    }) {
+      self.go_to_page(location, true, hub, rq, context);
+    }
+  }
+
+  fn review_neighbor(
+    &mut self,
+    dir: CycleDir,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let annotations = self.sorted_annotations();
+    let current = self
+      .review
+      .and_then(|sel| annotations.iter().position(|a| a.selection == sel));
+    let index = match (dir, current) {
+      (CycleDir::Next, Some(index)) if index + 1 < annotations.len() => index + 1,
+      (CycleDir::Previous, Some(index)) if index > 0 => index - 1,
+      _ => return,
+    };
+    self.show_review(&annotations, index, hub, rq, context);
+  }
+
+  fn stop_review(&mut self, rq: &mut RenderQueue) {
+    if let Some(index) = locate_by_id(self, ViewId::ReviewBar) {
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    }
+    if let Some(sel) = self.review.take() {
+      if let Some(rect) = self.text_rect(sel) {
+        rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+      }
+    }
+  }
+
+  // Shows the annotation that follows (or precedes) the removed one, or leaves the review mode.
+  fn review_after_removal(
+    &mut self,
+    sel: [TextLocation; 2],
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    let annotations = self.sorted_annotations();
+    if annotations.is_empty() {
+      self.stop_review(rq);
+      return;
+    }
+    let index = annotations
+      .iter()
+      .position(|a| a.selection[0] > sel[0])
+      .unwrap_or(annotations.len() - 1);
+    self.review = None;
+    self.show_review(&annotations, index, hub, rq, context);
+  }
+
+  // Refreshes the review bar after the current annotation was edited.
+  fn update_review(&mut self, rq: &mut RenderQueue) {
+    let sel = match self.review {
+      Some(sel) => sel,
+      None => return,
+    };
+    let annotations = self.sorted_annotations();
+    if let Some(index) = annotations.iter().position(|a| a.selection == sel) {
+      let annot = annotations[index].clone();
+      let marker = WHITE - annotation_drift(&annot);
+      if let Some(index_bar) = locate_by_id(self, ViewId::ReviewBar) {
+        if let Some(review_bar) = self.children[index_bar].downcast_mut::<ReviewBar>() {
+          review_bar.update(annot, index, annotations.len(), marker, rq);
+        }
+      }
+    }
+  }
+
+  // The words shown on the screen, in reading order.
+  fn visible_words(&self) -> Vec<BoundedText> {
+    let mut words = Vec::new();
+    for chunk in &self.chunks {
+      if let Some(text) = self.text.get(&chunk.location) {
+        words.extend(
+          text
+            .iter()
+            .filter(|w| (w.rect * chunk.scale).to_rect().overlaps(&chunk.frame))
+            .cloned(),
+        );
+      }
+    }
+    words.sort_by(|a, b| a.location.cmp(&b.location));
+    words
+  }
+
+  // Reads the current chapter aloud, from the top of the screen.
+  fn start_speech(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    if context.settings.reader.tts.program.is_none() {
+      hub
+        .send(Event::Notify(tr!("No speech synthesizer is set.")))
+        .ok();
+      return;
+    }
+    let (_, end) = self.chapter_bounds();
+    self.speak(None, end, hub, rq, context);
+  }
+
+  fn speak_next(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+    if let Some(speech) = self.speech.as_ref() {
+      self.speak(Some(speech.sentence[1]), speech.end, hub, rq, context);
+    }
+  }
+
+  // Speaks the sentence that follows the word at *after*, turning the pages as needed, and
+  // stops at *end*.
+  fn speak(
+    &mut self,
+    mut after: Option<TextLocation>,
+    end: usize,
+    hub: &Hub,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(speech) = self.speech.take() {
+      if let Some(rect) = self.text_rect(speech.sentence) {
+        rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+      }
+    }
+    loop {
+      let words = self.visible_words();
+      // When the page was turned by hand, the reading resumes from its top.
+      let after_visible = after.filter(|loc| words.iter().any(|w| w.location == *loc));
+      if let Some(sentence) = next_sentence(&words, after_visible) {
+        let text = sentence
+          .iter()
+          .map(|w| w.text.as_str())
+          .collect::<Vec<&str>>()
+          .join(" ");
+        let bounds = [sentence[0].location, sentence[sentence.len() - 1].location];
+        match speak(&text, &context.settings.reader.tts, hub) {
+          Ok(synthesizer) => {
+            self.speech = Some(Speech {
+              sentence: bounds,
+              synthesizer,
+              end,
+            });
+            if let Some(rect) = self.text_rect(bounds) {
+              rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+            }
+          },
+          Err(e) => report_error(hub, Severity::Warning, "Can't read aloud", &e),
+        }
+        return;
+      }
+      let position = (self.current_page, self.text_location_range());
+      self.go_to_neighbor(CycleDir::Next, hub, rq, context);
+      if (self.current_page, self.text_location_range()) == position || self.current_page >= end {
+        return;
+      }
+      after = None;
+    }
+  }
+
+  fn stop_speech(&mut self, rq: &mut RenderQueue) {
+    if let Some(speech) = self.speech.take() {
+      speech.synthesizer.silence();
+      if let Some(rect) = self.text_rect(speech.sentence) {
+        rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+      }
+    }
+  }
+
   fn find_annotation_ref(&mut self, sel: [TextLocation; 2]) -> Option<&Annotation> {
     self.info.reader.as_ref().and_then(|r| {
       r.annotations
@@ -3425,8 +5738,182 @@ impl Reader {
       hub.send(Event::ClockTick).ok();
       hub.send(Event::BatteryTick).ok();
     }
-
-    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
+
+  // Returns the document and its reading position, unless the document is ephemeral.
+  pub fn resume_info(&self) -> Option<Info> {
+    if self.ephemeral {
+      return None;
+    }
+    let mut info = self.info.clone();
+    if let Some(ref mut r) = info.reader {
+      r.current_page = self.current_page;
+      r.pages_count = self.pages_count;
+      r.finished = self.finished;
+    }
+    Some(info)
+  }
+
+  // Gives the zoom, crop and contrast of the current book to the other books of its directory:
+  // right away to the ones that were opened, and when they're first opened to the others.
+  fn apply_layout_to_directory(&mut self, hub: &Hub, context: &mut Context) {
+    self.save_state(context);
+    let layout = match self.info.reader.as_ref() {
+      Some(r) => DirectoryLayout::from_reader(r),
+      None => return,
+    };
+    let dir = self
+      .info
+      .file
+      .path
+      .parent()
+      .unwrap_or_else(|| Path::new(""))
+      .to_path_buf();
+    let mut count = 0;
+
+    if let Ok(entries) = fs::read_dir(context.library.home.join(&dir)) {
+      for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = dir.join(entry.file_name());
+        if path == self.info.file.path {
+          continue;
+        }
+        if let Some(mut r) = context
+          .library
+          .document_info(&path)
+          .and_then(|info| info.reader)
+        {
+          layout.apply(&mut r);
+          context.library.sync_reader_info(&path, &r);
+          count += 1;
+        }
+      }
+    }
+
+    let index = context.settings.selected_library;
+    if let Some(library) = context.settings.libraries.get_mut(index) {
+      library.directory_layouts.insert(dir, layout);
+    }
+
+    let msg = if count == 1 {
+      tr!("Layout applied to 1 opened book.")
+    } else {
+      tr!("Layout applied to {} opened books.", count)
+    };
+    hub.send(Event::Notify(msg)).ok();
+  }
+
+  // Whether the document's file changed since it was last checked.
+  fn check_file(&mut self, context: &Context) -> bool {
+    if self.file_stamp.is_none() {
+      return false;
+    }
+    let stamp = file_stamp(&context.library.home.join(&self.info.file.path));
+    if stamp.is_some() && stamp != self.file_stamp {
+      self.file_stamp = stamp;
+      return true;
+    }
+    false
+  }
+
+  // The first words of the current page, to find it again in the new version of the document.
+  fn anchor_text(&mut self) -> Option<String> {
+    self.load_text(self.current_page);
+    let words = &self.text[&self.current_page];
+    if words.is_empty() {
+      return None;
+    }
+    Some(
+      words
+        .iter()
+        .take(ANCHOR_WORDS)
+        .map(|w| w.text.as_str())
+        .collect::<Vec<&str>>()
+        .join(" "),
+    )
+  }
+
+  // Searches the pages around *location*, nearest first, for the one that contains *anchor*.
+  fn find_anchor(&mut self, anchor: &str, location: usize) -> Option<usize> {
+    let mut doc = self.doc.lock().unwrap();
+    let start = doc.resolve_location(Location::Exact(location))?;
+    let page_text = |doc: &mut Box<dyn Document>, loc: usize| {
+      doc
+        .words(Location::Exact(loc))
+        .map(|(words, _)| {
+          words
+            .iter()
+            .map(|w| w.text.as_str())
+            .collect::<Vec<&str>>()
+            .join(" ")
+        })
+        .unwrap_or_default()
+    };
+    let mut forward = Some(start);
+    let mut backward = doc.resolve_location(Location::Previous(start));
+    for _ in 0..ANCHOR_MAX_PAGES {
+      for loc in [forward, backward].iter().flatten() {
+        if page_text(&mut doc, *loc).contains(anchor) {
+          return Some(*loc);
+        }
+      }
+      forward = forward.and_then(|loc| doc.resolve_location(Location::Next(loc)));
+      backward = backward.and_then(|loc| doc.resolve_location(Location::Previous(loc)));
+      if forward.is_none() && backward.is_none() {
+        break;
+      }
+    }
+    None
+  }
+
+  // Opens the new version of the document's file in place of the current one.
+  fn reload(&mut self, hub: &Hub, context: &mut Context) {
+    let anchor = self.anchor_text();
+    let location = self.current_page;
+    let path = self.info.file.path.clone();
+
+    // The entry of the database follows the new fingerprint of the file.
+    if let Some(fp) = context.library.paths.get(&path).cloned() {
+      let new_fp = context
+        .library
+        .home
+        .join(&path)
+        .metadata()
+        .ok()
+        .and_then(|md| md.fingerprint(context.library.fat32_epoch).ok());
+      if let Some(new_fp) = new_fp.filter(|new_fp| *new_fp != fp) {
+        let issue = IntegrityIssue::Modified(fp, path.clone(), new_fp);
+        if let Err(e) = context.library.repair(&issue, Repair::UpdateEntry) {
+          report_error(hub, Severity::Warning, "Can't update the fingerprint", &e);
+        }
+      }
+    }
+
+    let mut info = self.info.clone();
+    if let Some(ref mut r) = info.reader {
+      r.current_page = location;
+      r.finished = false;
+    }
+
+    match Reader::new(self.rect, info, hub, context) {
+      Some(mut reader) => {
+        if let Some(loc) = anchor.and_then(|anchor| reader.find_anchor(&anchor, location)) {
+          reader.current_page = loc;
+          reader.view_port.top_offset = 0;
+        }
+        if let Some(ref mut s) = self.search {
+          s.running.store(false, AtomicOrdering::Relaxed);
+        }
+        reader.id = self.id;
+        *self = reader;
+        hub.send(Event::Notify(tr!("Document reloaded."))).ok();
+      },
+      None => {
+        let e = format_err!("{} can't be opened", path.display());
+        report_error(hub, Severity::Error, "Can't reload the document", &e);
+      },
+    }
   }
 
   fn quit(&mut self, context: &mut Context) {
@@ -3434,22 +5921,75 @@ impl Reader {
       s.running.store(false, AtomicOrdering::Relaxed);
     }
 
+    if let Some(speech) = self.speech.take() {
+      speech.synthesizer.silence();
+    }
+
     if self.ephemeral {
       return;
     }
 
+    context
+      .settings
+      .reading_speed
+      .add(&self.reading_timer.session);
+
+    self.update_reader_info(context);
+
+    // A book that was opened and closed without being read or changed stays new.
+    if self.initial_location == Some(self.current_page)
+      && !self.finished
+      && !context.settings.reader.auto_status.reading_on_open
+      && self.book_rotation_lock().is_none()
+      && self.reading_state() == self.initial_state
+    {
+      context
+        .library
+        .set_status(&self.info.file.path, SimpleStatus::New);
+      return;
+    }
+
+    self.save_state(context);
+  }
+
+  fn reading_state(&self) -> Option<String> {
+    self
+      .info
+      .reader
+      .as_ref()
+      .and_then(|r| serde_json::to_string(r).ok())
+  }
+
+  // Writes the current location, zoom and contrast into the reading state.
+  fn update_reader_info(&mut self, context: &Context) {
     if let Some(ref mut r) = self.info.reader {
       r.current_page = self.current_page;
       r.pages_count = self.pages_count;
       r.finished = self.finished;
 
-      if self.view_port.zoom_mode == ZoomMode::FitToPage {
-        r.zoom_mode = None;
-        r.top_offset = None;
+      r.zoom_mode = if self.view_port.zoom_mode == ZoomMode::FitToPage {
+        None
       } else {
-        r.zoom_mode = Some(self.view_port.zoom_mode);
-        r.top_offset = Some(self.view_port.top_offset);
-      }
+        Some(self.view_port.zoom_mode)
+      };
+
+      r.scroll_mode = if self.view_port.scroll_mode == ScrollMode::Continuous {
+        Some(ScrollMode::Continuous)
+      } else {
+        None
+      };
+
+      r.top_offset = if self.view_port.layout() == ZoomMode::FitToPage {
+        None
+      } else {
+        Some(self.view_port.top_offset)
+      };
+
+      r.column = if self.view_port.zoom_mode == ZoomMode::FitToColumn {
+        Some(self.view_port.column)
+      } else {
+        None
+      };
 
       r.rotation = Some(CURRENT_DEVICE.to_canonical(context.display.rotation));
 
@@ -3464,12 +6004,46 @@ impl Reader {
         r.contrast_exponent = None;
         r.contrast_gray = None;
       }
+    }
+  }
+
+  // Writes the reading state to the library, unless it hasn't changed since the last save.
+  fn save_state(&mut self, context: &mut Context) {
+    self.update_reader_info(context);
+    if let Some(ref r) = self.info.reader {
+      let state = serde_json::to_string(r).ok();
+      if state.is_none() || state != self.saved_state {
+        context.library.sync_reader_info(&self.info.file.path, r);
+        self.saved_state = state;
+      }
+    }
+  }
 
-      context.library.sync_reader_info(&self.info.file.path, r);
+  fn auto_save(&mut self, hub: &Hub, context: &mut Context) {
+    // The status of a book that wasn't read yet might be reset when it's closed.
+    if self.ephemeral
+      || (self.initial_location == Some(self.current_page)
+        && !context.settings.reader.auto_status.reading_on_open)
+    {
+      return;
+    }
+    self.saved_page_turns = self.page_turns;
+    self.save_state(context);
+    if let Err(e) = context.library.flush() {
+      report_error(hub, Severity::Error, "Can't save the reading state", &e);
     }
   }
 }
 
+// How much the annotation darkens the text it covers.
+fn annotation_drift(annot: &Annotation) -> u8 {
+  if annot.note.is_empty() {
+    HIGHLIGHT_DRIFT
+  } else {
+    ANNOTATION_DRIFT
+  }
+}
+
 impl View for Reader {
   fn handle_event(
     &mut self,
@@ -3479,6 +6053,22 @@ impl View for Reader {
     rq: &mut RenderQueue,
     context: &mut Context,
   ) -> bool {
+    // Each finger is tracked separately, so that a second finger doesn't alter the speed of a swipe.
+    if let Event::Device(DeviceEvent::Finger {
+      id, time, status, ..
+    }) = *evt
+    {
+      match status {
+        FingerStatus::Down => {
+          self.touch_starts.insert(id, time);
+        },
+        FingerStatus::Up => {
+          self.touch_duration = self.touch_starts.remove(&id).map(|start| time - start);
+        },
+        FingerStatus::Motion => (),
+      }
+    }
+
     match *evt {
       Event::Gesture(GestureEvent::Rotate { quarter_turns, .. }) if quarter_turns != 0 => {
         let (_, dir) = CURRENT_DEVICE.mirroring_scheme();
@@ -3487,10 +6077,22 @@ impl View for Reader {
         true
       },
       Event::Gesture(GestureEvent::Swipe { dir, start, end }) if self.rect.includes(start) => {
+        // Dragging the bookmark corner folds or unfolds it.
+        let corner = self.dog_ear_rect(2);
+        if !self.ephemeral && corner.includes(start) && !corner.includes(end) {
+          self.toggle_bookmark(rq);
+          return true;
+        }
         match dir {
           Dir::West => self.go_to_neighbor(CycleDir::Next, hub, rq, context),
           Dir::East => self.go_to_neighbor(CycleDir::Previous, hub, rq, context),
-          Dir::South | Dir::North => self.page_scroll(end.y - start.y, hub, rq, context),
+          Dir::South | Dir::North => {
+            let mut delta_y = end.y - start.y;
+            if let Some(duration) = self.touch_duration.filter(|_| self.reflowable) {
+              delta_y = kinetic_delta(delta_y, duration, self.rect.height());
+            }
+            self.page_scroll(delta_y, hub, rq, context)
+          },
         };
         true
       },
@@ -3552,18 +6154,7 @@ impl View for Reader {
           DiagDir::SouthWest => {
             if context.settings.frontlight_presets.len() > 1 {
               if context.settings.frontlight {
-                let lightsensor_level = if CURRENT_DEVICE.has_lightsensor() {
-                  context.lightsensor.level().ok()
-                } else {
-                  None
-                };
-                if let Some(ref frontlight_levels) =
-                  guess_frontlight(lightsensor_level, &context.settings.frontlight_presets)
-                {
-                  let LightLevels { intensity, warmth } = *frontlight_levels;
-                  context.frontlight.set_intensity(intensity);
-                  context.frontlight.set_warmth(warmth);
-                }
+                context.guess_frontlight();
               }
             } else {
               hub.send(Event::ToggleFrontlight).ok();
@@ -3572,6 +6163,12 @@ impl View for Reader {
         };
         true
       },
+      Event::Gesture(GestureEvent::MultiArrow {
+        dir: Dir::South, ..
+      }) => {
+        self.unlock_rotation(hub, rq, context);
+        true
+      },
       Event::Gesture(GestureEvent::MultiCorner { dir, .. }) => {
         match dir {
           DiagDir::NorthWest => self.go_to_annotation(CycleDir::Previous, hub, rq, context),
@@ -3846,7 +6443,7 @@ impl View for Reader {
           selection.end = end;
         }
         true
-      }
+      },
       Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
         if self.focus.is_some() {
           return true;
@@ -3874,6 +6471,9 @@ impl View for Reader {
         }
 
         if let Some(link) = nearest_link.take() {
+          if self.toc_tree.is_some() && self.toggle_toc_entry(&link.text, hub, rq, context) {
+            return true;
+          }
           let pdf_page = Regex::new(r"^#(\d+)(?:,-?\d+,-?\d+)?$").unwrap();
           let toc_page = Regex::new(r"^@(.+)$").unwrap();
           if let Some(caps) = toc_page.captures(&link.text) {
@@ -3891,6 +6491,13 @@ impl View for Reader {
             if let Ok(index) = caps[1].parse::<usize>() {
               self.go_to_page(index.saturating_sub(1), true, hub, rq, context);
             }
+          } else if let Some((info, fragment)) = self.linked_document(&link.text, context) {
+            hub.send(Event::Open(Box::new(info))).ok();
+            if let Some(fragment) = fragment {
+              hub
+                .send(Event::GoToLocation(Location::Uri(format!("#{}", fragment))))
+                .ok();
+            }
           } else {
             let mut doc = self.doc.lock().unwrap();
             let loc = Location::LocalUri(self.current_page, link.text.clone());
@@ -3903,6 +6510,21 @@ impl View for Reader {
           return true;
         }
 
+        if !self.ephemeral && self.dog_ear_rect(2).includes(center) {
+          self.toggle_bookmark(rq);
+          return true;
+        }
+
+        if context.settings.reader.tap_zones.enabled {
+          let action = context
+            .settings
+            .reader
+            .tap_zones
+            .action_at(&self.rect, center);
+          self.perform_tap_action(action, hub, rq, context);
+          return true;
+        }
+
         let w = self.rect.width() as i32;
         let h = self.rect.height() as i32;
         let m = w.min(h);
@@ -4051,41 +6673,39 @@ impl View for Reader {
         self.update(Some(mode), hub, rq, context);
         true
       },
+      Event::ClockTick => {
+        if self.check_file(context) {
+          hub.send(Event::DocumentChanged).ok();
+        }
+        false
+      },
+      Event::DocumentChanged => {
+        let notif = Notification::new(
+          ViewId::MessageNotif,
+          tr!("The document changed on disk. Tap to reload it."),
+          context,
+        )
+        .with_action(Event::Select(EntryId::ReloadDocument));
+        push_notification(&mut self.children, notif, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ApplyLayoutToDirectory) => {
+        self.apply_layout_to_directory(hub, context);
+        true
+      },
+      Event::Select(EntryId::ReloadDocument) => {
+        self.reload(hub, context);
+        true
+      },
       Event::LoadPixmap(location) => {
         self.load_pixmap(location);
         true
       },
       Event::Submit(ViewId::GoToPageInput, ref text) => {
-        let re = Regex::new(r#"^([-+"'])?(.+)$"#).unwrap();
-        if let Some(caps) = re.captures(text) {
-          let prefix = caps.get(1).map(|m| m.as_str());
-          if prefix == Some("\"") || prefix == Some("'") {
-            if let Some(location) = self.find_page_by_name(&caps[2]) {
-              self.go_to_page(location, true, hub, rq, context);
-            }
-          } else {
-            if text == "_" {
-              let location = (context.rng.next_u64() % self.pages_count as u64) as usize;
-              self.go_to_page(location, true, hub, rq, context);
-            } else if text == "(" {
-              self.go_to_page(0, true, hub, rq, context);
-            } else if text == ")" {
-              self.go_to_page(self.pages_count.saturating_sub(1), true, hub, rq, context);
-            } else if let Ok(number) = caps[2].parse::<f64>() {
-              let location = if !self.synthetic {
-                let mut index = number.max(0.0) as usize;
-                match prefix {
-                  Some("-") => index = self.current_page.saturating_sub(index),
-                  Some("+") => index += self.current_page,
-                  _ => index = index.saturating_sub(1),
-                }
-                index
-              } else {
-                (number * BYTES_PER_PAGE).max(0.0).round() as usize
-              };
-              self.go_to_page(location, true, hub, rq, context);
-            }
-          }
+        if let Some(location) =
+          parse_go_to(text).and_then(|go_to| self.resolve_go_to(go_to, context))
+        {
+          self.go_to_page(location, true, hub, rq, context);
         }
         true
       },
@@ -4133,9 +6753,18 @@ impl View for Reader {
         }
 
         self.update_annotations();
+        self.update_review(rq);
         self.toggle_keyboard(false, None, hub, rq, context);
         true
       },
+      Event::Submit(ViewId::ReaderSearchInput, ref text) if self.toc_tree.is_some() => {
+        if let Some(toc_tree) = self.toc_tree.as_mut() {
+          toc_tree.set_filter(text);
+        }
+        self.toggle_search_bar(false, hub, rq, context);
+        self.rebuild_toc(None, hub, rq, context);
+        true
+      },
       Event::Submit(ViewId::ReaderSearchInput, ref text) => {
         match make_query(text) {
           Some(query) => {
@@ -4146,12 +6775,10 @@ impl View for Reader {
           None => {
             let notif = Notification::new(
               ViewId::InvalidSearchQueryNotif,
-              "Invalid search query.".to_string(),
-              hub,
-              rq,
+              tr!("Invalid search query."),
               context,
             );
-            self.children.push(Box::new(notif) as Box<dyn View>);
+            push_notification(&mut self.children, notif, hub, rq, context);
           },
         }
         true
@@ -4164,6 +6791,17 @@ impl View for Reader {
         self.go_to_page(location, true, hub, rq, context);
         true
       },
+      Event::PagePreview(location) => {
+        self.update_page_preview(location, rq);
+        true
+      },
+      Event::History(dir, _) => {
+        match dir {
+          CycleDir::Previous => self.go_to_last_page(hub, rq, context),
+          CycleDir::Next => self.go_to_next_page_in_history(hub, rq, context),
+        }
+        true
+      },
       Event::GoToLocation(ref location) => {
         let offset_opt = {
           let mut doc = self.doc.lock().unwrap();
@@ -4215,6 +6853,14 @@ impl View for Reader {
         self.toggle_title_menu(rect, None, rq, context);
         true
       },
+      Event::ToggleNear(ViewId::HiddenMenu, rect) => {
+        self.toggle_hidden_menu(rect, None, rq, context);
+        true
+      },
+      Event::ToggleNear(ViewId::RecentMenu, rect) => {
+        self.toggle_recent_menu(rect, None, rq, context);
+        true
+      },
       Event::ToggleNear(ViewId::MainMenu, rect) => {
         toggle_main_menu(self, rect, None, rq, context);
         true
@@ -4311,20 +6957,42 @@ impl View for Reader {
         self.toggle_keyboard(false, None, hub, rq, context);
         false
       },
+      Event::Select(EntryId::Skim) => {
+        self.skim(hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ReadAloud) => {
+        if self.speech.is_some() {
+          self.stop_speech(rq);
+        } else {
+          self.start_speech(hub, rq, context);
+        }
+        true
+      },
+      Event::Spoken(id) => {
+        if self
+          .speech
+          .as_ref()
+          .map_or(false, |s| s.synthesizer.id == id)
+        {
+          self.speak_next(hub, rq, context);
+        }
+        true
+      },
       Event::Show(ViewId::TableOfContents) => {
         {
           self.toggle_bars(Some(false), hub, rq, context);
         }
         let mut doc = self.doc.lock().unwrap();
-        if let Some(toc) = self
-          .toc()
-          .or_else(|| doc.toc())
-          .filter(|toc| !toc.is_empty())
-        {
-          let chap_index = doc
-            .chapter(self.current_page, &toc)
-            .map(|chap| chap.index)
-            .unwrap_or(usize::MAX);
+        let mut toc = self.toc().or_else(|| doc.toc()).unwrap_or_default();
+        let chap_index = doc
+          .chapter(self.current_page, &toc)
+          .map(|chap| chap.index)
+          .unwrap_or(usize::MAX);
+        if let Some(entry) = self.named_pages_entry(next_toc_index(&toc)) {
+          toc.push(entry);
+        }
+        if !toc.is_empty() {
           hub.send(Event::OpenToc(toc, chap_index)).ok();
         }
         true
@@ -4383,17 +7051,34 @@ impl View for Reader {
         if results_count == 0 {
           let notif = Notification::new(
             ViewId::NoSearchResultsNotif,
-            "No search results.".to_string(),
-            hub,
-            rq,
+            tr!("No search results."),
             context,
           );
-          self.children.push(Box::new(notif) as Box<dyn View>);
+          push_notification(&mut self.children, notif, hub, rq, context);
           self.toggle_search_bar(true, hub, rq, context);
           hub.send(Event::Focus(Some(ViewId::ReaderSearchInput))).ok();
         }
         true
       },
+      Event::Finished => {
+        let notif = Notification::new(
+          ViewId::FinishedNotif,
+          tr!("Marked as finished. Tap to undo."),
+          context,
+        )
+        .with_action(Event::Select(EntryId::SetStatus(
+          self.info.file.path.clone(),
+          SimpleStatus::Reading,
+        )));
+        push_notification(&mut self.children, notif, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::SetStatus(ref path, status)) if *path == self.info.file.path => {
+        self.finished = status == SimpleStatus::Finished;
+        // Reaching the end again is required to finish the book again.
+        self.auto_finished = true;
+        true
+      },
       Event::Select(EntryId::AnnotateSelection) => {
         self.toggle_edit_note(None, Some(true), hub, rq, context);
         true
@@ -4417,6 +7102,15 @@ impl View for Reader {
 
         true
       },
+      Event::Select(EntryId::TranslateSelection) => {
+        if let Some(sel) = self.selection.take() {
+          if let Some(rect) = self.text_rect([sel.start, sel.end]) {
+            rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+          }
+          self.translate([sel.start, sel.end], hub, rq, context);
+        }
+        true
+      },
       Event::Select(EntryId::DefineSelection) => {
         if let Some(text) = self.selected_text() {
           let query = text
@@ -4443,12 +7137,10 @@ impl View for Reader {
             None => {
               let notif = Notification::new(
                 ViewId::InvalidSearchQueryNotif,
-                "Invalid search query.".to_string(),
-                hub,
-                rq,
+                tr!("Invalid search query."),
                 context,
               );
-              self.children.push(Box::new(notif) as Box<dyn View>);
+              push_notification(&mut self.children, notif, hub, rq, context);
             },
           }
         }
@@ -4458,6 +7150,16 @@ impl View for Reader {
         self.selection = None;
         true
       },
+      Event::Select(EntryId::CopySelection) => {
+        if let Some(text) = self.selected_text() {
+          hub.send(Event::Select(EntryId::Copy(text))).ok();
+        }
+        if let Some(rect) = self.selection_rect() {
+          rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+        }
+        self.selection = None;
+        true
+      },
       Event::Select(EntryId::GoToSelectedPageName) => {
         self
           .selected_text()
@@ -4496,6 +7198,7 @@ impl View for Reader {
           annot.modified = Local::now();
           self.update_annotations();
         }
+        self.update_review(rq);
         if let Some(rect) = self.text_rect(sel) {
           rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
         }
@@ -4503,18 +7206,85 @@ impl View for Reader {
       },
       Event::Select(EntryId::RemoveAnnotation(sel)) => {
         if let Some(annotations) = self.info.reader.as_mut().map(|r| &mut r.annotations) {
+          let removed = annotations
+            .iter()
+            .find(|annot| annot.selection == sel)
+            .cloned();
           annotations.retain(|annot| annot.selection[0] != sel[0] || annot.selection[1] != sel[1]);
           self.update_annotations();
+          if let Some(annotation) = removed {
+            let path = self.info.file.path.clone();
+            record_undo(
+              UndoAction::RemoveAnnotation { path, annotation },
+              hub,
+              context,
+            );
+          }
         }
         if let Some(rect) = self.text_rect(sel) {
           rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
         }
+        if self.review == Some(sel) {
+          self.review_after_removal(sel, hub, rq, context);
+        }
+        true
+      },
+      Event::Select(EntryId::ReviewAnnotations(sel)) => {
+        self.start_review(sel, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ReviewAnnotation(dir)) => {
+        self.review_neighbor(dir, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::StopReview) => {
+        self.stop_review(rq);
         true
       },
       Event::Select(EntryId::SetZoomMode(zoom_mode)) => {
         self.set_zoom_mode(zoom_mode, hub, rq, context);
         true
       },
+      Event::Select(EntryId::SetScrollMode(scroll_mode)) => {
+        self.set_scroll_mode(scroll_mode, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ToggleVerseLayout) => {
+        self.toggle_verse_layout(hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ToggleScanFilter(filter)) => {
+        self.toggle_scan_filter(filter, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::SetBookRotationLock(rotation_lock)) => {
+        if let Some(r) = self.info.reader.as_mut() {
+          r.rotation_lock = rotation_lock;
+        }
+        self.rotation_unlocked = None;
+        true
+      },
+      Event::Select(EntryId::OpenRecent(ref path)) => {
+        // Going back first keeps a single reader in the history.
+        if let Some(info) = context.library.document_info(path) {
+          hub.send(Event::Back).ok();
+          hub.send(Event::Open(Box::new(info))).ok();
+        }
+        true
+      },
+      Event::Select(EntryId::ToggleDiagnostics) => {
+        self.diagnostics = if self.diagnostics.is_some() {
+          None
+        } else {
+          Some(Diagnostics::default())
+        };
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
+      Event::Select(EntryId::UnlockRotation) => {
+        self.unlock_rotation(hub, rq, context);
+        true
+      },
       Event::Select(EntryId::Save) => {
         let name = format!(
           "{}-{}.{}",
@@ -4525,10 +7295,34 @@ impl View for Reader {
         let doc = self.doc.lock().unwrap();
         let msg = match doc.save(&name) {
           Err(e) => format!("{}", e),
-          Ok(()) => format!("Saved {}.", name),
+          Ok(()) => tr!("Saved {}.", name),
         };
-        let notif = Notification::new(ViewId::SaveDocumentNotif, msg, hub, rq, context);
-        self.children.push(Box::new(notif) as Box<dyn View>);
+        let notif = Notification::new(ViewId::SaveDocumentNotif, msg, context);
+        push_notification(&mut self.children, notif, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ExportText(format)) => {
+        self.export_text(format, hub, context);
+        true
+      },
+      Event::Select(EntryId::CiteSelection) => {
+        self.cite_selection(hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ShareBook) => {
+        self.share(hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ExportAnnotations(format)) => {
+        self.export_annotations(format, hub, context);
+        true
+      },
+      Event::Select(EntryId::ExportAnnotationsBibtex) => {
+        self.export_annotations_bibtex(hub, context);
+        true
+      },
+      Event::Select(EntryId::SetCitationStyle(style)) => {
+        context.settings.reader.citation_style = style;
         true
       },
       Event::Select(EntryId::ApplyCroppings(index, scheme)) => {
@@ -4552,14 +7346,67 @@ impl View for Reader {
         self.search_direction = dir;
         true
       },
+      Event::Select(EntryId::SearchScope(scope)) => {
+        self.search_scope = scope;
+        true
+      },
+      Event::Select(EntryId::ReloadFonts) => {
+        context.load_font_index();
+        let count = context.font_index.family_names().len();
+        hub
+          .send(Event::Notify(if count == 1 {
+            tr!("Found 1 font family.")
+          } else {
+            tr!("Found {} font families.", count)
+          }))
+          .ok();
+        true
+      },
       Event::Select(EntryId::SetFontFamily(ref font_family)) => {
         self.set_font_family(font_family, hub, rq, context);
         true
       },
+      Event::Select(EntryId::SetTextAlignOverride(value)) => {
+        self.set_text_align_override(value, hub, rq, context);
+        true
+      },
       Event::Select(EntryId::SetTextAlign(text_align)) => {
         self.set_text_align(text_align, hub, rq, context);
         true
       },
+      Event::Select(EntryId::ToggleHangingPunctuation) => {
+        let mut typography = context.settings.reader.typography;
+        typography.hanging_punctuation = !typography.hanging_punctuation;
+        self.set_typography(typography, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::ToggleWidowOrphanControl) => {
+        let mut typography = context.settings.reader.typography;
+        typography.widow_orphan_control = !typography.widow_orphan_control;
+        self.set_typography(typography, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::SetMaxHyphenatedLines(n)) => {
+        if let Some(ref mut r) = self.info.reader {
+          r.max_hyphenated_lines = Some(n);
+        }
+        self.set_typography(context.settings.reader.typography, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::SetMinHyphenatedLength(n)) => {
+        if let Some(ref mut r) = self.info.reader {
+          r.min_hyphenated_length = Some(n);
+        }
+        self.set_typography(context.settings.reader.typography, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::SetStretchTolerance(v)) => {
+        if let Some(ref mut r) = self.info.reader {
+          r.stretch_tolerance = Some(v as f32 / 100.0);
+        }
+        self.set_typography(context.settings.reader.typography, hub, rq, context);
+        true
+      },
       Event::Select(EntryId::SetFontSize(v)) => {
         let font_size = self
           .info
@@ -4567,10 +7414,14 @@ impl View for Reader {
           .as_ref()
           .and_then(|r| r.font_size)
           .unwrap_or(context.settings.reader.font_size);
-        let font_size = font_size - 1.0 + v as f32 / 10.0;
+        let font_size = font_size + v as f32 * CURRENT_DEVICE.font_size_step();
         self.set_font_size(font_size, hub, rq, context);
         true
       },
+      Event::Select(EntryId::SetEdgeMargin(dir, width)) => {
+        self.set_edge_margin(dir, width, hub, rq, context);
+        true
+      },
       Event::Select(EntryId::SetMarginWidth(width)) => {
         self.set_margin_width(width, hub, rq, context);
         true
@@ -4590,14 +7441,45 @@ impl View for Reader {
         self.set_contrast_gray(gray, hub, rq, context);
         true
       },
+      Event::Select(EntryId::ToggleThumbnails) => {
+        self.toggle_thumbnails(None, hub, rq);
+        true
+      },
+      Event::Close(ViewId::Thumbnails) => {
+        self.toggle_thumbnails(Some(false), hub, rq);
+        true
+      },
       Event::Select(EntryId::SetPageName) => {
         self.toggle_name_page(None, hub, rq, context);
         true
       },
       Event::Select(EntryId::RemovePageName) => {
+        let page = self.current_page;
+        if let Some(name) = self
+          .info
+          .reader
+          .as_mut()
+          .and_then(|r| r.page_names.remove(&page))
+        {
+          let path = self.info.file.path.clone();
+          record_undo(
+            UndoAction::RemovePageName { path, page, name },
+            hub,
+            context,
+          );
+        }
+        true
+      },
+      Event::Revert(ref action, dir) if action.book_path() == Some(&self.info.file.path) => {
         if let Some(ref mut r) = self.info.reader {
-          r.page_names.remove(&self.current_page);
+          action.apply_to_reader(r, dir);
         }
+        self.update_annotations();
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
+      Event::AutoSave => {
+        self.auto_save(hub, context);
         true
       },
       Event::Reseed => {
@@ -4651,9 +7533,22 @@ impl View for Reader {
     }
   }
 
-  fn render(&self, fb: &mut dyn Framebuffer, rect: Rectangle, _fonts: &mut Fonts) {
+  fn render(&self, fb: &mut dyn Framebuffer, rect: Rectangle, fonts: &mut Fonts) {
     fb.draw_rectangle(&rect, WHITE);
 
+    if self.header_height > 0 && rect.min.y < self.page_rect().min.y {
+      let font = font_from_style(fonts, &header_style(self.header_height), CURRENT_DEVICE.dpi);
+      let padding = font.em() as i32;
+      let max_width = self.rect.width() as i32 - 2 * padding;
+      let plan = font.plan(&self.header, Some(max_width), None);
+      let x_height = font.x_heights.0 as i32;
+      let pt = pt!(
+        self.rect.center().x - plan.width / 2,
+        self.rect.min.y + (self.header_height + x_height) / 2
+      );
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    }
+
     for chunk in &self.chunks {
       let Resource {
         ref pixmap, scale, ..
@@ -4715,11 +7610,11 @@ impl View for Reader {
 
         if let Some(annotations) = self.annotations.get(&chunk.location) {
           for annot in annotations {
-            let drift = if annot.note.is_empty() {
-              HIGHLIGHT_DRIFT
-            } else {
-              ANNOTATION_DRIFT
-            };
+            let mut drift = annotation_drift(annot);
+            // The reviewed annotation stands out from the others.
+            if self.review == Some(annot.selection) {
+              drift *= 2;
+            }
             let [start, end] = annot.selection;
             if let Some(text) = self.text.get(&chunk.location) {
               let mut last_rect: Option<Rectangle> = None;
@@ -4762,12 +7657,19 @@ impl View for Reader {
           }
         }
 
-        if let Some(sel) = self.selection.as_ref() {
+        // The sentence read aloud is shown like the selection.
+        let selections = self
+          .selection
+          .as_ref()
+          .map(|sel| [sel.start, sel.end])
+          .into_iter()
+          .chain(self.speech.as_ref().map(|s| s.sentence));
+        for [start, end] in selections {
           if let Some(text) = self.text.get(&chunk.location) {
             let mut last_rect: Option<Rectangle> = None;
             for word in text
               .iter()
-              .filter(|w| w.location >= sel.start && w.location <= sel.end)
+              .filter(|w| w.location >= start && w.location <= end)
             {
               let rect = (word.rect * scale).to_rect() - chunk.frame.min + chunk.position;
               if let Some(ref sel_rect) = rect.intersection(&region_rect) {
@@ -4810,20 +7712,20 @@ impl View for Reader {
       .reader
       .as_ref()
       .map_or(false, |r| r.bookmarks.contains(&self.current_page))
+      && rect.overlaps(&self.dog_ear_rect(1))
     {
-      let dpi = CURRENT_DEVICE.dpi;
-      let thickness = scale_by_dpi(3.0, dpi) as u16;
-      let radius = mm_to_px(0.4, dpi) as i32 + thickness as i32;
-      let center = pt!(self.rect.max.x - 5 * radius, self.rect.min.y + 5 * radius);
-      fb.draw_rounded_rectangle_with_border(
-        &Rectangle::from_disk(center, radius),
-        &CornerSpec::Uniform(radius),
-        &BorderSpec {
-          thickness,
-          color: WHITE,
-        },
-        &BLACK,
-      );
+      self.draw_dog_ear(fb);
+    }
+
+    if let Some(diagnostics) = self.diagnostics.as_ref() {
+      let locations: Vec<usize> = self.chunks.iter().map(|c| c.location).collect();
+      let cached_bytes = self
+        .cache
+        .values()
+        .map(|r| r.pixmap.data.len() as u64)
+        .sum();
+      let lines = diagnostics.lines(&locations, self.cache.len(), cached_bytes);
+      diagnostics.render(fb, &self.page_rect(), lines, fonts);
     }
   }
 
@@ -4831,6 +7733,22 @@ impl View for Reader {
     rect.intersection(&self.rect).unwrap_or(self.rect)
   }
 
+  fn inversion_exemptions(&self) -> Vec<Rectangle> {
+    let mut exemptions = Vec::new();
+    for chunk in &self.chunks {
+      let chunk_rect = chunk.frame - chunk.frame.min + chunk.position;
+      if let Some(resource) = self.cache.get(&chunk.location) {
+        exemptions.extend(
+          resource
+            .images
+            .iter()
+            .filter_map(|r| (*r - chunk.frame.min + chunk.position).intersection(&chunk_rect)),
+        );
+      }
+    }
+    exemptions
+  }
+
   fn resize(&mut self, rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
     if !self.children.is_empty() {
       let dpi = CURRENT_DEVICE.dpi;
@@ -4842,7 +7760,9 @@ impl View for Reader {
       );
       let mut floating_layer_start = 0;
 
-      self.children.retain(|child| !child.is::<Menu>());
+      self
+        .children
+        .retain(|child| !child.is::<Menu>() && !child.is::<PagePreview>());
 
       if self.children[0].is::<TopBar>() {
         let top_bar_rect = rect![
@@ -4967,7 +7887,7 @@ impl View for Reader {
       }
     }
 
-    if self.view_port.zoom_mode == ZoomMode::FitToWidth {
+    if self.view_port.layout() != ZoomMode::FitToPage {
       let ratio = (rect.width() as i32 - 2 * self.view_port.margin_width) as f32
         / (self.rect.width() as i32 - 2 * self.view_port.margin_width) as f32;
       self.view_port.top_offset = (self.view_port.top_offset as f32 * ratio) as i32;
@@ -4983,9 +7903,13 @@ impl View for Reader {
         .and_then(|r| r.font_size)
         .unwrap_or(context.settings.reader.font_size);
       let mut doc = self.doc.lock().unwrap();
-      doc.layout(rect.width(), rect.height(), font_size, CURRENT_DEVICE.dpi);
-      let current_page = self.current_page.min(doc.pages_count() - 1);
-      if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
+      doc.layout(
+        rect.width(),
+        rect.height() - self.header_height as u32,
+        font_size,
+        CURRENT_DEVICE.dpi,
+      );
+      if let Some(location) = doc.resolve_location(Location::Exact(self.current_page)) {
         self.current_page = location;
       }
       self.text.clear();
@@ -5022,4 +7946,21 @@ impl View for Reader {
   fn id(&self) -> Id {
     self.id
   }
+
+  fn commands(&self, context: &Context) -> Vec<PaletteCommand> {
+    let mut commands = vec![
+      PaletteCommand::new("Table of Contents", Event::Show(ViewId::TableOfContents)),
+      PaletteCommand::new("Search", Event::Show(ViewId::SearchBar)),
+      PaletteCommand::new("Go To Page", Event::Toggle(ViewId::GoToPage)),
+      PaletteCommand::new("Toggle Bars", Event::Toggle(ViewId::TopBottomBars)),
+    ];
+    if !self.reflowable {
+      commands.push(PaletteCommand::new(
+        "Crop Margins",
+        Event::Show(ViewId::MarginCropper),
+      ));
+    }
+    menu_commands(&self.title_menu_entries(context), "", &mut commands);
+    commands
+  }
 }
@@ -0,0 +1,142 @@
+use crate::{
+  app::Context,
+  color::{BLACK, WHITE},
+  device::CURRENT_DEVICE,
+  font::Fonts,
+  framebuffer::{Framebuffer, Pixmap},
+  geom::{BorderSpec, Rectangle},
+  unit::scale_by_dpi,
+  view::{Bus, Event, Hub, Id, RenderQueue, View, ID_FEEDER, THICKNESS_LARGE},
+};
+
+// The thumbnail of the page targeted while scrubbing the page label.
+pub struct PagePreview {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  location: usize,
+  pixmap: Pixmap,
+}
+
+// Scales *pixmap* down to fit in the given dimensions, averaging the pixels of each source area.
+pub fn scale_down(pixmap: &Pixmap, max_width: u32, max_height: u32) -> Pixmap {
+  let scale = (max_width as f32 / pixmap.width as f32)
+    .min(max_height as f32 / pixmap.height as f32)
+    .min(1.0);
+  let width = ((pixmap.width as f32 * scale) as u32).max(1);
+  let height = ((pixmap.height as f32 * scale) as u32).max(1);
+  let mut result = Pixmap::new(width, height);
+  for y in 0..height {
+    let y0 = (y as f32 / scale) as u32;
+    let y1 = (((y + 1) as f32 / scale) as u32)
+      .max(y0 + 1)
+      .min(pixmap.height);
+    for x in 0..width {
+      let x0 = (x as f32 / scale) as u32;
+      let x1 = (((x + 1) as f32 / scale) as u32)
+        .max(x0 + 1)
+        .min(pixmap.width);
+      let mut sum = 0;
+      for sy in y0..y1 {
+        let row = (sy * pixmap.width) as usize;
+        for sx in x0..x1 {
+          sum += pixmap.data[row + sx as usize] as u32;
+        }
+      }
+      let count = (y1.saturating_sub(y0) * x1.saturating_sub(x0)).max(1);
+      result.data[(y * width + x) as usize] = (sum / count) as u8;
+    }
+  }
+  result
+}
+
+impl PagePreview {
+  // The preview is centered within *rect*.
+  pub fn new(rect: Rectangle, location: usize, pixmap: Pixmap) -> PagePreview {
+    PagePreview {
+      id: ID_FEEDER.next(),
+      rect: preview_rect(rect, &pixmap),
+      children: Vec::new(),
+      location,
+      pixmap,
+    }
+  }
+
+  pub fn location(&self) -> usize {
+    self.location
+  }
+}
+
+fn preview_rect(rect: Rectangle, pixmap: &Pixmap) -> Rectangle {
+  let border = scale_by_dpi(THICKNESS_LARGE, CURRENT_DEVICE.dpi) as i32;
+  let width = pixmap.width as i32 + 2 * border;
+  let height = pixmap.height as i32 + 2 * border;
+  let center = rect.center();
+  rect![
+    center.x - width / 2,
+    center.y - height / 2,
+    center.x - width / 2 + width,
+    center.y - height / 2 + height
+  ]
+}
+
+impl View for PagePreview {
+  fn handle_event(
+    &mut self,
+    _evt: &Event,
+    _hub: &Hub,
+    _bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    false
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {
+    let border = scale_by_dpi(THICKNESS_LARGE, CURRENT_DEVICE.dpi) as u16;
+    fb.draw_rectangle(&self.rect, WHITE);
+    fb.draw_pixmap(&self.pixmap, self.rect.min + border as i32);
+    fb.draw_rectangle_outline(
+      &self.rect,
+      &BorderSpec {
+        thickness: border,
+        color: BLACK,
+      },
+    );
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::scale_down;
+  use crate::framebuffer::Pixmap;
+
+  #[test]
+  fn test_scale_down() {
+    let mut pixmap = Pixmap::new(4, 2);
+    pixmap.data = vec![0, 0, 255, 255, 0, 0, 255, 127];
+    let result = scale_down(&pixmap, 2, 2);
+    assert_eq!((result.width, result.height), (2, 1));
+    assert_eq!(result.data, vec![0, 223]);
+  }
+}
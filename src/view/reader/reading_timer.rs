@@ -0,0 +1,64 @@
+use crate::metadata::ReadingSpeed;
+use std::time::Instant;
+
+// The pages read faster or slower than this, in seconds, aren't taken into account.
+const MIN_PAGE_TIME: f64 = 2.0;
+const MAX_PAGE_TIME: f64 = 600.0;
+// Pages with fewer words, e.g. title pages, aren't taken into account.
+const MIN_PAGE_WORDS: usize = 20;
+
+// Measures the time spent on each location.
+pub struct ReadingTimer {
+  location: usize,
+  start: Instant,
+  // The speed measured since the document was opened.
+  pub session: ReadingSpeed,
+  // The speed measured across all the books when the document was opened.
+  pub global: ReadingSpeed,
+}
+
+impl ReadingTimer {
+  pub fn new(location: usize, global: ReadingSpeed) -> ReadingTimer {
+    ReadingTimer {
+      location,
+      start: Instant::now(),
+      session: ReadingSpeed::default(),
+      global,
+    }
+  }
+
+  pub fn location(&self) -> usize {
+    self.location
+  }
+
+  // Restarts the timer on *location*. Returns the sample measured on the previous location if
+  // *location* follows it. *words* is the number of words of the previous location.
+  pub fn advance(
+    &mut self,
+    location: usize,
+    next: Option<usize>,
+    words: usize,
+  ) -> Option<ReadingSpeed> {
+    let previous = self.location;
+    let seconds = self.start.elapsed().as_secs_f64();
+    self.location = location;
+    self.start = Instant::now();
+
+    if next != Some(location)
+      || location <= previous
+      || words < MIN_PAGE_WORDS
+      || seconds < MIN_PAGE_TIME
+      || seconds > MAX_PAGE_TIME
+    {
+      return None;
+    }
+
+    let sample = ReadingSpeed {
+      words: words as u64,
+      seconds,
+      span: (location - previous) as u64,
+    };
+    self.session.add(&sample);
+    Some(sample)
+  }
+}
@@ -1,4 +1,4 @@
-use super::results_label::ResultsLabel;
+use super::{results_label::ResultsLabel, SearchScope};
 use crate::{
   app::Context,
   color::WHITE,
@@ -39,6 +39,7 @@ impl ResultsBar {
     pages_count: usize,
     count: usize,
     completed: bool,
+    scope: SearchScope,
   ) -> ResultsBar {
     let id = ID_FEEDER.next();
     let mut children = Vec::new();
@@ -68,6 +69,7 @@ impl ResultsBar {
       ],
       count,
       completed,
+      scope,
     );
     children.push(Box::new(results_label) as Box<dyn View>);
 
@@ -1,3 +1,4 @@
+use super::SearchScope;
 use crate::{
   app::Context,
   color::TEXT_NORMAL,
@@ -14,16 +15,18 @@ pub struct ResultsLabel {
   children: Vec<Box<dyn View>>,
   count: usize,
   completed: bool,
+  scope: SearchScope,
 }
 
 impl ResultsLabel {
-  pub fn new(rect: Rectangle, count: usize, completed: bool) -> ResultsLabel {
+  pub fn new(rect: Rectangle, count: usize, completed: bool, scope: SearchScope) -> ResultsLabel {
     ResultsLabel {
       id: ID_FEEDER.next(),
       rect,
       children: vec![],
       count,
       completed,
+      scope,
     }
   }
 
@@ -33,12 +36,16 @@ impl ResultsLabel {
   }
 
   fn text(&self) -> String {
-    let qualifier = if self.count != 1 { "results" } else { "result" };
-
-    if self.count == 0 {
-      format!("No {}", qualifier)
-    } else {
-      format!("{} {}", self.count, qualifier)
+    match (self.scope, self.count) {
+      (SearchScope::Book, 0) => tr!("No results"),
+      (SearchScope::Book, 1) => tr!("1 result"),
+      (SearchScope::Book, n) => tr!("{} results", n),
+      (SearchScope::Chapter, 0) => tr!("No results in chapter"),
+      (SearchScope::Chapter, 1) => tr!("1 result in chapter"),
+      (SearchScope::Chapter, n) => tr!("{} results in chapter", n),
+      (SearchScope::Onward, 0) => tr!("No results onward"),
+      (SearchScope::Onward, 1) => tr!("1 result onward"),
+      (SearchScope::Onward, n) => tr!("{} results onward", n),
     }
   }
 }
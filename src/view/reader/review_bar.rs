@@ -0,0 +1,258 @@
+use crate::{
+  app::Context,
+  color::{BLACK, SEPARATOR_NORMAL, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{BorderSpec, CycleDir, Rectangle},
+  gesture::GestureEvent,
+  metadata::Annotation,
+  unit::scale_by_dpi,
+  view::{
+    button::Button,
+    filler::Filler,
+    icon::Icon,
+    Bus,
+    EntryId,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_MEDIUM,
+  },
+};
+
+// Steps through the annotations of a book: the current one is shown with its position,
+// its kind, and the actions that can be applied to it.
+pub struct ReviewBar {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  annotation: Annotation,
+  index: usize,
+  count: usize,
+  label: String,
+  // The shade used to draw the current annotation in the page.
+  marker: u8,
+}
+
+impl ReviewBar {
+  // The bar is placed at the top of *rect*.
+  pub fn new(
+    rect: Rectangle,
+    annotation: Annotation,
+    index: usize,
+    count: usize,
+    marker: u8,
+  ) -> ReviewBar {
+    let mut review_bar = ReviewBar {
+      id: ID_FEEDER.next(),
+      rect: bar_rect(rect),
+      children: Vec::new(),
+      annotation,
+      index,
+      count,
+      label: String::new(),
+      marker,
+    };
+    review_bar.layout();
+    review_bar
+  }
+
+  fn layout(&mut self) {
+    let rect = self.rect;
+    let (annot, index, count) = (&self.annotation, self.index, self.count);
+    let side = rect.height() as i32 / 2;
+    let padding = side / 6;
+    let kind = if annot.note.is_empty() {
+      "Highlight"
+    } else {
+      "Note"
+    };
+    let excerpt = if annot.note.is_empty() {
+      &annot.text
+    } else {
+      &annot.note
+    };
+    self.label = format!("{} {}/{}: {}", tr!(kind), index + 1, count, excerpt);
+    self.children.clear();
+
+    let prev_rect = rect![rect.min.x, rect.min.y, rect.min.x + side, rect.min.y + side];
+    if index > 0 {
+      self.children.push(Box::new(Icon::new(
+        "angle-left",
+        prev_rect,
+        Event::Select(EntryId::ReviewAnnotation(CycleDir::Previous)),
+      )) as Box<dyn View>);
+    } else {
+      self
+        .children
+        .push(Box::new(Filler::new(prev_rect, WHITE)) as Box<dyn View>);
+    }
+
+    let next_rect = rect![
+      rect.max.x - 2 * side,
+      rect.min.y,
+      rect.max.x - side,
+      rect.min.y + side
+    ];
+    if index + 1 < count {
+      self.children.push(Box::new(Icon::new(
+        "angle-right",
+        next_rect,
+        Event::Select(EntryId::ReviewAnnotation(CycleDir::Next)),
+      )) as Box<dyn View>);
+    } else {
+      self
+        .children
+        .push(Box::new(Filler::new(next_rect, WHITE)) as Box<dyn View>);
+    }
+
+    self.children.push(Box::new(Icon::new(
+      "close",
+      rect![rect.max.x - side, rect.min.y, rect.max.x, rect.min.y + side],
+      Event::Select(EntryId::StopReview),
+    )) as Box<dyn View>);
+
+    let sel = annot.selection;
+    let actions = [
+      (
+        if annot.note.is_empty() {
+          "Add Note"
+        } else {
+          "Edit Note"
+        },
+        EntryId::EditAnnotationNote(sel),
+      ),
+      ("Remove", EntryId::RemoveAnnotation(sel)),
+      ("Export", EntryId::ExportAnnotationsBibtex),
+    ];
+    let button_width = (rect.width() as i32 - padding) / actions.len() as i32;
+    for (i, (text, entry_id)) in actions.iter().enumerate() {
+      let x_min = rect.min.x + padding + i as i32 * button_width;
+      let button_rect = rect![
+        x_min,
+        rect.min.y + side + padding,
+        x_min + button_width - padding,
+        rect.max.y - padding
+      ];
+      self.children.push(Box::new(Button::new(
+        button_rect,
+        Event::Select(entry_id.clone()),
+        tr!(text),
+      )) as Box<dyn View>);
+    }
+  }
+
+  pub fn update(
+    &mut self,
+    annotation: Annotation,
+    index: usize,
+    count: usize,
+    marker: u8,
+    rq: &mut RenderQueue,
+  ) {
+    self.annotation = annotation;
+    self.index = index;
+    self.count = count;
+    self.marker = marker;
+    self.layout();
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
+}
+
+fn bar_rect(rect: Rectangle) -> Rectangle {
+  let height = 2 * scale_by_dpi(SMALL_BAR_HEIGHT, CURRENT_DEVICE.dpi) as i32;
+  rect![rect.min.x, rect.min.y, rect.max.x, rect.min.y + height]
+}
+
+impl View for ReviewBar {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    _hub: &Hub,
+    _bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => true,
+      Event::Gesture(GestureEvent::Swipe { start, .. }) if self.rect.includes(start) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let side = self.rect.height() as i32 / 2;
+    fb.draw_rectangle(&self.rect, WHITE);
+    fb.draw_rectangle(
+      &rect![
+        self.rect.min.x,
+        self.rect.max.y - thickness,
+        self.rect.max.x,
+        self.rect.max.y
+      ],
+      SEPARATOR_NORMAL,
+    );
+
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let marker_side = 2 * x_height;
+    let marker_rect = rect![
+      self.rect.min.x + side,
+      self.rect.min.y + (side - marker_side) / 2,
+      self.rect.min.x + side + marker_side,
+      self.rect.min.y + (side + marker_side) / 2
+    ];
+    fb.draw_rectangle(&marker_rect, self.marker);
+    fb.draw_rectangle_outline(
+      &marker_rect,
+      &BorderSpec {
+        thickness: 1,
+        color: BLACK,
+      },
+    );
+
+    let x_min = marker_rect.max.x + x_height;
+    let max_width = self.rect.max.x - 2 * side - x_height - x_min;
+    let plan = font.plan(&self.label, Some(max_width), None);
+    let pt = pt!(x_min, self.rect.min.y + (side + x_height) / 2);
+    font.render(fb, TEXT_NORMAL[1], &plan, pt);
+  }
+
+  fn resize(&mut self, rect: Rectangle, _hub: &Hub, _rq: &mut RenderQueue, _context: &mut Context) {
+    self.rect = bar_rect(rect);
+    self.layout();
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+
+  fn view_id(&self) -> Option<ViewId> {
+    Some(ViewId::ReviewBar)
+  }
+}
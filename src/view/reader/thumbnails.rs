@@ -0,0 +1,365 @@
+use super::page_preview::scale_down;
+use crate::{
+  app::Context,
+  color::{BLACK, GRAY08, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  document::{Document, Location},
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, Pixmap, UpdateMode},
+  geom::{BorderSpec, Dir, Rectangle},
+  gesture::GestureEvent,
+  unit::scale_by_dpi,
+  view::{
+    icon::Icon,
+    label::Label,
+    Align,
+    Bus,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_LARGE,
+    THICKNESS_MEDIUM,
+  },
+};
+use fxhash::FxHashMap;
+use std::{
+  ops::Range,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+    Mutex,
+  },
+  thread,
+};
+
+// A grid of the thumbnails of the pages of a document. The thumbnails of the visible pages are
+// rendered in the background, one after the other.
+pub struct Thumbnails {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  doc: Arc<Mutex<Box<dyn Document>>>,
+  pages_count: usize,
+  current_page: usize,
+  thumbnails: FxHashMap<usize, Pixmap>,
+  cells: Vec<Rectangle>,
+  grid_page: usize,
+  columns: usize,
+  rows: usize,
+  // Incremented when the visible pages change, to stop the pending renderings.
+  epoch: Arc<AtomicUsize>,
+}
+
+impl Thumbnails {
+  pub fn new(
+    rect: Rectangle,
+    doc: Arc<Mutex<Box<dyn Document>>>,
+    pages_count: usize,
+    current_page: usize,
+  ) -> Thumbnails {
+    let mut thumbnails = Thumbnails {
+      id: ID_FEEDER.next(),
+      rect,
+      children: Vec::new(),
+      doc,
+      pages_count,
+      current_page,
+      thumbnails: FxHashMap::default(),
+      cells: Vec::new(),
+      grid_page: 0,
+      columns: 4,
+      rows: 4,
+      epoch: Arc::new(AtomicUsize::new(0)),
+    };
+    thumbnails.layout();
+    thumbnails.grid_page = current_page / (thumbnails.columns * thumbnails.rows);
+    thumbnails.layout();
+    thumbnails
+  }
+
+  fn bar_height(&self) -> i32 {
+    scale_by_dpi(SMALL_BAR_HEIGHT, CURRENT_DEVICE.dpi) as i32
+  }
+
+  fn grid_pages_count(&self) -> usize {
+    let per_page = self.columns * self.rows;
+    ((self.pages_count + per_page - 1) / per_page).max(1)
+  }
+
+  pub fn visible_locations(&self) -> Range<usize> {
+    let per_page = self.columns * self.rows;
+    let start = self.grid_page * per_page;
+    start..(start + per_page).min(self.pages_count)
+  }
+
+  // The largest dimensions of a thumbnail.
+  pub fn thumbnail_size(&self) -> (u32, u32) {
+    let bar_height = self.bar_height();
+    self.cells.first().map_or((1, 1), |cell| {
+      (
+        (cell.width() as i32 - bar_height / 2).max(1) as u32,
+        (cell.height() as i32 - bar_height).max(1) as u32,
+      )
+    })
+  }
+
+  pub fn insert(&mut self, location: usize, pixmap: Pixmap) {
+    self.thumbnails.insert(location, pixmap);
+  }
+
+  fn layout(&mut self) {
+    let (columns, rows) = if self.rect.width() > self.rect.height() {
+      (5, 3)
+    } else {
+      (4, 4)
+    };
+    self.columns = columns;
+    self.rows = rows;
+    self.grid_page = self.grid_page.min(self.grid_pages_count() - 1);
+
+    let bar_height = self.bar_height();
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, CURRENT_DEVICE.dpi) as i32;
+    let title_rect = rect![
+      self.rect.min.x + bar_height,
+      self.rect.min.y,
+      self.rect.max.x - bar_height,
+      self.rect.min.y + bar_height
+    ];
+    let close_rect = rect![
+      self.rect.max.x - bar_height,
+      self.rect.min.y,
+      self.rect.max.x,
+      self.rect.min.y + bar_height
+    ];
+    let title = if self.grid_pages_count() > 1 {
+      tr!(
+        "Thumbnails ({}/{})",
+        self.grid_page + 1,
+        self.grid_pages_count()
+      )
+    } else {
+      tr!("Thumbnails")
+    };
+    self.children = vec![
+      Box::new(Label::new(title_rect, title, Align::Center)) as Box<dyn View>,
+      Box::new(Icon::new(
+        "close",
+        close_rect,
+        Event::Close(ViewId::Thumbnails),
+      )) as Box<dyn View>,
+    ];
+
+    let grid_rect = rect![
+      self.rect.min.x,
+      self.rect.min.y + bar_height + thickness,
+      self.rect.max.x,
+      self.rect.max.y
+    ];
+    let cell_width = grid_rect.width() as i32 / columns as i32;
+    let cell_height = grid_rect.height() as i32 / rows as i32;
+    self.cells = (0..self.visible_locations().len() as i32)
+      .map(|i| {
+        rect![
+          grid_rect.min.x + (i % columns as i32) * cell_width,
+          grid_rect.min.y + (i / columns as i32) * cell_height,
+          grid_rect.min.x + (i % columns as i32 + 1) * cell_width,
+          grid_rect.min.y + (i / columns as i32 + 1) * cell_height
+        ]
+      })
+      .collect();
+  }
+
+  // Renders the missing thumbnails of the visible pages.
+  pub fn load(&mut self, hub: &Hub) {
+    let epoch = self.epoch.fetch_add(1, Ordering::Relaxed) + 1;
+    let locations: Vec<usize> = self
+      .visible_locations()
+      .filter(|location| !self.thumbnails.contains_key(location))
+      .collect();
+    if locations.is_empty() {
+      return;
+    }
+    let (max_width, max_height) = self.thumbnail_size();
+    let doc = Arc::clone(&self.doc);
+    let epoch2 = Arc::clone(&self.epoch);
+    let hub2 = hub.clone();
+    thread::spawn(move || {
+      for location in locations {
+        if epoch2.load(Ordering::Relaxed) != epoch {
+          break;
+        }
+        let pixmap = {
+          let mut doc = doc.lock().unwrap();
+          let scale = doc.dims(location).map_or(1.0, |(width, height)| {
+            (max_width as f32 / width).min(max_height as f32 / height)
+          });
+          doc.pixmap(Location::Exact(location), scale)
+        };
+        if let Some((pixmap, _)) = pixmap {
+          let thumbnail = scale_down(&pixmap, max_width, max_height);
+          hub2
+            .send(Event::Thumbnail(location, Box::new(thumbnail)))
+            .ok();
+        }
+      }
+    });
+  }
+
+  fn set_grid_page(&mut self, dir: Dir, hub: &Hub, rq: &mut RenderQueue) {
+    let grid_page = match dir {
+      Dir::West if self.grid_page + 1 < self.grid_pages_count() => self.grid_page + 1,
+      Dir::East if self.grid_page > 0 => self.grid_page - 1,
+      _ => return,
+    };
+    self.grid_page = grid_page;
+    self.thumbnails.clear();
+    self.layout();
+    self.load(hub);
+    rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+  }
+}
+
+impl Drop for Thumbnails {
+  fn drop(&mut self) {
+    self.epoch.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+impl View for Thumbnails {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Thumbnail(location, ref pixmap) => {
+        let visible = self.visible_locations();
+        if visible.contains(&location) {
+          self.thumbnails.insert(location, pixmap.as_ref().clone());
+          let cell = self.cells[location - visible.start];
+          rq.add(RenderData::new(self.id, cell, UpdateMode::Gui));
+        }
+        true
+      },
+      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+        if let Some(index) = self.cells.iter().position(|cell| cell.includes(center)) {
+          let location = self.visible_locations().start + index;
+          hub.send(Event::Close(ViewId::Thumbnails)).ok();
+          hub.send(Event::GoTo(location)).ok();
+        }
+        true
+      },
+      Event::Gesture(GestureEvent::Swipe { dir, start, .. }) if self.rect.includes(start) => {
+        self.set_grid_page(context.ui_dir(dir), hub, rq);
+        true
+      },
+      Event::Gesture(..) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let bar_height = self.bar_height();
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let border = scale_by_dpi(THICKNESS_LARGE, dpi) as i32;
+    fb.draw_rectangle(&rect, WHITE);
+    fb.draw_rectangle(
+      &rect![
+        self.rect.min.x,
+        self.rect.min.y + bar_height,
+        self.rect.max.x,
+        self.rect.min.y + bar_height + thickness
+      ],
+      BLACK,
+    );
+
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let label_height = bar_height / 2;
+
+    for (location, cell) in self.visible_locations().zip(self.cells.iter()) {
+      if !cell.overlaps(&rect) {
+        continue;
+      }
+      let area = rect![
+        cell.min.x,
+        cell.min.y,
+        cell.max.x,
+        cell.max.y - label_height
+      ];
+      let is_current = location == self.current_page;
+      if let Some(pixmap) = self.thumbnails.get(&location) {
+        let pt = pt!(
+          area.center().x - pixmap.width as i32 / 2,
+          area.center().y - pixmap.height as i32 / 2
+        );
+        fb.draw_pixmap(pixmap, pt);
+        let frame_thickness = if is_current { border } else { 1 };
+        let frame = rect![
+          pt.x - frame_thickness,
+          pt.y - frame_thickness,
+          pt.x + pixmap.width as i32 + frame_thickness,
+          pt.y + pixmap.height as i32 + frame_thickness
+        ];
+        fb.draw_rectangle_outline(
+          &frame,
+          &BorderSpec {
+            thickness: frame_thickness as u16,
+            color: if is_current { BLACK } else { GRAY08 },
+          },
+        );
+      }
+      let plan = font.plan(&(location + 1).to_string(), None, None);
+      let pt = pt!(
+        cell.center().x - plan.width / 2,
+        cell.max.y - (label_height - x_height) / 2
+      );
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+      if is_current {
+        let y = pt.y + x_height / 2;
+        fb.draw_rectangle(&rect![pt.x, y, pt.x + plan.width, y + thickness], BLACK);
+      }
+    }
+  }
+
+  fn resize(&mut self, rect: Rectangle, hub: &Hub, _rq: &mut RenderQueue, _context: &mut Context) {
+    self.rect = rect;
+    self.thumbnails.clear();
+    self.layout();
+    self.load(hub);
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+
+  fn view_id(&self) -> Option<ViewId> {
+    Some(ViewId::Thumbnails)
+  }
+}
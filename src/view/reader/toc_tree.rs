@@ -0,0 +1,263 @@
+//! The table of contents shown as a collapsible tree that can be filtered.
+
+use crate::document::{chapter_from_index, skim::SkimEntry, Document, Location, TocEntry};
+use fxhash::FxHashSet;
+
+// Tables of contents with at most this number of entries are initially fully expanded.
+const EXPAND_ALL_THRESHOLD: usize = 48;
+
+pub struct TocTree {
+  toc: Vec<TocEntry>,
+  chap_index: usize,
+  expanded: FxHashSet<usize>,
+  filter: Option<String>,
+}
+
+fn count(toc: &[TocEntry]) -> usize {
+  toc.iter().map(|entry| 1 + count(&entry.children)).sum()
+}
+
+fn max_depth(toc: &[TocEntry]) -> usize {
+  toc
+    .iter()
+    .filter(|entry| !entry.children.is_empty())
+    .map(|entry| 1 + max_depth(&entry.children))
+    .max()
+    .unwrap_or(0)
+}
+
+// Fills *path* with the indices of the ancestors of the entry *index*.
+fn ancestors(toc: &[TocEntry], index: usize, path: &mut Vec<usize>) -> bool {
+  for entry in toc {
+    if entry.index == index {
+      return true;
+    }
+    path.push(entry.index);
+    if ancestors(&entry.children, index, path) {
+      return true;
+    }
+    path.pop();
+  }
+  false
+}
+
+fn matches(entry: &TocEntry, query: &str) -> bool {
+  entry.title.to_lowercase().contains(query) || entry.children.iter().any(|e| matches(e, query))
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
+
+pub fn link_uri(location: &Location) -> String {
+  match location {
+    Location::Exact(n) => format!("@{}", n),
+    Location::Uri(uri) => format!("@{}", uri),
+    _ => "#".to_string(),
+  }
+}
+
+// Returns the first page of *doc* holding a link to *uri*.
+pub fn page_of_link(doc: &mut dyn Document, uri: &str) -> Option<usize> {
+  let mut loc = Location::Exact(0);
+  while let Some((links, offset)) = doc.links(loc) {
+    if links.iter().any(|link| link.text == uri) {
+      return Some(offset);
+    }
+    loc = Location::Next(offset);
+  }
+  None
+}
+
+// The headings of a document, each followed by the first sentence of its section.
+pub fn skim_as_html(title: &str, entries: &[SkimEntry]) -> String {
+  let mut buf = format!(
+    "<html>\n\t<head>\n\t\t<title>{}</title>\n\t</head>\n\t<body>\n",
+    escape(title)
+  );
+  for entry in entries {
+    let level = (entry.depth + 2).min(4);
+    buf.push_str(&format!(
+      "\t\t<h{0}><a href=\"{1}\">{2}</a></h{0}>\n",
+      level,
+      link_uri(&entry.location),
+      escape(&entry.title)
+    ));
+    if !entry.excerpt.is_empty() {
+      buf.push_str(&format!("\t\t<p>{}</p>\n", escape(&entry.excerpt)));
+    }
+  }
+  buf.push_str("\t</body>\n</html>");
+  buf
+}
+
+impl TocTree {
+  pub fn new(toc: &[TocEntry], chap_index: usize) -> TocTree {
+    let mut tree = TocTree {
+      toc: toc.to_vec(),
+      chap_index,
+      expanded: FxHashSet::default(),
+      filter: None,
+    };
+    if count(toc) <= EXPAND_ALL_THRESHOLD {
+      tree.expand_to_depth(usize::MAX);
+    } else {
+      let mut path = Vec::new();
+      ancestors(toc, chap_index, &mut path);
+      tree.expanded.extend(path);
+    }
+    tree
+  }
+
+  pub fn toggle(&mut self, index: usize) {
+    if !self.expanded.remove(&index) {
+      self.expanded.insert(index);
+    }
+  }
+
+  // Expands the entries whose depth is less than *depth*.
+  pub fn expand_to_depth(&mut self, depth: usize) {
+    fn aux(toc: &[TocEntry], depth: usize, expanded: &mut FxHashSet<usize>) {
+      for entry in toc {
+        if entry.children.is_empty() {
+          continue;
+        }
+        if depth > 0 {
+          expanded.insert(entry.index);
+          aux(&entry.children, depth - 1, expanded);
+        } else {
+          expanded.remove(&entry.index);
+          aux(&entry.children, 0, expanded);
+        }
+      }
+    }
+    aux(&self.toc, depth, &mut self.expanded);
+  }
+
+  pub fn set_filter(&mut self, query: &str) {
+    let query = query.trim().to_lowercase();
+    self.filter = Some(query).filter(|q| !q.is_empty());
+  }
+
+  // The link of the current chapter, or of its closest visible ancestor.
+  pub fn current_link(&self) -> Option<String> {
+    let mut path = Vec::new();
+    if !ancestors(&self.toc, self.chap_index, &mut path) {
+      return None;
+    }
+    let index = path
+      .iter()
+      .position(|i| !self.expanded.contains(i))
+      .filter(|_| self.filter.is_none())
+      .map_or(self.chap_index, |p| path[p]);
+    chapter_from_index(index, &self.toc).map(|e| link_uri(&e.location))
+  }
+
+  pub fn as_html(&self) -> String {
+    let mut buf = "<html>\n\t<head>\n\t\t<title>Table of Contents</title>\n\t\t\
+                     <link rel=\"stylesheet\" type=\"text/css\" href=\"css/toc.css\"/>\n\t\
+                     </head>\n\t<body>\n"
+      .to_string();
+    if let Some(query) = self.filter.as_ref() {
+      buf.push_str(&format!(
+        "\t\t<p class=\"controls\">Filter: <em>{}</em> <a href=\"=clear\">✕</a></p>\n",
+        escape(query)
+      ));
+    } else {
+      let depth = max_depth(&self.toc);
+      if depth > 0 {
+        buf.push_str("\t\t<p class=\"controls\">Levels:");
+        for d in 0..=depth {
+          buf.push_str(&format!(" <a href=\"={}\">{}</a>", d, d + 1));
+        }
+        buf.push_str("</p>\n");
+      }
+    }
+    let mut path = Vec::new();
+    ancestors(&self.toc, self.chap_index, &mut path);
+    self.as_html_aux(&self.toc, &path, 0, &mut buf);
+    buf.push_str("\t</body>\n</html>");
+    buf
+  }
+
+  fn as_html_aux(&self, toc: &[TocEntry], path: &[usize], depth: usize, buf: &mut String) {
+    buf.push_str(&"\t".repeat(depth + 2));
+    if depth == 0 {
+      buf.push_str("<ul class=\"top\">\n");
+    } else {
+      buf.push_str("<ul>\n");
+    }
+    for entry in toc {
+      if let Some(query) = self.filter.as_ref() {
+        if !matches(entry, query) {
+          continue;
+        }
+      }
+      let expanded = !entry.children.is_empty()
+        && (self.filter.is_some() || self.expanded.contains(&entry.index));
+      buf.push_str(&"\t".repeat(depth + 3));
+      buf.push_str("<li>");
+      if !entry.children.is_empty() && self.filter.is_none() {
+        let (sign, label) = if expanded { ('-', "−") } else { ('+', "+") };
+        buf.push_str(&format!(
+          "<a class=\"toggle\" href=\"{}{}\">{}</a> ",
+          sign, entry.index, label
+        ));
+      }
+      buf.push_str(&format!("<a href=\"{}\">", link_uri(&entry.location)));
+      let title = escape(&entry.title);
+      // A collapsed ancestor stands for the current chapter.
+      let current = entry.index == self.chap_index
+        || (!expanded && path.contains(&entry.index) && self.filter.is_none());
+      if current {
+        buf.push_str(&format!("<strong>{}</strong>", title));
+      } else {
+        buf.push_str(&title);
+      }
+      buf.push_str("</a></li>\n");
+      if expanded {
+        self.as_html_aux(&entry.children, path, depth + 1, buf);
+      }
+    }
+    buf.push_str(&"\t".repeat(depth + 2));
+    buf.push_str("</ul>\n");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::TocTree;
+  use crate::document::{Location, TocEntry};
+
+  fn entry(title: &str, index: usize, children: Vec<TocEntry>) -> TocEntry {
+    TocEntry {
+      title: title.to_string(),
+      location: Location::Exact(index),
+      index,
+      children,
+    }
+  }
+
+  #[test]
+  fn test_collapse_and_filter() {
+    let toc = vec![
+      entry("Part One", 0, vec![entry("Basics", 1, vec![])]),
+      entry("Part Two", 2, vec![entry("Advanced Topics", 3, vec![])]),
+    ];
+    let mut tree = TocTree::new(&toc, 3);
+    tree.expand_to_depth(0);
+    let html = tree.as_html();
+    assert!(!html.contains("Basics"));
+    assert!(html.contains("<strong>Part Two</strong>"));
+    assert_eq!(tree.current_link().as_deref(), Some("@2"));
+    tree.toggle(2);
+    assert!(tree.as_html().contains("<strong>Advanced Topics</strong>"));
+    tree.set_filter("basics");
+    let html = tree.as_html();
+    assert!(html.contains("Part One") && html.contains("Basics"));
+    assert!(!html.contains("Part Two"));
+  }
+}
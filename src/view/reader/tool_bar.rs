@@ -1,3 +1,4 @@
+use super::page_margins;
 use crate::{
   app::Context,
   color::{SEPARATOR_NORMAL, WHITE},
@@ -7,7 +8,13 @@ use crate::{
   geom::Rectangle,
   gesture::GestureEvent,
   input::DeviceEvent,
-  metadata::{ReaderInfo, TextAlign, DEFAULT_CONTRAST_EXPONENT, DEFAULT_CONTRAST_GRAY},
+  metadata::{
+    PageMargins,
+    ReaderInfo,
+    TextAlign,
+    DEFAULT_CONTRAST_EXPONENT,
+    DEFAULT_CONTRAST_GRAY,
+  },
   settings::ReaderSettings,
   unit::scale_by_dpi,
   view::{
@@ -16,6 +23,7 @@ use crate::{
     labeled_icon::LabeledIcon,
     slider::Slider,
     Bus,
+    EntryId,
     Event,
     Hub,
     Id,
@@ -29,6 +37,17 @@ use crate::{
   },
 };
 
+// The label of the margins, with the range of widths when the edges differ.
+fn margins_label(margins: &PageMargins) -> String {
+  if let Some(width) = margins.width() {
+    return format!("{} mm", width);
+  }
+  let widths = [margins.top, margins.right, margins.bottom, margins.left];
+  let min = widths.iter().min().cloned().unwrap_or(0);
+  let max = widths.iter().max().cloned().unwrap_or(0);
+  format!("{}–{} mm", min, max)
+}
+
 #[derive(Debug)]
 pub struct ToolBar {
   id: Id,
@@ -60,9 +79,7 @@ impl ToolBar {
       // First row.
 
       let mut x_offset = rect.min.x;
-      let margin_width = reader_info
-        .and_then(|r| r.margin_width)
-        .unwrap_or(reader_settings.margin_width);
+      let margins = page_margins(reader_info, reader_settings);
       let margin_icon = LabeledIcon::new(
         "margin",
         rect![
@@ -72,7 +89,7 @@ impl ToolBar {
           rect.min.y + side
         ],
         Event::Show(ViewId::MarginWidthMenu),
-        format!("{} mm", margin_width),
+        margins_label(&margins),
       );
       children.push(Box::new(margin_icon) as Box<dyn View>);
       x_offset += side + margin_label_width;
@@ -149,7 +166,7 @@ impl ToolBar {
         rect![
           rect.min.x + 2 * side,
           rect.max.y - side,
-          rect.max.x - 2 * side,
+          rect.max.x - 3 * side,
           rect.max.y
         ],
         SliderId::FontSize,
@@ -243,7 +260,7 @@ impl ToolBar {
       );
       children.push(Box::new(crop_icon) as Box<dyn View>);
 
-      let remaining_width = rect.width() as i32 - 3 * side;
+      let remaining_width = rect.width() as i32 - 4 * side;
       let margin_label_width = (2 * side).min(remaining_width);
       let big_padding = (remaining_width - margin_label_width) / 2;
       let small_padding = remaining_width - margin_label_width - big_padding;
@@ -265,7 +282,7 @@ impl ToolBar {
         rect![
           rect.min.x + side + small_padding,
           rect.max.y - side,
-          rect.max.x - 2 * side - big_padding,
+          rect.max.x - 3 * side - big_padding,
           rect.max.y
         ],
         Event::Show(ViewId::MarginWidthMenu),
@@ -275,9 +292,9 @@ impl ToolBar {
 
       let filler = Filler::new(
         rect![
-          rect.max.x - 2 * side - big_padding,
+          rect.max.x - 3 * side - big_padding,
           rect.max.y - side,
-          rect.max.x - 2 * side,
+          rect.max.x - 3 * side,
           rect.max.y
         ],
         WHITE,
@@ -306,6 +323,19 @@ impl ToolBar {
     );
     children.push(Box::new(toc_icon) as Box<dyn View>);
 
+    // Pushed last, so that the indices of the other children don't depend on it.
+    let speaker_icon = Icon::new(
+      "speaker",
+      rect![
+        rect.max.x - 3 * side,
+        rect.max.y - side,
+        rect.max.x - 2 * side,
+        rect.max.y
+      ],
+      Event::Select(EntryId::ReadAloud),
+    );
+    children.push(Box::new(speaker_icon) as Box<dyn View>);
+
     ToolBar {
       id,
       rect,
@@ -321,6 +351,12 @@ impl ToolBar {
     }
   }
 
+  pub fn update_margins(&mut self, margins: &PageMargins, rq: &mut RenderQueue) {
+    if let Some(labeled_icon) = self.children[0].downcast_mut::<LabeledIcon>() {
+      labeled_icon.update(&margins_label(margins), rq);
+    }
+  }
+
   pub fn update_font_family(&mut self, font_family: String, rq: &mut RenderQueue) {
     if let Some(labeled_icon) = self.children[1].downcast_mut::<LabeledIcon>() {
       labeled_icon.update(&font_family, rq);
@@ -467,7 +503,7 @@ impl View for ToolBar {
         rect![
           rect.min.x + 2 * side,
           rect.max.y - side,
-          rect.max.x - 2 * side,
+          rect.max.x - 3 * side,
           rect.max.y
         ],
         hub,
@@ -552,7 +588,7 @@ impl View for ToolBar {
       );
       index += 1;
 
-      let remaining_width = rect.width() as i32 - 3 * side;
+      let remaining_width = rect.width() as i32 - 4 * side;
       let margin_label_width = self.children[index + 1].rect().width() as i32;
       let big_padding = (remaining_width - margin_label_width) / 2;
       let small_padding = remaining_width - margin_label_width - big_padding;
@@ -574,7 +610,7 @@ impl View for ToolBar {
         rect![
           rect.min.x + side + small_padding,
           rect.max.y - side,
-          rect.max.x - 2 * side - big_padding,
+          rect.max.x - 3 * side - big_padding,
           rect.max.y
         ],
         hub,
@@ -584,9 +620,9 @@ impl View for ToolBar {
       index += 1;
       self.children[index].resize(
         rect![
-          rect.max.x - 2 * side - big_padding,
+          rect.max.x - 3 * side - big_padding,
           rect.max.y - side,
-          rect.max.x - 2 * side,
+          rect.max.x - 3 * side,
           rect.max.y
         ],
         hub,
@@ -617,6 +653,19 @@ impl View for ToolBar {
       rq,
       context,
     );
+    index += 1;
+
+    self.children[index].resize(
+      rect![
+        rect.max.x - 3 * side,
+        rect.max.y - side,
+        rect.max.x - 2 * side,
+        rect.max.y
+      ],
+      hub,
+      rq,
+      context,
+    );
     self.rect = rect;
   }
 
@@ -0,0 +1,108 @@
+//! The errors reported to the user.
+//!
+//! Views send an `Event::Error` instead of only printing the error: the report is written to
+//! the log and shown as a notification, or as a dialog for the critical failures.
+
+use super::{
+  dialog::Dialog,
+  notification::{push_notification, Notification, NotificationPriority},
+  Event,
+  Hub,
+  RenderData,
+  RenderQueue,
+  View,
+  ViewId,
+};
+use crate::{app::Context, framebuffer::UpdateMode};
+use anyhow::Error;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Severity {
+  // The operation failed, but nothing was lost.
+  Warning,
+  // Something the user asked for didn't happen.
+  Error,
+  // Data might be lost.
+  Critical,
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+  pub severity: Severity,
+  // What was being done, e.g. *Can't save the metadata*.
+  pub context: String,
+  pub message: String,
+}
+
+impl ErrorReport {
+  pub fn new(severity: Severity, context: &str, err: &Error) -> ErrorReport {
+    ErrorReport {
+      severity,
+      context: tr!(context),
+      message: format!("{:#}", err),
+    }
+  }
+
+  pub fn text(&self) -> String {
+    format!("{}: {}.", self.context, self.message.trim_end_matches('.'))
+  }
+}
+
+// Sends a report of the given error through the hub.
+pub fn report_error(hub: &Hub, severity: Severity, context: &str, err: &Error) {
+  hub
+    .send(Event::Error(Box::new(ErrorReport::new(
+      severity, context, err,
+    ))))
+    .ok();
+}
+
+// Logs the given report and shows it within *children*.
+pub fn show_report(
+  children: &mut Vec<Box<dyn View>>,
+  report: &ErrorReport,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  let text = report.text();
+  eprintln!("{:?}: {}", report.severity, text);
+  match report.severity {
+    Severity::Warning => {
+      let notif = Notification::new(ViewId::ErrorNotif, text, context);
+      push_notification(children, notif, hub, rq, context);
+    },
+    Severity::Error => {
+      let notif = Notification::new(ViewId::ErrorNotif, text, context)
+        .with_priority(NotificationPriority::High);
+      push_notification(children, notif, hub, rq, context);
+    },
+    Severity::Critical => {
+      let dialog = Dialog::new(ViewId::ErrorDialog, None, text, context);
+      rq.add(RenderData::new(
+        dialog.id(),
+        *dialog.rect(),
+        UpdateMode::Gui,
+      ));
+      children.push(Box::new(dialog) as Box<dyn View>);
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ErrorReport, Severity};
+  use anyhow::{format_err, Context};
+
+  #[test]
+  fn test_report_text() {
+    let err = Err::<(), _>(format_err!("No space left on device."))
+      .context("can't write metadata.json")
+      .unwrap_err();
+    let report = ErrorReport::new(Severity::Critical, "Can't save the metadata", &err);
+    assert_eq!(
+      report.text(),
+      "Can't save the metadata: can't write metadata.json: No space left on device."
+    );
+  }
+}
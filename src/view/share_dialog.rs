@@ -0,0 +1,184 @@
+use super::{
+  Bus,
+  Event,
+  Hub,
+  Id,
+  RenderQueue,
+  View,
+  ViewId,
+  BORDER_RADIUS_MEDIUM,
+  ID_FEEDER,
+  THICKNESS_LARGE,
+};
+use crate::{
+  app::Context,
+  color::{BLACK, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, Pixmap},
+  geom::{BorderSpec, CornerSpec, Rectangle},
+  gesture::GestureEvent,
+  qrcode::QrCode,
+  share::ShareServer,
+  unit::scale_by_dpi,
+};
+
+// The light margin around the code, in modules.
+const QUIET_ZONE: usize = 4;
+
+// Shows the address of a running share server, and its QR code.
+// The server is stopped when the dialog is closed.
+pub struct ShareBookDialog {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  server: ShareServer,
+  title: String,
+  pixmap: Pixmap,
+}
+
+fn qr_pixmap(text: &str, max_side: u32) -> Pixmap {
+  let qr = match QrCode::encode(text.as_bytes()) {
+    Some(qr) => qr,
+    None => return Pixmap::new(1, 1),
+  };
+  let modules = qr.size() + 2 * QUIET_ZONE;
+  let scale = (max_side as usize / modules).max(1);
+  let side = (modules * scale) as u32;
+  let mut pixmap = Pixmap::new(side, side);
+  for y in 0..qr.size() {
+    for x in 0..qr.size() {
+      if !qr.get(x, y) {
+        continue;
+      }
+      for dy in 0..scale {
+        let row = ((QUIET_ZONE + y) * scale + dy) * side as usize;
+        let start = row + (QUIET_ZONE + x) * scale;
+        for px in &mut pixmap.data[start..start + scale] {
+          *px = BLACK;
+        }
+      }
+    }
+  }
+  pixmap
+}
+
+impl ShareBookDialog {
+  pub fn new(server: ShareServer, title: String, context: &mut Context) -> ShareBookDialog {
+    let (width, height) = context.display.dims;
+    let pixmap = qr_pixmap(server.url(), width.min(height) / 2);
+    let mut dialog = ShareBookDialog {
+      id: ID_FEEDER.next(),
+      rect: Rectangle::default(),
+      children: Vec::new(),
+      server,
+      title,
+      pixmap,
+    };
+    dialog.layout(context);
+    dialog
+  }
+
+  fn layout(&mut self, context: &mut Context) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let (width, height) = context.display.dims;
+    let font = font_from_style(&mut context.fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let padding = font.em() as i32;
+    let dialog_width = (self.pixmap.width as i32 + 2 * padding).max(width as i32 / 2);
+    let dialog_height = self.pixmap.height as i32 + 2 * padding + 8 * x_height;
+    let dx = (width as i32 - dialog_width) / 2;
+    let dy = (height as i32 - dialog_height) / 2;
+    self.rect = rect![dx, dy, dx + dialog_width, dy + dialog_height];
+  }
+}
+
+impl View for ShareBookDialog {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    _rq: &mut RenderQueue,
+    _context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Gesture(GestureEvent::Tap(center)) if !self.rect.includes(center) => {
+        hub.send(Event::Close(ViewId::ShareBookDialog)).ok();
+        true
+      },
+      Event::Gesture(..) => true,
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+    let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+
+    fb.draw_rounded_rectangle_with_border(
+      &self.rect,
+      &CornerSpec::Uniform(border_radius),
+      &BorderSpec {
+        thickness: border_thickness,
+        color: BLACK,
+      },
+      &WHITE,
+    );
+
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let padding = font.em() as i32;
+    let max_width = self.rect.width() as i32 - 2 * padding;
+    let center_x = self.rect.center().x;
+
+    let plan = font.plan(&self.title, Some(max_width), None);
+    let pt = pt!(
+      center_x - plan.width / 2,
+      self.rect.min.y + padding + 2 * x_height
+    );
+    font.render(fb, TEXT_NORMAL[1], &plan, pt);
+
+    let pixmap_y = self.rect.min.y + padding + 4 * x_height;
+    fb.draw_pixmap(
+      &self.pixmap,
+      pt!(center_x - self.pixmap.width as i32 / 2, pixmap_y),
+    );
+
+    let plan = font.plan(self.server.url(), Some(max_width), None);
+    let pt = pt!(
+      center_x - plan.width / 2,
+      pixmap_y + self.pixmap.height as i32 + 3 * x_height
+    );
+    font.render(fb, TEXT_NORMAL[1], &plan, pt);
+  }
+
+  fn resize(&mut self, _rect: Rectangle, _hub: &Hub, _rq: &mut RenderQueue, context: &mut Context) {
+    self.layout(context);
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+
+  fn view_id(&self) -> Option<ViewId> {
+    Some(ViewId::ShareBookDialog)
+  }
+}
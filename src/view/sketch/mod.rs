@@ -1,3 +1,6 @@
+mod notebook;
+
+use self::notebook::{export_notebook, Stroke, Strokes};
 use crate::{
   app::Context,
   color::{BLACK, WHITE},
@@ -13,7 +16,8 @@ use crate::{
     common::locate_by_id,
     icon::{Icon, ICONS_PIXMAPS},
     menu::{Menu, MenuKind},
-    notification::Notification,
+    notification::{push_notification, Notification},
+    progress::ProgressDialog,
     Bus,
     EntryId,
     EntryKind,
@@ -36,12 +40,15 @@ use globset::Glob;
 use rand_core::RngCore;
 use std::{
   fs::{self, File},
-  path::PathBuf,
+  path::{Path, PathBuf},
 };
 use walkdir::WalkDir;
 
 const FILENAME_PATTERN: &str = "sketch-%Y%m%d_%H%M%S.png";
+const EXPORT_PATTERN: &str = "notebook-%Y%m%d_%H%M%S";
 const ICON_NAME: &str = "enclosed_menu";
+// The maximum number of states kept for undoing.
+const UNDO_DEPTH: usize = 8;
 // https://oeis.org/A000041
 const PEN_SIZES: [i32; 12] = [1, 2, 3, 5, 7, 11, 15, 22, 30, 42, 56, 77];
 
@@ -49,11 +56,44 @@ struct TouchState {
   pt: Point,
   time: f64,
   radius: f32,
+  stroke: Option<usize>,
 }
 
 impl TouchState {
-  fn new(pt: Point, time: f64, radius: f32) -> TouchState {
-    TouchState { pt, time, radius }
+  fn new(pt: Point, time: f64, radius: f32, stroke: Option<usize>) -> TouchState {
+    TouchState {
+      pt,
+      time,
+      radius,
+      stroke,
+    }
+  }
+}
+
+// The names of the sketches saved in *dir*, most recent first.
+pub fn sketch_files(dir: &Path) -> Vec<PathBuf> {
+  let glob = Glob::new("**/*.png").unwrap().compile_matcher();
+  let mut files: Vec<PathBuf> = WalkDir::new(dir)
+    .min_depth(1)
+    .into_iter()
+    .filter_map(|e| {
+      e.ok()
+        .filter(|e| !e.is_hidden())
+        .and_then(|e| e.path().file_name().map(PathBuf::from))
+    })
+    .filter(|p| glob.is_match(p))
+    .collect();
+  files.sort_by(|a, b| b.cmp(a));
+  files
+}
+
+fn record_point(strokes: &mut Option<Strokes>, ts: &TouchState) {
+  if let (Some(strokes), Some(index)) = (strokes.as_mut(), ts.stroke) {
+    if let Some(stroke) = strokes.strokes.get_mut(index) {
+      stroke
+        .points
+        .push([ts.pt.x as f32, ts.pt.y as f32, ts.radius]);
+    }
   }
 }
 
@@ -67,10 +107,19 @@ pub struct Sketch {
   pen: Pen,
   save_path: PathBuf,
   filename: String,
+  // The vector strokes, unless a sketch without strokes was loaded.
+  strokes: Option<Strokes>,
+  undo_stack: Vec<(Pixmap, Option<Strokes>)>,
+  redo_stack: Vec<(Pixmap, Option<Strokes>)>,
 }
 
 impl Sketch {
-  pub fn new(rect: Rectangle, rq: &mut RenderQueue, context: &mut Context) -> Sketch {
+  pub fn new(
+    rect: Rectangle,
+    file: Option<&Path>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> Sketch {
     let id = ID_FEEDER.next();
     let mut children = Vec::new();
     let dpi = CURRENT_DEVICE.dpi;
@@ -102,7 +151,7 @@ impl Sketch {
       .home
       .join(&context.settings.sketch.save_path);
     rq.add(RenderData::new(id, rect, UpdateMode::Full));
-    Sketch {
+    let mut sketch = Sketch {
       id,
       rect,
       children,
@@ -112,7 +161,16 @@ impl Sketch {
       pen: context.settings.sketch.pen.clone(),
       save_path,
       filename: Local::now().format(FILENAME_PATTERN).to_string(),
+      strokes: Some(Strokes::new(rect.width(), rect.height())),
+      undo_stack: Vec::new(),
+      redo_stack: Vec::new(),
+    };
+    if let Some(file) = file {
+      if let Err(e) = sketch.load(&file.to_path_buf()) {
+        eprintln!("Can't load sketch {}: {:#}.", file.display(), e);
+      }
     }
+    sketch
   }
 
   fn toggle_title_menu(
@@ -137,25 +195,10 @@ impl Sketch {
         return;
       }
 
-      let glob = Glob::new("**/*.png").unwrap().compile_matcher();
-      let mut loadables: Vec<PathBuf> = WalkDir::new(&self.save_path)
-        .min_depth(1)
-        .into_iter()
-        .filter_map(|e| {
-          e.ok()
-            .filter(|e| !e.is_hidden())
-            .and_then(|e| e.path().file_name().map(PathBuf::from))
-        })
-        .filter(|p| glob.is_match(p))
-        .collect();
-      loadables.sort_by(|a, b| b.cmp(a));
+      let loadables = sketch_files(&self.save_path);
 
       let mut sizes = vec![
-        EntryKind::CheckBox(
-          "Dynamic".to_string(),
-          EntryId::TogglePenDynamism,
-          self.pen.dynamic,
-        ),
+        EntryKind::CheckBox(tr!("Dynamic"), EntryId::TogglePenDynamism, self.pen.dynamic),
         EntryKind::Separator,
       ];
 
@@ -169,12 +212,12 @@ impl Sketch {
 
       let mut colors = vec![
         EntryKind::RadioButton(
-          "White".to_string(),
+          tr!("White"),
           EntryId::SetPenColor(WHITE),
           self.pen.color == WHITE,
         ),
         EntryKind::RadioButton(
-          "Black".to_string(),
+          tr!("Black"),
           EntryId::SetPenColor(BLACK),
           self.pen.color == BLACK,
         ),
@@ -186,27 +229,31 @@ impl Sketch {
           colors.push(EntryKind::Separator);
         }
         colors.push(EntryKind::RadioButton(
-          format!("Gray {:02}", i),
+          tr!("Gray {}", format!("{:02}", i)),
           EntryId::SetPenColor(c),
           self.pen.color == c,
         ));
       }
 
       let mut entries = vec![
-        EntryKind::SubMenu("Size".to_string(), sizes),
-        EntryKind::SubMenu("Color".to_string(), colors),
+        EntryKind::SubMenu(tr!("Size"), sizes),
+        EntryKind::SubMenu(tr!("Color"), colors),
         EntryKind::Separator,
-        EntryKind::Command("Save".to_string(), EntryId::Save),
-        EntryKind::Command("Refresh".to_string(), EntryId::Refresh),
-        EntryKind::Command("New".to_string(), EntryId::New),
-        EntryKind::Command("Quit".to_string(), EntryId::Quit),
+        EntryKind::Command(tr!("Undo"), EntryId::Undo),
+        EntryKind::Command(tr!("Redo"), EntryId::Redo),
+        EntryKind::Separator,
+        EntryKind::Command(tr!("Save"), EntryId::Save),
+        EntryKind::Command(tr!("Export Notebook"), EntryId::ExportNotebook),
+        EntryKind::Command(tr!("Refresh"), EntryId::Refresh),
+        EntryKind::Command(tr!("New"), EntryId::New),
+        EntryKind::Command(tr!("Quit"), EntryId::Quit),
       ];
 
       if !loadables.is_empty() {
         entries.insert(
           entries.len() - 1,
           EntryKind::SubMenu(
-            "Load".to_string(),
+            tr!("Load"),
             loadables
               .into_iter()
               .map(|e| EntryKind::Command(e.to_string_lossy().into_owned(), EntryId::Load(e)))
@@ -233,22 +280,77 @@ impl Sketch {
 
   fn load(&mut self, filename: &PathBuf) -> Result<(), Error> {
     let path = self.save_path.join(filename);
-    let decoder = png::Decoder::new(File::open(path)?);
+    let decoder = png::Decoder::new(File::open(&path)?);
     let (_, mut reader) = decoder.read_info()?;
     reader.next_frame(self.pixmap.data_mut())?;
     self.filename = filename.to_string_lossy().into_owned();
+    self.strokes = Strokes::load(&path);
+    self.undo_stack.clear();
+    self.redo_stack.clear();
     Ok(())
   }
 
+  fn snapshot(&mut self) {
+    if self.undo_stack.len() >= UNDO_DEPTH {
+      self.undo_stack.remove(0);
+    }
+    self
+      .undo_stack
+      .push((self.pixmap.clone(), self.strokes.clone()));
+    self.redo_stack.clear();
+  }
+
+  // Moves the current state to *to* and restores the last state of *from*.
+  fn restore(&mut self, undo: bool, rq: &mut RenderQueue) {
+    let (from, to) = if undo {
+      (&mut self.undo_stack, &mut self.redo_stack)
+    } else {
+      (&mut self.redo_stack, &mut self.undo_stack)
+    };
+    if let Some((pixmap, strokes)) = from.pop() {
+      let pixmap = std::mem::replace(&mut self.pixmap, pixmap);
+      let strokes = std::mem::replace(&mut self.strokes, strokes);
+      to.push((pixmap, strokes));
+      rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+    }
+  }
+
   fn save(&self) -> Result<(), Error> {
     if !self.save_path.exists() {
       fs::create_dir_all(&self.save_path)?;
     }
     let path = self.save_path.join(&self.filename);
     self.pixmap.save(&path.to_string_lossy().into_owned())?;
+    if let Some(strokes) = self.strokes.as_ref() {
+      strokes.save(&path)?;
+    }
     Ok(())
   }
 
+  fn export(&self, rq: &mut RenderQueue, context: &mut Context) -> Result<String, Error> {
+    let names = sketch_files(&self.save_path);
+    let stem = Local::now().format(EXPORT_PATTERN).to_string();
+    let mut dialog = ProgressDialog::new(
+      ViewId::ExportProgress,
+      tr!("Exporting notebook"),
+      Some(0.0),
+      context,
+    );
+    let Context { fb, fonts, .. } = context;
+    dialog.show(fb.as_mut(), fonts);
+    let result = export_notebook(&self.save_path, &names, &stem, CURRENT_DEVICE.dpi, |p| {
+      dialog.advance(Some(p), fb.as_mut(), fonts)
+    });
+    rq.add(RenderData::expose(*dialog.rect(), UpdateMode::Gui));
+    let (zip_path, pdf_path) = result?;
+    Ok(tr!(
+      "Exported {} pages to {} and {}.",
+      names.len(),
+      zip_path.file_name().unwrap_or_default().to_string_lossy(),
+      pdf_path.file_name().unwrap_or_default().to_string_lossy()
+    ))
+  }
+
   fn quit(&self, context: &mut Context) {
     let import_settings = ImportSettings {
       allowed_kinds: ["png".to_string()].iter().cloned().collect(),
@@ -331,6 +433,7 @@ impl View for Sketch {
             &self.rect,
             rq,
           );
+          record_point(&mut self.strokes, ts);
         }
         true
       },
@@ -340,10 +443,21 @@ impl View for Sketch {
         position,
         time,
       }) => {
+        if self.fingers.is_empty() {
+          self.snapshot();
+        }
         let radius = self.pen.size as f32 / 2.0;
+        let color = self.pen.color;
+        let stroke = self.strokes.as_mut().map(|strokes| {
+          strokes.strokes.push(Stroke {
+            color,
+            points: vec![[position.x as f32, position.y as f32, radius]],
+          });
+          strokes.strokes.len() - 1
+        });
         self
           .fingers
-          .insert(id, TouchState::new(position, time, radius));
+          .insert(id, TouchState::new(position, time, radius, stroke));
         true
       },
       Event::Device(DeviceEvent::Finger {
@@ -363,6 +477,7 @@ impl View for Sketch {
             &self.rect,
             rq,
           );
+          record_point(&mut self.strokes, ts);
         }
         self.fingers.remove(&id);
         true
@@ -385,9 +500,9 @@ impl View for Sketch {
       },
       Event::Select(EntryId::Load(ref name)) => {
         if let Err(e) = self.load(name) {
-          let msg = format!("Couldn't load sketch: {}).", e);
-          let notif = Notification::new(ViewId::LoadSketchNotif, msg, hub, rq, context);
-          self.children.push(Box::new(notif) as Box<dyn View>);
+          let msg = tr!("Couldn't load sketch: {}).", e);
+          let notif = Notification::new(ViewId::LoadSketchNotif, msg, context);
+          push_notification(&mut self.children, notif, hub, rq, context);
         } else {
           rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
         }
@@ -400,26 +515,46 @@ impl View for Sketch {
       Event::Select(EntryId::New) => {
         self.pixmap.clear(WHITE);
         self.filename = Local::now().format(FILENAME_PATTERN).to_string();
+        self.strokes = Some(Strokes::new(self.rect.width(), self.rect.height()));
+        self.undo_stack.clear();
+        self.redo_stack.clear();
         rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
         true
       },
       Event::Select(EntryId::Save) => {
         let mut msg = match self.save() {
-          Err(e) => Some(format!("Can't save sketch: {}.", e)),
+          Err(e) => Some(tr!("Can't save sketch: {}.", e)),
           Ok(..) => {
             if context.settings.sketch.notify_success {
-              Some(format!("Saved {}.", self.filename))
+              Some(tr!("Saved {}.", self.filename))
             } else {
               None
             }
           },
         };
         if let Some(msg) = msg.take() {
-          let notif = Notification::new(ViewId::SaveSketchNotif, msg, hub, rq, context);
-          self.children.push(Box::new(notif) as Box<dyn View>);
+          let notif = Notification::new(ViewId::SaveSketchNotif, msg, context);
+          push_notification(&mut self.children, notif, hub, rq, context);
         }
         true
       },
+      Event::Select(EntryId::Undo) => {
+        self.restore(true, rq);
+        true
+      },
+      Event::Select(EntryId::Redo) => {
+        self.restore(false, rq);
+        true
+      },
+      Event::Select(EntryId::ExportNotebook) => {
+        let msg = match self.export(rq, context) {
+          Ok(msg) => msg,
+          Err(e) => tr!("Can't export notebook: {}.", e),
+        };
+        let notif = Notification::new(ViewId::SaveSketchNotif, msg, context);
+        push_notification(&mut self.children, notif, hub, rq, context);
+        true
+      },
       Event::Select(EntryId::Quit) => {
         self.quit(context);
         hub.send(Event::Back).ok();
@@ -437,6 +572,10 @@ impl View for Sketch {
     rect.intersection(&self.rect).unwrap_or(self.rect)
   }
 
+  fn inversion_exemptions(&self) -> Vec<Rectangle> {
+    vec![self.rect]
+  }
+
   fn might_rotate(&self) -> bool {
     false
   }
@@ -0,0 +1,317 @@
+//! The strokes of the sketches, and the export of a notebook (the sketches of a directory)
+//! as a bundle of PNG files and as a PDF document.
+//!
+//! The strokes of a sketch are saved next to its PNG file, in a JSON file with the same stem.
+//! The pages of the sketches without strokes are embedded as images in the PDF document.
+
+use crate::helpers::{load_json, save_json};
+use anyhow::{format_err, Context, Error};
+use flate2::{write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::{self, File},
+  io::Write,
+  path::{Path, PathBuf},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+pub const EXPORTS_DIRNAME: &str = "Exports";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stroke {
+  pub color: u8,
+  // The position and the radius of the pen at each point of the stroke.
+  pub points: Vec<[f32; 3]>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Strokes {
+  pub width: u32,
+  pub height: u32,
+  pub strokes: Vec<Stroke>,
+}
+
+pub fn strokes_path(path: &Path) -> PathBuf {
+  path.with_extension("json")
+}
+
+impl Strokes {
+  pub fn new(width: u32, height: u32) -> Strokes {
+    Strokes {
+      width,
+      height,
+      strokes: Vec::new(),
+    }
+  }
+
+  pub fn load(path: &Path) -> Option<Strokes> {
+    let path = strokes_path(path);
+    if !path.exists() {
+      return None;
+    }
+    load_json(&path).map_err(|e| eprintln!("{:#}", e)).ok()
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), Error> {
+    save_json(self, strokes_path(path))
+  }
+
+  // The content stream of the PDF page of these strokes.
+  fn content_stream(&self, scale: f32) -> String {
+    let mut buf = "1 J 1 j\n".to_string();
+    for stroke in &self.strokes {
+      let gray = stroke.color as f32 / 255.0;
+      buf.push_str(&format!("{:.3} G {:.3} g\n", gray, gray));
+      let pos = |p: &[f32; 3]| (scale * p[0], scale * (self.height as f32 - p[1]));
+      if let [point] = stroke.points.as_slice() {
+        // A single point is drawn as a zero-length segment with round caps.
+        let (x, y) = pos(point);
+        buf.push_str(&format!(
+          "{:.2} w {:.2} {:.2} m {:.2} {:.2} l S\n",
+          2.0 * scale * point[2],
+          x,
+          y,
+          x,
+          y
+        ));
+        continue;
+      }
+      for segment in stroke.points.windows(2) {
+        let (x0, y0) = pos(&segment[0]);
+        let (x1, y1) = pos(&segment[1]);
+        let width = scale * (segment[0][2] + segment[1][2]);
+        buf.push_str(&format!(
+          "{:.2} w {:.2} {:.2} m {:.2} {:.2} l S\n",
+          width, x0, y0, x1, y1
+        ));
+      }
+    }
+    buf
+  }
+}
+
+enum PageContent {
+  Strokes(Strokes),
+  Image {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+  },
+}
+
+fn read_png(path: &Path) -> Result<PageContent, Error> {
+  let decoder = png::Decoder::new(File::open(path)?);
+  let (info, mut reader) = decoder.read_info()?;
+  if info.color_type != png::ColorType::Grayscale || info.bit_depth != png::BitDepth::Eight {
+    return Err(format_err!("unsupported pixel format"));
+  }
+  let mut data = vec![0; info.buffer_size()];
+  reader.next_frame(&mut data)?;
+  Ok(PageContent::Image {
+    width: info.width,
+    height: info.height,
+    data,
+  })
+}
+
+struct PdfWriter {
+  buf: Vec<u8>,
+  offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+  fn new() -> PdfWriter {
+    PdfWriter {
+      buf: b"%PDF-1.4\n".to_vec(),
+      offsets: Vec::new(),
+    }
+  }
+
+  // Objects are numbered from 1, in the order of their addition.
+  fn add_object(&mut self, dict: &str, stream: Option<&[u8]>) {
+    self.offsets.push(self.buf.len());
+    let number = self.offsets.len();
+    self
+      .buf
+      .extend_from_slice(format!("{} 0 obj\n{}\n", number, dict).as_bytes());
+    if let Some(data) = stream {
+      self.buf.extend_from_slice(b"stream\n");
+      self.buf.extend_from_slice(data);
+      self.buf.extend_from_slice(b"\nendstream\n");
+    }
+    self.buf.extend_from_slice(b"endobj\n");
+  }
+
+  fn finish(mut self) -> Vec<u8> {
+    let xref_offset = self.buf.len();
+    let count = self.offsets.len() + 1;
+    self
+      .buf
+      .extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", count).as_bytes());
+    for offset in &self.offsets {
+      self
+        .buf
+        .extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    self.buf.extend_from_slice(
+      format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        count, xref_offset
+      )
+      .as_bytes(),
+    );
+    self.buf
+  }
+}
+
+fn build_pdf(pages: &[PageContent], dpi: u16) -> Result<Vec<u8>, Error> {
+  let scale = 72.0 / dpi as f32;
+  let mut writer = PdfWriter::new();
+  // Objects 1 and 2 are the catalog and the page tree, then each page is followed by
+  // its content stream and, possibly, its image.
+  let mut numbers = Vec::with_capacity(pages.len());
+  let mut number = 3;
+  for page in pages {
+    numbers.push(number);
+    number += if let PageContent::Image { .. } = page {
+      3
+    } else {
+      2
+    };
+  }
+  let kids: Vec<String> = numbers.iter().map(|n| format!("{} 0 R", n)).collect();
+  writer.add_object("<< /Type /Catalog /Pages 2 0 R >>", None);
+  writer.add_object(
+    &format!(
+      "<< /Type /Pages /Kids [{}] /Count {} >>",
+      kids.join(" "),
+      pages.len()
+    ),
+    None,
+  );
+
+  for (i, (page, &number)) in pages.iter().zip(numbers.iter()).enumerate() {
+    let (width, height) = match page {
+      PageContent::Strokes(strokes) => (strokes.width, strokes.height),
+      PageContent::Image { width, height, .. } => (*width, *height),
+    };
+    let (page_width, page_height) = (scale * width as f32, scale * height as f32);
+    let resources = if let PageContent::Image { .. } = page {
+      format!("<< /XObject << /Im{} {} 0 R >> >>", i, number + 2)
+    } else {
+      "<< >>".to_string()
+    };
+    writer.add_object(
+      &format!(
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+         /Resources {} /Contents {} 0 R >>",
+        page_width,
+        page_height,
+        resources,
+        number + 1
+      ),
+      None,
+    );
+    let (content, image) = match page {
+      PageContent::Strokes(strokes) => (strokes.content_stream(scale), None),
+      PageContent::Image {
+        width,
+        height,
+        data,
+      } => {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let content = format!(
+          "q {:.2} 0 0 {:.2} 0 0 cm /Im{} Do Q\n",
+          page_width, page_height, i
+        );
+        (content, Some((*width, *height, encoder.finish()?)))
+      },
+    };
+    writer.add_object(
+      &format!("<< /Length {} >>", content.len()),
+      Some(content.as_bytes()),
+    );
+    if let Some((width, height, data)) = image {
+      writer.add_object(
+        &format!(
+          "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray \
+           /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+          width,
+          height,
+          data.len()
+        ),
+        Some(&data),
+      );
+    }
+  }
+
+  Ok(writer.finish())
+}
+
+// Exports the given sketches of *dir* as a ZIP archive of PNG files and a PDF document.
+// *progress* is called with the fraction of the processed sketches.
+// Returns the paths of the created files.
+pub fn export_notebook<F: FnMut(f32)>(
+  dir: &Path,
+  names: &[PathBuf],
+  stem: &str,
+  dpi: u16,
+  mut progress: F,
+) -> Result<(PathBuf, PathBuf), Error> {
+  if names.is_empty() {
+    return Err(format_err!("the notebook is empty"));
+  }
+  let exports_dir = dir.join(EXPORTS_DIRNAME);
+  fs::create_dir_all(&exports_dir)?;
+
+  let zip_path = exports_dir.join(stem).with_extension("zip");
+  let mut zip = ZipWriter::new(File::create(&zip_path)?);
+  let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+  let mut pages = Vec::with_capacity(names.len());
+
+  for (index, name) in names.iter().enumerate() {
+    progress(index as f32 / names.len() as f32);
+    let path = dir.join(name);
+    zip.start_file(name.to_string_lossy(), options)?;
+    zip.write_all(&fs::read(&path)?)?;
+    let page = match Strokes::load(&path) {
+      Some(strokes) => PageContent::Strokes(strokes),
+      None => read_png(&path).with_context(|| format!("can't read {}", path.display()))?,
+    };
+    pages.push(page);
+  }
+  zip.finish()?;
+
+  let pdf_path = exports_dir.join(stem).with_extension("pdf");
+  fs::write(&pdf_path, build_pdf(&pages, dpi)?)?;
+  Ok((zip_path, pdf_path))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{build_pdf, PageContent, Stroke, Strokes};
+
+  #[test]
+  fn test_build_pdf() {
+    let mut strokes = Strokes::new(300, 400);
+    strokes.strokes.push(Stroke {
+      color: 0,
+      points: vec![[10.0, 10.0, 1.0], [20.0, 30.0, 2.0]],
+    });
+    let pages = vec![
+      PageContent::Strokes(strokes),
+      PageContent::Image {
+        width: 2,
+        height: 2,
+        data: vec![0, 255, 255, 0],
+      },
+    ];
+    let pdf = String::from_utf8_lossy(&build_pdf(&pages, 300).unwrap()).into_owned();
+    assert!(pdf.starts_with("%PDF-1.4"));
+    assert!(pdf.contains("/Count 2"));
+    assert!(pdf.contains("0.72 w 2.40 93.60 m 4.80 88.80 l S"));
+    assert!(pdf.contains("/Kids [3 0 R 5 0 R]"));
+    assert!(pdf.ends_with("%%EOF\n"));
+  }
+}
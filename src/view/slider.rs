@@ -12,17 +12,20 @@ use super::{
 };
 use crate::{
   app::Context,
-  color::{BLACK, PROGRESS_EMPTY, PROGRESS_FULL, PROGRESS_VALUE, WHITE},
+  color::{BLACK, GRAY08, PROGRESS_EMPTY, PROGRESS_FULL, PROGRESS_VALUE, WHITE},
   device::CURRENT_DEVICE,
   font::{font_from_style, Fonts, SLIDER_VALUE},
   framebuffer::{Framebuffer, UpdateMode},
   geom::{halves, BorderSpec, CornerSpec, Rectangle},
+  gesture::GestureEvent,
   input::{DeviceEvent, FingerStatus},
   unit::scale_by_dpi,
 };
 
 const PROGRESS_HEIGHT: f32 = 7.0;
 const BUTTON_DIAMETER: f32 = 46.0;
+// In fine mode, the finger moves the value this many times slower.
+const FINE_FACTOR: f32 = 10.0;
 
 pub struct Slider {
   id: Id,
@@ -33,6 +36,8 @@ pub struct Slider {
   min_value: f32,
   max_value: f32,
   active: bool,
+  // Entered by holding the button, left when it's released.
+  fine: bool,
   last_x: i32,
 }
 
@@ -53,6 +58,7 @@ impl Slider {
       min_value,
       max_value,
       active: false,
+      fine: false,
       last_x: -1,
     }
   }
@@ -71,6 +77,17 @@ impl Slider {
     self.value = self.min_value + progress * (self.max_value - self.min_value);
   }
 
+  // Moves the value relatively to the horizontal motion of the finger, by tenths.
+  fn nudge_value(&mut self, x_hit: i32) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let button_diameter = scale_by_dpi(BUTTON_DIAMETER, dpi) as i32;
+    let track_width = (self.rect.width() as i32 - button_diameter).max(1) as f32;
+    let delta =
+      (x_hit - self.last_x) as f32 / track_width * (self.max_value - self.min_value) / FINE_FACTOR;
+    let value = ((self.value + delta) * 10.0).round() / 10.0;
+    self.value = value.max(self.min_value).min(self.max_value);
+  }
+
   pub fn update(&mut self, value: f32, rq: &mut RenderQueue) {
     if (self.value - value).abs() >= f32::EPSILON {
       self.value = value;
@@ -101,7 +118,11 @@ impl View for Slider {
           true
         },
         FingerStatus::Motion if self.active && position.x != self.last_x => {
-          self.update_value(position.x);
+          if self.fine {
+            self.nudge_value(position.x);
+          } else {
+            self.update_value(position.x);
+          }
           rq.add(RenderData::no_wait(
             self.id,
             self.rect,
@@ -114,15 +135,27 @@ impl View for Slider {
         FingerStatus::Up if self.active => {
           self.active = false;
           if position.x != self.last_x {
-            self.update_value(position.x);
+            if self.fine {
+              self.nudge_value(position.x);
+            } else {
+              self.update_value(position.x);
+            }
             self.last_x = position.x;
           }
+          self.fine = false;
           rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
           bus.push_back(Event::Slider(self.slider_id, self.value, status));
           true
         },
         _ => self.active,
       },
+      Event::Gesture(GestureEvent::HoldFingerShort(center, _))
+        if self.active && self.rect.includes(center) =>
+      {
+        self.fine = true;
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
       _ => false,
     }
   }
@@ -173,7 +206,13 @@ impl View for Slider {
       x_offset + big_radius,
       self.rect.max.y - big_padding
     ];
-    let fill_color = if self.active { BLACK } else { WHITE };
+    let fill_color = if self.fine {
+      GRAY08
+    } else if self.active {
+      BLACK
+    } else {
+      WHITE
+    };
 
     fb.draw_rounded_rectangle_with_border(
       &rect,
@@ -0,0 +1,422 @@
+use crate::{
+  app::Context,
+  color::{BLACK, TEXT_NORMAL, WHITE},
+  device::CURRENT_DEVICE,
+  font::{font_from_style, Fonts, NORMAL_STYLE},
+  framebuffer::{Framebuffer, UpdateMode},
+  geom::{Axis, CornerSpec, Point, Rectangle},
+  gesture::GestureEvent,
+  input::{DeviceEvent, FingerStatus},
+  settings::{TapZones, TAP_ACTIONS},
+  unit::scale_by_dpi,
+  view::{
+    common::locate_by_id,
+    icon::{Icon, ICONS_PIXMAPS},
+    menu::{Menu, MenuKind},
+    notification::{push_notification, Notification},
+    Bus,
+    EntryId,
+    EntryKind,
+    Event,
+    Hub,
+    Id,
+    RenderData,
+    RenderQueue,
+    View,
+    ViewId,
+    BORDER_RADIUS_SMALL,
+    ID_FEEDER,
+    SMALL_BAR_HEIGHT,
+    THICKNESS_LARGE,
+  },
+};
+
+const ICON_NAME: &str = "enclosed_menu";
+// Maximum distance, in pixels at 300 DPI, between a finger and the boundary it grabs.
+const GRAB_DISTANCE: f32 = 36.0;
+
+// Shows the tap zones of the reader: the boundaries of the zones can be dragged,
+// and tapping a zone brings up the list of actions.
+pub struct TapZonesEditor {
+  id: Id,
+  rect: Rectangle,
+  children: Vec<Box<dyn View>>,
+  zones: TapZones,
+  drag: Option<(i32, Axis, usize)>,
+}
+
+impl TapZonesEditor {
+  pub fn new(rect: Rectangle, rq: &mut RenderQueue, context: &mut Context) -> TapZonesEditor {
+    let id = ID_FEEDER.next();
+    let mut children = Vec::new();
+    let dpi = CURRENT_DEVICE.dpi;
+    let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+    let border_radius = scale_by_dpi(BORDER_RADIUS_SMALL, dpi) as i32;
+    let pixmap = &ICONS_PIXMAPS[ICON_NAME];
+    let icon_padding = (small_height - pixmap.width.max(pixmap.height) as i32) / 2;
+    let width = pixmap.width as i32 + icon_padding;
+    let height = pixmap.height as i32 + icon_padding;
+    let dx = (small_height - width) / 2;
+    let dy = (small_height - height) / 2;
+    let icon_rect = rect![
+      rect.min.x + dx,
+      rect.max.y - dy - height,
+      rect.min.x + dx + width,
+      rect.max.y - dy
+    ];
+    let icon = Icon::new(
+      ICON_NAME,
+      icon_rect,
+      Event::ToggleNear(ViewId::TitleMenu, icon_rect),
+    )
+    .corners(Some(CornerSpec::Uniform(border_radius)));
+    children.push(Box::new(icon) as Box<dyn View>);
+    let mut zones = context.settings.reader.tap_zones.clone();
+    zones.normalize();
+    rq.add(RenderData::new(id, rect, UpdateMode::Full));
+    TapZonesEditor {
+      id,
+      rect,
+      children,
+      zones,
+      drag: None,
+    }
+  }
+
+  // Returns the boundary near the given point, if any.
+  fn boundary_at(&self, pt: Point) -> Option<(Axis, usize)> {
+    let grab_distance = scale_by_dpi(GRAB_DISTANCE, CURRENT_DEVICE.dpi) as i32;
+    let width = self.rect.width() as f32;
+    let height = self.rect.height() as f32;
+
+    let column = self
+      .zones
+      .columns
+      .iter()
+      .position(|b| (self.rect.min.x + (b * width).round() as i32 - pt.x).abs() < grab_distance);
+
+    if let Some(index) = column {
+      return Some((Axis::Horizontal, index));
+    }
+
+    self
+      .zones
+      .rows
+      .iter()
+      .position(|b| (self.rect.min.y + (b * height).round() as i32 - pt.y).abs() < grab_distance)
+      .map(|index| (Axis::Vertical, index))
+  }
+
+  fn toggle_title_menu(
+    &mut self,
+    rect: Rectangle,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::TapZonesMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let mut entries = vec![
+        EntryKind::CheckBox(tr!("Enabled"), EntryId::ToggleTapZones, self.zones.enabled),
+        EntryKind::Separator,
+        EntryKind::Command(tr!("Add Column"), EntryId::AddTapZone(Axis::Horizontal)),
+        EntryKind::Command(tr!("Add Row"), EntryId::AddTapZone(Axis::Vertical)),
+      ];
+
+      if !self.zones.columns.is_empty() {
+        entries.push(EntryKind::Command(
+          tr!("Remove Column"),
+          EntryId::RemoveTapZone(Axis::Horizontal),
+        ));
+      }
+
+      if !self.zones.rows.is_empty() {
+        entries.push(EntryKind::Command(
+          tr!("Remove Row"),
+          EntryId::RemoveTapZone(Axis::Vertical),
+        ));
+      }
+
+      entries.extend_from_slice(&[
+        EntryKind::Separator,
+        EntryKind::Command(tr!("Reset"), EntryId::ResetTapZones),
+        EntryKind::Command(tr!("Save"), EntryId::Save),
+        EntryKind::Command(tr!("Quit"), EntryId::Quit),
+      ]);
+
+      let title_menu = Menu::new(
+        rect,
+        ViewId::TapZonesMenu,
+        MenuKind::Contextual,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        title_menu.id(),
+        *title_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(title_menu) as Box<dyn View>);
+    }
+  }
+
+  fn toggle_action_menu(
+    &mut self,
+    center: Point,
+    enable: Option<bool>,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) {
+    if let Some(index) = locate_by_id(self, ViewId::TapActionMenu) {
+      if let Some(true) = enable {
+        return;
+      }
+
+      rq.add(RenderData::expose(
+        *self.child(index).rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.remove(index);
+    } else {
+      if let Some(false) = enable {
+        return;
+      }
+
+      let (column, row) = self.zones.zone_at(&self.rect, center);
+      let index = row * self.zones.columns_count() + column;
+      let current = self.zones.actions[index];
+      let entries = TAP_ACTIONS
+        .iter()
+        .map(|a| {
+          EntryKind::RadioButton(
+            tr!(a.label()),
+            EntryId::SetTapAction(index, *a),
+            *a == current,
+          )
+        })
+        .collect();
+
+      let action_menu = Menu::new(
+        Rectangle::from_point(center),
+        ViewId::TapActionMenu,
+        MenuKind::Contextual,
+        entries,
+        context,
+      );
+      rq.add(RenderData::new(
+        action_menu.id(),
+        *action_menu.rect(),
+        UpdateMode::Gui,
+      ));
+      self.children.push(Box::new(action_menu) as Box<dyn View>);
+    }
+  }
+}
+
+impl View for TapZonesEditor {
+  fn handle_event(
+    &mut self,
+    evt: &Event,
+    hub: &Hub,
+    _bus: &mut Bus,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+  ) -> bool {
+    match *evt {
+      Event::Device(DeviceEvent::Finger {
+        status: FingerStatus::Down,
+        id,
+        position,
+        ..
+      }) => {
+        let menu_open = self.children.iter().any(|c| c.is::<Menu>());
+        if self.drag.is_none() && !menu_open {
+          self.drag = self
+            .boundary_at(position)
+            .map(|(axis, index)| (id, axis, index));
+        }
+        true
+      },
+      Event::Device(DeviceEvent::Finger {
+        status: FingerStatus::Up,
+        id,
+        position,
+        ..
+      }) => {
+        if let Some((drag_id, axis, index)) = self.drag {
+          if drag_id == id {
+            let value = match axis {
+              Axis::Horizontal => (position.x - self.rect.min.x) as f32 / self.rect.width() as f32,
+              Axis::Vertical => (position.y - self.rect.min.y) as f32 / self.rect.height() as f32,
+            };
+            self.zones.move_boundary(axis, index, value);
+            self.drag = None;
+            rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+          }
+        }
+        true
+      },
+      Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+        if self.boundary_at(center).is_none() {
+          self.toggle_action_menu(center, None, rq, context);
+        }
+        true
+      },
+      Event::ToggleNear(ViewId::TitleMenu, rect) => {
+        self.toggle_title_menu(rect, None, rq, context);
+        true
+      },
+      Event::Select(EntryId::SetTapAction(index, action)) => {
+        if let Some(a) = self.zones.actions.get_mut(index) {
+          *a = action;
+          let columns_count = self.zones.columns_count();
+          let rect = self
+            .zones
+            .zone_rect(&self.rect, index % columns_count, index / columns_count);
+          rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+        }
+        true
+      },
+      Event::Select(EntryId::AddTapZone(axis)) => {
+        self.zones.add_boundary(axis);
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
+      Event::Select(EntryId::RemoveTapZone(axis)) => {
+        self.zones.remove_boundary(axis);
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
+      Event::Select(EntryId::ToggleTapZones) => {
+        self.zones.enabled = !self.zones.enabled;
+        true
+      },
+      Event::Select(EntryId::ResetTapZones) => {
+        self.zones = TapZones {
+          enabled: self.zones.enabled,
+          ..Default::default()
+        };
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        true
+      },
+      Event::Select(EntryId::Save) => {
+        context.settings.reader.tap_zones = self.zones.clone();
+        let msg = if self.zones.enabled {
+          "Saved the tap zones."
+        } else {
+          "Saved the tap zones. They are disabled."
+        };
+        let notif = Notification::new(ViewId::SaveTapZonesNotif, tr!(msg), context);
+        push_notification(&mut self.children, notif, hub, rq, context);
+        true
+      },
+      Event::Select(EntryId::Quit) => {
+        hub.send(Event::Back).ok();
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn render(&self, fb: &mut dyn Framebuffer, rect: Rectangle, fonts: &mut Fonts) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as i32;
+
+    fb.draw_rectangle(&rect, WHITE);
+
+    let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+    let x_height = font.x_heights.0 as i32;
+    let padding = font.em() as i32;
+    let columns_count = self.zones.columns_count();
+
+    for (index, action) in self.zones.actions.iter().enumerate() {
+      let zone_rect =
+        self
+          .zones
+          .zone_rect(&self.rect, index % columns_count, index / columns_count);
+      if !zone_rect.overlaps(&rect) {
+        continue;
+      }
+      let max_width = zone_rect.width() as i32 - padding;
+      let plan = font.plan(action.label(), Some(max_width), None);
+      let dx = (zone_rect.width() as i32 - plan.width) / 2;
+      let dy = (zone_rect.height() as i32 - x_height) / 2;
+      let pt = pt!(zone_rect.min.x + dx, zone_rect.max.y - dy);
+      font.render(fb, TEXT_NORMAL[1], &plan, pt);
+    }
+
+    let width = self.rect.width() as f32;
+    let height = self.rect.height() as f32;
+
+    for b in &self.zones.columns {
+      let x = self.rect.min.x + (b * width).round() as i32;
+      let line = rect![
+        x - thickness / 2,
+        self.rect.min.y,
+        x - thickness / 2 + thickness,
+        self.rect.max.y
+      ];
+      if let Some(line) = line.intersection(&rect) {
+        fb.draw_rectangle(&line, BLACK);
+      }
+    }
+
+    for b in &self.zones.rows {
+      let y = self.rect.min.y + (b * height).round() as i32;
+      let line = rect![
+        self.rect.min.x,
+        y - thickness / 2,
+        self.rect.max.x,
+        y - thickness / 2 + thickness
+      ];
+      if let Some(line) = line.intersection(&rect) {
+        fb.draw_rectangle(&line, BLACK);
+      }
+    }
+  }
+
+  fn render_rect(&self, rect: &Rectangle) -> Rectangle {
+    rect.intersection(&self.rect).unwrap_or(self.rect)
+  }
+
+  fn might_rotate(&self) -> bool {
+    false
+  }
+
+  fn is_background(&self) -> bool {
+    true
+  }
+
+  fn rect(&self) -> &Rectangle {
+    &self.rect
+  }
+
+  fn rect_mut(&mut self) -> &mut Rectangle {
+    &mut self.rect
+  }
+
+  fn children(&self) -> &Vec<Box<dyn View>> {
+    &self.children
+  }
+
+  fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+    &mut self.children
+  }
+
+  fn id(&self) -> Id {
+    self.id
+  }
+}
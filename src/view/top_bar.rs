@@ -1,13 +1,16 @@
 use crate::{
   app::Context,
+  device::CURRENT_DEVICE,
   font::Fonts,
   framebuffer::{Framebuffer, UpdateMode},
-  geom::Rectangle,
+  frontlight::step_intensity,
+  geom::{CycleDir, Rectangle},
   gesture::GestureEvent,
   input::DeviceEvent,
   view::{
     battery::Battery,
     clock::Clock,
+    common::locate_by_id,
     icon::Icon,
     label::Label,
     Align,
@@ -28,28 +31,69 @@ pub struct TopBar {
   id: Id,
   rect: Rectangle,
   children: Vec<Box<dyn View>>,
+  frontlight_buttons: bool,
+  rtl: bool,
 }
 
 impl TopBar {
   pub fn new(rect: Rectangle, root_event: Event, title: String, context: &mut Context) -> TopBar {
+    TopBar::build(rect, root_event, title, false, context)
+  }
+
+  // Adds, left of the clock, the buttons that step the frontlight intensity and toggle the
+  // automatic frontlight.
+  pub fn with_frontlight_buttons(
+    rect: Rectangle,
+    root_event: Event,
+    title: String,
+    context: &mut Context,
+  ) -> TopBar {
+    TopBar::build(rect, root_event, title, true, context)
+  }
+
+  fn build(
+    rect: Rectangle,
+    root_event: Event,
+    title: String,
+    frontlight_buttons: bool,
+    context: &mut Context,
+  ) -> TopBar {
     let id = ID_FEEDER.next();
     let mut children = Vec::new();
     let fonts = &mut context.fonts;
+    let frontlight_buttons = frontlight_buttons && CURRENT_DEVICE.has_frontlight();
+    let rtl = context.rtl;
+    let place = |r: Rectangle| if rtl { r.mirrored(&rect) } else { r };
 
     let side = rect.height() as i32;
+    let buttons_width = if frontlight_buttons { 3 * side } else { 0 };
     let icon_name = match root_event {
       Event::Back => "back",
       _ => "search",
     };
 
-    let root_icon = Icon::new(icon_name, rect![rect.min, rect.min + side], root_event);
+    let root_icon = Icon::new(
+      icon_name,
+      place(rect![rect.min, rect.min + side]),
+      root_event,
+    );
     children.push(Box::new(root_icon) as Box<dyn View>);
 
-    let mut clock_rect = rect![rect.max - pt!(4 * side, side), rect.max - pt!(3 * side, 0)];
-    let clock_label = Clock::new(&mut clock_rect, fonts);
-    let title_rect = rect![rect.min.x + side, rect.min.y, clock_rect.min.x, rect.max.y];
+    let mut clock_rect = rect![
+      rect.max - pt!(4 * side + buttons_width, side),
+      rect.max - pt!(3 * side + buttons_width, 0)
+    ];
+    let mut clock_label = Clock::new(&mut clock_rect, fonts);
+    *clock_label.rect_mut() = place(clock_rect);
+    let title_rect = place(rect![
+      rect.min.x + side,
+      rect.min.y,
+      clock_rect.min.x,
+      rect.max.y
+    ]);
     let title_label = Label::new(title_rect, title, Align::Center)
-      .event(Some(Event::ToggleNear(ViewId::TitleMenu, title_rect)));
+      .event(Some(Event::ToggleNear(ViewId::TitleMenu, title_rect)))
+      .hold_event(Some(Event::ToggleNear(ViewId::HiddenMenu, title_rect)));
     children.push(Box::new(title_label) as Box<dyn View>);
     children.push(Box::new(clock_label) as Box<dyn View>);
 
@@ -59,25 +103,16 @@ impl TopBar {
       .status()
       .unwrap_or(crate::battery::Status::Discharging);
     let battery_widget = Battery::new(
-      rect![rect.max - pt!(3 * side, side), rect.max - pt!(2 * side, 0)],
+      place(rect![
+        rect.max - pt!(3 * side, side),
+        rect.max - pt!(2 * side, 0)
+      ]),
       capacity,
       status,
     );
     children.push(Box::new(battery_widget) as Box<dyn View>);
 
-    let name = if context.settings.frontlight {
-      "frontlight"
-    } else {
-      "frontlight-disabled"
-    };
-    let frontlight_icon = Icon::new(
-      name,
-      rect![rect.max - pt!(2 * side, side), rect.max - pt!(side, 0)],
-      Event::Show(ViewId::Frontlight),
-    );
-    children.push(Box::new(frontlight_icon) as Box<dyn View>);
-
-    let menu_rect = rect![rect.max - side, rect.max];
+    let menu_rect = place(rect![rect.max - side, rect.max]);
     let menu_icon = Icon::new(
       "menu",
       menu_rect,
@@ -85,7 +120,52 @@ impl TopBar {
     );
     children.push(Box::new(menu_icon) as Box<dyn View>);
 
-    TopBar { id, rect, children }
+    // The children that follow are optional and looked up by id.
+    if CURRENT_DEVICE.has_frontlight() {
+      let frontlight_icon = Icon::new(
+        frontlight_icon_name(context),
+        place(rect![
+          rect.max - pt!(2 * side, side),
+          rect.max - pt!(side, 0)
+        ]),
+        Event::Show(ViewId::Frontlight),
+      )
+      .with_view_id(ViewId::FrontlightIcon);
+      children.push(Box::new(frontlight_icon) as Box<dyn View>);
+    }
+
+    if frontlight_buttons {
+      let x = rect.max.x - 3 * side - buttons_width;
+      let minus_icon = Icon::new(
+        "minus",
+        place(rect![x, rect.min.y, x + side, rect.max.y]),
+        Event::StepFrontlight(CycleDir::Previous),
+      )
+      .with_view_id(ViewId::FrontlightMinus);
+      children.push(Box::new(minus_icon) as Box<dyn View>);
+      let plus_icon = Icon::new(
+        "plus",
+        place(rect![x + side, rect.min.y, x + 2 * side, rect.max.y]),
+        Event::StepFrontlight(CycleDir::Next),
+      )
+      .with_view_id(ViewId::FrontlightPlus);
+      children.push(Box::new(plus_icon) as Box<dyn View>);
+      let auto_icon = Icon::new(
+        auto_frontlight_icon_name(context),
+        place(rect![x + 2 * side, rect.min.y, x + 3 * side, rect.max.y]),
+        Event::ToggleAutoFrontlight,
+      )
+      .with_view_id(ViewId::AutoFrontlight);
+      children.push(Box::new(auto_icon) as Box<dyn View>);
+    }
+
+    TopBar {
+      id,
+      rect,
+      children,
+      frontlight_buttons,
+      rtl,
+    }
   }
 
   pub fn update_root_icon(&mut self, name: &str, rq: &mut RenderQueue) {
@@ -102,14 +182,39 @@ impl TopBar {
   }
 
   pub fn update_frontlight_icon(&mut self, rq: &mut RenderQueue, context: &mut Context) {
-    let name = if context.settings.frontlight {
-      "frontlight"
-    } else {
-      "frontlight-disabled"
-    };
-    let icon = self.child_mut(4).downcast_mut::<Icon>().unwrap();
-    icon.name = name.to_string();
-    rq.add(RenderData::new(icon.id(), *icon.rect(), UpdateMode::Gui));
+    self.update_icon(ViewId::FrontlightIcon, frontlight_icon_name(context), rq);
+  }
+
+  fn update_auto_frontlight_icon(&mut self, rq: &mut RenderQueue, context: &mut Context) {
+    self.update_icon(
+      ViewId::AutoFrontlight,
+      auto_frontlight_icon_name(context),
+      rq,
+    );
+  }
+
+  fn update_icon(&mut self, view_id: ViewId, name: &str, rq: &mut RenderQueue) {
+    if let Some(index) = locate_by_id(self, view_id) {
+      let icon = self.child_mut(index).downcast_mut::<Icon>().unwrap();
+      icon.name = name.to_string();
+      rq.add(RenderData::new(icon.id(), *icon.rect(), UpdateMode::Gui));
+    }
+  }
+}
+
+fn frontlight_icon_name(context: &Context) -> &'static str {
+  if context.settings.frontlight {
+    "frontlight"
+  } else {
+    "frontlight-disabled"
+  }
+}
+
+fn auto_frontlight_icon_name(context: &Context) -> &'static str {
+  if context.settings.auto_frontlight {
+    "frontlight-auto"
+  } else {
+    "frontlight-auto-disabled"
   }
 }
 
@@ -117,12 +222,37 @@ impl View for TopBar {
   fn handle_event(
     &mut self,
     evt: &Event,
-    _hub: &Hub,
+    hub: &Hub,
     _bus: &mut Bus,
-    _rq: &mut RenderQueue,
-    _context: &mut Context,
+    rq: &mut RenderQueue,
+    context: &mut Context,
   ) -> bool {
     match *evt {
+      Event::StepFrontlight(dir) => {
+        if context.settings.frontlight {
+          let step = context.settings.reader.frontlight_step;
+          let intensity = context.frontlight.levels().intensity;
+          context
+            .frontlight
+            .set_intensity(step_intensity(intensity, step, dir));
+        } else if dir == CycleDir::Next {
+          hub.send(Event::ToggleFrontlight).ok();
+        }
+        // Manual adjustments take precedence over the automatic frontlight.
+        if context.settings.auto_frontlight {
+          context.settings.auto_frontlight = false;
+          self.update_auto_frontlight_icon(rq, context);
+        }
+        true
+      },
+      Event::ToggleAutoFrontlight => {
+        context.settings.auto_frontlight = !context.settings.auto_frontlight;
+        if context.settings.auto_frontlight && context.settings.frontlight {
+          context.guess_frontlight();
+        }
+        self.update_auto_frontlight_icon(rq, context);
+        true
+      },
       Event::Gesture(GestureEvent::Tap(center))
       | Event::Gesture(GestureEvent::HoldFingerShort(center, ..))
         if self.rect.includes(center) =>
@@ -143,32 +273,72 @@ impl View for TopBar {
 
   fn resize(&mut self, rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
     let side = rect.height() as i32;
-    self.children[0].resize(rect![rect.min, rect.min + side], hub, rq, context);
+    let rtl = self.rtl;
+    let place = |r: Rectangle| if rtl { r.mirrored(&rect) } else { r };
+    self.children[0].resize(place(rect![rect.min, rect.min + side]), hub, rq, context);
+    let buttons_width = if self.frontlight_buttons { 3 * side } else { 0 };
     let clock_width = self.children[2].rect().width() as i32;
     let clock_rect = rect![
-      rect.max - pt!(3 * side + clock_width, side),
-      rect.max - pt!(3 * side, 0)
+      rect.max - pt!(3 * side + buttons_width + clock_width, side),
+      rect.max - pt!(3 * side + buttons_width, 0)
     ];
     self.children[1].resize(
-      rect![rect.min.x + side, rect.min.y, clock_rect.min.x, rect.max.y],
+      place(rect![
+        rect.min.x + side,
+        rect.min.y,
+        clock_rect.min.x,
+        rect.max.y
+      ]),
       hub,
       rq,
       context,
     );
-    self.children[2].resize(clock_rect, hub, rq, context);
+    self.children[2].resize(place(clock_rect), hub, rq, context);
     self.children[3].resize(
-      rect![rect.max - pt!(3 * side, side), rect.max - pt!(2 * side, 0)],
-      hub,
-      rq,
-      context,
-    );
-    self.children[4].resize(
-      rect![rect.max - pt!(2 * side, side), rect.max - pt!(side, 0)],
+      place(rect![
+        rect.max - pt!(3 * side, side),
+        rect.max - pt!(2 * side, 0)
+      ]),
       hub,
       rq,
       context,
     );
-    self.children[5].resize(rect![rect.max - side, rect.max], hub, rq, context);
+    self.children[4].resize(place(rect![rect.max - side, rect.max]), hub, rq, context);
+    if let Some(index) = locate_by_id(self, ViewId::FrontlightIcon) {
+      self.children[index].resize(
+        place(rect![
+          rect.max - pt!(2 * side, side),
+          rect.max - pt!(side, 0)
+        ]),
+        hub,
+        rq,
+        context,
+      );
+    }
+    let x = rect.max.x - 3 * side - buttons_width;
+    for (i, view_id) in [
+      ViewId::FrontlightMinus,
+      ViewId::FrontlightPlus,
+      ViewId::AutoFrontlight,
+    ]
+    .iter()
+    .enumerate()
+    {
+      if let Some(index) = locate_by_id(self, *view_id) {
+        let i = i as i32;
+        self.children[index].resize(
+          place(rect![
+            x + i * side,
+            rect.min.y,
+            x + (i + 1) * side,
+            rect.max.y
+          ]),
+          hub,
+          rq,
+          context,
+        );
+      }
+    }
     self.rect = rect;
   }
 
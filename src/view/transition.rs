@@ -0,0 +1,136 @@
+//! Splits the refresh of a view that replaces the current one into regions.
+//!
+//! When a large view opens or closes, the whole screen is usually refreshed at once. The
+//! coordinator takes this refresh out of the render queue, and refreshes the bars of the view
+//! first, with the GUI waveform, and its content afterwards, with the requested update mode.
+
+use super::{process_render_queue, Id, RenderData, RenderQueue, View};
+use crate::{
+  app::Context,
+  framebuffer::UpdateMode,
+  geom::Rectangle,
+  settings::TransitionIntensity,
+};
+use fxhash::FxHashMap;
+use std::time::{Duration, Instant};
+
+// The views whose refresh isn't queued within this delay are refreshed as usual.
+const TRANSITION_TIMEOUT: Duration = Duration::from_secs(3);
+// The bars are at most this fraction of the height of the view.
+const MAX_BAR_RATIO: i32 = 4;
+
+pub struct Transition {
+  id: Id,
+  intensity: TransitionIntensity,
+  start: Instant,
+}
+
+impl Transition {
+  pub fn new(view: &dyn View, intensity: TransitionIntensity) -> Option<Transition> {
+    if intensity == TransitionIntensity::None {
+      return None;
+    }
+    Some(Transition {
+      id: view.id(),
+      intensity,
+      start: Instant::now(),
+    })
+  }
+
+  // Plays the transition once the refresh of the whole view is queued. Returns whether the
+  // transition is over.
+  pub fn play(
+    &self,
+    view: &dyn View,
+    rq: &mut RenderQueue,
+    context: &mut Context,
+    updating: &mut FxHashMap<u32, Rectangle>,
+  ) -> bool {
+    if view.id() != self.id {
+      return true;
+    }
+    let rect = *view.rect();
+    let mode = match take_refresh(rq, self.id, &rect) {
+      Some(mode) => mode,
+      None => return self.start.elapsed() > TRANSITION_TIMEOUT,
+    };
+    for (region, mode) in stages(view, mode) {
+      let mut stage_rq = RenderQueue::new();
+      stage_rq.add(RenderData::expose(region, mode));
+      process_render_queue(view, &mut stage_rq, context, updating);
+      // Each region waits for the previous one to be displayed.
+      if self.intensity == TransitionIntensity::Sequenced {
+        updating.retain(|tok, _| context.fb.wait(*tok).is_err());
+      }
+    }
+    true
+  }
+}
+
+// Removes the refresh of the whole view from the queue, and returns its update mode.
+fn take_refresh(rq: &mut RenderQueue, id: Id, rect: &Rectangle) -> Option<UpdateMode> {
+  let key = rq.iter().find_map(|(key, damage)| {
+    damage
+      .views
+      .get(&id)
+      .filter(|regions| regions.contains(rect))
+      .map(|_| *key)
+  })?;
+  let empty = {
+    let damage = rq.get_mut(&key)?;
+    if let Some(regions) = damage.views.get_mut(&id) {
+      regions.retain(|r| r != rect);
+      if regions.is_empty() {
+        damage.views.remove(&id);
+      }
+    }
+    damage.views.is_empty() && damage.exposed.is_empty()
+  };
+  if empty {
+    rq.remove(&key);
+  }
+  Some(key.0)
+}
+
+// The regions of the bars stacked against the top and the bottom edges of the view, followed by
+// the region of the content.
+fn stages(view: &dyn View, mode: UpdateMode) -> Vec<(Rectangle, UpdateMode)> {
+  let rect = *view.rect();
+  let max_height = rect.height() as i32 / MAX_BAR_RATIO;
+  let is_bar = |r: &Rectangle| r.width() == rect.width() && (r.height() as i32) <= max_height;
+  let bars: Vec<Rectangle> = view
+    .children()
+    .iter()
+    .map(|child| *child.rect())
+    .filter(is_bar)
+    .collect();
+
+  let mut top = rect.min.y;
+  while let Some(r) = bars.iter().find(|r| r.min.y == top && r.max.y > top) {
+    top = r.max.y;
+  }
+  let mut bottom = rect.max.y;
+  while let Some(r) = bars.iter().find(|r| r.max.y == bottom && r.min.y < bottom) {
+    bottom = r.min.y;
+  }
+
+  if top >= bottom || (top == rect.min.y && bottom == rect.max.y) {
+    return vec![(rect, mode)];
+  }
+
+  // The bars don't need to flash, unless a full refresh was requested.
+  let bar_mode = if mode == UpdateMode::Full {
+    UpdateMode::Full
+  } else {
+    UpdateMode::Gui
+  };
+  let mut stages = Vec::new();
+  if top > rect.min.y {
+    stages.push((rect![rect.min.x, rect.min.y, rect.max.x, top], bar_mode));
+  }
+  if bottom < rect.max.y {
+    stages.push((rect![rect.min.x, bottom, rect.max.x, rect.max.y], bar_mode));
+  }
+  stages.push((rect![rect.min.x, top, rect.max.x, bottom], mode));
+  stages
+}
@@ -0,0 +1,268 @@
+//! The destructive actions that can be undone.
+//!
+//! Views record what they remove on the undo stack of the context, and show a notification
+//! that sends `Event::Undo` when tapped. The application pops the stack and sends
+//! `Event::Revert` to the current view: the view that owns the data reverts the action, the
+//! others are handled by the application.
+
+use super::{
+  handle_event,
+  home::revert_trash,
+  notification::{push_notification, Notification},
+  report::{report_error, Severity},
+  Bus,
+  Event,
+  Hub,
+  RenderQueue,
+  View,
+  ViewId,
+};
+use crate::{
+  app::Context,
+  metadata::{Annotation, ReaderInfo},
+  settings::LightPreset,
+};
+use anyhow::{format_err, Error};
+use std::{collections::VecDeque, path::PathBuf};
+
+const UNDO_CAPACITY: usize = 16;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UndoDir {
+  Undo,
+  Redo,
+}
+
+#[derive(Debug, Clone)]
+pub enum UndoAction {
+  RemoveAnnotation {
+    path: PathBuf,
+    annotation: Annotation,
+  },
+  RemovePageName {
+    path: PathBuf,
+    page: usize,
+    name: String,
+  },
+  RemovePreset {
+    index: usize,
+    preset: LightPreset,
+  },
+  // *trash_path* is relative to the trash directory.
+  RemoveBook {
+    path: PathBuf,
+    trash_path: PathBuf,
+  },
+}
+
+impl UndoAction {
+  pub fn label(&self) -> &'static str {
+    match self {
+      UndoAction::RemoveAnnotation { .. } => "Annotation removed",
+      UndoAction::RemovePageName { .. } => "Page name removed",
+      UndoAction::RemovePreset { .. } => "Preset removed",
+      UndoAction::RemoveBook { .. } => "Book moved to the trash",
+    }
+  }
+
+  // The document whose reading state is affected by the action.
+  pub fn book_path(&self) -> Option<&PathBuf> {
+    match self {
+      UndoAction::RemoveAnnotation { path, .. } | UndoAction::RemovePageName { path, .. } => {
+        Some(path)
+      },
+      _ => None,
+    }
+  }
+
+  // Reverts or replays an action on the reading state it affects.
+  pub fn apply_to_reader(&self, reader: &mut ReaderInfo, dir: UndoDir) {
+    match (self, dir) {
+      (UndoAction::RemoveAnnotation { annotation, .. }, UndoDir::Undo) => {
+        let sel = annotation.selection;
+        if reader.annotations.iter().all(|a| a.selection != sel) {
+          let index = reader
+            .annotations
+            .iter()
+            .position(|a| a.selection[0] > sel[0])
+            .unwrap_or(reader.annotations.len());
+          reader.annotations.insert(index, annotation.clone());
+        }
+      },
+      (UndoAction::RemoveAnnotation { annotation, .. }, UndoDir::Redo) => {
+        let sel = annotation.selection;
+        reader.annotations.retain(|a| a.selection != sel);
+      },
+      (UndoAction::RemovePageName { page, name, .. }, UndoDir::Undo) => {
+        reader.page_names.insert(*page, name.clone());
+      },
+      (UndoAction::RemovePageName { page, .. }, UndoDir::Redo) => {
+        reader.page_names.remove(page);
+      },
+      _ => (),
+    }
+  }
+
+  pub fn apply_to_presets(&self, presets: &mut Vec<LightPreset>, dir: UndoDir) {
+    if let UndoAction::RemovePreset { index, preset } = self {
+      match dir {
+        UndoDir::Undo => presets.insert((*index).min(presets.len()), preset.clone()),
+        UndoDir::Redo if *index < presets.len() => {
+          presets.remove(*index);
+        },
+        UndoDir::Redo => (),
+      }
+    }
+  }
+}
+
+// Reverts or replays an action that the current view didn't handle.
+pub fn revert(action: &UndoAction, dir: UndoDir, context: &mut Context) -> Result<(), Error> {
+  match action {
+    UndoAction::RemoveAnnotation { path, .. } | UndoAction::RemovePageName { path, .. } => {
+      let info = context
+        .library
+        .document_info(path)
+        .ok_or_else(|| format_err!("can't find {}", path.display()))?;
+      if let Some(mut reader) = info.reader {
+        action.apply_to_reader(&mut reader, dir);
+        context.library.sync_reader_info(path, &reader);
+      }
+    },
+    UndoAction::RemovePreset { .. } => {
+      action.apply_to_presets(&mut context.settings.frontlight_presets, dir);
+    },
+    UndoAction::RemoveBook { path, trash_path } => {
+      revert_trash(path, trash_path, dir, context)?;
+    },
+  }
+  Ok(())
+}
+
+// Pops the last action of one of the stacks, and lets the current view revert it first.
+pub fn revert_last(
+  dir: UndoDir,
+  view: &mut dyn View,
+  hub: &Hub,
+  bus: &mut Bus,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  let action = match dir {
+    UndoDir::Undo => context.undo.undo(),
+    UndoDir::Redo => context.undo.redo(),
+  };
+  let action = match action {
+    Some(action) => action,
+    None => {
+      let text = match dir {
+        UndoDir::Undo => "Nothing to undo.",
+        UndoDir::Redo => "Nothing to redo.",
+      };
+      let notif = Notification::new(ViewId::UndoNotif, tr!(text), context);
+      push_notification(view.children_mut(), notif, hub, rq, context);
+      return;
+    },
+  };
+  let evt = Event::Revert(Box::new(action.clone()), dir);
+  if !handle_event(view, &evt, hub, bus, rq, context) {
+    if let Err(e) = revert(&action, dir, context) {
+      report_error(hub, Severity::Error, "Can't revert the action", &e);
+    }
+  }
+}
+
+// Shows the notification that undoes the last recorded action when tapped.
+pub fn show_undo_notification(
+  view: &mut dyn View,
+  hub: &Hub,
+  rq: &mut RenderQueue,
+  context: &mut Context,
+) {
+  if let Some(action) = context.undo.last() {
+    let text = tr!("{}. Tap to undo.", tr!(action.label()));
+    let notif = Notification::new(ViewId::UndoNotif, text, context).with_action(Event::Undo);
+    push_notification(view.children_mut(), notif, hub, rq, context);
+  }
+}
+
+// Records an action that can be undone, the application then shows the undo notification.
+pub fn record_undo(action: UndoAction, hub: &Hub, context: &mut Context) {
+  context.undo.push(action);
+  hub.send(Event::UndoRecorded).ok();
+}
+
+// Reverting an action doesn't record anything: the stacks only move the action between them.
+#[derive(Debug, Default)]
+pub struct UndoStack {
+  done: VecDeque<UndoAction>,
+  undone: Vec<UndoAction>,
+}
+
+impl UndoStack {
+  pub fn push(&mut self, action: UndoAction) {
+    self.undone.clear();
+    if self.done.len() >= UNDO_CAPACITY {
+      self.done.pop_front();
+    }
+    self.done.push_back(action);
+  }
+
+  pub fn undo(&mut self) -> Option<UndoAction> {
+    let action = self.done.pop_back()?;
+    self.undone.push(action.clone());
+    Some(action)
+  }
+
+  pub fn last(&self) -> Option<&UndoAction> {
+    self.done.back()
+  }
+
+  pub fn redo(&mut self) -> Option<UndoAction> {
+    let action = self.undone.pop()?;
+    self.done.push_back(action.clone());
+    Some(action)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{UndoAction, UndoStack};
+  use std::path::PathBuf;
+
+  fn page_name(page: usize) -> UndoAction {
+    UndoAction::RemovePageName {
+      path: PathBuf::from("book.epub"),
+      page,
+      name: "i".to_string(),
+    }
+  }
+
+  #[test]
+  fn test_undo_stack() {
+    let mut stack = UndoStack::default();
+    stack.push(page_name(1));
+    stack.push(page_name(2));
+    assert!(matches!(
+      stack.undo(),
+      Some(UndoAction::RemovePageName { page: 2, .. })
+    ));
+    assert!(matches!(
+      stack.redo(),
+      Some(UndoAction::RemovePageName { page: 2, .. })
+    ));
+    assert!(stack.redo().is_none());
+    stack.undo();
+    stack.push(page_name(3));
+    assert!(stack.redo().is_none());
+    assert!(matches!(
+      stack.undo(),
+      Some(UndoAction::RemovePageName { page: 3, .. })
+    ));
+    assert!(matches!(
+      stack.undo(),
+      Some(UndoAction::RemovePageName { page: 1, .. })
+    ));
+    assert!(stack.undo().is_none());
+  }
+}
@@ -2,7 +2,7 @@
 
 set -e
 
-declare -a packages=(zlib bzip2 libpng libjpeg openjpeg jbig2dec freetype2 harfbuzz djvulibre mupdf)
+declare -a packages=(zlib bzip2 zstd xz libpng libjpeg openjpeg jbig2dec freetype2 harfbuzz djvulibre mupdf)
 
 for name in "${@:-${packages[@]}}" ; do
 	echo "Building ${name}."
@@ -4,6 +4,8 @@ declare -A urls=(
 	# Compression
 	["zlib"]="https://zlib.net/zlib-1.2.11.tar.gz"
 	["bzip2"]="https://ftp.osuosl.org/pub/clfs/conglomeration/bzip2/bzip2-1.0.6.tar.gz"
+	["zstd"]="https://github.com/facebook/zstd/releases/download/v1.4.5/zstd-1.4.5.tar.gz"
+	["xz"]="https://tukaani.org/xz/xz-5.2.5.tar.gz"
 	# Images
 	["libpng"]="https://download.sourceforge.net/libpng/libpng-1.6.37.tar.gz"
 	["libjpeg"]="http://www.ijg.org/files/jpegsrc.v9d.tar.gz"
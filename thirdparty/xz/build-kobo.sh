@@ -0,0 +1,7 @@
+#! /bin/sh
+
+TRIPLE=arm-linux-gnueabihf
+export CFLAGS="-O2 -mcpu=cortex-a9 -mfpu=neon"
+
+./configure --host=${TRIPLE} --disable-xz --disable-xzdec --disable-lzmadec \
+	--disable-lzmainfo --disable-scripts --disable-doc && make
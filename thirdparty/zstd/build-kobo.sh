@@ -0,0 +1,7 @@
+#! /bin/sh
+
+export CC=arm-linux-gnueabihf-gcc
+export AR=arm-linux-gnueabihf-ar
+export CFLAGS="-O2 -mcpu=cortex-a9 -mfpu=neon"
+
+make -C lib libzstd